@@ -11,6 +11,7 @@ use metro_map_editor::{
     algorithms::{
         recalculate_map,
         run_a_star,
+        GridMode,
         Updater,
     },
     models::GridNode,
@@ -27,7 +28,14 @@ pub fn a_star_benchmark(c: &mut Criterion) {
     let to = GridNode::from((10, 15));
 
     c.bench_function("a_star", |b| {
-        b.iter(|| run_a_star(black_box(from), black_box(to)))
+        b.iter(|| {
+            run_a_star(
+                black_box(from),
+                black_box(to),
+                GridMode::Octilinear,
+                false,
+            )
+        })
     });
 }
 
@@ -3,10 +3,20 @@
 
 use std::{
     cmp::Ordering,
-    collections::BinaryHeap,
+    collections::{
+        BinaryHeap,
+        HashSet,
+    },
 };
 
-use crate::models::GridNode;
+use super::{
+    match_grid_angle_cost,
+    GridMode,
+};
+use crate::{
+    models::GridNode,
+    utils::calculate_grid_angle,
+};
 
 /// Holds the state for an item in the A* algorithm queue.
 #[derive(Clone)]
@@ -17,6 +27,8 @@ struct AStarState {
     node: GridNode,
     /// The length of the path so far.
     path_length: f64,
+    /// The cost of the bends in the path so far.
+    bend_cost: f64,
     /// The parent state of the current state.
     parent: Option<Box<AStarState>>,
 }
@@ -77,23 +89,46 @@ impl PartialEq for AStarState {
 impl Eq for AStarState {}
 
 /// Run the A* algorithm to get the shortest path from the given from node to
-/// the given to node.
-pub fn run_a_star(from: GridNode, to: GridNode) -> Vec<GridNode> {
+/// the given to node, moving between neighbors on a grid of the given mode.
+///
+/// If bends are avoided, every bend in the path adds the same cost the main
+/// algorithm gives it, so of the paths with about the same length the one with
+/// the fewest and widest bends is found instead of a staircase.
+pub fn run_a_star(
+    from: GridNode,
+    to: GridNode,
+    mode: GridMode,
+    avoid_bends: bool,
+) -> Vec<GridNode> {
+    let distance = |node: GridNode| {
+        match mode {
+            GridMode::Octilinear => node.diagonal_distance_to(to),
+            GridMode::Hexagonal => f64::from(node.hex_distance_to(to)),
+        }
+    };
+
     let mut heap = BinaryHeap::with_capacity(from.diagonal_distance_to(to) as usize * 8);
 
     let init = AStarState {
         cost: 0.0,
         path_length: 0.0,
+        bend_cost: 0.0,
         node: from,
         parent: None,
     };
     let mut last = init.clone();
     heap.push(init);
 
+    // When avoiding bends, the bend costs make many more paths worth exploring.
+    // A node entered from the same neighbor as before can not lead to a cheaper
+    // path than the first time it was expanded, so it is skipped.
+    let mut visited = HashSet::new();
+
     while let Some(
         current @ AStarState {
             node,
             path_length,
+            bend_cost,
             ..
         },
     ) = heap.pop()
@@ -103,11 +138,31 @@ pub fn run_a_star(from: GridNode, to: GridNode) -> Vec<GridNode> {
             break;
         }
 
+        let previous = current
+            .parent
+            .as_ref()
+            .map(|parent| parent.node);
+        if avoid_bends && !visited.insert((node, previous)) {
+            continue;
+        }
+
         last = current.clone();
-        for neighbor in node.get_neighbors() {
+        for neighbor in node.get_neighbors_for(mode) {
+            let next_bend_cost = match previous.filter(|_| avoid_bends) {
+                Some(previous) => {
+                    let angle = calculate_grid_angle(mode, previous, node, neighbor);
+                    bend_cost + match_grid_angle_cost(mode, angle).unwrap_or(f64::INFINITY)
+                },
+                None => bend_cost,
+            };
+            if next_bend_cost.is_infinite() {
+                continue;
+            }
+
             let next = AStarState {
                 path_length: path_length + 1.0,
-                cost: path_length + neighbor.diagonal_distance_to(to),
+                bend_cost: next_bend_cost,
+                cost: path_length + next_bend_cost + distance(neighbor),
                 node: neighbor,
                 parent: Some(Box::new(current.clone())),
             };
@@ -127,20 +182,24 @@ mod tests {
     fn test_to_path() {
         let states = AStarState {
             path_length: 3.0,
+            bend_cost: 0.0,
             cost: 0.0,
             node: (3, 3).into(),
             parent: Some(Box::new(AStarState {
                 cost: 0.0,
                 node: (2, 2).into(),
                 path_length: 2.0,
+                bend_cost: 0.0,
                 parent: Some(Box::new(AStarState {
                     cost: 0.0,
                     node: (1, 1).into(),
                     path_length: 1.0,
+                    bend_cost: 0.0,
                     parent: Some(Box::new(AStarState {
                         cost: 0.0,
                         node: (0, 0).into(),
                         path_length: 0.0,
+                        bend_cost: 0.0,
                         parent: None,
                     })),
                 })),
@@ -158,55 +217,100 @@ mod tests {
     fn test_a_star() {
         // down
         assert_eq!(
-            run_a_star((1, 1).into(), (1, 5).into()),
+            run_a_star(
+                (1, 1).into(),
+                (1, 5).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(1, 2), (1, 3), (1, 4)]
         );
 
         // down diag left
         assert_eq!(
-            run_a_star((5, 1).into(), (1, 5).into()),
+            run_a_star(
+                (5, 1).into(),
+                (1, 5).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(4, 2), (3, 3), (2, 4)]
         );
 
         // left
         assert_eq!(
-            run_a_star((5, 1).into(), (1, 1).into()),
+            run_a_star(
+                (5, 1).into(),
+                (1, 1).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(4, 1), (3, 1), (2, 1)]
         );
 
         // up diag left
         assert_eq!(
-            run_a_star((5, 5).into(), (1, 1).into()),
+            run_a_star(
+                (5, 5).into(),
+                (1, 1).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(4, 4), (3, 3), (2, 2)]
         );
 
         // up
         assert_eq!(
-            run_a_star((1, 5).into(), (1, 1).into()),
+            run_a_star(
+                (1, 5).into(),
+                (1, 1).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(1, 4), (1, 3), (1, 2)]
         );
 
         // up diag right
         assert_eq!(
-            run_a_star((1, 5).into(), (5, 1).into()),
+            run_a_star(
+                (1, 5).into(),
+                (5, 1).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(2, 4), (3, 3), (4, 2)]
         );
 
         // right
         assert_eq!(
-            run_a_star((1, 1).into(), (5, 1).into()),
+            run_a_star(
+                (1, 1).into(),
+                (5, 1).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(2, 1), (3, 1), (4, 1)]
         );
 
         // down diag right
         assert_eq!(
-            run_a_star((1, 1).into(), (5, 5).into()),
+            run_a_star(
+                (1, 1).into(),
+                (5, 5).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![(2, 2), (3, 3), (4, 4)]
         );
 
         // long with corner
         assert_eq!(
-            run_a_star((1, 1).into(), (10, 5).into()),
+            run_a_star(
+                (1, 1).into(),
+                (10, 5).into(),
+                GridMode::Octilinear,
+                false
+            ),
             vec![
                 (2, 2),
                 (3, 3),
@@ -219,4 +323,87 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_a_star_hexagonal() {
+        // right
+        assert_eq!(
+            run_a_star(
+                (1, 1).into(),
+                (4, 1).into(),
+                GridMode::Hexagonal,
+                false
+            ),
+            vec![(2, 1), (3, 1)]
+        );
+
+        // up right
+        assert_eq!(
+            run_a_star(
+                (1, 4).into(),
+                (4, 1).into(),
+                GridMode::Hexagonal,
+                false
+            ),
+            vec![(2, 3), (3, 2)]
+        );
+
+        // square diagonals are not neighbors, so they take two steps each
+        let path = run_a_star(
+            (1, 1).into(),
+            (3, 3).into(),
+            GridMode::Hexagonal,
+            false,
+        );
+        let mut nodes = vec![GridNode::from((1, 1))];
+        nodes.extend(path);
+        nodes.push(GridNode::from((3, 3)));
+
+        assert_eq!(nodes.len(), 5);
+        for pair in nodes.windows(2) {
+            assert_eq!(pair[0].hex_distance_to(pair[1]), 1);
+        }
+    }
+
+    #[test]
+    fn test_a_star_avoid_bends() {
+        // Count the changes of direction along the path between the two nodes.
+        let bends = |from: GridNode, path: &[GridNode], to: GridNode| {
+            let mut nodes = vec![from];
+            nodes.extend(path);
+            nodes.push(to);
+
+            nodes
+                .windows(3)
+                .filter(|w| {
+                    (w[1].0 - w[0].0, w[1].1 - w[0].1) != (w[2].0 - w[1].0, w[2].1 - w[1].1)
+                })
+                .count()
+        };
+
+        // A square diagonal on the hexagonal grid has many shortest paths, of
+        // which the plain search picks a staircase.
+        let (from, to) = (
+            GridNode::from((0, 0)),
+            GridNode::from((6, 6)),
+        );
+        let shortest = run_a_star(from, to, GridMode::Hexagonal, false);
+        let straighter = run_a_star(from, to, GridMode::Hexagonal, true);
+
+        assert_eq!(shortest.len(), straighter.len());
+        assert!(bends(from, &shortest, to) > 1);
+        assert_eq!(bends(from, &straighter, to), 1);
+
+        // Octilinear paths keep their single bend.
+        let (from, to) = (
+            GridNode::from((1, 1)),
+            GridNode::from((10, 5)),
+        );
+        let path = run_a_star(from, to, GridMode::Octilinear, true);
+        assert_eq!(
+            path,
+            run_a_star(from, to, GridMode::Octilinear, false)
+        );
+        assert_eq!(bends(from, &path, to), 1);
+    }
 }
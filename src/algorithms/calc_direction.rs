@@ -1,12 +1,17 @@
 //! Contains tools to determine the direction of an edge.
 
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
 use crate::{
     models::GridNode,
     utils::equal_pixel,
 };
 
 /// Represents the direction the edge is moving.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EdgeDirection {
     /// The edge is moving up.
     Up,
@@ -73,6 +78,20 @@ pub fn node_direction(start: GridNode, end: GridNode) -> EdgeDirection {
     to_direction(end.0 - start.0, end.1 - start.1)
 }
 
+/// Calculates the octilinear direction from the start to the end node. Returns
+/// `None` if the nodes are equal or the segment between them is not aligned
+/// horizontally, vertically or diagonally with the grid.
+pub fn octilinear_direction(start: GridNode, end: GridNode) -> Option<EdgeDirection> {
+    let horizontal = end.0 - start.0;
+    let vertical = end.1 - start.1;
+
+    if horizontal != 0 && vertical != 0 && horizontal.abs() != vertical.abs() {
+        return None;
+    }
+
+    Some(to_direction(horizontal, vertical)).filter(|d| *d != EdgeDirection::Equal)
+}
+
 /// Converts the given horizontal and vertical difference values to a direction.
 fn to_direction(horizontal: i32, vertical: i32) -> EdgeDirection {
     match (horizontal, vertical) {
@@ -131,4 +150,36 @@ mod tests {
             EdgeDirection::Equal
         );
     }
+
+    #[test]
+    fn test_octilinear_direction() {
+        let start = GridNode::from((0, 0));
+        let expected = [
+            ((0, -3), EdgeDirection::Up),
+            ((2, -2), EdgeDirection::DiagUpRight),
+            ((5, 0), EdgeDirection::Right),
+            ((1, 1), EdgeDirection::DiagDownRight),
+            ((0, 1), EdgeDirection::Down),
+            ((-4, 4), EdgeDirection::DiagDownLeft),
+            ((-1, 0), EdgeDirection::Left),
+            ((-2, -2), EdgeDirection::DiagUpLeft),
+        ];
+        for (end, direction) in expected {
+            assert_eq!(
+                octilinear_direction(start, GridNode::from(end)),
+                Some(direction),
+                "direction to {end:?}"
+            );
+        }
+
+        assert_eq!(
+            octilinear_direction(start, GridNode::from((2, 1))),
+            None
+        );
+        assert_eq!(
+            octilinear_direction(start, GridNode::from((-1, 3))),
+            None
+        );
+        assert_eq!(octilinear_direction(start, start), None);
+    }
 }
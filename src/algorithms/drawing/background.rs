@@ -0,0 +1,37 @@
+//! Contains everything for drawing the decorative background shapes, like
+//! rivers and parks, behind the map.
+
+use super::CanvasContext;
+use crate::{
+    components::CanvasState,
+    models::GridNode,
+};
+
+/// A filled shape drawn behind the map, given by the grid nodes along its
+/// outline and its color. Background shapes are purely decorative and are
+/// never taken into account when routing edges.
+pub type BackgroundShape = (Vec<GridNode>, (u8, u8, u8));
+
+/// Draws the given background shapes onto the canvas. This should be called
+/// right after the grid is drawn, so the shapes are behind the map.
+pub fn draw_background(canvas: &CanvasContext, shapes: &[BackgroundShape], state: CanvasState) {
+    canvas.set_global_alpha(1.0);
+
+    for (outline, (r, g, b)) in shapes {
+        let Some((first, rest)) = outline.split_first() else {
+            continue;
+        };
+
+        canvas.begin_path();
+        let (x, y) = first.to_canvas_pos(state);
+        canvas.move_to(x, y);
+        for node in rest {
+            let (x, y) = node.to_canvas_pos(state);
+            canvas.line_to(x, y);
+        }
+
+        // Filling the path closes it back to the first node.
+        canvas.set_fill_style_str(&format!("rgb({r}, {g}, {b})"));
+        canvas.fill();
+    }
+}
@@ -21,6 +21,7 @@ use wasm_bindgen::{
 use web_sys::js_sys::Uint8Array;
 use web_sys::{
     HtmlCanvasElement,
+    ImageBitmap,
     OffscreenCanvas,
 };
 
@@ -46,6 +47,10 @@ pub struct CanvasContext<'a> {
     inner: PhantomData<&'a ()>,
     #[cfg(any(test, feature = "benchmarking"))]
     recorder: RefCell<HashMap<String, Vec<String>>>,
+    /// The current translation of the mocked canvas, followed by the saved
+    /// ones, so recorded coordinates are always on the canvas itself.
+    #[cfg(any(test, feature = "benchmarking"))]
+    translation: RefCell<Vec<(f64, f64)>>,
 }
 
 #[cfg(all(not(test), not(feature = "benchmarking")))]
@@ -63,6 +68,7 @@ impl From<web_sys::CanvasRenderingContext2d> for CanvasContext<'static> {
         Self {
             inner: PhantomData,
             recorder: RefCell::new(HashMap::new()),
+            translation: RefCell::new(vec![(0.0, 0.0)]),
         }
     }
 }
@@ -105,6 +111,7 @@ impl<'a> From<&'a HtmlCanvasElement> for CanvasContext<'a> {
         Self {
             inner: PhantomData,
             recorder: RefCell::new(HashMap::new()),
+            translation: RefCell::new(vec![(0.0, 0.0)]),
         }
     }
 }
@@ -115,6 +122,7 @@ impl<'a> From<&'a OffscreenCanvas> for CanvasContext<'a> {
         Self {
             inner: PhantomData,
             recorder: RefCell::new(HashMap::new()),
+            translation: RefCell::new(vec![(0.0, 0.0)]),
         }
     }
 }
@@ -134,6 +142,7 @@ impl<'a> From<&'a web_sys::CanvasRenderingContext2d> for CanvasContext<'a> {
         Self {
             inner: PhantomData,
             recorder: RefCell::new(HashMap::new()),
+            translation: RefCell::new(vec![(0.0, 0.0)]),
         }
     }
 }
@@ -174,12 +183,32 @@ impl CanvasContext<'_> {
 
     impl_canvas_context_method!(rect(x: f64, y: f64, width: f64, height: f64) -> ());
 
+    impl_canvas_context_method!(clear_rect(x: f64, y: f64, width: f64, height: f64) -> ());
+
     impl_canvas_context_method!(arc(x: f64, y: f64, radius: f64, start_angle: f64, end_angle: f64) -> Result<(), JsValue>);
 
+    impl_canvas_context_method!(arc_to(x1: f64, y1: f64, x2: f64, y2: f64, radius: f64) -> Result<(), JsValue>);
+
     impl_canvas_context_method!(fill() -> ());
 
     impl_canvas_context_method!(set_fill_style_str(style: &str) -> ());
 
+    impl_canvas_context_method!(set_font(font: &str) -> ());
+
+    impl_canvas_context_method!(fill_text(text: &str, x: f64, y: f64) -> Result<(), JsValue>);
+
+    impl_canvas_context_method!(set_text_align(align: &str) -> ());
+
+    impl_canvas_context_method!(save() -> ());
+
+    impl_canvas_context_method!(restore() -> ());
+
+    impl_canvas_context_method!(translate(x: f64, y: f64) -> Result<(), JsValue>);
+
+    impl_canvas_context_method!(rotate(angle: f64) -> Result<(), JsValue>);
+
+    impl_canvas_context_method!(draw_image_with_image_bitmap(image: &ImageBitmap, dx: f64, dy: f64) -> Result<(), JsValue>);
+
     pub fn set_line_dash(&self, segments: &[u8]) -> Result<(), JsValue> {
         let array = Uint8Array::from(segments);
         match &self.inner {
@@ -202,6 +231,7 @@ impl<'a> CanvasContext<'a> {
         Self {
             inner: PhantomData,
             recorder: RefCell::new(HashMap::new()),
+            translation: RefCell::new(vec![(0.0, 0.0)]),
         }
     }
 
@@ -249,6 +279,13 @@ impl<'a> CanvasContext<'a> {
         );
     }
 
+    pub fn clear_rect(&self, x: f64, y: f64, width: f64, height: f64) {
+        self.record(
+            "clear_rect",
+            format!("{x:.1},{y:.1},{width:.1},{height:.1}").as_str(),
+        );
+    }
+
     pub fn arc(
         &self,
         x: f64,
@@ -264,6 +301,14 @@ impl<'a> CanvasContext<'a> {
         Ok(())
     }
 
+    pub fn arc_to(&self, x1: f64, y1: f64, x2: f64, y2: f64, radius: f64) -> Result<(), JsValue> {
+        self.record(
+            "arc_to",
+            format!("{x1:.1},{y1:.1},{x2:.1},{y2:.1},{radius:.1}").as_str(),
+        );
+        Ok(())
+    }
+
     pub fn set_line_dash(&self, _: &[u8]) -> Result<(), JsValue> {
         Ok(())
     }
@@ -276,6 +321,74 @@ impl<'a> CanvasContext<'a> {
         self.record("set_fill_style", style);
     }
 
+    pub fn set_font(&self, _: &str) {}
+
+    pub fn fill_text(&self, text: &str, x: f64, y: f64) -> Result<(), JsValue> {
+        let (x, y) = self.translated(x, y);
+        self.record(
+            "fill_text",
+            format!("{text},{x:.1},{y:.1}").as_str(),
+        );
+        Ok(())
+    }
+
+    pub fn set_text_align(&self, align: &str) {
+        self.record("set_text_align", align);
+    }
+
+    pub fn save(&self) {
+        let current = self.translated(0.0, 0.0);
+        self.translation
+            .borrow_mut()
+            .push(current);
+    }
+
+    pub fn restore(&self) {
+        let mut translation = self
+            .translation
+            .borrow_mut();
+        if translation.len() > 1 {
+            translation.pop();
+        }
+    }
+
+    pub fn translate(&self, x: f64, y: f64) -> Result<(), JsValue> {
+        let current = self.translated(x, y);
+        *self
+            .translation
+            .borrow_mut()
+            .last_mut()
+            .expect("translation stack is never empty") = current;
+        Ok(())
+    }
+
+    pub fn rotate(&self, angle: f64) -> Result<(), JsValue> {
+        self.record("rotate", format!("{angle:.2}").as_str());
+        Ok(())
+    }
+
+    pub fn draw_image_with_image_bitmap(
+        &self,
+        _: &ImageBitmap,
+        dx: f64,
+        dy: f64,
+    ) -> Result<(), JsValue> {
+        self.record(
+            "draw_image",
+            format!("{dx:.1},{dy:.1}").as_str(),
+        );
+        Ok(())
+    }
+
+    fn translated(&self, x: f64, y: f64) -> (f64, f64) {
+        let (dx, dy) = *self
+            .translation
+            .borrow()
+            .last()
+            .expect("translation stack is never empty");
+        (x + dx, y + dy)
+    }
+
     fn record(&self, name: &str, value: &str) {
         self.recorder
             .borrow_mut()
@@ -0,0 +1,161 @@
+//! Contains the helpers for the color-blind-safe rendering mode and for
+//! simulating color vision deficiencies.
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The dash patterns that get assigned to lines in the color-blind-safe
+/// rendering mode, in multiples of the line width. The first pattern is a solid
+/// line.
+pub const DASH_PATTERNS: [&[u8]; 6] = [
+    &[],
+    &[4, 2],
+    &[1, 2],
+    &[6, 2, 1, 2],
+    &[2, 4],
+    &[8, 3, 1, 3],
+];
+
+/// The type of color vision the map can be previewed with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorVision {
+    /// Regular color vision, colors are not altered.
+    #[default]
+    Normal,
+    /// Red-blind color vision.
+    Protanopia,
+    /// Green-blind color vision.
+    Deuteranopia,
+    /// Blue-blind color vision.
+    Tritanopia,
+}
+
+impl ColorVision {
+    /// Transform the given color into how it would be seen with this type of
+    /// color vision.
+    pub fn simulate(self, color: (u8, u8, u8)) -> (u8, u8, u8) {
+        let matrix = match self {
+            Self::Normal => return color,
+            Self::Protanopia => {
+                [
+                    [0.567, 0.433, 0.0],
+                    [0.558, 0.442, 0.0],
+                    [0.0, 0.242, 0.758],
+                ]
+            },
+            Self::Deuteranopia => {
+                [
+                    [0.625, 0.375, 0.0],
+                    [0.7, 0.3, 0.0],
+                    [0.0, 0.3, 0.7],
+                ]
+            },
+            Self::Tritanopia => {
+                [
+                    [0.95, 0.05, 0.0],
+                    [0.0, 0.433, 0.567],
+                    [0.0, 0.475, 0.525],
+                ]
+            },
+        };
+
+        let rgb = [
+            f64::from(color.0),
+            f64::from(color.1),
+            f64::from(color.2),
+        ];
+        let channel = |row: [f64; 3]| {
+            (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2])
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+
+        (
+            channel(matrix[0]),
+            channel(matrix[1]),
+            channel(matrix[2]),
+        )
+    }
+}
+
+/// Scale the given dash pattern, given in multiples of the line width, to the
+/// given line width.
+pub fn scale_dash_pattern(pattern: &[u8], width: f64) -> Vec<u8> {
+    pattern
+        .iter()
+        .map(|segment| {
+            (f64::from(*segment) * width)
+                .round()
+                .clamp(1.0, 255.0) as u8
+        })
+        .collect()
+}
+
+/// Calculate the hue of the given color in degrees.
+pub fn color_hue(color: (u8, u8, u8)) -> f64 {
+    let r = f64::from(color.0) / 255.0;
+    let g = f64::from(color.1) / 255.0;
+    let b = f64::from(color.2) / 255.0;
+
+    let max = r
+        .max(g)
+        .max(b);
+    let min = r
+        .min(g)
+        .min(b);
+    let delta = max - min;
+
+    if delta < f64::EPSILON {
+        return 0.0;
+    }
+
+    let hue = if (max - r).abs() < f64::EPSILON {
+        60.0 * (((g - b) / delta).rem_euclid(6.0))
+    } else if (max - g).abs() < f64::EPSILON {
+        60.0 * (((b - r) / delta) + 2.0)
+    } else {
+        60.0 * (((r - g) / delta) + 4.0)
+    };
+
+    hue.rem_euclid(360.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulate() {
+        assert_eq!(
+            ColorVision::Normal.simulate((255, 0, 0)),
+            (255, 0, 0)
+        );
+        assert_eq!(
+            ColorVision::Deuteranopia.simulate((128, 128, 128)),
+            (128, 128, 128)
+        );
+        assert_eq!(
+            ColorVision::Protanopia.simulate((255, 0, 0)),
+            (145, 142, 0)
+        );
+    }
+
+    #[test]
+    fn test_color_hue() {
+        assert_eq!(color_hue((255, 0, 0)), 0.0);
+        assert_eq!(color_hue((0, 255, 0)), 120.0);
+        assert_eq!(color_hue((0, 0, 255)), 240.0);
+        assert_eq!(color_hue((50, 50, 50)), 0.0);
+    }
+
+    #[test]
+    fn test_scale_dash_pattern() {
+        assert_eq!(
+            scale_dash_pattern(&[4, 2], 1.5),
+            vec![6, 3]
+        );
+        assert!(scale_dash_pattern(&[], 2.0).is_empty());
+    }
+}
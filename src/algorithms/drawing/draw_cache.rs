@@ -0,0 +1,121 @@
+//! Contains the [`DrawCache`] struct, which holds the values about the whole
+//! map that are needed when drawing its parts.
+
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+};
+
+use itertools::Itertools;
+
+use super::LabelObstacles;
+use crate::{
+    components::CanvasState,
+    models::{
+        Edge,
+        EdgeID,
+        Line,
+        LineID,
+        Map,
+        Station,
+        StationID,
+    },
+};
+
+/// The ids of the edges, lines and stations of a map in the order they are
+/// drawn in, which is by their id so chunks of the map can be drawn one after
+/// another.
+#[derive(Debug)]
+pub struct DrawOrder {
+    /// The ids of the edges in the order they are drawn in.
+    pub edges: Vec<EdgeID>,
+    /// The ids of the lines in the order they are drawn in.
+    pub lines: Vec<LineID>,
+    /// The ids of the stations in the order they are drawn in.
+    pub stations: Vec<StationID>,
+}
+
+impl DrawOrder {
+    /// Get the order to draw the edges, lines and stations of the given map in.
+    fn new(map: &Map) -> Self {
+        Self {
+            edges: map
+                .get_edges_sorted()
+                .into_iter()
+                .map(Edge::get_id)
+                .collect(),
+            lines: map
+                .get_lines()
+                .into_iter()
+                .map(Line::get_id)
+                .sorted()
+                .collect(),
+            stations: map
+                .get_stations_sorted()
+                .into_iter()
+                .map(Station::get_id)
+                .collect(),
+        }
+    }
+}
+
+/// Values about the whole map that are needed when drawing its edges and
+/// stations. Each value is computed the first time it is needed during a
+/// render of the map, instead of again for every edge, station or chunk drawn.
+/// A cache must only be used with the map and canvas state it was first used
+/// with.
+#[derive(Debug)]
+pub struct DrawCache {
+    /// The state of the canvas the map is drawn in.
+    state: CanvasState,
+    /// The stations and edges labels should not be drawn on top of.
+    label_obstacles: OnceCell<LabelObstacles>,
+    /// The order the edges, lines and stations are drawn in.
+    draw_order: OnceCell<DrawOrder>,
+    /// The largest weight of any station on the map.
+    max_station_weight: OnceCell<Option<f64>>,
+    /// The dash pattern of every line for the color-blind-safe mode.
+    line_dash_patterns: OnceCell<HashMap<LineID, &'static [u8]>>,
+}
+
+impl DrawCache {
+    /// Create an empty cache for drawing a map in the given state.
+    pub fn new(state: CanvasState) -> Self {
+        Self {
+            state,
+            label_obstacles: OnceCell::new(),
+            draw_order: OnceCell::new(),
+            max_station_weight: OnceCell::new(),
+            line_dash_patterns: OnceCell::new(),
+        }
+    }
+
+    /// Get the stations and edges of the given map labels should not be drawn
+    /// on top of.
+    pub fn label_obstacles(&self, map: &Map) -> &LabelObstacles {
+        self.label_obstacles
+            .get_or_init(|| LabelObstacles::new(map, self.state))
+    }
+
+    /// Get the order the edges, lines and stations of the given map are drawn
+    /// in.
+    pub fn draw_order(&self, map: &Map) -> &DrawOrder {
+        self.draw_order
+            .get_or_init(|| DrawOrder::new(map))
+    }
+
+    /// Get the largest weight of any station on the given map, if any station
+    /// has a weight.
+    pub fn max_station_weight(&self, map: &Map) -> Option<f64> {
+        *self
+            .max_station_weight
+            .get_or_init(|| map.get_max_station_weight())
+    }
+
+    /// Get the dash pattern of every line of the given map for the
+    /// color-blind-safe mode.
+    pub fn line_dash_patterns(&self, map: &Map) -> &HashMap<LineID, &'static [u8]> {
+        self.line_dash_patterns
+            .get_or_init(|| map.get_line_dash_patterns())
+    }
+}
@@ -29,8 +29,30 @@ pub fn add_offset(offset: f64, x: f64, y: f64, direction: EdgeDirection) -> (f64
     }
 }
 
+/// Calculates the distance between two canvas coordinates.
+fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt()
+}
+
+/// Calculates the radius of the rounded bend at the given corner between the
+/// previous and next point, based on the corner radius factor of the canvas.
+/// The radius never exceeds half of the shortest adjacent segment, so the
+/// curves of closely-spaced bends don't overlap.
+fn corner_radius(
+    state: CanvasState,
+    previous: (f64, f64),
+    corner: (f64, f64),
+    next: (f64, f64),
+) -> f64 {
+    let radius = state.drawn_square_size() * state.get_corner_radius_factor();
+    let shortest = distance(previous, corner).min(distance(corner, next));
+
+    radius.min(shortest / 2.0)
+}
+
 /// Draws an edge between two nodes with, optionally, the given step nodes in
 /// between. An offset can be given to draw the edge higher or lower.
+/// If the canvas has a corner radius factor set, bends are drawn rounded.
 pub fn draw_edge(
     from: GridNode,
     to: GridNode,
@@ -38,6 +60,32 @@ pub fn draw_edge(
     canvas: &CanvasContext<'_>,
     state: CanvasState,
     height_offset: f64,
+) {
+    draw_fanned_edge(
+        from,
+        to,
+        steps,
+        canvas,
+        state,
+        (
+            height_offset,
+            height_offset,
+            height_offset,
+        ),
+    );
+}
+
+/// Draws an edge like [`draw_edge`], but with separate offsets for where it
+/// leaves the from node, along the step nodes and where it enters the to node.
+/// This lets a line drawn close to the others along the edge fan out to where
+/// it is drawn on the edges next to it.
+pub fn draw_fanned_edge(
+    from: GridNode,
+    to: GridNode,
+    steps: &[GridNode],
+    canvas: &CanvasContext<'_>,
+    state: CanvasState,
+    (start_offset, height_offset, end_offset): (f64, f64, f64),
 ) {
     let mut steps = steps;
     let from_pos = from.to_canvas_pos(state);
@@ -65,13 +113,13 @@ pub fn draw_edge(
             .first()
             .map_or(to_pos, |s| s.to_canvas_pos(state)),
         state,
-        height_offset,
+        start_offset,
     );
     canvas.move_to(from_x, from_y);
 
-    // The position of the last node on the canvas and if it is on the canvas
+    // The position of the last node on the canvas
     let mut last_pos = (from_x, from_y);
-    let mut last_is = state.is_on_canvas(from);
+    let mut points = Vec::with_capacity(steps.len());
 
     for step in steps {
         let (mut step_x, mut step_y) = step.to_canvas_pos(state);
@@ -83,16 +131,10 @@ pub fn draw_edge(
             (step_x, step_y) = add_offset(height_offset, step_x, step_y, direction);
         }
 
-        // If the last step was off the canvas and the current step is off the canvas,
-        // then don't draw this edge
-        let step_is = state.is_on_canvas(*step);
-        if !last_is && !step_is {
-            canvas.move_to(step_x, step_y);
-            continue;
-        }
-        last_is = step_is;
-
-        canvas.line_to(step_x, step_y);
+        points.push((
+            (step_x, step_y),
+            state.is_on_canvas(*step),
+        ));
     }
 
     // The position of the target node on the canvas, based on the direction it is
@@ -103,8 +145,43 @@ pub fn draw_edge(
             .last()
             .map_or(from_pos, |s| s.to_canvas_pos(state)),
         state,
-        -height_offset,
+        -end_offset,
     );
+
+    // The position of the previous point and if the last node is on the canvas
+    let mut previous = (from_x, from_y);
+    let mut last_is = state.is_on_canvas(from);
+
+    for (i, &(step_pos, step_is)) in points
+        .iter()
+        .enumerate()
+    {
+        // If the last step was off the canvas and the current step is off the canvas,
+        // then don't draw this edge
+        if !last_is && !step_is {
+            canvas.move_to(step_pos.0, step_pos.1);
+            previous = step_pos;
+            continue;
+        }
+        last_is = step_is;
+
+        let next = points
+            .get(i + 1)
+            .map_or((to_x, to_y), |(pos, _)| *pos);
+        let radius = corner_radius(state, previous, step_pos, next);
+        previous = step_pos;
+
+        if radius > 0.0 {
+            canvas
+                .arc_to(
+                    step_pos.0, step_pos.1, next.0, next.1, radius,
+                )
+                .unwrap();
+        } else {
+            canvas.line_to(step_pos.0, step_pos.1);
+        }
+    }
+
     canvas.line_to(to_x, to_y);
 }
 
@@ -259,4 +336,44 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_draw_edge_rounded() {
+        let canvas = CanvasContext::new();
+        let mut state = CanvasState::new();
+        state.set_square_size(5);
+        state.set_size((100.0, 100.0));
+        state.set_corner_radius_factor(2.0);
+
+        let from = GridNode::from((0, 0));
+        let to = GridNode::from((2, 4));
+        let steps = vec![
+            GridNode::from((1, 1)),
+            GridNode::from((1, 2)),
+            GridNode::from((1, 3)),
+            GridNode::from((2, 3)),
+        ];
+
+        draw_edge(from, to, &steps, &canvas, state, 0.0);
+
+        assert_eq!(
+            canvas.get_record("move_to"),
+            Some(vec!["1.3,1.3".to_owned(),])
+        );
+
+        // The radius is clamped to half of the shortest adjacent segment.
+        assert_eq!(
+            canvas.get_record("arc_to"),
+            Some(vec![
+                "5.0,5.0,5.0,10.0,2.5".to_owned(),
+                "5.0,10.0,5.0,15.0,2.5".to_owned(),
+                "5.0,15.0,10.0,15.0,2.5".to_owned(),
+                "10.0,15.0,10.0,18.4,1.7".to_owned(),
+            ])
+        );
+        assert_eq!(
+            canvas.get_record("line_to"),
+            Some(vec!["10.0,18.4".to_owned()])
+        );
+    }
 }
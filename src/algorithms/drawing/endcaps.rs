@@ -0,0 +1,371 @@
+//! Contains the functions for drawing the name of every line in a pill shaped
+//! endcap at its termini.
+
+use std::f64::consts::PI;
+
+use super::{
+    draw_upright_label,
+    upright_label_angle,
+    CanvasContext,
+};
+use crate::{
+    components::CanvasState,
+    models::{
+        Line,
+        LineID,
+        Map,
+        Station,
+        StationID,
+    },
+};
+
+/// The gap between a terminus and its first endcap, and between stacked
+/// endcaps, as a fraction of the square size.
+const ENDCAP_GAP: f64 = 0.25;
+
+/// The endcap of a line at one of its termini.
+#[derive(Clone, Debug, PartialEq)]
+struct Endcap {
+    /// The line the endcap is for.
+    line: LineID,
+    /// The terminus station the endcap is drawn at.
+    station: StationID,
+    /// The direction pointing away from the line at the terminus, as a unit
+    /// vector.
+    direction: (f64, f64),
+}
+
+/// Get the direction pointing away from the given line at the given terminus,
+/// based on the last segment of the edge of the line ending there.
+fn terminus_direction(map: &Map, line: &Line, station: StationID) -> Option<(f64, f64)> {
+    let edge = line
+        .get_edges()
+        .iter()
+        .filter_map(|id| map.get_edge(*id))
+        .find(|edge| edge.get_from() == station || edge.get_to() == station)?;
+
+    let terminus = map
+        .get_station(station)?
+        .get_pos();
+    let previous = if edge.get_from() == station {
+        edge.get_nodes()
+            .first()
+            .copied()
+            .or_else(|| {
+                map.get_station(edge.get_to())
+                    .map(Station::get_pos)
+            })
+    } else {
+        edge.get_nodes()
+            .last()
+            .copied()
+            .or_else(|| {
+                map.get_station(edge.get_from())
+                    .map(Station::get_pos)
+            })
+    }?;
+
+    let dx = f64::from(terminus.0 - previous.0);
+    let dy = f64::from(terminus.1 - previous.1);
+    let length = dx.hypot(dy);
+    (length > 0.0).then(|| (dx / length, dy / length))
+}
+
+/// Get the direction pointing away from the center of the given loop line at
+/// the given station, so its name is drawn on the outside of the loop. Points
+/// up if the station lies at the center.
+fn loop_direction(map: &Map, line: &Line, station: StationID) -> Option<(f64, f64)> {
+    let positions = line
+        .get_stations()
+        .iter()
+        .filter_map(|id| map.get_station(*id))
+        .map(Station::get_pos)
+        .collect::<Vec<_>>();
+    let count = positions.len() as f64;
+    let center = positions
+        .iter()
+        .fold((0.0, 0.0), |(x, y), pos| {
+            (
+                x + f64::from(pos.0) / count,
+                y + f64::from(pos.1) / count,
+            )
+        });
+
+    let pos = map
+        .get_station(station)?
+        .get_pos();
+    let dx = f64::from(pos.0) - center.0;
+    let dy = f64::from(pos.1) - center.1;
+    let length = dx.hypot(dy);
+    Some(
+        if length > 0.0 {
+            (dx / length, dy / length)
+        } else {
+            (0.0, -1.0)
+        },
+    )
+}
+
+/// Get the endcaps of all named lines on the map. The terminus that comes first
+/// in the stations of a line is its start and gets the first endcap. The
+/// endcaps are grouped by terminus, in the order of the lines, so a terminus
+/// shared by multiple lines can stack them. A loop line has no termini, so it
+/// gets a single endcap at its first station on the outside of the loop.
+fn map_endcaps(map: &Map) -> Vec<Vec<Endcap>> {
+    let mut lines = map.get_lines();
+    lines.sort_by_key(|line| line.get_id());
+
+    let mut termini: Vec<Vec<Endcap>> = Vec::new();
+    for line in lines {
+        if line
+            .get_name()
+            .is_empty()
+            || line
+                .get_edges()
+                .is_empty()
+        {
+            continue;
+        }
+
+        let is_cycle = line.is_cycle(map);
+        let mut ends = if is_cycle {
+            line.get_stations()
+                .first()
+                .copied()
+                .into_iter()
+                .collect()
+        } else {
+            line.get_line_ends(map)
+        };
+        ends.sort_by_key(|end| {
+            line.get_stations()
+                .iter()
+                .position(|station| station == end)
+        });
+
+        for station in ends {
+            let direction = if is_cycle {
+                loop_direction(map, line, station)
+            } else {
+                terminus_direction(map, line, station)
+            };
+            let Some(direction) = direction else {
+                continue;
+            };
+            let endcap = Endcap {
+                line: line.get_id(),
+                station,
+                direction,
+            };
+
+            if let Some(stack) = termini
+                .iter_mut()
+                .find(|stack| stack[0].station == station)
+            {
+                stack.push(endcap);
+            } else {
+                termini.push(vec![endcap]);
+            }
+        }
+    }
+
+    termini
+}
+
+/// Draw the name of every line on the map in a pill shaped endcap in the color
+/// of the line at both its termini. The endcaps are placed beyond the terminus
+/// in the direction the line ends in, and stacked if multiple lines end at the
+/// same station. Nothing is drawn if labels are hidden.
+pub fn draw_endcaps(canvas: &CanvasContext<'_>, map: &Map, state: CanvasState, base_alpha: f64) {
+    if !state.is_labels_shown() {
+        return;
+    }
+
+    let square_size = state.drawn_square_size();
+    let label_size = state.drawn_label_size();
+    let height = label_size * 1.4;
+    let radius = height / 2.0;
+
+    canvas
+        .set_line_dash(&[])
+        .unwrap();
+    canvas.set_global_alpha(base_alpha);
+    canvas.set_font(&state.label_font_css());
+
+    for stack in map_endcaps(map) {
+        let Some(station) = map.get_station(stack[0].station) else {
+            continue;
+        };
+        let (x, y) = station.get_canvas_pos(state);
+        let (dx, dy) = stack[0].direction;
+        // Stack away from the line, so the endcaps never cover it.
+        let stack_direction = if dy < 0.0 { -1.0 } else { 1.0 };
+
+        for (i, endcap) in stack
+            .iter()
+            .enumerate()
+        {
+            let Some(line) = map.get_line(endcap.line) else {
+                continue;
+            };
+            let name = line.get_name();
+            let width = (name.len() as f64 * label_size * 0.6) + height;
+
+            let distance =
+                square_size * (0.5 + ENDCAP_GAP) + dx.abs() * width / 2.0 + dy.abs() * height / 2.0;
+            let center_x = x + dx * distance;
+            let center_y = y
+                + dy * distance
+                + stack_direction * i as f64 * (height + square_size * ENDCAP_GAP);
+
+            let color = state
+                .get_color_vision()
+                .simulate(line.get_color());
+            canvas.set_fill_style_str(&format!(
+                "rgb({} {} {})",
+                color.0, color.1, color.2,
+            ));
+            canvas.begin_path();
+            canvas
+                .arc(
+                    center_x - width / 2.0 + radius,
+                    center_y,
+                    radius,
+                    PI / 2.0,
+                    PI * 1.5,
+                )
+                .unwrap();
+            canvas
+                .arc(
+                    center_x + width / 2.0 - radius,
+                    center_y,
+                    radius,
+                    PI * 1.5,
+                    PI / 2.0,
+                )
+                .unwrap();
+            canvas.fill();
+
+            // The name reads away from the terminus, so an endcap to its left
+            // has the name anchored at the far end of the pill.
+            let (_, flipped) = upright_label_angle((x, y), (center_x, y));
+            let text_x = if flipped {
+                center_x + width / 2.0 - radius
+            } else {
+                center_x - width / 2.0 + radius
+            };
+
+            canvas.set_fill_style_str("white");
+            draw_upright_label(
+                canvas,
+                name,
+                (text_x, center_y + label_size / 3.0),
+                (x, y),
+                (center_x, y),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_endcaps() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (4, 0).into());
+        let c = map.add_named_station(&"C", (4, 4).into());
+        let red = map.add_line_through((255, 0, 0), &"Red", &[a, b]);
+        let blue = map.add_line_through((0, 0, 255), &"Blue", &[c, b]);
+        map.add_line_through((0, 255, 0), &"", &[a, c]);
+
+        let endcaps = map_endcaps(&map);
+
+        // The unnamed line has no endcaps, and both named lines end at B.
+        assert_eq!(endcaps.len(), 3);
+        let shared = endcaps
+            .iter()
+            .find(|stack| stack[0].station == b)
+            .unwrap();
+        assert_eq!(
+            shared
+                .iter()
+                .map(|endcap| endcap.line)
+                .collect::<Vec<_>>(),
+            vec![red.min(blue), red.max(blue)]
+        );
+
+        let red_start = endcaps
+            .iter()
+            .flatten()
+            .find(|endcap| endcap.line == red && endcap.station == a)
+            .unwrap();
+        assert_eq!(red_start.direction, (-1.0, 0.0));
+        let blue_end = shared
+            .iter()
+            .find(|endcap| endcap.line == blue)
+            .unwrap();
+        assert_eq!(blue_end.direction, (0.0, -1.0));
+
+        let context = CanvasContext::new();
+        draw_endcaps(&context, &map, CanvasState::new(), 1.0);
+        assert_eq!(
+            context
+                .get_record("fill_text")
+                .map(|texts| texts.len()),
+            Some(4)
+        );
+
+        // Hiding the labels hides the endcaps as well.
+        let context = CanvasContext::new();
+        let mut state = CanvasState::new();
+        state.set_labels_shown(false);
+        draw_endcaps(&context, &map, state, 1.0);
+        assert!(
+            context
+                .get_record("fill_text")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_map_endcaps_loop() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (4, 0).into());
+        let c = map.add_named_station(&"C", (4, 4).into());
+        let d = map.add_named_station(&"D", (0, 4).into());
+        let circle = map.add_line_through(
+            (255, 200, 0),
+            &"Circle",
+            &[a, b, c, d, a],
+        );
+
+        // The loop has a single endcap, pointing away from its center.
+        let endcaps = map_endcaps(&map);
+        assert_eq!(endcaps.len(), 1);
+        assert_eq!(endcaps[0].len(), 1);
+        let endcap = &endcaps[0][0];
+        assert_eq!(endcap.line, circle);
+        assert_eq!(endcap.station, a);
+        let diagonal = std::f64::consts::FRAC_1_SQRT_2;
+        assert!(
+            (endcap
+                .direction
+                .0
+                + diagonal)
+                .abs()
+                < 1e-9
+        );
+        assert!(
+            (endcap
+                .direction
+                .1
+                + diagonal)
+                .abs()
+                < 1e-9
+        );
+    }
+}
@@ -9,7 +9,11 @@ use crate::components::CanvasState;
 pub fn draw_grid(canvas: &CanvasContext, state: CanvasState) {
     canvas.begin_path();
     canvas.set_line_width(0.3);
-    canvas.set_stroke_style_str("grey");
+    canvas.set_stroke_style_str(
+        state
+            .get_theme()
+            .grid(),
+    );
 
     let (height, width) = state.get_size();
     let drawn_square_size = state.drawn_square_size();
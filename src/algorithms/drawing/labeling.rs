@@ -1,13 +1,73 @@
 //! Contains functions to help with placing labels on the canvas.
 
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    f64::consts::{
+        FRAC_PI_2,
+        PI,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::CanvasContext;
 use crate::{
     algorithms::calc_direction::{
         calc_direction,
         EdgeDirection,
     },
+    models::{
+        Map,
+        Station,
+        StationID,
+    },
     CanvasState,
 };
 
+/// The estimated width of a character in a label, as a fraction of the font
+/// size.
+const CHAR_WIDTH_FACTOR: f64 = 0.6;
+/// How far past straight up or down, in radians, text may point before it is
+/// turned around to stay upright.
+const UPRIGHT_TOLERANCE: f64 = 1e-9;
+
+/// The font family labels are drawn with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelFont {
+    /// The default sans-serif font of the browser.
+    #[default]
+    SansSerif,
+    /// The default serif font of the browser.
+    Serif,
+    /// The default monospace font of the browser.
+    Monospace,
+    /// The Helvetica font, or a similar one if it is not available.
+    Helvetica,
+    /// The Georgia font, or a similar one if it is not available.
+    Georgia,
+}
+
+impl LabelFont {
+    /// Get the CSS font family list for this font. Named fonts end with a
+    /// generic family, so a similar font is used if the named one is not
+    /// available.
+    pub fn css_family(self) -> &'static str {
+        match self {
+            Self::SansSerif => "sans-serif",
+            Self::Serif => "serif",
+            Self::Monospace => "monospace",
+            Self::Helvetica => "Helvetica, Arial, sans-serif",
+            Self::Georgia => "Georgia, 'Times New Roman', serif",
+        }
+    }
+}
+
 /// Calculate the position of the label based on the given coordinates of the
 /// node that should be labeled.
 ///
@@ -111,3 +171,621 @@ pub fn calc_label_pos(
         },
     }
 }
+
+/// Calculate the angle at which text running along the direction from the
+/// given coordinate to the next one should be drawn, so it is always upright
+/// and read from left to right, even when the direction points backwards.
+///
+/// Returns the angle in radians, which always lies within `[-π/2, π/2]`, and
+/// whether the text was turned around to get there. Turned around text should
+/// be anchored at its end instead of its start, so it still sits on the same
+/// side of the coordinate rather than being drawn upside down.
+pub fn upright_label_angle(coord: (f64, f64), next_coord: (f64, f64)) -> (f64, bool) {
+    let angle = (next_coord.1 - coord.1).atan2(next_coord.0 - coord.0);
+
+    // Straight up or down is readable either way, so rounding errors around
+    // those angles should not turn the text around.
+    if angle > FRAC_PI_2 + UPRIGHT_TOLERANCE {
+        (angle - PI, true)
+    } else if angle < -FRAC_PI_2 - UPRIGHT_TOLERANCE {
+        (angle + PI, true)
+    } else {
+        (angle, false)
+    }
+}
+
+/// Draw the given text as a label at the given canvas position, running along
+/// the direction from the given coordinate to the next one. The text is turned
+/// as given by [`upright_label_angle`], and anchored at its end when it was
+/// turned around, so it still extends away from the given coordinate.
+pub fn draw_upright_label(
+    canvas: &CanvasContext<'_>,
+    text: &str,
+    pos: (f64, f64),
+    coord: (f64, f64),
+    next_coord: (f64, f64),
+) {
+    let (angle, flipped) = upright_label_angle(coord, next_coord);
+
+    canvas.save();
+    canvas
+        .translate(pos.0, pos.1)
+        .unwrap();
+    canvas
+        .rotate(angle)
+        .unwrap();
+    canvas.set_text_align(if flipped { "right" } else { "left" });
+    canvas
+        .fill_text(text, 0.0, 0.0)
+        .unwrap();
+    canvas.restore();
+}
+
+/// Calculate the distance between the given point and the line segment between
+/// the given start and end.
+fn distance_to_segment(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+    let segment = (end.0 - start.0, end.1 - start.1);
+    let length_squared = segment.0 * segment.0 + segment.1 * segment.1;
+
+    let progress = if length_squared < f64::EPSILON {
+        0.0
+    } else {
+        (((point.0 - start.0) * segment.0 + (point.1 - start.1) * segment.1) / length_squared)
+            .clamp(0.0, 1.0)
+    };
+
+    let closest = (
+        start.0 + progress * segment.0,
+        start.1 + progress * segment.1,
+    );
+    ((point.0 - closest.0).powi(2) + (point.1 - closest.1).powi(2)).sqrt()
+}
+
+/// The grid cell on the canvas the given coordinate lies in, for cells of the
+/// given size.
+fn grid_cell(pos: (f64, f64), cell_size: f64) -> (i64, i64) {
+    (
+        (pos.0 / cell_size).floor() as i64,
+        (pos.1 / cell_size).floor() as i64,
+    )
+}
+
+/// A straight part of an edge, as its start and end on the canvas.
+type Segment = ((f64, f64), (f64, f64));
+
+/// The stations and edges on the map a label should not be drawn on top of,
+/// bucketed into a grid over the canvas. Building this once per draw lets
+/// every label position be checked against only the stations and edges near
+/// it, instead of against all of them.
+#[derive(Clone, Debug)]
+pub struct LabelObstacles {
+    /// The width and height of a cell of the grid on the canvas.
+    cell_size: f64,
+    /// How close a label may get to the center of a station.
+    station_radius: f64,
+    /// How close a label may get to an edge.
+    edge_margin: f64,
+    /// The canvas positions of the stations that lie within reach of a cell.
+    stations: HashMap<(i64, i64), Vec<(f64, f64)>>,
+    /// The segments of the edges, as their start and end on the canvas, that
+    /// lie within reach of a cell.
+    segments: HashMap<(i64, i64), Vec<Segment>>,
+}
+
+impl LabelObstacles {
+    /// Collect the stations and edges of the given map into the grid, using
+    /// their positions on the canvas in the given state.
+    pub fn new(map: &Map, state: CanvasState) -> Self {
+        let cell_size = state
+            .drawn_square_size()
+            .max(1.0);
+        let mut obstacles = Self {
+            cell_size,
+            station_radius: state.drawn_square_size() / 3.0,
+            edge_margin: state.drawn_square_size() / 8.0,
+            stations: HashMap::new(),
+            segments: HashMap::new(),
+        };
+
+        for station in map.get_stations() {
+            let pos = station.get_canvas_pos(state);
+            for cell in obstacles.cells_around(pos, obstacles.station_radius) {
+                obstacles
+                    .stations
+                    .entry(cell)
+                    .or_default()
+                    .push(pos);
+            }
+        }
+
+        for edge in map.get_edges() {
+            let (Some(from), Some(to)) = (
+                map.get_station(edge.get_from()),
+                map.get_station(edge.get_to()),
+            ) else {
+                continue;
+            };
+
+            let mut points = vec![from.get_canvas_pos(state)];
+            points.extend(
+                edge.get_nodes()
+                    .iter()
+                    .map(|node| node.to_canvas_pos(state)),
+            );
+            points.push(to.get_canvas_pos(state));
+
+            for segment in points.windows(2) {
+                obstacles.add_segment(segment[0], segment[1]);
+            }
+        }
+
+        obstacles
+    }
+
+    /// All cells that lie at least partly within the given distance of the
+    /// given canvas coordinate.
+    fn cells_around(
+        &self,
+        pos: (f64, f64),
+        distance: f64,
+    ) -> impl Iterator<Item = (i64, i64)> + use<> {
+        let min = grid_cell(
+            (pos.0 - distance, pos.1 - distance),
+            self.cell_size,
+        );
+        let max = grid_cell(
+            (pos.0 + distance, pos.1 + distance),
+            self.cell_size,
+        );
+
+        (min.0..=max.0).flat_map(move |x| (min.1..=max.1).map(move |y| (x, y)))
+    }
+
+    /// Add the segment between the given start and end to every cell it is
+    /// within reach of. The segment is walked in steps of a cell, so a long
+    /// segment is only added to the cells along it and not to every cell of
+    /// its bounding box.
+    fn add_segment(&mut self, start: (f64, f64), end: (f64, f64)) {
+        let length = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+        let steps = (length / self.cell_size).ceil() as usize;
+        // Every point within the margin of the segment is within this distance
+        // of one of the steps along it.
+        let reach = self.edge_margin + self.cell_size / 2.0;
+
+        let mut cells = HashSet::new();
+        for step in 0..=steps {
+            let progress = if steps == 0 {
+                0.0
+            } else {
+                step as f64 / steps as f64
+            };
+            let pos = (
+                start.0 + (end.0 - start.0) * progress,
+                start.1 + (end.1 - start.1) * progress,
+            );
+            cells.extend(self.cells_around(pos, reach));
+        }
+
+        for cell in cells {
+            self.segments
+                .entry(cell)
+                .or_default()
+                .push((start, end));
+        }
+    }
+
+    /// Check if a label at the given canvas coordinate would overlap any of the
+    /// stations or edges.
+    fn collides(&self, pos: (f64, f64)) -> bool {
+        let cell = grid_cell(pos, self.cell_size);
+
+        let on_station = self
+            .stations
+            .get(&cell)
+            .is_some_and(|stations| {
+                stations
+                    .iter()
+                    .any(|station_pos| {
+                        ((pos.0 - station_pos.0).powi(2) + (pos.1 - station_pos.1).powi(2)).sqrt()
+                            < self.station_radius
+                    })
+            });
+        if on_station {
+            return true;
+        }
+
+        self.segments
+            .get(&cell)
+            .is_some_and(|segments| {
+                segments
+                    .iter()
+                    .any(|(start, end)| distance_to_segment(pos, *start, *end) < self.edge_margin)
+            })
+    }
+}
+
+/// Pick the first of the given candidate label positions, as returned by
+/// [`calc_label_pos`], that does not overlap any of the given obstacles.
+/// If all candidates overlap something, the first candidate is returned.
+pub fn pick_free_label_pos(obstacles: &LabelObstacles, candidates: &[(f64, f64)]) -> (f64, f64) {
+    candidates
+        .iter()
+        .find(|pos| !obstacles.collides(**pos))
+        .or(candidates.first())
+        .copied()
+        .expect("no label position candidates given")
+}
+
+/// Estimate the box taken up by a label with the given text drawn with its
+/// baseline starting at the given position, or ending there if it is anchored
+/// at its end, as its top-left and bottom-right corners.
+fn label_box(
+    state: CanvasState,
+    text: &str,
+    pos: (f64, f64),
+    end_anchored: bool,
+) -> ((f64, f64), (f64, f64)) {
+    let size = state.drawn_label_size();
+    let width = text
+        .chars()
+        .count() as f64
+        * size
+        * CHAR_WIDTH_FACTOR;
+    let start = if end_anchored { pos.0 - width } else { pos.0 };
+
+    (
+        (start, pos.1 - size),
+        (start + width, pos.1),
+    )
+}
+
+/// The direction the label of the station at the given canvas coordinate
+/// runs along when placed at the given position, as the coordinate to pass to
+/// [`draw_upright_label`] after the station itself. Station labels are drawn
+/// horizontally, away from the side of the station they are on.
+pub fn station_label_direction(coord: (f64, f64), label_pos: (f64, f64)) -> (f64, f64) {
+    (label_pos.0, coord.1)
+}
+
+/// Estimate the box taken up by the label of the given station, with its
+/// position picked to avoid the given obstacles.
+fn station_label_box(
+    state: CanvasState,
+    obstacles: &LabelObstacles,
+    station: &Station,
+) -> ((f64, f64), (f64, f64)) {
+    let coord = station.get_canvas_pos(state);
+    let pos = station.get_label_pos(obstacles, state);
+    let (_, flipped) = upright_label_angle(
+        coord,
+        station_label_direction(coord, pos),
+    );
+
+    label_box(state, station.get_name(), pos, flipped)
+}
+
+/// Find the station whose label contains the given canvas coordinate, if any.
+/// Stations without a name and checkpoints have no label and are skipped.
+pub fn station_label_at(map: &Map, state: CanvasState, pos: (f64, f64)) -> Option<StationID> {
+    let obstacles = LabelObstacles::new(map, state);
+
+    map.get_stations()
+        .into_iter()
+        .filter(|station| {
+            !station.is_checkpoint()
+                && !station
+                    .get_name()
+                    .is_empty()
+        })
+        .find(|station| {
+            let (min, max) = station_label_box(state, &obstacles, station);
+            min.0 <= pos.0 && pos.0 <= max.0 && min.1 <= pos.1 && pos.1 <= max.1
+        })
+        .map(Station::get_id)
+}
+
+/// Find all pairs of stations whose labels would overlap at the current zoom
+/// and label size, with every label at the position it is drawn at as given
+/// by [`Station::get_label_pos`]. Stations without a name and checkpoints have
+/// no label and are skipped. The pairs are ordered by the ids
+/// of their stations.
+pub fn find_overlapping_labels(map: &Map, state: CanvasState) -> Vec<(StationID, StationID)> {
+    let obstacles = LabelObstacles::new(map, state);
+
+    let mut boxes = map
+        .get_stations()
+        .into_iter()
+        .filter(|station| {
+            !station.is_checkpoint()
+                && !station
+                    .get_name()
+                    .is_empty()
+        })
+        .map(|station| {
+            (
+                station.get_id(),
+                station_label_box(state, &obstacles, station),
+            )
+        })
+        .collect::<Vec<_>>();
+    boxes.sort_by(|(_, (a, _)), (_, (b, _))| {
+        a.0.total_cmp(&b.0)
+    });
+
+    let mut overlapping = Vec::new();
+    for (i, (id, (min, max))) in boxes
+        .iter()
+        .enumerate()
+    {
+        // The boxes are sorted by their left side, so once one starts right of
+        // this box none of the ones after it can overlap it either.
+        for (other_id, (other_min, other_max)) in boxes[i + 1..]
+            .iter()
+            .take_while(|(_, (other_min, _))| other_min.0 < max.0)
+        {
+            if other_min.1 < max.1 && min.1 < other_max.1 && min.0 < other_max.0 {
+                overlapping.push((
+                    (*id).min(*other_id),
+                    (*id).max(*other_id),
+                ));
+            }
+        }
+    }
+
+    overlapping.sort();
+    overlapping
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::GridNode;
+
+    #[test]
+    fn test_pick_free_label_pos() {
+        let mut state = CanvasState::new();
+        state.set_square_size(40);
+
+        let mut map = Map::new();
+        let station = Station::new(GridNode::new(5, 5), None);
+        let station_id = station.get_id();
+        let station_pos = station.get_canvas_pos(state);
+        map.add_station(station);
+
+        let candidates = calc_label_pos(state, station_pos, None, None);
+
+        // Nothing around the station, so the first candidate is free.
+        assert_eq!(
+            pick_free_label_pos(
+                &LabelObstacles::new(&map, state),
+                &candidates
+            ),
+            candidates[0]
+        );
+
+        // An edge going up and to the right crosses the first candidate.
+        let other = Station::new(GridNode::new(7, 3), None);
+        let other_id = other.get_id();
+        map.add_station(other);
+        map.get_edge_id_between(station_id, other_id);
+
+        assert_eq!(
+            pick_free_label_pos(
+                &LabelObstacles::new(&map, state),
+                &candidates
+            ),
+            candidates[1]
+        );
+
+        // With every candidate taken, the first one is used.
+        for (x, y) in [(7, 7), (3, 3), (3, 7)] {
+            let blocker = Station::new(GridNode::new(x, y), None);
+            let blocker_id = blocker.get_id();
+            map.add_station(blocker);
+            map.get_edge_id_between(station_id, blocker_id);
+        }
+        assert_eq!(
+            pick_free_label_pos(
+                &LabelObstacles::new(&map, state),
+                &candidates
+            ),
+            candidates[0]
+        );
+    }
+
+    #[test]
+    fn test_label_obstacles_long_edge() {
+        let mut state = CanvasState::new();
+        state.set_square_size(20);
+
+        let mut map = Map::new();
+        let from = Station::new(GridNode::new(2, 5), None);
+        let to = Station::new(GridNode::new(30, 5), None);
+        let from_id = from.get_id();
+        let to_id = to.get_id();
+        let from_pos = from.get_canvas_pos(state);
+        let to_pos = to.get_canvas_pos(state);
+        map.add_station(from);
+        map.add_station(to);
+        map.get_edge_id_between(from_id, to_id);
+
+        let obstacles = LabelObstacles::new(&map, state);
+
+        // Halfway the edge is far from both stations, but still on the edge.
+        let middle = (
+            (from_pos.0 + to_pos.0) / 2.0,
+            from_pos.1,
+        );
+        assert!(obstacles.collides(middle));
+        assert!(!obstacles.collides((
+            middle.0,
+            middle.1 + state.drawn_square_size() * 2.0,
+        )));
+    }
+
+    #[test]
+    fn test_find_overlapping_labels() {
+        let mut state = CanvasState::new();
+        state.set_square_size(20);
+
+        let mut map = Map::new();
+        let first = map.add_named_station(&"Central", (1, 1).into());
+        let second = map.add_named_station(&"Harbour", (3, 1).into());
+        map.add_named_station(&"Airport", (1, 8).into());
+        map.add_named_station(&"", (2, 1).into());
+
+        // The long label of the first station runs into the second one.
+        assert_eq!(
+            find_overlapping_labels(&map, state),
+            vec![(first, second)]
+        );
+
+        // With smaller labels they fit next to each other.
+        state.set_label_size(0.2);
+        assert!(find_overlapping_labels(&map, state).is_empty());
+
+        // Zooming out brings them back together.
+        state.set_zoom_factor(0.4);
+        state.set_label_size(0.5);
+        assert_eq!(
+            find_overlapping_labels(&map, state),
+            vec![(first, second)]
+        );
+    }
+
+    #[test]
+    fn test_station_label_at() {
+        let mut state = CanvasState::new();
+        state.set_square_size(20);
+
+        let mut map = Map::new();
+        let mut station = Station::new(GridNode::new(5, 5), None);
+        station.set_name(&"Central");
+        let station_id = station.get_id();
+        map.add_station(station);
+        map.add_station(Station::new(GridNode::new(10, 5), None));
+        let label_pos = map
+            .get_station(station_id)
+            .unwrap()
+            .get_label_pos(&LabelObstacles::new(&map, state), state);
+
+        let inside = (label_pos.0 + 2.0, label_pos.1 - 2.0);
+        assert_eq!(
+            station_label_at(&map, state, inside),
+            Some(station_id)
+        );
+        assert_eq!(
+            station_label_at(
+                &map,
+                state,
+                (label_pos.0 - 2.0, label_pos.1 - 2.0)
+            ),
+            None
+        );
+
+        // The label follows its offset.
+        map.get_mut_station(station_id)
+            .unwrap()
+            .set_label_offset((0, 2));
+        assert_eq!(
+            station_label_at(&map, state, inside),
+            None
+        );
+        assert_eq!(
+            station_label_at(&map, state, (inside.0, inside.1 + 40.0)),
+            Some(station_id)
+        );
+
+        // The label moves out of the way of an edge leaving the station towards
+        // its preferred position.
+        map.get_mut_station(station_id)
+            .unwrap()
+            .set_label_offset((0, 0));
+        let other = map.add_named_station(&"Other", GridNode::new(8, 2));
+        map.add_line_through(
+            (255, 0, 0),
+            &"Red",
+            &[station_id, other],
+        );
+        let station = map
+            .get_station(station_id)
+            .unwrap();
+        assert_eq!(
+            station.get_label_pos(&LabelObstacles::new(&map, state), state),
+            calc_label_pos(
+                state,
+                station.get_canvas_pos(state),
+                None,
+                None
+            )[1]
+        );
+    }
+
+    #[test]
+    fn test_draw_upright_label() {
+        let canvas = CanvasContext::new();
+
+        // Running to the right, the label is drawn as is.
+        draw_upright_label(
+            &canvas,
+            "A",
+            (10.0, 20.0),
+            (0.0, 0.0),
+            (5.0, 0.0),
+        );
+        // Running to the left, it is turned around and anchored at its end.
+        draw_upright_label(
+            &canvas,
+            "B",
+            (30.0, 40.0),
+            (0.0, 0.0),
+            (-5.0, 0.0),
+        );
+
+        assert_eq!(
+            canvas.get_record("fill_text"),
+            Some(vec![
+                "A,10.0,20.0".to_owned(),
+                "B,30.0,40.0".to_owned(),
+            ])
+        );
+        assert_eq!(
+            canvas.get_record("set_text_align"),
+            Some(vec![
+                "left".to_owned(),
+                "right".to_owned(),
+            ])
+        );
+        assert_eq!(
+            canvas.get_record("rotate"),
+            Some(vec![
+                "0.00".to_owned(),
+                "0.00".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_upright_label_angle() {
+        for degrees in (0..360).step_by(15) {
+            let natural = f64::from(degrees).to_radians();
+            let next = (
+                natural.cos() * 10.0,
+                natural.sin() * 10.0,
+            );
+            let (angle, flipped) = upright_label_angle((0.0, 0.0), next);
+
+            assert!(
+                (-FRAC_PI_2 - 1e-9..=FRAC_PI_2 + 1e-9).contains(&angle),
+                "{degrees} degrees gave an unreadable angle of {angle}"
+            );
+            assert_eq!(flipped, degrees > 90 && degrees < 270);
+
+            // Flipping only turns the text around, it still runs along the
+            // same direction.
+            let turned = if flipped { angle + PI } else { angle };
+            assert!((turned.sin() - natural.sin()).abs() < 1e-9);
+            assert!((turned.cos() - natural.cos()).abs() < 1e-9);
+        }
+    }
+}
@@ -1,21 +1,77 @@
 //! This module contains all functions for drawing the [`crate::models::Map`] to
 //! the canvas.
 
+mod background;
 mod canvas_context;
 mod closest_corner;
+mod color_vision;
+mod draw_cache;
 mod draw_edge;
+mod endcaps;
 mod grid;
 mod labeling;
+mod ruler;
+mod station_order;
+mod theme;
 
+use background::draw_background;
+pub use background::BackgroundShape;
 pub use canvas_context::CanvasContext;
 use closest_corner::calc_closest_corner;
-pub use draw_edge::draw_edge;
+pub use color_vision::{
+    color_hue,
+    scale_dash_pattern,
+    ColorVision,
+    DASH_PATTERNS,
+};
+pub use draw_cache::{
+    DrawCache,
+    DrawOrder,
+};
+pub use draw_edge::{
+    draw_edge,
+    draw_fanned_edge,
+};
+pub use endcaps::draw_endcaps;
 use grid::draw_grid;
-pub use labeling::calc_label_pos;
+use ruler::draw_ruler;
+use station_order::draw_station_order;
+pub use theme::Theme;
+pub use labeling::{
+    calc_label_pos,
+    draw_upright_label,
+    find_overlapping_labels,
+    pick_free_label_pos,
+    station_label_at,
+    station_label_direction,
+    upright_label_angle,
+    LabelFont,
+    LabelObstacles,
+};
 
-use crate::components::MapState;
+use itertools::Itertools;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
-/// Redraws the given canvas based on the given state
+use crate::{
+    algorithms::StraighteningCandidate,
+    components::{
+        CanvasState,
+        MapState,
+    },
+    models::Map,
+};
+
+/// The amount of stations that moved the most which are highlighted by the
+/// displaced stations overlay.
+pub const DISPLACED_STATION_COUNT: usize = 5;
+
+/// Redraws the given canvas based on the given state. On screen the map is
+/// drawn in a single step of [`redraw_canvas_step`], so the layers of the map
+/// and the selections on top of them are drawn in the same order as everywhere
+/// else.
 pub fn redraw_canvas<'a, C>(canvas: C, state: &MapState)
 where
     C: Into<CanvasContext<'a>>,
@@ -26,23 +82,304 @@ where
     // If we're offscreen, then this is for an image of the map to get downloaded,
     // so we only need to draw the map and no grid.
     if !context.is_onscreen() {
-        let map = state
-            .get_map()
-            .without_checkpoints();
+        let layers = MapLayers::for_export(state.get_map());
+
+        let mut canvas_state = state.get_canvas_state();
+        // Unless only the current view is exported, zoom and pan so the whole
+        // map fits on the image. Anything outside the view is clipped by the
+        // edges of the canvas.
+        if !canvas_state.is_export_current_view() {
+            canvas_state.fit_to(
+                layers
+                    .map
+                    .bounds(),
+            );
+        }
 
-        map.draw(&context, state.get_canvas_state(), 1.0);
+        layers.draw(&context, LayerOptions::for_export(canvas_state));
 
         return;
     }
 
-    draw_grid(&context, state.get_canvas_state());
+    let cache = StepCache::new(state);
+    let mut step = Some(DrawStep::Grid);
+    while let Some(current) = step {
+        step = redraw_canvas_step(
+            &context,
+            state,
+            current,
+            usize::MAX,
+            &cache,
+        );
+    }
+}
+
+/// A step of drawing the canvas in chunks with [`redraw_canvas_step`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawStep {
+    /// Draw the grid and the background shapes.
+    Grid,
+    /// Draw the chunk with the given index of the original map overlay.
+    Original(usize),
+    /// Draw the chunk with the given index of the map.
+    Map(usize),
+    /// Draw the overlays and the selections on top of the map.
+    Overlays,
+}
+
+/// The caches for drawing the map and the original map overlay of a state,
+/// which are kept for all steps of drawing it with [`redraw_canvas_step`].
+#[derive(Debug)]
+pub struct StepCache {
+    /// The cache for drawing the map.
+    map: DrawCache,
+    /// The cache for drawing the original map overlay.
+    original: DrawCache,
+}
+
+impl StepCache {
+    /// Create empty caches for drawing the given state.
+    pub fn new(state: &MapState) -> Self {
+        Self {
+            map: DrawCache::new(state.get_canvas_state()),
+            original: DrawCache::new(state.get_canvas_state()),
+        }
+    }
+}
+
+/// Draws a single step of the given state to the given canvas, with the map
+/// split into chunks of the given size. The layers are drawn in the same order
+/// as [`MapLayers::draw`] does and the selections are drawn on top of them
+/// with [`draw_selections`]. Returns the next step to draw,
+/// or `None` if the canvas is done. The canvas is expected to be cleared
+/// before the first step, and the same cache is expected to be given for every
+/// step.
+pub fn redraw_canvas_step(
+    context: &CanvasContext,
+    state: &MapState,
+    step: DrawStep,
+    chunk_size: usize,
+    cache: &StepCache,
+) -> Option<DrawStep> {
+    let canvas_state = state.get_canvas_state();
+    let map = state.get_map();
+    let original = state
+        .get_last_loaded()
+        .filter(|_| state.is_original_overlay_enabled());
+
+    match step {
+        DrawStep::Grid => {
+            draw_grid(context, canvas_state);
+            draw_background(
+                context,
+                map.get_background(),
+                canvas_state,
+            );
+            Some(original.map_or(DrawStep::Map(0), |_| DrawStep::Original(0)))
+        },
+        DrawStep::Original(chunk) => {
+            let more = original.is_some_and(|original| {
+                original.draw_chunk(
+                    context,
+                    canvas_state,
+                    0.3,
+                    &cache.original,
+                    chunk,
+                    chunk_size,
+                )
+            });
+            Some(if more {
+                DrawStep::Original(chunk + 1)
+            } else {
+                DrawStep::Map(0)
+            })
+        },
+        DrawStep::Map(chunk) => {
+            Some(
+                if map.draw_chunk(
+                    context,
+                    canvas_state,
+                    1.0,
+                    &cache.map,
+                    chunk,
+                    chunk_size,
+                ) {
+                    DrawStep::Map(chunk + 1)
+                } else {
+                    DrawStep::Overlays
+                },
+            )
+        },
+        DrawStep::Overlays => {
+            draw_overlays(
+                context,
+                map,
+                LayerOptions::from_state(state),
+            );
+            draw_selections(context, state, &cache.map);
+            None
+        },
+    }
+}
+
+/// The maps of the map state that are drawn below any selections. These can be
+/// sent to a worker to be drawn to an offscreen canvas there.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MapLayers {
+    /// The map to draw.
+    map: Map,
+    /// The map as it was originally loaded, if any.
+    original: Option<Map>,
+}
+
+/// The settings for drawing the [`MapLayers`] of a map state, which are sent
+/// along with them to the worker drawing them.
+// The layers are all toggled independently, so many bools are needed
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LayerOptions {
+    /// The state of the canvas to draw the layers for.
+    canvas_state: CanvasState,
+    /// If the original map should be drawn as an overlay.
+    show_original: bool,
+    /// If edges drawn on top of other edges should be highlighted.
+    show_overlap: bool,
+    /// If the stations that moved the most should be highlighted.
+    show_displacement: bool,
+    /// If the stations contracted into edges should be drawn along them.
+    show_contracted: bool,
+    /// If the layers are drawn for an exported image, which leaves out the
+    /// grid and the overlays and adds the ruler if it should be exported.
+    export: bool,
+}
+
+impl LayerOptions {
+    /// Collect the settings for drawing the layers from the given map state.
+    pub fn from_state(state: &MapState) -> Self {
+        Self {
+            canvas_state: state.get_canvas_state(),
+            show_original: state.is_original_overlay_enabled(),
+            show_overlap: state.is_overlap_overlay_enabled(),
+            show_displacement: state.is_displacement_overlay_enabled(),
+            show_contracted: state.is_contracted_overlay_enabled(),
+            export: false,
+        }
+    }
+
+    /// Collect the settings for drawing the layers to an exported image with
+    /// the given canvas state. The color vision simulation is only a preview
+    /// and exported images always use the light theme, so neither is taken
+    /// from the canvas state.
+    pub fn for_export(mut canvas_state: CanvasState) -> Self {
+        canvas_state.set_color_vision(ColorVision::Normal);
+        canvas_state.set_theme(Theme::Light);
+
+        Self {
+            canvas_state,
+            show_original: false,
+            show_overlap: false,
+            show_displacement: false,
+            show_contracted: false,
+            export: true,
+        }
+    }
+
+    /// A getter method for the state of the canvas the layers are drawn for.
+    pub fn get_canvas_state(&self) -> CanvasState {
+        self.canvas_state
+    }
+}
+
+impl MapLayers {
+    /// Collect the maps to draw from the given map state. As these only change
+    /// together with the revision of the map state, see
+    /// [`MapState::get_map_revision`], they only need to be collected again
+    /// once that changed.
+    pub fn from_state(state: &MapState) -> Self {
+        Self {
+            map: state
+                .get_map()
+                .clone(),
+            original: state
+                .get_last_loaded()
+                .cloned(),
+        }
+    }
+
+    /// Collect the maps to draw for an exported image of the given map, which
+    /// leaves out its checkpoints and the original map.
+    pub fn for_export(map: &Map) -> Self {
+        Self {
+            map: map.without_checkpoints(),
+            original: None,
+        }
+    }
+
+    /// Draw the grid, the background shapes, the original map overlay, the map,
+    /// the overlapping edges, the displaced stations, the contracted
+    /// stations and the search region of the algorithm to the given canvas
+    /// with the given settings. For an exported image only the background
+    /// shapes, the map and the ruler are drawn.
+    pub fn draw(&self, context: &CanvasContext, options: LayerOptions) {
+        let canvas_state = options.canvas_state;
+
+        if !options.export {
+            draw_grid(context, canvas_state);
+        }
+        draw_background(
+            context,
+            self.map
+                .get_background(),
+            canvas_state,
+        );
+
+        if let Some(original) = self
+            .original
+            .as_ref()
+            .filter(|_| options.show_original)
+        {
+            original.draw(context, canvas_state, 0.3);
+        }
+
+        self.map
+            .draw(context, canvas_state, 1.0);
 
-    if state.is_original_overlay_enabled() {
-        if let Some(original) = state.get_last_loaded() {
-            original.draw(&context, state.get_canvas_state(), 0.3);
+        if options.export {
+            if canvas_state.is_ruler_in_export() {
+                draw_ruler(context, canvas_state);
+            }
+        } else {
+            draw_overlays(context, &self.map, options);
         }
     }
+}
+
+/// Draws the overlays of the given map that are enabled in the given settings
+/// on top of it, which are the overlapping edges, the displaced stations, the
+/// contracted stations and the search region of the algorithm.
+fn draw_overlays(context: &CanvasContext, map: &Map, options: LayerOptions) {
+    let canvas_state = options.canvas_state;
+
+    if options.show_overlap {
+        draw_overlapping_edges(context, map, canvas_state);
+    }
+
+    if options.show_displacement {
+        draw_displaced_stations(context, map, canvas_state);
+    }
+
+    if options.show_contracted {
+        draw_contracted_stations(context, map, canvas_state);
+    }
+
+    draw_search_region(context, map, canvas_state);
+}
 
+/// Draws the selected stations and lines, the order of the stations of lines,
+/// the straightening preview, the box select and the ruler of the given state
+/// on top of the map, after its layers have been drawn offscreen. The cache is
+/// the one used to draw the map of the state.
+pub fn draw_selections(context: &CanvasContext, state: &MapState, cache: &DrawCache) {
     let map = state.get_map();
 
     state
@@ -51,27 +388,245 @@ where
         .for_each(|d| {
             d.draw(
                 map,
-                &context,
+                context,
                 state.get_canvas_state(),
                 state.get_selected_stations(),
+                cache,
             );
         });
 
-    map.draw(&context, state.get_canvas_state(), 1.0);
-
     state
         .get_selected_lines()
         .iter()
-        .for_each(|d| d.draw(map, &context, state.get_canvas_state()));
+        .for_each(|d| d.draw(map, context, state.get_canvas_state()));
+
+    draw_station_order(
+        context,
+        map,
+        state.get_canvas_state(),
+        state.get_station_order_lines(),
+    );
+
+    state
+        .get_straighten_preview()
+        .inspect(|candidate| draw_straighten_preview(context, candidate, state.get_canvas_state()));
 
     state
         .get_box_select()
-        .inspect(|(start, end)| draw_box_select(&context, *start, *end));
+        .inspect(|(start, end)| draw_box_select(context, *start, *end, state.get_canvas_state()));
+
+    if state
+        .get_canvas_state()
+        .is_ruler_shown()
+    {
+        draw_ruler(context, state.get_canvas_state());
+    }
+}
+
+/// Highlights all edges that are drawn on top of another edge.
+fn draw_overlapping_edges(context: &CanvasContext, map: &Map, state: CanvasState) {
+    let mut width = state.drawn_square_size() / 2.0;
+    if width < 3.0 {
+        width = 3.0;
+    }
+
+    context
+        .set_line_dash(&[])
+        .unwrap();
+    context.set_line_width(width);
+    context.set_global_alpha(0.4);
+    context.set_stroke_style_str("red");
+
+    for edge in map
+        .find_overlapping_edges()
+        .into_iter()
+        .flat_map(|(a, b)| [a, b])
+        .unique()
+        .filter_map(|id| map.get_edge(id))
+    {
+        let (Some(from), Some(to)) = (
+            map.get_station(edge.get_from()),
+            map.get_station(edge.get_to()),
+        ) else {
+            continue;
+        };
+
+        context.begin_path();
+        draw_edge(
+            from.get_pos(),
+            to.get_pos(),
+            edge.get_nodes(),
+            context,
+            state,
+            0.0,
+        );
+        context.stroke();
+    }
+}
+
+/// Draws a halo around the stations that moved the most from their original
+/// position, tinted from yellow to red by how far they moved compared to the
+/// station that moved the furthest.
+fn draw_displaced_stations(context: &CanvasContext, map: &Map, state: CanvasState) {
+    let displaced = map
+        .station_displacements()
+        .into_iter()
+        .take(DISPLACED_STATION_COUNT)
+        .filter(|(_, displacement)| *displacement > 0.0)
+        .collect::<Vec<_>>();
+    let Some(max) = displaced
+        .first()
+        .map(|(_, displacement)| *displacement)
+    else {
+        return;
+    };
+
+    let radius = (state.drawn_square_size() * 0.75).max(6.0);
+
+    context
+        .set_line_dash(&[])
+        .unwrap();
+    context.set_global_alpha(0.5);
+
+    for (id, displacement) in displaced {
+        let Some(station) = map.get_station(id) else {
+            continue;
+        };
+        let (x, y) = station.get_canvas_pos(state);
+        let green = (200.0 * (1.0 - displacement / max)).round() as u8;
+
+        context.set_fill_style_str(&format!("rgb(255, {green}, 0)"));
+        context.begin_path();
+        context
+            .arc(x, y, radius, 0.0, 2.0 * std::f64::consts::PI)
+            .unwrap();
+        context.fill();
+    }
+}
+
+/// Draws a small hollow marker for every station that is contracted into an
+/// edge, spread evenly along that edge, to check what the station contraction
+/// of the algorithm did before the stations are expanded again.
+fn draw_contracted_stations(context: &CanvasContext, map: &Map, state: CanvasState) {
+    let radius = (state.drawn_square_size() / 4.0).max(2.0);
+
+    context
+        .set_line_dash(&[])
+        .unwrap();
+    context.set_global_alpha(0.8);
+    context.set_line_width(2.0);
+    context.set_stroke_style_str("magenta");
+    context.set_fill_style_str("white");
+
+    for edge in map.get_edges() {
+        for (x, y) in edge.contracted_station_positions(map, state) {
+            context.begin_path();
+            context
+                .arc(x, y, radius, 0.0, 2.0 * std::f64::consts::PI)
+                .unwrap();
+            context.fill();
+            context.stroke();
+        }
+    }
+}
+
+/// Draws the nodes the algorithm considers for the stations at both ends of
+/// the edge it is routing as squares, blue for the from station and orange for
+/// the to station, if the map has such a search region.
+fn draw_search_region(context: &CanvasContext, map: &Map, state: CanvasState) {
+    let Some(region) = map.get_search_region() else {
+        return;
+    };
+    let size = (state.drawn_square_size() / 2.0).max(4.0);
+
+    context
+        .set_line_dash(&[])
+        .unwrap();
+    context.set_global_alpha(0.5);
+
+    for (nodes, color) in [
+        (&region.from_nodes, "dodgerblue"),
+        (&region.to_nodes, "darkorange"),
+    ] {
+        context.set_fill_style_str(color);
+        context.begin_path();
+        for node in nodes {
+            let (x, y) = node.to_canvas_pos(state);
+            context.rect(x - size / 2.0, y - size / 2.0, size, size);
+        }
+        context.fill();
+    }
+}
+
+/// Draws a previewed straightening faintly on the canvas, with the new
+/// positions of the stations and the cost of the straightening next to its
+/// end.
+fn draw_straighten_preview(
+    context: &CanvasContext,
+    candidate: &StraighteningCandidate,
+    state: CanvasState,
+) {
+    let nodes = candidate.get_nodes();
+    let Some(last) = nodes.last() else {
+        return;
+    };
+
+    let width = (state.drawn_square_size() / 4.0).max(2.0);
+
+    context
+        .set_line_dash(&[])
+        .unwrap();
+    context.set_global_alpha(0.4);
+    context.set_line_width(width);
+    context.set_stroke_style_str("blue");
+    context.set_fill_style_str("blue");
+
+    context.begin_path();
+    for (i, node) in nodes
+        .iter()
+        .enumerate()
+    {
+        let (x, y) = node.to_canvas_pos(state);
+        if i == 0 {
+            context.move_to(x, y);
+        } else {
+            context.line_to(x, y);
+        }
+    }
+    context.stroke();
+
+    for node in candidate
+        .get_stations()
+        .values()
+    {
+        let (x, y) = node.to_canvas_pos(state);
+        context.begin_path();
+        context
+            .arc(x, y, width * 1.5, 0.0, 2.0 * std::f64::consts::PI)
+            .unwrap();
+        context.fill();
+    }
+
+    let (x, y) = last.to_canvas_pos(state);
+    context.set_global_alpha(1.0);
+    context.set_font("12px sans-serif");
+    context
+        .fill_text(
+            &format!("cost: {}", candidate.get_cost()),
+            x + width * 2.0,
+            y - width * 2.0,
+        )
+        .unwrap();
 }
 
 /// Draws a box select overlay on the canvas.
-fn draw_box_select(context: &CanvasContext, start: (f64, f64), end: (f64, f64)) {
-    context.set_stroke_style_str("black");
+fn draw_box_select(
+    context: &CanvasContext,
+    start: (f64, f64),
+    end: (f64, f64),
+    state: CanvasState,
+) {
+    context.set_stroke_style_str(state.get_theme().foreground());
     context
         .set_line_dash(&[10, 2, 2, 2])
         .unwrap();
@@ -87,3 +642,228 @@ fn draw_box_select(context: &CanvasContext, start: (f64, f64), end: (f64, f64))
     );
     context.stroke();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        algorithms::straightening_candidates,
+        models::{
+            Edge,
+            SelectedStation,
+            Station,
+            StationID,
+        },
+    };
+
+    #[test]
+    fn test_map_layers() {
+        let mut map = Map::new();
+        map.add_station(Station::new((2, 2).into(), None));
+
+        let mut state = MapState::new(map.clone());
+        state.set_last_loaded(map);
+
+        let layers = MapLayers::from_state(&state);
+        assert!(layers
+            .original
+            .is_some());
+
+        // The original map is only drawn if its overlay is enabled.
+        let context = CanvasContext::new();
+        layers.draw(&context, LayerOptions::from_state(&state));
+        assert_eq!(
+            context
+                .get_record("arc")
+                .map(|r| r.len()),
+            Some(1)
+        );
+
+        state.set_original_overlay_enabled(true);
+        let context = CanvasContext::new();
+        layers.draw(&context, LayerOptions::from_state(&state));
+        assert_eq!(
+            context
+                .get_record("arc")
+                .map(|r| r.len()),
+            Some(2)
+        );
+
+        state.set_box_select_start((1.0, 1.0));
+        state.update_box_select_end((3.0, 4.0));
+        let context = CanvasContext::new();
+        draw_selections(
+            &context,
+            &state,
+            &DrawCache::new(state.get_canvas_state()),
+        );
+        assert_eq!(
+            context.get_record("rect"),
+            Some(vec!["1.0,1.0,2.0,3.0".to_string()])
+        );
+        assert!(context
+            .get_record("arc")
+            .is_none());
+    }
+
+    #[test]
+    fn test_map_layers_export() {
+        let mut map = Map::new();
+        map.add_station(Station::new((2, 2).into(), None));
+        map.add_station(Station::new_checkpoint((4, 2).into(), None));
+
+        let mut state = MapState::new(map.clone());
+        state.set_last_loaded(map);
+        state.set_original_overlay_enabled(true);
+
+        // The grid is drawn with lines, which an exported image leaves out.
+        let context = CanvasContext::new();
+        MapLayers::from_state(&state).draw(&context, LayerOptions::from_state(&state));
+        assert!(context
+            .get_record("line_to")
+            .is_some());
+
+        // Neither the checkpoint nor the original map end up in an exported
+        // image.
+        let context = CanvasContext::new();
+        MapLayers::for_export(state.get_map()).draw(
+            &context,
+            LayerOptions::for_export(state.get_canvas_state()),
+        );
+        assert!(context
+            .get_record("line_to")
+            .is_none());
+        assert_eq!(
+            context
+                .get_record("arc")
+                .map(|r| r.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_redraw_canvas_step() {
+        let mut map = Map::new();
+        for x in 0..3 {
+            map.add_station(Station::new((x * 2, 0).into(), None));
+        }
+
+        let state = MapState::new(map);
+        let context = CanvasContext::new();
+
+        let cache = StepCache::new(&state);
+        let mut steps = vec![DrawStep::Grid];
+        while let Some(next) = redraw_canvas_step(
+            &context,
+            &state,
+            *steps
+                .last()
+                .unwrap(),
+            2,
+            &cache,
+        ) {
+            steps.push(next);
+        }
+
+        assert_eq!(
+            steps,
+            vec![
+                DrawStep::Grid,
+                DrawStep::Map(0),
+                DrawStep::Map(1),
+                DrawStep::Overlays,
+            ]
+        );
+        assert_eq!(
+            context
+                .get_record("arc")
+                .map(|r| r.len()),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_draw_displaced_stations() {
+        let mut map = Map::new();
+        for x in 0..7 {
+            let mut station = Station::new((x * 2, 0).into(), None);
+            station.set_pos((x * 2, x).into());
+            map.add_station(station);
+        }
+
+        let mut state = CanvasState::new();
+        state.set_square_size(10);
+
+        let context = CanvasContext::new();
+        draw_displaced_stations(&context, &map, state);
+
+        // The station that did not move is never highlighted, and only the
+        // stations that moved the most are.
+        assert_eq!(
+            context
+                .get_record("arc")
+                .map(|r| r.len()),
+            Some(DISPLACED_STATION_COUNT)
+        );
+        assert_eq!(
+            context
+                .get_record("set_fill_style")
+                .and_then(|r| r.first().cloned()),
+            Some("rgb(255, 0, 0)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_draw_straighten_preview() {
+        let mut map = Map::new();
+        let ids: Vec<StationID> = vec![1.into(), 2.into(), 3.into()];
+        for (id, pos) in ids
+            .iter()
+            .zip([(0, 0), (2, 0), (4, 0)])
+        {
+            map.add_station(Station::new(pos.into(), Some(*id)));
+        }
+        for pair in ids.windows(2) {
+            map.get_edge_id_between(pair[0], pair[1]);
+        }
+
+        let selected_stations = ids
+            .iter()
+            .filter_map(|id| map.get_station(*id))
+            .cloned()
+            .map(SelectedStation::new)
+            .collect();
+        let selected_edges = map
+            .get_edges()
+            .into_iter()
+            .map(Edge::get_id)
+            .collect();
+
+        let mut state = MapState::new(map);
+        state.set_selected_stations(selected_stations);
+        state.set_selected_edges(selected_edges);
+        state.update_canvas_state(|canvas| canvas.set_square_size(10));
+
+        let (edges, stations) = state.get_selected_section();
+        let candidate = straightening_candidates(state.get_map(), &edges, &stations)
+            .unwrap()
+            .remove(0);
+        state.set_straighten_preview(candidate);
+
+        let context = CanvasContext::new();
+        draw_selections(
+            &context,
+            &state,
+            &DrawCache::new(state.get_canvas_state()),
+        );
+        assert_eq!(
+            context.get_record("fill_text"),
+            Some(vec!["cost: 0,45.0,-5.0".to_owned()])
+        );
+
+        state.clear_all_selections();
+        assert!(state
+            .get_straighten_preview()
+            .is_none());
+    }
+}
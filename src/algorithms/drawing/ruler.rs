@@ -0,0 +1,187 @@
+//! Contains everything for drawing the ruler along the edges of the canvas and
+//! the scale bar.
+
+use super::CanvasContext;
+use crate::components::CanvasState;
+
+/// The width of the ruler bands along the top and left edge of the canvas in
+/// pixels.
+const RULER_WIDTH: f64 = 24.0;
+/// The minimum amount of pixels between two labeled ticks on the ruler.
+const MIN_LABEL_SPACING: f64 = 40.0;
+/// The minimum length of the scale bar in pixels.
+const MIN_SCALE_BAR_LENGTH: f64 = 60.0;
+/// The distance of the scale bar from the bottom-right corner of the canvas,
+/// leaving room for the zoom buttons.
+const SCALE_BAR_MARGIN: (f64, f64) = (100.0, 20.0);
+
+/// Get the smallest amount of grid squares out of 1, 2, 5, 10, 20, 50 and so
+/// on, that spans at least the given length in pixels with the given size of
+/// a grid square.
+fn nice_step(square_size: f64, min_length: f64) -> i32 {
+    if square_size <= 0.0 {
+        return 1;
+    }
+
+    let mut magnitude = 1;
+    loop {
+        for multiplier in [1, 2, 5] {
+            let step = multiplier * magnitude;
+            if f64::from(step) * square_size >= min_length {
+                return step;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+/// Draws a ruler marking the grid coordinates along the top and left edge of
+/// the canvas, and a scale bar showing how many pixels a grid square currently
+/// is. This should be called after everything else is drawn, so it is on top.
+pub fn draw_ruler(canvas: &CanvasContext, state: CanvasState) {
+    let (height, width) = state.get_size();
+    let square_size = state.drawn_square_size();
+    let offset = state.get_offset();
+    let step = nice_step(square_size, MIN_LABEL_SPACING);
+
+    canvas
+        .set_line_dash(&[])
+        .unwrap();
+    canvas.set_global_alpha(0.85);
+    canvas.set_fill_style_str(
+        state
+            .get_theme()
+            .background(),
+    );
+    canvas.begin_path();
+    canvas.rect(0.0, 0.0, width, RULER_WIDTH);
+    canvas.rect(0.0, 0.0, RULER_WIDTH, height);
+    canvas.fill();
+
+    canvas.set_global_alpha(1.0);
+    canvas.set_line_width(1.0);
+    canvas.set_stroke_style_str(
+        state
+            .get_theme()
+            .foreground(),
+    );
+    canvas.set_fill_style_str(
+        state
+            .get_theme()
+            .foreground(),
+    );
+    canvas.set_font("10px sans-serif");
+    canvas.begin_path();
+
+    let horizontal_count = (width / square_size).ceil() as i32;
+    for i in 0..=horizontal_count {
+        let x = f64::from(i) * square_size;
+        if x < RULER_WIDTH {
+            continue;
+        }
+
+        let coordinate = i + offset.0;
+        if coordinate.rem_euclid(step) == 0 {
+            canvas.move_to(x, RULER_WIDTH / 2.0);
+            canvas
+                .fill_text(&coordinate.to_string(), x + 2.0, 10.0)
+                .unwrap();
+        } else {
+            canvas.move_to(x, RULER_WIDTH * 0.75);
+        }
+        canvas.line_to(x, RULER_WIDTH);
+    }
+
+    let vertical_count = (height / square_size).ceil() as i32;
+    for i in 0..=vertical_count {
+        let y = f64::from(i) * square_size;
+        if y < RULER_WIDTH {
+            continue;
+        }
+
+        let coordinate = i + offset.1;
+        if coordinate.rem_euclid(step) == 0 {
+            canvas.move_to(RULER_WIDTH / 2.0, y);
+            canvas
+                .fill_text(&coordinate.to_string(), 1.0, y - 2.0)
+                .unwrap();
+        } else {
+            canvas.move_to(RULER_WIDTH * 0.75, y);
+        }
+        canvas.line_to(RULER_WIDTH, y);
+    }
+
+    canvas.stroke();
+
+    draw_scale_bar(canvas, (width, height), square_size);
+}
+
+/// Draws a scale bar spanning a round amount of grid squares in the
+/// bottom-right corner of the canvas, labeled with its length in pixels.
+fn draw_scale_bar(canvas: &CanvasContext, (width, height): (f64, f64), square_size: f64) {
+    let squares = nice_step(square_size, MIN_SCALE_BAR_LENGTH);
+    let length = f64::from(squares) * square_size;
+
+    let end = width - SCALE_BAR_MARGIN.0;
+    let start = end - length;
+    let y = height - SCALE_BAR_MARGIN.1;
+
+    canvas.set_line_width(2.0);
+    canvas.begin_path();
+    canvas.move_to(start, y - 5.0);
+    canvas.line_to(start, y);
+    canvas.line_to(end, y);
+    canvas.line_to(end, y - 5.0);
+    canvas.stroke();
+
+    let label = if squares == 1 {
+        format!("1 square = {length:.0}px")
+    } else {
+        format!("{squares} squares = {length:.0}px")
+    };
+    canvas
+        .fill_text(&label, start, y - 8.0)
+        .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nice_step() {
+        assert_eq!(nice_step(50.0, 40.0), 1);
+        assert_eq!(nice_step(25.0, 40.0), 2);
+        assert_eq!(nice_step(10.0, 40.0), 5);
+        assert_eq!(nice_step(3.0, 40.0), 20);
+        assert_eq!(nice_step(0.0, 40.0), 1);
+    }
+
+    #[test]
+    fn test_draw_ruler() {
+        let mut state = CanvasState::new();
+        state.set_square_size(10);
+        state.set_size((100.0, 200.0));
+        state.set_offset((-3, 2));
+
+        let canvas = CanvasContext::new();
+        draw_ruler(&canvas, state);
+
+        let labels = canvas
+            .get_record("fill_text")
+            .unwrap();
+        // Every 5 squares is labeled, skipping the ones under the corner.
+        assert_eq!(
+            labels,
+            vec![
+                "0,32.0,10.0".to_owned(),
+                "5,82.0,10.0".to_owned(),
+                "10,132.0,10.0".to_owned(),
+                "15,182.0,10.0".to_owned(),
+                "5,1.0,28.0".to_owned(),
+                "10,1.0,78.0".to_owned(),
+                "10 squares = 100px,0.0,72.0".to_owned(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Contains the functions for drawing the index of every station within a line
+//! next to it, to check the order the stations of the line are in.
+
+use super::CanvasContext;
+use crate::{
+    components::CanvasState,
+    models::{
+        LineID,
+        Map,
+    },
+};
+
+/// Draw the index of every station within each of the given lines next to it,
+/// in the color of the line. The numbers are drawn above and to the right of
+/// the station so they don't cover it, and the numbers of multiple lines are
+/// stacked above each other. Nothing is drawn if labels are hidden.
+pub fn draw_station_order(
+    canvas: &CanvasContext<'_>,
+    map: &Map,
+    state: CanvasState,
+    lines: &[LineID],
+) {
+    if lines.is_empty() || !state.is_labels_shown() {
+        return;
+    }
+
+    let offset = state.drawn_square_size() * 0.6;
+    let label_size = state.drawn_label_size();
+
+    canvas.set_global_alpha(1.0);
+    canvas.set_font(&format!(
+        "bold {}",
+        state.label_font_css()
+    ));
+
+    for (i, line) in lines
+        .iter()
+        .filter_map(|id| map.get_line(*id))
+        .enumerate()
+    {
+        let color = state
+            .get_color_vision()
+            .simulate(line.get_color());
+        canvas.set_fill_style_str(&format!(
+            "rgb({} {} {})",
+            color.0, color.1, color.2,
+        ));
+
+        for (index, id) in line
+            .get_stations()
+            .iter()
+            .enumerate()
+        {
+            let Some(station) = map.get_station(*id) else {
+                continue;
+            };
+            let (x, y) = station.get_canvas_pos(state);
+
+            canvas
+                .fill_text(
+                    &index.to_string(),
+                    x + offset,
+                    y - offset - i as f64 * label_size,
+                )
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_station_order() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (4, 0).into());
+        let c = map.add_named_station(&"C", (4, 4).into());
+        let red = map.add_line_through((255, 0, 0), &"Red", &[a, b, c]);
+        let blue = map.add_line_through((0, 0, 255), &"Blue", &[c, b]);
+
+        let context = CanvasContext::new();
+        draw_station_order(
+            &context,
+            &map,
+            CanvasState::new(),
+            &[red, blue],
+        );
+        assert_eq!(
+            context
+                .get_record("fill_text")
+                .map(|texts| texts.len()),
+            Some(5)
+        );
+
+        // Lines without the order shown draw nothing.
+        let context = CanvasContext::new();
+        draw_station_order(&context, &map, CanvasState::new(), &[]);
+        assert!(
+            context
+                .get_record("fill_text")
+                .is_none()
+        );
+
+        // Neither does hiding the labels.
+        let context = CanvasContext::new();
+        let mut state = CanvasState::new();
+        state.set_labels_shown(false);
+        draw_station_order(&context, &map, state, &[red, blue]);
+        assert!(
+            context
+                .get_record("fill_text")
+                .is_none()
+        );
+    }
+}
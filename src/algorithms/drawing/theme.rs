@@ -0,0 +1,89 @@
+//! Contains the [`Theme`] the canvas is drawn with.
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The color theme the canvas is drawn with, matching the theme of the rest of
+/// the page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    /// Dark lines and text on a light background.
+    #[default]
+    Light,
+    /// Light lines and text on a dark background.
+    Dark,
+}
+
+impl Theme {
+    /// The color of stations, text and other regular strokes.
+    pub fn foreground(self) -> &'static str {
+        match self {
+            Self::Light => "black",
+            Self::Dark => "white",
+        }
+    }
+
+    /// The color of less important details, like the lock markers and the
+    /// interchange connections.
+    pub fn muted(self) -> &'static str {
+        match self {
+            Self::Light => "grey",
+            Self::Dark => "#a3a3a3",
+        }
+    }
+
+    /// The color of the grid lines.
+    pub fn grid(self) -> &'static str {
+        match self {
+            Self::Light => "grey",
+            Self::Dark => "#737373",
+        }
+    }
+
+    /// The color of backgrounds drawn on top of the map, like the ruler bands.
+    pub fn background(self) -> &'static str {
+        match self {
+            Self::Light => "white",
+            Self::Dark => "#404040",
+        }
+    }
+
+    /// The name of the theme as it is stored in the browser.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+
+    /// Get the theme with the given stored name, if it is valid.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_names() {
+        for theme in [Theme::Light, Theme::Dark] {
+            assert_eq!(
+                Theme::from_name(theme.as_str()),
+                Some(theme)
+            );
+        }
+        assert_eq!(Theme::from_name("blue"), None);
+        assert_ne!(
+            Theme::Light.foreground(),
+            Theme::Dark.foreground()
+        );
+    }
+}
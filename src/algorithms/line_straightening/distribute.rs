@@ -0,0 +1,310 @@
+//! Contains the function to spread the stations of a line section evenly along
+//! its edges.
+
+use itertools::Itertools;
+
+use crate::{
+    models::{
+        Edge,
+        GridNode,
+        Map,
+        Station,
+        StationID,
+    },
+    utils::{
+        line_sections::{
+            to_line_section,
+            LineSection,
+        },
+        Result,
+    },
+    Error,
+};
+
+/// The nodes along a line section, together with its stations in order and the
+/// index of the node each of them is on.
+type SectionPolyline = (Vec<GridNode>, Vec<(StationID, usize)>);
+
+/// Extend the given polyline with single grid steps until it reaches the
+/// target node, so every node of the polyline neighbors the next one.
+fn walk_to(polyline: &mut Vec<GridNode>, target: GridNode) {
+    let mut current = *polyline
+        .last()
+        .expect("polyline starts at a station");
+
+    while current != target {
+        current = GridNode(
+            current.0 + (target.0 - current.0).signum(),
+            current.1 + (target.1 - current.1).signum(),
+        );
+        polyline.push(current);
+    }
+}
+
+/// Follow the edges of the line section from its first end to its last one,
+/// collecting every node along the way. Returns those nodes together with the
+/// stations of the section in order and the index of their node.
+fn section_polyline(map: &Map, section: &LineSection) -> Result<SectionPolyline> {
+    let station_pos = |id: StationID| {
+        map.get_station(id)
+            .map(Station::get_pos)
+            .ok_or_else(|| Error::other(format!("station {id} not found")))
+    };
+
+    let mut current = section.ends[0];
+    let mut polyline = vec![station_pos(current)?];
+    let mut stations = vec![(current, 0)];
+
+    for edge in &section.edges {
+        let next = edge
+            .opposite(current)
+            .ok_or_else(|| Error::other("the selected edges do not form a chain"))?;
+
+        let mut nodes = edge
+            .get_nodes()
+            .to_vec();
+        if edge.get_from() != current {
+            nodes.reverse();
+        }
+        nodes.push(station_pos(next)?);
+
+        for node in nodes {
+            walk_to(&mut polyline, node);
+        }
+
+        stations.push((next, polyline.len() - 1));
+        current = next;
+    }
+
+    Ok((polyline, stations))
+}
+
+/// Spread the free stations between every two fixed stations evenly over the
+/// indices between them. The first and last stations are always fixed, as are
+/// the ones in the given list of locked stations.
+fn spread_indices(stations: &[(StationID, usize)], locked: &[StationID]) -> Result<Vec<usize>> {
+    let mut indices = stations
+        .iter()
+        .map(|(_, index)| *index)
+        .collect::<Vec<_>>();
+
+    let anchors = stations
+        .iter()
+        .enumerate()
+        .filter(|(i, (id, _))| *i == 0 || *i == stations.len() - 1 || locked.contains(id))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    for (start, end) in anchors
+        .into_iter()
+        .tuple_windows()
+    {
+        let free = end - start - 1;
+        let room = indices[end] - indices[start];
+        if room <= free {
+            return Err(Error::other(
+                "not enough room along the edges to distribute the stations",
+            ));
+        }
+
+        for step in 1..=free {
+            indices[start + step] =
+                indices[start] + (room as f64 * step as f64 / (free + 1) as f64).round() as usize;
+        }
+    }
+
+    Ok(indices)
+}
+
+/// Move the stations in the middle of the selected line section so they are
+/// spread evenly along its current edges, between the two ends of the
+/// section. Locked stations stay where they are and the stations between them
+/// are spread out on their own. The edges of the section are cut to fit the
+/// new positions and all other edges of the moved stations are recalculated.
+///
+/// Returns an error if the selection is not one line section, or if one of its
+/// stations in the middle is a junction with more than two neighbors.
+pub fn distribute_evenly(
+    map: &mut Map,
+    selected_edges: &[Edge],
+    selected_stations: &[Station],
+) -> Result<()> {
+    let section = to_line_section(selected_stations, selected_edges)?;
+
+    for id in &section.middles {
+        let neighbors = map
+            .get_station(*id)
+            .map(|station| {
+                station
+                    .get_edges()
+                    .iter()
+                    .filter_map(|edge| map.get_edge(*edge))
+                    .filter_map(|edge| edge.opposite(*id))
+                    .unique()
+                    .count()
+            })
+            .unwrap_or_default();
+        if neighbors > 2 {
+            return Err(Error::other(format!(
+                "station {id} is a junction, only a simple chain of stations can be distributed"
+            )));
+        }
+    }
+
+    let (polyline, stations) = section_polyline(map, &section)?;
+    let locked = stations
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| {
+            map.get_station(*id)
+                .is_some_and(Station::is_locked)
+        })
+        .collect::<Vec<_>>();
+    let indices = spread_indices(&stations, &locked)?;
+
+    let mut moved = Vec::new();
+    for ((id, old_index), index) in stations
+        .iter()
+        .zip(&indices)
+    {
+        if old_index == index {
+            continue;
+        }
+        map.get_mut_station(*id)
+            .expect("station of the section exists")
+            .set_pos(polyline[*index]);
+        moved.push(*id);
+    }
+
+    for (i, edge) in section
+        .edges
+        .iter()
+        .enumerate()
+    {
+        let mut nodes = polyline[indices[i] + 1..indices[i + 1]].to_vec();
+        if edge.get_from() != stations[i].0 {
+            nodes.reverse();
+        }
+        let edge = map
+            .get_mut_edge(edge.get_id())
+            .expect("edge of the section exists");
+        edge.set_nodes(nodes);
+        edge.mark_manually_edited();
+    }
+
+    let section_edges = section
+        .edges
+        .iter()
+        .map(Edge::get_id)
+        .collect::<Vec<_>>();
+    let temp_map = map.clone();
+    for id in moved {
+        for edge_id in temp_map
+            .get_station(id)
+            .expect("moved station exists")
+            .get_edges()
+        {
+            if section_edges.contains(edge_id) {
+                continue;
+            }
+            if let Some(edge) = map
+                .get_mut_edge(*edge_id)
+                .filter(|edge| !edge.is_locked())
+            {
+                edge.calculate_nodes(&temp_map);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a map with a chain of stations at the given x positions and
+    /// return the selected section of all of them.
+    fn chain(xs: &[i32]) -> (Map, Vec<Edge>, Vec<Station>) {
+        let mut map = Map::new();
+        let ids = xs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| map.add_named_station(&i, (*x, 0).into()))
+            .collect::<Vec<_>>();
+        for pair in ids.windows(2) {
+            map.get_edge_id_between(pair[0], pair[1]);
+        }
+
+        let edges = map
+            .get_edges()
+            .into_iter()
+            .cloned()
+            .collect();
+        let stations = ids
+            .iter()
+            .filter_map(|id| map.get_station(*id))
+            .cloned()
+            .collect();
+        (map, edges, stations)
+    }
+
+    /// Get the x positions of the given stations on the map.
+    fn xs(map: &Map, stations: &[Station]) -> Vec<i32> {
+        stations
+            .iter()
+            .filter_map(|station| map.get_station(station.get_id()))
+            .map(|station| {
+                station
+                    .get_pos()
+                    .0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_distribute_evenly() {
+        let (mut map, edges, stations) = chain(&[0, 1, 2, 6]);
+        distribute_evenly(&mut map, &edges, &stations).unwrap();
+        assert_eq!(xs(&map, &stations), vec![0, 2, 4, 6]);
+
+        // The nodes of the edges follow the new positions.
+        for edge in map.get_edges() {
+            let from = map
+                .get_station(edge.get_from())
+                .unwrap()
+                .get_pos();
+            assert_eq!(
+                edge.get_nodes(),
+                &[GridNode(from.0 + 1, 0)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_distribute_evenly_locked() {
+        let (mut map, edges, stations) = chain(&[0, 1, 2, 3, 8]);
+        map.get_mut_station(stations[2].get_id())
+            .unwrap()
+            .lock();
+
+        distribute_evenly(&mut map, &edges, &stations).unwrap();
+        assert_eq!(xs(&map, &stations), vec![0, 1, 2, 5, 8]);
+    }
+
+    #[test]
+    fn test_distribute_evenly_errors() {
+        let (mut map, edges, stations) = chain(&[0, 1, 2]);
+        let branch = map.add_named_station(&"branch", (1, 3).into());
+        map.get_edge_id_between(stations[1].get_id(), branch);
+        assert!(distribute_evenly(&mut map, &edges, &stations).is_err());
+
+        // The selection has a gap, so it is not one line section.
+        let (mut map, edges, stations) = chain(&[0, 1, 2, 3]);
+        let edges = edges
+            .into_iter()
+            .filter(|edge| edge.opposite(stations[1].get_id()) != Some(stations[2].get_id()))
+            .collect::<Vec<_>>();
+        assert!(distribute_evenly(&mut map, &edges, &stations).is_err());
+    }
+}
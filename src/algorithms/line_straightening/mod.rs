@@ -1,22 +1,32 @@
 //! This module contains the line straightening algorithm.
 
+use std::collections::HashMap;
+
 use crate::{
+    algorithms::OccupiedNodes,
     models::{
         Edge,
+        GridNode,
         Map,
         Station,
+        StationID,
     },
     utils::{
-        line_sections::to_line_section,
+        line_sections::{
+            to_line_section,
+            LineSection,
+        },
         Result,
     },
     Error,
 };
 
+mod distribute;
 mod fit_edges;
 mod line_creation;
 mod update_map;
 
+pub use distribute::distribute_evenly;
 use fit_edges::{
     attach_stations,
     calculate_cost,
@@ -30,19 +40,64 @@ use update_map::{
 
 use super::AlgorithmSettings;
 
-/// This algorithm will try to find a straight line between the selected
-/// stations. For this, it requires them to be part of one line section.
-pub fn straighten_line(
-    settings: AlgorithmSettings,
-    map: &mut Map,
+/// The maximum amount of straightening candidates that are previewed to the
+/// user to pick from.
+pub const MAX_PREVIEWED_CANDIDATES: usize = 6;
+
+/// A possible straightening of a line section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StraighteningCandidate {
+    /// The new positions of the stations in the line section.
+    stations: HashMap<StationID, GridNode>,
+    /// The nodes the straightened line goes through, including both ends.
+    nodes: Vec<GridNode>,
+    /// The cost of moving the stations to their new positions, as calculated
+    /// by [`calculate_cost`].
+    cost: i32,
+}
+
+impl StraighteningCandidate {
+    /// A getter for the new positions of the stations in the line section.
+    pub fn get_stations(&self) -> &HashMap<StationID, GridNode> {
+        &self.stations
+    }
+
+    /// A getter for the nodes the straightened line goes through.
+    pub fn get_nodes(&self) -> &[GridNode] {
+        &self.nodes
+    }
+
+    /// A getter for the cost of this straightening.
+    pub fn get_cost(&self) -> i32 {
+        self.cost
+    }
+}
+
+/// Get the line section of the selected stations and edges, together with the
+/// occupied nodes of the map without that section.
+fn section_and_occupied(
+    map: &Map,
     selected_edges: &[Edge],
     selected_stations: &[Station],
-) -> Result<()> {
+) -> Result<(LineSection, OccupiedNodes)> {
     let mut occupied = map.get_occupied_nodes();
     let line_section = to_line_section(selected_stations, selected_edges)?;
 
     deoccupy_section(map, &mut occupied, &line_section);
 
+    Ok((line_section, occupied))
+}
+
+/// Find all possible straight lines between the selected stations, ordered
+/// from the lowest to the highest cost. For this, the stations are required
+/// to be part of one line section.
+pub fn straightening_candidates(
+    map: &Map,
+    selected_edges: &[Edge],
+    selected_stations: &[Station],
+) -> Result<Vec<StraighteningCandidate>> {
+    let (line_section, occupied) = section_and_occupied(map, selected_edges, selected_stations)?;
+
     let start_station = map
         .get_station(line_section.ends[0])
         .expect("start station not found");
@@ -58,7 +113,7 @@ pub fn straighten_line(
         &line_section.edges,
     )?;
 
-    let result = edge_candidates
+    let mut candidates = edge_candidates
         .into_iter()
         .filter_map(|(start, mut nodes, end)| {
             let Ok(mut attached_stations) = attach_stations(map, &line_section, &nodes) else {
@@ -72,25 +127,43 @@ pub fn straighten_line(
 
             let cost = calculate_cost(map, &line_section, &attached_stations);
 
-            Some((attached_stations, nodes, cost))
+            Some(StraighteningCandidate {
+                stations: attached_stations,
+                nodes,
+                cost,
+            })
         })
-        .min_by_key(|(_, _, cost)| *cost)
-        .map(|(section, nodes, _)| (section, nodes))
-        .ok_or(Error::other(
-            "No straight line possible",
-        ))?;
+        .collect::<Vec<_>>();
+
+    // A stable sort, so the first of equally costly candidates stays first.
+    candidates.sort_by_key(StraighteningCandidate::get_cost);
+
+    Ok(candidates)
+}
+
+/// Straighten the selected stations onto the given candidate, which should
+/// have been found by [`straightening_candidates`] for the same selection.
+pub fn apply_straightening(
+    settings: AlgorithmSettings,
+    map: &mut Map,
+    selected_edges: &[Edge],
+    selected_stations: &[Station],
+    candidate: &StraighteningCandidate,
+) -> Result<()> {
+    let (line_section, mut occupied) =
+        section_and_occupied(map, selected_edges, selected_stations)?;
 
     let mut temp_map = map.clone();
     update_map(
         &mut temp_map,
-        &result.0,
+        &candidate.stations,
         &mut occupied,
         &line_section.edges,
     )?;
     recalculate_adjacent_edges(
         settings,
         &mut temp_map,
-        &result.0,
+        &candidate.stations,
         occupied,
         &line_section.edges,
     )?;
@@ -98,3 +171,84 @@ pub fn straighten_line(
 
     Ok(())
 }
+
+/// This algorithm will try to find a straight line between the selected
+/// stations. For this, it requires them to be part of one line section. Of all
+/// possible straight lines, the one with the lowest cost is picked.
+pub fn straighten_line(
+    settings: AlgorithmSettings,
+    map: &mut Map,
+    selected_edges: &[Edge],
+    selected_stations: &[Station],
+) -> Result<()> {
+    let candidate = straightening_candidates(map, selected_edges, selected_stations)?
+        .into_iter()
+        .next()
+        .ok_or(Error::other(
+            "No straight line possible",
+        ))?;
+
+    apply_straightening(
+        settings,
+        map,
+        selected_edges,
+        selected_stations,
+        &candidate,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_straightening_candidates() {
+        let mut map = Map::new();
+        let ids: Vec<StationID> = vec![1.into(), 2.into(), 3.into(), 4.into()];
+        for (id, pos) in ids
+            .iter()
+            .zip([(0, 0), (2, 0), (4, 1), (6, 1)])
+        {
+            map.add_station(Station::new(pos.into(), Some(*id)));
+        }
+        for pair in ids.windows(2) {
+            map.get_edge_id_between(pair[0], pair[1]);
+        }
+
+        let stations = ids
+            .iter()
+            .filter_map(|id| map.get_station(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let edges = map
+            .get_edges()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let candidates = straightening_candidates(&map, &edges, &stations).unwrap();
+        // Mostly going right, so either all at y 0 or all at y 1.
+        assert_eq!(candidates.len(), 2);
+        assert!(candidates
+            .windows(2)
+            .all(|pair| pair[0].get_cost() <= pair[1].get_cost()));
+
+        // Straightening without picking a candidate uses the cheapest one.
+        let best = candidates[0].clone();
+        straighten_line(
+            AlgorithmSettings::default(),
+            &mut map,
+            &edges,
+            &stations,
+        )
+        .unwrap();
+        for (id, pos) in best.get_stations() {
+            assert_eq!(
+                map.get_station(*id)
+                    .unwrap()
+                    .get_pos(),
+                *pos
+            );
+        }
+    }
+}
@@ -58,9 +58,11 @@ pub fn update_map(
             occupied.insert(*node, OccupiedNode::Edge(edge.get_id()));
         }
 
-        map.get_mut_edge(edge.get_id())
-            .expect("edge not found")
-            .set_nodes(nodes);
+        let edge = map
+            .get_mut_edge(edge.get_id())
+            .expect("edge not found");
+        edge.set_nodes(nodes);
+        edge.mark_manually_edited();
     }
 
     Ok(())
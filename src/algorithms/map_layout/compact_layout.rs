@@ -0,0 +1,252 @@
+//! Contains the post-processing step that removes empty rows and columns from
+//! the map.
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use crate::models::{
+    GridNode,
+    Map,
+    Station,
+};
+
+/// Get all straight segments of the map, being the consecutive nodes of every
+/// edge including the stations at its ends.
+fn map_segments(map: &Map) -> Vec<(GridNode, GridNode)> {
+    map.get_edges()
+        .into_iter()
+        .flat_map(|edge| {
+            let from = map
+                .get_station(edge.get_from())
+                .map(Station::get_pos);
+            let to = map
+                .get_station(edge.get_to())
+                .map(Station::get_pos);
+
+            from.into_iter()
+                .chain(
+                    edge.get_nodes()
+                        .iter()
+                        .copied(),
+                )
+                .chain(to)
+                .tuple_windows()
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Find the values between the given minimum and maximum that are not used by
+/// any of the given occupied values and are not crossed by any of the given
+/// segments, so they can be removed without changing the angle of a segment.
+fn find_empty(
+    (min, max): (i32, i32),
+    occupied: &HashSet<i32>,
+    segments: &[(i32, i32)],
+) -> Vec<i32> {
+    (min..=max)
+        .filter(|value| !occupied.contains(value))
+        .filter(|value| {
+            !segments
+                .iter()
+                .any(|(a, b)| a.min(b) < value && value < a.max(b))
+        })
+        .collect()
+}
+
+/// Get the value after removing all the given sorted empty values before it.
+fn shift(value: i32, empty: &[i32]) -> i32 {
+    value - empty.partition_point(|e| *e < value) as i32
+}
+
+/// Collapses all rows and columns within the bounds of the map that are not
+/// occupied by any station or edge, moving everything after them to close the
+/// gap. Rows and columns crossed by a straight segment of an edge are kept, so
+/// all angles stay the same and octilinear edges stay octilinear. Locked
+/// stations and edges are never moved, so only rows and columns after them are
+/// removed.
+pub fn compact_layout(map: &mut Map) {
+    let Some((mut min, max)) = map.bounds() else {
+        return;
+    };
+
+    for node in map
+        .get_occupied_by_locks()
+        .keys()
+    {
+        min = GridNode::from((
+            min.0
+                .max(node.0),
+            min.1
+                .max(node.1),
+        ));
+    }
+
+    let occupied = map.get_occupied_nodes();
+    let segments = map_segments(map);
+
+    let empty_columns = find_empty(
+        (min.0, max.0),
+        &occupied
+            .keys()
+            .map(|node| node.0)
+            .collect(),
+        &segments
+            .iter()
+            .map(|(a, b)| (a.0, b.0))
+            .collect::<Vec<_>>(),
+    );
+    let empty_rows = find_empty(
+        (min.1, max.1),
+        &occupied
+            .keys()
+            .map(|node| node.1)
+            .collect(),
+        &segments
+            .iter()
+            .map(|(a, b)| (a.1, b.1))
+            .collect::<Vec<_>>(),
+    );
+
+    if empty_columns.is_empty() && empty_rows.is_empty() {
+        return;
+    }
+
+    let move_node = |node: GridNode| {
+        GridNode::from((
+            shift(node.0, &empty_columns),
+            shift(node.1, &empty_rows),
+        ))
+    };
+
+    for station in map.get_mut_stations() {
+        station.set_pos(move_node(station.get_pos()));
+    }
+    for edge in map.get_mut_edges() {
+        let nodes = edge
+            .get_nodes()
+            .iter()
+            .copied()
+            .map(move_node)
+            .collect();
+        edge.set_nodes(nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        algorithms::octilinear_direction,
+        models::Edge,
+    };
+
+    #[test]
+    fn test_compact_layout() {
+        let mut map = Map::new();
+        for (id, pos) in [
+            (1, (0, 0)),
+            (2, (8, 0)),
+            (3, (10, 2)),
+            (4, (10, 9)),
+        ] {
+            map.add_station(Station::new(
+                pos.into(),
+                Some(id.into()),
+            ));
+        }
+
+        // A horizontal edge with a gap in its routed nodes, which may not be
+        // removed from under the long segment.
+        let mut horizontal = Edge::new(1.into(), 2.into(), Some(1.into()));
+        horizontal.set_nodes(vec![(1, 0).into(), (5, 0).into()]);
+        let mut diagonal = Edge::new(2.into(), 3.into(), Some(2.into()));
+        diagonal.set_nodes(vec![(9, 1).into()]);
+        // An unrouted vertical edge spanning the empty rows.
+        let vertical = Edge::new(3.into(), 4.into(), Some(3.into()));
+        map.add_edge(horizontal);
+        map.add_edge(diagonal);
+        map.add_edge(vertical);
+
+        // An isolated station in the far corner, leaving empty rows between it
+        // and the rest.
+        map.add_station(Station::new(
+            (0, 20).into(),
+            Some(5.into()),
+        ));
+
+        let old_segments = map_segments(&map).len();
+        compact_layout(&mut map);
+
+        // Rows 10 to 19 are empty and crossed by nothing, the columns are all
+        // used or crossed.
+        assert_eq!(
+            map.bounds(),
+            Some(((0, 0).into(), (10, 10).into()))
+        );
+        assert_eq!(
+            map.get_station(5.into())
+                .unwrap()
+                .get_pos(),
+            (0, 10)
+        );
+        assert_eq!(
+            map.get_station(4.into())
+                .unwrap()
+                .get_pos(),
+            (10, 9)
+        );
+
+        assert_eq!(map_segments(&map).len(), old_segments);
+        assert!(
+            map_segments(&map)
+                .into_iter()
+                .all(|(a, b)| octilinear_direction(a, b).is_some())
+        );
+    }
+
+    #[test]
+    fn test_compact_layout_locked() {
+        let mut map = Map::new();
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(1.into()),
+        ));
+        map.add_station(Station::new(
+            (0, 5).into(),
+            Some(2.into()),
+        ));
+        map.add_station(Station::new(
+            (0, 9).into(),
+            Some(3.into()),
+        ));
+        map.get_mut_station(2.into())
+            .unwrap()
+            .lock();
+
+        compact_layout(&mut map);
+
+        // Only the rows after the locked station are removed.
+        assert_eq!(
+            map.get_station(2.into())
+                .unwrap()
+                .get_pos(),
+            (0, 5)
+        );
+        assert_eq!(
+            map.get_station(3.into())
+                .unwrap()
+                .get_pos(),
+            (0, 6)
+        );
+    }
+
+    #[test]
+    fn test_shift() {
+        let empty = [2, 5, 6];
+        assert_eq!(shift(1, &empty), 1);
+        assert_eq!(shift(3, &empty), 2);
+        assert_eq!(shift(7, &empty), 4);
+    }
+}
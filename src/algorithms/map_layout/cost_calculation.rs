@@ -3,9 +3,13 @@
 
 use core::f64;
 
-use super::AlgorithmSettings;
+use super::{
+    AlgorithmSettings,
+    GridMode,
+};
 use crate::{
     algorithms::{
+        calc_direction::node_direction,
         diagonal_occupied,
         log_print,
         node_outside_grid,
@@ -22,7 +26,7 @@ use crate::{
         Station,
     },
     utils::{
-        calculate_angle,
+        calculate_grid_angle,
         Result,
     },
     Error,
@@ -35,6 +39,7 @@ type SortedStationEdgeList = Vec<(Edge, f64)>;
 /// Get the edges connected to the given station, sorted by the angle with which
 /// they are connected to the station, as seen from the given incoming edge.
 fn edges_by_angle(
+    mode: GridMode,
     map: &Map,
     station: &Station,
     incoming_station_node: GridNode,
@@ -45,7 +50,7 @@ fn edges_by_angle(
 )> {
     let neighbor_nodes = station
         .get_pos()
-        .get_neighbors();
+        .get_neighbors_for(mode);
     let mut left_wards = Vec::new();
 
     for edge_id in station.get_edges() {
@@ -64,7 +69,8 @@ fn edges_by_angle(
                 if neighbor_nodes.contains(&edge_node) {
                     left_wards.push((
                         edge.clone(),
-                        calculate_angle(
+                        calculate_grid_angle(
+                            mode,
                             incoming_station_node,
                             station.get_pos(),
                             edge_node,
@@ -85,7 +91,8 @@ fn edges_by_angle(
 
             left_wards.push((
                 edge.clone(),
-                calculate_angle(
+                calculate_grid_angle(
+                    mode,
                     incoming_station_node,
                     station.get_pos(),
                     opposite_station.get_pos(),
@@ -127,6 +134,7 @@ fn station_approach_available(
     // with which they are connected to it. 1 rightwards and the other
     // leftwards.
     let (left_wards, right_wards) = edges_by_angle(
+        settings.grid_mode,
         map,
         station,
         incoming_station.get_pos(),
@@ -135,18 +143,22 @@ fn station_approach_available(
 
     let mut cost = 0;
 
-    let possible_angle = move |angle, cost| {
-        match angle {
-            0.0..=45.0 => cost < 1,
-            45.0..=90.0 => cost < 2,
-            90.0..=135.0 => cost < 3,
-            135.0..=180.0 => cost < 4,
-            180.0..=225.0 => cost < 5,
-            225.0..=270.0 => cost < 6,
-            270.0..=315.0 => cost < 7,
-            315.0..=360.0 => cost < 8,
-            _ => panic!("found impossible angle of {angle}"),
-        }
+    // The amount of edges that fit between the incoming edge and an edge at the
+    // given angle, being one less than the amount of grid directions up to and
+    // including that angle.
+    let possible_angle = move |angle: f64, cost| {
+        assert!(
+            (0.0..=360.0).contains(&angle),
+            "found impossible angle of {angle}"
+        );
+
+        let directions = (angle
+            / settings
+                .grid_mode
+                .angle_step())
+        .ceil()
+        .max(1.0) as usize;
+        cost < directions
     };
 
     // For both the right and leftwards edges, we check if the angle between the
@@ -209,7 +221,7 @@ fn station_approach_available(
 
 /// Match the given angle to the cost of a bend of that angle
 #[inline]
-fn match_angle_cost(angle: f64) -> Result<f64> {
+pub fn match_angle_cost(angle: f64) -> Result<f64> {
     Ok(match angle {
         360.0 => f64::INFINITY,
         315.0 => 5.0,
@@ -228,29 +240,74 @@ fn match_angle_cost(angle: f64) -> Result<f64> {
     })
 }
 
+/// Match the given angle to the cost of a bend of that angle on a grid of the
+/// given mode.
+#[inline]
+pub fn match_grid_angle_cost(mode: GridMode, angle: f64) -> Result<f64> {
+    if mode == GridMode::Octilinear {
+        return match_angle_cost(angle);
+    }
+
+    Ok(match angle {
+        360.0 => f64::INFINITY,
+        300.0 => 5.0,
+        240.0 => 1.0,
+        180.0 => 0.0,
+        120.0 => 1.0,
+        60.0 => 5.0,
+        0.0 => f64::INFINITY,
+        _ => {
+            Err(Error::other(format!(
+                "found impossible angle of {angle}"
+            )))?
+        },
+    })
+}
+
 /// Calculate the cost of the angle between three nodes.
 /// The second point is assumed to be the middle node where the angle is
 /// located.
-fn calc_angle_cost(first: GridNode, second: GridNode, third: GridNode, round: bool) -> Result<f64> {
+fn calc_angle_cost(
+    mode: GridMode,
+    first: GridNode,
+    second: GridNode,
+    third: GridNode,
+    round: bool,
+) -> Result<f64> {
+    let angle = calculate_grid_angle(mode, first, second, third);
     let angle = if round {
-        (calculate_angle(first, second, third) / 45.0).floor() * 45.0
+        (angle / mode.angle_step()).floor() * mode.angle_step()
     } else {
-        calculate_angle(first, second, third)
+        angle
     };
 
-    match_angle_cost(angle).map_err(|_| {
+    match_grid_angle_cost(mode, angle).map_err(|_| {
         Error::other(format!(
             "found invalid angle of {angle} between {first}, {second}, {third}",
         ))
     })
 }
 
+/// Check if the edge may leave the station at the given station node through
+/// the given neighboring node, which is not the case if the user required the
+/// edge to leave the station in another direction.
+pub fn direction_allowed(
+    edge: &Edge,
+    station: &Station,
+    station_node: GridNode,
+    node: GridNode,
+) -> bool {
+    edge.get_required_direction(station.get_id())
+        .is_none_or(|direction| node_direction(station_node, node) == direction)
+}
+
 /// Calculate the cost of the node attached to the given station on the path
 /// going away from the station.
 ///
 /// note: the angle cost is halved here to make it have a preference, but not
 /// have it force a double bend later on to compensate.
 fn calc_station_exit_cost(
+    mode: GridMode,
     map: &Map,
     current_edge: &Edge,
     station: &Station,
@@ -258,13 +315,39 @@ fn calc_station_exit_cost(
     station_node: GridNode,
     target_node: GridNode,
 ) -> Result<f64> {
+    // A direction required by the user replaces any preference for the exit
+    // angle.
+    if current_edge
+        .get_required_direction(station.get_id())
+        .is_some()
+    {
+        return Ok(
+            if direction_allowed(
+                current_edge,
+                station,
+                station_node,
+                node,
+            ) {
+                0.0
+            } else {
+                f64::INFINITY
+            },
+        );
+    }
+
     if !station.is_settled()
         || station
             .get_edges()
             .len()
             <= 1
     {
-        return calc_angle_cost(station_node, node, target_node, true);
+        return calc_angle_cost(
+            mode,
+            station_node,
+            node,
+            target_node,
+            true,
+        );
     }
 
     let mut biggest_overlap = None;
@@ -298,14 +381,14 @@ fn calc_station_exit_cost(
     if let Some(mut opposite_edge) = biggest_overlap.cloned() {
         let neighbor_nodes = station
             .get_pos()
-            .get_neighbors();
+            .get_neighbors_for(mode);
 
         // If the station has been settled and moved, but the opposite edge might not
         // have been settled, then there is likely a gap in the edge to the station and
         // thus we need to recalculate the nodes in the edge to get a correct bordering
         // edge.
         if !opposite_edge.is_settled() && station.get_pos() != station.get_original_pos() {
-            opposite_edge.calculate_nodes(map);
+            opposite_edge.calculate_nodes_for(map, mode);
         }
 
         // If the ends of the opposite edge are in the neighbors of the station, we
@@ -314,6 +397,7 @@ fn calc_station_exit_cost(
         for edge_node in opposite_edge.get_edge_ends() {
             if neighbor_nodes.contains(&edge_node) {
                 return calc_angle_cost(
+                    mode,
                     edge_node,
                     station.get_pos(),
                     node,
@@ -329,6 +413,7 @@ fn calc_station_exit_cost(
             if let Some(opp_station) = map.get_station(opp_station_id) {
                 // If the opposite edge is not
                 return calc_angle_cost(
+                    mode,
                     opp_station.get_pos(),
                     station.get_pos(),
                     node,
@@ -343,10 +428,23 @@ fn calc_station_exit_cost(
     Ok(0.0)
 }
 
+/// Calculate the extra cost of moving diagonally from one node to the next, as
+/// set by the diagonal penalty in the settings. Only moves on an octilinear
+/// grid can be diagonal, a hexagonal grid has no horizontal and vertical moves
+/// to prefer.
+fn calc_diagonal_cost(settings: AlgorithmSettings, from: GridNode, to: GridNode) -> f64 {
+    if settings.grid_mode == GridMode::Octilinear && from.0 != to.0 && from.1 != to.1 {
+        settings.diagonal_penalty
+    } else {
+        0.0
+    }
+}
+
 /// Calculate the cost of the node on the path between two stations.
-/// The cost is dependent on the angle between the previous two nodes and if the
-/// node is exiting or approaching a station. It also validates if the node can
-/// be used for a path, and else giving a cost of infinity.
+/// The cost is dependent on the angle between the previous two nodes, if the
+/// move to the node is diagonal and if the node is exiting or approaching a
+/// station. It also validates if the node can be used for a path, and else
+/// giving a cost of infinity.
 /// This is the Calculate Node Cost function from the paper.
 pub fn calc_node_cost(
     settings: AlgorithmSettings,
@@ -366,7 +464,7 @@ pub fn calc_node_cost(
     // stations. We don't get about the from station cause we do not apply an extra
     // penalty there.
     let mut adj_cost = 0.0;
-    for neighbor_node in node.get_neighbors() {
+    for neighbor_node in node.get_neighbors_for(settings.grid_mode) {
         if let Some(&OccupiedNode::Station(neighbor_station)) = occupied.get(&neighbor_node) {
             if neighbor_station != to_station.get_id() {
                 adj_cost += 1.0;
@@ -406,6 +504,7 @@ pub fn calc_node_cost(
         }
 
         return calc_station_exit_cost(
+            settings.grid_mode,
             map,
             edge,
             from_station,
@@ -413,6 +512,7 @@ pub fn calc_node_cost(
             previous[0],
             to_station.get_pos(),
         ) // cost of exiting station
+        .map(|c| c + calc_diagonal_cost(settings, previous[0], node)) // cost of a diagonal move
         .map(|c| c + settings.move_cost); // standard cost of a move
     }
 
@@ -420,14 +520,22 @@ pub fn calc_node_cost(
         return Ok(f64::INFINITY);
     }
 
-    calc_angle_cost(previous[0], previous[1], node, false) // cost of angle between previous nodes
-        .map(|c| c + adj_cost) // add the cost of adjacent stations
-        .map(|c| c + settings.move_cost) // standard cost of a move
+    calc_angle_cost(
+        settings.grid_mode,
+        previous[0],
+        previous[1],
+        node,
+        false,
+    ) // cost of angle between previous nodes
+    .map(|c| c + adj_cost) // add the cost of adjacent stations
+    .map(|c| c + calc_diagonal_cost(settings, previous[1], node)) // cost of a diagonal move
+    .map(|c| c + settings.move_cost) // standard cost of a move
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::algorithms::EdgeDirection;
 
     #[test]
     fn test_station_approach_available() {
@@ -631,67 +739,133 @@ mod tests {
         let first_45 = GridNode::from((1, 0));
         let second_45 = GridNode::from((1, 1));
         let third_45 = GridNode::from((2, 0));
-        let result_45 = calc_angle_cost(first_45, second_45, third_45, false);
+        let result_45 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_45,
+            second_45,
+            third_45,
+            false,
+        );
         assert_eq!(result_45, Ok(5.0));
 
         let first_90 = GridNode::from((0, 0));
         let second_90 = GridNode::from((1, 1));
         let third_90 = GridNode::from((2, 0));
-        let result_90 = calc_angle_cost(first_90, second_90, third_90, false);
+        let result_90 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_90,
+            second_90,
+            third_90,
+            false,
+        );
         assert_eq!(result_90, Ok(2.5));
 
         let first_135 = GridNode::from((0, 1));
         let second_135 = GridNode::from((1, 1));
         let third_135 = GridNode::from((2, 0));
-        let result_135 = calc_angle_cost(first_135, second_135, third_135, false);
+        let result_135 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_135,
+            second_135,
+            third_135,
+            false,
+        );
         assert_eq!(result_135, Ok(0.5));
 
         let first_180 = GridNode::from((0, 2));
         let second_180 = GridNode::from((1, 1));
         let third_180 = GridNode::from((2, 0));
-        let result_180 = calc_angle_cost(first_180, second_180, third_180, false);
+        let result_180 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_180,
+            second_180,
+            third_180,
+            false,
+        );
         assert_eq!(result_180, Ok(0.0));
 
         let first_135 = GridNode::from((1, 2));
         let second_135 = GridNode::from((1, 1));
         let third_135 = GridNode::from((2, 0));
-        let result_135 = calc_angle_cost(first_135, second_135, third_135, false);
+        let result_135 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_135,
+            second_135,
+            third_135,
+            false,
+        );
         assert_eq!(result_135, Ok(0.5));
 
         let first_90 = GridNode::from((2, 2));
         let second_90 = GridNode::from((1, 1));
         let third_90 = GridNode::from((2, 0));
-        let result_90 = calc_angle_cost(first_90, second_90, third_90, false);
+        let result_90 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_90,
+            second_90,
+            third_90,
+            false,
+        );
         assert_eq!(result_90, Ok(2.5));
 
         let first_45 = GridNode::from((2, 1));
         let second_45 = GridNode::from((1, 1));
         let third_45 = GridNode::from((2, 0));
-        let result_45 = calc_angle_cost(first_45, second_45, third_45, false);
+        let result_45 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_45,
+            second_45,
+            third_45,
+            false,
+        );
         assert_eq!(result_45, Ok(5.0));
 
         let first_180 = GridNode::from((2, 0));
         let second_180 = GridNode::from((1, 1));
         let third_180 = GridNode::from((0, 2));
-        let result_180 = calc_angle_cost(first_180, second_180, third_180, false);
+        let result_180 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_180,
+            second_180,
+            third_180,
+            false,
+        );
         assert_eq!(result_180, Ok(0.0));
 
         let first_135 = GridNode::from((2, 0));
         let second_135 = GridNode::from((1, 1));
         let third_135 = GridNode::from((1, 2));
-        let result_135 = calc_angle_cost(first_135, second_135, third_135, false);
+        let result_135 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_135,
+            second_135,
+            third_135,
+            false,
+        );
         assert_eq!(result_135, Ok(0.5));
 
         let first_90 = GridNode::from((2, 0));
         let second_90 = GridNode::from((1, 1));
         let third_90 = GridNode::from((2, 2));
-        let result_90 = calc_angle_cost(first_90, second_90, third_90, false);
+        let result_90 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_90,
+            second_90,
+            third_90,
+            false,
+        );
         assert_eq!(result_90, Ok(2.5));
 
         let first_45 = GridNode::from((2, 0));
         let second_45 = GridNode::from((1, 1));
         let third_45 = GridNode::from((2, 1));
-        let result_45 = calc_angle_cost(first_45, second_45, third_45, false);
+        let result_45 = calc_angle_cost(
+            GridMode::Octilinear,
+            first_45,
+            second_45,
+            third_45,
+            false,
+        );
         assert_eq!(result_45, Ok(5.0));
     }
 
@@ -735,6 +909,7 @@ mod tests {
         assert_eq!(
             0.0,
             calc_station_exit_cost(
+                GridMode::Octilinear,
                 &map,
                 &opposite_edge,
                 &unsettled_station,
@@ -748,6 +923,7 @@ mod tests {
         assert_eq!(
             2.5,
             calc_station_exit_cost(
+                GridMode::Octilinear,
                 &map,
                 &opposite_edge,
                 &unsettled_station,
@@ -762,6 +938,7 @@ mod tests {
         assert_eq!(
             0.0,
             calc_station_exit_cost(
+                GridMode::Octilinear,
                 &map,
                 &opposite_settled_edge,
                 map.get_station(settled_station.get_id())
@@ -777,6 +954,7 @@ mod tests {
         assert_eq!(
             2.5,
             calc_station_exit_cost(
+                GridMode::Octilinear,
                 &map,
                 &opposite_settled_edge,
                 map.get_station(settled_station.get_id())
@@ -788,4 +966,73 @@ mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn test_required_direction() {
+        let mut map = Map::new();
+        let terminus = Station::new(GridNode::from((5, 5)), None);
+        let south = Station::new(GridNode::from((5, 10)), None);
+        let mut edge = Edge::new(terminus.get_id(), south.get_id(), None);
+        edge.set_required_direction(
+            terminus.get_id(),
+            Some(EdgeDirection::Up),
+        );
+
+        map.add_station(terminus.clone());
+        map.add_station(south.clone());
+        map.add_edge(edge.clone());
+
+        let exit_cost = |node: GridNode| {
+            calc_station_exit_cost(
+                GridMode::Octilinear,
+                &map,
+                &edge,
+                &terminus,
+                node,
+                terminus.get_pos(),
+                south.get_pos(),
+            )
+            .unwrap()
+        };
+
+        // Leaving north is allowed, even though it is away from the target.
+        assert!(exit_cost((5, 4).into()).is_finite());
+        for node in terminus
+            .get_pos()
+            .get_neighbors()
+            .into_iter()
+            .filter(|n| *n != (5, 4))
+        {
+            assert_eq!(exit_cost(node), f64::INFINITY);
+        }
+    }
+
+    #[test]
+    fn test_calc_hexagonal_angle_cost() {
+        let mode = GridMode::Hexagonal;
+        let first = GridNode::from((-1, 0));
+        let second = GridNode::from((0, 0));
+
+        let expected = [
+            ((1, 0), 0.0),
+            ((0, 1), 1.0),
+            ((1, -1), 1.0),
+            ((-1, 1), 5.0),
+            ((0, -1), 5.0),
+        ];
+        for (third, cost) in expected {
+            assert_eq!(
+                calc_angle_cost(mode, first, second, third.into(), false).unwrap(),
+                cost,
+                "angle cost to {third:?}"
+            );
+        }
+
+        assert_eq!(
+            match_grid_angle_cost(mode, 0.0).unwrap(),
+            f64::INFINITY
+        );
+        assert!(match_grid_angle_cost(mode, 45.0).is_err());
+        assert!(match_grid_angle_cost(GridMode::Octilinear, 60.0).is_err());
+    }
 }
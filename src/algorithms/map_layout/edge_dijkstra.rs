@@ -17,7 +17,10 @@ use ordered_float::NotNan;
 use priority_queue::PriorityQueue;
 
 use super::{
-    cost_calculation::calc_node_cost,
+    cost_calculation::{
+        calc_node_cost,
+        direction_allowed,
+    },
     AlgorithmSettings,
 };
 use crate::{
@@ -87,6 +90,7 @@ impl Hash for QueueItem {
 
 /// A Dijkstra implementation that finds the shortest path between two start and
 /// end node sets. This is the Edge Dijkstra algorithm in the paper.
+#[allow(clippy::too_many_lines)] // mostly due to the checks on every neighbor
 pub fn edge_dijkstra(
     settings: AlgorithmSettings,
     map: &Map,
@@ -150,9 +154,14 @@ pub fn edge_dijkstra(
 
         for neighbor in current
             .node
-            .get_neighbors()
+            .get_neighbors_for(settings.grid_mode)
         {
-            if visited.contains(&neighbor) {
+            // Arriving at a possible location of the to station from the current node
+            // is leaving that station towards it, which the user may not allow.
+            if visited.contains(&neighbor)
+                || (to_nodes.contains_key(&neighbor)
+                    && !direction_allowed(edge, to_station, neighbor, current.node))
+            {
                 continue;
             }
 
@@ -229,7 +238,10 @@ pub fn edge_dijkstra(
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use super::*;
+    use crate::algorithms::EdgeDirection;
 
     // More of a sanity check for the algorithm
     #[test]
@@ -295,4 +307,103 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_edge_dijkstra_diagonal_penalty() {
+        let mut map = Map::new();
+        let occupied = HashMap::new();
+        let from_station = Station::new(GridNode::from((0, 0)), None);
+        let to_station = Station::new(GridNode::from((8, 4)), None);
+        let edge = Edge::new(
+            from_station.get_id(),
+            to_station.get_id(),
+            None,
+        );
+
+        map.add_station(from_station.clone());
+        map.add_station(to_station.clone());
+        map.add_edge(edge.clone());
+
+        let edge = map
+            .get_edge(edge.get_id())
+            .unwrap()
+            .clone();
+        let from_station = map
+            .get_station(from_station.get_id())
+            .unwrap()
+            .clone();
+        let to_station = map
+            .get_station(to_station.get_id())
+            .unwrap()
+            .clone();
+
+        let diagonal_moves = |diagonal_penalty: f64| {
+            let settings = AlgorithmSettings {
+                diagonal_penalty,
+                ..AlgorithmSettings::default()
+            };
+            let (start, path, end, _) = edge_dijkstra(
+                settings,
+                &map,
+                &edge,
+                &[(from_station.get_pos(), 0.0)],
+                &from_station,
+                &[(to_station.get_pos(), 0.0)],
+                &to_station,
+                &occupied,
+            )
+            .unwrap();
+
+            std::iter::once(start)
+                .chain(path)
+                .chain(std::iter::once(end))
+                .tuple_windows()
+                .filter(|(a, b)| a.0 != b.0 && a.1 != b.1)
+                .count()
+        };
+
+        assert_eq!(diagonal_moves(0.0), 4);
+        assert_eq!(diagonal_moves(100.0), 0);
+    }
+
+    #[test]
+    fn test_edge_dijkstra_required_direction() {
+        let mut map = Map::new();
+        let occupied = HashMap::new();
+        let from_station = Station::new(GridNode::from((5, 10)), None);
+        let to_station = Station::new(GridNode::from((5, 5)), None);
+        let mut edge = Edge::new(
+            from_station.get_id(),
+            to_station.get_id(),
+            None,
+        );
+        edge.set_required_direction(
+            to_station.get_id(),
+            Some(EdgeDirection::Up),
+        );
+
+        map.add_station(from_station.clone());
+        map.add_station(to_station.clone());
+        map.add_edge(edge.clone());
+
+        // The to station is not settled yet and may end up on any of these nodes, all
+        // of which have to be approached from the north.
+        let to_nodes = [(5, 5), (5, 6), (6, 5)].map(|node| (GridNode::from(node), 0.0));
+        let (_, path, end, _) = edge_dijkstra(
+            AlgorithmSettings::default(),
+            &map,
+            &edge,
+            &[(from_station.get_pos(), 0.0)],
+            &from_station,
+            &to_nodes,
+            &to_station,
+            &occupied,
+        )
+        .unwrap();
+
+        let before_end = *path
+            .last()
+            .unwrap();
+        assert_eq!(GridNode(end.0, end.1 - 1), before_end);
+    }
 }
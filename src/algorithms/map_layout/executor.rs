@@ -23,6 +23,7 @@ use super::{
     AlgorithmSettings,
 };
 use crate::{
+    algorithms::OccupiedNodes,
     models::Map,
     utils::{
         IDData,
@@ -43,6 +44,9 @@ pub struct AlgorithmResponse {
     pub id_manager_data: IDData,
     /// If an error occurred during the algorithm, this contains it.
     pub error: Option<Error>,
+    /// The nodes occupied by the stations and edges of the map once the
+    /// algorithm has finished, this is empty for midway results.
+    pub occupied: Option<OccupiedNodes>,
 }
 
 /// The inner state of the executor.
@@ -88,7 +92,7 @@ impl AlgorithmExecutor {
         let closure_executor = executor.clone();
         wasm_bindgen_futures::spawn_local(async move {
             let recalc_executor = closure_executor.clone();
-            let mut map = map.clone();
+            let mut map = map;
 
             let res = recalculate_map(
                 settings,
@@ -100,7 +104,7 @@ impl AlgorithmExecutor {
                             let recalc_executor = recalc_executor.clone();
                             async move {
                                 recalc_executor
-                                    .update_last_res(map, id_manager_data, true)
+                                    .update_last_res(map, id_manager_data, true, None)
                                     .await;
                                 recalc_executor
                                     .wake()
@@ -116,7 +120,14 @@ impl AlgorithmExecutor {
             .await;
 
             closure_executor
-                .update_last_res(map, IDManager::to_data(), res.is_ok())
+                .update_last_res(
+                    map,
+                    IDManager::to_data(),
+                    res.is_ok(),
+                    res.as_ref()
+                        .ok()
+                        .cloned(),
+                )
                 .await;
             closure_executor
                 .set_error(res.err())
@@ -133,12 +144,19 @@ impl AlgorithmExecutor {
     }
 
     /// Update the last result of the algorithm.
-    async fn update_last_res(&self, map: Map, id_manager_data: IDData, success: bool) {
+    async fn update_last_res(
+        &self,
+        map: Map,
+        id_manager_data: IDData,
+        success: bool,
+        occupied: Option<OccupiedNodes>,
+    ) {
         let res = AlgorithmResponse {
             success,
             map,
             id_manager_data,
             error: None,
+            occupied,
         };
 
         self.inner
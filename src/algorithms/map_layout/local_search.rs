@@ -11,6 +11,7 @@ use ordered_float::NotNan;
 use super::{
     edge_dijkstra::edge_dijkstra,
     recalculate_map::Updater,
+    route_edges::too_close_to_other_station,
     AlgorithmSettings,
 };
 use crate::{
@@ -189,7 +190,7 @@ pub async fn local_search(
 ) {
     let mut last_version = HashMap::<StationID, Station>::new();
     let mut all_stations = map
-        .get_stations()
+        .get_stations_sorted()
         .into_iter()
         .cloned()
         .collect::<VecDeque<_>>();
@@ -210,7 +211,7 @@ pub async fn local_search(
 
         let mut neighborhood = station
             .get_pos()
-            .get_neighbors();
+            .get_neighbors_for(settings.grid_mode);
 
         neighborhood.sort_by(|a, b| {
             total_distance(map, *a, &station).cmp(&total_distance(map, *b, &station))
@@ -222,6 +223,9 @@ pub async fn local_search(
         {
             neighborhood.insert(0, station.get_original_pos());
         }
+        // Stations may not be moved closer to others than the minimum spacing,
+        // the same as when picking the nodes to route an edge to.
+        neighborhood.retain(|node| !too_close_to_other_station(map, settings, &station, *node));
 
         let mut best = None;
 
@@ -6,7 +6,9 @@ use serde::{
 };
 
 use super::LogType;
+use crate::models::GridNode;
 
+mod compact_layout;
 mod cost_calculation;
 mod edge_dijkstra;
 mod executor;
@@ -14,8 +16,15 @@ mod local_search;
 mod order_edges;
 mod recalculate_map;
 mod route_edges;
+mod routing_failure;
 mod station_contraction;
+mod station_exits;
 
+pub use compact_layout::compact_layout;
+pub use cost_calculation::{
+    match_angle_cost,
+    match_grid_angle_cost,
+};
 pub(super) use edge_dijkstra::edge_dijkstra;
 pub use executor::{
     AlgorithmExecutor,
@@ -30,6 +39,43 @@ pub use recalculate_map::{
     recalculate_map,
     Updater,
 };
+pub use routing_failure::EdgeRoutingFailure;
+pub use station_exits::spread_station_exits;
+
+/// The shape of the node-set of possible new station locations around a
+/// station.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeSetShape {
+    /// A diamond shape, containing all nodes within the manhattan distance of
+    /// the radius.
+    Manhattan,
+    /// A square shape, containing all nodes within the radius when diagonal
+    /// moves are allowed.
+    Diagonal,
+}
+
+/// The shape of the grid the edges are routed on. The editor only draws the
+/// octilinear grid, so its settings do not offer the hexagonal one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridMode {
+    /// Every node has eight neighbors, so edges bend in steps of 45 degrees.
+    #[default]
+    Octilinear,
+    /// Every node has six neighbors, so edges bend in steps of 60 degrees. The
+    /// nodes are in axial coordinates, where the third axis runs from the
+    /// bottom-left to the top-right neighbor.
+    Hexagonal,
+}
+
+impl GridMode {
+    /// The angle in degrees between two neighboring directions on the grid.
+    pub fn angle_step(self) -> f64 {
+        match self {
+            Self::Octilinear => 45.0,
+            Self::Hexagonal => 60.0,
+        }
+    }
+}
 
 /// Stores the settings for the algorithm.
 // This is a settings struct, so many bools are needed
@@ -40,11 +86,26 @@ pub struct AlgorithmSettings {
     /// the possible new station location.
     /// Default: 3
     pub node_set_radius: i32,
+    /// The shape of the node-set around a station.
+    /// Default: [`NodeSetShape::Manhattan`]
+    pub node_set_shape: NodeSetShape,
+    /// The shape of the grid the edges are routed on.
+    /// Default: [`GridMode::Octilinear`]
+    pub grid_mode: GridMode,
     /// Max amount of attempts allowed of routing edges before erroring out.
     /// Default: 3
     pub edge_routing_attempts: usize,
+    /// The amount of times in a row that only the edges involved in a routing
+    /// failure are reshuffled, before all edges are reshuffled instead. When
+    /// 0, all edges are always reshuffled.
+    /// Default: 0
+    pub local_edge_reshuffles: usize,
     /// The cost of moving from one node to another.
     pub move_cost: f64,
+    /// The extra cost of moving diagonally from one node to another, pushing
+    /// edges towards horizontal and vertical segments when high.
+    /// Default: 0.0
+    pub diagonal_penalty: f64,
     /// The highest and lowest possible x values of the grid.
     pub grid_x_limits: (i32, i32),
     /// The highest and lowest possible y values of the grid.
@@ -63,6 +124,18 @@ pub struct AlgorithmSettings {
     pub early_local_search_abort: bool,
     /// Whether to use iterative local search or exit after the first iteration.
     pub iterative_local_search: bool,
+    /// The minimum manhattan distance between two stations, stations will not
+    /// be placed closer to each other than this.
+    /// Default: 0
+    pub min_station_spacing: i32,
+    /// Whether to collapse the empty rows and columns of the map after the
+    /// algorithm has run, to reduce its size.
+    /// Default: false
+    pub compact_layout: bool,
+    /// Whether to send the node-sets of every edge through the midway updates
+    /// before routing it, so they can be drawn on the canvas.
+    /// Default: false
+    pub debug_node_sets: bool,
 }
 
 impl AlgorithmSettings {
@@ -83,14 +156,39 @@ impl AlgorithmSettings {
         self.log_level = log_level;
         self
     }
+
+    /// Only route the edges, keeping every station where it is. Besides
+    /// turning off station relocation, this turns off the local search and
+    /// compacting the layout, as both move stations as well.
+    pub fn edges_only(mut self) -> Self {
+        self.allow_station_relocation = false;
+        self.local_search = false;
+        self.compact_layout = false;
+        self
+    }
+
+    /// Set the grid limits to the given bounds of a map with a margin of 2
+    /// grid squares on each side. The limits are left as is if there are no
+    /// bounds.
+    pub fn fit_grid_limits(mut self, bounds: Option<(GridNode, GridNode)>) -> Self {
+        if let Some((min, max)) = bounds {
+            self.grid_x_limits = (min.0 - 2, max.0 + 2);
+            self.grid_y_limits = (min.1 - 2, max.1 + 2);
+        }
+        self
+    }
 }
 
 impl Default for AlgorithmSettings {
     fn default() -> Self {
         Self {
             node_set_radius: 3,
+            node_set_shape: NodeSetShape::Manhattan,
+            grid_mode: GridMode::Octilinear,
             edge_routing_attempts: 3,
+            local_edge_reshuffles: 0,
             move_cost: 1.0,
+            diagonal_penalty: 0.0,
             log_level: LogType::Warn,
             grid_x_limits: (i32::MIN, i32::MAX),
             grid_y_limits: (i32::MIN, i32::MAX),
@@ -99,6 +197,26 @@ impl Default for AlgorithmSettings {
             output_on_fail: false,
             early_local_search_abort: true,
             iterative_local_search: false,
+            min_station_spacing: 0,
+            compact_layout: false,
+            debug_node_sets: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_grid_limits() {
+        let settings = AlgorithmSettings::default()
+            .fit_grid_limits(Some((GridNode::from((-1, 3)), GridNode::from((4, 7)))));
+        assert_eq!(settings.grid_x_limits, (-3, 6));
+        assert_eq!(settings.grid_y_limits, (1, 9));
+
+        let unchanged = settings.fit_grid_limits(None);
+        assert_eq!(unchanged.grid_x_limits, (-3, 6));
+        assert_eq!(unchanged.grid_y_limits, (1, 9));
+    }
+}
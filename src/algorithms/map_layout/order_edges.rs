@@ -62,11 +62,17 @@ impl HeapStation {
     }
 }
 
+// Stations with the same degree are ordered by their id, so the order of the
+// edges does not depend on the order the stations are iterated in.
 impl PartialOrd for HeapStation {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(
             self.degree
-                .cmp(&other.degree),
+                .cmp(&other.degree)
+                .then_with(|| {
+                    self.station
+                        .cmp(&other.station)
+                }),
         )
     }
 }
@@ -76,6 +82,11 @@ impl Ord for HeapStation {
         other
             .degree
             .cmp(&self.degree)
+            .then_with(|| {
+                other
+                    .station
+                    .cmp(&self.station)
+            })
     }
 }
 
@@ -91,7 +102,7 @@ fn calc_all_degrees(
     let mut highest = HeapStation::new(0.into(), usize::MIN);
 
     for station_id in map
-        .get_stations()
+        .get_stations_sorted()
         .into_iter()
         .map(Station::get_id)
     {
@@ -138,12 +149,19 @@ pub fn order_edges(map: &Map) -> Result<Vec<Edge>> {
                 line_degree_map.remove(&edge.get_from());
                 line_degree_map.remove(&edge.get_to());
             }
-            highest = HeapStation::new(0.into(), usize::MIN);
-            for station in line_degree_map.values() {
-                if station.degree > highest.degree {
-                    highest = *station;
-                }
-            }
+            highest = line_degree_map
+                .values()
+                .copied()
+                .filter(|station| station.degree > usize::MIN)
+                .min_by(|a, b| {
+                    b.degree
+                        .cmp(&a.degree)
+                        .then_with(|| {
+                            a.station
+                                .cmp(&b.station)
+                        })
+                })
+                .unwrap_or(HeapStation::new(0.into(), usize::MIN));
         }
     }
 
@@ -195,10 +213,15 @@ fn order_edges_alg(
             }
         }
 
-        // Sort the edges by the degree of the opposite in descending order
-        station_edges.sort_unstable_by(|a, b| {
+        // Sort the edges by the degree of the opposite in descending order, and
+        // by their id when the degrees are equal.
+        station_edges.sort_by(|a, b| {
             b.1.degree
                 .cmp(&a.1.degree)
+                .then_with(|| {
+                    a.0.get_id()
+                        .cmp(&b.0.get_id())
+                })
         });
 
         edges.append(
@@ -306,6 +329,18 @@ mod tests {
             disjoint_sorted_ids == vec![edge3_id, edge4_id, edge1_id, edge2_id]
                 || disjoint_sorted_ids == vec![edge1_id, edge2_id, edge3_id, edge4_id]
         );
+
+        // A copy of the map with its own hash maps is ordered the same way.
+        let copy: Map = serde_json::from_str(
+            &serde_json::to_string(&disjoint_map).expect("failed to encode map"),
+        )
+        .expect("failed to decode map");
+        let copy_sorted_ids: Vec<EdgeID> = order_edges(&copy)
+            .unwrap()
+            .iter()
+            .map(|edge| edge.get_id())
+            .collect();
+        assert_eq!(copy_sorted_ids, disjoint_sorted_ids);
     }
 
     #[test]
@@ -9,26 +9,29 @@ use std::{
 use futures_core::future::LocalBoxFuture;
 use leptos::logging;
 
+#[cfg(not(feature = "heatmap"))]
+use super::station_contraction::expand_stations;
 use super::{
+    compact_layout,
     local_search::local_search,
     order_edges::order_edges,
     route_edges::route_edges,
-    station_contraction::{
-        contract_stations,
-        expand_stations,
-    },
+    station_contraction::contract_stations,
     AlgorithmSettings,
+    EdgeRoutingFailure,
 };
 use crate::{
     algorithms::{
         log_print,
         randomize_edges,
+        randomize_edges_locally,
         unsettle_map,
         LogType,
         OccupiedNodes,
     },
     models::{
         Edge,
+        EdgeID,
         Map,
     },
     utils::{
@@ -49,7 +52,10 @@ pub enum Updater {
 }
 
 /// Attempt to route the edges of the map, retrying with different, random, edge
-/// orders if it fails.
+/// orders if it fails. Depending on the settings, only the edges involved in
+/// the failure are reshuffled for a number of retries before all edges are. If
+/// all attempts fail, the returned error describes the edge that failed to
+/// route most often, as that is the one blocking the others.
 async fn attempt_edge_routing(
     settings: AlgorithmSettings,
     map: &mut Map,
@@ -59,6 +65,11 @@ async fn attempt_edge_routing(
 ) -> Result<()> {
     let mut attempt: u64 = 0;
     let mut found = false;
+    // The amount of retries in a row that only reshuffled the involved edges.
+    let mut local_reshuffles = 0;
+    // The amount of times each edge failed to route, the last attempt it failed
+    // on and the failure of that attempt.
+    let mut failures: HashMap<EdgeID, (u64, u64, EdgeRoutingFailure)> = HashMap::new();
 
     while !found {
         let mut alg_map = map.clone();
@@ -80,14 +91,51 @@ async fn attempt_edge_routing(
                 LogType::Error,
             );
 
+            let mut involved = None;
+            if let Error::EdgeRouting(failure) = e {
+                involved = Some(failure.get_involved_edges());
+                let count = failures
+                    .get(&failure.get_edge())
+                    .map_or(0, |(count, ..)| *count);
+                failures.insert(
+                    failure.get_edge(),
+                    (count + 1, attempt, *failure),
+                );
+            }
+
             if attempt >= settings.edge_routing_attempts as u64 {
                 *map = alg_map;
-                return Err(Error::other(
-                    "Reached max amount of retries when routing edges.",
-                ));
+                return Err(failures
+                    .into_values()
+                    .max_by_key(|(count, last_attempt, _)| (*count, *last_attempt))
+                    .map_or(
+                        Error::other("Reached max amount of retries when routing edges."),
+                        |(_, _, failure)| Error::EdgeRouting(Box::new(failure)),
+                    ));
             }
 
-            randomize_edges(&mut edges, attempt);
+            if let Some(involved) =
+                involved.filter(|_| local_reshuffles < settings.local_edge_reshuffles)
+            {
+                local_reshuffles += 1;
+                log_print(
+                    settings,
+                    &format!(
+                        "Retrying with the {} edges involved in the failure reshuffled",
+                        involved.len()
+                    ),
+                    LogType::Debug,
+                );
+                randomize_edges_locally(&mut edges, &involved, attempt);
+            } else {
+                local_reshuffles = 0;
+                log_print(
+                    settings,
+                    "Retrying with all edges reshuffled",
+                    LogType::Debug,
+                );
+                randomize_edges(&mut edges, attempt);
+            }
         } else {
             found = true;
             *map = alg_map;
@@ -98,6 +146,56 @@ async fn attempt_edge_routing(
     Ok(())
 }
 
+/// Check that all stations can be placed within the grid limits, given the
+/// radius they are allowed to move from their original position. Returns an
+/// error naming the offending stations if not.
+fn validate_grid_limits(settings: AlgorithmSettings, map: &Map) -> Result<()> {
+    let radius = if settings.allow_station_relocation {
+        settings.node_set_radius
+    } else {
+        0
+    };
+    let (x_min, x_max) = settings.grid_x_limits;
+    let (y_min, y_max) = settings.grid_y_limits;
+
+    let mut offending = map
+        .get_stations()
+        .into_iter()
+        .filter(|s| {
+            let pos = s.get_original_pos();
+            pos.0 < x_min.saturating_sub(radius)
+                || pos.0 > x_max.saturating_add(radius)
+                || pos.1 < y_min.saturating_sub(radius)
+                || pos.1 > y_max.saturating_add(radius)
+        })
+        .collect::<Vec<_>>();
+
+    if offending.is_empty() {
+        return Ok(());
+    }
+
+    offending.sort_by_key(|s| s.get_id());
+    let names = offending
+        .iter()
+        .map(|s| {
+            if s.get_name()
+                .is_empty()
+            {
+                s.get_id()
+                    .to_string()
+            } else {
+                s.get_name()
+                    .to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Err(Error::other(format!(
+        "The grid limits are too tight for these stations: {names}"
+    )))
+}
+
 /// Recalculate the map, all the positions of the stations and the edges between
 /// them, as a whole. This is the Recalculate Map algorithm in the paper.
 pub async fn recalculate_map(
@@ -113,6 +211,11 @@ pub async fn recalculate_map(
         return Ok(HashMap::new());
     }
 
+    validate_grid_limits(settings, map)?;
+
+    // The bends of the map are counted on the grid its edges are routed on.
+    map.set_grid_mode(settings.grid_mode);
+
     let mut occupied = map.get_occupied_by_locks();
 
     log_print(
@@ -128,7 +231,18 @@ pub async fn recalculate_map(
         LogType::Debug,
     );
 
-    let contracted_stations = contract_stations(settings, map);
+    // Contracting stations removes their interchanges, so they are restored
+    // once the stations are expanded again. Interchanges are not routed.
+    let interchanges = map
+        .get_interchanges()
+        .to_vec();
+    // Expanding the contracted stations spreads them out along their edge
+    // again, so no stations are contracted if they may not move.
+    let contracted_stations = if settings.allow_station_relocation {
+        contract_stations(settings, map)
+    } else {
+        HashMap::new()
+    };
 
     log_print(
         settings,
@@ -204,6 +318,13 @@ pub async fn recalculate_map(
     #[cfg(not(feature = "heatmap"))]
     expand_stations(settings, map, &contracted_stations)?;
 
+    map.set_interchanges(interchanges);
+
+    if settings.compact_layout {
+        compact_layout(map);
+        occupied = map.get_occupied_nodes();
+    }
+
     #[cfg(all(not(test), not(feature = "benchmarking")))]
     logging::log!("Recalculated map");
 
@@ -213,13 +334,21 @@ pub async fn recalculate_map(
 #[cfg(test)]
 mod tests {
     use futures_test::test;
+    use itertools::Itertools;
 
     use super::*;
     use crate::{
         algorithms::{
             occupation::OccupiedNodes,
+            GridMode,
             LogType,
         },
+        models::{
+            Interchange,
+            Line,
+            Station,
+            StationID,
+        },
         utils::{
             graphml,
             json,
@@ -228,6 +357,255 @@ mod tests {
         MapState,
     };
 
+    /// The maps of the corpus of existing maps the algorithm is tested on.
+    const CORPUS_MAPS: [&str; 6] = [
+        "existing_maps/disjointed_test.json",
+        "existing_maps/routing_test.json",
+        "existing_maps/montreal.graphml",
+        "existing_maps/wien.graphml",
+        "existing_maps/washington.graphml",
+        "existing_maps/karlsruhe.graphml",
+    ];
+
+    /// The position of every station and the nodes of every edge between two
+    /// stations, sorted so the layouts of two runs can be compared.
+    type MapLayout = (
+        Vec<(StationID, (i32, i32))>,
+        Vec<(StationID, StationID, Vec<(i32, i32)>)>,
+    );
+
+    /// Decode the given map file from the corpus of existing maps.
+    fn load_map(map_file: &str) -> Map {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(7);
+        canvas.set_size((800.0, 1648.0));
+
+        let test_file_content = std::fs::read_to_string(map_file).expect(&format!(
+            "test data file {map_file} does not exist"
+        ));
+
+        if map_file.ends_with(".json") {
+            json::decode_map(&test_file_content, canvas).expect(&format!(
+                "failed to decode json of {map_file}"
+            ))
+        } else {
+            graphml::decode_map(&test_file_content, canvas).expect(&format!(
+                "failed to decode graphml of {map_file}"
+            ))
+        }
+    }
+
+    /// Get the layout of the given map, with nodes as plain coordinates so they
+    /// can be sorted. Edges are identified by their stations,
+    /// as the algorithm gives new ids to the edges it contracts and expands.
+    fn map_layout(map: &Map) -> MapLayout {
+        let mut stations = map
+            .get_stations()
+            .into_iter()
+            .map(|station| {
+                let pos = station.get_pos();
+                (station.get_id(), (pos.0, pos.1))
+            })
+            .collect::<Vec<_>>();
+        stations.sort();
+
+        let mut edges = map
+            .get_edges()
+            .into_iter()
+            .map(|edge| {
+                (
+                    edge.get_from(),
+                    edge.get_to(),
+                    edge.get_nodes()
+                        .iter()
+                        .map(|node| (node.0, node.1))
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        edges.sort();
+
+        (stations, edges)
+    }
+
+    /// Recalculate the given map file from the corpus twice with the same
+    /// settings and assert both runs result in exactly the same layout.
+    async fn assert_deterministic(map_file: &str) {
+        let map = load_map(map_file);
+
+        let mut state = MapState::new(map.clone());
+        state.calculate_algorithm_settings();
+        let mut settings = state.get_algorithm_settings();
+        settings.edge_routing_attempts = 1;
+        settings.log_level = LogType::Error;
+
+        // A clone of a map iterates its stations and edges in the same order,
+        // so every run gets a map with freshly seeded hash maps instead.
+        let encoded = serde_json::to_string(&map).expect("failed to encode map");
+
+        let mut layouts = Vec::new();
+        for _ in 0..2 {
+            let mut run: Map = serde_json::from_str(&encoded).expect("failed to decode map");
+            recalculate_map(settings, &mut run, Updater::NoUpdates)
+                .await
+                .expect(&format!(
+                    "failed to recalculate map {map_file}"
+                ));
+            layouts.push(map_layout(&run));
+        }
+
+        assert!(
+            layouts[0] == layouts[1],
+            "recalculating {map_file} twice gave different layouts"
+        );
+    }
+
+    #[test]
+    async fn test_validate_grid_limits() {
+        let mut map = Map::new();
+        let mut inside = Station::new((0, 0).into(), None);
+        inside.set_name(&"Inside");
+        let mut outside = Station::new((10, 0).into(), None);
+        outside.set_name(&"Outside");
+        map.add_station(inside);
+        map.add_station(outside);
+
+        let settings = AlgorithmSettings::default()
+            .set_grid_x_limits((-2, 8))
+            .set_grid_y_limits((-2, 2));
+        assert!(validate_grid_limits(settings, &map).is_ok());
+
+        let settings = settings.set_grid_x_limits((-2, 6));
+        assert_eq!(
+            validate_grid_limits(settings, &map),
+            Err(Error::other(
+                "The grid limits are too tight for these stations: Outside"
+            ))
+        );
+    }
+
+    #[test]
+    async fn test_attempt_edge_routing_failure() {
+        let mut map = Map::new();
+        let stations = [
+            Station::new((0, 0).into(), None),
+            Station::new((4, 0).into(), None),
+            Station::new((0, 6).into(), None),
+            Station::new((4, 6).into(), None),
+        ];
+        for station in &stations {
+            map.add_station(station.clone());
+        }
+        let blocked = map.get_edge_id_between(
+            stations[2].get_id(),
+            stations[3].get_id(),
+        );
+        map.get_edge_id_between(
+            stations[0].get_id(),
+            stations[1].get_id(),
+        );
+
+        // Wall in the last station, so only the edge to it can never be routed.
+        let mut occupied: OccupiedNodes = HashMap::new();
+        for x in 3..=5 {
+            for y in 5..=7 {
+                if (x, y) != (4, 6) {
+                    occupied.insert((x, y).into(), EdgeID::from(999).into());
+                }
+            }
+        }
+
+        let mut settings = AlgorithmSettings::default()
+            .set_grid_x_limits((-2, 8))
+            .set_grid_y_limits((-2, 8));
+        settings.allow_station_relocation = false;
+        settings.log_level = LogType::Error;
+        let edges = map
+            .get_edges()
+            .into_iter()
+            .cloned()
+            .collect();
+
+        let Err(Error::EdgeRouting(failure)) = attempt_edge_routing(
+            settings,
+            &mut map,
+            &mut occupied,
+            edges,
+            Updater::NoUpdates,
+        )
+        .await
+        else {
+            panic!("expected the edge routing to fail");
+        };
+
+        assert_eq!(failure.get_edge(), blocked);
+        assert_eq!(failure.get_to(), stations[3].get_id());
+        assert_eq!(
+            failure
+                .get_blockers()
+                .len(),
+            8
+        );
+    }
+
+    #[test]
+    async fn test_attempt_edge_routing_local_reshuffle() {
+        let mut map = Map::new();
+        let stations = [
+            Station::new((0, 0).into(), None),
+            Station::new((6, 0).into(), None),
+            Station::new((3, 2).into(), None),
+            Station::new((3, -2).into(), None),
+        ];
+        for station in &stations {
+            map.add_station(station.clone());
+        }
+        let long = map.get_edge_id_between(
+            stations[0].get_id(),
+            stations[1].get_id(),
+        );
+        let crossing = map.get_edge_id_between(
+            stations[2].get_id(),
+            stations[3].get_id(),
+        );
+
+        // The long edge can go around the crossing edge, but not the other way
+        // around, so the crossing edge only fits when it is routed first.
+        let mut settings = AlgorithmSettings::default()
+            .set_grid_x_limits((0, 6))
+            .set_grid_y_limits((-3, 3));
+        settings.allow_station_relocation = false;
+        settings.log_level = LogType::Error;
+        settings.edge_routing_attempts = 2;
+        settings.local_edge_reshuffles = 1;
+        let edges = vec![
+            map.get_edge(long)
+                .unwrap()
+                .clone(),
+            map.get_edge(crossing)
+                .unwrap()
+                .clone(),
+        ];
+
+        attempt_edge_routing(
+            settings,
+            &mut map,
+            &mut HashMap::new(),
+            edges,
+            Updater::NoUpdates,
+        )
+        .await
+        .expect("expected the local reshuffle to fix the routing");
+
+        assert!(
+            map.get_edge(crossing)
+                .unwrap()
+                .get_nodes()
+                .iter()
+                .all(|node| node.0 == 3)
+        );
+    }
+
     #[test]
     async fn test_recalculate_map_no_overlap_check() {
         let map_file = "existing_maps/wien.graphml";
@@ -302,6 +680,160 @@ mod tests {
         }
     }
 
+    #[test]
+    async fn test_recalculate_map_keeps_interchanges() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(7);
+        canvas.set_size((800.0, 1648.0));
+
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+        let mut map = json::decode_map(&test_file_content, canvas).expect("failed to decode json");
+
+        // The middle station gets contracted while routing.
+        map.add_interchange(0.into(), 1.into())
+            .unwrap();
+        let edge_count = map
+            .get_edges()
+            .len();
+
+        let mut state = MapState::new(map.clone());
+        state.calculate_algorithm_settings();
+
+        recalculate_map(
+            state.get_algorithm_settings(),
+            &mut map,
+            Updater::NoUpdates,
+        )
+        .await
+        .expect("failed to recalculate map");
+
+        assert_eq!(
+            map.get_interchanges(),
+            &[Interchange::new(0.into(), 1.into())]
+        );
+        assert_eq!(
+            map.get_edges()
+                .len(),
+            edge_count
+        );
+    }
+
+    #[test]
+    async fn test_recalculate_map_loop_line() {
+        let mut map = Map::new();
+        let stations = [(0, 0), (8, 0), (8, 8), (0, 8)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, pos)| map.add_named_station(&format!("S{i}"), pos.into()))
+            .collect::<Vec<_>>();
+        let circle = map.add_line_through(
+            (255, 200, 0),
+            &"Circle",
+            &[
+                stations[0],
+                stations[1],
+                stations[2],
+                stations[3],
+                stations[0],
+            ],
+        );
+
+        let mut state = MapState::new(map.clone());
+        state.calculate_algorithm_settings();
+        let mut settings = state.get_algorithm_settings();
+        settings.edge_routing_attempts = 1;
+        settings.log_level = LogType::Error;
+
+        recalculate_map(settings, &mut map, Updater::NoUpdates)
+            .await
+            .expect("failed to recalculate map with a loop line");
+
+        let line = map
+            .get_line(circle)
+            .unwrap();
+        assert!(line.is_cycle(&map));
+        assert!(
+            line.get_line_ends(&map)
+                .is_empty()
+        );
+
+        // Every edge of the loop is routed from one of its stations to the
+        // other.
+        for edge in line.get_edges() {
+            let edge = map
+                .get_edge(*edge)
+                .unwrap();
+            let route = [edge.get_from()]
+                .into_iter()
+                .filter_map(|id| map.get_station(id))
+                .map(Station::get_pos)
+                .chain(
+                    edge.get_nodes()
+                        .iter()
+                        .copied(),
+                )
+                .chain(
+                    map.get_station(edge.get_to())
+                        .map(Station::get_pos),
+                )
+                .collect::<Vec<_>>();
+            assert!(
+                route
+                    .windows(2)
+                    .all(|pair| pair[0].is_neighbor_of(&pair[1]))
+            );
+        }
+    }
+
+    #[test]
+    async fn test_recalculate_map_hexagonal() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(7);
+        canvas.set_size((800.0, 1648.0));
+
+        let test_file_content = std::fs::read_to_string("existing_maps/routing_test.json")
+            .expect("test data file does not exist");
+        let mut map = json::decode_map(&test_file_content, canvas).expect("failed to decode json");
+
+        let mut state = MapState::new(map.clone());
+        state.calculate_algorithm_settings();
+        let mut settings = state.get_algorithm_settings();
+        settings.grid_mode = GridMode::Hexagonal;
+
+        recalculate_map(settings, &mut map, Updater::NoUpdates)
+            .await
+            .expect("failed to recalculate map");
+
+        for edge in map.get_edges() {
+            let from = map
+                .get_station(edge.get_from())
+                .unwrap()
+                .get_pos();
+            let to = map
+                .get_station(edge.get_to())
+                .unwrap()
+                .get_pos();
+
+            let mut nodes = vec![from];
+            nodes.extend_from_slice(edge.get_nodes());
+            nodes.push(to);
+            for pair in nodes.windows(2) {
+                assert_eq!(
+                    pair[0].hex_distance_to(pair[1]),
+                    1,
+                    "edge {} has a non-hexagonal step",
+                    edge.get_id()
+                );
+            }
+        }
+
+        assert_eq!(map.get_grid_mode(), GridMode::Hexagonal);
+        assert!(map
+            .total_bend_cost()
+            .is_finite());
+    }
+
     #[test]
     async fn test_recalculate_map() {
         let map_files = vec![
@@ -359,4 +891,127 @@ mod tests {
             panic!("Some maps failed to recalculate");
         }
     }
+
+    #[test]
+    async fn test_recalculate_map_edges_only() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(7);
+        canvas.set_size((800.0, 1648.0));
+
+        let test_file_content = std::fs::read_to_string("existing_maps/routing_test.json")
+            .expect("test data file does not exist");
+        let mut map = json::decode_map(&test_file_content, canvas).expect("failed to decode json");
+
+        let mut state = MapState::new(map.clone());
+        state.calculate_algorithm_settings();
+        let mut settings = state.get_algorithm_settings();
+        settings.compact_layout = true;
+        settings.log_level = LogType::Error;
+
+        let positions = |map: &Map| {
+            map.get_stations()
+                .into_iter()
+                .map(|s| (s.get_id(), s.get_pos()))
+                .collect::<HashMap<_, _>>()
+        };
+        let before = positions(&map);
+
+        recalculate_map(
+            settings.edges_only(),
+            &mut map,
+            Updater::NoUpdates,
+        )
+        .await
+        .expect("failed to recalculate map");
+
+        // Neither contracting the stations, the local search nor compacting
+        // the layout moved any station.
+        assert_eq!(positions(&map), before);
+    }
+
+    #[test]
+    async fn test_recalculate_map_min_station_spacing() {
+        // A hub right next to one of its neighbors, with enough edges for the
+        // local search to try moving it.
+        let mut map = Map::new();
+        let hub = Station::new((0, 0).into(), None);
+        let neighbors = [
+            Station::new((1, 0).into(), None),
+            Station::new((0, 4).into(), None),
+            Station::new((-4, 0).into(), None),
+        ];
+        map.add_station(hub.clone());
+        let mut line = Line::new(None);
+        for neighbor in &neighbors {
+            map.add_station(neighbor.clone());
+            let edge = map.get_edge_id_between(hub.get_id(), neighbor.get_id());
+            line.add_edge(edge, &mut map);
+        }
+        map.add_line(line);
+
+        let mut settings = AlgorithmSettings::default()
+            .set_grid_x_limits((-8, 8))
+            .set_grid_y_limits((-8, 8));
+        settings.log_level = LogType::Error;
+        settings.min_station_spacing = 2;
+
+        recalculate_map(settings, &mut map, Updater::NoUpdates)
+            .await
+            .expect("failed to recalculate map");
+
+        for (a, b) in map
+            .get_stations()
+            .into_iter()
+            .tuple_combinations()
+        {
+            assert!(
+                a.get_pos()
+                    .manhattan_distance_to(b.get_pos())
+                    >= 2,
+                "stations {} and {} are closer than the minimum spacing",
+                a.get_id(),
+                b.get_id()
+            );
+        }
+    }
+
+    #[test]
+    async fn test_recalculate_map_all_locked() {
+        let mut map = load_map("existing_maps/routing_test.json");
+
+        let mut state = MapState::new(map.clone());
+        state.calculate_algorithm_settings();
+        let mut settings = state.get_algorithm_settings();
+        settings.edge_routing_attempts = 1;
+        settings.log_level = LogType::Error;
+
+        recalculate_map(settings, &mut map, Updater::NoUpdates)
+            .await
+            .expect("failed to recalculate map");
+        map.lock_all();
+
+        let occupied = map.get_occupied_by_locks();
+        for station in map.get_stations() {
+            assert!(occupied.contains_key(&station.get_pos()));
+        }
+        for edge in map.get_edges() {
+            for node in edge.get_nodes() {
+                assert!(occupied.contains_key(node));
+            }
+        }
+
+        let before = map_layout(&map);
+        recalculate_map(settings, &mut map, Updater::NoUpdates)
+            .await
+            .expect("failed to recalculate locked map");
+
+        assert_eq!(before, map_layout(&map));
+    }
+
+    #[test]
+    async fn test_recalculate_map_deterministic() {
+        for map_file in CORPUS_MAPS {
+            assert_deterministic(map_file).await;
+        }
+    }
 }
@@ -4,6 +4,8 @@
 use super::{
     edge_dijkstra::edge_dijkstra,
     AlgorithmSettings,
+    EdgeRoutingFailure,
+    NodeSetShape,
     Updater,
 };
 use crate::{
@@ -16,6 +18,7 @@ use crate::{
         Edge,
         GridNode,
         Map,
+        SearchRegion,
         Station,
     },
     utils::{
@@ -25,6 +28,28 @@ use crate::{
     Error,
 };
 
+/// Check if the given node is closer than the minimum station spacing to any
+/// other station on the map. Checkpoints are not taken into account.
+pub(super) fn too_close_to_other_station(
+    map: &Map,
+    settings: AlgorithmSettings,
+    station: &Station,
+    node: GridNode,
+) -> bool {
+    if settings.min_station_spacing <= 1 || station.is_checkpoint() {
+        return false;
+    }
+
+    map.get_stations()
+        .into_iter()
+        .filter(|s| s.get_id() != station.get_id() && !s.is_checkpoint())
+        .any(|s| {
+            s.get_pos()
+                .manhattan_distance_to(node)
+                < settings.min_station_spacing
+        })
+}
+
 /// Get a set of nodes in the radius around the given station.
 fn get_node_set(
     map: &Map,
@@ -45,16 +70,27 @@ fn get_node_set(
     for x in (station_pos.0 - radius)..=(station_pos.0 + radius) {
         for y in (station_pos.1 - radius)..=(station_pos.1 + radius) {
             let node = GridNode::from((x, y));
-            if occupied.contains_key(&node) {
+            if occupied.contains_key(&node)
+                || too_close_to_other_station(map, settings, station, node)
+            {
                 continue;
             }
 
-            let distance = node.manhattan_distance_to(original_station_pos);
-            if distance <= radius {
-                nodes.push((
-                    node,
-                    f64::from(distance) * settings.move_cost,
-                ));
+            let (in_radius, distance) = match settings.node_set_shape {
+                NodeSetShape::Manhattan => {
+                    let distance = node.manhattan_distance_to(original_station_pos);
+                    (distance <= radius, f64::from(distance))
+                },
+                NodeSetShape::Diagonal => {
+                    (
+                        node.chebyshev_distance_to(original_station_pos) <= radius,
+                        node.diagonal_distance_to(original_station_pos),
+                    )
+                },
+            };
+
+            if in_radius {
+                nodes.push((node, distance * settings.move_cost));
             }
         }
     }
@@ -185,6 +221,24 @@ pub async fn route_edges(
             LogType::Debug,
         );
 
+        if settings.debug_node_sets {
+            if let Updater::Updater(updater) = midway_updater.clone() {
+                let mut debug_map = map.clone();
+                debug_map.set_search_region(Some(SearchRegion {
+                    edge: edge.get_id(),
+                    from_nodes: from_nodes
+                        .iter()
+                        .map(|(node, _)| *node)
+                        .collect(),
+                    to_nodes: to_nodes
+                        .iter()
+                        .map(|(node, _)| *node)
+                        .collect(),
+                }));
+                updater(debug_map, IDManager::to_data()).await;
+            }
+        }
+
         let (start, nodes, end, cost) = edge_dijkstra(
             settings,
             map,
@@ -194,7 +248,16 @@ pub async fn route_edges(
             &to_nodes,
             to_station,
             &occupied,
-        )?;
+        )
+        .map_err(|e| {
+            Error::EdgeRouting(Box::new(EdgeRoutingFailure::new(
+                edge.get_id(),
+                (from_station.get_id(), &from_nodes),
+                (to_station.get_id(), &to_nodes),
+                &occupied,
+                &e,
+            )))
+        })?;
 
         log_print(
             settings,
@@ -269,9 +332,16 @@ pub async fn route_edges(
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            Mutex,
+        },
+    };
 
     use futures_test::test;
+    use futures_util::FutureExt;
 
     use super::*;
     use crate::models::Station;
@@ -321,6 +391,99 @@ mod tests {
         );
     }
 
+    #[test]
+    async fn test_get_node_set_min_station_spacing() {
+        let mut map = Map::new();
+        let station = Station::new((0, 0).into(), None);
+        let neighbor = Station::new((1, 0).into(), None);
+        map.add_station(station.clone());
+        map.add_station(neighbor);
+
+        let mut settings = AlgorithmSettings::default();
+        settings.node_set_radius = 1;
+
+        let result = get_node_set(
+            &map,
+            settings,
+            &station,
+            &HashMap::new(),
+        );
+        assert!(
+            result
+                .iter()
+                .any(|(n, _)| *n == (0, 0))
+        );
+
+        settings.min_station_spacing = 2;
+        let result = get_node_set(
+            &map,
+            settings,
+            &station,
+            &HashMap::new(),
+        );
+
+        assert_eq!(
+            result,
+            vec![
+                (GridNode::from((-1, 0)), 1.0),
+                (GridNode::from((0, -1)), 1.0),
+                (GridNode::from((0, 1)), 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    async fn test_get_node_set_shapes() {
+        let mut map = Map::new();
+        let station = Station::new((0, 0).into(), None);
+        map.add_station(station.clone());
+
+        let mut settings = AlgorithmSettings::default();
+        settings.node_set_radius = 2;
+
+        let manhattan = get_node_set(
+            &map,
+            settings,
+            &station,
+            &HashMap::new(),
+        );
+
+        settings.node_set_shape = NodeSetShape::Diagonal;
+        let diagonal = get_node_set(
+            &map,
+            settings,
+            &station,
+            &HashMap::new(),
+        );
+
+        assert_eq!(manhattan.len(), 13);
+        assert_eq!(diagonal.len(), 25);
+
+        // Every node in the diamond is also in the square.
+        for (node, _) in &manhattan {
+            assert!(
+                diagonal
+                    .iter()
+                    .any(|(n, _)| n == node)
+            );
+        }
+
+        // The corners are only in the square, at their diagonal distance as cost.
+        assert!(
+            !manhattan
+                .iter()
+                .any(|(n, _)| *n == (2, 2))
+        );
+        let (_, corner_cost) = diagonal
+            .iter()
+            .find(|(n, _)| *n == (2, 2))
+            .unwrap();
+        assert_eq!(
+            *corner_cost,
+            GridNode::from((0, 0)).diagonal_distance_to(GridNode::from((2, 2)))
+        );
+    }
+
     #[test]
     async fn test_split_overlap() {
         let from = Station::new((0, 0).into(), None);
@@ -386,4 +549,56 @@ mod tests {
 
         assert_eq!(result, HashMap::new());
     }
+
+    #[test]
+    async fn test_route_edges_debug_node_sets() {
+        let mut map = Map::new();
+        let from = map.add_named_station(&"A", (0, 0).into());
+        let to = map.add_named_station(&"B", (4, 0).into());
+        map.add_line_through((255, 0, 0), &"Red", &[from, to]);
+        let edges = map
+            .get_edges()
+            .into_iter()
+            .cloned()
+            .collect();
+
+        let regions = Arc::new(Mutex::new(Vec::new()));
+        let updater_regions = regions.clone();
+        let mut settings = AlgorithmSettings::default();
+        settings.debug_node_sets = true;
+
+        route_edges(
+            settings,
+            &mut map,
+            edges,
+            HashMap::new(),
+            Updater::Updater(Arc::new(Box::new(
+                move |map: Map, _| {
+                    if let Some(region) = map.get_search_region() {
+                        updater_regions
+                            .lock()
+                            .unwrap()
+                            .push(region.clone());
+                    }
+                    async {}.boxed_local()
+                },
+            ))),
+        )
+        .await
+        .unwrap();
+
+        let regions = regions
+            .lock()
+            .unwrap();
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0]
+            .from_nodes
+            .contains(&(0, 0).into()));
+        assert!(regions[0]
+            .to_nodes
+            .contains(&(4, 0).into()));
+        assert!(map
+            .get_search_region()
+            .is_none());
+    }
 }
@@ -0,0 +1,240 @@
+//! Contains the [`EdgeRoutingFailure`] struct, describing why an edge could not
+//! be routed by the Route Edges algorithm.
+
+use std::fmt::Display;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    algorithms::{
+        OccupiedNode,
+        OccupiedNodes,
+    },
+    models::{
+        EdgeID,
+        GridNode,
+        StationID,
+    },
+};
+
+/// Describes an edge for which no path could be found, including the node sets
+/// that were tried and the occupied nodes around them that were in the way.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EdgeRoutingFailure {
+    /// The edge that could not be routed.
+    edge: EdgeID,
+    /// The station the edge starts at.
+    from: StationID,
+    /// The station the edge ends at.
+    to: StationID,
+    /// The possible locations of the from station that were tried.
+    from_nodes: Vec<GridNode>,
+    /// The possible locations of the to station that were tried.
+    to_nodes: Vec<GridNode>,
+    /// The occupied nodes in the area spanned by the node sets.
+    blockers: Vec<(GridNode, OccupiedNode)>,
+    /// Why routing the edge failed.
+    message: String,
+}
+
+impl EdgeRoutingFailure {
+    /// Create a new [`EdgeRoutingFailure`] for the given edge between the given
+    /// stations. The blockers are all occupied nodes within the area spanned by
+    /// the given node sets, extended by one node on every side.
+    pub fn new(
+        edge: EdgeID,
+        (from, from_nodes): (StationID, &[(GridNode, f64)]),
+        (to, to_nodes): (StationID, &[(GridNode, f64)]),
+        occupied: &OccupiedNodes,
+        message: &impl ToString,
+    ) -> Self {
+        let from_nodes = from_nodes
+            .iter()
+            .map(|(n, _)| *n)
+            .collect::<Vec<_>>();
+        let to_nodes = to_nodes
+            .iter()
+            .map(|(n, _)| *n)
+            .collect::<Vec<_>>();
+
+        let all_nodes = from_nodes
+            .iter()
+            .chain(to_nodes.iter());
+        let min_x = all_nodes
+            .clone()
+            .map(|n| n.0)
+            .min()
+            .unwrap_or_default()
+            - 1;
+        let max_x = all_nodes
+            .clone()
+            .map(|n| n.0)
+            .max()
+            .unwrap_or_default()
+            + 1;
+        let min_y = all_nodes
+            .clone()
+            .map(|n| n.1)
+            .min()
+            .unwrap_or_default()
+            - 1;
+        let max_y = all_nodes
+            .map(|n| n.1)
+            .max()
+            .unwrap_or_default()
+            + 1;
+
+        let mut blockers = occupied
+            .iter()
+            .filter(|(node, _)| {
+                (min_x..=max_x).contains(&node.0) && (min_y..=max_y).contains(&node.1)
+            })
+            .map(|(node, occupant)| (*node, *occupant))
+            .collect::<Vec<_>>();
+        blockers.sort_by_key(|(node, _)| (node.0, node.1));
+
+        Self {
+            edge,
+            from,
+            to,
+            from_nodes,
+            to_nodes,
+            blockers,
+            message: message.to_string(),
+        }
+    }
+
+    /// A getter method for the edge that could not be routed.
+    pub fn get_edge(&self) -> EdgeID {
+        self.edge
+    }
+
+    /// A getter method for the station the edge starts at.
+    pub fn get_from(&self) -> StationID {
+        self.from
+    }
+
+    /// A getter method for the station the edge ends at.
+    pub fn get_to(&self) -> StationID {
+        self.to
+    }
+
+    /// A getter method for the possible locations of the from station that
+    /// were tried.
+    pub fn get_from_nodes(&self) -> &[GridNode] {
+        &self.from_nodes
+    }
+
+    /// A getter method for the possible locations of the to station that were
+    /// tried.
+    pub fn get_to_nodes(&self) -> &[GridNode] {
+        &self.to_nodes
+    }
+
+    /// A getter method for the occupied nodes in the area spanned by the node
+    /// sets.
+    pub fn get_blockers(&self) -> &[(GridNode, OccupiedNode)] {
+        &self.blockers
+    }
+
+    /// Get the edges involved in the failure, which are the edge that could
+    /// not be routed followed by the edges blocking it.
+    pub fn get_involved_edges(&self) -> Vec<EdgeID> {
+        let mut involved = vec![self.edge];
+        for edge in self
+            .blockers
+            .iter()
+            .filter_map(|(_, occupant)| occupant.get_edge_id())
+        {
+            if !involved.contains(&edge) {
+                involved.push(edge);
+            }
+        }
+        involved
+    }
+
+    /// A getter method for why routing the edge failed.
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for EdgeRoutingFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "could not route edge {} from station {} to station {} ({} blocked nodes nearby): {}",
+            self.edge,
+            self.from,
+            self.to,
+            self.blockers
+                .len(),
+            self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_edge_routing_failure() {
+        let occupied: OccupiedNodes = HashMap::from([
+            (
+                GridNode::from((1, 1)),
+                OccupiedNode::Edge(EdgeID::from(5)),
+            ),
+            (
+                GridNode::from((0, 0)),
+                OccupiedNode::Station(StationID::from(3)),
+            ),
+            (
+                GridNode::from((10, 10)),
+                OccupiedNode::Edge(EdgeID::from(6)),
+            ),
+        ]);
+
+        let failure = EdgeRoutingFailure::new(
+            EdgeID::from(1),
+            (
+                StationID::from(2),
+                &[(GridNode::from((0, 1)), 0.0)],
+            ),
+            (
+                StationID::from(4),
+                &[(GridNode::from((3, 1)), 0.0)],
+            ),
+            &occupied,
+            &"No path found",
+        );
+
+        assert_eq!(failure.get_edge(), EdgeID::from(1));
+        assert_eq!(
+            failure.get_from_nodes(),
+            &[GridNode::from((0, 1))]
+        );
+        assert_eq!(
+            failure.get_involved_edges(),
+            vec![EdgeID::from(1), EdgeID::from(5)]
+        );
+        assert_eq!(
+            failure.get_blockers(),
+            &[
+                (
+                    GridNode::from((0, 0)),
+                    OccupiedNode::Station(StationID::from(3))
+                ),
+                (
+                    GridNode::from((1, 1)),
+                    OccupiedNode::Edge(EdgeID::from(5))
+                ),
+            ]
+        );
+    }
+}
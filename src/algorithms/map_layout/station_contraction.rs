@@ -2,29 +2,125 @@
 //! and expanding those edges back out again.
 
 use std::collections::HashMap;
+#[cfg(not(feature = "heatmap"))]
+use std::collections::HashSet;
 
 use super::AlgorithmSettings;
+#[cfg(not(feature = "heatmap"))]
 use crate::{
     algorithms::{
         log_print,
         LogType,
     },
+    utils::Result,
+    Error,
+};
+use crate::{
     models::{
         Edge,
         Map,
         Station,
         StationID,
     },
-    utils::{
-        line_sections::{
-            trace_line_section,
-            LineSection,
-        },
-        Result,
+    utils::line_sections::{
+        trace_line_section,
+        LineSection,
     },
-    Error,
 };
 
+/// A station that was contracted into an edge, together with where along the
+/// line section it was contracted from it was located.
+#[derive(Clone, Debug)]
+pub struct ContractedStation {
+    /// The contracted station.
+    #[cfg(not(feature = "heatmap"))]
+    station: Station,
+    /// The station at the start of the line section the station was contracted
+    /// from.
+    #[cfg(not(feature = "heatmap"))]
+    section_start: StationID,
+    /// How far along the line section the station was located, measured from
+    /// its start, as a fraction of the length of the whole section.
+    #[cfg(not(feature = "heatmap"))]
+    offset: f64,
+}
+
+impl ContractedStation {
+    /// Create a new contracted station. With the heatmap enabled the stations
+    /// are never expanded again, so none of this is kept.
+    #[cfg_attr(feature = "heatmap", allow(unused_variables))] // only read when expanding
+    fn new(station: Station, section_start: StationID, offset: f64) -> Self {
+        Self {
+            #[cfg(not(feature = "heatmap"))]
+            station,
+            #[cfg(not(feature = "heatmap"))]
+            section_start,
+            #[cfg(not(feature = "heatmap"))]
+            offset,
+        }
+    }
+}
+
+/// Get how far along the line section each of its middle stations is located
+/// when walking it from the given start station, as a fraction of the length
+/// of the whole section. Middle stations that can't be reached by walking the
+/// section keep their relative position in the list of middles.
+fn section_offsets(
+    map: &Map,
+    line_section: &LineSection,
+    start: StationID,
+) -> HashMap<StationID, f64> {
+    let station_pos = |id: StationID| {
+        map.get_station(id)
+            .map(Station::get_pos)
+    };
+
+    let mut distances = HashMap::new();
+    let mut remaining = line_section
+        .edges
+        .clone();
+    let mut current = start;
+    let mut length = 0.0;
+
+    while let Some(index) = remaining
+        .iter()
+        .position(|e| e.is_from(current) || e.is_to(current))
+    {
+        let next = remaining
+            .swap_remove(index)
+            .opposite(current)
+            .expect("edge is connected to current station");
+
+        if let (Some(current_pos), Some(next_pos)) = (station_pos(current), station_pos(next)) {
+            length += current_pos.diagonal_distance_to(next_pos);
+        }
+        if line_section
+            .middles
+            .contains(&next)
+        {
+            distances.insert(next, length);
+        }
+
+        current = next;
+    }
+
+    let middle_count = line_section
+        .middles
+        .len() as f64;
+    line_section
+        .middles
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let offset = match distances.get(id) {
+                Some(distance) if length > 0.0 => distance / length,
+                _ => (i as f64 + 1.0) / (middle_count + 1.0),
+            };
+            (*id, offset)
+        })
+        .collect()
+}
+
 /// Resolves a cycle of two stations in a line section by taking out the
 /// starting edge from the line section.
 fn resolve_two_station_cycle(mut line_section: LineSection) -> LineSection {
@@ -149,11 +245,16 @@ fn can_contract_into(
 
     // Check if the stations are far enough apart. If they are too close, the
     // stations might become too close for the contracted station to be re-inserted
-    // after the algorithm has ran its course.
+    // after the algorithm has ran its course, taking the minimum station spacing
+    // into account.
     start_station
         .get_pos()
         .manhattan_distance_to(end_station.get_pos())
-        > settings.node_set_radius * radius_mult + station_count as i32
+        > settings.node_set_radius * radius_mult
+            + station_count as i32
+                * settings
+                    .min_station_spacing
+                    .max(1)
 }
 
 /// Contract all stations with degree two into an edge between their neighboring
@@ -162,11 +263,13 @@ fn can_contract_into(
 pub fn contract_stations(
     settings: AlgorithmSettings,
     map: &mut Map,
-) -> HashMap<StationID, Station> {
+) -> HashMap<StationID, ContractedStation> {
     let mut contracted_stations = HashMap::new();
 
+    // Go through the edges in order of their id, so the same map is always
+    // contracted the same way.
     let mut unchecked_edges = map
-        .get_edges()
+        .get_edges_sorted()
         .into_iter()
         .cloned()
         .collect::<Vec<_>>()
@@ -216,6 +319,16 @@ pub fn contract_stations(
             start = line_section.ends[0];
         }
 
+        // Edges the user required to leave a station in a certain direction are
+        // kept as they are, so the requirement can be enforced.
+        if line_section
+            .edges
+            .iter()
+            .any(Edge::has_required_direction)
+        {
+            continue;
+        }
+
         // Check for other edge cases preventing contraction.
         if !can_contract_into(
             settings,
@@ -238,6 +351,7 @@ pub fn contract_stations(
 
         new_edge.extend_contracted_stations(&line_section.middles);
 
+        let offsets = section_offsets(map, &line_section, start);
         let middle_stations = line_section
             .middles
             .iter()
@@ -249,8 +363,12 @@ pub fn contract_stations(
             .collect::<Vec<_>>();
 
         for station in middle_stations {
-            contracted_stations.insert(station.get_id(), station.clone());
+            let offset = offsets[&station.get_id()];
             map.remove_station(station.get_id());
+            contracted_stations.insert(
+                station.get_id(),
+                ContractedStation::new(station, start, offset),
+            );
         }
 
         // Remove the edges that we contracted from our list of unchecked edges, as we
@@ -271,6 +389,7 @@ pub fn contract_stations(
 /// Reinsert all contracted stations into the map.
 /// The stations in `expand_stations` are reinserted into the map at the
 /// locations given by `station_locs`.
+#[cfg(not(feature = "heatmap"))]
 fn reinsert_stations(
     map: &mut Map,
     edge: &Edge,
@@ -363,23 +482,87 @@ fn reinsert_stations(
     }
 }
 
+/// Move the given locations of contracted stations on the nodes of the given
+/// edge off nodes that are already taken by another station. A station that
+/// would land on a taken node is pushed to the next free node along the edge,
+/// or to the closest free node before it if there is not enough room left
+/// behind it for the stations after it. The order of the stations is kept.
+/// The last location, which is that of the end of the edge, is left as is.
+#[cfg(not(feature = "heatmap"))]
+fn free_station_locs(map: &Map, edge: &Edge, station_locs: &[usize]) -> Result<Vec<usize>> {
+    let nodes = edge.get_nodes();
+    let taken = map
+        .get_stations()
+        .into_iter()
+        .map(Station::get_pos)
+        .collect::<HashSet<_>>();
+    let free = (0..nodes.len())
+        .filter(|i| !taken.contains(&nodes[*i]))
+        .collect::<Vec<_>>();
+
+    let (end, station_locs) = station_locs
+        .split_last()
+        .ok_or(Error::other(
+            "no station locations given",
+        ))?;
+
+    let mut result = Vec::with_capacity(station_locs.len() + 1);
+    let mut previous = None;
+    for (i, loc) in station_locs
+        .iter()
+        .enumerate()
+    {
+        let remaining = station_locs.len() - i - 1;
+        // The free nodes after the previous station that leave enough room for
+        // the remaining stations.
+        let candidates = free
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| previous.is_none_or(|p| **node > p))
+            .filter(|(j, _)| free.len() - j > remaining)
+            .map(|(_, node)| *node)
+            .collect::<Vec<_>>();
+
+        let chosen = candidates
+            .iter()
+            .find(|node| **node >= *loc)
+            .or(candidates.last())
+            .copied()
+            .ok_or(Error::other(format!(
+                "No room on edge {} to re-insert {} contracted stations",
+                edge.get_id(),
+                station_locs.len()
+            )))?;
+
+        result.push(chosen);
+        previous = Some(chosen);
+    }
+    result.push(*end);
+
+    Ok(result)
+}
+
 /// Expand all contracted stations into new stations and edges.
 /// The contracted stations are returned equidistantly between the two ends of
 /// the edge they were contracted into.
+#[cfg(not(feature = "heatmap"))]
 pub fn expand_stations(
     settings: AlgorithmSettings,
     map: &mut Map,
-    contracted_stations: &HashMap<StationID, Station>,
+    contracted_stations: &HashMap<StationID, ContractedStation>,
 ) -> Result<()> {
+    // The new stations get their ids in order of the edges they are expanded
+    // from, so the edges are expanded in order of their id.
     let edges = map
-        .get_edges()
+        .get_edges_sorted()
         .into_iter()
         .cloned()
         .collect::<Vec<_>>();
 
     for edge in edges {
-        // Get all stations that were contracted into the edge.
-        let mut to_expand = edge
+        // Get all stations that were contracted into the edge, ordered by where they
+        // were located along the line section in the direction of the edge.
+        let mut contracted = edge
             .get_contracted_stations()
             .iter()
             .map(|id| {
@@ -387,7 +570,23 @@ pub fn expand_stations(
                     .get(id)
                     .unwrap()
             })
-            .cloned()
+            .collect::<Vec<_>>();
+        contracted.sort_by(|a, b| {
+            a.offset
+                .total_cmp(&b.offset)
+        });
+        if contracted
+            .first()
+            .is_some_and(|c| !edge.is_from(c.section_start))
+        {
+            contracted.reverse();
+        }
+        let mut to_expand = contracted
+            .into_iter()
+            .map(|c| {
+                c.station
+                    .clone()
+            })
             .collect::<Vec<_>>();
 
         if to_expand.is_empty() {
@@ -419,6 +618,9 @@ pub fn expand_stations(
             .map(|i| ((i as f64) * step) as usize)
             .collect::<Vec<_>>()[1..]
             .to_vec();
+        // Stations placed equidistantly can still end up on a node already taken
+        // by another station, so move them to free nodes along the edge.
+        let station_locs = free_station_locs(map, &edge, &station_locs)?;
 
         log_print(
             settings,
@@ -432,8 +634,6 @@ pub fn expand_stations(
             LogType::Debug,
         );
 
-        // FIXME: the order of stations can still flip!
-
         reinsert_stations(
             map,
             &edge,
@@ -446,3 +646,147 @@ pub fn expand_stations(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::GridNode;
+
+    #[test]
+    fn test_expand_stations_keeps_order_on_bent_section() {
+        let mut map = Map::new();
+        let hub = Station::new((0, 0).into(), None);
+        let branch = Station::new((-6, 0).into(), None);
+        map.add_station(hub.clone());
+        map.add_station(branch.clone());
+        map.get_edge_id_between(branch.get_id(), hub.get_id());
+
+        // A loop leaving the hub and coming back to it, bending around two
+        // corners.
+        let ring = [
+            (0, 10),
+            (0, 20),
+            (10, 20),
+            (20, 20),
+            (20, 10),
+            (20, 0),
+        ]
+        .into_iter()
+        .map(|pos| Station::new(pos.into(), None))
+        .collect::<Vec<_>>();
+        let mut previous = hub.get_id();
+        for station in &ring {
+            map.add_station(station.clone());
+            map.get_edge_id_between(previous, station.get_id());
+            previous = station.get_id();
+        }
+        map.get_edge_id_between(previous, hub.get_id());
+
+        let mut settings = AlgorithmSettings::default();
+        settings.node_set_radius = 1;
+        let contracted = contract_stations(settings, &mut map);
+        assert_eq!(contracted.len(), 4);
+
+        // Route the contracted edge along the original loop, from the last
+        // ring station back to the first one.
+        let contracted_edge = map
+            .get_edge_id_between_if_exists(ring[5].get_id(), ring[0].get_id())
+            .expect("stations were not contracted into an edge");
+        let nodes = (1..=20)
+            .map(|y| GridNode::from((20, y)))
+            .chain(
+                (0..20)
+                    .rev()
+                    .map(|x| GridNode::from((x, 20))),
+            )
+            .chain(
+                (11..20)
+                    .rev()
+                    .map(|y| GridNode::from((0, y))),
+            )
+            .collect();
+        map.get_mut_edge(contracted_edge)
+            .unwrap()
+            .set_nodes(nodes);
+
+        expand_stations(settings, &mut map, &contracted).unwrap();
+
+        for station in &ring {
+            assert_eq!(
+                map.get_station(station.get_id())
+                    .expect("station was not expanded")
+                    .get_pos(),
+                station.get_pos()
+            );
+        }
+        for pair in ring.windows(2) {
+            assert!(
+                map.get_edge_id_between_if_exists(pair[0].get_id(), pair[1].get_id())
+                    .is_some(),
+                "no edge between {} and {}",
+                pair[0].get_id(),
+                pair[1].get_id()
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_stations_avoids_taken_nodes() {
+        let mut map = Map::new();
+        let start = Station::new((0, 0).into(), None);
+        let end = Station::new((4, 0).into(), None);
+        // A station from elsewhere that ended up on the route of the edge.
+        let other = Station::new((2, 0).into(), None);
+        map.add_station(start.clone());
+        map.add_station(end.clone());
+        map.add_station(other.clone());
+
+        let middles = [
+            Station::new((1, 0).into(), None),
+            Station::new((3, 0).into(), None),
+        ];
+        let contracted = middles
+            .iter()
+            .enumerate()
+            .map(|(i, station)| {
+                (
+                    station.get_id(),
+                    ContractedStation {
+                        station: station.clone(),
+                        section_start: start.get_id(),
+                        offset: i as f64,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        // The edge is just long enough for both stations, but naively they
+        // would be placed on the two last nodes, the first of which is taken.
+        let edge_id = map.get_edge_id_between(start.get_id(), end.get_id());
+        let edge = map
+            .get_mut_edge(edge_id)
+            .unwrap();
+        edge.set_nodes(vec![
+            (1, 0).into(),
+            (2, 0).into(),
+            (3, 0).into(),
+        ]);
+        edge.extend_contracted_stations(&[middles[0].get_id(), middles[1].get_id()]);
+
+        expand_stations(
+            AlgorithmSettings::default(),
+            &mut map,
+            &contracted,
+        )
+        .unwrap();
+
+        for station in &middles {
+            assert_eq!(
+                map.get_station(station.get_id())
+                    .expect("station was not expanded")
+                    .get_pos(),
+                station.get_pos()
+            );
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! Contains the function to spread the edges leaving a station over the
+//! directions available around it.
+
+use futures_util::FutureExt;
+use itertools::Itertools;
+
+use super::{
+    route_edges::route_edges,
+    AlgorithmSettings,
+    GridMode,
+    Updater,
+};
+use crate::{
+    algorithms::{
+        calc_direction::node_direction,
+        OccupiedNodes,
+    },
+    models::{
+        Edge,
+        EdgeID,
+        GridNode,
+        Map,
+        StationID,
+    },
+    utils::{
+        calculate_grid_angle,
+        Result,
+    },
+    Error,
+};
+
+/// The edges to move together with the node next to the station they are
+/// assigned to leave it through.
+type ExitAssignment = Vec<(Edge, GridNode)>;
+
+/// Assign every edge its own node out of the free nodes around the station at
+/// the given position, as close as possible to the direction of the station at
+/// its other end, while keeping the edges in the same order around the station.
+///
+/// Every set of free directions is tried with every rotation of the edges over
+/// them, keeping the one that strays the least from where the edges want to go.
+fn assign_exits(
+    mode: GridMode,
+    station_pos: GridNode,
+    movable: Vec<(Edge, GridNode)>,
+    free: &[GridNode],
+) -> ExitAssignment {
+    // All angles are measured from the same side of the station, so they give
+    // the order of the edges and directions around it.
+    let left = GridNode::new(station_pos.0 - 1, station_pos.1);
+    let around = |node: GridNode| calculate_grid_angle(mode, left, station_pos, node);
+
+    let free = free
+        .iter()
+        .copied()
+        .sorted_by(|a, b| around(*a).total_cmp(&around(*b)))
+        .collect::<Vec<_>>();
+    let movable = movable
+        .into_iter()
+        .sorted_by(|a, b| around(a.1).total_cmp(&around(b.1)))
+        .collect::<Vec<_>>();
+
+    let mut best: Option<(f64, Vec<(usize, GridNode)>)> = None;
+    for directions in free
+        .iter()
+        .combinations(movable.len())
+    {
+        for rotation in 0..movable.len() {
+            let assignment = directions
+                .iter()
+                .enumerate()
+                .map(|(i, direction)| {
+                    (
+                        (i + rotation) % movable.len(),
+                        **direction,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let cost = assignment
+                .iter()
+                .map(|(edge, direction)| {
+                    let angle = calculate_grid_angle(
+                        mode,
+                        movable[*edge].1,
+                        station_pos,
+                        *direction,
+                    );
+                    angle.min(360.0 - angle)
+                })
+                .sum::<f64>();
+
+            if best
+                .as_ref()
+                .is_none_or(|(best_cost, _)| cost < *best_cost)
+            {
+                best = Some((cost, assignment));
+            }
+        }
+    }
+
+    let (_, assignment) = best.expect("there is at least one set of directions");
+    assignment
+        .into_iter()
+        .map(|(edge, exit)| {
+            (
+                movable[edge]
+                    .0
+                    .clone(),
+                exit,
+            )
+        })
+        .collect()
+}
+
+/// Reroute the given edges from the nodes next to the station they are assigned
+/// to leave it through, around the given occupied nodes, using the given map
+/// with the routes of these edges taken off. Only the new routes are taken
+/// over onto the map.
+fn reroute_from_exits(
+    map: &mut Map,
+    mut routed: Map,
+    station_id: StationID,
+    assignment: ExitAssignment,
+    occupied: OccupiedNodes,
+    settings: AlgorithmSettings,
+) -> Result<()> {
+    let station_pos = routed
+        .get_station(station_id)
+        .ok_or(Error::other(
+            "station to reroute from not found",
+        ))?
+        .get_pos();
+    let edge_ids = assignment
+        .iter()
+        .map(|(edge, _)| edge.get_id())
+        .collect::<Vec<EdgeID>>();
+
+    // Everything is settled where it is, with the edges to move cut short to
+    // only their assigned node, so the routing of one edge approaches the
+    // station around the directions of all others.
+    for station in routed.get_mut_stations() {
+        station.settle(station.get_pos());
+    }
+    for edge in routed.get_mut_edges() {
+        edge.settle();
+    }
+    for (edge, exit) in &assignment {
+        routed
+            .get_mut_edge(edge.get_id())
+            .expect("edge was found before")
+            .set_nodes(vec![*exit]);
+    }
+
+    // The edges are required to leave the station through their assigned node
+    // while they are rerouted, so the routing keeps to the new directions.
+    let edges = assignment
+        .into_iter()
+        .map(|(mut edge, exit)| {
+            edge.unsettle();
+            edge.set_required_direction(
+                station_id,
+                Some(node_direction(station_pos, exit)),
+            );
+            edge
+        })
+        .collect();
+
+    route_edges(
+        AlgorithmSettings {
+            allow_station_relocation: false,
+            ..settings
+        },
+        &mut routed,
+        edges,
+        occupied,
+        Updater::NoUpdates,
+    )
+    .now_or_never()
+    .ok_or(Error::other(
+        "edge routing did not finish",
+    ))??;
+
+    // Only the new routes are taken over, so the stations and the directions
+    // required by the user stay as they were.
+    for edge_id in edge_ids {
+        let nodes = routed
+            .get_edge(edge_id)
+            .expect("edge was routed")
+            .get_nodes()
+            .to_vec();
+        map.get_mut_edge(edge_id)
+            .expect("edge was found before")
+            .set_nodes(nodes);
+    }
+
+    Ok(())
+}
+
+/// Redistribute the directions in which the edges leave the given station, so
+/// they use the directions around it evenly instead of bunching up on one side,
+/// and reroute those edges from their new direction.
+///
+/// Every edge gets its own direction, as close as possible to the direction of
+/// the station at its other end, while keeping the edges in the same order
+/// around the station. Locked edges, edges to a neighboring station and edges
+/// required to leave in a certain direction keep their direction, and
+/// neighboring nodes taken up by other stations or edges are not used. The
+/// edges are rerouted like the algorithm does, around all occupied nodes.
+///
+/// Returns an error if there are more edges to move than free directions, or
+/// if an edge can not be routed from its new direction.
+pub fn spread_station_exits(
+    map: &mut Map,
+    station_id: StationID,
+    settings: AlgorithmSettings,
+) -> Result<()> {
+    let station = map
+        .get_station(station_id)
+        .ok_or_else(|| {
+            Error::other(format!(
+                "station {station_id} not found"
+            ))
+        })?;
+    let station_pos = station.get_pos();
+    let neighbors = station_pos.get_neighbors_for(settings.grid_mode);
+
+    // The edges that can be moved, together with the position of the station at
+    // their other end.
+    let mut movable: Vec<(Edge, GridNode)> = Vec::new();
+    for edge_id in station.get_edges() {
+        let edge = map
+            .get_edge(*edge_id)
+            .ok_or(Error::other(
+                "edge connected to station not found",
+            ))?;
+        let opposite_pos = map
+            .get_station(
+                edge.opposite(station_id)
+                    .ok_or(Error::other(
+                        "edge connected to station does not end at it",
+                    ))?,
+            )
+            .ok_or(Error::other(
+                "station on connected edge not found",
+            ))?
+            .get_pos();
+
+        if !edge.is_locked()
+            && !neighbors.contains(&opposite_pos)
+            && edge
+                .get_required_direction(station_id)
+                .is_none()
+        {
+            movable.push((edge.clone(), opposite_pos));
+        }
+    }
+
+    if movable.is_empty() {
+        return Ok(());
+    }
+
+    // Every node still occupied once the routes of the edges to move are
+    // taken off the map can not be used as their new direction, including
+    // those of the edges that stay and of other edges passing by the station.
+    let mut routed = map.clone();
+    for (edge, _) in &movable {
+        routed
+            .get_mut_edge(edge.get_id())
+            .expect("edge was found before")
+            .set_nodes(Vec::new());
+    }
+    let occupied = routed.get_occupied_nodes();
+
+    let free = neighbors
+        .into_iter()
+        .filter(|node| !occupied.contains_key(node))
+        .collect::<Vec<_>>();
+    if free.len() < movable.len() {
+        return Err(Error::other(format!(
+            "station {station_id} has {} edges to spread out, but only {} free directions",
+            movable.len(),
+            free.len()
+        )));
+    }
+
+    // The edges are required to leave the station through their assigned node
+    // while they are rerouted, so the routing keeps to the new directions.
+    let assignment = assign_exits(
+        settings.grid_mode,
+        station_pos,
+        movable,
+        &free,
+    );
+    reroute_from_exits(
+        map, routed, station_id, assignment, occupied, settings,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::run_a_star;
+
+    /// Get the node next to the given station that the given edge leaves it
+    /// through.
+    fn exit_of(map: &Map, station: StationID, edge: EdgeID) -> GridNode {
+        let edge = map
+            .get_edge(edge)
+            .unwrap();
+        if edge.get_from() == station {
+            edge.get_nodes()[0]
+        } else {
+            *edge
+                .get_nodes()
+                .last()
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn test_spread_station_exits() {
+        let mut map = Map::new();
+        let center = map.add_named_station(&"Center", (5, 5).into());
+        let others = [(10, 4), (10, 5), (10, 6), (10, 7)]
+            .map(|pos| map.add_named_station(&"Other", pos.into()));
+
+        // All edges leave the station to the right, bunched up on one side.
+        let mut edges = Vec::new();
+        for other in others {
+            map.add_line_through((255, 0, 0), &"Red", &[center, other]);
+            let edge_id = map
+                .get_edge_id_between_if_exists(center, other)
+                .unwrap();
+            let target = map
+                .get_station(other)
+                .unwrap()
+                .get_pos();
+
+            let mut nodes = vec![GridNode::from((6, 5))];
+            nodes.extend(run_a_star(
+                (6, 5).into(),
+                target,
+                GridMode::Octilinear,
+                true,
+            ));
+            let edge = map
+                .get_mut_edge(edge_id)
+                .unwrap();
+            if edge.get_from() != center {
+                nodes.reverse();
+            }
+            edge.set_nodes(nodes);
+            edges.push(edge_id);
+        }
+
+        let mut spread = map.clone();
+        spread_station_exits(
+            &mut spread,
+            center,
+            AlgorithmSettings::default(),
+        )
+        .unwrap();
+
+        let exits = edges
+            .iter()
+            .map(|edge| exit_of(&spread, center, *edge))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            exits,
+            [(6, 4), (6, 5), (6, 6), (5, 6)].map(GridNode::from)
+        );
+
+        // A locked edge keeps its route, and its direction is not used by the
+        // other edges.
+        let locked_nodes = map
+            .get_edge(edges[0])
+            .unwrap()
+            .get_nodes()
+            .to_vec();
+        map.get_mut_edge(edges[0])
+            .unwrap()
+            .lock();
+        spread_station_exits(
+            &mut map,
+            center,
+            AlgorithmSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            map.get_edge(edges[0])
+                .unwrap()
+                .get_nodes(),
+            locked_nodes
+        );
+        let exits = edges
+            .iter()
+            .map(|edge| exit_of(&map, center, *edge))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            exits,
+            [(6, 5), (6, 4), (6, 6), (5, 6)].map(GridNode::from)
+        );
+    }
+
+    #[test]
+    fn test_spread_station_exits_crossed_neighbors() {
+        let mut map = Map::new();
+        let center = map.add_named_station(&"Center", (5, 5).into());
+        for pos in [(10, 5), (10, 6), (10, 7), (10, 8)] {
+            let far = map.add_named_station(&"Far", pos.into());
+            map.add_line_through((255, 0, 0), &"Red", &[center, far]);
+        }
+
+        // Another line passes right above the station, through three of its
+        // neighboring nodes.
+        let west = map.add_named_station(&"West", (2, 4).into());
+        let east = map.add_named_station(&"East", (8, 4).into());
+        map.add_line_through((0, 0, 255), &"Blue", &[west, east]);
+        let passing = map
+            .get_edge_id_between_if_exists(west, east)
+            .unwrap();
+        let passing_nodes = (3..=7)
+            .map(|x| GridNode::from((x, 4)))
+            .collect::<Vec<_>>();
+        map.get_mut_edge(passing)
+            .unwrap()
+            .set_nodes(passing_nodes.clone());
+
+        spread_station_exits(
+            &mut map,
+            center,
+            AlgorithmSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            map.get_edge(passing)
+                .unwrap()
+                .get_nodes(),
+            passing_nodes
+        );
+        let station = map
+            .get_station(center)
+            .unwrap();
+        for edge_id in station.get_edges() {
+            let exit = exit_of(&map, center, *edge_id);
+            assert!([(4, 5), (4, 6), (5, 6), (6, 5), (6, 6)]
+                .map(GridNode::from)
+                .contains(&exit));
+            assert!(!map
+                .get_edge(*edge_id)
+                .unwrap()
+                .get_nodes()
+                .iter()
+                .any(|node| passing_nodes.contains(node)));
+        }
+    }
+
+    #[test]
+    fn test_spread_station_exits_no_room() {
+        let mut map = Map::new();
+        let center = map.add_named_station(&"Center", (5, 5).into());
+        for pos in [(4, 4), (5, 4), (6, 4), (4, 5), (4, 6)] {
+            map.add_named_station(&"Near", pos.into());
+        }
+        for pos in [(10, 2), (10, 5), (10, 8), (5, 10)] {
+            let far = map.add_named_station(&"Far", pos.into());
+            map.add_line_through((255, 0, 0), &"Red", &[center, far]);
+        }
+
+        assert!(spread_station_exits(
+            &mut map,
+            center,
+            AlgorithmSettings::default()
+        )
+        .is_err());
+    }
+}
@@ -10,7 +10,10 @@ mod occupation;
 mod utils;
 
 pub use a_star::run_a_star;
-use calc_direction::EdgeDirection;
+pub use calc_direction::{
+    octilinear_direction,
+    EdgeDirection,
+};
 pub use drawing::*;
 pub use line_straightening::*;
 pub use map_layout::*;
@@ -27,5 +30,6 @@ use utils::{
     node_outside_grid,
     overlap_amount,
     randomize_edges,
+    randomize_edges_locally,
     unsettle_map,
 };
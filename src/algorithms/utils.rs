@@ -14,6 +14,7 @@ use serde::{
 use super::AlgorithmSettings;
 use crate::models::{
     Edge,
+    EdgeID,
     GridNode,
     Map,
 };
@@ -31,6 +32,7 @@ pub fn unsettle_map(map: &mut Map) {
         if !edge.is_locked() {
             edge.unsettle();
             edge.set_nodes(Vec::new());
+            edge.clear_manually_edited();
         }
     }
 }
@@ -43,6 +45,45 @@ pub fn randomize_edges(edges: &mut [Edge], attempt: u64) {
     edges.shuffle(&mut rng);
 }
 
+/// Randomizes the order of only the given involved edges, keeping all other
+/// edges in their place. The first involved edge is moved to the earliest of
+/// their positions, so it gets routed before the edges that blocked it.
+/// Attempt will be used as the seed for the random number generator when
+/// shuffeling.
+pub fn randomize_edges_locally(edges: &mut [Edge], involved: &[EdgeID], attempt: u64) {
+    let positions = edges
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| involved.contains(&e.get_id()))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mut subset = positions
+        .iter()
+        .map(|i| edges[*i].clone())
+        .collect::<Vec<_>>();
+
+    let mut rng = SmallRng::seed_from_u64(attempt);
+    subset.shuffle(&mut rng);
+
+    if let Some(first) = involved
+        .first()
+        .and_then(|id| {
+            subset
+                .iter()
+                .position(|e| e.get_id() == *id)
+        })
+    {
+        subset[..=first].rotate_right(1);
+    }
+
+    for (i, edge) in positions
+        .into_iter()
+        .zip(subset)
+    {
+        edges[i] = edge;
+    }
+}
+
 /// Returns true if the given node is outside the grid limits.
 pub fn node_outside_grid(settings: AlgorithmSettings, node: GridNode) -> bool {
     node.0
@@ -70,29 +111,59 @@ pub fn overlap_amount<T: PartialEq>(left: &[T], right: &[T]) -> usize {
         .count()
 }
 
-/// The different types of log messages.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The different types of log messages, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum LogType {
     Debug,
+    Info,
     Warn,
     Error,
 }
 
-/// Prints a debug message if the settings allow it.
+impl LogType {
+    /// All log levels, from least to most severe.
+    pub const ALL: [Self; 4] = [
+        Self::Debug,
+        Self::Info,
+        Self::Warn,
+        Self::Error,
+    ];
+
+    /// The name of the log level as it is shown to the user.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Debug => "Debug",
+            Self::Info => "Info",
+            Self::Warn => "Warn",
+            Self::Error => "Error",
+        }
+    }
+
+    /// Get the log level with the given name, if it is valid.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str() == name)
+    }
+}
+
+/// Prints a log message if it is at least as severe as the log level in the
+/// settings.
 pub fn log_print(settings: AlgorithmSettings, msg: &str, log_type: LogType) {
+    if log_type < settings.log_level {
+        return;
+    }
+
     match log_type {
-        LogType::Debug if settings.log_level == LogType::Debug => {
+        LogType::Debug | LogType::Info => {
             logging::log!("{}", msg);
         },
-        LogType::Warn
-            if settings.log_level == LogType::Debug || settings.log_level == LogType::Warn =>
-        {
+        LogType::Warn => {
             logging::warn!("{}", msg);
         },
         LogType::Error => {
             logging::error!("{}", msg);
         },
-        _ => {},
     }
 }
 
@@ -132,10 +203,56 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_randomize_edges_locally() {
+        let mut edges = (0..6)
+            .map(|i| {
+                Edge::new(
+                    0.into(),
+                    1.into(),
+                    Some(EdgeID::from(i)),
+                )
+            })
+            .collect::<Vec<_>>();
+        let involved = [
+            EdgeID::from(4),
+            EdgeID::from(1),
+            EdgeID::from(5),
+        ];
+
+        randomize_edges_locally(&mut edges, &involved, 3);
+
+        let ids = edges
+            .iter()
+            .map(|e| u64::from(e.get_id()))
+            .collect::<Vec<_>>();
+        assert_eq!(ids[0], 0);
+        assert_eq!(ids[1], 4);
+        assert_eq!(ids[2], 2);
+        assert_eq!(ids[3], 3);
+
+        let mut rest = vec![ids[4], ids[5]];
+        rest.sort_unstable();
+        assert_eq!(rest, vec![1, 5]);
+    }
+
     #[test]
     fn test_overlap_amount() {
         let left = vec![1, 2, 3, 0, 4, 5, 2];
         let right = vec![8, 3, 4, 10, 5, 6, 7];
         assert_eq!(overlap_amount(&left, &right), 3);
     }
+
+    #[test]
+    fn test_log_type_names() {
+        for level in LogType::ALL {
+            assert_eq!(
+                LogType::from_name(level.as_str()),
+                Some(level)
+            );
+        }
+        assert_eq!(LogType::from_name("Verbose"), None);
+        assert!(LogType::Debug < LogType::Info);
+        assert!(LogType::Warn < LogType::Error);
+    }
 }
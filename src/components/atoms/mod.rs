@@ -6,6 +6,7 @@ mod canvas_info_box;
 mod link_icon;
 mod modal;
 mod number_input;
+mod select;
 mod text_with_edit;
 mod toggle;
 
@@ -21,5 +22,6 @@ pub use link_icon::{
 };
 pub use modal::Modal;
 pub use number_input::NumberInput;
+pub use select::Select;
 pub use text_with_edit::TextWithEdit;
 pub use toggle::Toggle;
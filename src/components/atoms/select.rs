@@ -0,0 +1,49 @@
+//! Contains the [`Select`] component.
+
+use leptos::prelude::*;
+
+/// A dropdown html element for picking one of a list of options.
+#[component]
+pub fn Select<F, V>(
+    /// The label on the dropdown.
+    text: &'static str,
+    /// The options that can be picked.
+    options: Vec<&'static str>,
+    /// Gets called with the picked option when the selection is changed.
+    on_change: F,
+    /// Gets called to set the currently picked option.
+    #[prop(optional)]
+    value: Option<V>,
+) -> impl IntoView
+where
+    F: Fn(String) + 'static,
+    V: (Fn() -> &'static str) + Copy + Send + 'static,
+{
+    let id = text
+        .to_lowercase()
+        .replace(' ', "_");
+
+    let parse_change = move |ev| on_change(event_target_value(&ev));
+
+    view! {
+    <div class="relative mb-3" data-twe-input-wrapper-init>
+      <select
+        class="peer block min-h-[auto] w-full rounded border-b-2 rounded-md border-solid border-blue-400 bg-transparent px-3 pt-[0.9rem] pb-[0.32rem] leading-[1.6] outline-none transition-all duration-200 ease-linear motion-reduce:transition-none dark:text-white dark:bg-neutral-700 dark:border-blue-600 focus:border-blue-600 dark:focus:border-blue-800"
+        id={id.clone()}
+        on:change=parse_change
+        prop:value=move || value.map(|v| v())>
+        {options
+            .into_iter()
+            .map(|option| view! {
+                <option value=option selected=move || value.is_some_and(|v| v() == option)>{option}</option>
+            })
+            .collect_view()}
+      </select>
+      <label
+        for={id}
+        class="pointer-events-none absolute left-3 top-0 mb-0 max-w-[90%] origin-[0_0] truncate pt-[0.37rem] leading-[1.6] text-neutral-500 peer-focus:text-primary -translate-y-[0.9rem] scale-[0.8] dark:text-neutral-400 dark:peer-focus:text-primary"
+        >{text}
+      </label>
+    </div>
+        }
+}
@@ -0,0 +1,121 @@
+//! Contains the drawing of large maps in chunks spread over multiple animation
+//! frames, for browsers that can not draw them offscreen.
+//!
+//! Drawing a map with thousands of edges at once blocks the page, so the map is
+//! drawn a chunk at a time with [`redraw_canvas_step`], showing the parts
+//! drawn so far in between. The selections are drawn on top once the whole map
+//! is done. The values about the whole map needed to draw its parts are kept
+//! in a [`StepCache`] for all chunks of a drawing. Every change to the map
+//! state starts a new drawing, which stops the one still in progress so they
+//! never draw over each other.
+
+use leptos::{
+    html::Canvas as HtmlCanvas,
+    prelude::*,
+};
+
+use crate::{
+    algorithms::{
+        redraw_canvas_step,
+        CanvasContext,
+        DrawStep,
+        StepCache,
+    },
+    MapState,
+};
+
+/// The combined amount of stations and edges from which a map is drawn in
+/// chunks instead of all at once.
+const CHUNKED_THRESHOLD: usize = 1000;
+
+/// The amount of edges, lines, interchanges and stations drawn per animation
+/// frame.
+const CHUNK_SIZE: usize = 250;
+
+/// Keeps track of the drawing in progress, so it can be stopped once a newer
+/// one is started.
+#[derive(Debug, Default)]
+pub struct ChunkedRenderer {
+    /// Counts up every time a drawing is started or cancelled. A drawing stops
+    /// once its generation is no longer the current one.
+    generation: u64,
+}
+
+impl ChunkedRenderer {
+    /// Check if the map of the given state is large enough to be worth drawing
+    /// in chunks.
+    pub fn should_render(state: &MapState) -> bool {
+        let map = state.get_map();
+        map.get_stations()
+            .len()
+            + map
+                .get_edges()
+                .len()
+            >= CHUNKED_THRESHOLD
+    }
+
+    /// Stop the drawing that is currently in progress, if any.
+    pub fn cancel(&mut self) {
+        self.generation += 1;
+    }
+}
+
+/// Draw the given state onto the canvas in chunks, one chunk per animation
+/// frame. The canvas is expected to be cleared already, and any drawing that is
+/// still in progress is stopped.
+pub fn render_chunked(
+    renderer: StoredValue<ChunkedRenderer, LocalStorage>,
+    state: MapState,
+    canvas_ref: NodeRef<HtmlCanvas>,
+) {
+    let Some(generation) = renderer.try_update_value(|r| {
+        r.cancel();
+        r.generation
+    }) else {
+        return;
+    };
+
+    let cache = StepCache::new(&state);
+    draw_chunk(
+        renderer,
+        state,
+        cache,
+        canvas_ref,
+        generation,
+        DrawStep::Grid,
+    );
+}
+
+/// Draw the given step of the state and schedule the next one for the next
+/// animation frame, unless a newer drawing has been started in the meantime.
+fn draw_chunk(
+    renderer: StoredValue<ChunkedRenderer, LocalStorage>,
+    state: MapState,
+    cache: StepCache,
+    canvas_ref: NodeRef<HtmlCanvas>,
+    generation: u64,
+    step: DrawStep,
+) {
+    if renderer.try_with_value(|r| r.generation) != Some(generation) {
+        return;
+    }
+    let Some(canvas) = canvas_ref.get_untracked() else {
+        return;
+    };
+
+    let Some(next) = redraw_canvas_step(
+        &CanvasContext::from(&canvas),
+        &state,
+        step,
+        CHUNK_SIZE,
+        &cache,
+    ) else {
+        return;
+    };
+
+    request_animation_frame(move || {
+        draw_chunk(
+            renderer, state, cache, canvas_ref, generation, next,
+        );
+    });
+}
@@ -17,6 +17,10 @@ use crate::{
 ///
 /// [dblclick]: https://developer.mozilla.org/en-US/docs/Web/API/Element/dblclick_event
 pub fn on_dbl_click(map_state: &mut MapState, ev: &UiEvent, shift_key: bool) {
+    if map_state.is_read_only() {
+        return;
+    }
+
     if !shift_key {
         map_state.clear_all_selections();
     }
@@ -4,41 +4,96 @@ use leptos::prelude::*;
 use web_sys::KeyboardEvent;
 
 use crate::{
-    components::HistoryState,
+    components::state::{
+        is_text_field_focused,
+        InteractionState,
+        KeyAction,
+    },
     MapState,
 };
 
-/// Listener for the [keydown] event on the canvas.
+/// Listener for the [keydown] event on the canvas element itself, so it only
+/// fires while the canvas has focus. Tab and Shift+Tab move the focus through
+/// the stations instead of to the next element on the page.
 ///
 /// [keydown]: https://developer.mozilla.org/en-US/docs/Web/API/Element/keydown_event
-pub fn on_keydown(map_state_signal: &RwSignal<MapState>, ev: &KeyboardEvent) {
-    if ev.key() == "Escape" {
-        map_state_signal.update(|map_state| {
-            map_state.clear_all_selections();
-        });
+pub fn on_canvas_keydown(map_state_signal: &RwSignal<MapState>, ev: &KeyboardEvent) {
+    if map_state_signal.with_untracked(MapState::is_read_only) || ev.key() != "Tab" {
+        return;
     }
 
-    if ev.key() == "z" && ev.ctrl_key() {
-        map_state_signal.update(|map_state| {
-            if let Some(map) = HistoryState::undo(
-                map_state
-                    .get_map()
-                    .clone(),
-            ) {
-                map_state.set_map_no_history(map);
-            }
-        });
+    ev.prevent_default();
+    map_state_signal.update(|map_state| {
+        map_state.focus_next_station(ev.shift_key());
+    });
+}
+
+/// Listener for the [keydown] event on the whole page. Looks up the action
+/// bound to the pressed key in the key bindings of the interaction state.
+/// Panning, zooming, fitting the selection and recalculating are handled by the
+/// canvas controls.
+///
+/// [keydown]: https://developer.mozilla.org/en-US/docs/Web/API/Element/keydown_event
+pub fn on_keydown(
+    map_state_signal: &RwSignal<MapState>,
+    interaction_state: &RwSignal<InteractionState>,
+    ev: &KeyboardEvent,
+) {
+    if map_state_signal.with_untracked(MapState::is_read_only) || is_text_field_focused() {
+        return;
     }
 
-    if ev.key() == "Z" && ev.ctrl_key() {
-        map_state_signal.update(|map_state| {
-            if let Some(map) = HistoryState::redo(
-                map_state
-                    .get_map()
-                    .clone(),
-            ) {
-                map_state.set_map_no_history(map);
-            }
-        });
+    let Some(action) = interaction_state.with_untracked(|state| {
+        state
+            .get_key_bindings()
+            .get_event_action(ev)
+    }) else {
+        return;
+    };
+
+    match action {
+        KeyAction::ClearSelection => {
+            map_state_signal.update(|map_state| {
+                map_state.clear_all_selections();
+            });
+        },
+        KeyAction::Undo => {
+            map_state_signal.update(|map_state| {
+                map_state.undo();
+            });
+        },
+        KeyAction::Redo => {
+            map_state_signal.update(|map_state| {
+                map_state.redo();
+            });
+        },
+        KeyAction::DeleteSelection => {
+            map_state_signal.update(|map_state| {
+                let selected = map_state
+                    .get_selected_stations()
+                    .iter()
+                    .map(|s| {
+                        s.get_station()
+                            .get_id()
+                    })
+                    .collect::<Vec<_>>();
+                if selected.is_empty() {
+                    return;
+                }
+
+                map_state.clear_all_selections();
+                let map = map_state.get_mut_map("removed selected stations");
+                for id in selected {
+                    map.remove_station(id);
+                }
+            });
+        },
+        KeyAction::SelectConnected => {
+            map_state_signal.update(MapState::select_connected_to_selection);
+        },
+        KeyAction::CycleLineColor => {
+            map_state_signal.update(MapState::cycle_line_colors);
+        },
+        _ => {},
     }
 }
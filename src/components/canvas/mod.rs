@@ -23,21 +23,35 @@ use crate::components::{
     MapState,
 };
 
+mod chunked;
 mod dbl_click;
 mod keydown;
 mod mouse_down;
 mod mouse_move;
 mod mouse_out;
 mod mouse_up;
+mod offscreen;
 mod other;
 mod scroll;
 
+use chunked::{
+    render_chunked,
+    ChunkedRenderer,
+};
 use dbl_click::on_dbl_click;
-use keydown::on_keydown;
+use keydown::{
+    on_canvas_keydown,
+    on_keydown,
+};
 use mouse_down::on_mouse_down;
 use mouse_move::on_mouse_move;
 use mouse_out::on_mouse_out;
 use mouse_up::on_mouse_up;
+pub use offscreen::render_frames;
+use offscreen::{
+    render_offscreen,
+    OffscreenRenderer,
+};
 use other::update_canvas_size;
 use scroll::on_scroll;
 
@@ -67,7 +81,11 @@ pub fn Canvas() -> impl IntoView {
             DOCUMENT_LOADED.store(true, Ordering::Release);
             let on_resize = Closure::<dyn Fn()>::new(move || update_canvas_size(&map_state));
             let on_keydown = Closure::<dyn Fn(JsValue)>::new(move |ev: JsValue| {
-                on_keydown(&map_state, ev.unchecked_ref());
+                on_keydown(
+                    &map_state,
+                    &interaction_state,
+                    ev.unchecked_ref(),
+                );
             });
             window().set_onresize(Some(
                 on_resize
@@ -84,20 +102,22 @@ pub fn Canvas() -> impl IntoView {
         }
     });
 
+    // draws large maps in a web worker, if the browser supports it.
+    let offscreen_renderer = OffscreenRenderer::new().map(StoredValue::new_local);
+    // draws large maps in chunks if they can't be drawn in a web worker.
+    let chunked_renderer = StoredValue::new_local(ChunkedRenderer::default());
+
     // redraw the canvas if the map state changes.
     Effect::new(move |_| {
         let canvas_node = &canvas_ref
             .get()
             .expect("should be loaded now");
-        let s = map_state
-            .get()
+        let state = map_state.get();
+        let s = state
             .get_canvas_state()
             .get_size();
-        canvas_node.set_height(s.0 as u32);
-        canvas_node.set_width(s.1 as u32);
 
-        let is_dragging = map_state
-            .get()
+        let is_dragging = state
             .get_drag_offset()
             .is_some();
         if is_dragging {
@@ -106,9 +126,30 @@ pub fn Canvas() -> impl IntoView {
             interaction_state.update(|state| state.set_cursor("default"));
         }
 
-        map_state
-            .get()
-            .draw_to_canvas(&canvas_ref);
+        // Any drawing in chunks that is still in progress is of an outdated state.
+        chunked_renderer.update_value(ChunkedRenderer::cancel);
+
+        match offscreen_renderer {
+            Some(renderer) if OffscreenRenderer::should_render(&state) => {
+                // Resizing clears the canvas, so it is only done when needed to keep the
+                // previous frame visible until the worker is done drawing.
+                if canvas_node.height() != s.0 as u32 || canvas_node.width() != s.1 as u32 {
+                    canvas_node.set_height(s.0 as u32);
+                    canvas_node.set_width(s.1 as u32);
+                }
+                render_offscreen(renderer, map_state, canvas_ref);
+            },
+            _ if ChunkedRenderer::should_render(&state) => {
+                canvas_node.set_height(s.0 as u32);
+                canvas_node.set_width(s.1 as u32);
+                render_chunked(chunked_renderer, state, canvas_ref);
+            },
+            _ => {
+                canvas_node.set_height(s.0 as u32);
+                canvas_node.set_width(s.1 as u32);
+                state.draw_to_canvas(&canvas_ref);
+            },
+        }
     });
 
     view! {
@@ -117,19 +158,21 @@ pub fn Canvas() -> impl IntoView {
                 node_ref=canvas_ref
 
                 on:mousedown=move |ev| map_state.update(|state| on_mouse_down(state, ev.as_ref(), ev.shift_key()))
-                on:mouseup=move |ev| map_state.update(|state| on_mouse_up(state, error_state, ev.as_ref(), ev.shift_key()))
+                on:mouseup=move |ev| map_state.update(|state| on_mouse_up(state, error_state, ev.as_ref(), ev.shift_key(), ev.alt_key()))
                 on:mousemove=move |ev| on_mouse_move(&map_state, ev.as_ref())
                 on:mouseout=move |_| map_state.update(on_mouse_out)
                 on:dblclick=move |ev| map_state.update(|state| on_dbl_click(state, ev.as_ref(), ev.shift_key()))
 
                 on:touchstart=move |ev| map_state.update(|state| on_mouse_down(state, ev.as_ref(), ev.shift_key()))
-                on:touchend=move |ev| map_state.update(|state| on_mouse_up(state, error_state, ev.as_ref(), ev.shift_key()))
+                on:touchend=move |ev| map_state.update(|state| on_mouse_up(state, error_state, ev.as_ref(), ev.shift_key(), ev.alt_key()))
                 on:touchmove=move |ev| on_mouse_move(&map_state, ev.as_ref())
                 on:touchcancel=move |_| map_state.update(on_mouse_out)
 
                 on:wheel=move |ev| map_state.update(|state| on_scroll(state, ev.delta_y()))
+                on:keydown=move |ev| on_canvas_keydown(&map_state, &ev)
 
                 id="canvas"
+                tabindex="0"
                 style="touch-action: none;"
                 class="object-contain"/>
         </div>
@@ -2,16 +2,55 @@
 
 use web_sys::UiEvent;
 
-use super::other::canvas_click_pos;
+use super::other::{
+    canvas_click_pos,
+    recalculate_edge_nodes,
+};
 use crate::{
+    algorithms::station_label_at,
     models::{
         GridNode,
+        Map,
         SelectedLine,
         SelectedStation,
+        StationID,
     },
     MapState,
 };
 
+/// Extend the line that is being drawn with the given station, connecting it to
+/// the station that was clicked before it.
+fn extend_drawn_line(map_state: &mut MapState, mut map: Map, station: StationID) {
+    let Some(mut selected_line) = map_state
+        .get_selected_lines()
+        .first()
+        .copied()
+    else {
+        return;
+    };
+
+    // Clicking the last station again, like when double-clicking to finish.
+    let (before, _) = selected_line.get_before_after();
+    if before == Some(station) {
+        return;
+    }
+
+    let mut line = map
+        .get_or_add_line(selected_line.get_line())
+        .clone();
+    line.add_station(&mut map, station, before, None);
+
+    if let Some(before_station) = before {
+        let edge_id = map.get_line_edge_id_between(before_station, station, line.get_id());
+        recalculate_edge_nodes(&mut map, edge_id);
+    }
+
+    map.add_line(line);
+    selected_line.set_before(station);
+    map_state.set_selected_lines(vec![selected_line]);
+    map_state.set_map_no_history(map);
+}
+
 /// Listener for the [mousedown] event on the canvas.
 ///
 /// [mousedown]: https://developer.mozilla.org/en-US/docs/Web/API/Element/mousedown_event
@@ -28,15 +67,30 @@ pub fn on_mouse_down(map_state: &mut MapState, ev: &UiEvent, shift_key: bool) {
         return;
     }
 
+    let canvas_state = map_state.get_canvas_state();
+    let canvas_pos = canvas_click_pos(canvas_state.get_size(), ev);
+
+    // In the read-only viewer the map can only be dragged as a whole.
+    if map_state.is_read_only() {
+        map_state.set_drag_offset(Some((canvas_pos, false)));
+        return;
+    }
+
     let mut map = map_state
         .get_map()
         .clone();
-    let canvas_state = map_state.get_canvas_state();
-    let canvas_pos = canvas_click_pos(canvas_state.get_size(), ev);
     let mouse_pos = GridNode::from_canvas_pos(canvas_pos, canvas_state);
     let station_at_node = map.station_at_node(mouse_pos);
     let edge_at_node = map.edge_at_node(mouse_pos);
 
+    // Handle a click while drawing a new line.
+    if map_state.is_drawing_line() {
+        if let Some(station_at_pos) = station_at_node {
+            extend_drawn_line(map_state, map, station_at_pos);
+        }
+        return;
+    }
+
     // Handle a click while having a new station selected.
     if let Some(selected) = map_state
         .get_selected_stations()
@@ -47,10 +101,15 @@ pub fn on_mouse_down(map_state: &mut MapState, ev: &UiEvent, shift_key: bool) {
             let mut new_station = selected.deselect();
             new_station.set_pos(mouse_pos);
             new_station.set_original_pos(mouse_pos);
+            let label = if new_station.is_checkpoint() {
+                "added checkpoint"
+            } else {
+                "added station"
+            };
 
             map.add_station(new_station);
             map_state.clear_selected_stations();
-            map_state.set_map(map);
+            map_state.set_map(map, label);
             return;
         }
     }
@@ -72,11 +131,22 @@ pub fn on_mouse_down(map_state: &mut MapState, ev: &UiEvent, shift_key: bool) {
                 map.add_line(line);
             }
         }
-        map_state.set_map(map);
+        map_state.set_map(map, "extended line");
         map_state.clear_selected_lines();
         return;
     }
 
+    // Handle a drag starting on the label of a station instead of on the
+    // station itself. Stations and edges beneath the label take precedence.
+    if station_at_node.is_none()
+        && edge_at_node.is_none()
+        && canvas_state.is_labels_shown()
+        && let Some(station) = station_label_at(&map, canvas_state, canvas_pos)
+    {
+        map_state.start_label_drag(station, canvas_pos);
+        return;
+    }
+
     // Handle a click on an edge has been selected
     if let Some(selected_edge) = edge_at_node {
         if map_state
@@ -197,3 +267,71 @@ pub fn on_mouse_down(map_state: &mut MapState, ev: &UiEvent, shift_key: bool) {
         map_state.set_drag_offset(Some((canvas_pos, false)));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Station;
+
+    #[test]
+    fn test_draw_line() {
+        let mut map = Map::new();
+        for (id, pos) in [(1, (0, 0)), (2, (3, 0)), (3, (3, 3))] {
+            map.add_station(Station::new(
+                pos.into(),
+                Some(id.into()),
+            ));
+        }
+        let mut state = MapState::new(map);
+
+        state.start_drawing_line();
+        assert!(state.is_drawing_line());
+        for id in [1, 2, 2, 3] {
+            let map = state
+                .get_map()
+                .clone();
+            extend_drawn_line(&mut state, map, id.into());
+        }
+
+        let line = state
+            .get_map()
+            .get_lines()[0]
+            .clone();
+        assert_eq!(
+            line.get_stations(),
+            &[1.into(), 2.into(), 3.into()]
+        );
+        assert_eq!(
+            line.get_edges()
+                .len(),
+            2
+        );
+
+        // Finishing stores the whole line as a single change.
+        state.clear_all_selections();
+        assert!(!state.is_drawing_line());
+        assert!(state.undo());
+        assert!(
+            state
+                .get_map()
+                .get_lines()
+                .is_empty()
+        );
+        assert!(!state.undo());
+
+        // A line without any edges is thrown away.
+        state.start_drawing_line();
+        let map = state
+            .get_map()
+            .clone();
+        extend_drawn_line(&mut state, map, 1.into());
+        state.finish_drawing_line();
+        assert!(
+            state
+                .get_map()
+                .get_lines()
+                .is_empty()
+        );
+        assert!(!state.undo());
+    }
+}
@@ -19,6 +19,16 @@ pub fn on_mouse_move(map_state_signal: &RwSignal<MapState>, ev: &UiEvent) {
     let canvas_pos = canvas_click_pos(canvas_state.get_size(), ev);
     let mouse_pos = GridNode::from_canvas_pos(canvas_pos, canvas_state);
 
+    // Handle a station label getting dragged.
+    if map_state
+        .get_dragged_label()
+        .is_some()
+    {
+        map_state.move_dragged_label(canvas_pos);
+        map_state_signal.set(map_state);
+        return;
+    }
+
     // Handle move of selected line.
     if !map_state
         .get_selected_lines()
@@ -9,4 +9,5 @@ pub fn on_mouse_out(map_state: &mut MapState) {
     map_state.clear_selected_lines();
     map_state.clear_box_select();
     map_state.clear_drag_offset();
+    map_state.cancel_label_drag();
 }
@@ -1,5 +1,6 @@
 //! Contains the mouseup event handler for the [`Canvas`] component.
 
+use itertools::Itertools;
 use leptos::prelude::{
     RwSignal,
     Update,
@@ -11,19 +12,28 @@ use super::other::{
     recalculate_edge_nodes,
 };
 use crate::{
+    algorithms::octilinear_direction,
     components::{
         state::ActionType,
         ErrorState,
     },
     models::{
         GridNode,
+        Map,
         SelectedLine,
         SelectedStation,
+        StationID,
     },
     MapState,
 };
 
-/// Listener for the [mouseup] event on the canvas.
+/// The furthest a dragged station is moved, in grid nodes, to snap it onto the
+/// octilinear line between its two neighbors.
+const SNAP_DISTANCE: i32 = 1;
+
+/// Listener for the [mouseup] event on the canvas. Holding alt while dropping
+/// a dragged station places it exactly where it was dropped, instead of
+/// snapping it onto the line between its neighbors.
 ///
 /// [mouseup]: https://developer.mozilla.org/en-US/docs/Web/API/Element/mouseup_event
 pub fn on_mouse_up(
@@ -31,6 +41,7 @@ pub fn on_mouse_up(
     error_state: RwSignal<ErrorState>,
     ev: &UiEvent,
     shift_key: bool,
+    alt_key: bool,
 ) {
     if ev.detail() > 1 {
         return;
@@ -46,6 +57,15 @@ pub fn on_mouse_up(
     let station_at_node = map.station_at_node(mouse_pos);
     let edge_at_node = map.edge_at_node(mouse_pos);
 
+    // Drop a dragged station label where the mouse was released.
+    if map_state
+        .get_dragged_label()
+        .is_some()
+    {
+        map_state.drop_dragged_label(canvas_pos);
+        return;
+    }
+
     // if we were dragging, we aren't anymore now.
     if map_state
         .get_drag_offset()
@@ -54,6 +74,11 @@ pub fn on_mouse_up(
         map_state.clear_drag_offset();
     }
 
+    // Stations are added to a line being drawn on mousedown already.
+    if map_state.is_read_only() || map_state.is_drawing_line() {
+        return;
+    }
+
     // Handle a click while having an operation selected
     if let Some(action_type) = map_state.get_selected_action() {
         match action_type {
@@ -95,7 +120,7 @@ pub fn on_mouse_up(
                 }
             },
         }
-        map_state.set_map(map);
+        map_state.set_map(map, action_type.history_label());
         if !shift_key {
             map_state.clear_selected_action();
         }
@@ -118,12 +143,20 @@ pub fn on_mouse_up(
                 line.add_station(&mut map, station_at_pos, before, after);
 
                 if let Some(before_station) = before {
-                    let edge_id = map.get_edge_id_between(before_station, station_at_pos);
+                    let edge_id = map.get_line_edge_id_between(
+                        before_station,
+                        station_at_pos,
+                        line.get_id(),
+                    );
                     recalculate_edge_nodes(&mut map, edge_id);
                 }
 
                 if let Some(after_station) = after {
-                    let edge_id = map.get_edge_id_between(station_at_pos, after_station);
+                    let edge_id = map.get_line_edge_id_between(
+                        station_at_pos,
+                        after_station,
+                        line.get_id(),
+                    );
                     recalculate_edge_nodes(&mut map, edge_id);
                 }
 
@@ -134,7 +167,7 @@ pub fn on_mouse_up(
 
         if added_station {
             map_state.clear_all_selections();
-            map_state.set_map(map);
+            map_state.set_map(map, "added station to line");
             return;
         }
 
@@ -207,31 +240,7 @@ pub fn on_mouse_up(
             .iter()
             .any(SelectedStation::has_moved)
     {
-        for selected_station in map_state
-            .get_selected_stations()
-            .iter()
-            .cloned()
-            .map(SelectedStation::deselect)
-        {
-            let station = map
-                .get_mut_station(selected_station.get_id())
-                .expect("selected station does not exist");
-
-            if station.get_pos() == selected_station.get_pos() {
-                continue;
-            }
-
-            station.set_pos(selected_station.get_pos());
-            station.set_original_pos(selected_station.get_pos());
-            station.lock();
-
-            for edge_id in selected_station.get_edges() {
-                recalculate_edge_nodes(&mut map, *edge_id);
-            }
-        }
-
-        map_state.set_map(map);
-        map_state.clear_all_selections();
+        commit_moved_stations(map_state, map, !alt_key);
         return;
     }
 
@@ -295,3 +304,277 @@ pub fn on_mouse_up(
         map_state.clear_all_selections();
     }
 }
+
+/// Find the node close to the given position that lies on octilinear segments
+/// to both of the given neighbors of the station, so a station dropped between
+/// them keeps the line straight. Nodes in line with both neighbors are
+/// preferred over ones with a bend between them, after that the node closest
+/// to the position wins. Returns `None` if there are not exactly two
+/// neighbors or no such node is free and close enough.
+fn snap_between(
+    map: &Map,
+    station: StationID,
+    pos: GridNode,
+    neighbors: &[StationID],
+) -> Option<GridNode> {
+    let [first, second] = neighbors else {
+        return None;
+    };
+    let first = map
+        .get_station(*first)?
+        .get_pos();
+    let second = map
+        .get_station(*second)?
+        .get_pos();
+
+    (-SNAP_DISTANCE..=SNAP_DISTANCE)
+        .cartesian_product(-SNAP_DISTANCE..=SNAP_DISTANCE)
+        .map(|offset| pos + offset.into())
+        .filter(|node| {
+            map.station_at_node(*node)
+                .is_none_or(|id| id == station)
+        })
+        .filter_map(|node| {
+            let towards = octilinear_direction(first, node)?;
+            let onwards = octilinear_direction(node, second)?;
+            Some((
+                towards != onwards,
+                node.diagonal_distance_to(pos),
+                node,
+            ))
+        })
+        .min_by(
+            |(bend_a, dist_a, _), (bend_b, dist_b, _)| {
+                bend_a
+                    .cmp(bend_b)
+                    .then(dist_a.total_cmp(dist_b))
+            },
+        )
+        .map(|(_, _, node)| node)
+}
+
+/// Moves all selected stations to where they were dragged to and reroutes
+/// their edges on the given map, which is then stored as a single change in
+/// the redo/undo history. Afterwards everything is deselected.
+///
+/// If snapping is enabled and a single station was dragged, it is snapped
+/// onto the line between its two neighbors when it was dropped close to it.
+fn commit_moved_stations(map_state: &mut MapState, mut map: Map, snap: bool) {
+    // A group of stations keeps its shape, so only a single one is snapped.
+    let snap = snap
+        && map_state
+            .get_selected_stations()
+            .len()
+            == 1;
+
+    for selected_station in map_state
+        .get_selected_stations()
+        .to_vec()
+    {
+        let id = selected_station
+            .get_station()
+            .get_id();
+        let mut pos = selected_station.get_pos();
+        if snap {
+            let (before, after) = selected_station.get_before_after();
+            let neighbors = before
+                .iter()
+                .chain(after)
+                .copied()
+                .unique()
+                .collect::<Vec<_>>();
+            pos = snap_between(&map, id, pos, &neighbors).unwrap_or(pos);
+        }
+
+        let selected_station = selected_station.deselect();
+        let station = map
+            .get_mut_station(id)
+            .expect("selected station does not exist");
+
+        if station.get_pos() == pos {
+            continue;
+        }
+
+        station.set_pos(pos);
+        station.set_original_pos(pos);
+        station.lock();
+
+        for edge_id in selected_station.get_edges() {
+            recalculate_edge_nodes(&mut map, *edge_id);
+        }
+    }
+
+    map_state.set_map(map, "moved station");
+    map_state.clear_all_selections();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Station,
+        StationID,
+    };
+
+    #[test]
+    fn test_commit_moved_stations_single_undo() {
+        let mut map = Map::new();
+        let ids: Vec<StationID> = vec![1.into(), 2.into(), 3.into()];
+        for (i, id) in ids
+            .iter()
+            .enumerate()
+        {
+            map.add_station(Station::new(
+                (i as i32 * 4, 0).into(),
+                Some(*id),
+            ));
+        }
+        map.get_edge_id_between(ids[0], ids[1]);
+        map.get_edge_id_between(ids[1], ids[2]);
+
+        let mut map_state = MapState::new(map);
+        for id in &ids {
+            let station = map_state
+                .get_map()
+                .get_station(*id)
+                .cloned()
+                .unwrap();
+            map_state.select_station(SelectedStation::new(station));
+        }
+
+        // Drag all three stations down by two nodes, in several steps.
+        for offset in 1..=2 {
+            for selected in map_state.get_mut_selected_stations() {
+                let original = selected
+                    .get_original_position()
+                    .unwrap();
+                selected.update_pos(original + GridNode(0, offset));
+            }
+        }
+
+        let map = map_state
+            .get_map()
+            .clone();
+        commit_moved_stations(&mut map_state, map, true);
+
+        let positions = |state: &MapState| {
+            ids.iter()
+                .map(|id| {
+                    state
+                        .get_map()
+                        .get_station(*id)
+                        .unwrap()
+                        .get_pos()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            positions(&map_state),
+            vec![
+                GridNode(0, 2),
+                GridNode(4, 2),
+                GridNode(8, 2)
+            ]
+        );
+
+        assert!(map_state.undo());
+        assert_eq!(
+            positions(&map_state),
+            vec![
+                GridNode(0, 0),
+                GridNode(4, 0),
+                GridNode(8, 0)
+            ]
+        );
+        assert!(!map_state.undo());
+    }
+
+    /// Create a map with three stations in a row, of which the middle one is
+    /// selected so it can be dragged.
+    fn dragged_middle_station() -> MapState {
+        let mut map = Map::new();
+        for (id, x) in [(1, 0), (2, 2), (3, 4)] {
+            map.add_station(Station::new(
+                (x, 0).into(),
+                Some(StationID::from(id)),
+            ));
+        }
+        map.get_edge_id_between(1.into(), 2.into());
+        map.get_edge_id_between(2.into(), 3.into());
+
+        let mut selected = SelectedStation::new(
+            map.get_station(2.into())
+                .cloned()
+                .unwrap(),
+        );
+        selected.add_before(1.into());
+        selected.add_after(3.into());
+
+        let mut map_state = MapState::new(map);
+        map_state.select_station(selected);
+        map_state
+    }
+
+    #[test]
+    fn test_snap_between() {
+        let map_state = dragged_middle_station();
+        let map = map_state.get_map();
+        let neighbors = [1.into(), 3.into()];
+
+        // Close to the line it snaps back onto it.
+        assert_eq!(
+            snap_between(
+                map,
+                2.into(),
+                GridNode(3, 1),
+                &neighbors
+            ),
+            Some(GridNode(3, 0))
+        );
+        // Too far away from any octilinear position.
+        assert_eq!(
+            snap_between(
+                map,
+                2.into(),
+                GridNode(2, 5),
+                &neighbors
+            ),
+            None
+        );
+        // Only one neighbor.
+        assert_eq!(
+            snap_between(
+                map,
+                2.into(),
+                GridNode(3, 1),
+                &neighbors[..1]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_commit_moved_station_snaps() {
+        for (snap, expected) in [
+            (true, GridNode(3, 0)),
+            (false, GridNode(3, 1)),
+        ] {
+            let mut map_state = dragged_middle_station();
+            map_state.get_mut_selected_stations()[0].update_pos(GridNode(3, 1));
+
+            let map = map_state
+                .get_map()
+                .clone();
+            commit_moved_stations(&mut map_state, map, snap);
+
+            assert_eq!(
+                map_state
+                    .get_map()
+                    .get_station(2.into())
+                    .unwrap()
+                    .get_pos(),
+                expected
+            );
+        }
+    }
+}
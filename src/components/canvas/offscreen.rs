@@ -0,0 +1,347 @@
+//! Contains the rendering of large maps to an [`OffscreenCanvas`] in a web
+//! worker, so redrawing them does not block panning and zooming.
+//!
+//! Only the map layers, see [`MapLayers`], are drawn in the worker. The
+//! resulting bitmap is drawn onto the canvas on the main thread, after which
+//! the selection highlights are drawn on top of it from the current state. The
+//! worker keeps the maps it was sent last, so they are only sent again once
+//! they changed, and not for every pan or zoom. If
+//! the browser has no support for web workers or a 2d offscreen canvas context,
+//! the canvas is drawn on the main thread instead, in chunks for large maps
+//! and with [`MapState::draw_to_canvas`] for small ones.
+//!
+//! The frames of recorded animations are drawn in the same worker, see
+//! [`render_frames`].
+
+// Async is used for futures, which are used in the worker, even though the drawing itself is
+// sync.
+#![allow(clippy::unused_async)]
+// This otherwise gets triggered by one in the wasm worker.
+#![allow(unexpected_cfgs)]
+
+use std::cell::RefCell;
+
+use leptos::{
+    html::Canvas as HtmlCanvas,
+    logging,
+    prelude::*,
+    task::spawn_local,
+};
+use leptos_workers::{
+    executors::PoolExecutor,
+    worker,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    js_sys::Uint8Array,
+    Blob,
+    HtmlCanvasElement,
+    ImageBitmap,
+    ImageEncodeOptions,
+    OffscreenCanvas,
+};
+
+use crate::{
+    algorithms::{
+        draw_selections,
+        CanvasContext,
+        DrawCache,
+        LayerOptions,
+        MapLayers,
+    },
+    Error,
+    MapState,
+};
+
+/// The combined amount of stations and edges from which a map is drawn
+/// offscreen instead of on the main thread.
+const OFFSCREEN_THRESHOLD: usize = 400;
+
+/// A request for the [`RenderWorker`] to draw the map layers.
+#[derive(Clone, Serialize, Deserialize)]
+struct RenderRequest {
+    /// The maps to draw, or `None` if they did not change since they were last
+    /// sent to the worker.
+    layers: Option<MapLayers>,
+    /// The settings to draw the map layers with.
+    options: LayerOptions,
+}
+
+thread_local! {
+    /// The maps last sent to the worker, which are drawn for requests that do
+    /// not contain any.
+    static LAYERS: RefCell<Option<MapLayers>> = const { RefCell::new(None) };
+}
+
+/// The map layers drawn by the [`RenderWorker`].
+#[derive(Clone, Serialize, Deserialize)]
+struct RenderedLayers {
+    /// The bitmap the layers were drawn to.
+    #[serde(with = "leptos_workers::transferable")]
+    bitmap: ImageBitmap,
+}
+
+/// The worker that draws the map layers to an offscreen canvas.
+#[allow(dead_code)] // usage is hidden
+#[worker(RenderWorker)]
+async fn render_layers(req: RenderRequest) -> RenderedLayers {
+    let (height, width) = req
+        .options
+        .get_canvas_state()
+        .get_size();
+    let canvas = OffscreenCanvas::new(width as u32, height as u32)
+        .expect("failed to create offscreen canvas");
+
+    LAYERS.with_borrow_mut(|layers| {
+        if let Some(sent) = req.layers {
+            *layers = Some(sent);
+        }
+        if let Some(layers) = layers {
+            layers.draw(
+                &CanvasContext::from(&canvas),
+                req.options,
+            );
+        }
+    });
+
+    RenderedLayers {
+        bitmap: canvas
+            .transfer_to_image_bitmap()
+            .expect("failed to transfer offscreen canvas to bitmap"),
+    }
+}
+
+/// Check if the browser can draw to a 2d offscreen canvas inside a web worker.
+fn is_supported() -> bool {
+    let has_workers = js_sys::Reflect::has(
+        &js_sys::global(),
+        &JsValue::from_str("Worker"),
+    )
+    .unwrap_or(false);
+
+    has_workers
+        && OffscreenCanvas::new(1, 1)
+            .ok()
+            .and_then(|canvas| {
+                canvas
+                    .get_context("2d")
+                    .ok()
+                    .flatten()
+            })
+            .is_some()
+}
+
+/// Keeps track of the worker drawing the map layers, making sure only one
+/// drawing is in progress at a time.
+pub struct OffscreenRenderer {
+    /// The executor running the [`RenderWorker`].
+    executor: PoolExecutor<RenderWorker>,
+    /// If the worker is currently drawing the map layers.
+    in_flight: bool,
+    /// If the map state changed while the worker was drawing, so the layers
+    /// need to be drawn again once it is done.
+    pending: bool,
+    /// The revision of the map state whose maps were last sent to the worker,
+    /// see [`MapState::get_map_revision`].
+    sent_revision: Option<u64>,
+}
+
+impl OffscreenRenderer {
+    /// Create a new [`OffscreenRenderer`], or `None` if the browser does not
+    /// support offscreen rendering.
+    pub fn new() -> Option<Self> {
+        if !is_supported() {
+            return None;
+        }
+
+        PoolExecutor::<RenderWorker>::new(1)
+            .inspect_err(|e| logging::warn!("offscreen rendering unavailable: {e:?}"))
+            .ok()
+            .map(|executor| {
+                Self {
+                    executor,
+                    in_flight: false,
+                    pending: false,
+                    sent_revision: None,
+                }
+            })
+    }
+
+    /// Check if the map of the given state is large enough to be worth drawing
+    /// offscreen.
+    pub fn should_render(state: &MapState) -> bool {
+        let map = state.get_map();
+        map.get_stations()
+            .len()
+            + map
+                .get_edges()
+                .len()
+            >= OFFSCREEN_THRESHOLD
+    }
+}
+
+/// Draw the map layers of the current state in the worker, and afterwards draw
+/// the result and the selection highlights onto the canvas. If a drawing is
+/// already in progress, another one is started with the then current state
+/// once it is done.
+pub fn render_offscreen(
+    renderer: StoredValue<OffscreenRenderer, LocalStorage>,
+    map_state: RwSignal<MapState>,
+    canvas_ref: NodeRef<HtmlCanvas>,
+) {
+    let busy = renderer
+        .try_update_value(|r| {
+            if r.in_flight {
+                r.pending = true;
+            }
+            std::mem::replace(&mut r.in_flight, true)
+        })
+        .unwrap_or(true);
+    if busy {
+        return;
+    }
+
+    let revision = map_state.with_untracked(MapState::get_map_revision);
+    let unchanged = renderer
+        .try_with_value(|r| r.sent_revision == Some(revision))
+        .unwrap_or(false);
+    let request = map_state.with_untracked(|state| {
+        RenderRequest {
+            layers: (!unchanged).then(|| MapLayers::from_state(state)),
+            options: LayerOptions::from_state(state),
+        }
+    });
+    let canvas_state = request
+        .options
+        .get_canvas_state();
+
+    let Some(Ok((_, result))) = renderer.try_update_value(|r| {
+        r.sent_revision = Some(revision);
+        r.executor
+            .run(request)
+    }) else {
+        logging::error!("failed to start offscreen rendering");
+        renderer.update_value(|r| {
+            r.in_flight = false;
+            r.sent_revision = None;
+        });
+        return;
+    };
+
+    spawn_local(async move {
+        let drawn = result.await;
+
+        let state = map_state.get_untracked();
+
+        // A bitmap of a different size than the canvas would be stretched, so
+        // it is skipped and the layers are drawn again.
+        if state
+            .get_canvas_state()
+            .get_size()
+            == canvas_state.get_size()
+        {
+            if let Some(canvas) = canvas_ref.get_untracked() {
+                draw_rendered_layers(&canvas, &drawn, &state);
+            }
+        } else {
+            renderer.update_value(|r| r.pending = true);
+        }
+
+        let pending = renderer
+            .try_update_value(|r| {
+                r.in_flight = false;
+                std::mem::take(&mut r.pending)
+            })
+            .unwrap_or(false);
+        if pending {
+            render_offscreen(renderer, map_state, canvas_ref);
+        }
+    });
+}
+
+/// Replace the contents of the canvas with the rendered map layers and draw
+/// the selection highlights of the given state on top of them.
+fn draw_rendered_layers(canvas: &HtmlCanvasElement, rendered: &RenderedLayers, state: &MapState) {
+    let context = CanvasContext::from(canvas);
+    let (height, width) = state
+        .get_canvas_state()
+        .get_size();
+
+    context.clear_rect(0.0, 0.0, width, height);
+    if let Err(e) = context.draw_image_with_image_bitmap(&rendered.bitmap, 0.0, 0.0) {
+        logging::error!("failed to draw offscreen bitmap: {e:?}");
+    }
+
+    draw_selections(
+        &context,
+        state,
+        &DrawCache::new(state.get_canvas_state()),
+    );
+}
+
+/// Draw the map layers of every given frame with the given settings and encode
+/// each of them as a PNG image. The frames are drawn one after another in a
+/// [`RenderWorker`] of their own, so recording an animation does not block the
+/// page, or on the main thread if the browser does not support that.
+pub async fn render_frames(
+    frames: Vec<MapLayers>,
+    options: LayerOptions,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let (height, width) = options
+        .get_canvas_state()
+        .get_size();
+    let executor = is_supported()
+        .then(|| {
+            PoolExecutor::<RenderWorker>::new(1)
+                .inspect_err(|e| logging::warn!("offscreen rendering unavailable: {e:?}"))
+                .ok()
+        })
+        .flatten();
+
+    let encode_options = ImageEncodeOptions::new();
+    encode_options.set_type("image/png");
+
+    let mut images = Vec::with_capacity(frames.len());
+    for layers in frames {
+        let canvas = OffscreenCanvas::new(width as u32, height as u32)?;
+        let context = CanvasContext::from(&canvas);
+
+        if let Some(executor) = &executor {
+            let (_, result) = executor
+                .run(RenderRequest {
+                    layers: Some(layers),
+                    options,
+                })
+                .map_err(|e| {
+                    Error::other(format!(
+                        "failed to start offscreen rendering: {e:?}"
+                    ))
+                })?;
+            context.draw_image_with_image_bitmap(
+                &result
+                    .await
+                    .bitmap,
+                0.0,
+                0.0,
+            )?;
+        } else {
+            layers.draw(&context, options);
+        }
+
+        let blob = JsFuture::from(canvas.convert_to_blob_with_options(&encode_options)?)
+            .await?
+            .dyn_into::<Blob>()?;
+        let buffer = JsFuture::from(blob.array_buffer()).await?;
+        images.push(Uint8Array::new(&buffer).to_vec());
+    }
+
+    Ok(images)
+}
@@ -4,7 +4,10 @@ use leptos::{
     logging,
     prelude::*,
 };
-use web_sys::UiEvent;
+use web_sys::{
+    Element,
+    UiEvent,
+};
 
 use crate::{
     models::{
@@ -30,20 +33,12 @@ pub fn update_canvas_size(map_state: &RwSignal<MapState>) {
         .unwrap()
         .round();
 
-    let navbar = doc
+    // the read-only viewer has no navbar.
+    let nav_height = doc
         .get_element_by_id("navbar")
-        .expect("navbar should exist");
-    let nav_height_px = window()
-        .get_computed_style(&navbar)
-        .unwrap()
-        .expect("should have style")
-        .get_property_value("height")
-        .expect("should have height property");
-    let nav_height = nav_height_px
-        .trim_end_matches("px")
-        .parse::<f64>()
-        .expect("height should be a number")
-        .round();
+        .map_or(0.0, |navbar| {
+            computed_size(&navbar, "height")
+        });
 
     let height = win_height - nav_height;
 
@@ -55,20 +50,12 @@ pub fn update_canvas_size(map_state: &RwSignal<MapState>) {
         .unwrap()
         .round();
 
-    let sidebar = doc
+    // the read-only viewer has no sidebar either.
+    let side_width = doc
         .get_element_by_id("sidebar")
-        .expect("sidebar should exist");
-    let side_width_px = window()
-        .get_computed_style(&sidebar)
-        .unwrap()
-        .expect("should have style")
-        .get_property_value("width")
-        .expect("should have width property");
-    let side_width = side_width_px
-        .trim_end_matches("px")
-        .parse::<f64>()
-        .expect("width should be a number")
-        .round();
+        .map_or(0.0, |sidebar| {
+            computed_size(&sidebar, "width")
+        });
 
     let width = win_width - side_width;
 
@@ -86,6 +73,21 @@ pub fn update_canvas_size(map_state: &RwSignal<MapState>) {
     });
 }
 
+/// Gets the computed size in pixels of the given property, like its height or
+/// width, of the given element.
+fn computed_size(element: &Element, property: &str) -> f64 {
+    window()
+        .get_computed_style(element)
+        .unwrap()
+        .expect("should have style")
+        .get_property_value(property)
+        .expect("should have the size property")
+        .trim_end_matches("px")
+        .parse::<f64>()
+        .expect("size should be a number")
+        .round()
+}
+
 /// Gets the position on the canvas that was clicked.
 pub fn canvas_click_pos(map_size: (f64, f64), ev: &UiEvent) -> (f64, f64) {
     let win_height = window()
@@ -10,7 +10,11 @@ mod organisms;
 mod pages;
 mod state;
 
-pub use pages::Home;
+pub use pages::{
+    viewer_map_url,
+    Home,
+    Viewer,
+};
 pub use state::{
     CanvasState,
     ErrorState,
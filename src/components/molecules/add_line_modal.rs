@@ -0,0 +1,109 @@
+//! Contains the [`AddLineModal`] component.
+
+use leptos::prelude::*;
+
+use crate::{
+    components::{
+        atoms::{
+            Button,
+            Modal,
+        },
+        MapState,
+    },
+    models::LINE_PALETTE,
+    utils::{
+        color_to_hex,
+        parse_color,
+    },
+};
+
+/// A modal asking the user for the name and color of a new line, after which
+/// the line is drawn by clicking on stations in order.
+#[component]
+pub fn AddLineModal<C>(
+    /// If the modal should be shown.
+    show: ReadSignal<bool>,
+    /// Gets called if the modal is closed.
+    on_close: C,
+) -> impl IntoView
+where
+    C: Fn() + Send + Clone + 'static,
+{
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let (name, set_name) = signal(String::new());
+    let (color, set_color) = signal(String::new());
+
+    // Start every time the modal opens with an empty name and the next
+    // suggested color.
+    Effect::new(move |_| {
+        if show.get() {
+            let line_count = map_state.with_untracked(|state| {
+                state
+                    .get_map()
+                    .get_lines()
+                    .len()
+            });
+            set_name(String::new());
+            set_color(color_to_hex(
+                LINE_PALETTE[line_count % LINE_PALETTE.len()],
+            ));
+        }
+    });
+
+    let on_confirm = {
+        let on_close = on_close.clone();
+        move |_| {
+            let color = parse_color(&color.get_untracked()).unwrap_or_default();
+            map_state.update(|state| {
+                state.start_drawing_named_line(&name.get_untracked(), color);
+            });
+            on_close();
+        }
+    };
+
+    view! {
+        <Modal show=show on_close=on_close.clone()>
+            // body
+            <div class="p-4 md:p-5 space-y-4">
+                <h2 class="text-lg font-semibold text-black dark:text-white">"Add line"</h2>
+                <div class="relative mb-3">
+                    <input
+                        type="text"
+                        maxlength="100"
+                        class="peer block min-h-[auto] w-full rounded border-b-2 rounded-md border-solid border-blue-400 bg-transparent px-3 pt-[0.9rem] pb-[0.32rem] leading-[1.6] outline-none transition-all duration-200 ease-linear motion-reduce:transition-none dark:text-white dark:border-blue-600 focus:border-blue-600 dark:focus:border-blue-800"
+                        id="add_line_name"
+                        on:input=move |ev| set_name(event_target_value(&ev))
+                        prop:value=name />
+                    <label
+                        for="add_line_name"
+                        class="pointer-events-none absolute left-3 top-0 mb-0 max-w-[90%] origin-[0_0] truncate pt-[0.37rem] leading-[1.6] text-neutral-500 peer-focus:text-primary -translate-y-[0.9rem] scale-[0.8] dark:text-neutral-400 dark:peer-focus:text-primary"
+                        >"Line name"
+                    </label>
+                </div>
+                <label class="flex items-center gap-x-2 text-neutral-500 dark:text-neutral-400">
+                    <input
+                        type="color"
+                        class="h-8 w-12 cursor-pointer rounded bg-transparent"
+                        on:input=move |ev| set_color(event_target_value(&ev))
+                        prop:value=color />
+                    "Line color"
+                </label>
+                <Show when=move || name.get().trim().is_empty()>
+                    <p class="text-sm text-amber-700 dark:text-amber-300">
+                        "The line has no name yet, so it is listed as unnamed and left out of the line name labels."
+                    </p>
+                </Show>
+                <p class="text-neutral-500 dark:text-neutral-400">
+                    "After adding the line, click on its stations in order to draw it."
+                </p>
+            </div>
+            // footer
+            <div class="flex items-center gap-x-2 p-4 md:p-5 border-t border-gray-200 rounded-b dark:border-gray-600">
+                <Button text="Start drawing" on_click=Box::new(on_confirm)/>
+                <Button text="Cancel" outlined=true on_click=Box::new(move |_| on_close())/>
+            </div>
+        </Modal>
+    }
+}
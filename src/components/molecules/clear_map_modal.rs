@@ -0,0 +1,51 @@
+//! Contains the [`ClearMapModal`] component.
+
+use leptos::prelude::*;
+
+use crate::components::{
+    atoms::{
+        Button,
+        Modal,
+    },
+    MapState,
+};
+
+/// A modal asking the user to confirm they want to clear the whole map.
+#[component]
+pub fn ClearMapModal<C>(
+    /// If the modal should be shown.
+    show: ReadSignal<bool>,
+    /// Gets called if the modal is closed.
+    on_close: C,
+) -> impl IntoView
+where
+    C: Fn() + Send + Clone + 'static,
+{
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let on_confirm = {
+        let on_close = on_close.clone();
+        move |_| {
+            map_state.update(MapState::clear_map);
+            on_close();
+        }
+    };
+
+    view! {
+        <Modal show=show on_close=on_close.clone()>
+            // body
+            <div class="p-4 md:p-5 space-y-4">
+                <h2 class="text-lg font-semibold text-black dark:text-white">"Clear map"</h2>
+                <p class="text-neutral-500 dark:text-neutral-400">
+                    "This removes all stations, lines and edges from the map. It can be undone afterwards."
+                </p>
+            </div>
+            // footer
+            <div class="flex items-center gap-x-2 p-4 md:p-5 border-t border-gray-200 rounded-b dark:border-gray-600">
+                <Button text="Clear" danger=true on_click=Box::new(on_confirm)/>
+                <Button text="Cancel" outlined=true on_click=Box::new(move |_| on_close())/>
+            </div>
+        </Modal>
+    }
+}
@@ -0,0 +1,69 @@
+//! Contains the [`DisplacementPanel`] component.
+
+use leptos::prelude::*;
+
+use crate::{
+    algorithms::DISPLACED_STATION_COUNT,
+    components::MapState,
+    models::StationID,
+};
+
+/// A panel listing the stations that moved the most from their original
+/// position. Clicking a station anchors it at its original position and runs
+/// the algorithm again.
+#[component]
+pub fn DisplacementPanel(
+    /// Gets called with the station that should be anchored before the
+    /// algorithm is run again.
+    on_anchor: Callback<StationID>,
+) -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let displaced = move || {
+        map_state.with(|state| {
+            let map = state.get_map();
+            map.station_displacements()
+                .into_iter()
+                .take(DISPLACED_STATION_COUNT)
+                .filter(|(_, displacement)| *displacement > 0.0)
+                .map(|(id, displacement)| {
+                    let name = map
+                        .get_station(id)
+                        .map(|s| {
+                            s.get_name()
+                                .to_owned()
+                        })
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or_else(|| format!("station {id}"));
+                    (id, name, displacement)
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+
+    view! {
+        <div class="flex flex-col w-56 max-h-64 overflow-y-auto p-2 gap-y-1 rounded-md bg-white dark:bg-neutral-700 text-black dark:text-white shadow-md text-sm">
+            <h2 class="text-md font-semibold px-2">"Displaced Stations"</h2>
+            <Show
+                when=move || !displaced().is_empty()
+                fallback=|| view! { <span class="px-2 text-neutral-500">"No stations have moved."</span> }>
+                {move || {
+                    displaced()
+                        .into_iter()
+                        .map(|(id, name, displacement)| {
+                            view! {
+                                <button
+                                    class="block w-full text-left px-2 py-1 rounded hover:bg-blue-100 dark:hover:bg-neutral-600"
+                                    title="anchor at the original position and recalculate"
+                                    on:click=move |_| on_anchor.run(id)>
+                                    {format!("{name}: moved {displacement:.1}")}
+                                </button>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </Show>
+        </div>
+    }
+}
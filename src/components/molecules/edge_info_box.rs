@@ -3,11 +3,20 @@
 use leptos::prelude::*;
 
 use crate::{
-    components::atoms::{
-        CanvasInfoBox,
-        TextWithEdit,
+    algorithms::EdgeDirection,
+    components::{
+        atoms::{
+            Button,
+            ButtonGroup,
+            ButtonProps,
+            CanvasInfoBox,
+            TextWithEdit,
+            Toggle,
+        },
+        ErrorState,
     },
     models::{
+        Edge,
         Line,
         LineID,
     },
@@ -18,12 +27,33 @@ use crate::{
     MapState,
 };
 
+/// The directions an edge can be required to leave a station in, together
+/// with the label of their button.
+const DIRECTION_CHOICES: [(&str, Option<EdgeDirection>); 9] = [
+    ("Any", None),
+    ("N", Some(EdgeDirection::Up)),
+    ("NE", Some(EdgeDirection::DiagUpRight)),
+    ("E", Some(EdgeDirection::Right)),
+    ("SE", Some(EdgeDirection::DiagDownRight)),
+    ("S", Some(EdgeDirection::Down)),
+    ("SW", Some(EdgeDirection::DiagDownLeft)),
+    ("W", Some(EdgeDirection::Left)),
+    ("NW", Some(EdgeDirection::DiagUpLeft)),
+];
+
 #[component]
 fn LineInfo(
     /// The line to show information about.
     line: Line,
     /// The index of the line in the list of lines.
     i: usize,
+    /// Gets called to lay out the line again with the rest of the map locked.
+    on_relayout: Callback<LineID>,
+    /// Gets called to split the line at the from station, or the to station
+    /// if true, of the clicked edge.
+    on_split: Callback<(LineID, bool)>,
+    /// The names of the from and to station of the clicked edge.
+    end_names: Signal<(String, String)>,
 ) -> impl IntoView {
     let map_state =
         use_context::<RwSignal<MapState>>().expect("to have found the global map state");
@@ -33,7 +63,7 @@ fn LineInfo(
         if let Ok(color) = parse_color(&new_color) {
             map_state.update(|state| {
                 if let Some(line) = state
-                    .get_mut_map()
+                    .get_mut_map("changed line color")
                     .get_mut_line(line_id)
                 {
                     line.set_color(color);
@@ -45,7 +75,7 @@ fn LineInfo(
     let edit_line_name = move |line_id: LineID, new_name: String| {
         map_state.update(|state| {
             if let Some(line) = state
-                .get_mut_map()
+                .get_mut_map("renamed line")
                 .get_mut_line(line_id)
             {
                 line.set_name(&new_name);
@@ -78,6 +108,20 @@ fn LineInfo(
         )
     };
 
+    let is_order_shown = Signal::derive(move || {
+        map_state
+            .get()
+            .is_station_order_shown(line_id())
+    });
+    let toggle_order = move |_| {
+        map_state.update(|state| {
+            state.set_station_order_shown(
+                line_id(),
+                !is_order_shown.get_untracked(),
+            );
+        });
+    };
+
     view! {
         {
             if i > 0 {
@@ -103,15 +147,48 @@ fn LineInfo(
                     on_edit=move |s| edit_line_color(line_id(), s)/>
             </span>
         </p>
+        <div class="mt-1">
+            <Button
+                text="Re-layout this line"
+                on_click=Box::new(move |_| on_relayout.run(line_id()))
+                smaller=true
+                outlined=true/>
+            <Button
+                text="Show station order"
+                on_click=Box::new(toggle_order)
+                active=is_order_shown
+                smaller=true
+                outlined=true/>
+        </div>
+        <div class="mt-1">
+            <Button
+                text=Signal::derive(move || format!("Split at {}", end_names.get().0))
+                on_click=Box::new(move |_| on_split.run((line_id(), false)))
+                smaller=true
+                outlined=true/>
+            <Button
+                text=Signal::derive(move || format!("Split at {}", end_names.get().1))
+                on_click=Box::new(move |_| on_split.run((line_id(), true)))
+                smaller=true
+                outlined=true/>
+        </div>
     }
 }
 
 /// A canvas info box that shows information about an edge and lets you change
-/// the name and color of the lines that follow it.
+/// the name and color of the lines that follow it, lay out one of those lines
+/// again or split it in two at one of the stations of the edge.
 #[component]
-pub fn EdgeInfoBox() -> impl IntoView {
+pub fn EdgeInfoBox(
+    /// Gets called to lay out the given line again with everything else
+    /// locked, and if the stations it shares with other lines should stay
+    /// fixed.
+    on_relayout_line: Callback<(LineID, bool)>,
+) -> impl IntoView {
     let map_state =
         use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+    let error_state =
+        use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
 
     let edge_was_clicked = move || {
         map_state
@@ -129,21 +206,180 @@ pub fn EdgeInfoBox() -> impl IntoView {
         state
             .get_clicked_on_edge()
             .map_or(Vec::new(), |e| {
-                e.get_lines()
-                    .iter()
-                    .map(|l| {
-                        state
-                            .get_map()
-                            .get_line(*l)
-                            .unwrap()
-                            .clone()
-                    })
+                state
+                    .get_map()
+                    .lines_on_edge(e.get_id())
+                    .into_iter()
+                    .cloned()
                     .collect()
             })
             .into_iter()
             .enumerate()
     });
 
+    // If stations shared with other lines stay in place when re-laying out a
+    // line.
+    let (keep_junctions, set_keep_junctions) = signal(true);
+    let on_relayout = Callback::new(move |line_id: LineID| {
+        on_relayout_line.run((line_id, keep_junctions.get_untracked()));
+    });
+
+    let is_bundled = move || {
+        let state = map_state.get();
+        state
+            .get_clicked_on_edge()
+            .and_then(|e| {
+                state
+                    .get_map()
+                    .get_edge(e.get_id())
+            })
+            .is_some_and(Edge::is_bundled)
+    };
+    let set_bundled = move |bundled: bool| {
+        map_state.update(|state| {
+            let Some(edge_id) = state
+                .get_clicked_on_edge()
+                .map(Edge::get_id)
+            else {
+                return;
+            };
+
+            let map = state.get_mut_map(
+                if bundled {
+                    "bundled corridor"
+                } else {
+                    "unbundled corridor"
+                },
+            );
+            for id in map.corridor_edges(edge_id) {
+                if let Some(edge) = map.get_mut_edge(id) {
+                    edge.set_bundled(bundled);
+                }
+            }
+        });
+    };
+
+    // The station at the from or to end of the clicked edge.
+    let end_station = move |at_to: bool| {
+        map_state
+            .get()
+            .get_clicked_on_edge()
+            .map(
+                |e| {
+                    if at_to { e.get_to() } else { e.get_from() }
+                },
+            )
+    };
+    let end_name = move |at_to: bool| {
+        let state = map_state.get();
+        end_station(at_to)
+            .and_then(|id| {
+                state
+                    .get_map()
+                    .get_station(id)
+            })
+            .map(|station| {
+                if station
+                    .get_name()
+                    .is_empty()
+                {
+                    format!("station {}", station.get_id())
+                } else {
+                    station
+                        .get_name()
+                        .to_owned()
+                }
+            })
+            .unwrap_or_default()
+    };
+    let end_names = Signal::derive(move || (end_name(false), end_name(true)));
+
+    let on_split = Callback::new(
+        move |(line_id, at_to): (LineID, bool)| {
+            let Some(station) = end_station(at_to) else {
+                return;
+            };
+            map_state.update(|state| {
+                let mut map = state
+                    .get_map()
+                    .clone();
+                match map.split_line(line_id, station) {
+                    Ok(_) => {
+                        state.clear_all_selections();
+                        state.set_map(map, "split line");
+                    },
+                    Err(err) => error_state.update(|state| state.set_error(err)),
+                }
+            });
+        },
+    );
+
+    let direction_buttons = move |at_to: bool| {
+        DIRECTION_CHOICES
+            .into_iter()
+            .map(|(text, direction)| {
+                ButtonProps::builder()
+                    .text(text)
+                    .smaller(true)
+                    .on_click(Box::new(move |_| {
+                        let Some(station) = end_station(at_to) else {
+                            return;
+                        };
+                        map_state.update(|state| {
+                            let Some(edge_id) = state
+                                .get_clicked_on_edge()
+                                .map(Edge::get_id)
+                            else {
+                                return;
+                            };
+                            if let Some(edge) = state
+                                .get_mut_map("changed edge direction")
+                                .get_mut_edge(edge_id)
+                            {
+                                edge.set_required_direction(station, direction);
+                            }
+                        });
+                    }))
+                    .active(Signal::derive(move || {
+                        let state = map_state.get();
+                        let required = state
+                            .get_clicked_on_edge()
+                            .and_then(|e| {
+                                state
+                                    .get_map()
+                                    .get_edge(e.get_id())
+                            })
+                            .zip(end_station(at_to))
+                            .and_then(|(edge, station)| edge.get_required_direction(station));
+                        required == direction
+                    }))
+                    .build()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let insert_station = move |_| {
+        map_state.update(|state| {
+            let Some(edge_id) = state
+                .get_clicked_on_edge()
+                .map(Edge::get_id)
+            else {
+                return;
+            };
+
+            let mut map = state
+                .get_map()
+                .clone();
+            match map.insert_station_at_edge_midpoint(edge_id) {
+                Ok(_) => {
+                    state.clear_all_selections();
+                    state.set_map(map, "added station on edge");
+                },
+                Err(err) => error_state.update(|state| state.set_error(err)),
+            }
+        });
+    };
+
     view! {
         <Show when=edge_was_clicked>
             <CanvasInfoBox
@@ -154,11 +390,41 @@ pub fn EdgeInfoBox() -> impl IntoView {
                         key=|(_, line)| line.get_id()
                         children=move |(i, line)| {
                             view!{
-                                <LineInfo line=line i=i/>
+                                <LineInfo
+                                    line=line
+                                    i=i
+                                    on_relayout=on_relayout
+                                    on_split=on_split
+                                    end_names=end_names/>
                             }
                         }
                     />
                 </div>
+                <div class="mt-2">
+                    <Toggle
+                        text="Keep stations shared with other lines fixed when re-laying out a line."
+                        value=move || keep_junctions.get()
+                        on_input=move |b| set_keep_junctions(b)/>
+                </div>
+                <div class="mt-2">
+                    <span class="block text-sm">"Leave "{move || end_name(false)}" towards:"</span>
+                    <ButtonGroup children=direction_buttons(false)/>
+                    <span class="block text-sm mt-1">"Leave "{move || end_name(true)}" towards:"</span>
+                    <ButtonGroup children=direction_buttons(true)/>
+                </div>
+                <div class="mt-2">
+                    <Toggle
+                        text="Bundle the lines along this corridor into a single stroke."
+                        value=is_bundled
+                        on_input=set_bundled/>
+                </div>
+                <div class="mt-2">
+                    <Button
+                        text="Add station at midpoint"
+                        on_click=Box::new(insert_station)
+                        smaller=true
+                        outlined=true/>
+                </div>
             </CanvasInfoBox>
         </Show>
     }
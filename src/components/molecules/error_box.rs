@@ -11,7 +11,8 @@ use crate::{
     Error,
 };
 
-/// A pop-up box for displaying errors.
+/// A pop-up box for displaying errors, and the warnings about skipped elements
+/// of a map file that was only partially loaded.
 #[component]
 pub fn ErrorBox() -> impl IntoView {
     let error_state =
@@ -49,6 +50,24 @@ pub fn ErrorBox() -> impl IntoView {
         err
     };
 
+    let on_warnings_click = move |_| {
+        error_state.update(ErrorState::clear_warnings);
+    };
+
+    let has_warnings = move || {
+        error_state
+            .get()
+            .has_warnings()
+    };
+    let warning_messages = move || {
+        error_state
+            .get()
+            .get_warnings()
+            .iter()
+            .map(|warning| view! { <li>{warning.to_string()}</li> })
+            .collect_view()
+    };
+
     view! {
         <Show when=has_error>
             <div
@@ -64,5 +83,20 @@ pub fn ErrorBox() -> impl IntoView {
                 </div>
             </div>
         </Show>
+        <Show when=has_warnings>
+            <div
+            id="warning-box"
+            tabindex="-1"
+            style:pointer-events="none"
+            class="overflow-y-auto overflow-x-hidden fixed flex top-0 right-0 left-0 z-40 justify-center items-start w-full md:inset-0 h-[calc(100%-1rem)] max-h-full">
+                <div class="mt-3.5 z-40 w-fit min-w-14 max-w-2xl" style:pointer-events="auto" on:click=on_warnings_click>
+                    <div class="bg-amber-400 text-black top-2 z-40 p-3.5 rounded-lg text-base cursor-pointer relative">
+                        <span class="absolute -top-1 right-2">x</span>
+                        <span class="font-semibold">"The map was loaded, but there were problems with some parts:"</span>
+                        <ul class="list-disc pl-5 max-h-48 overflow-y-auto">{warning_messages}</ul>
+                    </div>
+                </div>
+            </div>
+        </Show>
     }
 }
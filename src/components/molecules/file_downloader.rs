@@ -1,16 +1,6 @@
 //! Contains the [`FileDownloader`] component.
 
 use leptos::prelude::*;
-use wasm_bindgen::{
-    JsCast,
-    JsValue,
-};
-use web_sys::{
-    js_sys::Array,
-    Blob,
-    BlobPropertyBag,
-    Url,
-};
 
 use super::FileType;
 use crate::{
@@ -20,7 +10,10 @@ use crate::{
         MapState,
     },
     unwrap_or_return,
-    utils::json::encode_map,
+    utils::{
+        download::download_text,
+        json::encode_map,
+    },
 };
 
 /// A button that lets the user download a file representing the map.
@@ -38,46 +31,30 @@ pub fn FileDownloader() -> impl IntoView {
                 FileType::Json => {
                     let state = map_state.get_untracked();
                     encode_map(
-                        state.get_map(),
+                        &state.get_export_map(),
                         state.get_canvas_state(),
                     )
                 },
-                FileType::GraphML => return,
+                FileType::GraphML | FileType::Adjacency | FileType::GeoJson => return,
             }
         );
-        let options = BlobPropertyBag::new();
-        options.set_type(file_type.to_mime_type());
-
-        let str_sequence = std::iter::once(JsValue::from_str(&encoded)).collect::<Array>();
-        let blob = unwrap_or_return!(
-            error_state,
-            Blob::new_with_str_sequence_and_options(&str_sequence, &options)
-        );
-        let url = unwrap_or_return!(
-            error_state,
-            Url::create_object_url_with_blob(&blob)
-        );
-
-        let elem = unwrap_or_return!(
-            error_state,
-            document().create_element("a")
-        )
-        .dyn_into::<web_sys::HtmlAnchorElement>()
-        .expect("to convert the element to an anchor element");
-
-        elem.set_href(&url);
-        elem.set_download(&format!(
+        let file_name = format!(
             "metro-map.{}",
             match file_type {
                 FileType::Json => "json",
                 FileType::GraphML => "graphml",
+                FileType::Adjacency => "txt",
+                FileType::GeoJson => "geojson",
             }
-        ));
-        elem.click();
+        );
 
         unwrap_or_return!(
             error_state,
-            Url::revoke_object_url(&url)
+            download_text(
+                &encoded,
+                file_type.to_mime_type(),
+                &file_name,
+            )
         );
     };
 
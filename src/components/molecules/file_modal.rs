@@ -6,6 +6,10 @@ use leptos::{
     html::Input,
     prelude::*,
 };
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use wasm_bindgen::{
     closure::Closure,
     JsValue,
@@ -17,6 +21,7 @@ use crate::{
         atoms::{
             Button,
             Modal,
+            Toggle,
         },
         ErrorState,
     },
@@ -25,12 +30,16 @@ use crate::{
 };
 
 /// The accepted file types.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileType {
     /// JSON file type.
     Json,
     /// GraphML file type.
     GraphML,
+    /// Adjacency-list text file type.
+    Adjacency,
+    /// `GeoJSON` file type, containing the shapes to draw behind the map.
+    GeoJson,
 }
 
 impl FileType {
@@ -39,15 +48,22 @@ impl FileType {
         match self {
             FileType::Json => "application/json",
             FileType::GraphML => "application/graphml+xml",
+            FileType::Adjacency => "text/plain",
+            FileType::GeoJson => "application/geo+json",
         }
     }
 }
 
 /// Gets the file uploaded to the input element by the user and passes its
-/// contents to the provided `on_submit` callback function.
-fn get_file<S>(input: &HtmlInputElement, on_submit: S, error_state: RwSignal<ErrorState>)
-where
-    S: Fn(FileType, String) + 'static,
+/// contents to the provided `on_submit` callback function, together with if the
+/// map should be laid out after importing it.
+fn get_file<S>(
+    input: &HtmlInputElement,
+    on_submit: S,
+    auto_layout: bool,
+    error_state: RwSignal<ErrorState>,
+) where
+    S: Fn(FileType, String, bool) + 'static,
 {
     let Some(file) = input
         .files()
@@ -63,6 +79,10 @@ where
         FileType::Json
     } else if file_ext.is_some_and(|ext| ext.eq_ignore_ascii_case("graphml")) {
         FileType::GraphML
+    } else if file_ext.is_some_and(|ext| ext.eq_ignore_ascii_case("txt")) {
+        FileType::Adjacency
+    } else if file_ext.is_some_and(|ext| ext.eq_ignore_ascii_case("geojson")) {
+        FileType::GeoJson
     } else {
         return;
     };
@@ -77,6 +97,7 @@ where
                         "file contents should be a string"
                     ))
             ),
+            auto_layout,
         );
     });
 
@@ -92,20 +113,22 @@ where
 pub fn FileModal<S, C>(
     /// If the modal should be shown.
     show: ReadSignal<bool>,
-    /// Gets called on file submit with the contents of the file.
+    /// Gets called on file submit with the contents of the file and if the map
+    /// should be laid out by the algorithm right after importing it.
     on_submit: S,
     /// Gets called if the modal is closed without submit (the user clicks
     /// outside of the modal).
     on_close: C,
 ) -> impl IntoView
 where
-    S: Fn(FileType, String) + Send + 'static + Copy,
+    S: Fn(FileType, String, bool) + Send + 'static + Copy,
     C: Fn() + 'static,
 {
     let error_state =
         use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
 
     let input_ref: NodeRef<Input> = NodeRef::new();
+    let (auto_layout, set_auto_layout) = signal(false);
 
     view! {
         <Modal show=show on_close=on_close>
@@ -120,12 +143,16 @@ where
                     id="file-form"
                     node_ref=input_ref
                     type="file"
-                    accept=".json, .graphml"
+                    accept=".json, .graphml, .txt, .geojson"
                     class="relative m-0 block w-full min-w-0 flex-auto cursor-pointer rounded border border-solid border-secondary-500 bg-transparent bg-clip-padding px-3 py-[0.32rem] text-base font-normal text-surface transition duration-300 ease-in-out file:-mx-3 file:-my-[0.32rem] file:me-3 file:cursor-pointer file:overflow-hidden file:rounded-none file:border-0 file:border-e file:border-solid file:border-inherit file:bg-transparent file:px-3  file:py-[0.32rem] file:text-surface focus:border-primary focus:text-gray-700 focus:shadow-inset focus:outline-none dark:border-white/70 dark:text-white  file:dark:text-white"/>
+                <Toggle
+                    text="auto-arrange after import"
+                    value=move || auto_layout.get()
+                    on_input=move |b| set_auto_layout(b)/>
             </div>
             // footer
             <div class="flex items-center p-4 md:p-5 border-t border-gray-200 rounded-b dark:border-gray-600">
-                <Button text="Upload File" on_click=Box::new(move |_| get_file(&input_ref.get().unwrap(), on_submit, error_state))/>
+                <Button text="Upload File" on_click=Box::new(move |_| get_file(&input_ref.get().unwrap(), on_submit, auto_layout.get_untracked(), error_state))/>
             </div>
         </Modal>
     }
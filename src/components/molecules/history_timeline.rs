@@ -0,0 +1,83 @@
+//! Contains the [`HistoryTimeline`] component.
+
+use leptos::prelude::*;
+
+use crate::components::{
+    HistoryState,
+    MapState,
+};
+
+/// A list of the recent changes to the map, which allows the user to jump
+/// straight to the state of the map at any of them.
+#[component]
+pub fn HistoryTimeline() -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    // The history is not reactive itself, but every change to it goes together
+    // with a change to the map state.
+    let past_labels = move || map_state.with(|_| HistoryState::get_past_labels());
+    let future_labels = move || {
+        map_state.with(|_| {
+            let mut labels = HistoryState::get_future_labels();
+            labels.reverse();
+            labels
+        })
+    };
+
+    let jump_to_past = move |index: usize| {
+        map_state.update(|state| {
+            state.jump_to_past(index);
+        });
+    };
+    let jump_to_future = move |index: usize| {
+        map_state.update(|state| {
+            state.jump_to_future(index);
+        });
+    };
+
+    let entry_class =
+        "block w-full text-left px-2 py-1 rounded hover:bg-blue-100 dark:hover:bg-neutral-600";
+
+    view! {
+        <div class="flex flex-col w-56 p-2 gap-y-1 rounded-md bg-white dark:bg-neutral-700 text-black dark:text-white shadow-md text-sm">
+            <h2 class="text-md font-semibold px-2">"History"</h2>
+            {move || {
+                let future = future_labels();
+                let count = future.len();
+                future
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, label)| {
+                        let index = count - 1 - i;
+                        view! {
+                            <button
+                                class=format!("{entry_class} text-neutral-400 italic")
+                                title="redo up to this change"
+                                on:click=move |_| jump_to_future(index)>
+                                {label}
+                            </button>
+                        }
+                    })
+                    .collect_view()
+            }}
+            <span class="px-2 py-1 font-semibold text-blue-500">"current map"</span>
+            {move || {
+                past_labels()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, label)| {
+                        view! {
+                            <button
+                                class=entry_class
+                                title="undo up to this change"
+                                on:click=move |_| jump_to_past(index)>
+                                {label}
+                            </button>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,87 @@
+//! Contains the [`LabelOverlapPanel`] component.
+
+use leptos::prelude::*;
+
+use crate::{
+    algorithms::find_overlapping_labels,
+    components::MapState,
+    models::{
+        SelectedStation,
+        StationID,
+    },
+};
+
+/// A panel warning about the pairs of stations whose labels overlap at the
+/// current zoom and label size. Clicking a pair selects both stations and
+/// zooms to them. Nothing is shown if no labels overlap.
+#[component]
+pub fn LabelOverlapPanel() -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let overlapping = Memo::new(move |_| {
+        map_state.with(|state| {
+            let map = state.get_map();
+            find_overlapping_labels(map, state.get_canvas_state())
+                .into_iter()
+                .filter_map(|(first, second)| {
+                    Some((
+                        first,
+                        second,
+                        map.get_station(first)?
+                            .get_name()
+                            .to_owned(),
+                        map.get_station(second)?
+                            .get_name()
+                            .to_owned(),
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let select_pair = move |first: StationID, second: StationID| {
+        map_state.update(|state| {
+            let stations = [first, second]
+                .into_iter()
+                .filter_map(|id| {
+                    state
+                        .get_map()
+                        .get_station(id)
+                        .cloned()
+                })
+                .map(SelectedStation::new)
+                .collect();
+
+            state.clear_all_selections();
+            state.set_selected_stations(stations);
+            state.fit_selection();
+        });
+    };
+
+    view! {
+        <Show when=move || !overlapping.get().is_empty()>
+            <div class="flex flex-col max-h-48 min-h-0 gap-y-1 text-sm">
+                <h2 class="text-md font-semibold px-2">"Overlapping Labels"</h2>
+                <div class="flex flex-col gap-y-1 overflow-y-auto">
+                    {move || {
+                        overlapping
+                            .get()
+                            .into_iter()
+                            .map(|(first, second, first_name, second_name)| {
+                                view! {
+                                    <button
+                                        class="block w-full text-left px-2 py-1 rounded-md bg-white dark:bg-neutral-700 shadow-sm text-amber-700 dark:text-amber-300 hover:bg-blue-100 dark:hover:bg-neutral-600"
+                                        title="select both stations"
+                                        on:click=move |_| select_pair(first, second)>
+                                        {format!("labels of {first_name} and {second_name} overlap")}
+                                    </button>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </div>
+            </div>
+        </Show>
+    }
+}
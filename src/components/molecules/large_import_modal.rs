@@ -0,0 +1,51 @@
+//! Contains the [`LargeImportModal`] component.
+
+use leptos::prelude::*;
+
+use crate::components::atoms::{
+    Button,
+    Modal,
+};
+
+/// A modal telling the user the map they imported is larger than the limit set
+/// in the settings, asking if it should be loaded anyway.
+#[component]
+pub fn LargeImportModal<C>(
+    /// If the modal should be shown.
+    show: ReadSignal<bool>,
+    /// Why the map was not loaded.
+    message: ReadSignal<String>,
+    /// Gets called if the modal is closed.
+    on_close: C,
+    /// Loads the map regardless of its size.
+    on_confirm: Callback<()>,
+) -> impl IntoView
+where
+    C: Fn() + Send + Clone + 'static,
+{
+    let on_proceed = {
+        let on_close = on_close.clone();
+        move |_| {
+            on_close();
+            on_confirm.run(());
+        }
+    };
+
+    view! {
+        <Modal show=show on_close=on_close.clone()>
+            // body
+            <div class="p-4 md:p-5 space-y-4">
+                <h2 class="text-lg font-semibold text-black dark:text-white">"Map too large"</h2>
+                <p class="text-neutral-500 dark:text-neutral-400">{move || message.get()}</p>
+                <p class="text-neutral-500 dark:text-neutral-400">
+                    "Loading it anyway may make the page slow or unresponsive."
+                </p>
+            </div>
+            // footer
+            <div class="flex items-center gap-x-2 p-4 md:p-5 border-t border-gray-200 rounded-b dark:border-gray-600">
+                <Button text="Load anyway" danger=true on_click=Box::new(on_proceed)/>
+                <Button text="Cancel" outlined=true on_click=Box::new(move |_| on_close())/>
+            </div>
+        </Modal>
+    }
+}
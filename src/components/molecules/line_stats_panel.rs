@@ -0,0 +1,213 @@
+//! Contains the [`LineStatsPanel`] component.
+
+use leptos::prelude::*;
+
+use crate::{
+    components::MapState,
+    models::{
+        Line,
+        LineID,
+        LineStats,
+    },
+    utils::color_to_hex,
+};
+
+/// A panel listing every line of the map with its statistics, like the amount
+/// of stations and bends. Clicking on a line selects it and moves the canvas
+/// to it.
+#[component]
+pub fn LineStatsPanel() -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let lines = move || {
+        map_state.with(|state| {
+            let mut lines = state
+                .get_map()
+                .get_lines()
+                .into_iter()
+                .map(Line::get_id)
+                .collect::<Vec<_>>();
+            lines.sort();
+            lines
+        })
+    };
+
+    // The name, color, statistics and service pattern names of a line, so its
+    // row only updates when one of them changes.
+    let line_row = move |id: LineID| {
+        Memo::new(move |_| {
+            map_state.with(|state| {
+                let map = state.get_map();
+                let Some(line) = map.get_line(id) else {
+                    return Default::default();
+                };
+
+                let name = if line
+                    .get_name()
+                    .is_empty()
+                {
+                    "Unnamed".to_owned()
+                } else {
+                    line.get_name()
+                        .to_owned()
+                };
+
+                let patterns = line
+                    .get_patterns()
+                    .iter()
+                    .map(|p| {
+                        p.get_name()
+                            .to_owned()
+                    })
+                    .collect::<Vec<_>>();
+
+                (
+                    name,
+                    color_to_hex(line.get_color()),
+                    line.get_stats(map),
+                    patterns,
+                )
+            })
+        })
+    };
+
+    let focus_line = move |id: LineID| map_state.update(|state| state.focus_line(id));
+    let toggle_pattern = move |id: LineID, index: usize| {
+        map_state.update(|state| {
+            state.update_canvas_state(|canvas| {
+                if canvas.get_active_pattern() == Some((id, index)) {
+                    canvas.set_active_pattern(None);
+                } else {
+                    canvas.set_active_pattern(Some((id, index)));
+                }
+            });
+        });
+    };
+    let active_pattern = move |id: LineID| {
+        Signal::derive(move || {
+            map_state
+                .get()
+                .get_canvas_state()
+                .get_active_pattern()
+                .filter(|(line, _)| *line == id)
+                .map(|(_, index)| index)
+        })
+    };
+
+    view! {
+        <div class="flex flex-col flex-1 min-h-0 gap-y-1 text-sm">
+            <h2 class="text-md font-semibold px-2">"Lines"</h2>
+            <div class="flex flex-col gap-y-1 overflow-y-auto">
+                <For
+                    each=lines
+                    key=|id| *id
+                    children=move |id| {
+                        let row = line_row(id);
+                        view! {
+                            <LineStatsRow
+                                name=Signal::derive(move || row.with(|r| r.0.clone()))
+                                color=Signal::derive(move || row.with(|r| r.1.clone()))
+                                stats=Signal::derive(move || row.with(|r| r.2))
+                                patterns=Signal::derive(move || row.with(|r| r.3.clone()))
+                                active_pattern=active_pattern(id)
+                                on_click=move || focus_line(id)
+                                on_pattern_click=move |index| toggle_pattern(id, index)/>
+                        }
+                    }
+                />
+            </div>
+        </div>
+    }
+}
+
+/// A single row in the [`LineStatsPanel`] showing the statistics of a line.
+/// The service patterns of the line are listed below it, clicking one displays
+/// which stations it stops at.
+#[component]
+fn LineStatsRow<C, P>(
+    /// The name of the line.
+    name: Signal<String>,
+    /// The color of the line as a hex string.
+    color: Signal<String>,
+    /// The statistics of the line.
+    stats: Signal<LineStats>,
+    /// The names of the service patterns of the line.
+    patterns: Signal<Vec<String>>,
+    /// The index of the service pattern of the line that is displayed, if any.
+    active_pattern: Signal<Option<usize>>,
+    /// Gets called when the row is clicked on.
+    on_click: C,
+    /// Gets called with the index of the service pattern that is clicked on.
+    on_pattern_click: P,
+) -> impl IntoView
+where
+    C: Fn() + 'static,
+    P: Fn(usize) + Copy + Send + Sync + 'static,
+{
+    let pattern_buttons = move || {
+        patterns
+            .get()
+            .into_iter()
+            .enumerate()
+            .map(|(index, pattern)| {
+                let pattern = if pattern.is_empty() {
+                    format!("pattern {}", index + 1)
+                } else {
+                    pattern
+                };
+
+                view! {
+                    <button
+                        class=move || {
+                            if active_pattern.get() == Some(index) {
+                                "px-2 rounded-full border border-blue-400 text-xs bg-blue-200 dark:bg-blue-800"
+                            } else {
+                                "px-2 rounded-full border border-blue-400 text-xs hover:bg-blue-100 dark:hover:bg-neutral-600"
+                            }
+                        }
+                        title="show the stations this pattern stops at"
+                        on:click=move |_| on_pattern_click(index)>
+                        {pattern}
+                    </button>
+                }
+            })
+            .collect_view()
+    };
+
+    view! {
+        <div class="flex flex-col rounded-md bg-white dark:bg-neutral-700 shadow-sm">
+        <button
+            class="flex flex-col text-left px-2 py-1 rounded-md hover:bg-blue-100 dark:hover:bg-neutral-600"
+            on:click=move |_| on_click()>
+            <span class="font-semibold">
+                <span class="inline-block w-3 h-3 mr-1 rounded-full" style:background-color=move || color.get()></span>
+                {move || name.get()}
+            </span>
+            <span>
+                {move || {
+                    let stats = stats.get();
+                    format!(
+                        "{} stations, length {:.1}",
+                        stats.station_count,
+                        stats.length,
+                    )
+                }}
+            </span>
+            <span>
+                {move || {
+                    let stats = stats.get();
+                    format!(
+                        "{} bends, {} interchanges",
+                        stats.bend_count,
+                        stats.interchange_count,
+                    )
+                }}
+            </span>
+        </button>
+        <div class="flex flex-wrap gap-1 px-2 empty:hidden">
+            {pattern_buttons}
+        </div>
+        </div>
+    }
+}
@@ -0,0 +1,76 @@
+//! Contains the [`ManualEditsModal`] component.
+
+use leptos::prelude::*;
+
+use crate::components::{
+    atoms::{
+        Button,
+        Modal,
+    },
+    MapState,
+};
+
+/// A modal warning the user that recalculating the map reroutes the edges they
+/// edited by hand, offering to lock those edges first.
+#[component]
+pub fn ManualEditsModal<C>(
+    /// If the modal should be shown.
+    show: ReadSignal<bool>,
+    /// Gets called if the modal is closed.
+    on_close: C,
+    /// Starts the recalculation of the map.
+    on_continue: Callback<()>,
+) -> impl IntoView
+where
+    C: Fn() + Send + Clone + 'static,
+{
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let edited_count = Signal::derive(move || {
+        map_state.with(|state| {
+            state
+                .get_map()
+                .get_unlocked_manual_edges()
+                .len()
+        })
+    });
+
+    let on_lock = {
+        let on_close = on_close.clone();
+        move |_| {
+            map_state.update(MapState::lock_manual_edges);
+            on_close();
+            on_continue.run(());
+        }
+    };
+
+    let on_discard = {
+        let on_close = on_close.clone();
+        move |_| {
+            on_close();
+            on_continue.run(());
+        }
+    };
+
+    view! {
+        <Modal show=show on_close=on_close.clone()>
+            // body
+            <div class="p-4 md:p-5 space-y-4">
+                <h2 class="text-lg font-semibold text-black dark:text-white">"Discard manual edits?"</h2>
+                <p class="text-neutral-500 dark:text-neutral-400">
+                    {move || format!(
+                        "{} edge(s) were edited by hand and are not locked. Recalculating the map will reroute them.",
+                        edited_count.get(),
+                    )}
+                </p>
+            </div>
+            // footer
+            <div class="flex items-center gap-x-2 p-4 md:p-5 border-t border-gray-200 rounded-b dark:border-gray-600">
+                <Button text="Lock my edits and continue" on_click=Box::new(on_lock)/>
+                <Button text="Continue anyway" danger=true on_click=Box::new(on_discard)/>
+                <Button text="Cancel" outlined=true on_click=Box::new(move |_| on_close())/>
+            </div>
+        </Modal>
+    }
+}
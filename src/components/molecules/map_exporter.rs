@@ -6,22 +6,41 @@ use wasm_bindgen_futures::JsFuture;
 use web_sys::{
     ImageEncodeOptions,
     OffscreenCanvas,
-    Url,
 };
 
 use crate::{
     algorithms::redraw_canvas,
     components::{
         atoms::Button,
+        ErrorState,
         MapState,
     },
+    unwrap_or_return,
+    utils::{
+        download::{
+            download_blob,
+            download_text,
+        },
+        json::{
+            encode_map_compact,
+            encode_occupied_nodes,
+            encode_topology,
+        },
+        mermaid::map_to_mermaid,
+    },
 };
 
-/// A button that lets the user export and download the map as a png file.
+/// Buttons that let the user export and download the map as a png file, as
+/// compact JSON, as topology-only JSON or as a Mermaid flowchart. The png file
+/// either shows the whole map or only the part currently visible on the canvas.
+/// After the algorithm has run, the nodes it left occupied can be exported as
+/// JSON as well.
 #[component]
 pub fn MapExporter() -> impl IntoView {
     let map_state =
         use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+    let error_state =
+        use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
 
     let export_map = Action::new_local(move |()| {
         async move {
@@ -50,24 +69,120 @@ pub fn MapExporter() -> impl IntoView {
                 .dyn_into::<web_sys::Blob>()
                 .expect("to convert the promise to a blob");
 
-            let url = Url::create_object_url_with_blob(&blob)
-                .expect("to create an object URL from the blob");
-
-            let elem = document()
-                .create_element("a")
-                .expect("to create an anchor element")
-                .dyn_into::<web_sys::HtmlAnchorElement>()
-                .expect("to convert the element to an anchor element");
-
-            elem.set_href(&url);
-            elem.set_download("metro-map.png");
-            elem.click();
+            unwrap_or_return!(
+                error_state,
+                download_blob(&blob, "metro-map.png")
+            );
+        }
+    });
 
-            Url::revoke_object_url(&url).unwrap();
+    let is_current_view = move || {
+        map_state
+            .get()
+            .get_canvas_state()
+            .is_export_current_view()
+    };
+    let png_area_text = Signal::derive(move || {
+        if is_current_view() {
+            "PNG Of Current View".to_owned()
+        } else {
+            "PNG Of Whole Map".to_owned()
         }
     });
+    let toggle_png_area = move |_| {
+        map_state.update(|state| {
+            state.update_canvas_state(|canvas| {
+                canvas.set_export_current_view(!canvas.is_export_current_view());
+            });
+        });
+    };
+
+    let export_text = move |encoded: String, mime_type: &str, file_name: &str| {
+        unwrap_or_return!(
+            error_state,
+            download_text(&encoded, mime_type, file_name)
+        );
+    };
+
+    let export_compact_json = move |_| {
+        let state = map_state.get_untracked();
+        let encoded = unwrap_or_return!(
+            error_state,
+            encode_map_compact(
+                &state.get_export_map(),
+                state.get_canvas_state(),
+            )
+        );
+
+        export_text(
+            encoded,
+            "application/json",
+            "metro-map.json",
+        );
+    };
+
+    let export_topology = move |_| {
+        let state = map_state.get_untracked();
+        let encoded = unwrap_or_return!(
+            error_state,
+            encode_topology(
+                &state.get_export_map(),
+                state.get_canvas_state(),
+            )
+        );
+
+        export_text(
+            encoded,
+            "application/json",
+            "metro-map-topology.json",
+        );
+    };
+
+    let export_mermaid = move |_| {
+        let encoded = map_to_mermaid(
+            map_state
+                .get_untracked()
+                .get_map(),
+        );
+
+        export_text(
+            encoded,
+            "text/vnd.mermaid",
+            "metro-map.mmd",
+        );
+    };
+
+    let has_occupied = move || {
+        map_state
+            .get()
+            .get_last_occupied()
+            .is_some()
+    };
+    let export_occupied = move |_| {
+        let state = map_state.get_untracked();
+        let Some(occupied) = state.get_last_occupied() else {
+            return;
+        };
+        let encoded = unwrap_or_return!(
+            error_state,
+            encode_occupied_nodes(occupied)
+        );
+
+        export_text(
+            encoded,
+            "application/json",
+            "metro-map-occupied.json",
+        );
+    };
 
     view! {
         <Button text="To PNG" outlined=true can_focus=false on_click=Box::new(move |_| {export_map.dispatch(());})/>
+        <Button text=png_area_text outlined=true can_focus=false active=Signal::derive(is_current_view) on_click=Box::new(toggle_png_area)/>
+        <Button text="To Compact JSON" outlined=true can_focus=false on_click=Box::new(export_compact_json)/>
+        <Button text="To Topology JSON" outlined=true can_focus=false on_click=Box::new(export_topology)/>
+        <Button text="To Mermaid" outlined=true can_focus=false on_click=Box::new(export_mermaid)/>
+        <Show when=has_occupied>
+            <Button text="Occupied Nodes To JSON" outlined=true can_focus=false on_click=Box::new(export_occupied)/>
+        </Show>
     }
 }
@@ -0,0 +1,136 @@
+//! Contains the [`MetricsPanel`] component.
+
+use leptos::prelude::*;
+
+use crate::{
+    components::{
+        atoms::{
+            Button,
+            NumberInput,
+        },
+        MapState,
+    },
+    models::LayoutWeights,
+};
+
+/// A panel showing quality metrics of the current map, like the amount of bends
+/// in its edges, and its overall layout score with configurable weights.
+#[component]
+pub fn MetricsPanel<C>(
+    /// Gets called if the panel is closed.
+    on_close: C,
+) -> impl IntoView
+where
+    C: Fn() + Send + Sync + 'static,
+{
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let bend_count = move || {
+        map_state.with(|state| {
+            state
+                .get_map()
+                .total_bend_count()
+        })
+    };
+    let bend_cost = move || {
+        map_state.with(|state| {
+            format!(
+                "{:.1}",
+                state
+                    .get_map()
+                    .total_bend_cost()
+            )
+        })
+    };
+    let displacement = move || {
+        map_state.with(|state| {
+            let displacements = state
+                .get_map()
+                .station_displacements();
+            let max = displacements
+                .first()
+                .map_or(0.0, |(_, d)| *d);
+            let mean = if displacements.is_empty() {
+                0.0
+            } else {
+                displacements
+                    .iter()
+                    .map(|(_, d)| d)
+                    .sum::<f64>()
+                    / displacements.len() as f64
+            };
+            format!("{max:.1} max, {mean:.1} mean")
+        })
+    };
+
+    let crossing_count = move || {
+        map_state.with(|state| {
+            state
+                .get_map()
+                .layout_score()
+                .crossing_count
+        })
+    };
+    let score = move || {
+        map_state.with(|state| {
+            format!(
+                "{:.1}",
+                state
+                    .get_map()
+                    .layout_score()
+                    .weighted(state.get_layout_weights())
+            )
+        })
+    };
+    let weight = move |get: fn(&LayoutWeights) -> f64| {
+        move || map_state.with(|state| get(&state.get_layout_weights()))
+    };
+    let set_weight = move |set: fn(&mut LayoutWeights, f64)| {
+        move |n: f64| {
+            map_state.update(|state| {
+                let mut weights = state.get_layout_weights();
+                set(&mut weights, n);
+                state.set_layout_weights(weights);
+            });
+        }
+    };
+
+    view! {
+        <div class="flex flex-col w-56 p-2 gap-y-1 rounded-md bg-white dark:bg-neutral-700 text-black dark:text-white shadow-md text-sm">
+            <div class="flex items-center justify-between px-2">
+                <h2 class="text-md font-semibold">"Map Metrics"</h2>
+                <Button text="close" on_click=Box::new(move |_| on_close()) smaller=true outlined=true>
+                    "x"
+                </Button>
+            </div>
+            <span class="px-2"><b>"Bends: "</b>{bend_count}</span>
+            <span class="px-2"><b>"Bend cost: "</b>{bend_cost}</span>
+            <span class="px-2"><b>"Displacement: "</b>{displacement}</span>
+            <span class="px-2"><b>"Crossings: "</b>{crossing_count}</span>
+            <span class="px-2"><b>"Layout score: "</b>{score}</span>
+            <div class="flex flex-col px-2 pt-2">
+                <NumberInput
+                    text="Bend weight"
+                    step=0.1
+                    value=weight(|w| w.bends)
+                    on_input=set_weight(|w, n| w.bends = n)/>
+                <NumberInput
+                    text="Length weight"
+                    step=0.1
+                    value=weight(|w| w.length)
+                    on_input=set_weight(|w, n| w.length = n)/>
+                <NumberInput
+                    text="Crossing weight"
+                    step=0.1
+                    value=weight(|w| w.crossings)
+                    on_input=set_weight(|w, n| w.crossings = n)/>
+                <NumberInput
+                    text="Displacement weight"
+                    step=0.1
+                    value=weight(|w| w.displacement)
+                    on_input=set_weight(|w, n| w.displacement = n)/>
+            </div>
+        </div>
+    }
+}
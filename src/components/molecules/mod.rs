@@ -1,14 +1,30 @@
 //! Contains components that contain child components or have complex state
 //! interactions.
 
+mod add_line_modal;
+mod clear_map_modal;
+mod displacement_panel;
 mod edge_info_box;
 mod error_box;
 mod file_downloader;
 mod file_modal;
+mod history_timeline;
+mod label_overlap_panel;
+mod large_import_modal;
+mod line_stats_panel;
+mod manual_edits_modal;
 mod map_exporter;
+mod metrics_panel;
+mod overlap_panel;
 mod settings_modal;
+mod shortcuts_modal;
 mod station_info_box;
+mod status_bar;
+mod straighten_panel;
 
+pub use add_line_modal::AddLineModal;
+pub use clear_map_modal::ClearMapModal;
+pub use displacement_panel::DisplacementPanel;
 pub use edge_info_box::EdgeInfoBox;
 pub use error_box::ErrorBox;
 pub use file_downloader::FileDownloader;
@@ -16,6 +32,16 @@ pub use file_modal::{
     FileModal,
     FileType,
 };
+pub use history_timeline::HistoryTimeline;
+pub use label_overlap_panel::LabelOverlapPanel;
+pub use large_import_modal::LargeImportModal;
+pub use line_stats_panel::LineStatsPanel;
+pub use manual_edits_modal::ManualEditsModal;
 pub use map_exporter::MapExporter;
+pub use metrics_panel::MetricsPanel;
+pub use overlap_panel::OverlapPanel;
 pub use settings_modal::SettingsModal;
+pub use shortcuts_modal::ShortcutsModal;
 pub use station_info_box::StationInfoBox;
+pub use status_bar::StatusBar;
+pub use straighten_panel::StraightenPanel;
@@ -0,0 +1,56 @@
+//! Contains the [`OverlapPanel`] component.
+
+use leptos::prelude::*;
+
+use crate::{
+    components::MapState,
+    models::EdgeID,
+};
+
+/// A panel listing all pairs of edges that are drawn on top of each other.
+/// Clicking a pair selects both edges.
+#[component]
+pub fn OverlapPanel() -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let overlapping = move || {
+        map_state.with(|state| {
+            state
+                .get_map()
+                .find_overlapping_edges()
+        })
+    };
+
+    let select_pair = move |first: EdgeID, second: EdgeID| {
+        map_state.update(|state| {
+            state.clear_all_selections();
+            state.set_selected_edges(vec![first, second]);
+        });
+    };
+
+    view! {
+        <div class="flex flex-col w-56 max-h-64 overflow-y-auto p-2 gap-y-1 rounded-md bg-white dark:bg-neutral-700 text-black dark:text-white shadow-md text-sm">
+            <h2 class="text-md font-semibold px-2">"Overlapping Edges"</h2>
+            <Show
+                when=move || !overlapping().is_empty()
+                fallback=|| view! { <span class="px-2 text-neutral-500">"No overlapping edges found."</span> }>
+                {move || {
+                    overlapping()
+                        .into_iter()
+                        .map(|(first, second)| {
+                            view! {
+                                <button
+                                    class="block w-full text-left px-2 py-1 rounded hover:bg-blue-100 dark:hover:bg-neutral-600"
+                                    title="select both edges"
+                                    on:click=move |_| select_pair(first, second)>
+                                    {format!("edge {first} and edge {second}")}
+                                </button>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </Show>
+        </div>
+    }
+}
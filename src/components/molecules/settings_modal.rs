@@ -1,18 +1,103 @@
 //! Contains the [`SettingsModal`] component.
 
-use leptos::prelude::*;
+use leptos::{
+    ev::KeyboardEvent,
+    prelude::*,
+};
 
 use crate::{
-    algorithms::LogType,
-    components::atoms::{
-        Button,
-        Modal,
-        NumberInput,
-        Toggle,
+    algorithms::{
+        ColorVision,
+        LabelFont,
+        LogType,
+        NodeSetShape,
+        Theme,
+    },
+    components::{
+        atoms::{
+            Button,
+            ButtonGroup,
+            ButtonProps,
+            Modal,
+            NumberInput,
+            Select,
+            Toggle,
+        },
+        state::{
+            InteractionState,
+            KeyAction,
+            KeyBinding,
+            KeyBindings,
+        },
+        ErrorState,
     },
     MapState,
 };
 
+/// A field showing the key bound to the given action. Pressing a key while the
+/// field has focus binds that key to the action, unless another action already
+/// uses it.
+#[component]
+fn KeyBindingInput(
+    /// The action the key is bound to.
+    action: KeyAction,
+) -> impl IntoView {
+    let interaction_state = use_context::<RwSignal<InteractionState>>()
+        .expect("to have found the global interaction state");
+    let error_state =
+        use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
+
+    let id = format!("key_{action:?}").to_lowercase();
+    let binding = move || {
+        interaction_state
+            .get()
+            .get_key_bindings()
+            .get_binding(action)
+            .map_or_else(
+                || "unbound".to_owned(),
+                ToString::to_string,
+            )
+    };
+
+    let rebind = move |ev: KeyboardEvent| {
+        // Tab still moves the focus to the next field.
+        if ev.key() == "Tab" {
+            return;
+        }
+        ev.prevent_default();
+        ev.stop_propagation();
+
+        let Some(new_binding) = KeyBinding::from_event(&ev) else {
+            return;
+        };
+        interaction_state.update(|state| {
+            if let Err(err) = state
+                .get_mut_key_bindings()
+                .set_binding(action, new_binding)
+            {
+                error_state.update(|state| state.set_error(err));
+            }
+        });
+    };
+
+    view! {
+    <div class="relative mb-3">
+      <input
+        type="text"
+        readonly=true
+        class="peer block min-h-[auto] w-full rounded border-b-2 rounded-md border-solid border-blue-400 bg-transparent px-3 pt-[0.9rem] pb-[0.32rem] leading-[1.6] outline-none transition-all duration-200 ease-linear motion-reduce:transition-none dark:text-white dark:border-blue-600 focus:border-blue-600 dark:focus:border-blue-800 cursor-pointer"
+        id={id.clone()}
+        on:keydown=rebind
+        prop:value=binding />
+      <label
+        for={id}
+        class="pointer-events-none absolute left-3 top-0 mb-0 max-w-[90%] origin-[0_0] truncate pt-[0.37rem] leading-[1.6] text-neutral-500 peer-focus:text-primary -translate-y-[0.9rem] scale-[0.8] dark:text-neutral-400 dark:peer-focus:text-primary"
+        >{action.as_str()}
+      </label>
+    </div>
+    }
+}
+
 /// A modal for changing the settings of the algorithm.
 #[component]
 pub fn SettingsModal<C>(
@@ -26,32 +111,86 @@ where
 {
     let map_state =
         use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+    let interaction_state = use_context::<RwSignal<InteractionState>>()
+        .expect("to have found the global interaction state");
 
     let update_square_size = move |mut n: f64| {
         if n < 1.0 {
             n = 1.0;
         }
 
-        map_state
-            .update(|state| state.update_canvas_state(|canvas| canvas.set_square_size(n as u32)));
+        map_state.update(|state| state.set_square_size(n.round() as u32));
+    };
+
+    let color_vision_button = move |text: &'static str, color_vision: ColorVision| {
+        ButtonProps::builder()
+            .text(text)
+            .on_click(Box::new(move |_| {
+                map_state.update(|state| {
+                    state.update_canvas_state(|canvas| canvas.set_color_vision(color_vision));
+                });
+            }))
+            .active(Signal::derive(move || {
+                map_state
+                    .get()
+                    .get_canvas_state()
+                    .get_color_vision()
+                    == color_vision
+            }))
+            .build()
+    };
+
+    let label_font_button = move |text: &'static str, font: LabelFont| {
+        ButtonProps::builder()
+            .text(text)
+            .on_click(Box::new(move |_| {
+                map_state.update(|state| {
+                    state.update_canvas_state(|canvas| canvas.set_label_font(font));
+                });
+            }))
+            .active(Signal::derive(move || {
+                map_state
+                    .get()
+                    .get_canvas_state()
+                    .get_label_font()
+                    == font
+            }))
+            .build()
+    };
+
+    let halo_color_button = move |text: &'static str, color: (u8, u8, u8)| {
+        ButtonProps::builder()
+            .text(text)
+            .on_click(Box::new(move |_| {
+                map_state.update(|state| {
+                    state.update_canvas_state(|canvas| canvas.set_halo_color(color));
+                });
+            }))
+            .active(Signal::derive(move || {
+                map_state
+                    .get()
+                    .get_canvas_state()
+                    .get_halo_color()
+                    == color
+            }))
+            .build()
     };
 
     view! {
         <Modal show=show on_close=on_close.clone()>
             // body
             <div class="p-4 md:p-5 space-y-4">
-                <Toggle
-                    text="Enable debug output."
-                    value=move || map_state.get().get_algorithm_settings().log_level == LogType::Debug
-                    on_input=move |_| {
-                        map_state
-                            .update(|state| state.update_algorithm_settings(|settings| {
-                                if settings.log_level == LogType::Warn {
-                                    *settings = settings.set_log_level(LogType::Debug);
-                                } else {
-                                    *settings = settings.set_log_level(LogType::Warn);
-                                }
-                            }));
+                <Select
+                    text="Log level"
+                    options=LogType::ALL.into_iter().map(LogType::as_str).collect()
+                    value=move || map_state.get().get_algorithm_settings().log_level.as_str()
+                    on_change=move |name: String| {
+                        if let Some(level) = LogType::from_name(&name) {
+                            map_state
+                                .update(|state| state.update_algorithm_settings(|settings| {
+                                    *settings = settings.set_log_level(level);
+                                }));
+                        }
                     }/>
                 <Toggle
                     text="Enable local search result optimization."
@@ -71,6 +210,28 @@ where
                                 settings.allow_station_relocation = b;
                             }));
                     }/>
+                <Toggle
+                    text="Collapse empty rows and columns after running the algorithm, to reduce the map size."
+                    value=move || map_state.get().get_algorithm_settings().compact_layout
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_algorithm_settings(|settings| {
+                                settings.compact_layout = b;
+                            }));
+                    }/>
+                <Toggle
+                    text="Use a square node-set instead of a diamond for possible station placement."
+                    value=move || map_state.get().get_algorithm_settings().node_set_shape == NodeSetShape::Diagonal
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_algorithm_settings(|settings| {
+                                settings.node_set_shape = if b {
+                                    NodeSetShape::Diagonal
+                                } else {
+                                    NodeSetShape::Manhattan
+                                };
+                            }));
+                    }/>
                 <Toggle
                     text="On failure of the algorithm, output the map at point of failure anyway."
                     value=move || map_state.get().get_algorithm_settings().output_on_fail
@@ -89,12 +250,250 @@ where
                                 settings.iterative_local_search = b;
                             }));
                     }/>
+                <Toggle
+                    text="While recalculating with real-time updates, show the possible station locations considered for every edge before it is routed."
+                    value=move || map_state.get().get_algorithm_settings().debug_node_sets
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_algorithm_settings(|settings| {
+                                settings.debug_node_sets = b;
+                            }));
+                    }/>
+                <Toggle
+                    text="Record recalculations with real-time updates and download them as an animated PNG image."
+                    value=move || map_state.get().get_canvas_state().is_record_animation()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_record_animation(b);
+                            }));
+                    }/>
+                <NumberInput
+                    text="Set the amount of frames per second of recorded recalculations."
+                    min=1.0
+                    max=60.0
+                    step=1.0
+                    value=move || f64::from(map_state.get().get_canvas_state().get_animation_fps())
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_animation_fps(n.round() as u32);
+                            }));
+                    }/>
+                <NumberInput
+                    text="Set the width in pixels of recorded recalculations."
+                    min=100.0
+                    max=3840.0
+                    step=50.0
+                    value=move || f64::from(map_state.get().get_canvas_state().get_animation_width())
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_animation_width(n.round() as u32);
+                            }));
+                    }/>
+                <Toggle
+                    text="Distinguish lines by dash pattern and label in addition to color."
+                    value=move || map_state.get().get_canvas_state().is_color_blind_mode()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_color_blind_mode(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Draw stations with a size proportional to their weight, like ridership."
+                    value=move || map_state.get().get_canvas_state().is_weighted_station_size()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_weighted_station_size(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Show a ruler with the grid coordinates and a scale bar along the edges of the canvas."
+                    value=move || map_state.get().get_canvas_state().is_ruler_shown()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_ruler_shown(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Include the ruler and scale bar when exporting the map as a PNG image."
+                    value=move || map_state.get().get_canvas_state().is_ruler_in_export()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_ruler_in_export(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Limit panning so part of the map always stays visible."
+                    value=move || map_state.get().get_canvas_state().is_pan_limited()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_pan_limited(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Draw short ticks along lines where they pass through stops."
+                    value=move || map_state.get().get_canvas_state().is_stop_ticks_shown()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_stop_ticks_shown(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Bundle lines sharing an edge into a single stroke with a badge showing the amount of lines."
+                    value=move || map_state.get().get_canvas_state().is_bundling_lines()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_bundling_lines(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Draw a halo beneath the lines, so they stand out where they cross each other or the background image."
+                    value=move || map_state.get().get_canvas_state().is_halo_shown()
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_halo_shown(b);
+                            }));
+                    }/>
+                <Toggle
+                    text="Renumber the stations, lines and edges from 1 upwards when downloading or exporting the map as JSON."
+                    value=move || map_state.get().is_export_ids_normalized()
+                    on_input=move |b| {
+                        map_state.update(|state| state.set_export_ids_normalized(b));
+                    }/>
+                <Toggle
+                    text="Keep a history of panning and zooming, which can be stepped through with its own keyboard shortcuts without undoing map changes."
+                    value=move || map_state.get().is_view_history_enabled()
+                    on_input=move |b| {
+                        map_state.update(|state| state.set_view_history_enabled(b));
+                    }/>
+                <Toggle
+                    text="Use a dark theme for the page and the map."
+                    value=move || map_state.get().get_canvas_state().get_theme() == Theme::Dark
+                    on_input=move |b| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_theme(if b { Theme::Dark } else { Theme::Light });
+                            }));
+                    }/>
+                <div>
+                    <span class="inline-block p-2 text-neutral-500 dark:text-neutral-400">"Preview the map with simulated color vision."</span>
+                    <ButtonGroup
+                        children={vec![
+                            color_vision_button("Normal", ColorVision::Normal),
+                            color_vision_button("Protanopia", ColorVision::Protanopia),
+                            color_vision_button("Deuteranopia", ColorVision::Deuteranopia),
+                            color_vision_button("Tritanopia", ColorVision::Tritanopia),
+                        ]}/>
+                </div>
+                <div>
+                    <span class="inline-block p-2 text-neutral-500 dark:text-neutral-400">"Set the color of the halo beneath the lines."</span>
+                    <ButtonGroup
+                        children={vec![
+                            halo_color_button("White", (255, 255, 255)),
+                            halo_color_button("Light gray", (220, 220, 220)),
+                            halo_color_button("Dark gray", (64, 64, 64)),
+                            halo_color_button("Black", (0, 0, 0)),
+                        ]}/>
+                </div>
+                <div>
+                    <span class="inline-block p-2 text-neutral-500 dark:text-neutral-400">"Shapes like rivers and parks can be drawn behind the map by uploading a GeoJSON file."</span>
+                    <Button
+                        text="Remove background shapes"
+                        smaller=true
+                        outlined=true
+                        disabled=Signal::derive(move || map_state.get().get_map().get_background().is_empty())
+                        on_click=Box::new(move |_| {
+                            map_state.update(|state| {
+                                state
+                                    .get_mut_map("removed background shapes")
+                                    .set_background(Vec::new());
+                            });
+                        })/>
+                </div>
+                <div>
+                    <span class="inline-block p-2 text-neutral-500 dark:text-neutral-400">"Set the font labels are drawn with."</span>
+                    <ButtonGroup
+                        children={vec![
+                            label_font_button("Sans-serif", LabelFont::SansSerif),
+                            label_font_button("Serif", LabelFont::Serif),
+                            label_font_button("Monospace", LabelFont::Monospace),
+                            label_font_button("Helvetica", LabelFont::Helvetica),
+                            label_font_button("Georgia", LabelFont::Georgia),
+                        ]}/>
+                </div>
+                <NumberInput
+                    text="Set the size labels are drawn with, as a fraction of the grid size."
+                    min=0.1
+                    max=3.0
+                    step=0.1
+                    value=move || map_state.get().get_canvas_state().get_label_size()
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_label_size(n);
+                            }));
+                    }/>
                 <NumberInput
                     text="Set canvas grid size."
                     min=2.0
                     max=100.0
                     value=move || f64::from(map_state.get().get_canvas_state().get_square_size())
                     on_input=update_square_size/>
+                <NumberInput
+                    text="Set how rounded bends in edges are drawn, as a fraction of the grid size (0 for sharp corners)."
+                    min=0.0
+                    max=1.0
+                    step=0.05
+                    value=move || map_state.get().get_canvas_state().get_corner_radius_factor()
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_corner_radius_factor(n);
+                            }));
+                    }/>
+                <NumberInput
+                    text="Set how much wider lines are drawn for every other line sharing their edge, as a fraction of their width (0 for the same width everywhere)."
+                    min=0.0
+                    max=1.0
+                    step=0.05
+                    value=move || map_state.get().get_canvas_state().get_line_width_scaling()
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_line_width_scaling(n);
+                            }));
+                    }/>
+                <NumberInput
+                    text="Set how far the halo extends beyond the lines on either side, as a fraction of their width."
+                    min=0.0
+                    max=3.0
+                    step=0.25
+                    value=move || map_state.get().get_canvas_state().get_halo_width()
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_canvas_state(|canvas| {
+                                canvas.set_halo_width(n);
+                            }));
+                    }/>
+                <NumberInput
+                    text="Set the maximum amount of stations or edges an imported map may have before asking to confirm loading it (0 for no limit)."
+                    min=0.0
+                    max=1_000_000.0
+                    step=1000.0
+                    value=move || map_state.get().get_max_import_size() as f64
+                    on_input=move |n| {
+                        map_state.update(|state| state.set_max_import_size(n.round().abs() as usize));
+                    }/>
                 <NumberInput
                     text="Set maximum number of algorithm iterations."
                     min=1.0
@@ -106,6 +505,17 @@ where
                                 settings.edge_routing_attempts = n.round().abs() as usize;
                             }));
                     }/>
+                <NumberInput
+                    text="Set how many retries only reshuffle the edges involved in a routing failure, before reshuffling all edges (0 to always reshuffle all)."
+                    min=0.0
+                    max=20.0
+                    value=move || map_state.get().get_algorithm_settings().local_edge_reshuffles as f64
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_algorithm_settings(|settings| {
+                                settings.local_edge_reshuffles = n.round().abs() as usize;
+                            }));
+                    }/>
                 <NumberInput
                     text="Set the node-set radius for possible station placement in algorithm pathfinding."
                     min=0.0
@@ -129,6 +539,43 @@ where
                                 settings.move_cost = n;
                             }));
                     }/>
+                <NumberInput
+                    text="Set the extra cost of extending an edge diagonally, a high value prefers horizontal and vertical segments (0 for no preference)."
+                    min=0.0
+                    max=100.0
+                    step=0.5
+                    value=move || map_state.get().get_algorithm_settings().diagonal_penalty
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_algorithm_settings(|settings| {
+                                settings.diagonal_penalty = n;
+                            }));
+                    }/>
+                <NumberInput
+                    text="Set the minimum distance between two stations."
+                    min=0.0
+                    max=10.0
+                    value=move || f64::from(map_state.get().get_algorithm_settings().min_station_spacing)
+                    on_input=move |n| {
+                        map_state
+                            .update(|state| state.update_algorithm_settings(|settings| {
+                                settings.min_station_spacing = n.round().abs() as i32;
+                            }));
+                    }/>
+                <div>
+                    <span class="inline-block p-2 text-neutral-500 dark:text-neutral-400">"Keyboard shortcuts, click on a field and press the new key to change it."</span>
+                    {KeyAction::ALL
+                        .into_iter()
+                        .map(|action| view! { <KeyBindingInput action=action/> })
+                        .collect_view()}
+                    <Button
+                        text="Reset keyboard shortcuts"
+                        smaller=true
+                        outlined=true
+                        on_click=Box::new(move |_| {
+                            interaction_state.update(|state| state.set_key_bindings(KeyBindings::default()));
+                        })/>
+                </div>
             </div>
             // footer
             <div class="flex items-center p-4 md:p-5 border-t border-gray-200 rounded-b dark:border-gray-600">
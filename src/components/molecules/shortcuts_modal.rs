@@ -0,0 +1,89 @@
+//! Contains the [`ShortcutsModal`] component.
+
+use leptos::prelude::*;
+
+use crate::components::{
+    atoms::{
+        Button,
+        Modal,
+    },
+    state::{
+        is_apple_platform,
+        InteractionState,
+        KeyAction,
+    },
+};
+
+/// A single row of the list of shortcuts, showing the keys and what they do.
+#[component]
+fn ShortcutRow(
+    /// The keys to press.
+    keys: String,
+    /// What pressing the keys does.
+    description: &'static str,
+) -> impl IntoView {
+    view! {
+        <tr>
+            <td class="py-1 pr-4">
+                <kbd class="rounded border border-neutral-300 bg-neutral-100 px-1.5 font-mono text-sm dark:border-neutral-600 dark:bg-neutral-700">{keys}</kbd>
+            </td>
+            <td class="py-1 text-neutral-500 dark:text-neutral-400">{description}</td>
+        </tr>
+    }
+}
+
+/// A read-only modal listing the keyboard shortcuts as they are currently
+/// bound, with the modifier key named as it is on the user's platform.
+#[component]
+pub fn ShortcutsModal<C>(
+    /// If the modal should be shown.
+    show: ReadSignal<bool>,
+    /// Gets called if the modal is closed.
+    on_close: C,
+) -> impl IntoView
+where
+    C: Fn() + Send + Clone + 'static,
+{
+    let interaction_state = use_context::<RwSignal<InteractionState>>()
+        .expect("to have found the global interaction state");
+
+    let apple = is_apple_platform();
+    let rows = move || {
+        let state = interaction_state.get();
+        KeyAction::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = state
+                    .get_key_bindings()
+                    .get_binding(action)
+                    .map_or_else(
+                        || "unbound".to_owned(),
+                        |binding| binding.to_platform_string(apple),
+                    );
+                view! { <ShortcutRow keys=keys description=action.as_str()/> }
+            })
+            .collect_view()
+    };
+
+    view! {
+        <Modal show=show on_close=on_close.clone()>
+            // body
+            <div class="p-4 md:p-5 space-y-4">
+                <h2 class="text-lg font-semibold text-black dark:text-white">"Keyboard shortcuts"</h2>
+                <table class="text-black dark:text-white">
+                    <tbody>
+                        {rows}
+                        <ShortcutRow keys="Tab".to_owned() description="While the map has focus, focus the next station, or the previous one with Shift"/>
+                    </tbody>
+                </table>
+                <p class="text-neutral-500 dark:text-neutral-400">
+                    "The shortcuts can be changed at the bottom of the advanced settings."
+                </p>
+            </div>
+            // footer
+            <div class="flex items-center p-4 md:p-5 border-t border-gray-200 rounded-b dark:border-gray-600">
+                <Button text="Close" on_click=Box::new(move |_| on_close())/>
+            </div>
+        </Modal>
+    }
+}
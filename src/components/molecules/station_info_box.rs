@@ -1,13 +1,23 @@
 //! Contains the [`StationInfoBox`] component.
 
+use itertools::Itertools;
 use leptos::prelude::*;
+use web_sys::MouseEvent;
 
 use crate::{
-    components::atoms::{
-        CanvasInfoBox,
-        TextWithEdit,
+    algorithms::spread_station_exits,
+    components::{
+        atoms::{
+            Button,
+            ButtonGroup,
+            ButtonProps,
+            CanvasInfoBox,
+            TextWithEdit,
+        },
+        ErrorState,
     },
     models::{
+        LineID,
         Station,
         StationID,
     },
@@ -15,11 +25,14 @@ use crate::{
 };
 
 /// A canvas info box that shows information about a station and lets you change
-/// its name.
+/// its name, select everything connected to it, spread out the edges leaving an
+/// interchange, or join two lines that both end at it.
 #[component]
 pub fn StationInfoBox() -> impl IntoView {
     let map_state =
         use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+    let error_state =
+        use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
 
     let station_was_clicked = move || {
         map_state
@@ -74,7 +87,7 @@ pub fn StationInfoBox() -> impl IntoView {
         if let Some(station_id) = station_id_opt {
             map_state.update(|state| {
                 let updated = if let Some(station) = state
-                    .get_mut_map()
+                    .get_mut_map("renamed station")
                     .get_mut_station(station_id)
                 {
                     station.set_name(&new_name);
@@ -88,6 +101,106 @@ pub fn StationInfoBox() -> impl IntoView {
         }
     };
 
+    // Select everything connected to the clicked station, adding it to the
+    // current selection if shift is held.
+    let select_connected = move |ev: MouseEvent| {
+        if let Some(station) = station_id() {
+            map_state.update(|state| state.select_connected(&[station], ev.shift_key()));
+        }
+    };
+
+    // Only interchanges have enough edges for them to bunch up.
+    let is_interchange = move || {
+        map_state
+            .get()
+            .get_clicked_on_station()
+            .is_some_and(|s| {
+                s.get_edges()
+                    .len()
+                    > 2
+            })
+    };
+    let spread_exits = move |_| {
+        let Some(station) = station_id() else {
+            return;
+        };
+        map_state.update(|state| {
+            let mut map = state
+                .get_map()
+                .clone();
+            match spread_station_exits(
+                &mut map,
+                station,
+                state.get_algorithm_settings(),
+            ) {
+                Ok(()) => {
+                    state.clear_all_selections();
+                    state.set_map(map, "spread station exits");
+                },
+                Err(err) => error_state.update(|state| state.set_error(err)),
+            }
+        });
+    };
+
+    // Every pair of lines that both end at the clicked station, with their
+    // ids and names.
+    let joinable_lines = move || {
+        let state = map_state.get();
+        let Some(station) = station_id() else {
+            return Vec::new();
+        };
+        let map = state.get_map();
+        map.get_lines()
+            .into_iter()
+            .filter(|line| {
+                line.get_line_ends(map)
+                    .contains(&station)
+            })
+            .map(|line| {
+                let name = if line
+                    .get_name()
+                    .is_empty()
+                {
+                    format!("line {}", line.get_id())
+                } else {
+                    line.get_name()
+                        .to_owned()
+                };
+                (line.get_id(), name)
+            })
+            .tuple_combinations()
+            .collect::<Vec<_>>()
+    };
+    let join_lines = move |keep: LineID, other: LineID| {
+        map_state.update(|state| {
+            let mut map = state
+                .get_map()
+                .clone();
+            match map.join_lines(keep, other) {
+                Ok(()) => {
+                    state.clear_all_selections();
+                    state.set_map(map, "joined lines");
+                },
+                Err(err) => error_state.update(|state| state.set_error(err)),
+            }
+        });
+    };
+    let join_buttons = move |a: (LineID, String), b: (LineID, String)| {
+        [(a.clone(), b.0), (b, a.0)]
+            .into_iter()
+            .map(|((keep, name), other)| {
+                ButtonProps::builder()
+                    .text(name)
+                    .smaller(true)
+                    .outlined(true)
+                    .on_click(Box::new(move |_| {
+                        join_lines(keep, other);
+                    }))
+                    .build()
+            })
+            .collect::<Vec<_>>()
+    };
+
     view! {
         <>
         <Show when=station_was_clicked>
@@ -102,6 +215,35 @@ pub fn StationInfoBox() -> impl IntoView {
                             on_edit=move |s| edit_station_name(station_id(), s)/>
                     </span>
                 </div>
+                <div class="mt-2">
+                    <Button
+                        text="Select connected"
+                        smaller=true
+                        outlined=true
+                        on_click=Box::new(select_connected)/>
+                </div>
+                <Show when=is_interchange>
+                    <div class="mt-2">
+                        <Button
+                            text="Spread exits"
+                            smaller=true
+                            outlined=true
+                            on_click=Box::new(spread_exits)/>
+                    </div>
+                </Show>
+                <For each=joinable_lines
+                    key=|(a, b)| (a.0, b.0)
+                    children=move |(a, b)| {
+                        view!{
+                            <div class="mt-2">
+                                <span class="block text-sm">
+                                    "Join "{a.1.clone()}" and "{b.1.clone()}", keeping the name and color of:"
+                                </span>
+                                <ButtonGroup children=join_buttons(a, b)/>
+                            </div>
+                        }
+                    }
+                />
             </CanvasInfoBox>
         </Show>
         <Show when=checkpoint_was_clicked>
@@ -0,0 +1,85 @@
+//! Contains the [`StatusBar`] component.
+
+use leptos::prelude::*;
+
+use crate::components::MapState;
+
+/// Format the given amount of items with the singular or plural form of the
+/// name, like `1 station` or `3 stations`.
+fn amount(count: usize, name: &str) -> String {
+    if count == 1 {
+        format!("{count} {name}")
+    } else {
+        format!("{count} {name}s")
+    }
+}
+
+/// A bar along the bottom of the page showing the size of the map, the
+/// current selection and the zoom level of the canvas.
+#[component]
+pub fn StatusBar() -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+
+    let map_counts = move || {
+        map_state.with(|state| {
+            let map = state.get_map();
+            format!(
+                "{}, {}, {}",
+                amount(
+                    map.get_stations()
+                        .len(),
+                    "station"
+                ),
+                amount(
+                    map.get_edges()
+                        .len(),
+                    "edge"
+                ),
+                amount(
+                    map.get_lines()
+                        .len(),
+                    "line"
+                ),
+            )
+        })
+    };
+
+    let selection_counts = move || {
+        map_state.with(|state| {
+            let stations = state
+                .get_selected_stations()
+                .len();
+            let edges = state
+                .get_selected_edges()
+                .len();
+
+            (stations + edges > 0).then(|| {
+                format!(
+                    "{}, {} selected",
+                    amount(stations, "station"),
+                    amount(edges, "edge")
+                )
+            })
+        })
+    };
+
+    let zoom = move || {
+        map_state.with(|state| {
+            let canvas = state.get_canvas_state();
+            format!(
+                "zoom {:.0}%, square size {:.0}px",
+                canvas.get_zoom_factor() * 100.0,
+                canvas.drawn_square_size()
+            )
+        })
+    };
+
+    view! {
+        <div id="status-bar" class="flex flex-row justify-between gap-x-4 px-3 py-1 text-xs bg-zinc-100 text-black dark:bg-neutral-750 dark:text-white">
+            <span>{map_counts}</span>
+            <span>{selection_counts}</span>
+            <span>{zoom}</span>
+        </div>
+    }
+}
@@ -0,0 +1,112 @@
+//! Contains the [`StraightenPanel`] component.
+
+use leptos::prelude::*;
+
+use crate::{
+    algorithms::{
+        apply_straightening,
+        straightening_candidates,
+        StraighteningCandidate,
+        MAX_PREVIEWED_CANDIDATES,
+    },
+    components::{
+        ErrorState,
+        MapState,
+    },
+    unwrap_or_return,
+};
+
+/// A panel listing the cheapest possible straightenings of the selected
+/// stations. Hovering over one previews it on the canvas, clicking on it
+/// straightens the selection onto it.
+#[component]
+pub fn StraightenPanel() -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+    let error_state =
+        use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
+
+    // The revision of the map and the selected stations the candidates are
+    // computed from, so previewing a candidate or moving the canvas does not
+    // compute them again.
+    let section = Memo::new(move |_| {
+        map_state.with(|state| {
+            (
+                state.get_map_revision(),
+                state
+                    .get_selected_stations()
+                    .iter()
+                    .map(|station| {
+                        station
+                            .get_station()
+                            .get_id()
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+    });
+
+    let candidates = Memo::new(move |_| {
+        section.track();
+        map_state.with_untracked(|state| {
+            let (edges, stations) = state.get_selected_section();
+            if stations.len() < 2 || edges.is_empty() {
+                return Vec::new();
+            }
+
+            straightening_candidates(state.get_map(), &edges, &stations)
+                .map(|mut candidates| {
+                    candidates.truncate(MAX_PREVIEWED_CANDIDATES);
+                    candidates
+                })
+                .unwrap_or_default()
+        })
+    });
+
+    let apply = move |candidate: StraighteningCandidate| {
+        map_state.update(|state| {
+            let (selected_edges, selected_stations) = state.get_selected_section();
+
+            state.clear_all_selections();
+
+            unwrap_or_return!(
+                error_state,
+                apply_straightening(
+                    state.get_algorithm_settings(),
+                    state.get_mut_map("straightened line"),
+                    &selected_edges,
+                    &selected_stations,
+                    &candidate,
+                )
+            );
+        });
+    };
+
+    view! {
+        <Show when=move || !candidates.get().is_empty()>
+            <div class="flex flex-col max-h-48 overflow-y-auto p-2 gap-y-1 rounded-md bg-white dark:bg-neutral-700 text-sm">
+                <h2 class="text-md font-semibold px-2">"Straightening Options"</h2>
+                {move || {
+                    candidates
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, candidate)| {
+                            let preview = candidate.clone();
+                            let label = format!("option {}: cost {}", i + 1, candidate.get_cost());
+                            view! {
+                                <button
+                                    class="block w-full text-left px-2 py-1 rounded hover:bg-blue-100 dark:hover:bg-neutral-600"
+                                    on:mouseenter=move |_| map_state.update(|state| state.set_straighten_preview(preview.clone()))
+                                    on:mouseleave=move |_| map_state.update(MapState::clear_straighten_preview)
+                                    on:click=move |_| apply(candidate.clone())>
+                                    {label}
+                                </button>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </div>
+        </Show>
+    }
+}
@@ -6,6 +6,12 @@
 // This otherwise gets triggered by one in the wasm worker.
 #![allow(unexpected_cfgs)]
 
+use std::{
+    collections::VecDeque,
+    mem,
+    time::Duration,
+};
+
 use futures_util::StreamExt;
 use leptos::{
     ev::keydown,
@@ -30,26 +36,54 @@ use crate::{
         AlgorithmExecutor,
         AlgorithmResponse,
         AlgorithmSettings,
+        EdgeRoutingFailure,
+        LayerOptions,
+        MapLayers,
     },
     components::{
         atoms::Button,
-        canvas::Canvas,
+        canvas::{
+            render_frames,
+            Canvas,
+        },
         molecules::{
+            DisplacementPanel,
             EdgeInfoBox,
+            HistoryTimeline,
+            ManualEditsModal,
+            MetricsPanel,
+            OverlapPanel,
+            ShortcutsModal,
             StationInfoBox,
         },
-        state::InteractionState,
+        state::{
+            InteractionState,
+            KeyAction,
+        },
         CanvasState,
         ErrorState,
         HistoryState,
         MapState,
     },
-    models::Map,
+    models::{
+        Edge,
+        GridNode,
+        Interchange,
+        LineID,
+        Map,
+        StationID,
+    },
     unwrap_or_return,
     utils::{
+        apng::{
+            encode_apng,
+            sample_evenly,
+        },
+        download::download_bytes,
         IDData,
         IDManager,
     },
+    Error,
 };
 
 /// The request to run the algorithm.
@@ -68,24 +102,283 @@ struct AlgorithmRequest {
     midway_updates: bool,
 }
 
+/// The amount of interpolated steps drawn between two midway updates of the
+/// algorithm.
+const ANIMATION_STEPS: u32 = 6;
+/// The time between two drawn animation steps.
+const ANIMATION_STEP_INTERVAL: Duration = Duration::from_millis(30);
+/// The maximum amount of midway updates waiting to be animated, older ones get
+/// dropped if the algorithm outpaces the animation.
+const MAX_BUFFERED_FRAMES: usize = 4;
+
+/// The maximum amount of frames in a recorded animation of the algorithm,
+/// longer runs are sampled evenly to fit.
+const MAX_RECORDED_FRAMES: usize = 150;
+
+/// Holds the state of the animation between the midway updates of the
+/// algorithm.
+#[derive(Clone, Debug, Default)]
+struct MidwayAnimation {
+    /// The map currently animated away from.
+    shown: Option<Map>,
+    /// The map currently animated towards.
+    target: Option<Map>,
+    /// The midway updates waiting to be animated.
+    frames: VecDeque<Map>,
+    /// The last midway update added, which is a real map unlike the
+    /// interpolated frames drawn between them.
+    latest: Option<Map>,
+    /// The current step of the animation between shown and target.
+    step: u32,
+}
+
+impl MidwayAnimation {
+    /// Add a new midway update to the buffer, dropping the oldest ones if
+    /// there are too many waiting.
+    fn push_frame(&mut self, map: Map) {
+        self.latest = Some(map.clone());
+        self.frames
+            .push_back(map);
+
+        while self
+            .frames
+            .len()
+            > MAX_BUFFERED_FRAMES
+        {
+            self.frames
+                .pop_front();
+        }
+    }
+
+    /// Get the last midway update added, if any.
+    fn latest_frame(&self) -> Option<Map> {
+        self.latest
+            .clone()
+    }
+
+    /// Get the next map to draw, if any.
+    fn next_frame(&mut self) -> Option<Map> {
+        if self
+            .target
+            .is_none()
+        {
+            self.target = self
+                .frames
+                .pop_front();
+            self.step = 0;
+        }
+
+        let target = self
+            .target
+            .clone()?;
+
+        let Some(shown) = &self.shown else {
+            self.shown = self
+                .target
+                .take();
+            return Some(target);
+        };
+
+        self.step += 1;
+        let progress = f64::from(self.step) / f64::from(ANIMATION_STEPS);
+        let frame = interpolate_map(shown, &target, progress);
+
+        if self.step >= ANIMATION_STEPS {
+            self.shown = self
+                .target
+                .take();
+        }
+
+        Some(frame)
+    }
+
+    /// Reset the animation.
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Holds the midway updates of the algorithm recorded for an animated image.
+/// Only every so many updates is recorded, which doubles whenever twice the
+/// maximum amount of frames is reached, so long runs are sampled evenly without
+/// keeping all their maps around.
+#[derive(Clone, Debug, Default)]
+struct AnimationRecording {
+    /// The recorded maps, in order.
+    frames: Vec<Map>,
+    /// The amount of midway updates per recorded one.
+    stride: usize,
+    /// The amount of midway updates skipped since the last recorded one.
+    skipped: usize,
+    /// The last skipped midway update, if no update was recorded since, so the
+    /// animation always ends on the final map.
+    last_skipped: Option<Map>,
+}
+
+impl AnimationRecording {
+    /// Record the given midway update, if it is not skipped.
+    fn push_frame(&mut self, map: Map) {
+        if self.skipped + 1 < self.stride {
+            self.skipped += 1;
+            self.last_skipped = Some(map);
+            return;
+        }
+        self.skipped = 0;
+        self.last_skipped = None;
+        self.frames
+            .push(map);
+
+        if self
+            .frames
+            .len()
+            >= 2 * MAX_RECORDED_FRAMES
+        {
+            self.frames = mem::take(&mut self.frames)
+                .into_iter()
+                .step_by(2)
+                .collect();
+            self.stride = self
+                .stride
+                .max(1)
+                * 2;
+        }
+    }
+
+    /// Get the recorded frames, ending with the last midway update and sampled
+    /// down to the maximum amount of frames.
+    fn into_frames(mut self) -> Vec<Map> {
+        if let Some(last) = self
+            .last_skipped
+            .take()
+        {
+            self.frames
+                .push(last);
+        }
+        sample_evenly(self.frames, MAX_RECORDED_FRAMES)
+    }
+}
+
+/// Draw every given map with the given canvas state onto an image with the
+/// width set for recorded animations and assemble the images into an animated
+/// PNG. The view is fit to all maps together, so the map does not jump around
+/// between the frames.
+async fn encode_animation(
+    mut canvas_state: CanvasState,
+    frames: Vec<Map>,
+) -> Result<Vec<u8>, Error> {
+    let (height, width) = canvas_state.get_size();
+    let image_width = canvas_state.get_animation_width();
+    let image_height = ((f64::from(image_width) * height / width).round() as u32).max(1);
+
+    let bounds = frames
+        .iter()
+        .filter_map(Map::bounds)
+        .reduce(|(min, max), (other_min, other_max)| {
+            (
+                GridNode(
+                    min.0
+                        .min(other_min.0),
+                    min.1
+                        .min(other_min.1),
+                ),
+                GridNode(
+                    max.0
+                        .max(other_max.0),
+                    max.1
+                        .max(other_max.1),
+                ),
+            )
+        });
+
+    canvas_state.set_size((
+        f64::from(image_height),
+        f64::from(image_width),
+    ));
+    canvas_state.fit_to(bounds);
+
+    let images = render_frames(
+        frames
+            .iter()
+            .map(MapLayers::for_export)
+            .collect(),
+        LayerOptions::for_export(canvas_state),
+    )
+    .await?;
+
+    encode_apng(
+        &images,
+        canvas_state.get_animation_fps(),
+    )
+}
+
+/// Get the target map with its stations moved the given progress (between 0.0
+/// and 1.0) of the way from their position in the from map.
+fn interpolate_map(from: &Map, target: &Map, progress: f64) -> Map {
+    let mut frame = target.clone();
+
+    for station in frame.get_mut_stations() {
+        if let Some(from_station) = from.get_station(station.get_id()) {
+            station.set_pos(
+                from_station
+                    .get_pos()
+                    .interpolate(station.get_pos(), progress),
+            );
+        }
+    }
+
+    frame
+}
+
+/// Select the edge that failed to route and center the canvas on it, so the
+/// user can see what blocked it. Falls back to an edge directly between the
+/// same stations if the failed edge only existed in the contracted map.
+fn show_routing_failure(state: &mut MapState, failure: &EdgeRoutingFailure) {
+    let map = state.get_map();
+    let (Some(from), Some(to)) = (
+        map.get_station(failure.get_from()),
+        map.get_station(failure.get_to()),
+    ) else {
+        return;
+    };
+    let (from_pos, to_pos) = (from.get_pos(), to.get_pos());
+
+    let edge = map
+        .get_edge(failure.get_edge())
+        .map(Edge::get_id)
+        .or_else(|| map.get_edge_id_between_if_exists(failure.get_from(), failure.get_to()));
+
+    state.clear_all_selections();
+    if let Some(edge) = edge {
+        state.set_selected_edges(vec![edge]);
+    }
+    state.update_canvas_state(|canvas| {
+        canvas.center_on(GridNode::from((
+            i32::midpoint(from_pos.0, to_pos.0),
+            i32::midpoint(from_pos.1, to_pos.1),
+        )));
+    });
+}
+
 /// The worker that runs the algorithm.
+///
+/// The worker is kept alive between runs, so the only setup done per request is
+/// syncing the [`IDManager`] and fitting the grid limits to the map. Syncing
+/// only ever raises the id counters, so ids generated in earlier runs are never
+/// handed out again. The map itself is moved straight into the executor instead
+/// of going through a temporary [`MapState`], saving a full copy of the map.
 #[allow(dead_code)] // usage is hidden
 #[worker(AlgorithmWorker)]
 fn run_algorithm(req: AlgorithmRequest) -> impl leptos_workers::Stream<Item = AlgorithmResponse> {
     IDManager::from_data(req.id_manager_data);
-
-    let mut temp_state = MapState::new(req.map);
-    temp_state.set_algorithm_settings(req.settings);
-    temp_state.calculate_algorithm_settings();
+    let settings = req
+        .settings
+        .fit_grid_limits(
+            req.map
+                .bounds(),
+        );
 
     // Start the stream and thus the algorithm.
-    AlgorithmExecutor::new(
-        temp_state.get_algorithm_settings(),
-        temp_state
-            .get_map()
-            .clone(),
-        req.midway_updates,
-    )
+    AlgorithmExecutor::new(settings, req.map, req.midway_updates)
 }
 
 /// The canvas and the controls overlayed on it.
@@ -103,28 +396,22 @@ pub fn CanvasControls() -> impl IntoView {
     );
     let (abort_handle, set_abort_handle) =
         signal_local(Option::<(AbortHandle<AlgorithmWorker>, Map)>::None);
+    let animation = StoredValue::new(MidwayAnimation::default());
+    // The midway updates recorded for an animated image, if this run is
+    // recorded.
+    let recording = StoredValue::new(Option::<AnimationRecording>::None);
+    let (animation_handle, set_animation_handle) = signal_local(Option::<IntervalHandle>::None);
+    // If the metrics of the map should be shown, which happens after a run.
+    let (show_metrics, set_show_metrics) = signal(false);
 
-    Effect::new(move |_| {
-        window_event_listener(
-            keydown,
-            move |keyevent: KeyboardEvent| {
-                map_state.update(|state| {
-                    state.update_canvas_state(|canvas| {
-                        match keyevent
-                            .key()
-                            .as_str()
-                        {
-                            "ArrowDown" => canvas.move_down(),
-                            "ArrowUp" => canvas.move_up(),
-                            "ArrowLeft" => canvas.move_left(),
-                            "ArrowRight" => canvas.move_right(),
-                            _ => {},
-                        }
-                    });
-                });
-            },
-        );
-    });
+    // Stop animating the midway updates of the algorithm.
+    let stop_animation = move || {
+        if let Some(handle) = animation_handle.get_untracked() {
+            handle.clear();
+        }
+        set_animation_handle(None);
+        animation.update_value(MidwayAnimation::clear);
+    };
 
     // If parts of the map has been selected and is not being moved.
     let has_parts_selected = Signal::derive(move || {
@@ -138,6 +425,56 @@ pub fn CanvasControls() -> impl IntoView {
                 .all(|s| !s.has_moved())
     });
 
+    // The two stations that are selected, if exactly two are selected and
+    // neither is being moved.
+    let selected_station_pair = Signal::derive(move || {
+        let state = map_state.get();
+        match state.get_selected_stations() {
+            [a, b] if !a.has_moved() && !b.has_moved() => {
+                Some((
+                    a.get_station()
+                        .get_id(),
+                    b.get_station()
+                        .get_id(),
+                ))
+            },
+            _ => None,
+        }
+    });
+
+    // If the two selected stations are connected by an interchange.
+    let has_interchange = Signal::derive(move || {
+        selected_station_pair
+            .get()
+            .is_some_and(|(a, b)| {
+                map_state
+                    .get()
+                    .get_map()
+                    .get_interchanges()
+                    .contains(&Interchange::new(a, b))
+            })
+    });
+
+    // Add or remove the interchange between the two selected stations.
+    let toggle_interchange = move |_| {
+        let Some((a, b)) = selected_station_pair.get_untracked() else {
+            return;
+        };
+
+        map_state.update(|state| {
+            let mut map = state
+                .get_map()
+                .clone();
+            if has_interchange.get_untracked() {
+                map.remove_interchange(a, b);
+                state.set_map(map, "removed interchange");
+            } else {
+                unwrap_or_return!(error_state, map.add_interchange(a, b));
+                state.set_map(map, "added interchange");
+            }
+        });
+    };
+
     // Handle the response from the algorithm.
     let handle_algorithm_response = move |resp: AlgorithmResponse, partial: bool, midway: bool| {
         if resp.success
@@ -146,36 +483,60 @@ pub fn CanvasControls() -> impl IntoView {
                 .get_algorithm_settings()
                 .output_on_fail
         {
-            map_state.update(|state| {
-                if partial && midway {
-                    let mut temp_map = state
-                        .get_map()
-                        .clone();
+            if midway {
+                let mut frame = resp.map;
 
-                    unwrap_or_return!(
-                        error_state,
-                        temp_map.update_from_partial(&resp.map)
-                    );
+                if partial {
+                    // The map on the canvas is an interpolated frame, so apply the
+                    // partial update to the last real map instead.
+                    let mut temp_map = animation
+                        .with_value(MidwayAnimation::latest_frame)
+                        .unwrap_or_else(|| {
+                            map_state
+                                .get_untracked()
+                                .get_map()
+                                .clone()
+                        });
 
-                    state.set_map_no_history(temp_map);
-                } else if partial {
                     unwrap_or_return!(
                         error_state,
-                        state
-                            .get_mut_map()
-                            .update_from_partial(&resp.map)
+                        temp_map.update_from_partial(&frame)
                     );
-                } else if midway {
-                    state.set_map_no_history(resp.map);
-                } else {
+
+                    frame = temp_map;
+                }
+
+                recording.update_value(|recording| {
+                    if let Some(recording) = recording {
+                        recording.push_frame(frame.clone());
+                    }
+                });
+                animation.update_value(|animation| animation.push_frame(frame));
+            } else {
+                stop_animation();
+                map_state.update(|state| {
                     if let Some((_, before_map)) = abort_handle.get_untracked() {
                         state.set_map_no_history(before_map);
                     }
-                    state.set_map(resp.map);
-                }
-            });
+                    if partial {
+                        unwrap_or_return!(
+                            error_state,
+                            state
+                                .get_mut_map("recalculated selection")
+                                .update_from_partial(&resp.map)
+                        );
+                    } else {
+                        state.set_map(resp.map, "recalculated map");
+                    }
+                    if let Some(occupied) = resp.occupied {
+                        state.set_last_occupied(occupied);
+                    }
+                });
+                set_show_metrics(true);
+            }
             IDManager::from_data(resp.id_manager_data);
         } else {
+            stop_animation();
             map_state.update(|state| {
                 if let Some((_, before_map)) = abort_handle.get_untracked() {
                     state.set_map_no_history(before_map);
@@ -188,6 +549,9 @@ pub fn CanvasControls() -> impl IntoView {
             });
         }
         if let Some(error) = resp.error {
+            if let Error::EdgeRouting(failure) = &error {
+                map_state.update(|state| show_routing_failure(state, failure));
+            }
             error_state.update(|state| {
                 state.set_error(error);
             });
@@ -218,6 +582,52 @@ pub fn CanvasControls() -> impl IntoView {
                 state.set_cursor("wait");
             });
 
+            // Record the midway updates from the algorithm for an animated image,
+            // starting from the map as it is now.
+            let record = req.midway_updates
+                && map_state
+                    .get_untracked()
+                    .get_canvas_state()
+                    .is_record_animation();
+            recording.set_value(record.then(|| {
+                let mut recording = AnimationRecording::default();
+                recording.push_frame(
+                    map_state
+                        .get_untracked()
+                        .get_map()
+                        .clone(),
+                );
+                recording
+            }));
+
+            // Animate the midway updates from the algorithm, drawing the next frame on
+            // every tick.
+            if req.midway_updates {
+                animation.update_value(|animation| {
+                    animation.clear();
+                    animation.push_frame(
+                        map_state
+                            .get_untracked()
+                            .get_map()
+                            .clone(),
+                    );
+                });
+
+                let handle = set_interval_with_handle(
+                    move || {
+                        if let Some(frame) = animation
+                            .try_update_value(MidwayAnimation::next_frame)
+                            .flatten()
+                        {
+                            map_state.update(|state| state.set_map_no_history(frame));
+                        }
+                    },
+                    ANIMATION_STEP_INTERVAL,
+                )
+                .ok();
+                set_animation_handle(handle);
+            }
+
             // Handle the responses from the algorithm.
             // This is done in a fold to ensure only the last response is handled later, but
             // all midway updates are handled conditionally.
@@ -233,109 +643,300 @@ pub fn CanvasControls() -> impl IntoView {
                 )
                 .await;
 
+            let succeeded = last
+                .as_ref()
+                .is_some_and(|resp| resp.success);
+
             // If we got a response and it wasn't handled by the midway handler, handle it
             // now.
             if let Some(resp) = last {
                 handle_algorithm_response(resp, req.partial, false);
             }
 
+            stop_animation();
             set_abort_handle(None);
             interaction_state.update(|state| {
                 state.set_busy(false);
                 state.set_cursor("default");
             });
+
+            // Turn the recorded midway updates of a successful run into an animated
+            // image. The final map was recorded as the last midway update.
+            let recorded = recording
+                .try_update_value(Option::take)
+                .flatten();
+            if let Some(recorded) = recorded.filter(|_| succeeded) {
+                let image = unwrap_or_return!(
+                    error_state,
+                    encode_animation(
+                        map_state.with_untracked(MapState::get_canvas_state),
+                        recorded.into_frames(),
+                    )
+                    .await
+                );
+                unwrap_or_return!(
+                    error_state,
+                    download_bytes(
+                        &image,
+                        "image/apng",
+                        "metro-map-animation.png",
+                    )
+                );
+            }
         }
     });
 
+    // Dispatch the algorithm on the given map with the given settings.
+    let dispatch_algorithm =
+        move |map: Map, settings: AlgorithmSettings, partial: bool, midway_updates: bool| {
+            algorithm_req.dispatch(AlgorithmRequest {
+                settings,
+                id_manager_data: IDManager::to_data(),
+                map,
+                partial,
+                midway_updates,
+            });
+        };
+
     let zoom_in =
         move |_| map_state.update(|state| state.update_canvas_state(CanvasState::zoom_in));
     let zoom_out =
         move |_| map_state.update(|state| state.update_canvas_state(CanvasState::zoom_out));
+    let recenter = move |_| map_state.update(MapState::recenter);
 
     let undo = move |_| {
         map_state.update(|map_state| {
-            let current_map = map_state
-                .get_map()
-                .clone();
-            if let Some(map) = HistoryState::undo(current_map) {
-                map_state.set_map_no_history(map);
-            }
+            map_state.undo();
         });
     };
     let cannot_undo = Signal::derive(move || map_state.with(|_| HistoryState::cannot_undo()));
     let redo = move |_| {
         map_state.update(|map_state| {
-            let current_map = map_state
-                .get_map()
-                .clone();
-            if let Some(map) = HistoryState::redo(current_map) {
-                map_state.set_map_no_history(map);
-            }
+            map_state.redo();
         });
     };
     let cannot_redo = Signal::derive(move || map_state.with(|_| HistoryState::cannot_redo()));
 
+    // If the history timeline is shown.
+    let (show_history, set_show_history) = signal(false);
+    let toggle_history = move |_| set_show_history.update(|show| *show = !*show);
+    let cannot_show_history = Signal::derive(move || {
+        !show_history.get()
+            && map_state.with(|_| HistoryState::cannot_undo() && HistoryState::cannot_redo())
+    });
+
+    // If the warning about discarding manually edited edges is shown.
+    let (show_manual_edits, set_show_manual_edits) = signal(false);
+
+    // If the list of keyboard shortcuts is shown.
+    let (show_shortcuts, set_show_shortcuts) = signal(false);
+
     // Run the algorithm on the entire map.
-    let run_algorithm = move |_| {
-        let req = AlgorithmRequest {
-            settings: map_state
-                .get_untracked()
-                .get_algorithm_settings(),
-            map: map_state
-                .get_untracked()
+    let run_recalculation = Callback::new(move |()| {
+        let state = map_state.get_untracked();
+        dispatch_algorithm(
+            state
                 .get_map()
                 .clone(),
-            id_manager_data: IDManager::to_data(),
-            partial: false,
-            midway_updates: false,
-        };
+            state.get_algorithm_settings(),
+            false,
+            false,
+        );
+    });
+
+    // Run the algorithm on the entire map, first warning the user if it would
+    // reroute edges they edited by hand.
+    let recalculate = move || {
+        let has_manual_edits = map_state.with_untracked(|state| {
+            !state
+                .get_map()
+                .get_unlocked_manual_edges()
+                .is_empty()
+        });
 
-        algorithm_req.dispatch(req);
+        if has_manual_edits {
+            set_show_manual_edits(true);
+        } else {
+            run_recalculation.run(());
+        }
     };
+    let run_algorithm = move |_| recalculate();
+
+    // Lay out the whole map when requested elsewhere, like after importing a
+    // map with auto-arrange enabled.
+    Effect::new(move |_| {
+        if interaction_state.with(InteractionState::is_layout_requested) {
+            interaction_state.update(|state| state.set_layout_requested(false));
+            recalculate();
+        }
+    });
+
+    // Pan, zoom, recalculate and show the shortcuts with the keys bound to those
+    // actions.
+    Effect::new(move |_| {
+        window_event_listener(
+            keydown,
+            move |keyevent: KeyboardEvent| {
+                let Some(action) = interaction_state.with_untracked(|state| {
+                    state
+                        .get_key_bindings()
+                        .get_event_action(&keyevent)
+                }) else {
+                    return;
+                };
+
+                if action == KeyAction::Recalculate {
+                    if !interaction_state
+                        .get_untracked()
+                        .is_busy()
+                        && !map_state.with_untracked(MapState::is_read_only)
+                    {
+                        recalculate();
+                    }
+                    return;
+                }
+
+                if action == KeyAction::ShowShortcuts {
+                    set_show_shortcuts.update(|shown| *shown = !*shown);
+                    return;
+                }
+
+                if action == KeyAction::FitSelection {
+                    map_state.update(MapState::fit_selection);
+                    return;
+                }
+
+                if action == KeyAction::ViewBack {
+                    map_state.update(MapState::view_back);
+                    return;
+                }
+
+                if action == KeyAction::ViewForward {
+                    map_state.update(MapState::view_forward);
+                    return;
+                }
+
+                map_state.update(|state| {
+                    state.update_canvas_state(|canvas| {
+                        match action {
+                            KeyAction::PanDown => canvas.move_down(),
+                            KeyAction::PanUp => canvas.move_up(),
+                            KeyAction::PanLeft => canvas.move_left(),
+                            KeyAction::PanRight => canvas.move_right(),
+                            KeyAction::ZoomIn => canvas.zoom_in(),
+                            KeyAction::ZoomOut => canvas.zoom_out(),
+                            _ => {},
+                        }
+                    });
+                });
+            },
+        );
+    });
 
     // Run the algorithm only on the selected stations and edges.
     let run_partial_algorithm = move |_| {
-        let req = AlgorithmRequest {
-            settings: map_state
-                .get_untracked()
-                .get_algorithm_settings(),
-            map: map_state
-                .get_untracked()
-                .lock_all_unselected(),
-            id_manager_data: IDManager::to_data(),
-            partial: true,
-            midway_updates: false,
-        };
+        let state = map_state.get_untracked();
+        dispatch_algorithm(
+            state.lock_all_unselected(),
+            state.get_algorithm_settings(),
+            true,
+            false,
+        );
+    };
 
-        algorithm_req.dispatch(req);
+    // Run the algorithm only on the part of the map visible on the canvas.
+    let run_visible_algorithm = move |_| {
+        let state = map_state.get_untracked();
+        let map = unwrap_or_return!(
+            error_state,
+            state.lock_all_outside_view()
+        );
+        dispatch_algorithm(
+            map,
+            state.get_algorithm_settings(),
+            true,
+            false,
+        );
     };
 
+    // Run the algorithm on a single line, with the rest of the map locked.
+    let run_line_algorithm = Callback::new(
+        move |(line_id, keep_junctions): (LineID, bool)| {
+            let state = map_state.get_untracked();
+            dispatch_algorithm(
+                state.lock_all_except_line(line_id, keep_junctions),
+                state.get_algorithm_settings(),
+                true,
+                false,
+            );
+        },
+    );
+
+    // Anchor a station at its original position and run the algorithm again.
+    let anchor_and_rerun = Callback::new(move |station_id: StationID| {
+        map_state.update(|state| state.anchor_station(station_id));
+
+        let state = map_state.get_untracked();
+        dispatch_algorithm(
+            state
+                .get_map()
+                .clone(),
+            state.get_algorithm_settings(),
+            false,
+            false,
+        );
+    });
+
     // Run the algorithm on the entire map.
     let run_stream_algorithm = move |_| {
         let partial = has_parts_selected.get_untracked();
-        let req = AlgorithmRequest {
-            settings: map_state
-                .get_untracked()
-                .get_algorithm_settings(),
-            map: map_state
-                .get_untracked()
+        let state = map_state.get_untracked();
+        dispatch_algorithm(
+            state
                 .get_map()
                 .clone(),
-            id_manager_data: IDManager::to_data(),
+            state.get_algorithm_settings(),
             partial,
-            midway_updates: true,
-        };
+            true,
+        );
+    };
+
+    // Run the algorithm on the entire map, only routing the edges and keeping
+    // all stations where they are. This overrides the settings that move
+    // stations for this run only.
+    let run_edges_only_algorithm = move |_| {
+        let state = map_state.get_untracked();
 
-        algorithm_req.dispatch(req);
+        dispatch_algorithm(
+            state
+                .get_map()
+                .clone(),
+            state
+                .get_algorithm_settings()
+                .edges_only(),
+            false,
+            false,
+        );
     };
 
+    // If the settings already keep all stations in place, so the regular run
+    // does the same as routing only the edges.
+    let is_edges_only = Signal::derive(move || {
+        let settings = map_state
+            .get()
+            .get_algorithm_settings();
+        !settings.allow_station_relocation && !settings.local_search && !settings.compact_layout
+    });
+
     // Abort the algorithm.
     let abort_algorithm = move |_| {
         if let Some((handle, original_map)) = abort_handle.get_untracked() {
             handle.abort();
             algorithm_req.clear();
+            stop_animation();
+            recording.set_value(None);
             map_state.update(|state| {
-                state.set_map(original_map);
+                state.set_map(original_map, "aborted recalculation");
             });
             set_abort_handle(None);
         }
@@ -369,6 +970,92 @@ pub fn CanvasControls() -> impl IntoView {
             .is_original_overlay_enabled()
     });
 
+    let overlay_overlapping_edges = move |_| {
+        map_state.update(|state| {
+            state.set_overlap_overlay_enabled(!state.is_overlap_overlay_enabled());
+        });
+    };
+
+    // If the overlapping edges overlay is active.
+    let is_overlap_overlay_active = Signal::derive(move || {
+        map_state
+            .get()
+            .is_overlap_overlay_enabled()
+    });
+
+    let overlay_displaced_stations = move |_| {
+        map_state.update(|state| {
+            state.set_displacement_overlay_enabled(!state.is_displacement_overlay_enabled());
+        });
+    };
+
+    // If the displaced stations overlay is active.
+    let is_displacement_overlay_active = Signal::derive(move || {
+        map_state
+            .get()
+            .is_displacement_overlay_enabled()
+    });
+
+    let overlay_contracted_stations = move |_| {
+        map_state.update(|state| {
+            state.set_contracted_overlay_enabled(!state.is_contracted_overlay_enabled());
+        });
+    };
+
+    // If the contracted stations overlay is active.
+    let is_contracted_overlay_active = Signal::derive(move || {
+        map_state
+            .get()
+            .is_contracted_overlay_enabled()
+    });
+
+    // If any edge of the map has stations contracted into it, which is only the
+    // case while the algorithm has not expanded them yet.
+    let has_contracted_stations = move || {
+        map_state
+            .get()
+            .get_map()
+            .get_edges()
+            .into_iter()
+            .any(|edge| {
+                !edge
+                    .get_contracted_stations()
+                    .is_empty()
+            })
+    };
+
+    let toggle_endcaps = move |_| {
+        map_state.update(|state| {
+            state.update_canvas_state(|canvas| {
+                canvas.set_endcaps_shown(!canvas.is_endcaps_shown());
+            });
+        });
+    };
+
+    // If the line names are drawn in endcaps at the termini.
+    let is_endcaps_active = Signal::derive(move || {
+        map_state
+            .get()
+            .get_canvas_state()
+            .is_endcaps_shown()
+    });
+
+    let toggle_labels = move |_| {
+        map_state.update(|state| {
+            state.update_canvas_state(|canvas| {
+                canvas.set_labels_shown(!canvas.is_labels_shown());
+            });
+        });
+    };
+
+    // If the labels are hidden.
+    let is_labels_hidden = Signal::derive(move || {
+        !map_state
+            .get()
+            .get_canvas_state()
+            .is_labels_shown()
+    });
+
     view! {
     <div node_ref=container_ref id="canvas-container" class="grow flex self-stretch relative">
         <Canvas/>
@@ -408,6 +1095,27 @@ pub fn CanvasControls() -> impl IntoView {
                 </Button>
             </div>
         </Show>
+        <div class="absolute right-5 top-40">
+            <Button text="route edges only,\nkeeping stations in place" on_click=Box::new(run_edges_only_algorithm) overlay=true active=is_edges_only>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <circle cx="5" cy="19" r="2" />
+                    <circle cx="19" cy="5" r="2" />
+                    <path d="M7 19h4a2 2 0 0 0 2 -2v-10a2 2 0 0 1 2 -2h2" />
+                </svg>
+            </Button>
+        </div>
+        <div class="absolute right-5 top-56">
+            <Button text="recalculate visible\narea only" on_click=Box::new(run_visible_algorithm) overlay=true>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <path d="M4 8v-2a2 2 0 0 1 2 -2h2" />
+                    <path d="M4 16v2a2 2 0 0 0 2 2h2" />
+                    <path d="M16 4h2a2 2 0 0 1 2 2v2" />
+                    <path d="M16 20h2a2 2 0 0 0 2 -2v-2" />
+                    <path d="M15 10a3.5 3.5 0 0 0 -6 -1m-.5 -2v2h2" />
+                    <path d="M9 14a3.5 3.5 0 0 0 6 1m.5 2v-2h-2" />
+                </svg>
+            </Button>
+        </div>
         <div class="absolute right-24 top-5 group">
             <Button text="show original\nmap overlay" on_click=Box::new(overlay_original_map) overlay=true active=is_original_overlay_active>
                 <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 32 32" stroke-width="2.1" stroke="currentColor" fill="none">
@@ -415,6 +1123,85 @@ pub fn CanvasControls() -> impl IntoView {
                 </svg>
             </Button>
         </div>
+        <div class="absolute right-44 top-5 group">
+            <Button text="show overlapping\nedges" on_click=Box::new(overlay_overlapping_edges) overlay=true active=is_overlap_overlay_active>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <path d="M12 3l9 16h-18z" />
+                    <path d="M12 10v4" />
+                    <path d="M12 17v.01" />
+                </svg>
+            </Button>
+        </div>
+        <div class="absolute right-64 top-5 group">
+            <Button text="show most displaced\nstations" on_click=Box::new(overlay_displaced_stations) overlay=true active=is_displacement_overlay_active>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <circle cx="6" cy="18" r="2" />
+                    <circle cx="18" cy="6" r="3" />
+                    <path d="M8 16l7 -7" stroke-dasharray="2 2" />
+                </svg>
+            </Button>
+        </div>
+        <Show when=move || selected_station_pair.get().is_some()>
+            <div class="absolute right-[21rem] top-5 group">
+                <Button text="toggle interchange between\nselected stations" on_click=Box::new(toggle_interchange) overlay=true active=has_interchange>
+                    <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                        <circle cx="5" cy="12" r="3" />
+                        <circle cx="19" cy="12" r="3" />
+                        <path d="M8 12h8" stroke-width="4" />
+                    </svg>
+                </Button>
+            </div>
+        </Show>
+        <Show when=move || is_overlap_overlay_active.get()>
+            <div class="absolute right-24 top-24">
+                <OverlapPanel/>
+            </div>
+        </Show>
+        <Show when=move || is_displacement_overlay_active.get()>
+            <div class="absolute right-[21rem] top-24">
+                <DisplacementPanel on_anchor=anchor_and_rerun/>
+            </div>
+        </Show>
+        <Show when=has_contracted_stations>
+            <div class="absolute right-5 bottom-[17rem]">
+                <Button text="show contracted\nstations" on_click=Box::new(overlay_contracted_stations) overlay=true active=is_contracted_overlay_active>
+                    <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                        <path d="M3 12h18" />
+                        <circle cx="8" cy="12" r="2" />
+                        <circle cx="16" cy="12" r="2" />
+                    </svg>
+                </Button>
+            </div>
+        </Show>
+        <div class="absolute right-5 bottom-52">
+            <Button text="show line names\nat termini" on_click=Box::new(toggle_endcaps) overlay=true active=is_endcaps_active>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <path d="M3 12h6" />
+                    <rect x="9" y="8" width="12" height="8" rx="4" />
+                </svg>
+            </Button>
+        </div>
+        <div class="absolute right-24 bottom-52">
+            <Button text="hide labels" on_click=Box::new(toggle_labels) overlay=true active=is_labels_hidden>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <path d="M4 7v-2h16v2" />
+                    <path d="M12 5v14" />
+                    <path d="M9 19h6" />
+                    <path d="M3 3l18 18" />
+                </svg>
+            </Button>
+        </div>
+        <div class="absolute right-5 bottom-36">
+            <Button text="recenter map" on_click=Box::new(recenter) overlay=true>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <circle cx="12" cy="12" r="3" />
+                    <path d="M4 8v-2a2 2 0 0 1 2 -2h2" />
+                    <path d="M4 16v2a2 2 0 0 0 2 2h2" />
+                    <path d="M16 4h2a2 2 0 0 1 2 2v2" />
+                    <path d="M16 20h2a2 2 0 0 0 2 -2v-2" />
+                </svg>
+            </Button>
+        </div>
         <div class="absolute right-5 bottom-20">
             <Button text="zoom in" on_click=Box::new(zoom_in) overlay=true>+</Button>
         </div>
@@ -435,8 +1222,33 @@ pub fn CanvasControls() -> impl IntoView {
                 </svg>
             </Button>
         </div>
+        <div class="absolute left-36 bottom-5">
+            <Button text="show history timeline" on_click=Box::new(toggle_history) overlay=true active=show_history disabled=cannot_show_history>
+                <svg class="text-blue-500 -m-1" width="20" height="20" viewBox="0 0 24 24" stroke-width="2" stroke="currentColor" fill="none" stroke-linecap="round" stroke-linejoin="round">
+                    <circle cx="12" cy="12" r="9" />
+                    <polyline points="12 7 12 12 15 15" />
+                </svg>
+            </Button>
+        </div>
+        <Show when=move || show_history.get()>
+            <div class="absolute left-5 bottom-20">
+                <HistoryTimeline/>
+            </div>
+        </Show>
+        <Show when=move || show_metrics.get()>
+            <div class="absolute left-5 top-5">
+                <MetricsPanel on_close=move || set_show_metrics(false)/>
+            </div>
+        </Show>
         <StationInfoBox/>
-        <EdgeInfoBox/>
+        <EdgeInfoBox on_relayout_line=run_line_algorithm/>
+        <ManualEditsModal
+            show=show_manual_edits
+            on_close=move || set_show_manual_edits(false)
+            on_continue=run_recalculation />
+        <ShortcutsModal
+            show=show_shortcuts
+            on_close=move || set_show_shortcuts(false) />
     </div>
     }
 }
@@ -1,8 +1,35 @@
 //! Contains the [`Navbar`] component.
 
-use leptos::prelude::*;
+// Async is used for futures, which are used in the worker, even though the decoding itself is
+// sync.
+#![allow(clippy::unused_async)]
+// This otherwise gets triggered by one in the wasm worker.
+#![allow(unexpected_cfgs)]
+
+use std::mem;
+
+use leptos::{
+    logging,
+    prelude::*,
+    task::spawn_local,
+};
+use leptos_workers::{
+    executors::{
+        AbortHandle,
+        PoolExecutor,
+    },
+    worker,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
 use crate::{
+    algorithms::{
+        BackgroundShape,
+        GridMode,
+    },
     components::{
         atoms::{
             Button,
@@ -13,19 +40,141 @@ use crate::{
             FileDownloader,
             FileModal,
             FileType,
+            LargeImportModal,
             MapExporter,
             SettingsModal,
         },
+        state::InteractionState,
+        CanvasState,
         ErrorState,
         MapState,
     },
+    models::Map,
     unwrap_or_return,
     utils::{
+        adjacency,
+        flag_non_octilinear_segments,
+        geojson,
         graphml,
         json,
+        DecodeWarning,
+        GridTransform,
+        IDData,
+        IDManager,
     },
+    Error,
 };
 
+/// The request to decode an uploaded map file.
+#[derive(Clone, Serialize, Deserialize)]
+struct DecodeRequest {
+    /// The type of the uploaded file.
+    file_type: FileType,
+    /// The contents of the uploaded file.
+    contents: String,
+    /// The state of the canvas the map is decoded for.
+    canvas_state: CanvasState,
+    /// The data for the [`IDManager`] to ensure the ids generated while
+    /// decoding are unique.
+    id_manager_data: IDData,
+    /// The maximum amount of stations or edges the decoded map may have, or
+    /// of shapes in decoded background shapes, 0 meaning there is no limit.
+    max_size: usize,
+    /// The transform the current map was imported with, which background
+    /// shapes are placed onto the grid with.
+    grid_transform: Option<GridTransform>,
+}
+
+/// The contents of a decoded file.
+#[derive(Clone, Serialize, Deserialize)]
+enum DecodedFile {
+    /// A map to replace the current one, and the warnings for its skipped
+    /// elements.
+    Map(Box<Map>, Vec<DecodeWarning>),
+    /// Shapes to draw behind the current map.
+    Background(Vec<BackgroundShape>),
+}
+
+impl DecodedFile {
+    /// Check the decoded map has no more stations or edges than the given
+    /// limit, or the decoded background no more shapes. A limit of 0 means
+    /// there is no limit.
+    fn check_size(&self, limit: usize) -> crate::utils::Result<()> {
+        match self {
+            Self::Map(map, _) => map.check_size(limit),
+            Self::Background(shapes) => {
+                if limit > 0 && shapes.len() > limit {
+                    return Err(Error::BackgroundTooLarge {
+                        shapes: shapes.len(),
+                        limit,
+                    });
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// The response with the decoded map file.
+#[derive(Clone, Serialize, Deserialize)]
+struct DecodeResponse {
+    /// The contents of the decoded file, or the error that stopped decoding.
+    // The result alias is not imported, as it would clash with the one used by the worker macro.
+    result: crate::utils::Result<DecodedFile>,
+    /// The error for the decoded file being larger than the maximum size, in
+    /// which case it is only loaded once the user confirms.
+    size_error: Option<Error>,
+    /// The data for the [`IDManager`] after decoding, ensuring the main thread
+    /// will not create IDs in conflict with those in the map.
+    id_manager_data: IDData,
+}
+
+/// The worker that decodes uploaded map files, so decoding a huge file does
+/// not block the page and can be cancelled.
+#[allow(dead_code)] // usage is hidden
+#[worker(DecodeWorker)]
+async fn decode_file(req: DecodeRequest) -> DecodeResponse {
+    IDManager::from_data(req.id_manager_data);
+
+    let result = match req.file_type {
+        FileType::Json => {
+            json::decode_map_lenient(&req.contents, req.canvas_state)
+                .map(|(map, warnings)| DecodedFile::Map(Box::new(map), warnings))
+        },
+        FileType::GraphML => {
+            graphml::decode_map_lenient(&req.contents, req.canvas_state)
+                .map(|(map, warnings)| DecodedFile::Map(Box::new(map), warnings))
+        },
+        FileType::Adjacency => {
+            adjacency::decode_map(&req.contents, req.canvas_state)
+                .map(|map| DecodedFile::Map(Box::new(map), Vec::new()))
+        },
+        FileType::GeoJson => {
+            geojson::decode_background(
+                &req.contents,
+                req.canvas_state,
+                req.grid_transform,
+            )
+            .map(DecodedFile::Background)
+        },
+    };
+
+    let size_error = result
+        .as_ref()
+        .ok()
+        .and_then(|decoded| {
+            decoded
+                .check_size(req.max_size)
+                .err()
+        });
+
+    DecodeResponse {
+        result,
+        size_error,
+        id_manager_data: IDManager::to_data(),
+    }
+}
+
 /// The navbar at the top of the page.
 /// Also contains the modal for uploading a file.
 #[component]
@@ -36,26 +185,161 @@ pub fn Navbar() -> impl IntoView {
         use_context::<RwSignal<MapState>>().expect("to have found the global map state");
     let error_state =
         use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
+    let interaction_state = use_context::<RwSignal<InteractionState>>()
+        .expect("to have found the global interaction state");
+    let (executor, _) = signal_local(
+        PoolExecutor::<DecodeWorker>::new(1).expect("failed to start web-worker pool"),
+    );
+    let (abort_handle, set_abort_handle) = signal_local(Option::<AbortHandle<DecodeWorker>>::None);
+    // Counts the started and cancelled loads, so the result of a cancelled load
+    // is ignored even if it still arrives.
+    let load_count = StoredValue::new(0_u64);
 
-    let on_submit = move |file_type: FileType, s: String| {
-        set_show_file_modal(false);
+    // If the page was marked busy by loading a file, rather than by something
+    // else like a running algorithm, which should stay busy after the load.
+    let marked_busy = StoredValue::new(false);
+
+    // Stop showing the page as busy loading a file.
+    let finish_loading = move || {
+        set_abort_handle(None);
+        if marked_busy
+            .try_update_value(mem::take)
+            .unwrap_or(false)
+        {
+            interaction_state.update(|state| {
+                state.set_busy(false);
+                state.set_cursor("default");
+            });
+        }
+    };
+
+    // The decoded file that was larger than the limit, kept so it can be loaded
+    // anyway if the user confirms.
+    let too_large_import = StoredValue::new(Option::<(DecodedFile, bool)>::None);
+    let (show_large_import_modal, set_show_large_import_modal) = signal(false);
+    let (large_import_message, set_large_import_message) = signal(String::new());
+
+    // Load the decoded map, or draw the decoded background shapes behind the
+    // current map.
+    let load_decoded = move |decoded: DecodedFile, auto_layout: bool| {
+        let (map, mut warnings) = match decoded {
+            DecodedFile::Map(map, warnings) => (*map, warnings),
+            // The shapes are drawn behind the current map instead of
+            // replacing it.
+            DecodedFile::Background(shapes) => {
+                map_state.update(|state| {
+                    state
+                        .get_mut_map("loaded background shapes")
+                        .set_background(shapes);
+                });
+                return;
+            },
+        };
 
         map_state.update(|state| {
-            let map = unwrap_or_return!(
-                error_state,
-                match file_type {
-                    FileType::Json => {
-                        json::decode_map(&s, state.get_canvas_state())
-                    },
-                    FileType::GraphML => {
-                        graphml::decode_map(&s, state.get_canvas_state())
-                    },
-                }
-            );
+            if state
+                .get_algorithm_settings()
+                .grid_mode
+                == GridMode::Octilinear
+            {
+                warnings.extend(flag_non_octilinear_segments(&map));
+            }
+            error_state.update(|error_state| error_state.set_warnings(warnings));
 
-            state.set_map(map.clone());
+            state.set_map(map.clone(), "loaded map");
             state.set_last_loaded(map);
         });
+
+        // The map as it was loaded stays the last loaded one, so the
+        // original overlay shows it next to the laid out map.
+        if auto_layout {
+            interaction_state.update(|state| state.set_layout_requested(true));
+        }
+    };
+
+    // Decode the file in the worker and load the map or background shapes. A
+    // map with more stations or edges, or a background with more shapes, than
+    // the maximum import size is only loaded once the user confirms.
+    let load_file = move |file_type: FileType, contents: String, auto_layout: bool| {
+        let (canvas_state, grid_transform, max_size) = map_state.with_untracked(|state| {
+            (
+                state.get_canvas_state(),
+                state
+                    .get_map()
+                    .get_grid_transform(),
+                state.get_max_import_size(),
+            )
+        });
+        let req = DecodeRequest {
+            file_type,
+            contents,
+            canvas_state,
+            id_manager_data: IDManager::to_data(),
+            max_size,
+            grid_transform,
+        };
+        let (handle, resp) = executor
+            .get_untracked()
+            .run(req)
+            .expect("failed to start decoding worker");
+
+        load_count.update_value(|count| *count += 1);
+        let load = load_count.get_value();
+        set_abort_handle(Some(handle));
+        if !interaction_state.with_untracked(InteractionState::is_busy) {
+            marked_busy.set_value(true);
+            interaction_state.update(|state| {
+                state.set_busy(true);
+                state.set_cursor("wait");
+            });
+        }
+
+        spawn_local(async move {
+            let resp = resp.await;
+            if load_count.get_value() != load {
+                return;
+            }
+            finish_loading();
+
+            IDManager::from_data(resp.id_manager_data);
+            let decoded = unwrap_or_return!(error_state, resp.result);
+            if let Some(err) = resp.size_error {
+                too_large_import.set_value(Some((decoded, auto_layout)));
+                set_large_import_message(err.to_user_friendly_string());
+                set_show_large_import_modal(true);
+                return;
+            }
+
+            load_decoded(decoded, auto_layout);
+        });
+    };
+
+    let on_submit = move |file_type: FileType, contents: String, auto_layout: bool| {
+        set_show_file_modal(false);
+        load_file(file_type, contents, auto_layout);
+    };
+
+    // Load the decoded file that was too large regardless of its size.
+    let load_too_large = Callback::new(move |()| {
+        if let Some((decoded, auto_layout)) = too_large_import
+            .try_update_value(Option::take)
+            .flatten()
+        {
+            logging::warn!(
+                "loading map larger than the import limit as confirmed by the user: {}",
+                large_import_message.get_untracked()
+            );
+            load_decoded(decoded, auto_layout);
+        }
+    });
+
+    // Cancel loading the file, leaving the current map as it is.
+    let cancel_loading = move |_| {
+        if let Some(handle) = abort_handle.get_untracked() {
+            handle.abort();
+            load_count.update_value(|count| *count += 1);
+            finish_loading();
+        }
     };
 
     view! {
@@ -70,7 +354,14 @@ pub fn Navbar() -> impl IntoView {
             <Button text="Advanced Settings" outlined=true can_focus=true on_click=Box::new(move |_| set_show_settings_modal(true))/>
             <MapExporter/>
             <FileDownloader/>
-            <Button text="Upload File" outlined=true can_focus=true on_click=Box::new(move |_| set_show_file_modal(true))/>
+            <Show
+                when=move || abort_handle.get().is_some()
+                fallback=move || view! {
+                    <Button text="Upload File" outlined=true can_focus=true on_click=Box::new(move |_| set_show_file_modal(true))/>
+                }>
+                <span class="animate-pulse text-black dark:text-white">loading map...</span>
+                <Button text="Cancel Loading" outlined=true danger=true never_too_busy=true on_click=Box::new(cancel_loading)/>
+            </Show>
         </div>
       </div>
     </nav>
@@ -78,6 +369,11 @@ pub fn Navbar() -> impl IntoView {
         show=show_file_modal
         on_close=move || set_show_file_modal(false)
         on_submit=on_submit />
+    <LargeImportModal
+        show=show_large_import_modal
+        message=large_import_message
+        on_close=move || set_show_large_import_modal(false)
+        on_confirm=load_too_large />
     <SettingsModal
         show=show_settings_modal
         on_close=move || set_show_settings_modal(false) />
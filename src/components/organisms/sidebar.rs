@@ -3,13 +3,23 @@
 use leptos::prelude::*;
 
 use crate::{
-    algorithms::straighten_line,
+    algorithms::{
+        distribute_evenly,
+        straighten_line,
+    },
     components::{
         atoms::{
             Button,
             ButtonGroup,
             ButtonProps,
         },
+        molecules::{
+            AddLineModal,
+            ClearMapModal,
+            LabelOverlapPanel,
+            LineStatsPanel,
+            StraightenPanel,
+        },
         state::ActionType,
         ErrorState,
         MapState,
@@ -17,7 +27,6 @@ use crate::{
     models::{
         Line,
         Map,
-        SelectedLine,
         SelectedStation,
         Station,
     },
@@ -31,6 +40,7 @@ pub fn Sidebar() -> impl IntoView {
         use_context::<RwSignal<MapState>>().expect("to have found the global map state");
     let error_state =
         use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
+    let (show_clear_modal, set_show_clear_modal) = signal(false);
 
     let action_selected = move |action| {
         Signal::derive(move || {
@@ -72,13 +82,22 @@ pub fn Sidebar() -> impl IntoView {
         });
     };
 
-    let add_line = move |_| {
-        map_state.update(|state| {
-            state.clear_all_selections();
-            let line = SelectedLine::new_line(state.get_mut_map());
-            state.set_selected_lines(vec![line]);
-        });
+    // New lines are named and colored in a dialog before they are drawn.
+    let (show_add_line_modal, set_show_add_line_modal) = signal(false);
+    let open_add_line = move || {
+        map_state.update(MapState::clear_all_selections);
+        set_show_add_line_modal(true);
+    };
+    let add_line = move |_| open_add_line();
+
+    let draw_line = move |_| {
+        if map_state.with_untracked(MapState::is_drawing_line) {
+            map_state.update(MapState::finish_drawing_line);
+        } else {
+            open_add_line();
+        }
     };
+    let drawing_line = Signal::derive(move || map_state.with(MapState::is_drawing_line));
 
     let remove_station = move |_| update_action(ActionType::RemoveStation);
     let remove_station_selected = action_selected(ActionType::RemoveStation);
@@ -127,25 +146,22 @@ pub fn Sidebar() -> impl IntoView {
     };
     let unlock_selected = action_selected(ActionType::Unlock);
 
+    let lock_all = move |_| {
+        map_state.update(|state| {
+            state.clear_all_selections();
+            state.lock_all();
+        });
+    };
+    let unlock_all = move |_| {
+        map_state.update(|state| {
+            state.clear_all_selections();
+            state.unlock_all();
+        });
+    };
+
     let straighten_line = move |_| {
         map_state.update(|state| {
-            let selected_stations = state
-                .get_selected_stations()
-                .iter()
-                .map(SelectedStation::get_station)
-                .cloned()
-                .collect::<Vec<_>>();
-            let selected_edges = state
-                .get_selected_edges()
-                .iter()
-                .map(|id| {
-                    state
-                        .get_map()
-                        .get_edge(*id)
-                        .unwrap()
-                })
-                .cloned()
-                .collect::<Vec<_>>();
+            let (selected_edges, selected_stations) = state.get_selected_section();
 
             state.clear_all_selections();
 
@@ -153,13 +169,54 @@ pub fn Sidebar() -> impl IntoView {
                 error_state,
                 straighten_line(
                     state.get_algorithm_settings(),
-                    state.get_mut_map(),
+                    state.get_mut_map("straightened line"),
                     &selected_edges,
                     &selected_stations,
                 )
             );
         });
     };
+    let distribute_evenly = move |_| {
+        map_state.update(|state| {
+            let (selected_edges, selected_stations) = state.get_selected_section();
+            let mut map = state
+                .get_map()
+                .clone();
+
+            unwrap_or_return!(
+                error_state,
+                distribute_evenly(
+                    &mut map,
+                    &selected_edges,
+                    &selected_stations
+                )
+            );
+
+            state.clear_all_selections();
+            state.set_map(map, "distributed stations");
+        });
+    };
+    let snap_to_grid = move |_| {
+        map_state.update(|state| {
+            state
+                .get_mut_map("snapped to grid")
+                .snap_to_grid();
+        });
+    };
+    let recompute_edges = move |_| {
+        map_state.update(|state| {
+            state
+                .get_mut_map("recomputed edges")
+                .quickcalc_edges();
+        });
+    };
+    let normalize_line_directions = move |_| {
+        map_state.update(|state| {
+            state
+                .get_mut_map("normalized line directions")
+                .normalize_line_directions();
+        });
+    };
     let cannot_straighten = move || {
         let state = map_state.get();
         state
@@ -176,9 +233,13 @@ pub fn Sidebar() -> impl IntoView {
             <Button
                 on_click=Box::new(move |_| map_state.update(|state| {
                     state.clear_all_selections();
-                    state.set_map(state.get_last_loaded().cloned().unwrap_or_else(testmap));
+                    state.set_map(state.get_last_loaded().cloned().unwrap_or_else(testmap), "reset map");
                 }))
                 text="reset map" />
+            <Button
+                on_click=Box::new(move |_| set_show_clear_modal(true))
+                danger=true
+                text="clear map" />
             <ButtonGroup
                 children={vec![
                     ButtonProps::builder()
@@ -200,6 +261,11 @@ pub fn Sidebar() -> impl IntoView {
                         .on_click(Box::new(add_line))
                         .can_focus(true)
                         .build(),
+                    ButtonProps::builder()
+                        .text("Draw Line")
+                        .on_click(Box::new(draw_line))
+                        .active(drawing_line)
+                        .build(),
                     ButtonProps::builder()
                         .text("Remove Line")
                         .on_click(Box::new(remove_line))
@@ -221,6 +287,18 @@ pub fn Sidebar() -> impl IntoView {
                         .danger(true)
                         .build(),
                 ]}/>
+            <ButtonGroup
+                children={vec![
+                    ButtonProps::builder()
+                        .text("Lock All")
+                        .on_click(Box::new(lock_all))
+                        .build(),
+                    ButtonProps::builder()
+                        .text("Unlock All")
+                        .on_click(Box::new(unlock_all))
+                        .danger(true)
+                        .build(),
+                ]}/>
             <ButtonGroup
                 children={vec![
                     ButtonProps::builder()
@@ -239,7 +317,29 @@ pub fn Sidebar() -> impl IntoView {
                 on_click=Box::new(straighten_line)
                 disabled=Signal::derive(cannot_straighten)
                 text="straighten selected" />
+            <Button
+                on_click=Box::new(distribute_evenly)
+                disabled=Signal::derive(cannot_straighten)
+                text="distribute evenly" />
+            <Button
+                on_click=Box::new(snap_to_grid)
+                text="snap to grid" />
+            <Button
+                on_click=Box::new(recompute_edges)
+                text="recompute edges" />
+            <Button
+                on_click=Box::new(normalize_line_directions)
+                text="normalize line directions" />
+            <StraightenPanel/>
+            <LabelOverlapPanel/>
+            <LineStatsPanel/>
         </div>
+        <ClearMapModal
+            show=show_clear_modal
+            on_close=move || set_show_clear_modal(false) />
+        <AddLineModal
+            show=show_add_line_modal
+            on_close=move || set_show_add_line_modal(false) />
     }
 }
 
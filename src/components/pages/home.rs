@@ -3,7 +3,10 @@
 use leptos::prelude::*;
 
 use crate::components::{
-    molecules::ErrorBox,
+    molecules::{
+        ErrorBox,
+        StatusBar,
+    },
     organisms::{
         CanvasControls,
         Navbar,
@@ -27,5 +30,6 @@ pub fn Home() -> impl IntoView {
                     <CanvasControls/>
                 </div>
             </div>
+            <StatusBar/>
     }
 }
@@ -1,5 +1,10 @@
-//! The pages of the site (currently just one (home) page)
+//! The pages of the site, the editor (home) page and the read-only viewer page.
 
 mod home;
+mod viewer;
 
 pub use home::Home;
+pub use viewer::{
+    viewer_map_url,
+    Viewer,
+};
@@ -0,0 +1,116 @@
+//! Contains the [`Viewer`] page component
+
+use leptos::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    Response,
+    UrlSearchParams,
+};
+
+use crate::{
+    components::{
+        canvas::Canvas,
+        molecules::ErrorBox,
+        ErrorState,
+        MapState,
+    },
+    unwrap_or_return,
+    utils::{
+        graphml,
+        json,
+        Result,
+    },
+    Error,
+};
+
+/// The name of the query parameter holding the url of the map to show in the
+/// read-only viewer.
+const VIEWER_QUERY_PARAM: &str = "view";
+
+/// Get the url of the map to show in the read-only viewer from the query of the
+/// page url, if the page should be the viewer.
+pub fn viewer_map_url() -> Option<String> {
+    let query = window()
+        .location()
+        .search()
+        .ok()?;
+
+    UrlSearchParams::new_with_str(&query)
+        .ok()?
+        .get(VIEWER_QUERY_PARAM)
+        .filter(|url| !url.is_empty())
+}
+
+/// Fetch the contents of the file at the given url as text.
+async fn fetch_text(url: &str) -> Result<String> {
+    let response = JsFuture::from(window().fetch_with_str(url))
+        .await?
+        .dyn_into::<Response>()?;
+
+    if !response.ok() {
+        return Err(Error::other(format!(
+            "failed to load map from {url}: status {}",
+            response.status()
+        )));
+    }
+
+    JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .ok_or(Error::other(format!(
+            "map at {url} is not a text file"
+        )))
+}
+
+/// A read-only page that only shows the map, for embedding a finished map.
+/// The map can be panned and zoomed, but not edited.
+#[component]
+pub fn Viewer(
+    /// The url of the map file to show.
+    map_url: String,
+) -> impl IntoView {
+    let map_state =
+        use_context::<RwSignal<MapState>>().expect("to have found the global map state");
+    let error_state =
+        use_context::<RwSignal<ErrorState>>().expect("to have found the global error state");
+
+    map_state.update(|state| state.set_read_only(true));
+
+    let load_map = Action::new_local(move |url: &String| {
+        let url = url.clone();
+        async move {
+            let contents = unwrap_or_return!(error_state, fetch_text(&url).await);
+
+            map_state.update(|state| {
+                let is_graphml = url
+                    .split(['?', '#'])
+                    .next()
+                    .is_some_and(|path| {
+                        path.to_ascii_lowercase()
+                            .ends_with(".graphml")
+                    });
+
+                let (map, warnings) = unwrap_or_return!(
+                    error_state,
+                    if is_graphml {
+                        graphml::decode_map_lenient(&contents, state.get_canvas_state())
+                    } else {
+                        json::decode_map_lenient(&contents, state.get_canvas_state())
+                    }
+                );
+
+                error_state.update(|error_state| error_state.set_warnings(warnings));
+                state.set_map_no_history(map);
+            });
+        }
+    });
+    load_map.dispatch(map_url);
+
+    view! {
+            <ErrorBox/>
+            <div class="grow flex self-stretch relative">
+                <Canvas/>
+            </div>
+    }
+}
@@ -1,9 +1,44 @@
 //! Contains the [`CanvasState`] struct and its methods.
 
-use crate::models::GridNode;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    algorithms::{
+        ColorVision,
+        LabelFont,
+        Theme,
+    },
+    models::{
+        GridNode,
+        LineID,
+    },
+};
+
+/// How far the center of the canvas may be panned outside the bounds of the
+/// map when panning is limited, as a fraction of the visible width and height.
+/// Being smaller than a half ensures part of the map always stays visible.
+const PAN_MARGIN_FRACTION: f64 = 0.4;
+/// The amount of grid squares kept free around the map when zooming it to fit
+/// the canvas.
+const FIT_MARGIN: i32 = 2;
+
+/// The part of the map that is looked at on the canvas, which is stored in
+/// the view history.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CanvasView {
+    /// The height and width offset from panning the canvas.
+    pub offset: (i32, i32),
+    /// How much the canvas is zoomed in.
+    pub zoom_factor: f64,
+}
 
 /// Contains the current state of the canvas.
-#[derive(Clone, Copy, Debug)]
+// This also holds the display settings, so many bools are needed
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct CanvasState {
     /// The height and width of the current canvas.
     size: (f64, f64),
@@ -19,6 +54,64 @@ pub struct CanvasState {
     y_limit: (i32, i32),
     /// The size of the sidebar and navbar, which border the canvas.
     neighbor_sizes: (f64, f64),
+    /// If lines should also be distinguished by dash pattern and label.
+    color_blind_mode: bool,
+    /// The type of color vision the map is drawn with.
+    color_vision: ColorVision,
+    /// How rounded the bends in edges are drawn, as a fraction of the square
+    /// size. Zero draws sharp corners.
+    corner_radius_factor: f64,
+    /// How much wider every line on an edge is drawn for each other line
+    /// sharing the edge, as a fraction of the normal width. Zero draws every
+    /// line with the same width.
+    line_width_scaling: f64,
+    /// If stations are drawn with a size proportional to their weight.
+    weighted_station_size: bool,
+    /// The font family labels are drawn with.
+    label_font: LabelFont,
+    /// The size labels are drawn with, as a fraction of the square size.
+    label_size: f64,
+    /// The line and index of the service pattern that is displayed, if any.
+    active_pattern: Option<(LineID, usize)>,
+    /// If the ruler and scale bar are drawn along the edges of the canvas.
+    show_ruler: bool,
+    /// If the ruler and scale bar are included when exporting the map as an
+    /// image.
+    ruler_in_export: bool,
+    /// If exported images only show the part of the map that is currently
+    /// visible on the canvas, instead of the whole map.
+    export_current_view: bool,
+    /// If the real-time updates of the algorithm are recorded and downloaded
+    /// as an animated image once it finishes.
+    record_animation: bool,
+    /// The frames per second of the recorded animation.
+    animation_fps: u32,
+    /// The width in pixels of the recorded animation, its height follows from
+    /// the shape of the canvas.
+    animation_width: u32,
+    /// The bounds of the map, which panning is limited to.
+    pan_bounds: Option<(GridNode, GridNode)>,
+    /// If panning is limited to the bounds of the map.
+    pan_limited: bool,
+    /// The color theme the canvas is drawn with.
+    theme: Theme,
+    /// If short ticks are drawn along edges where they pass through stops.
+    stop_ticks: bool,
+    /// If the lines on every edge with multiple lines are drawn bundled into a
+    /// single stroke.
+    bundle_lines: bool,
+    /// If the name of every line is drawn in an endcap at its termini.
+    endcaps: bool,
+    /// If the labels with the names of stations and lines are drawn.
+    show_labels: bool,
+    /// If a halo is drawn beneath the lines, to make them stand out where they
+    /// cross each other or the background image.
+    halo: bool,
+    /// The color of the halo drawn beneath the lines.
+    halo_color: (u8, u8, u8),
+    /// How far the halo extends beyond the lines on either side, as a fraction
+    /// of the width of a line.
+    halo_width: f64,
 }
 
 impl CanvasState {
@@ -32,6 +125,30 @@ impl CanvasState {
             x_limit: (0, 0),
             y_limit: (0, 0),
             neighbor_sizes: (0.0, 0.0),
+            color_blind_mode: false,
+            color_vision: ColorVision::Normal,
+            corner_radius_factor: 0.0,
+            line_width_scaling: 0.0,
+            weighted_station_size: false,
+            label_font: LabelFont::SansSerif,
+            label_size: 0.5,
+            active_pattern: None,
+            show_ruler: false,
+            ruler_in_export: false,
+            export_current_view: false,
+            record_animation: false,
+            animation_fps: 10,
+            animation_width: 800,
+            pan_bounds: None,
+            pan_limited: true,
+            theme: Theme::Light,
+            stop_ticks: false,
+            bundle_lines: false,
+            endcaps: false,
+            show_labels: true,
+            halo: false,
+            halo_color: (255, 255, 255),
+            halo_width: 0.75,
         };
         s.recalculate_limits();
         s
@@ -227,6 +344,317 @@ impl CanvasState {
         f64::from(self.get_square_size()) * self.get_zoom_factor()
     }
 
+    /// If the color-blind-safe rendering mode is enabled.
+    pub fn is_color_blind_mode(&self) -> bool {
+        self.color_blind_mode
+    }
+
+    /// Enable or disable the color-blind-safe rendering mode.
+    pub fn set_color_blind_mode(&mut self, enabled: bool) {
+        self.color_blind_mode = enabled;
+    }
+
+    /// A getter method for the simulated color vision.
+    pub fn get_color_vision(&self) -> ColorVision {
+        self.color_vision
+    }
+
+    /// A setter method for the simulated color vision.
+    pub fn set_color_vision(&mut self, color_vision: ColorVision) {
+        self.color_vision = color_vision;
+    }
+
+    /// A getter method for the color theme the canvas is drawn with.
+    pub fn get_theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// A setter method for the color theme the canvas is drawn with.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// If short ticks are drawn along edges where they pass through stops.
+    pub fn is_stop_ticks_shown(&self) -> bool {
+        self.stop_ticks
+    }
+
+    /// Enable or disable drawing short ticks along edges where they pass
+    /// through stops.
+    pub fn set_stop_ticks_shown(&mut self, shown: bool) {
+        self.stop_ticks = shown;
+    }
+
+    /// If the lines on every edge with multiple lines are drawn bundled into a
+    /// single stroke.
+    pub fn is_bundling_lines(&self) -> bool {
+        self.bundle_lines
+    }
+
+    /// Enable or disable drawing the lines on every edge with multiple lines
+    /// bundled into a single stroke.
+    pub fn set_bundling_lines(&mut self, bundle: bool) {
+        self.bundle_lines = bundle;
+    }
+
+    /// If the name of every line is drawn in an endcap at its termini.
+    pub fn is_endcaps_shown(&self) -> bool {
+        self.endcaps
+    }
+
+    /// Enable or disable drawing the name of every line in an endcap at its
+    /// termini.
+    pub fn set_endcaps_shown(&mut self, shown: bool) {
+        self.endcaps = shown;
+    }
+
+    /// If the labels with the names of stations and lines are drawn.
+    pub fn is_labels_shown(&self) -> bool {
+        self.show_labels
+    }
+
+    /// Show or hide the labels with the names of stations and lines.
+    pub fn set_labels_shown(&mut self, shown: bool) {
+        self.show_labels = shown;
+    }
+
+    /// If a halo is drawn beneath the lines.
+    pub fn is_halo_shown(&self) -> bool {
+        self.halo
+    }
+
+    /// Enable or disable drawing a halo beneath the lines.
+    pub fn set_halo_shown(&mut self, shown: bool) {
+        self.halo = shown;
+    }
+
+    /// A getter method for the color of the halo drawn beneath the lines.
+    pub fn get_halo_color(&self) -> (u8, u8, u8) {
+        self.halo_color
+    }
+
+    /// A setter method for the color of the halo drawn beneath the lines.
+    pub fn set_halo_color(&mut self, color: (u8, u8, u8)) {
+        self.halo_color = color;
+    }
+
+    /// A getter method for how far the halo extends beyond the lines on either
+    /// side, as a fraction of the width of a line.
+    pub fn get_halo_width(&self) -> f64 {
+        self.halo_width
+    }
+
+    /// A setter method for how far the halo extends beyond the lines on either
+    /// side, as a fraction of the width of a line. Negative values are treated
+    /// as zero.
+    pub fn set_halo_width(&mut self, width: f64) {
+        self.halo_width = width.max(0.0);
+    }
+
+    /// A getter method for the corner radius of bends in edges, as a fraction
+    /// of the square size.
+    pub fn get_corner_radius_factor(&self) -> f64 {
+        self.corner_radius_factor
+    }
+
+    /// A setter method for the corner radius of bends in edges, as a fraction
+    /// of the square size. Negative values are treated as zero.
+    pub fn set_corner_radius_factor(&mut self, factor: f64) {
+        self.corner_radius_factor = factor.max(0.0);
+    }
+
+    /// A getter method for how much wider every line on an edge is drawn for
+    /// each other line sharing the edge.
+    pub fn get_line_width_scaling(&self) -> f64 {
+        self.line_width_scaling
+    }
+
+    /// A setter method for how much wider every line on an edge is drawn for
+    /// each other line sharing the edge. Negative values are treated as zero.
+    pub fn set_line_width_scaling(&mut self, scaling: f64) {
+        self.line_width_scaling = scaling.max(0.0);
+    }
+
+    /// The width every line on an edge shared by the given amount of lines is
+    /// drawn with, given the normal width of a line.
+    pub fn scaled_line_width(&self, width: f64, line_count: usize) -> f64 {
+        width * (1.0 + self.line_width_scaling * line_count.saturating_sub(1) as f64)
+    }
+
+    /// If stations are drawn with a size proportional to their weight.
+    pub fn is_weighted_station_size(&self) -> bool {
+        self.weighted_station_size
+    }
+
+    /// Enable or disable drawing stations with a size proportional to their
+    /// weight.
+    pub fn set_weighted_station_size(&mut self, enabled: bool) {
+        self.weighted_station_size = enabled;
+    }
+
+    /// A getter method for the font family labels are drawn with.
+    pub fn get_label_font(&self) -> LabelFont {
+        self.label_font
+    }
+
+    /// A setter method for the font family labels are drawn with.
+    pub fn set_label_font(&mut self, font: LabelFont) {
+        self.label_font = font;
+    }
+
+    /// A getter method for the size labels are drawn with, as a fraction of
+    /// the square size.
+    pub fn get_label_size(&self) -> f64 {
+        self.label_size
+    }
+
+    /// A setter method for the size labels are drawn with, as a fraction of
+    /// the square size. Negative sizes are clamped to zero.
+    pub fn set_label_size(&mut self, size: f64) {
+        self.label_size = size.max(0.0);
+    }
+
+    /// Get the size labels are drawn with in pixels, taking the zoom into
+    /// account. Labels are never drawn smaller than 8 pixels to keep them
+    /// readable.
+    pub fn drawn_label_size(&self) -> f64 {
+        (self.drawn_square_size() * self.label_size).max(8.0)
+    }
+
+    /// Get the CSS font string labels are drawn with, as used by the canvas.
+    pub fn label_font_css(&self) -> String {
+        format!(
+            "{}px {}",
+            self.drawn_label_size(),
+            self.label_font
+                .css_family()
+        )
+    }
+
+    /// A getter method for the line and index of the service pattern that is
+    /// displayed.
+    pub fn get_active_pattern(&self) -> Option<(LineID, usize)> {
+        self.active_pattern
+    }
+
+    /// A setter method for the line and index of the service pattern that is
+    /// displayed, [`None`] displays no pattern.
+    pub fn set_active_pattern(&mut self, pattern: Option<(LineID, usize)>) {
+        self.active_pattern = pattern;
+    }
+
+    /// A getter method for if the ruler and scale bar are drawn.
+    pub fn is_ruler_shown(&self) -> bool {
+        self.show_ruler
+    }
+
+    /// A setter method for if the ruler and scale bar are drawn.
+    pub fn set_ruler_shown(&mut self, shown: bool) {
+        self.show_ruler = shown;
+    }
+
+    /// A getter method for if the ruler and scale bar are included in exported
+    /// images.
+    pub fn is_ruler_in_export(&self) -> bool {
+        self.ruler_in_export
+    }
+
+    /// A setter method for if the ruler and scale bar are included in exported
+    /// images.
+    pub fn set_ruler_in_export(&mut self, included: bool) {
+        self.ruler_in_export = included;
+    }
+
+    /// A getter method for if exported images only show the part of the map
+    /// that is currently visible on the canvas.
+    pub fn is_export_current_view(&self) -> bool {
+        self.export_current_view
+    }
+
+    /// A setter method for if exported images only show the part of the map
+    /// that is currently visible on the canvas.
+    pub fn set_export_current_view(&mut self, current_view: bool) {
+        self.export_current_view = current_view;
+    }
+
+    /// A getter method for if the real-time updates of the algorithm are
+    /// recorded as an animated image.
+    pub fn is_record_animation(&self) -> bool {
+        self.record_animation
+    }
+
+    /// A setter method for if the real-time updates of the algorithm are
+    /// recorded as an animated image.
+    pub fn set_record_animation(&mut self, record: bool) {
+        self.record_animation = record;
+    }
+
+    /// A getter method for the frames per second of the recorded animation.
+    pub fn get_animation_fps(&self) -> u32 {
+        self.animation_fps
+    }
+
+    /// A setter method for the frames per second of the recorded animation.
+    /// It is kept at a minimum of one frame per second.
+    pub fn set_animation_fps(&mut self, fps: u32) {
+        self.animation_fps = fps.max(1);
+    }
+
+    /// A getter method for the width in pixels of the recorded animation.
+    pub fn get_animation_width(&self) -> u32 {
+        self.animation_width
+    }
+
+    /// A setter method for the width in pixels of the recorded animation. It is
+    /// kept at a minimum of one pixel.
+    pub fn set_animation_width(&mut self, width: u32) {
+        self.animation_width = width.max(1);
+    }
+
+    /// If panning is limited to the bounds of the map.
+    pub fn is_pan_limited(&self) -> bool {
+        self.pan_limited
+    }
+
+    /// Set if panning is limited to the bounds of the map.
+    pub fn set_pan_limited(&mut self, limited: bool) {
+        self.pan_limited = limited;
+        self.recalculate_limits();
+    }
+
+    /// Set the bounds of the map, which panning is limited to.
+    pub fn set_pan_bounds(&mut self, bounds: Option<(GridNode, GridNode)>) {
+        self.pan_bounds = bounds;
+    }
+
+    /// Zoom and pan the canvas so the given bounds of a map fit on it and are
+    /// centered.
+    pub fn fit_to(&mut self, bounds: Option<(GridNode, GridNode)>) {
+        let Some((min, max)) = bounds else {
+            return;
+        };
+
+        let width = f64::from(max.0 - min.0 + 2 * FIT_MARGIN);
+        let height = f64::from(max.1 - min.1 + 2 * FIT_MARGIN);
+        let zoom = (self
+            .size
+            .1
+            / width)
+            .min(
+                self.size
+                    .0
+                    / height,
+            )
+            / f64::from(self.square_size);
+
+        self.zoom_factor = zoom.max(0.2);
+        self.pan_bounds = bounds;
+        self.center_on(GridNode::from((
+            i32::midpoint(min.0, max.0),
+            i32::midpoint(min.1, max.1),
+        )));
+    }
+
     /// A getter method for the offset
     pub fn get_offset(&self) -> (i32, i32) {
         self.offset
@@ -238,6 +666,30 @@ impl CanvasState {
         self.recalculate_limits();
     }
 
+    /// Get the part of the map that is currently looked at.
+    pub fn get_view(&self) -> CanvasView {
+        CanvasView {
+            offset: self.offset,
+            zoom_factor: self.zoom_factor,
+        }
+    }
+
+    /// Look at the given part of the map.
+    pub fn set_view(&mut self, view: CanvasView) {
+        self.offset = view.offset;
+        self.zoom_factor = view.zoom_factor;
+        self.recalculate_limits();
+    }
+
+    /// Move the visible part of the canvas so the given node is in its center.
+    pub fn center_on(&mut self, node: GridNode) {
+        let (width, height) = self.get_visible_size();
+        self.set_offset((
+            node.0 - (width / 2) as i32,
+            node.1 - (height / 2) as i32,
+        ));
+    }
+
     /// A getter method for the neighbor sizes
     pub fn get_neighbor_sizes(&self) -> (f64, f64) {
         self.neighbor_sizes
@@ -262,6 +714,8 @@ impl CanvasState {
             / self.drawn_square_size())
         .round() as i32;
 
+        self.clamp_offset(width_node_count, height_node_count);
+
         self.x_limit = (
             self.offset
                 .0,
@@ -280,6 +734,45 @@ impl CanvasState {
         );
     }
 
+    /// Clamps the offset so the center of the canvas stays near the bounds of
+    /// the map if panning is limited, given the amount of visible nodes. Along
+    /// an axis the whole map is already visible on, the offset is kept as is.
+    fn clamp_offset(&mut self, width_node_count: i32, height_node_count: i32) {
+        let Some((min, max)) = self
+            .pan_bounds
+            .filter(|_| self.pan_limited)
+        else {
+            return;
+        };
+
+        let clamp_axis = |offset: i32, min: i32, max: i32, node_count: i32| {
+            if min >= offset && max <= offset + node_count {
+                return offset;
+            }
+
+            let half = node_count / 2;
+            let margin = (f64::from(node_count) * PAN_MARGIN_FRACTION).round() as i32;
+            offset.clamp(min - margin - half, max + margin - half)
+        };
+
+        self.offset = (
+            clamp_axis(
+                self.offset
+                    .0,
+                min.0,
+                max.0,
+                width_node_count,
+            ),
+            clamp_axis(
+                self.offset
+                    .1,
+                min.1,
+                max.1,
+                height_node_count,
+            ),
+        );
+    }
+
     /// Returns true if the given grid node is on the canvas.
     #[inline]
     pub fn is_on_canvas(&self, node: GridNode) -> bool {
@@ -310,3 +803,51 @@ impl Default for CanvasState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pan_limits() {
+        let mut state = CanvasState::new();
+        state.set_square_size(10);
+        state.set_size((100.0, 100.0));
+        state.set_pan_bounds(Some((
+            GridNode::from((0, 0)),
+            GridNode::from((20, 20)),
+        )));
+
+        // 10 nodes are visible, so the center may be 4 nodes outside the map.
+        state.set_offset((100, -100));
+        assert_eq!(state.get_offset(), (19, -9));
+
+        // The view is not moved along an axis the whole map is visible on.
+        state.set_pan_bounds(Some((
+            GridNode::from((0, 0)),
+            GridNode::from((20, 0)),
+        )));
+        state.set_offset((0, 0));
+        assert_eq!(state.get_offset(), (0, 0));
+
+        state.set_pan_limited(false);
+        state.set_offset((100, -100));
+        assert_eq!(state.get_offset(), (100, -100));
+    }
+
+    #[test]
+    fn test_fit_to() {
+        let mut state = CanvasState::new();
+        state.set_square_size(10);
+        state.set_size((100.0, 200.0));
+        state.fit_to(Some((
+            GridNode::from((10, 10)),
+            GridNode::from((26, 14)),
+        )));
+
+        assert!((state.get_zoom_factor() - 1.0).abs() < f64::EPSILON);
+        assert!(state.is_on_canvas(GridNode::from((10, 10))));
+        assert!(state.is_on_canvas(GridNode::from((26, 14))));
+        assert_eq!(state.get_offset(), (8, 7));
+    }
+}
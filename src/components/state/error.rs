@@ -1,6 +1,9 @@
 //! Contains the [`ErrorState`] and its methods.
 
-use crate::Error;
+use crate::{
+    utils::DecodeWarning,
+    Error,
+};
 
 /// Contains the current error state.
 /// This state is then used to display the error message to the user.
@@ -8,6 +11,8 @@ use crate::Error;
 pub struct ErrorState {
     /// The error last encountered.
     error: Option<Error>,
+    /// The warnings about skipped elements of the last loaded map file.
+    warnings: Vec<DecodeWarning>,
 }
 
 impl ErrorState {
@@ -15,6 +20,7 @@ impl ErrorState {
     pub fn new() -> Self {
         Self {
             error: None,
+            warnings: Vec::new(),
         }
     }
 
@@ -39,4 +45,27 @@ impl ErrorState {
     pub fn clear_error(&mut self) {
         self.error = None;
     }
+
+    /// Sets the warnings about skipped elements to be displayed.
+    pub fn set_warnings(&mut self, warnings: Vec<DecodeWarning>) {
+        self.warnings = warnings;
+    }
+
+    /// Returns if there are warnings to be displayed.
+    pub fn has_warnings(&self) -> bool {
+        !self
+            .warnings
+            .is_empty()
+    }
+
+    /// Gets the current warnings.
+    pub fn get_warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
+    /// Clears the current warnings.
+    pub fn clear_warnings(&mut self) {
+        self.warnings
+            .clear();
+    }
 }
@@ -1,22 +1,63 @@
 //! Contains everything for being able to redo and undo map changes.
 
 use std::{
+    cell::RefCell,
     collections::VecDeque,
-    sync::{
-        LazyLock,
-        Mutex,
-    },
 };
 
-use crate::models::Map;
+use super::CanvasView;
+use crate::models::{
+    EdgeID,
+    Map,
+    SelectedLine,
+    StationID,
+};
+
+/// The maximum amount of views kept in either direction of the view history.
+const VIEW_HISTORY_SIZE: usize = 50;
+
+// The history is only used from the thread the editor runs on, keeping it per
+// thread also gives every test its own history.
+thread_local! {
+    /// The stack that contains the past maps.
+    static PAST_STACK: RefCell<BoundedStack<5, HistoryEntry>> =
+        RefCell::new(BoundedStack::new());
+    /// The stack that contains maps with changes that were undone by the user.
+    static FUTURE_STACK: RefCell<BoundedStack<5, HistoryEntry>> =
+        RefCell::new(BoundedStack::new());
+    /// The stack that contains the views that were looked at before, kept
+    /// apart from the map history.
+    static VIEW_PAST_STACK: RefCell<BoundedStack<VIEW_HISTORY_SIZE, CanvasView>> =
+        RefCell::new(BoundedStack::new());
+    /// The stack that contains the views that were stepped back from.
+    static VIEW_FUTURE_STACK: RefCell<BoundedStack<VIEW_HISTORY_SIZE, CanvasView>> =
+        RefCell::new(BoundedStack::new());
+}
 
-/// The stack that contains the past maps.
-static PAST_STACK: LazyLock<Mutex<BoundedStack<5, Map>>> =
-    LazyLock::new(|| Mutex::new(BoundedStack::new()));
-/// The stack that contains maps with changes that were undone by the user.
-static FUTURE_STACK: LazyLock<Mutex<BoundedStack<5, Map>>> =
-    LazyLock::new(|| Mutex::new(BoundedStack::new()));
+/// The objects the user had selected at the time a map was stored in the
+/// history, so they can be selected again when the map is restored.
+#[derive(Clone, Debug, Default)]
+pub struct HistorySelection {
+    /// The ids of the selected stations.
+    pub stations: Vec<StationID>,
+    /// The ids of the selected edges.
+    pub edges: Vec<EdgeID>,
+    /// The selected lines.
+    pub lines: Vec<SelectedLine>,
+}
 
+/// A map stored in the history together with the selection at that time and
+/// a short description of the change that separates it from the map that came
+/// after it.
+#[derive(Clone, Debug)]
+struct HistoryEntry {
+    /// The description of the change, e.g. "moved station".
+    label: String,
+    /// The stored map.
+    map: Map,
+    /// The selection at the time the map was stored.
+    selection: HistorySelection,
+}
 /// A stack that is bounded to a certain size.
 struct BoundedStack<const N: usize, T> {
     /// The stack itself.
@@ -56,6 +97,74 @@ impl<const N: usize, T> BoundedStack<N, T> {
         self.stack
             .clear();
     }
+
+    /// The item that was pushed most recently, if any.
+    fn peek(&self) -> Option<&T> {
+        self.stack
+            .back()
+    }
+
+    /// The amount of items on the stack.
+    fn len(&self) -> usize {
+        self.stack
+            .len()
+    }
+
+    /// Iterate over the items on the stack, starting with the most recently
+    /// pushed one.
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        self.stack
+            .iter()
+            .rev()
+    }
+}
+
+/// Moves the given amount of entries from the source stack onto the target
+/// stack, starting from the current map and selection, and returns the map
+/// and selection that were reached. Every map that is passed by is pushed onto
+/// the target stack with the label of the change it was separated by.
+///
+/// Returns [`None`] and leaves the stacks untouched if the source stack does
+/// not contain enough entries.
+fn travel<const N: usize>(
+    source: &mut BoundedStack<N, HistoryEntry>,
+    target: &mut BoundedStack<N, HistoryEntry>,
+    steps: usize,
+    current: (Map, HistorySelection),
+) -> Option<(Map, HistorySelection)> {
+    if steps == 0 || steps > source.len() {
+        return None;
+    }
+
+    let mut current = current;
+    for _ in 0..steps {
+        let entry = source.pop()?;
+        target.push(HistoryEntry {
+            label: entry.label,
+            map: current.0,
+            selection: current.1,
+        });
+        current = (entry.map, entry.selection);
+    }
+    Some(current)
+}
+
+/// Moves back one view on the source stack from the current view, skipping
+/// views that are the same as the current one, and pushes the current view
+/// onto the target stack. Returns the view that was reached, or [`None`] if
+/// the source stack has no other view.
+fn travel_view<const N: usize>(
+    source: &mut BoundedStack<N, CanvasView>,
+    target: &mut BoundedStack<N, CanvasView>,
+    current: CanvasView,
+) -> Option<CanvasView> {
+    while let Some(view) = source.pop() {
+        if view != current {
+            target.push(current);
+            return Some(view);
+        }
+    }
+    None
 }
 
 /// Contains everything for being able to redo and undo map changes.
@@ -63,59 +172,287 @@ impl<const N: usize, T> BoundedStack<N, T> {
 pub struct HistoryState {}
 
 impl HistoryState {
-    /// Returns the last map that was stored.
-    pub fn undo(current: Map) -> Option<Map> {
-        let map = PAST_STACK
-            .lock()
-            .unwrap()
-            .pop()?;
-        FUTURE_STACK
-            .lock()
-            .unwrap()
-            .push(current);
-        Some(map)
+    /// Returns the last map that was stored, together with the selection at
+    /// that time.
+    pub fn undo(current: (Map, HistorySelection)) -> Option<(Map, HistorySelection)> {
+        Self::jump_to_past(0, current)
     }
 
     /// Returns if there is no map in the past stack that can be reverted to.
     pub fn cannot_undo() -> bool {
-        PAST_STACK
-            .lock()
-            .unwrap()
-            .stack
-            .is_empty()
+        PAST_STACK.with_borrow(|stack| {
+            stack
+                .stack
+                .is_empty()
+        })
     }
 
-    /// Returns the last map that was undone.
-    pub fn redo(current: Map) -> Option<Map> {
-        let map = FUTURE_STACK
-            .lock()
-            .unwrap()
-            .pop()?;
-        PAST_STACK
-            .lock()
-            .unwrap()
-            .push(current);
-        Some(map)
+    /// Returns the last map that was undone, together with the selection at
+    /// that time.
+    pub fn redo(current: (Map, HistorySelection)) -> Option<(Map, HistorySelection)> {
+        Self::jump_to_future(0, current)
     }
 
     /// Returns if there is no map in the future stack that can be redone.
     pub fn cannot_redo() -> bool {
-        FUTURE_STACK
-            .lock()
-            .unwrap()
-            .stack
-            .is_empty()
+        FUTURE_STACK.with_borrow(|stack| {
+            stack
+                .stack
+                .is_empty()
+        })
+    }
+
+    /// Returns the labels of the changes that can be undone, starting with
+    /// the most recent one.
+    pub fn get_past_labels() -> Vec<String> {
+        PAST_STACK.with_borrow(|stack| {
+            stack
+                .iter()
+                .map(|entry| {
+                    entry
+                        .label
+                        .clone()
+                })
+                .collect()
+        })
+    }
+
+    /// Returns the labels of the changes that can be redone, starting with
+    /// the first one that would be redone.
+    pub fn get_future_labels() -> Vec<String> {
+        FUTURE_STACK.with_borrow(|stack| {
+            stack
+                .iter()
+                .map(|entry| {
+                    entry
+                        .label
+                        .clone()
+                })
+                .collect()
+        })
     }
+
+    /// Undoes all changes up to and including the one at the given index of
+    /// [`HistoryState::get_past_labels`] and returns the map from before that
+    /// change, together with the selection at that time. All undone changes
+    /// can be redone afterwards.
+    pub fn jump_to_past(
+        index: usize,
+        current: (Map, HistorySelection),
+    ) -> Option<(Map, HistorySelection)> {
+        PAST_STACK.with_borrow_mut(|past| {
+            FUTURE_STACK.with_borrow_mut(|future| travel(past, future, index + 1, current))
+        })
+    }
+
+    /// Redoes all changes up to and including the one at the given index of
+    /// [`HistoryState::get_future_labels`] and returns the map from after
+    /// that change, together with the selection at that time. All redone
+    /// changes can be undone afterwards.
+    pub fn jump_to_future(
+        index: usize,
+        current: (Map, HistorySelection),
+    ) -> Option<(Map, HistorySelection)> {
+        PAST_STACK.with_borrow_mut(|past| {
+            FUTURE_STACK.with_borrow_mut(|future| travel(future, past, index + 1, current))
+        })
+    }
+
+    /// Store a view that is about to be left in the view history, and forget
+    /// the views that were stepped back from. A view that is the same as the
+    /// last stored one is not stored again.
+    pub fn push_view(view: CanvasView) {
+        VIEW_PAST_STACK.with_borrow_mut(|past| {
+            if past.peek() != Some(&view) {
+                past.push(view);
+            }
+        });
+        VIEW_FUTURE_STACK.with_borrow_mut(BoundedStack::clear);
+    }
+
+    /// Returns the view that was looked at before the current one, if any.
+    pub fn view_back(current: CanvasView) -> Option<CanvasView> {
+        VIEW_PAST_STACK.with_borrow_mut(|past| {
+            VIEW_FUTURE_STACK.with_borrow_mut(|future| travel_view(past, future, current))
+        })
+    }
+
+    /// Returns the view that was stepped back from last, if any.
+    pub fn view_forward(current: CanvasView) -> Option<CanvasView> {
+        VIEW_PAST_STACK.with_borrow_mut(|past| {
+            VIEW_FUTURE_STACK.with_borrow_mut(|future| travel_view(future, past, current))
+        })
+    }
+}
+
+/// Pushes the current map and selection onto the past stack, labeled with the
+/// change that is about to be made to the map, and clears the future stack.
+pub(super) fn push_past_map(map: Map, selection: HistorySelection, label: &str) {
+    PAST_STACK.with_borrow_mut(|past| {
+        past.push(HistoryEntry {
+            label: label.to_owned(),
+            map,
+            selection,
+        });
+    });
+    FUTURE_STACK.with_borrow_mut(BoundedStack::clear);
 }
 
-/// Pushes the current map onto the past stack and clears the future stack.
-pub(super) fn push_past_map(map: Map) {
-    PAST_STACK
-        .lock()
-        .unwrap()
-        .push(map);
-    FUTURE_STACK
-        .lock()
-        .unwrap()
-        .clear();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Station;
+
+    fn map_with_stations(count: u64) -> Map {
+        let mut map = Map::new();
+        for i in 0..count {
+            map.add_station(Station::new(
+                (0, 0).into(),
+                Some(i.into()),
+            ));
+        }
+        map
+    }
+
+    fn entry(label: &str, stations: u64) -> HistoryEntry {
+        HistoryEntry {
+            label: label.to_owned(),
+            map: map_with_stations(stations),
+            selection: HistorySelection {
+                stations: vec![stations.into()],
+                ..HistorySelection::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_travel_resplits_stacks() {
+        let mut past: BoundedStack<5, HistoryEntry> = BoundedStack::new();
+        let mut future: BoundedStack<5, HistoryEntry> = BoundedStack::new();
+        past.push(entry("added station", 0));
+        past.push(entry("added line", 1));
+        past.push(entry("moved station", 2));
+
+        let (map, selection) = travel(
+            &mut past,
+            &mut future,
+            2,
+            (
+                map_with_stations(3),
+                HistorySelection::default(),
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            map.get_stations()
+                .len(),
+            1
+        );
+        assert_eq!(selection.stations, vec![1.into()]);
+        assert_eq!(
+            past.iter()
+                .map(|e| {
+                    e.label
+                        .as_str()
+                })
+                .collect::<Vec<_>>(),
+            vec!["added station"]
+        );
+        assert_eq!(
+            future
+                .iter()
+                .map(|e| {
+                    e.label
+                        .as_str()
+                })
+                .collect::<Vec<_>>(),
+            vec!["added line", "moved station"]
+        );
+
+        let (map, selection) = travel(
+            &mut future,
+            &mut past,
+            2,
+            (map, selection),
+        )
+        .unwrap();
+        assert_eq!(
+            map.get_stations()
+                .len(),
+            3
+        );
+        assert!(
+            selection
+                .stations
+                .is_empty()
+        );
+        assert_eq!(past.len(), 3);
+        assert_eq!(future.len(), 0);
+    }
+
+    #[test]
+    fn test_travel_out_of_range() {
+        let mut past: BoundedStack<5, HistoryEntry> = BoundedStack::new();
+        let mut future: BoundedStack<5, HistoryEntry> = BoundedStack::new();
+        past.push(entry("added station", 0));
+
+        assert!(
+            travel(
+                &mut past,
+                &mut future,
+                2,
+                (Map::new(), HistorySelection::default())
+            )
+            .is_none()
+        );
+        assert!(
+            travel(
+                &mut past,
+                &mut future,
+                0,
+                (Map::new(), HistorySelection::default())
+            )
+            .is_none()
+        );
+        assert_eq!(past.len(), 1);
+        assert_eq!(future.len(), 0);
+    }
+
+    #[test]
+    fn test_view_history() {
+        let view = |x: i32| {
+            CanvasView {
+                offset: (x, 0),
+                zoom_factor: 1.0,
+            }
+        };
+
+        HistoryState::push_view(view(0));
+        HistoryState::push_view(view(1));
+        HistoryState::push_view(view(1));
+
+        // Stepping back skips the views that are the same as the current one.
+        assert_eq!(
+            HistoryState::view_back(view(1)),
+            Some(view(0))
+        );
+        assert_eq!(HistoryState::view_back(view(0)), None);
+        assert_eq!(
+            HistoryState::view_forward(view(0)),
+            Some(view(1))
+        );
+        assert_eq!(HistoryState::view_forward(view(1)), None);
+
+        // Moving the view after stepping back forgets the views ahead of it.
+        assert_eq!(
+            HistoryState::view_back(view(1)),
+            Some(view(0))
+        );
+        HistoryState::push_view(view(0));
+        assert_eq!(HistoryState::view_forward(view(2)), None);
+
+        // The map history is not touched by the view history.
+        assert!(HistoryState::cannot_undo());
+        assert!(HistoryState::cannot_redo());
+    }
 }
@@ -2,15 +2,22 @@
 
 use std::fmt::Display;
 
+use super::KeyBindings;
+
 /// Stores the current state of the user interaction.
-/// This is for keeping track of the cursor state on the canvas and if the
-/// application is busy.
+/// This is for keeping track of the cursor state on the canvas, if the
+/// application is busy and which keys perform which actions.
 #[derive(Debug, Clone)]
 pub struct InteractionState {
     /// The cursor, can be "default", "wait" and "grabbing"
     cursor: String,
     /// If the application is busy.
     busy: bool,
+    /// The keys that are bound to the keyboard shortcuts.
+    key_bindings: KeyBindings,
+    /// If the whole map should be laid out by the algorithm as soon as
+    /// possible, like after importing a map with auto-arrange enabled.
+    layout_requested: bool,
 }
 
 impl InteractionState {
@@ -19,6 +26,8 @@ impl InteractionState {
         Self {
             cursor: "default".to_string(),
             busy: false,
+            key_bindings: KeyBindings::default(),
+            layout_requested: false,
         }
     }
 
@@ -42,4 +51,31 @@ impl InteractionState {
     pub fn is_busy(&self) -> bool {
         self.busy
     }
+
+    /// A getter method for the keys that are bound to the keyboard shortcuts.
+    pub fn get_key_bindings(&self) -> &KeyBindings {
+        &self.key_bindings
+    }
+
+    /// A mutable getter method for the keys that are bound to the keyboard
+    /// shortcuts.
+    pub fn get_mut_key_bindings(&mut self) -> &mut KeyBindings {
+        &mut self.key_bindings
+    }
+
+    /// A setter method for the keys that are bound to the keyboard shortcuts.
+    pub fn set_key_bindings(&mut self, key_bindings: KeyBindings) {
+        self.key_bindings = key_bindings;
+    }
+
+    /// Check if the whole map should be laid out by the algorithm.
+    #[inline]
+    pub fn is_layout_requested(&self) -> bool {
+        self.layout_requested
+    }
+
+    /// Set if the whole map should be laid out by the algorithm.
+    pub fn set_layout_requested(&mut self, requested: bool) {
+        self.layout_requested = requested;
+    }
 }
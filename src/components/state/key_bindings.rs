@@ -0,0 +1,416 @@
+//! Contains the [`KeyBindings`] struct and the actions that can be bound to a
+//! key.
+
+use std::fmt::Display;
+
+use leptos::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use web_sys::KeyboardEvent;
+
+use crate::{
+    utils::Result,
+    Error,
+};
+
+/// Checks if the element that currently has focus is a field the user can type
+/// in.
+pub fn is_text_field_focused() -> bool {
+    document()
+        .active_element()
+        .is_some_and(|element| {
+            matches!(
+                element
+                    .tag_name()
+                    .as_str(),
+                "INPUT" | "TEXTAREA" | "SELECT"
+            )
+        })
+}
+
+/// Checks if the page is shown on an Apple device, where the command key is
+/// used for shortcuts instead of the control key.
+pub fn is_apple_platform() -> bool {
+    window()
+        .navigator()
+        .platform()
+        .is_ok_and(|platform| platform.starts_with("Mac") || platform.starts_with("iP"))
+}
+
+/// The actions that can be performed with a keyboard shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyAction {
+    /// Deselect everything that is selected.
+    ClearSelection,
+    /// Undo the last change to the map.
+    Undo,
+    /// Redo the last undone change to the map.
+    Redo,
+    /// Remove the selected stations from the map.
+    DeleteSelection,
+    /// Expand the selection to the whole connected parts of the map it is in.
+    SelectConnected,
+    /// Run the algorithm on the entire map.
+    Recalculate,
+    /// Zoom and pan the canvas so the selection fits on it.
+    FitSelection,
+    /// Change the color of the lines on the clicked or selected edges to the
+    /// next color of the palette.
+    CycleLineColor,
+    /// Zoom the canvas in.
+    ZoomIn,
+    /// Zoom the canvas out.
+    ZoomOut,
+    /// Move the visible part of the canvas up.
+    PanUp,
+    /// Move the visible part of the canvas down.
+    PanDown,
+    /// Move the visible part of the canvas left.
+    PanLeft,
+    /// Move the visible part of the canvas right.
+    PanRight,
+    /// Go back to the previous view in the view history.
+    ViewBack,
+    /// Go forward to the next view in the view history.
+    ViewForward,
+    /// Show or hide the list of keyboard shortcuts.
+    ShowShortcuts,
+}
+
+impl KeyAction {
+    /// All actions, in the order they are shown to the user.
+    pub const ALL: [Self; 17] = [
+        Self::ClearSelection,
+        Self::Undo,
+        Self::Redo,
+        Self::DeleteSelection,
+        Self::SelectConnected,
+        Self::Recalculate,
+        Self::FitSelection,
+        Self::CycleLineColor,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::PanUp,
+        Self::PanDown,
+        Self::PanLeft,
+        Self::PanRight,
+        Self::ViewBack,
+        Self::ViewForward,
+        Self::ShowShortcuts,
+    ];
+
+    /// The description of the action as it is shown to the user.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ClearSelection => "Clear the selection",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::DeleteSelection => "Delete the selected stations",
+            Self::SelectConnected => "Select everything connected to the selection",
+            Self::Recalculate => "Recalculate the map",
+            Self::FitSelection => "Fit the selection to the view",
+            Self::CycleLineColor => "Cycle the color of the selected lines",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::PanUp => "Move up",
+            Self::PanDown => "Move down",
+            Self::PanLeft => "Move left",
+            Self::PanRight => "Move right",
+            Self::ViewBack => "Go back to the previous view",
+            Self::ViewForward => "Go forward to the next view",
+            Self::ShowShortcuts => "Show the keyboard shortcuts",
+        }
+    }
+
+    /// The key the action is bound to by default.
+    fn default_binding(self) -> KeyBinding {
+        match self {
+            Self::ClearSelection => KeyBinding::new("Escape", false),
+            Self::Undo => KeyBinding::new("z", true),
+            Self::Redo => KeyBinding::new("Z", true),
+            Self::DeleteSelection => KeyBinding::new("Delete", false),
+            Self::SelectConnected => KeyBinding::new("g", false),
+            Self::Recalculate => KeyBinding::new("Enter", true),
+            Self::FitSelection => KeyBinding::new("f", false),
+            Self::CycleLineColor => KeyBinding::new("c", false),
+            Self::ZoomIn => KeyBinding::new("+", false),
+            Self::ZoomOut => KeyBinding::new("-", false),
+            Self::PanUp => KeyBinding::new("ArrowUp", false),
+            Self::PanDown => KeyBinding::new("ArrowDown", false),
+            Self::PanLeft => KeyBinding::new("ArrowLeft", false),
+            Self::PanRight => KeyBinding::new("ArrowRight", false),
+            Self::ViewBack => KeyBinding::new("[", false),
+            Self::ViewForward => KeyBinding::new("]", false),
+            Self::ShowShortcuts => KeyBinding::new("?", false),
+        }
+    }
+}
+
+/// A key, optionally pressed together with the control key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBinding {
+    /// The value of the key as given by the keyboard event, so shift is part of
+    /// it for letters.
+    key: String,
+    /// If the control or command key has to be held down.
+    ctrl: bool,
+}
+
+impl KeyBinding {
+    /// Create a new key binding.
+    pub fn new(key: &str, ctrl: bool) -> Self {
+        Self {
+            key: key.to_owned(),
+            ctrl,
+        }
+    }
+
+    /// Get the key binding pressed in the given keyboard event. Returns None if
+    /// only a modifier key was pressed, or if the key can not be bound as
+    /// described in [`KeyBinding::from_keys`].
+    pub fn from_event(ev: &KeyboardEvent) -> Option<Self> {
+        Self::from_keys(
+            ev.key(),
+            ev.ctrl_key() || ev.meta_key(),
+            ev.shift_key(),
+            ev.alt_key(),
+        )
+    }
+
+    /// Get the key binding for the given key pressed together with the given
+    /// modifier keys. Shift is only part of a binding through the character it
+    /// types, so keys that do not type a character can not be bound together
+    /// with shift. Keys pressed together with alt can not be bound at all.
+    fn from_keys(key: String, ctrl: bool, shift: bool, alt: bool) -> Option<Self> {
+        if matches!(
+            key.as_str(),
+            "Control" | "Shift" | "Alt" | "Meta"
+        ) {
+            return None;
+        }
+
+        let is_character = key
+            .chars()
+            .count()
+            == 1;
+        if alt || (shift && !is_character) {
+            return None;
+        }
+
+        Some(Self {
+            key,
+            ctrl,
+        })
+    }
+
+    /// Describe the key binding the way the keys are labeled on the user's
+    /// platform, which calls the control key the command key on Apple devices.
+    pub fn to_platform_string(&self, apple: bool) -> String {
+        let key = if self.key == " " { "Space" } else { &self.key };
+
+        match (self.ctrl, apple) {
+            (false, _) => key.to_owned(),
+            (true, false) => format!("Ctrl+{key}"),
+            (true, true) => format!("Cmd+{key}"),
+        }
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_platform_string(false))
+    }
+}
+
+/// The keys every [`KeyAction`] is bound to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    /// The actions together with the key they are bound to.
+    bindings: Vec<(KeyAction, KeyBinding)>,
+}
+
+impl KeyBindings {
+    /// Get the key the given action is bound to.
+    pub fn get_binding(&self, action: KeyAction) -> Option<&KeyBinding> {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, binding)| binding)
+    }
+
+    /// Get the action that is bound to the given key, if any.
+    pub fn get_action(&self, binding: &KeyBinding) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(_, b)| b == binding)
+            .map(|(action, _)| *action)
+    }
+
+    /// Get the action that is bound to the key pressed in the given keyboard
+    /// event, if any. Keys pressed while typing in a text field never perform
+    /// an action.
+    pub fn get_event_action(&self, ev: &KeyboardEvent) -> Option<KeyAction> {
+        if is_text_field_focused() {
+            return None;
+        }
+
+        KeyBinding::from_event(ev).and_then(|binding| self.get_action(&binding))
+    }
+
+    /// Bind the given action to the given key. Returns an error if the key is
+    /// already bound to another action.
+    pub fn set_binding(&mut self, action: KeyAction, binding: KeyBinding) -> Result<()> {
+        if let Some(other) = self
+            .get_action(&binding)
+            .filter(|other| *other != action)
+        {
+            return Err(Error::other(format!(
+                "{binding} is already used to {}",
+                other
+                    .as_str()
+                    .to_lowercase()
+            )));
+        }
+
+        self.bindings
+            .retain(|(a, _)| *a != action);
+        self.bindings
+            .push((action, binding));
+        Ok(())
+    }
+
+    /// Fill in the default key of every action that is not bound, as long as
+    /// that key is not used by another action already. Used after loading
+    /// stored bindings that are missing newer actions.
+    pub fn fill_defaults(&mut self) {
+        for action in KeyAction::ALL {
+            if self
+                .get_binding(action)
+                .is_none()
+            {
+                let _ = self.set_binding(action, action.default_binding());
+            }
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: KeyAction::ALL
+                .into_iter()
+                .map(|action| (action, action.default_binding()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_bindings() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(
+            bindings.get_action(&KeyBinding::new("z", true)),
+            Some(KeyAction::Undo)
+        );
+        assert_eq!(
+            bindings.get_action(&KeyBinding::new("z", false)),
+            None
+        );
+
+        // Rebinding to a free key replaces the old key.
+        bindings
+            .set_binding(
+                KeyAction::Undo,
+                KeyBinding::new("u", false),
+            )
+            .unwrap();
+        assert_eq!(
+            bindings.get_action(&KeyBinding::new("z", true)),
+            None
+        );
+        assert_eq!(
+            bindings
+                .get_binding(KeyAction::Undo)
+                .map(ToString::to_string),
+            Some("u".to_owned())
+        );
+
+        // A key used by another action is refused.
+        assert!(
+            bindings
+                .set_binding(
+                    KeyAction::Redo,
+                    KeyBinding::new("u", false)
+                )
+                .is_err()
+        );
+        assert_eq!(
+            bindings
+                .get_binding(KeyAction::Redo)
+                .map(ToString::to_string),
+            Some("Ctrl+Z".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_from_keys() {
+        assert_eq!(
+            KeyBinding::from_keys("Z".to_owned(), true, true, false),
+            Some(KeyBinding::new("Z", true))
+        );
+        assert_eq!(
+            KeyBinding::from_keys("Shift".to_owned(), false, true, false),
+            None
+        );
+
+        // Shift with a key that types no character and alt are never bound.
+        assert_eq!(
+            KeyBinding::from_keys("ArrowUp".to_owned(), false, true, false),
+            None
+        );
+        assert_eq!(
+            KeyBinding::from_keys("z".to_owned(), true, false, true),
+            None
+        );
+    }
+
+    #[test]
+    fn test_to_platform_string() {
+        let undo = KeyBinding::new("z", true);
+        assert_eq!(undo.to_platform_string(false), "Ctrl+z");
+        assert_eq!(undo.to_platform_string(true), "Cmd+z");
+        assert_eq!(undo.to_string(), "Ctrl+z");
+
+        let space = KeyBinding::new(" ", false);
+        assert_eq!(space.to_platform_string(true), "Space");
+    }
+
+    #[test]
+    fn test_fill_defaults() {
+        let mut bindings = KeyBindings {
+            bindings: vec![(
+                KeyAction::Undo,
+                KeyBinding::new("Escape", false),
+            )],
+        };
+        bindings.fill_defaults();
+
+        // Clearing the selection keeps no key, as its default is taken.
+        assert_eq!(
+            bindings.get_binding(KeyAction::ClearSelection),
+            None
+        );
+        assert_eq!(
+            bindings
+                .get_binding(KeyAction::PanUp)
+                .map(ToString::to_string),
+            Some("ArrowUp".to_owned())
+        );
+    }
+}
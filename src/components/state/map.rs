@@ -1,5 +1,8 @@
 //! Contains the [`MapState`] and all its methods.
 
+use std::collections::HashSet;
+
+use itertools::Itertools;
 use leptos::{
     html::Canvas,
     prelude::{
@@ -10,25 +13,48 @@ use leptos::{
 use web_sys::HtmlCanvasElement;
 
 use super::{
-    history::push_past_map,
+    history::{
+        push_past_map,
+        HistorySelection,
+    },
     CanvasState,
+    HistoryState,
 };
 use crate::{
     algorithms::{
         redraw_canvas,
         AlgorithmSettings,
+        OccupiedNodes,
+        StraighteningCandidate,
     },
     models::{
         Edge,
         EdgeID,
         GridNode,
+        LayoutWeights,
+        Line,
+        LineID,
         Map,
         SelectedLine,
         SelectedStation,
         Station,
+        StationID,
     },
+    utils::{
+        canvas_offset_to_grid_offset,
+        Result,
+    },
+    Error,
 };
 
+/// The default maximum amount of stations or edges an imported map may have
+/// before the user is asked to confirm loading it.
+const DEFAULT_MAX_IMPORT_SIZE: usize = 10_000;
+
+/// A station whose label is being dragged, together with the point the drag
+/// started from and the label offset of the station before the drag.
+type DraggedLabel = (StationID, (f64, f64), (i32, i32));
+
 /// The type of operation that is currently selected.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ActionType {
@@ -54,8 +80,24 @@ pub enum ActionType {
     Unlock,
 }
 
+impl ActionType {
+    /// A short description of the change made by this action, used in the
+    /// redo/undo history.
+    pub fn history_label(self) -> &'static str {
+        match self {
+            Self::RemoveStation => "removed station",
+            Self::RemoveCheckpoint => "removed checkpoint",
+            Self::RemoveLine => "removed line",
+            Self::Lock => "locked",
+            Self::Unlock => "unlocked",
+        }
+    }
+}
+
 /// Holds all the state of the current [`Map`], canvas and any potentially
 /// selected objects.
+// The overlays are all toggled independently, so many bools are needed
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug)]
 pub struct MapState {
     /// The current state of the map.
@@ -76,6 +118,20 @@ pub struct MapState {
     last_loaded: Option<Map>,
     /// If the `last_loaded` map should be overlayed on the current map.
     original_overlay_enabled: bool,
+    /// If the edges that are drawn on top of each other should be highlighted.
+    overlap_overlay_enabled: bool,
+    /// If the stations that moved the most from their original position should
+    /// be highlighted.
+    displacement_overlay_enabled: bool,
+    /// If the stations contracted into edges by the algorithm should be drawn
+    /// along those edges.
+    contracted_overlay_enabled: bool,
+    /// The lines that have the index of each of their stations drawn next to
+    /// it.
+    station_order_lines: Vec<LineID>,
+    /// If the map is shown in the read-only viewer, where it can only be panned
+    /// and zoomed.
+    read_only: bool,
     /// The point the user is dragging the map from and if they're dragging the
     /// map as a whole, or a station and/or edge.
     drag_offset: Option<((f64, f64), bool)>,
@@ -84,8 +140,37 @@ pub struct MapState {
     box_select: Option<((f64, f64), (f64, f64))>,
     /// The station the user clicked on for more information or editing.
     clicked_on_station: Option<Station>,
+    /// The station last focused by navigating through the stations with the
+    /// keyboard.
+    focused_station: Option<StationID>,
     /// The edge the user clicked on for more information or editing.
     clicked_on_edge: Option<(Edge, (f64, f64))>,
+    /// The station whose label the user is dragging.
+    dragged_label: Option<DraggedLabel>,
+    /// The straightening of the selected stations the user is currently
+    /// hovering over to preview.
+    straighten_preview: Option<StraighteningCandidate>,
+    /// The map from before the user started drawing a new line by clicking on
+    /// stations, which is stored in the redo/undo history once they finish.
+    drawing_line_base: Option<Map>,
+    /// The nodes occupied by the stations and edges of the map after the last
+    /// run of the algorithm.
+    last_occupied: Option<OccupiedNodes>,
+    /// The weights used to combine the layout score of the map into a single
+    /// number.
+    layout_weights: LayoutWeights,
+    /// If the ids of the stations, lines and edges are renumbered into a
+    /// compact range when the map is downloaded or exported.
+    normalize_export_ids: bool,
+    /// The maximum amount of stations or edges a map may have when it is
+    /// imported, before asking the user to confirm. 0 means there is no limit.
+    max_import_size: usize,
+    /// If panning and zooming the canvas is stored in the view history, so
+    /// the user can step back to where they were looking.
+    view_history: bool,
+    /// Counts up every time the map or the last loaded map might have changed,
+    /// so copies of them only need to be made again once it changed.
+    map_revision: u64,
 }
 
 impl MapState {
@@ -102,15 +187,31 @@ impl MapState {
             algorithm_settings: AlgorithmSettings::default(),
             last_loaded: None,
             original_overlay_enabled: false,
+            overlap_overlay_enabled: false,
+            displacement_overlay_enabled: false,
+            contracted_overlay_enabled: false,
+            station_order_lines: Vec::new(),
+            read_only: false,
             drag_offset: None,
             box_select: None,
             clicked_on_station: None,
+            dragged_label: None,
+            focused_station: None,
             clicked_on_edge: None,
+            straighten_preview: None,
+            last_occupied: None,
+            layout_weights: LayoutWeights::default(),
+            normalize_export_ids: false,
+            max_import_size: DEFAULT_MAX_IMPORT_SIZE,
+            view_history: false,
+            drawing_line_base: None,
+            map_revision: 0,
         }
     }
 
-    /// Clear all selections.
+    /// Clear all selections, finishing the line being drawn if there is one.
     pub fn clear_all_selections(&mut self) {
+        self.finish_drawing_line();
         self.clear_selected_stations();
         self.clear_selected_lines();
         self.clear_selected_action();
@@ -119,6 +220,8 @@ impl MapState {
         self.clear_clicked_on_station();
         self.clear_clicked_on_edge();
         self.clear_drag_offset();
+        self.cancel_label_drag();
+        self.clear_straighten_preview();
     }
 
     /// A getter method for the [`Map`].
@@ -126,30 +229,338 @@ impl MapState {
         &self.map
     }
 
-    /// A mutable getter method for the [`Map`].
-    pub fn get_mut_map(&mut self) -> &mut Map {
+    /// A mutable getter method for the [`Map`], the given label describes the
+    /// change that will be made for the redo/undo history.
+    pub fn get_mut_map(&mut self, label: &str) -> &mut Map {
         push_past_map(
             self.map
                 .clone(),
+            self.get_history_selection(),
+            label,
         );
+        self.map_changed();
         &mut self.map
     }
 
-    /// A setter method for the [`Map`].
-    pub fn set_map(&mut self, map: Map) {
+    /// A setter method for the [`Map`], the given label describes the change
+    /// for the redo/undo history. The nodes occupied after the last run of the
+    /// algorithm are cleared, as they belong to the previous map.
+    pub fn set_map(&mut self, map: Map, label: &str) {
         push_past_map(
             self.map
                 .clone(),
+            self.get_history_selection(),
+            label,
         );
+        self.map_changed();
         self.map = map;
+        self.last_occupied = None;
+    }
+
+    /// Replace the map with an empty one, clearing all selections and the
+    /// last loaded map as well. The cleared map is stored in the redo/undo
+    /// history together with its selection, so it can be undone. The last
+    /// loaded map is not part of the history, so it stays cleared.
+    pub fn clear_map(&mut self) {
+        self.set_map(Map::new(), "cleared map");
+        self.clear_all_selections();
+        self.focused_station = None;
+        self.last_loaded = None;
+        self.original_overlay_enabled = false;
     }
 
     /// A setter method for the [`Map`] without the redo/undo history getting
     /// updated.
     pub fn set_map_no_history(&mut self, map: Map) {
+        self.map_changed();
         self.map = map;
     }
 
+    /// Replace the map as part of the last change stored in the redo/undo
+    /// history, for changes that follow from it, like repairing the lines it
+    /// broke. Undoing the last change undoes this one as well.
+    pub fn amend_last_change(&mut self, map: Map) {
+        self.set_map_no_history(map);
+    }
+
+    /// A getter method for the revision of the map, which changes every time
+    /// the map or the last loaded map might have changed.
+    pub fn get_map_revision(&self) -> u64 {
+        self.map_revision
+    }
+
+    /// Mark the map or the last loaded map as changed.
+    fn map_changed(&mut self) {
+        self.map_revision += 1;
+    }
+
+    /// Get the current selection to store in the redo/undo history.
+    fn get_history_selection(&self) -> HistorySelection {
+        HistorySelection {
+            stations: self
+                .selected_stations
+                .iter()
+                .map(|s| {
+                    s.get_station()
+                        .get_id()
+                })
+                .collect(),
+            edges: self
+                .selected_edges
+                .clone(),
+            lines: self
+                .selected_lines
+                .clone(),
+        }
+    }
+
+    /// Replace the map with one from the redo/undo history and select again
+    /// what was selected at the time it was stored. Selected objects that no
+    /// longer exist in the map are dropped.
+    fn restore_from_history(&mut self, (map, selection): (Map, HistorySelection)) {
+        self.clear_all_selections();
+        self.map_changed();
+        self.map = map;
+
+        // The stored map can still have edges marked as selected that are not
+        // in the stored selection, so they are all deselected first.
+        for edge in self
+            .map
+            .get_mut_edges()
+        {
+            edge.deselect();
+        }
+
+        let stations = selection
+            .stations
+            .into_iter()
+            .filter_map(|id| {
+                self.map
+                    .get_station(id)
+                    .cloned()
+            })
+            .map(SelectedStation::new)
+            .collect();
+        self.set_selected_stations(stations);
+
+        let edges = selection
+            .edges
+            .into_iter()
+            .filter(|id| {
+                self.map
+                    .get_edge(*id)
+                    .is_some()
+            })
+            .collect();
+        self.set_selected_edges(edges);
+
+        let lines = selection
+            .lines
+            .into_iter()
+            .filter(|l| {
+                self.map
+                    .get_line(l.get_line())
+                    .is_some()
+            })
+            .collect();
+        self.set_selected_lines(lines);
+    }
+
+    /// Undo the last change to the map and restore the selection from before
+    /// that change. Returns if there was a change to undo.
+    pub fn undo(&mut self) -> bool {
+        // Undoing while drawing a line only throws away the drawn line.
+        if self.cancel_drawing_line() {
+            return true;
+        }
+
+        let current = (
+            self.map
+                .clone(),
+            self.get_history_selection(),
+        );
+        HistoryState::undo(current)
+            .map(|past| self.restore_from_history(past))
+            .is_some()
+    }
+
+    /// Redo the last undone change to the map and restore the selection from
+    /// after that change. Returns if there was a change to redo.
+    pub fn redo(&mut self) -> bool {
+        self.cancel_drawing_line();
+
+        let current = (
+            self.map
+                .clone(),
+            self.get_history_selection(),
+        );
+        HistoryState::redo(current)
+            .map(|future| self.restore_from_history(future))
+            .is_some()
+    }
+
+    /// Undo all changes up to and including the one at the given index of
+    /// [`HistoryState::get_past_labels`], restoring the selection from before
+    /// that change. Returns if the changes could be undone.
+    pub fn jump_to_past(&mut self, index: usize) -> bool {
+        self.cancel_drawing_line();
+
+        let current = (
+            self.map
+                .clone(),
+            self.get_history_selection(),
+        );
+        HistoryState::jump_to_past(index, current)
+            .map(|past| self.restore_from_history(past))
+            .is_some()
+    }
+
+    /// Redo all changes up to and including the one at the given index of
+    /// [`HistoryState::get_future_labels`], restoring the selection from after
+    /// that change. Returns if the changes could be redone.
+    pub fn jump_to_future(&mut self, index: usize) -> bool {
+        self.cancel_drawing_line();
+
+        let current = (
+            self.map
+                .clone(),
+            self.get_history_selection(),
+        );
+        HistoryState::jump_to_future(index, current)
+            .map(|future| self.restore_from_history(future))
+            .is_some()
+    }
+
+    /// Start drawing a new line, which the user extends by clicking on
+    /// stations in order. The drawn line is only stored in the redo/undo
+    /// history as a whole, once it is finished.
+    pub fn start_drawing_line(&mut self) {
+        self.clear_all_selections();
+        self.drawing_line_base = Some(
+            self.map
+                .clone(),
+        );
+        self.map_changed();
+        let line = SelectedLine::new_line(&mut self.map);
+        self.set_selected_lines(vec![line]);
+    }
+
+    /// Start drawing a new line with the given name and color, like
+    /// [`MapState::start_drawing_line`] does for a line without either.
+    pub fn start_drawing_named_line(&mut self, name: &str, color: (u8, u8, u8)) {
+        self.start_drawing_line();
+        let Some(line_id) = self
+            .selected_lines
+            .first()
+            .map(SelectedLine::get_line)
+        else {
+            return;
+        };
+
+        let mut line = self
+            .map
+            .get_or_add_line(line_id)
+            .clone();
+        line.set_name(&name.trim());
+        line.set_color(color);
+        self.map_changed();
+        self.map
+            .add_line(line);
+    }
+
+    /// Change the color of the lines on the clicked edge, or on the selected
+    /// edges if no edge was clicked, to the next color of the palette as a
+    /// single undo step. The dash patterns of the lines follow their new color
+    /// the next time the map is drawn.
+    pub fn cycle_line_colors(&mut self) {
+        let edges = self
+            .get_clicked_on_edge()
+            .map_or_else(
+                || {
+                    self.selected_edges
+                        .clone()
+                },
+                |edge| vec![edge.get_id()],
+            );
+        let lines = edges
+            .into_iter()
+            .flat_map(|edge| {
+                self.map
+                    .lines_on_edge(edge)
+            })
+            .map(Line::get_id)
+            .unique()
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return;
+        }
+
+        let map = self.get_mut_map("cycled line color");
+        for id in lines {
+            if let Some(line) = map.get_mut_line(id) {
+                line.cycle_color();
+            }
+        }
+    }
+
+    /// If the user is currently drawing a new line.
+    pub fn is_drawing_line(&self) -> bool {
+        self.drawing_line_base
+            .is_some()
+    }
+
+    /// Finish drawing the new line. If it connects any stations it is stored
+    /// in the redo/undo history, otherwise it is thrown away.
+    pub fn finish_drawing_line(&mut self) {
+        let Some(base) = self
+            .drawing_line_base
+            .take()
+        else {
+            return;
+        };
+
+        let has_edges = self
+            .selected_lines
+            .first()
+            .and_then(|selected| {
+                self.map
+                    .get_line(selected.get_line())
+            })
+            .is_some_and(|line| {
+                !line
+                    .get_edges()
+                    .is_empty()
+            });
+
+        if has_edges {
+            push_past_map(
+                base,
+                HistorySelection::default(),
+                "drew line",
+            );
+        } else {
+            self.map_changed();
+            self.map = base;
+        }
+        self.clear_selected_lines();
+    }
+
+    /// Throw away the line that is being drawn. Returns if a line was being
+    /// drawn.
+    pub fn cancel_drawing_line(&mut self) -> bool {
+        let Some(base) = self
+            .drawing_line_base
+            .take()
+        else {
+            return false;
+        };
+
+        self.map_changed();
+        self.map = base;
+        self.clear_selected_lines();
+        true
+    }
+
     /// A getter method for the selected station.
     pub fn get_selected_stations(&self) -> &[SelectedStation] {
         &self.selected_stations
@@ -242,6 +653,7 @@ impl MapState {
 
     /// Add a new edge to the selected edges.
     pub fn select_edge(&mut self, edge: EdgeID) {
+        self.map_changed();
         self.map
             .get_mut_edge(edge)
             .expect("Edge to select does not exist.")
@@ -255,6 +667,7 @@ impl MapState {
     pub fn set_selected_edges(&mut self, edges: Vec<EdgeID>) {
         self.clear_selected_edges();
 
+        self.map_changed();
         for id in &edges {
             self.map
                 .get_mut_edge(*id)
@@ -266,6 +679,7 @@ impl MapState {
 
     /// Deselect all selected edges.
     pub fn clear_selected_edges(&mut self) {
+        self.map_changed();
         for id in &self.selected_edges {
             if let Some(edge) = self
                 .map
@@ -278,8 +692,99 @@ impl MapState {
         self.selected_edges = Vec::new();
     }
 
+    /// Select the whole connected parts of the map the given stations are in,
+    /// being all stations and edges that can be reached from them. The
+    /// current selection is kept and extended if `merge` is set, and replaced
+    /// otherwise.
+    pub fn select_connected(&mut self, start: &[StationID], merge: bool) {
+        let (mut stations, mut edges) = if merge {
+            (
+                self.selected_stations
+                    .iter()
+                    .map(|s| {
+                        s.get_station()
+                            .get_id()
+                    })
+                    .collect::<Vec<_>>(),
+                self.selected_edges
+                    .clone(),
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        for id in start {
+            let (component_stations, component_edges) = self
+                .map
+                .connected_component(*id);
+            stations.extend(component_stations);
+            edges.extend(component_edges);
+        }
+
+        let selected = stations
+            .into_iter()
+            .unique()
+            .filter_map(|id| {
+                self.map
+                    .get_station(id)
+            })
+            .map(|station| {
+                let mut selected = SelectedStation::new(station.clone());
+                for edge in station
+                    .get_edges()
+                    .iter()
+                    .filter_map(|id| {
+                        self.map
+                            .get_edge(*id)
+                    })
+                {
+                    if edge.get_from() == station.get_id() {
+                        selected.add_after(edge.get_to());
+                    } else {
+                        selected.add_before(edge.get_from());
+                    }
+                }
+                selected
+            })
+            .collect();
+
+        self.set_selected_stations(selected);
+        self.set_selected_edges(
+            edges
+                .into_iter()
+                .unique()
+                .collect(),
+        );
+    }
+
+    /// Expand the selection to the whole connected parts of the map the
+    /// selected stations and edges are in.
+    pub fn select_connected_to_selection(&mut self) {
+        let start = self
+            .selected_stations
+            .iter()
+            .map(|s| {
+                s.get_station()
+                    .get_id()
+            })
+            .chain(
+                self.selected_edges
+                    .iter()
+                    .filter_map(|id| {
+                        self.map
+                            .get_edge(*id)
+                    })
+                    .flat_map(|edge| [edge.get_from(), edge.get_to()]),
+            )
+            .unique()
+            .collect::<Vec<_>>();
+
+        self.select_connected(&start, true);
+    }
+
     /// Lock all selected edges and stations.
     pub fn lock_selected(&mut self) {
+        self.map_changed();
         for id in &self.selected_edges {
             self.map
                 .get_mut_edge(*id)
@@ -302,6 +807,7 @@ impl MapState {
 
     /// Unlock all selected edges and stations.
     pub fn unlock_selected(&mut self) {
+        self.map_changed();
         for id in &self.selected_edges {
             self.map
                 .get_mut_edge(*id)
@@ -322,13 +828,40 @@ impl MapState {
         }
     }
 
+    /// Lock all edges and stations on the map as a single undo step.
+    pub fn lock_all(&mut self) {
+        self.get_mut_map("locked all")
+            .lock_all();
+    }
+
+    /// Unlock all edges and stations on the map as a single undo step.
+    pub fn unlock_all(&mut self) {
+        self.get_mut_map("unlocked all")
+            .unlock_all();
+    }
+
+    /// Lock all edges whose nodes were edited by hand as a single undo step.
+    pub fn lock_manual_edges(&mut self) {
+        self.get_mut_map("locked manual edits")
+            .lock_manual_edges();
+    }
+
     /// A getter method for the drag offset.
     pub fn get_drag_offset(&self) -> Option<((f64, f64), bool)> {
         self.drag_offset
     }
 
-    /// A setter method for the drag offset.
+    /// A setter method for the drag offset. Starting to drag the map as a
+    /// whole stores the view in the view history, while the view changes
+    /// during the drag are not stored.
     pub fn set_drag_offset(&mut self, offset: Option<((f64, f64), bool)>) {
+        if self
+            .drag_offset
+            .is_none()
+            && matches!(offset, Some((_, false)))
+        {
+            self.record_view();
+        }
         self.drag_offset = offset;
     }
 
@@ -337,15 +870,53 @@ impl MapState {
         self.drag_offset = None;
     }
 
+    /// Change the size of the grid squares, while scaling the grid positions of
+    /// the stations and the canvas offset so the map looks the same as before.
+    /// As the zoom factor applies equally to the old and new square size, it
+    /// does not affect the scaling.
+    pub fn set_square_size(&mut self, size: u32) {
+        let old_size = self
+            .canvas
+            .get_square_size();
+        if size == old_size || size == 0 {
+            return;
+        }
+        let factor = f64::from(old_size) / f64::from(size);
+
+        self.clear_all_selections();
+        self.get_mut_map("changed grid size")
+            .rescale_grid(factor);
+        if let Some(last_loaded) = self
+            .last_loaded
+            .as_mut()
+        {
+            last_loaded.rescale_grid(factor);
+        }
+
+        let offset = self
+            .canvas
+            .get_offset();
+        self.canvas
+            .set_square_size(size);
+        self.canvas
+            .set_offset((
+                (f64::from(offset.0) * factor).round() as i32,
+                (f64::from(offset.1) * factor).round() as i32,
+            ));
+    }
+
     /// A getter method for the last loaded map.
     pub fn get_last_loaded(&self) -> Option<&Map> {
         self.last_loaded
             .as_ref()
     }
 
-    /// A setter method for the last loaded map.
+    /// A setter method for the last loaded map. This clears the nodes occupied
+    /// after the last run of the algorithm, as they belong to another map.
     pub fn set_last_loaded(&mut self, map: Map) {
+        self.map_changed();
         self.last_loaded = Some(map);
+        self.last_occupied = None;
     }
 
     /// A getter method for the original overlay enabled state.
@@ -359,6 +930,100 @@ impl MapState {
         self.original_overlay_enabled = enabled;
     }
 
+    /// A getter method for the overlapping edges overlay enabled state.
+    #[inline]
+    pub fn is_overlap_overlay_enabled(&self) -> bool {
+        self.overlap_overlay_enabled
+    }
+
+    /// A setter method for the overlapping edges overlay enabled state.
+    pub fn set_overlap_overlay_enabled(&mut self, enabled: bool) {
+        self.overlap_overlay_enabled = enabled;
+    }
+
+    /// A getter method for the displaced stations overlay enabled state.
+    #[inline]
+    pub fn is_displacement_overlay_enabled(&self) -> bool {
+        self.displacement_overlay_enabled
+    }
+
+    /// A setter method for the displaced stations overlay enabled state.
+    pub fn set_displacement_overlay_enabled(&mut self, enabled: bool) {
+        self.displacement_overlay_enabled = enabled;
+    }
+
+    /// A getter method for the contracted stations overlay enabled state.
+    #[inline]
+    pub fn is_contracted_overlay_enabled(&self) -> bool {
+        self.contracted_overlay_enabled
+    }
+
+    /// A setter method for the contracted stations overlay enabled state.
+    pub fn set_contracted_overlay_enabled(&mut self, enabled: bool) {
+        self.contracted_overlay_enabled = enabled;
+    }
+
+    /// A getter method for the lines that have the order of their stations
+    /// drawn.
+    #[inline]
+    pub fn get_station_order_lines(&self) -> &[LineID] {
+        &self.station_order_lines
+    }
+
+    /// Check if the order of the stations of the given line is drawn.
+    pub fn is_station_order_shown(&self, line: LineID) -> bool {
+        self.station_order_lines
+            .contains(&line)
+    }
+
+    /// Set if the order of the stations of the given line is drawn.
+    pub fn set_station_order_shown(&mut self, line: LineID, shown: bool) {
+        self.station_order_lines
+            .retain(|id| *id != line);
+        if shown {
+            self.station_order_lines
+                .push(line);
+        }
+    }
+
+    /// Anchor the given station by moving it back to its original position and
+    /// locking it there, so the algorithm keeps it in place. If another
+    /// station is at the original position, the station is locked where it
+    /// is.
+    pub fn anchor_station(&mut self, id: StationID) {
+        let Some(original_pos) = self
+            .map
+            .get_station(id)
+            .map(Station::get_original_pos)
+        else {
+            return;
+        };
+        let is_free = self
+            .map
+            .station_at_node(original_pos)
+            .is_none_or(|other| other == id);
+
+        let station = self
+            .get_mut_map("anchored station")
+            .get_mut_station(id)
+            .expect("station to anchor exists");
+        if is_free {
+            station.set_pos(original_pos);
+        }
+        station.lock();
+    }
+
+    /// A getter method for if the map is read-only.
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// A setter method for if the map is read-only.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
     /// A getter method for the state of the canvas.
     #[inline]
     pub fn get_canvas_state(&self) -> CanvasState {
@@ -370,7 +1035,152 @@ impl MapState {
     where
         F: FnOnce(&mut CanvasState),
     {
+        self.canvas
+            .set_pan_bounds(
+                self.map
+                    .bounds(),
+            );
+        let before = self
+            .canvas
+            .get_view();
         f(&mut self.canvas);
+
+        // The view changes while dragging the map are not stored, as the view
+        // from before the drag was stored when it started.
+        let is_dragging_map = matches!(self.drag_offset, Some((_, false)));
+        if self.view_history
+            && !is_dragging_map
+            && self
+                .canvas
+                .get_view()
+                != before
+        {
+            HistoryState::push_view(before);
+        }
+    }
+
+    /// Store the current view in the view history, if it is enabled.
+    pub fn record_view(&self) {
+        if self.view_history {
+            HistoryState::push_view(
+                self.canvas
+                    .get_view(),
+            );
+        }
+    }
+
+    /// Go back to the view that was looked at before the current one, if the
+    /// view history is enabled. This does not change the map.
+    pub fn view_back(&mut self) {
+        if !self.view_history {
+            return;
+        }
+        if let Some(view) = HistoryState::view_back(
+            self.canvas
+                .get_view(),
+        ) {
+            self.canvas
+                .set_view(view);
+        }
+    }
+
+    /// Go forward to the view that was stepped back from last, if the view
+    /// history is enabled. This does not change the map.
+    pub fn view_forward(&mut self) {
+        if !self.view_history {
+            return;
+        }
+        if let Some(view) = HistoryState::view_forward(
+            self.canvas
+                .get_view(),
+        ) {
+            self.canvas
+                .set_view(view);
+        }
+    }
+
+    /// Zoom and pan the canvas so the whole map fits on it, as an escape hatch
+    /// for when the map got lost while panning.
+    pub fn recenter(&mut self) {
+        let bounds = self
+            .map
+            .bounds();
+        self.update_canvas_state(|canvas| canvas.fit_to(bounds));
+    }
+
+    /// Zoom and pan the canvas so the selected stations and edges fit on it. A
+    /// single selected station is centered on without changing the zoom, and
+    /// nothing happens if nothing is selected.
+    pub fn fit_selection(&mut self) {
+        let nodes = self
+            .selected_stations
+            .iter()
+            .map(|s| {
+                s.get_station()
+                    .get_pos()
+            })
+            .chain(
+                self.selected_edges
+                    .iter()
+                    .filter_map(|id| {
+                        self.map
+                            .get_edge(*id)
+                    })
+                    .flat_map(|edge| {
+                        edge.get_nodes()
+                            .iter()
+                            .copied()
+                            .chain(
+                                [edge.get_from(), edge.get_to()]
+                                    .into_iter()
+                                    .filter_map(|id| {
+                                        self.map
+                                            .get_station(id)
+                                    })
+                                    .map(Station::get_pos),
+                            )
+                    }),
+            )
+            .collect::<Vec<_>>();
+
+        let Some(first) = nodes
+            .first()
+            .copied()
+        else {
+            return;
+        };
+        let (min, max) = nodes
+            .iter()
+            .fold((first, first), |(min, max), node| {
+                (
+                    GridNode(
+                        min.0
+                            .min(node.0),
+                        min.1
+                            .min(node.1),
+                    ),
+                    GridNode(
+                        max.0
+                            .max(node.0),
+                        max.1
+                            .max(node.1),
+                    ),
+                )
+            });
+
+        // Fitting limits panning to the given bounds, so the bounds of the whole
+        // map are set again afterwards.
+        let map_bounds = self
+            .map
+            .bounds();
+        self.update_canvas_state(|canvas| {
+            if min == max {
+                canvas.center_on(min);
+            } else {
+                canvas.fit_to(Some((min, max)));
+                canvas.set_pan_bounds(map_bounds);
+            }
+        });
     }
 
     /// Draw the current state to the provided canvas.
@@ -441,6 +1251,312 @@ impl MapState {
         self.clicked_on_station = None;
     }
 
+    /// Getter for the station whose label is being dragged, the point the drag
+    /// started from and the label offset of the station before the drag.
+    pub fn get_dragged_label(&self) -> Option<DraggedLabel> {
+        self.dragged_label
+    }
+
+    /// Start dragging the label of the given station from the given canvas
+    /// coordinate.
+    pub fn start_label_drag(&mut self, station: StationID, start: (f64, f64)) {
+        let Some(offset) = self
+            .map
+            .get_station(station)
+            .map(Station::get_label_offset)
+        else {
+            return;
+        };
+
+        self.dragged_label = Some((station, start, offset));
+    }
+
+    /// Stop dragging the dragged label, moving it back to where it was before
+    /// the drag.
+    pub fn cancel_label_drag(&mut self) {
+        let Some((station, _, offset)) = self
+            .dragged_label
+            .take()
+        else {
+            return;
+        };
+
+        self.map_changed();
+        if let Some(station) = self
+            .map
+            .get_mut_station(station)
+        {
+            station.set_label_offset(offset);
+        }
+    }
+
+    /// Get the label offset the dragged label would get when dropped at the
+    /// given canvas coordinate, snapped to whole grid squares.
+    fn dragged_label_offset(&self, pos: (f64, f64)) -> Option<(StationID, (i32, i32))> {
+        let (station, start, offset) = self.dragged_label?;
+        let grid_offset = canvas_offset_to_grid_offset(
+            (pos.0 - start.0, pos.1 - start.1),
+            self.canvas
+                .drawn_square_size(),
+        );
+
+        Some((
+            station,
+            (
+                offset.0 + grid_offset.0,
+                offset.1 + grid_offset.1,
+            ),
+        ))
+    }
+
+    /// Move the dragged label to the given canvas coordinate, without storing
+    /// it in the redo/undo history.
+    pub fn move_dragged_label(&mut self, pos: (f64, f64)) {
+        let Some((station, offset)) = self.dragged_label_offset(pos) else {
+            return;
+        };
+
+        self.map_changed();
+        if let Some(station) = self
+            .map
+            .get_mut_station(station)
+        {
+            station.set_label_offset(offset);
+        }
+    }
+
+    /// Drop the dragged label at the given canvas coordinate and store the
+    /// moved label in the redo/undo history, if it moved at all.
+    pub fn drop_dragged_label(&mut self, pos: (f64, f64)) {
+        let Some((station_id, offset)) = self.dragged_label_offset(pos) else {
+            return;
+        };
+
+        // Put the label back where it was before the drag first, so the
+        // history stores the map as it was before the label moved.
+        self.cancel_label_drag();
+        if self
+            .map
+            .get_station(station_id)
+            .is_none_or(|station| station.get_label_offset() == offset)
+        {
+            return;
+        }
+
+        self.get_mut_map("moved label")
+            .get_mut_station(station_id)
+            .expect("dragged station exists")
+            .set_label_offset(offset);
+    }
+
+    /// Get the currently selected edges and the stations of the selected
+    /// stations, as used by the line straightening algorithm.
+    pub fn get_selected_section(&self) -> (Vec<Edge>, Vec<Station>) {
+        let stations = self
+            .selected_stations
+            .iter()
+            .map(SelectedStation::get_station)
+            .cloned()
+            .collect();
+        let edges = self
+            .selected_edges
+            .iter()
+            .filter_map(|id| {
+                self.map
+                    .get_edge(*id)
+            })
+            .cloned()
+            .collect();
+
+        (edges, stations)
+    }
+
+    /// Getter for the previewed straightening of the selected stations.
+    pub fn get_straighten_preview(&self) -> Option<&StraighteningCandidate> {
+        self.straighten_preview
+            .as_ref()
+    }
+
+    /// Setter for the previewed straightening of the selected stations.
+    pub fn set_straighten_preview(&mut self, candidate: StraighteningCandidate) {
+        self.straighten_preview = Some(candidate);
+    }
+
+    /// Clear the previewed straightening of the selected stations.
+    pub fn clear_straighten_preview(&mut self) {
+        self.straighten_preview = None;
+    }
+
+    /// Getter for the nodes occupied after the last run of the algorithm.
+    pub fn get_last_occupied(&self) -> Option<&OccupiedNodes> {
+        self.last_occupied
+            .as_ref()
+    }
+
+    /// Setter for the nodes occupied after the last run of the algorithm.
+    pub fn set_last_occupied(&mut self, occupied: OccupiedNodes) {
+        self.last_occupied = Some(occupied);
+    }
+
+    /// Check if the ids of the map are renumbered into a compact range when it
+    /// is downloaded or exported.
+    #[inline]
+    pub fn is_export_ids_normalized(&self) -> bool {
+        self.normalize_export_ids
+    }
+
+    /// Set if the ids of the map are renumbered into a compact range when it
+    /// is downloaded or exported.
+    pub fn set_export_ids_normalized(&mut self, normalized: bool) {
+        self.normalize_export_ids = normalized;
+    }
+
+    /// Getter for the maximum amount of stations or edges an imported map may
+    /// have before asking the user to confirm, 0 meaning there is no limit.
+    #[inline]
+    pub fn get_max_import_size(&self) -> usize {
+        self.max_import_size
+    }
+
+    /// Set the maximum amount of stations or edges an imported map may have
+    /// before asking the user to confirm, 0 meaning there is no limit.
+    pub fn set_max_import_size(&mut self, max_import_size: usize) {
+        self.max_import_size = max_import_size;
+    }
+
+    /// Check if panning and zooming the canvas is stored in the view history.
+    #[inline]
+    pub fn is_view_history_enabled(&self) -> bool {
+        self.view_history
+    }
+
+    /// Set if panning and zooming the canvas is stored in the view history.
+    pub fn set_view_history_enabled(&mut self, enabled: bool) {
+        self.view_history = enabled;
+    }
+
+    /// Get the map as it should be downloaded or exported, with its ids
+    /// renumbered if that is enabled.
+    pub fn get_export_map(&self) -> Map {
+        if self.normalize_export_ids {
+            self.map
+                .with_normalized_ids()
+        } else {
+            self.map
+                .clone()
+        }
+    }
+
+    /// Getter for the weights used to combine the layout score into a single
+    /// number.
+    pub fn get_layout_weights(&self) -> LayoutWeights {
+        self.layout_weights
+    }
+
+    /// Setter for the weights used to combine the layout score into a single
+    /// number.
+    pub fn set_layout_weights(&mut self, weights: LayoutWeights) {
+        self.layout_weights = weights;
+    }
+
+    /// Getter for the station focused with the keyboard.
+    pub fn get_focused_station(&self) -> Option<StationID> {
+        self.focused_station
+    }
+
+    /// Move the keyboard focus to the next station in order of id, or the
+    /// previous one if going backwards. The newly focused station is selected
+    /// and the canvas is centered on it.
+    pub fn focus_next_station(&mut self, backwards: bool) {
+        let current = self
+            .clicked_on_station
+            .as_ref()
+            .map(Station::get_id)
+            .or(self.focused_station);
+
+        let Some(station) = self
+            .map
+            .get_next_station_id(current, backwards)
+            .and_then(|id| {
+                self.map
+                    .get_station(id)
+                    .cloned()
+            })
+        else {
+            return;
+        };
+
+        self.clear_all_selections();
+        self.update_canvas_state(|canvas| canvas.center_on(station.get_pos()));
+        self.focused_station = Some(station.get_id());
+        self.set_selected_stations(vec![SelectedStation::new(
+            station.clone(),
+        )]);
+        self.set_clicked_on_station(station);
+    }
+
+    /// Select all stations and edges of the given line and move the canvas so
+    /// the line is in the center.
+    pub fn focus_line(&mut self, id: LineID) {
+        let Some(line) = self
+            .map
+            .get_line(id)
+            .cloned()
+        else {
+            return;
+        };
+
+        let stations = line
+            .get_stations()
+            .iter()
+            .filter_map(|s| {
+                self.map
+                    .get_station(*s)
+                    .cloned()
+            })
+            .collect::<Vec<_>>();
+        let Some((min_x, min_y, max_x, max_y)) = stations
+            .iter()
+            .map(Station::get_pos)
+            .fold(None, |acc, pos| {
+                let (min_x, min_y, max_x, max_y) = acc.unwrap_or((pos.0, pos.1, pos.0, pos.1));
+                Some((
+                    min_x.min(pos.0),
+                    min_y.min(pos.1),
+                    max_x.max(pos.0),
+                    max_y.max(pos.1),
+                ))
+            })
+        else {
+            return;
+        };
+
+        self.clear_all_selections();
+        self.update_canvas_state(|canvas| {
+            canvas.center_on(GridNode::from((
+                i32::midpoint(min_x, max_x),
+                i32::midpoint(min_y, max_y),
+            )));
+        });
+        self.set_selected_stations(
+            stations
+                .into_iter()
+                .map(SelectedStation::new)
+                .collect(),
+        );
+        self.set_selected_edges(
+            line.get_edges()
+                .iter()
+                .copied()
+                .filter(|e| {
+                    self.map
+                        .get_edge(*e)
+                        .is_some()
+                })
+                .collect(),
+        );
+    }
+
     /// Getter for the clicked on edge.
     pub fn get_clicked_on_edge(&self) -> Option<&Edge> {
         self.clicked_on_edge
@@ -498,35 +1614,727 @@ impl MapState {
         map
     }
 
+    /// Create a map with everything locked except the stations and edges of
+    /// the given line, so only that line is laid out again against the rest of
+    /// the map. If `keep_junctions` is set, the stations the line shares with
+    /// other lines stay locked as well.
+    pub fn lock_all_except_line(&self, id: LineID, keep_junctions: bool) -> Map {
+        let mut map = self
+            .map
+            .clone();
+
+        let free_stations = map
+            .get_stations()
+            .into_iter()
+            .filter(|station| {
+                let lines = map.lines_through_station(station.get_id());
+                lines
+                    .iter()
+                    .any(|line| line.get_id() == id)
+                    && (!keep_junctions || lines.len() == 1)
+            })
+            .map(Station::get_id)
+            .collect::<Vec<_>>();
+
+        for edge in map.get_mut_edges() {
+            if !edge
+                .get_lines()
+                .contains(&id)
+            {
+                edge.lock();
+            }
+        }
+
+        for station in map.get_mut_stations() {
+            if !free_stations.contains(&station.get_id()) {
+                station.lock();
+            }
+        }
+
+        map
+    }
+
+    /// Create a map with everything locked except the stations currently
+    /// visible on the canvas and the edges connected to them, so only the
+    /// visible area is laid out again. Edges crossing the edge of the canvas
+    /// are kept free, while their station outside of it stays locked. Returns
+    /// an error if no stations are visible.
+    pub fn lock_all_outside_view(&self) -> Result<Map> {
+        let mut map = self
+            .map
+            .clone();
+
+        let visible_stations = map
+            .get_stations()
+            .into_iter()
+            .filter(|station| {
+                self.canvas
+                    .is_on_canvas(station.get_pos())
+            })
+            .map(Station::get_id)
+            .collect::<HashSet<_>>();
+        if visible_stations.is_empty() {
+            return Err(Error::other(
+                "No stations are visible to recalculate, move the map into view first.",
+            ));
+        }
+
+        for edge in map.get_mut_edges() {
+            if !visible_stations.contains(&edge.get_from())
+                && !visible_stations.contains(&edge.get_to())
+            {
+                edge.lock();
+            }
+        }
+
+        for station in map.get_mut_stations() {
+            if !visible_stations.contains(&station.get_id()) {
+                station.lock();
+            }
+        }
+
+        Ok(map)
+    }
+
     /// Recalculate the x and y limits for the algorithm settings based on the
     /// current map.
     pub fn calculate_algorithm_settings(&mut self) {
-        let mut x_limits = (i32::MAX, i32::MIN);
-        let mut y_limits = (i32::MAX, i32::MIN);
+        self.algorithm_settings = self
+            .algorithm_settings
+            .fit_grid_limits(
+                self.map
+                    .bounds(),
+            );
+    }
+}
 
-        for station in self
-            .map
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::LINE_PALETTE,
+        utils::json,
+        CanvasState,
+    };
+
+    #[test]
+    fn test_select_connected() {
+        let test_file_content = std::fs::read_to_string("existing_maps/disjointed_test.json")
+            .expect("test data file does not exist");
+        let map = json::decode_map(&test_file_content, CanvasState::new())
+            .expect("failed to decode json");
+        let mut state = MapState::new(map);
+
+        let selected_stations = |state: &MapState| {
+            let mut stations = state
+                .get_selected_stations()
+                .iter()
+                .map(|s| {
+                    u64::from(
+                        s.get_station()
+                            .get_id(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            stations.sort_unstable();
+            stations
+        };
+
+        state.select_connected(&[2.into()], false);
+        assert_eq!(selected_stations(&state), vec![1, 2, 3]);
+        assert_eq!(
+            state
+                .get_selected_edges()
+                .len(),
+            2
+        );
+
+        // Without merging, the other component replaces the selection.
+        state.select_connected(&[5.into()], false);
+        assert_eq!(selected_stations(&state), vec![4, 5, 6]);
+
+        // Merging keeps the component that was selected already.
+        state.select_connected(&[1.into()], true);
+        assert_eq!(
+            selected_stations(&state),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+        assert_eq!(
+            state
+                .get_selected_edges()
+                .len(),
+            4
+        );
+
+        // Station 7 has no edges, so it is a component on its own.
+        state.clear_all_selections();
+        state.select_station(SelectedStation::new(
+            state
+                .get_map()
+                .get_station(7.into())
+                .unwrap()
+                .clone(),
+        ));
+        state.select_connected_to_selection();
+        assert_eq!(selected_stations(&state), vec![7]);
+    }
+
+    #[test]
+    fn test_lock_all_except_line() {
+        let test_file_content = std::fs::read_to_string("existing_maps/routing_test.json")
+            .expect("test data file does not exist");
+        let map = json::decode_map(&test_file_content, CanvasState::new())
+            .expect("failed to decode json");
+        let line = map
+            .get_lines()
+            .into_iter()
+            .find(|l| l.get_name() == "lineU2")
+            .expect("line does not exist")
+            .get_id();
+        let state = MapState::new(map);
+
+        let unlocked_stations = |map: &Map| {
+            let mut stations = map
+                .get_stations()
+                .into_iter()
+                .filter(|s| !s.is_locked())
+                .map(|s| u64::from(s.get_id()))
+                .collect::<Vec<_>>();
+            stations.sort_unstable();
+            stations
+        };
+
+        let result = state.lock_all_except_line(line, false);
+        assert_eq!(
+            unlocked_stations(&result),
+            vec![2, 4, 5, 6]
+        );
+        for edge in result.get_edges() {
+            assert_eq!(
+                edge.is_locked(),
+                !edge
+                    .get_lines()
+                    .contains(&line)
+            );
+        }
+
+        let result = state.lock_all_except_line(line, true);
+        assert_eq!(unlocked_stations(&result), vec![2, 6]);
+    }
+
+    #[test]
+    fn test_lock_all_outside_view() {
+        let mut map = Map::new();
+        let inside = map.add_named_station(&"Inside", (5, 5).into());
+        let edge_of_view = map.add_named_station(&"Edge of view", (15, 5).into());
+        let outside = map.add_named_station(&"Outside", (40, 5).into());
+        let far = map.add_named_station(&"Far", (60, 5).into());
+        map.add_line_through(
+            (255, 0, 0),
+            &"Red",
+            &[inside, edge_of_view, outside, far],
+        );
+
+        let mut state = MapState::new(map);
+        state.update_canvas_state(|canvas| {
+            canvas.set_square_size(10);
+            canvas.set_size((100.0, 200.0));
+            canvas.set_offset((0, 0));
+        });
+
+        let result = state
+            .lock_all_outside_view()
+            .unwrap();
+        for (station, locked) in [
+            (inside, false),
+            (edge_of_view, false),
+            (outside, true),
+            (far, true),
+        ] {
+            assert_eq!(
+                result
+                    .get_station(station)
+                    .unwrap()
+                    .is_locked(),
+                locked
+            );
+        }
+
+        // The edge crossing the edge of the canvas is free, the one fully
+        // outside of it is locked.
+        for (from, to, locked) in [
+            (inside, edge_of_view, false),
+            (edge_of_view, outside, false),
+            (outside, far, true),
+        ] {
+            assert_eq!(
+                result
+                    .get_edge(
+                        result
+                            .get_edge_id_between_if_exists(from, to)
+                            .unwrap()
+                    )
+                    .unwrap()
+                    .is_locked(),
+                locked
+            );
+        }
+
+        state.update_canvas_state(|canvas| {
+            canvas.set_pan_limited(false);
+            canvas.set_offset((200, 200));
+        });
+        assert!(
+            state
+                .lock_all_outside_view()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_view_history() {
+        let mut state = MapState::new(Map::new());
+        let start = state
+            .get_canvas_state()
+            .get_view();
+
+        // Off by default, so nothing is stored.
+        state.update_canvas_state(CanvasState::zoom_in);
+        state.view_back();
+        assert_ne!(
+            state
+                .get_canvas_state()
+                .get_view(),
+            start
+        );
+
+        state.set_view_history_enabled(true);
+        let zoomed = state
+            .get_canvas_state()
+            .get_view();
+        state.update_canvas_state(|canvas| canvas.set_offset((10, 10)));
+
+        // Dragging the map only stores the view from before the drag.
+        state.set_drag_offset(Some(((0.0, 0.0), false)));
+        state.update_canvas_state(|canvas| canvas.set_offset((20, 20)));
+        state.update_canvas_state(|canvas| canvas.set_offset((30, 30)));
+        state.clear_drag_offset();
+
+        state.view_back();
+        assert_eq!(
+            state
+                .get_canvas_state()
+                .get_offset(),
+            (10, 10)
+        );
+        state.view_back();
+        assert_eq!(
+            state
+                .get_canvas_state()
+                .get_view(),
+            zoomed
+        );
+        state.view_forward();
+        assert_eq!(
+            state
+                .get_canvas_state()
+                .get_offset(),
+            (10, 10)
+        );
+
+        // Map changes are kept apart from the view history.
+        assert!(HistoryState::cannot_undo());
+    }
+
+    #[test]
+    fn test_restore_from_history_drops_missing() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (2, 0).into(),
+            Some(station2),
+        ));
+        let edge = map.get_edge_id_between(station1, station2);
+
+        // The stored map still marks the edge as selected.
+        let mut stored = map.clone();
+        stored
+            .get_mut_edge(edge)
+            .unwrap()
+            .select();
+
+        let mut state = MapState::new(map);
+        state.restore_from_history((
+            stored,
+            HistorySelection {
+                stations: vec![station1, 3.into()],
+                edges: vec![4.into()],
+                lines: Vec::new(),
+            },
+        ));
+
+        assert_eq!(
+            state
+                .get_selected_stations()
+                .iter()
+                .map(|s| {
+                    s.get_station()
+                        .get_id()
+                })
+                .collect::<Vec<_>>(),
+            vec![station1]
+        );
+        assert!(
+            state
+                .get_selected_edges()
+                .is_empty()
+        );
+        assert!(
+            !state
+                .get_map()
+                .get_edge(edge)
+                .unwrap()
+                .is_selected()
+        );
+
+        let selection = state.get_history_selection();
+        assert_eq!(selection.stations, vec![station1]);
+    }
+
+    #[test]
+    fn test_clear_map() {
+        let test_file_content = std::fs::read_to_string("existing_maps/routing_test.json")
+            .expect("test data file does not exist");
+        let map = json::decode_map(&test_file_content, CanvasState::new())
+            .expect("failed to decode json");
+        let station = map
             .get_stations()
-        {
-            let pos = station.get_pos();
-
-            x_limits.0 = x_limits
-                .0
-                .min(pos.0);
-            x_limits.1 = x_limits
-                .1
-                .max(pos.0);
-            y_limits.0 = y_limits
-                .0
-                .min(pos.1);
-            y_limits.1 = y_limits
-                .1
-                .max(pos.1);
+            .into_iter()
+            .next()
+            .expect("map has no stations")
+            .clone();
+
+        let mut state = MapState::new(map.clone());
+        state.set_last_loaded(map.clone());
+        state.set_original_overlay_enabled(true);
+        state.set_selected_stations(vec![SelectedStation::new(
+            station.clone(),
+        )]);
+
+        state.clear_map();
+
+        assert!(
+            state
+                .get_map()
+                .get_stations()
+                .is_empty()
+        );
+        assert!(
+            state
+                .get_map()
+                .get_lines()
+                .is_empty()
+        );
+        assert!(
+            state
+                .get_selected_stations()
+                .is_empty()
+        );
+        assert!(
+            state
+                .get_last_loaded()
+                .is_none()
+        );
+        assert!(!state.is_original_overlay_enabled());
+
+        // Undoing brings back the map and what was selected on it.
+        assert!(state.undo());
+        assert_eq!(
+            state
+                .get_map()
+                .get_stations()
+                .len(),
+            map.get_stations()
+                .len()
+        );
+        assert_eq!(
+            state
+                .get_selected_stations()
+                .iter()
+                .map(|s| {
+                    s.get_station()
+                        .get_id()
+                })
+                .collect::<Vec<_>>(),
+            vec![station.get_id()]
+        );
+    }
+
+    #[test]
+    fn test_anchor_station() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (4, 0).into(),
+            Some(station2),
+        ));
+        map.get_mut_station(station1)
+            .unwrap()
+            .set_pos((2, 2).into());
+        map.get_mut_station(station2)
+            .unwrap()
+            .set_pos((0, 0).into());
+
+        let mut state = MapState::new(map);
+
+        // The original position of the first station is taken by the second.
+        state.anchor_station(station1);
+        let station = state
+            .get_map()
+            .get_station(station1)
+            .unwrap();
+        assert_eq!(station.get_pos(), (2, 2));
+        assert!(station.is_locked());
+
+        state.anchor_station(station2);
+        let station = state
+            .get_map()
+            .get_station(station2)
+            .unwrap();
+        assert_eq!(station.get_pos(), (4, 0));
+        assert!(station.is_locked());
+    }
+
+    #[test]
+    fn test_fit_selection() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        let station3: StationID = 3.into();
+        for (id, pos) in [
+            (station1, (10, 10)),
+            (station2, (26, 14)),
+            (station3, (100, 100)),
+        ] {
+            map.add_station(Station::new(pos.into(), Some(id)));
         }
 
-        self.algorithm_settings
-            .grid_x_limits = (x_limits.0 - 2, x_limits.1 + 2);
-        self.algorithm_settings
-            .grid_y_limits = (y_limits.0 - 2, y_limits.1 + 2);
+        let mut state = MapState::new(map);
+        state.update_canvas_state(|canvas| {
+            canvas.set_square_size(10);
+            canvas.set_size((100.0, 200.0));
+        });
+        let selected = |state: &MapState, ids: &[StationID]| {
+            ids.iter()
+                .map(|id| {
+                    SelectedStation::new(
+                        state
+                            .get_map()
+                            .get_station(*id)
+                            .unwrap()
+                            .clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Nothing selected leaves the canvas as it is.
+        let before = state
+            .get_canvas_state()
+            .get_offset();
+        state.fit_selection();
+        assert_eq!(
+            state
+                .get_canvas_state()
+                .get_offset(),
+            before
+        );
+
+        let stations = selected(&state, &[station1, station2]);
+        state.set_selected_stations(stations);
+        state.fit_selection();
+        let canvas = state.get_canvas_state();
+        assert!((canvas.get_zoom_factor() - 1.0).abs() < f64::EPSILON);
+        assert_eq!(canvas.get_offset(), (8, 7));
+
+        // A single station is centered on at the same zoom.
+        let stations = selected(&state, &[station3]);
+        state.set_selected_stations(stations);
+        state.fit_selection();
+        let canvas = state.get_canvas_state();
+        assert!((canvas.get_zoom_factor() - 1.0).abs() < f64::EPSILON);
+        assert!(canvas.is_on_canvas((100, 100).into()));
+    }
+
+    #[test]
+    fn test_drag_label() {
+        let mut map = Map::new();
+        let station_id: StationID = 1.into();
+        let mut station = Station::new((5, 5).into(), Some(station_id));
+        station.set_name(&"Central");
+        map.add_station(station);
+
+        let mut state = MapState::new(map);
+        state.update_canvas_state(|canvas| canvas.set_square_size(10));
+        let label_offset = |state: &MapState| {
+            state
+                .get_map()
+                .get_station(station_id)
+                .unwrap()
+                .get_label_offset()
+        };
+
+        // Cancelling puts the label back where it was.
+        state.start_label_drag(station_id, (50.0, 50.0));
+        state.move_dragged_label((71.0, 41.0));
+        assert_eq!(label_offset(&state), (2, -1));
+        state.cancel_label_drag();
+        assert_eq!(label_offset(&state), (0, 0));
+        assert!(
+            state
+                .get_dragged_label()
+                .is_none()
+        );
+
+        // Dropping the label snaps it to whole grid squares.
+        state.start_label_drag(station_id, (50.0, 50.0));
+        state.move_dragged_label((60.0, 50.0));
+        state.drop_dragged_label((71.0, 41.0));
+        assert_eq!(label_offset(&state), (2, -1));
+        assert!(
+            state
+                .get_dragged_label()
+                .is_none()
+        );
+
+        // Dragging on from there adds to the offset.
+        state.start_label_drag(station_id, (0.0, 0.0));
+        state.drop_dragged_label((-30.0, 0.0));
+        assert_eq!(label_offset(&state), (-1, -1));
+    }
+
+    #[test]
+    fn test_start_drawing_named_line() {
+        let mut state = MapState::new(Map::new());
+
+        state.start_drawing_named_line(" Red Line ", (227, 6, 19));
+        assert!(state.is_drawing_line());
+
+        let line_id = state
+            .get_selected_lines()
+            .first()
+            .unwrap()
+            .get_line();
+        let line = state
+            .get_map()
+            .get_line(line_id)
+            .unwrap();
+        assert_eq!(line.get_name(), "Red Line");
+        assert_eq!(line.get_color(), (227, 6, 19));
+
+        // A line without a name can still be drawn.
+        state.cancel_drawing_line();
+        state.start_drawing_named_line("", (0, 101, 189));
+        assert!(state.is_drawing_line());
+        let line_id = state
+            .get_selected_lines()
+            .first()
+            .unwrap()
+            .get_line();
+        assert_eq!(
+            state
+                .get_map()
+                .get_line(line_id)
+                .unwrap()
+                .get_name(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_cycle_line_colors() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (2, 0).into());
+        let c = map.add_named_station(&"C", (4, 0).into());
+        let red = map.add_line_through(LINE_PALETTE[0], &"Red", &[a, b]);
+        let blue = map.add_line_through(LINE_PALETTE[1], &"Blue", &[b, c]);
+        let edge = map
+            .get_edge_id_between_if_exists(a, b)
+            .unwrap();
+
+        let mut state = MapState::new(map);
+        let color = |state: &MapState, line| {
+            state
+                .get_map()
+                .get_line(line)
+                .unwrap()
+                .get_color()
+        };
+
+        // Nothing is cycled without a selection.
+        state.cycle_line_colors();
+        assert_eq!(color(&state, red), LINE_PALETTE[0]);
+
+        state.select_edge(edge);
+        state.cycle_line_colors();
+        assert_eq!(color(&state, red), LINE_PALETTE[1]);
+        // The line that is not on the selected edge keeps its color.
+        assert_eq!(color(&state, blue), LINE_PALETTE[1]);
+    }
+
+    #[test]
+    fn test_map_revision() {
+        let mut map = Map::new();
+        let station_id: StationID = 1.into();
+        map.add_station(Station::new((5, 5).into(), Some(station_id)));
+        let mut state = MapState::new(map);
+        let revision = state.get_map_revision();
+
+        // Panning and zooming leave the map as it is.
+        state.update_canvas_state(|canvas| canvas.set_square_size(10));
+        assert_eq!(state.get_map_revision(), revision);
+
+        // Moving a label changes the map without storing it in the history.
+        state.start_label_drag(station_id, (50.0, 50.0));
+        state.move_dragged_label((71.0, 41.0));
+        assert!(state.get_map_revision() > revision);
+
+        let revision = state.get_map_revision();
+        state.set_last_loaded(Map::new());
+        assert!(state.get_map_revision() > revision);
+
+        let revision = state.get_map_revision();
+        state.get_mut_map("moved station");
+        assert!(state.get_map_revision() > revision);
+    }
+    #[test]
+    fn test_last_occupied_cleared() {
+        let mut state = MapState::new(Map::new());
+        state.set_last_occupied(OccupiedNodes::new());
+        state.set_map(Map::new(), "recalculated map");
+        assert!(
+            state
+                .get_last_occupied()
+                .is_none()
+        );
+
+        state.set_last_occupied(OccupiedNodes::new());
+        state.set_last_loaded(Map::new());
+        assert!(
+            state
+                .get_last_occupied()
+                .is_none()
+        );
     }
 }
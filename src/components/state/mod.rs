@@ -1,23 +1,199 @@
 //! Contains everything for keeping track of the current state of the page.
 
+use std::collections::HashMap;
+
 use leptos::prelude::*;
 
 mod canvas;
 mod error;
 mod history;
 mod interaction;
+mod key_bindings;
 mod map;
 
-pub use canvas::CanvasState;
+pub use canvas::{
+    CanvasState,
+    CanvasView,
+};
 pub use error::ErrorState;
 pub use history::HistoryState;
 pub use interaction::InteractionState;
+pub use key_bindings::{
+    is_apple_platform,
+    is_text_field_focused,
+    KeyAction,
+    KeyBinding,
+    KeyBindings,
+};
 pub use map::{
     ActionType,
     MapState,
 };
 
-use crate::models::Map;
+use crate::{
+    algorithms::Theme,
+    models::{
+        EdgeID,
+        LineID,
+        Map,
+        StationID,
+    },
+    utils::Result,
+};
+
+/// The key the chosen theme is stored under in the local storage of the
+/// browser.
+const THEME_STORAGE_KEY: &str = "theme";
+
+/// The key the keyboard shortcuts are stored under in the local storage of
+/// the browser.
+const KEY_BINDINGS_STORAGE_KEY: &str = "key_bindings";
+
+/// Get the theme the user chose before, falling back to the theme preferred by
+/// the browser if none was chosen yet.
+fn initial_theme() -> Theme {
+    let window = window();
+
+    let stored = window
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| {
+            storage
+                .get_item(THEME_STORAGE_KEY)
+                .ok()
+                .flatten()
+        })
+        .and_then(|name| Theme::from_name(&name));
+    if let Some(theme) = stored {
+        return theme;
+    }
+
+    let prefers_dark = window
+        .match_media("(prefers-color-scheme: dark)")
+        .ok()
+        .flatten()
+        .is_some_and(|query| query.matches());
+    if prefers_dark {
+        Theme::Dark
+    } else {
+        Theme::Light
+    }
+}
+
+/// Apply the given theme to the page and store it, so it is used again the
+/// next time the page is opened.
+fn apply_theme(theme: Theme) {
+    let window = window();
+
+    if let Some(root) = window
+        .document()
+        .and_then(|document| document.document_element())
+    {
+        let _ = root
+            .class_list()
+            .toggle_with_force("dark", theme == Theme::Dark);
+    }
+
+    if let Some(storage) = window
+        .local_storage()
+        .ok()
+        .flatten()
+    {
+        let _ = storage.set_item(THEME_STORAGE_KEY, theme.as_str());
+    }
+}
+
+/// Get the keyboard shortcuts the user set before, falling back to the
+/// default keys for any action that was not stored.
+fn initial_key_bindings() -> KeyBindings {
+    let mut key_bindings = window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| {
+            storage
+                .get_item(KEY_BINDINGS_STORAGE_KEY)
+                .ok()
+                .flatten()
+        })
+        .and_then(|stored| serde_json::from_str::<KeyBindings>(&stored).ok())
+        .unwrap_or_default();
+    key_bindings.fill_defaults();
+    key_bindings
+}
+
+/// Store the given keyboard shortcuts, so they are used again the next time
+/// the page is opened.
+fn store_key_bindings(key_bindings: &KeyBindings) {
+    let Ok(encoded) = serde_json::to_string(key_bindings) else {
+        return;
+    };
+
+    if let Some(storage) = window()
+        .local_storage()
+        .ok()
+        .flatten()
+    {
+        let _ = storage.set_item(KEY_BINDINGS_STORAGE_KEY, &encoded);
+    }
+}
+
+/// The stations and edges of a line as they were when it was last checked for
+/// being continuous.
+type CheckedLine = (Vec<StationID>, Vec<EdgeID>);
+
+/// Get the lines of the given map whose stations or edges changed since they
+/// were last checked for being continuous, and store their current stations
+/// and edges as checked. Lines that no longer exist are forgotten.
+fn touched_lines(map: &Map, checked: &mut HashMap<LineID, CheckedLine>) -> Vec<LineID> {
+    checked.retain(|id, _| {
+        map.get_line(*id)
+            .is_some()
+    });
+
+    let mut touched = Vec::new();
+    for line in map.get_lines() {
+        let unchanged = checked
+            .get(&line.get_id())
+            .is_some_and(|(stations, edges)| {
+                stations == line.get_stations() && edges == line.get_edges()
+            });
+        if unchanged {
+            continue;
+        }
+
+        checked.insert(
+            line.get_id(),
+            (
+                line.get_stations()
+                    .to_vec(),
+                line.get_edges()
+                    .to_vec(),
+            ),
+        );
+        touched.push(line.get_id());
+    }
+    touched
+}
+
+/// Repair the given lines of the map, which are no longer continuous after an
+/// edit. Returns the repaired map, or the error of the first line that could
+/// not be repaired.
+fn repair_lines(map: &Map, lines: &[LineID]) -> Result<Map> {
+    let mut repaired = map.clone();
+    for id in lines {
+        let Some(mut line) = repaired
+            .get_line(*id)
+            .cloned()
+        else {
+            continue;
+        };
+        line.repair(&repaired)?;
+        repaired.add_line(line);
+    }
+    Ok(repaired)
+}
 
 /// Provides all global state contexts to the page.
 #[component]
@@ -25,17 +201,110 @@ pub fn StateProvider(
     /// The contents of the page that will have access to the global state.
     children: Children,
 ) -> impl IntoView {
-    let map_state = RwSignal::new(MapState::new(Map::new()));
+    let mut initial_state = MapState::new(Map::new());
+    initial_state.update_canvas_state(|canvas| canvas.set_theme(initial_theme()));
+    let map_state = RwSignal::new(initial_state);
     let error_state = RwSignal::new(error::ErrorState::new());
-    let interaction_state = RwSignal::new(interaction::InteractionState::new());
+    let mut initial_interaction = interaction::InteractionState::new();
+    initial_interaction.set_key_bindings(initial_key_bindings());
+    let interaction_state = RwSignal::new(initial_interaction);
 
     provide_context::<RwSignal<MapState>>(map_state);
     provide_context::<RwSignal<ErrorState>>(error_state);
     provide_context::<RwSignal<InteractionState>>(interaction_state);
 
+    let theme = Memo::new(move |_| {
+        map_state
+            .get()
+            .get_canvas_state()
+            .get_theme()
+    });
+    Effect::new(move |_| apply_theme(theme.get()));
+
+    let key_bindings = Memo::new(move |_| {
+        interaction_state
+            .get()
+            .get_key_bindings()
+            .clone()
+    });
+    Effect::new(move |_| store_key_bindings(&key_bindings.get()));
+
+    // Check the lines touched by an edit are still continuous afterwards, but
+    // not while the algorithm is still changing the map. The repair is part of
+    // the edit that broke the lines, so undoing the edit undoes it as well.
+    let checked_lines = StoredValue::new(HashMap::new());
+    Effect::new(move |_| {
+        if interaction_state
+            .get()
+            .is_busy()
+        {
+            return;
+        }
+
+        let broken = map_state.with(|state| {
+            let map = state.get_map();
+            checked_lines
+                .try_update_value(|checked| touched_lines(map, checked))
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|id| {
+                    map.get_line(*id)
+                        .is_some_and(|line| !line.is_continuous(map))
+                })
+                .collect::<Vec<_>>()
+        });
+        if broken.is_empty() {
+            return;
+        }
+
+        match map_state.with_untracked(|state| repair_lines(state.get_map(), &broken)) {
+            Ok(map) => map_state.update(|state| state.amend_last_change(map)),
+            Err(e) => error_state.update(|state| state.set_error(e)),
+        }
+    });
+
     view! {
         <div class=move || format!("cursor-{}", interaction_state.get().get_cursor())>
         {children()}
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Station;
+
+    #[test]
+    fn test_touched_lines() {
+        let mut map = Map::new();
+        let ids: Vec<StationID> = vec![1.into(), 2.into(), 3.into()];
+        for (id, x) in ids
+            .iter()
+            .zip([0, 2, 4])
+        {
+            map.add_station(Station::new((x, 0).into(), Some(*id)));
+        }
+        let red = map.add_line_through((255, 0, 0), &"Red", &ids[..2]);
+        let blue = map.add_line_through((0, 0, 255), &"Blue", &ids[1..]);
+
+        // Every line is new the first time.
+        let mut checked = HashMap::new();
+        let mut touched = touched_lines(&map, &mut checked);
+        touched.sort();
+        assert_eq!(touched, vec![red, blue]);
+        assert!(touched_lines(&map, &mut checked).is_empty());
+
+        // Moving a station leaves the lines as they are.
+        map.get_mut_station(ids[0])
+            .unwrap()
+            .set_pos((0, 2).into());
+        assert!(touched_lines(&map, &mut checked).is_empty());
+
+        map.remove_station(ids[2]);
+        assert_eq!(
+            touched_lines(&map, &mut checked),
+            vec![blue]
+        );
+    }
+}
@@ -35,8 +35,10 @@ pub mod utils;
 mod components;
 pub use components::{
     CanvasState,
+    viewer_map_url,
     Home,
     MapState,
     StateProvider,
+    Viewer,
 };
 pub use utils::Error;
@@ -13,6 +13,13 @@ fn main() {
         return;
     }
 
+    #[cfg(feature = "batch")]
+    if cfg!(feature = "batch") {
+        // Lay out all maps in the given directory
+        utils::batch_export::run_batch_export();
+        return;
+    }
+
     if web_sys::window().is_some() {
         // Initialize the panic hook, which will print any panic that occurs to the
         // console
@@ -25,13 +32,20 @@ fn main() {
     }
 }
 
-/// The App component that is the root for the application as a whole
+/// The App component that is the root for the application as a whole.
+/// Shows the read-only viewer instead of the editor if a map to view is given
+/// in the page url.
 #[component]
 fn App() -> impl IntoView {
+    let map_url = viewer_map_url();
+
     view! {
         <StateProvider>
             <div class="flex flex-col h-screen max-w-screen">
-                <Home/>
+                {match map_url {
+                    Some(map_url) => view! { <Viewer map_url=map_url/> }.into_any(),
+                    None => view! { <Home/> }.into_any(),
+                }}
             </div>
         </StateProvider>
     }
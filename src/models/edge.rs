@@ -5,6 +5,7 @@ use std::fmt::{
     Formatter,
 };
 
+use itertools::Itertools;
 use leptos::logging;
 use serde::{
     Deserialize,
@@ -12,21 +13,34 @@ use serde::{
 };
 
 use super::{
+    map::IDMapping,
     GridNode,
     Line,
     LineID,
     Map,
+    Station,
     StationID,
 };
 use crate::{
     algorithms::{
         calc_label_pos,
         draw_edge,
+        draw_fanned_edge,
+        draw_upright_label,
+        match_grid_angle_cost,
+        pick_free_label_pos,
         run_a_star,
+        scale_dash_pattern,
         CanvasContext,
+        DrawCache,
+        EdgeDirection,
+        GridMode,
     },
     components::CanvasState,
-    utils::IDManager,
+    utils::{
+        calculate_grid_angle,
+        IDManager,
+    },
 };
 
 /// An identifier for an edge.
@@ -53,6 +67,8 @@ impl Display for EdgeID {
 }
 
 /// Represents an edge, which is the connection between two stations.
+// The flags are all independent states of the edge
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Edge {
     /// ID of the edge.
@@ -69,10 +85,21 @@ pub struct Edge {
     is_settled: bool,
     /// If the edge is locked into its current shape by the user.
     is_locked: bool,
+    /// If the nodes of the edge were edited by hand instead of routed by the
+    /// A* or the full algorithm.
+    is_manually_edited: bool,
     /// If the edge is selected by the user.
     is_selected: bool,
     /// The stations contracted into this line in the algorithm.
     contracted_stations: Vec<StationID>,
+    /// If the lines on this edge are drawn bundled into a single stroke.
+    is_bundled: bool,
+    /// The direction the edge is required to leave its from station in, if
+    /// the user set one.
+    from_direction: Option<EdgeDirection>,
+    /// The direction the edge is required to leave its to station in, if the
+    /// user set one.
+    to_direction: Option<EdgeDirection>,
 }
 
 impl Edge {
@@ -86,8 +113,12 @@ impl Edge {
             lines: Vec::new(),
             is_settled: false,
             is_locked: false,
+            is_manually_edited: false,
             is_selected: false,
             contracted_stations: Vec::new(),
+            is_bundled: false,
+            from_direction: None,
+            to_direction: None,
         }
     }
 
@@ -177,6 +208,70 @@ impl Edge {
         self.nodes = nodes;
     }
 
+    /// Get the angles, rounded to a multiple of the angle step of the given
+    /// grid mode, at every node visited between the stations that has a node
+    /// on both sides of it.
+    fn node_angles(&self, mode: GridMode) -> impl Iterator<Item = f64> + '_ {
+        let step = mode.angle_step();
+        self.nodes
+            .windows(3)
+            .map(move |w| (calculate_grid_angle(mode, w[0], w[1], w[2]) / step).round() * step)
+    }
+
+    /// The amount of bends in the nodes visited between the stations, on a
+    /// grid of the given mode.
+    pub fn bend_count(&self, mode: GridMode) -> usize {
+        self.node_angles(mode)
+            .filter(|angle| (angle - 180.0).abs() > f64::EPSILON)
+            .count()
+    }
+
+    /// The sum of the costs of all bends in the nodes visited between the
+    /// stations, on a grid of the given mode.
+    pub fn total_bend_cost(&self, mode: GridMode) -> f64 {
+        self.node_angles(mode)
+            .map(|angle| {
+                match_grid_angle_cost(mode, angle)
+                    .expect("angle is rounded to a multiple of the angle step")
+            })
+            .sum()
+    }
+
+    /// Get all nodes the edge passes through, starting with the position of
+    /// the station it starts at and ending with the position of the station it
+    /// ends at, unlike [`Edge::get_nodes`]. A station that does not exist on
+    /// the map is left out.
+    pub fn full_path(&self, map: &Map) -> Vec<GridNode> {
+        self.full_path_iter(map)
+            .collect()
+    }
+
+    /// Iterate over the nodes of the [`Edge::full_path`] without collecting
+    /// them.
+    fn full_path_iter(&self, map: &Map) -> impl Iterator<Item = GridNode> {
+        map.get_station(self.from)
+            .map(Station::get_pos)
+            .into_iter()
+            .chain(
+                self.nodes
+                    .iter()
+                    .copied(),
+            )
+            .chain(
+                map.get_station(self.to)
+                    .map(Station::get_pos),
+            )
+    }
+
+    /// The length of the route of the edge, from the station it starts at
+    /// through all nodes it visits to the station it ends at.
+    pub fn routed_length(&self, map: &Map) -> f64 {
+        self.full_path_iter(map)
+            .tuple_windows()
+            .map(|(a, b)| f64::from(a.0 - b.0).hypot(f64::from(a.1 - b.1)))
+            .sum()
+    }
+
     /// Get the start and end nodes of the edge.
     pub fn get_edge_ends(&self) -> Vec<GridNode> {
         if self
@@ -229,6 +324,67 @@ impl Edge {
         self.is_locked = false;
     }
 
+    /// A getter for if the nodes of the edge were edited by hand.
+    #[inline]
+    pub fn is_manually_edited(&self) -> bool {
+        self.is_manually_edited
+    }
+
+    /// Mark the nodes of the edge as edited by hand.
+    pub fn mark_manually_edited(&mut self) {
+        self.is_manually_edited = true;
+    }
+
+    /// Mark the nodes of the edge as routed automatically again.
+    pub fn clear_manually_edited(&mut self) {
+        self.is_manually_edited = false;
+    }
+
+    /// A getter for if the lines on the edge are drawn bundled into a single
+    /// stroke.
+    #[inline]
+    pub fn is_bundled(&self) -> bool {
+        self.is_bundled
+    }
+
+    /// Set if the lines on the edge are drawn bundled into a single stroke.
+    pub fn set_bundled(&mut self, bundled: bool) {
+        self.is_bundled = bundled;
+    }
+
+    /// Get the direction the edge is required to leave the given station in,
+    /// if any. Returns `None` if the station is not on the edge.
+    pub fn get_required_direction(&self, station: StationID) -> Option<EdgeDirection> {
+        if station == self.from {
+            self.from_direction
+        } else if station == self.to {
+            self.to_direction
+        } else {
+            None
+        }
+    }
+
+    /// Set the direction the edge is required to leave the given station in,
+    /// or remove the requirement with `None`. Does nothing if the station is
+    /// not on the edge.
+    pub fn set_required_direction(&mut self, station: StationID, direction: Option<EdgeDirection>) {
+        if station == self.from {
+            self.from_direction = direction;
+        } else if station == self.to {
+            self.to_direction = direction;
+        }
+    }
+
+    /// If the edge is required to leave either of its stations in a certain
+    /// direction.
+    pub fn has_required_direction(&self) -> bool {
+        self.from_direction
+            .is_some()
+            || self
+                .to_direction
+                .is_some()
+    }
+
     /// A getter for if the edge is selected.
     #[inline]
     pub fn is_selected(&self) -> bool {
@@ -268,28 +424,34 @@ impl Edge {
             .clear();
     }
 
+    /// Replace the id of the edge and the ids of its stations, lines and
+    /// contracted stations with their new ids in the given mapping.
+    pub(super) fn remap_ids(&mut self, mapping: &IDMapping) {
+        self.id = mapping
+            .edge(self.id)
+            .unwrap_or(self.id);
+        self.from = mapping
+            .station(self.from)
+            .unwrap_or(self.from);
+        self.to = mapping
+            .station(self.to)
+            .unwrap_or(self.to);
+        self.lines = self
+            .lines
+            .iter()
+            .filter_map(|line| mapping.line(*line))
+            .collect();
+        self.contracted_stations = self
+            .contracted_stations
+            .iter()
+            .filter_map(|station| mapping.station(*station))
+            .collect();
+    }
+
     /// Returns if the edge visits the node.
     pub fn visits_node(&self, map: &Map, node: GridNode) -> bool {
-        if self
-            .nodes
-            .contains(&node)
-        {
-            return true;
-        }
-
-        if let Some(from) = map.get_station(self.get_from()) {
-            if from.get_pos() == node {
-                return true;
-            }
-        }
-
-        if let Some(to) = map.get_station(self.get_to()) {
-            if to.get_pos() == node {
-                return true;
-            }
-        }
-
-        false
+        self.full_path_iter(map)
+            .any(|visited| visited == node)
     }
 
     #[allow(dead_code)]
@@ -345,8 +507,15 @@ impl Edge {
         Some((None, None))
     }
 
-    /// Recalculates the nodes between the stations using the A* algorithm.
+    /// Recalculates the nodes between the stations using the A* algorithm on
+    /// the grid the map is routed on.
     pub fn calculate_nodes(&mut self, map: &Map) {
+        self.calculate_nodes_for(map, map.get_grid_mode());
+    }
+
+    /// Recalculates the nodes between the stations using the A* algorithm on
+    /// a grid of the given mode, avoiding unnecessary bends.
+    pub fn calculate_nodes_for(&mut self, map: &Map, mode: GridMode) {
         let from = map
             .get_station(self.get_from())
             .expect("invalid station id");
@@ -354,12 +523,26 @@ impl Edge {
             .get_station(self.get_to())
             .expect("invalid station id");
 
-        self.set_nodes(run_a_star(from.get_pos(), to.get_pos()));
+        self.set_nodes(run_a_star(
+            from.get_pos(),
+            to.get_pos(),
+            mode,
+            true,
+        ));
+        self.clear_manually_edited();
     }
 
-    /// Draw the edge to the given canvas.
+    /// Draw the edge to the given canvas, with the values about the whole map
+    /// it needs taken from the given cache.
     #[allow(clippy::too_many_lines)]
-    pub fn draw(&self, map: &Map, canvas: &CanvasContext<'_>, state: CanvasState, base_alpha: f64) {
+    pub fn draw(
+        &self,
+        map: &Map,
+        canvas: &CanvasContext<'_>,
+        state: CanvasState,
+        base_alpha: f64,
+        cache: &DrawCache,
+    ) {
         let from = map
             .get_station(self.get_from())
             .expect("invalid from station id when drawing");
@@ -392,26 +575,57 @@ impl Edge {
             canvas.stroke();
         }
 
-        let colors = self
+        let lines = self
             .lines
             .iter()
             .filter_map(|l| map.get_line(*l))
-            .map(Line::get_color)
             .collect::<Vec<_>>();
 
-        let mut width = state.drawn_square_size() / 10.0 + 0.5;
-        if width < 1.0 {
-            width = 1.0;
-        }
+        let dash_patterns = state
+            .is_color_blind_mode()
+            .then(|| cache.line_dash_patterns(map));
+
+        let color_count = lines.len();
+        // All offsets below are in the width of a line, so the lines stay next
+        // to each other.
+        let width = Self::line_width(state, color_count);
+        let bundled = self.is_drawn_bundled(map, state);
+        // A bundled edge has its lines as thin stripes next to each other in a
+        // single trunk.
+        let stripe_width = if bundled {
+            self.lines_width(map, state, color_count) / color_count as f64
+        } else {
+            width
+        };
+        let fans_out = (
+            bundled && self.lines_diverge_at(map, state, self.get_from()),
+            bundled && self.lines_diverge_at(map, state, self.get_to()),
+        );
 
-        let color_count = colors.len();
-        for (i, color) in colors
+        for (i, line) in lines
             .into_iter()
             .enumerate()
         {
-            canvas.set_line_width(width);
+            let color = state
+                .get_color_vision()
+                .simulate(line.get_color());
+
+            canvas.set_line_width(stripe_width);
             canvas.set_global_alpha(1.0 * base_alpha);
 
+            if let Some(patterns) = &dash_patterns {
+                let pattern = patterns
+                    .get(&line.get_id())
+                    .copied()
+                    .unwrap_or_default();
+                canvas
+                    .set_line_dash(&scale_dash_pattern(
+                        pattern,
+                        stripe_width,
+                    ))
+                    .unwrap();
+            }
+
             canvas.set_stroke_style_str(&format!(
                 "rgb({} {} {})",
                 color.0, color.1, color.2,
@@ -423,19 +637,62 @@ impl Edge {
             } else {
                 ((i as f64) * width) - ((color_count as f64 * width) / 2.0) + (width / 2.0)
             };
+            // Within a bundle the lines are drawn in the trunk, but where they
+            // diverge at a station they fan out to their usual place.
+            let offsets = if bundled {
+                let stripe_offset = (i as f64) * stripe_width - width + (stripe_width / 2.0);
+                (
+                    if fans_out.0 {
+                        color_offset
+                    } else {
+                        stripe_offset
+                    },
+                    stripe_offset,
+                    if fans_out.1 {
+                        color_offset
+                    } else {
+                        stripe_offset
+                    },
+                )
+            } else {
+                (color_offset, color_offset, color_offset)
+            };
 
-            draw_edge(
+            draw_fanned_edge(
                 from.get_pos(),
                 to.get_pos(),
                 &self.nodes,
                 canvas,
                 state,
-                color_offset,
+                offsets,
             );
 
             canvas.stroke();
         }
 
+        if bundled {
+            self.draw_bundle_badge(map, canvas, state, width);
+        }
+
+        if dash_patterns.is_some() {
+            canvas
+                .set_line_dash(&[])
+                .unwrap();
+            if state.is_labels_shown() {
+                self.draw_line_label(
+                    map,
+                    canvas,
+                    state,
+                    cache,
+                    self.lines_width(map, state, color_count),
+                );
+            }
+        }
+
+        if state.is_stop_ticks_shown() {
+            self.draw_stop_ticks(map, canvas, state, width);
+        }
+
         // Add lock icon if locked
         if self.is_locked() {
             let first_pos = if let Some(first_node) = self
@@ -462,14 +719,21 @@ impl Edge {
                 * width)
                 - ((color_count as f64 * width) / 2.0)
                 + (width / 2.0);
-            let locked_label_pos = calc_label_pos(
-                state,
-                first_pos,
-                Some(second_pos),
-                Some(offset),
-            )[0]; // FIXME: Check for occupancy
+            let locked_label_pos = pick_free_label_pos(
+                cache.label_obstacles(map),
+                &calc_label_pos(
+                    state,
+                    first_pos,
+                    Some(second_pos),
+                    Some(offset),
+                ),
+            );
 
-            canvas.set_stroke_style_str("grey");
+            canvas.set_stroke_style_str(
+                state
+                    .get_theme()
+                    .muted(),
+            );
             canvas.begin_path();
             canvas
                 .arc(
@@ -480,11 +744,388 @@ impl Edge {
                     2.0 * std::f64::consts::PI,
                 )
                 .unwrap();
-            canvas.set_fill_style_str("grey");
+            canvas.set_fill_style_str(
+                state
+                    .get_theme()
+                    .muted(),
+            );
             canvas.fill();
             canvas.stroke();
         }
     }
+
+    /// Draw a halo beneath the lines on the edge, a stroke slightly wider than
+    /// the lines in the halo color of the [`CanvasState`]. This is drawn for
+    /// all edges before any of the lines themselves, so the halos never cover
+    /// the lines of a neighboring edge.
+    pub fn draw_halo(
+        &self,
+        map: &Map,
+        canvas: &CanvasContext<'_>,
+        state: CanvasState,
+        base_alpha: f64,
+    ) {
+        let (Some(from), Some(to)) = (
+            map.get_station(self.get_from()),
+            map.get_station(self.get_to()),
+        ) else {
+            return;
+        };
+        let color_count = self
+            .lines
+            .iter()
+            .filter(|l| {
+                map.get_line(**l)
+                    .is_some()
+            })
+            .count();
+        if color_count == 0 {
+            return;
+        }
+
+        let width = Self::line_width(state, color_count);
+        let lines_width = self.lines_width(map, state, color_count);
+
+        let color = state.get_halo_color();
+        canvas.set_line_width(lines_width + 2.0 * state.get_halo_width() * width);
+        canvas.set_global_alpha(base_alpha);
+        canvas.set_stroke_style_str(&format!(
+            "rgb({} {} {})",
+            color.0, color.1, color.2,
+        ));
+        canvas.begin_path();
+
+        draw_edge(
+            from.get_pos(),
+            to.get_pos(),
+            &self.nodes,
+            canvas,
+            state,
+            0.0,
+        );
+
+        canvas.stroke();
+    }
+
+    /// Get the canvas positions of the stations at the ends of the edge and the
+    /// nodes between them, in order.
+    fn canvas_points(&self, map: &Map, state: CanvasState) -> Vec<(f64, f64)> {
+        if map
+            .get_station(self.get_from())
+            .is_none()
+            || map
+                .get_station(self.get_to())
+                .is_none()
+        {
+            return Vec::new();
+        }
+
+        self.full_path(map)
+            .into_iter()
+            .map(|node| node.to_canvas_pos(state))
+            .collect()
+    }
+
+    /// Get the canvas position the given amount of segments along the given
+    /// points, together with the direction of the segment it lies on.
+    fn point_along(points: &[(f64, f64)], along: f64) -> ((f64, f64), (f64, f64)) {
+        let segment = (along.floor() as usize).min(points.len() - 2);
+        let fraction = along - segment as f64;
+
+        let (start, end) = (points[segment], points[segment + 1]);
+        (
+            (
+                start.0 + (end.0 - start.0) * fraction,
+                start.1 + (end.1 - start.1) * fraction,
+            ),
+            (end.0 - start.0, end.1 - start.1),
+        )
+    }
+
+    /// Get the canvas positions of the stops along the edge, together with the
+    /// direction the edge runs in at that position. These are its end stations
+    /// and the stations contracted into it, which are spread evenly over its
+    /// nodes as their exact location is not known.
+    fn stop_tick_positions(&self, map: &Map, state: CanvasState) -> Vec<((f64, f64), (f64, f64))> {
+        let points = self.canvas_points(map, state);
+        if points.is_empty() {
+            return Vec::new();
+        }
+        let last = points.len() - 1;
+
+        let contracted = self
+            .contracted_stations
+            .len();
+        (0..=contracted + 1)
+            .map(|i| {
+                // How many segments of the edge lie before the stop.
+                let along = (i * last) as f64 / (contracted + 1) as f64;
+                Self::point_along(&points, along)
+            })
+            .collect()
+    }
+
+    /// Get the canvas positions of the stations contracted into the edge,
+    /// spread evenly over its nodes as their exact location is not known.
+    pub fn contracted_station_positions(&self, map: &Map, state: CanvasState) -> Vec<(f64, f64)> {
+        let positions = self.stop_tick_positions(map, state);
+        if positions.len() < 3 {
+            return Vec::new();
+        }
+
+        positions[1..positions.len() - 1]
+            .iter()
+            .map(|(pos, _)| *pos)
+            .collect()
+    }
+
+    /// The width a single line on an edge with the given amount of lines is
+    /// drawn with. Every line gets the same width, which grows with the amount
+    /// of lines on the edge if enabled.
+    fn line_width(state: CanvasState, line_count: usize) -> f64 {
+        let width = (state.drawn_square_size() / 10.0 + 0.5).max(1.0);
+        state.scaled_line_width(width, line_count)
+    }
+
+    /// The width of all the given amount of lines on the edge together, which
+    /// is the width of the trunk, twice that of a single line, if they are
+    /// drawn bundled.
+    fn lines_width(&self, map: &Map, state: CanvasState, line_count: usize) -> f64 {
+        let width = Self::line_width(state, line_count);
+        if self.is_drawn_bundled(map, state) {
+            width * 2.0
+        } else {
+            width * line_count as f64
+        }
+    }
+
+    /// Check if the lines on the edge are drawn bundled into a single trunk,
+    /// either because the edge is bundled or all edges are drawn bundled.
+    fn is_drawn_bundled(&self, map: &Map, state: CanvasState) -> bool {
+        let line_count = self
+            .lines
+            .iter()
+            .filter(|l| {
+                map.get_line(**l)
+                    .is_some()
+            })
+            .count();
+
+        line_count > 1 && (self.is_bundled || state.is_bundling_lines())
+    }
+
+    /// Check if the lines on the edge diverge at the given station, meaning
+    /// another edge there carries some of its lines without being drawn
+    /// bundled with exactly the same lines.
+    fn lines_diverge_at(&self, map: &Map, state: CanvasState, station: StationID) -> bool {
+        let Some(station) = map.get_station(station) else {
+            return false;
+        };
+
+        station
+            .get_edges()
+            .iter()
+            .filter(|id| **id != self.id)
+            .filter_map(|id| map.get_edge(*id))
+            .filter(|other| {
+                other
+                    .lines
+                    .iter()
+                    .any(|l| {
+                        self.lines
+                            .contains(l)
+                    })
+            })
+            .any(|other| {
+                !other.is_drawn_bundled(map, state)
+                    || other
+                        .lines
+                        .len()
+                        != self
+                            .lines
+                            .len()
+                    || other
+                        .lines
+                        .iter()
+                        .any(|l| {
+                            !self
+                                .lines
+                                .contains(l)
+                        })
+            })
+    }
+
+    /// Draw a badge in the middle of a bundled edge showing the amount of
+    /// lines in the bundle.
+    fn draw_bundle_badge(
+        &self,
+        map: &Map,
+        canvas: &CanvasContext<'_>,
+        state: CanvasState,
+        width: f64,
+    ) {
+        let theme = state.get_theme();
+
+        canvas
+            .set_line_dash(&[])
+            .unwrap();
+        let points = self.canvas_points(map, state);
+        let ((x, y), _) = Self::point_along(&points, (points.len() - 1) as f64 / 2.0);
+        let radius = (state.drawn_square_size() / 4.0).max(4.0);
+
+        canvas.set_line_width(width);
+        canvas.set_fill_style_str(theme.background());
+        canvas.set_stroke_style_str(theme.foreground());
+        canvas.begin_path();
+        canvas
+            .arc(
+                x,
+                y,
+                radius,
+                0.0,
+                2.0 * std::f64::consts::PI,
+            )
+            .unwrap();
+        canvas.fill();
+        canvas.stroke();
+
+        canvas.set_font(&format!(
+            "{}px sans-serif",
+            (radius * 1.2).round()
+        ));
+        canvas.set_fill_style_str(theme.foreground());
+        canvas
+            .fill_text(
+                &self
+                    .lines
+                    .len()
+                    .to_string(),
+                x - radius / 3.0,
+                y + radius / 3.0,
+            )
+            .unwrap();
+    }
+
+    /// Draw a short tick perpendicular to the edge at every stop along it, to
+    /// hint at the stops without drawing a full station.
+    fn draw_stop_ticks(
+        &self,
+        map: &Map,
+        canvas: &CanvasContext<'_>,
+        state: CanvasState,
+        width: f64,
+    ) {
+        let color = self
+            .lines
+            .iter()
+            .find_map(|l| map.get_line(*l))
+            .map_or_else(
+                || {
+                    state
+                        .get_theme()
+                        .foreground()
+                        .to_owned()
+                },
+                |line| {
+                    let color = state
+                        .get_color_vision()
+                        .simulate(line.get_color());
+                    format!(
+                        "rgb({} {} {})",
+                        color.0, color.1, color.2
+                    )
+                },
+            );
+        let length = state.drawn_square_size() / 3.0;
+
+        canvas
+            .set_line_dash(&[])
+            .unwrap();
+        canvas.set_line_width(width);
+        canvas.set_stroke_style_str(&color);
+        canvas.begin_path();
+        for ((x, y), (dx, dy)) in self.stop_tick_positions(map, state) {
+            let distance = dx.hypot(dy);
+            if distance == 0.0 {
+                continue;
+            }
+
+            canvas.move_to(x, y);
+            canvas.line_to(
+                x - dy / distance * length,
+                y + dx / distance * length,
+            );
+        }
+        canvas.stroke();
+    }
+
+    /// Draw the names of the lines on the edge next to its middle node, so
+    /// lines can be told apart without relying on their color.
+    fn draw_line_label(
+        &self,
+        map: &Map,
+        canvas: &CanvasContext<'_>,
+        state: CanvasState,
+        cache: &DrawCache,
+        offset: f64,
+    ) {
+        let label = self
+            .lines
+            .iter()
+            .filter_map(|l| map.get_line(*l))
+            .map(Line::get_name)
+            .filter(|name| !name.is_empty())
+            .join(" / ");
+        if label.is_empty() {
+            return;
+        }
+
+        let middle = self
+            .nodes
+            .len()
+            / 2;
+        let Some(node) = self
+            .nodes
+            .get(middle)
+        else {
+            return;
+        };
+        let next_pos = self
+            .nodes
+            .get(middle + 1)
+            .map_or_else(
+                || {
+                    map.get_station(self.get_to())
+                        .expect("invalid to station id when drawing")
+                        .get_canvas_pos(state)
+                },
+                |next| next.to_canvas_pos(state),
+            );
+
+        let label_pos = pick_free_label_pos(
+            cache.label_obstacles(map),
+            &calc_label_pos(
+                state,
+                node.to_canvas_pos(state),
+                Some(next_pos),
+                Some(offset / 2.0),
+            ),
+        );
+
+        canvas.set_font(&state.label_font_css());
+        canvas.set_fill_style_str(
+            state
+                .get_theme()
+                .foreground(),
+        );
+        draw_upright_label(
+            canvas,
+            &label,
+            label_pos,
+            node.to_canvas_pos(state),
+            next_pos,
+        );
+    }
 }
 
 impl PartialEq for Edge {
@@ -498,6 +1139,80 @@ mod tests {
     use super::*;
     use crate::models::Station;
 
+    #[test]
+    fn test_bend_count() {
+        let mut edge = Edge::new(1.into(), 2.into(), None);
+
+        edge.set_nodes(vec![
+            GridNode::from((0, 0)),
+            GridNode::from((1, 0)),
+            GridNode::from((2, 0)),
+            GridNode::from((3, 0)),
+        ]);
+        assert_eq!(edge.bend_count(GridMode::Octilinear), 0);
+        assert_eq!(
+            edge.total_bend_cost(GridMode::Octilinear),
+            0.0
+        );
+
+        edge.set_nodes(vec![
+            GridNode::from((0, 0)),
+            GridNode::from((1, 0)),
+            GridNode::from((1, 1)),
+            GridNode::from((1, 2)),
+        ]);
+        assert_eq!(edge.bend_count(GridMode::Octilinear), 1);
+        assert_eq!(
+            edge.total_bend_cost(GridMode::Octilinear),
+            2.5
+        );
+
+        edge.set_nodes(vec![
+            GridNode::from((0, 0)),
+            GridNode::from((1, 0)),
+            GridNode::from((2, 1)),
+        ]);
+        assert_eq!(edge.bend_count(GridMode::Octilinear), 1);
+        assert_eq!(
+            edge.total_bend_cost(GridMode::Octilinear),
+            0.5
+        );
+
+        // Straight on the hexagonal grid, as the diagonal is one of its axes.
+        edge.set_nodes(vec![
+            GridNode::from((0, 0)),
+            GridNode::from((1, -1)),
+            GridNode::from((2, -2)),
+        ]);
+        assert_eq!(edge.bend_count(GridMode::Hexagonal), 0);
+        assert_eq!(
+            edge.total_bend_cost(GridMode::Hexagonal),
+            0.0
+        );
+
+        edge.set_nodes(vec![
+            GridNode::from((0, 0)),
+            GridNode::from((1, 0)),
+            GridNode::from((1, 1)),
+        ]);
+        assert_eq!(edge.bend_count(GridMode::Hexagonal), 1);
+        assert_eq!(
+            edge.total_bend_cost(GridMode::Hexagonal),
+            1.0
+        );
+
+        edge.set_nodes(vec![
+            GridNode::from((0, 0)),
+            GridNode::from((1, 0)),
+            GridNode::from((1, -1)),
+        ]);
+        assert_eq!(edge.bend_count(GridMode::Hexagonal), 1);
+        assert_eq!(
+            edge.total_bend_cost(GridMode::Hexagonal),
+            5.0
+        );
+    }
+
     #[test]
     fn test_draw() {
         let mut map = Map::new();
@@ -524,7 +1239,13 @@ mod tests {
         map.add_line(line2);
 
         edge.calculate_nodes(&map);
-        edge.draw(&map, &canvas, state, 1.0);
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
 
         assert_eq!(
             canvas.get_record("move_to"),
@@ -546,4 +1267,394 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_draw_color_blind_mode() {
+        let mut map = Map::new();
+        let canvas = CanvasContext::new();
+        let mut state = CanvasState::new();
+        state.set_square_size(5);
+        state.set_size((100.0, 100.0));
+
+        let mut line1 = Line::new(None);
+        line1.set_name(&"Red");
+        let mut line2 = Line::new(None);
+        line2.set_name(&"Green");
+
+        let from = Station::new((0, 0).into(), None);
+        let to = Station::new((3, 3).into(), None);
+        let mut edge = Edge::new(from.get_id(), to.get_id(), None);
+
+        map.add_station(from);
+        map.add_station(to);
+
+        edge.set_lines(vec![line1.get_id(), line2.get_id()]);
+        map.add_line(line1);
+        map.add_line(line2);
+        edge.calculate_nodes(&map);
+
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+        assert_eq!(canvas.get_record("fill_text"), None);
+
+        state.set_color_blind_mode(true);
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+        assert_eq!(
+            canvas.get_record("fill_text"),
+            Some(vec!["Red / Green,10.0,7.8".to_owned()])
+        );
+
+        // Hidden labels are not drawn, even in color-blind mode.
+        let canvas = CanvasContext::new();
+        state.set_labels_shown(false);
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+        assert_eq!(canvas.get_record("fill_text"), None);
+    }
+
+    #[test]
+    fn test_draw_scaled_line_width() {
+        let mut map = Map::new();
+        let mut state = CanvasState::new();
+        state.set_square_size(5);
+        state.set_size((100.0, 100.0));
+
+        let from = map.add_named_station(&"From", (0, 0).into());
+        let to = map.add_named_station(&"To", (4, 0).into());
+        map.add_line_through((255, 0, 0), &"Red", &[from, to]);
+        map.add_line_through((0, 0, 255), &"Blue", &[from, to]);
+        let edge = map
+            .get_edge(
+                map.get_edge_id_between_if_exists(from, to)
+                    .unwrap(),
+            )
+            .unwrap()
+            .clone();
+
+        // By default every line has the same width, no matter how many share
+        // the edge.
+        let canvas = CanvasContext::new();
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+        assert_eq!(
+            canvas.get_record("set_line_width"),
+            Some(vec!["1".to_owned(), "1".to_owned()])
+        );
+
+        let canvas = CanvasContext::new();
+        state.set_line_width_scaling(0.5);
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+        assert_eq!(
+            canvas.get_record("set_line_width"),
+            Some(vec!["1.5".to_owned(), "1.5".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_draw_bundled() {
+        let mut map = Map::new();
+        let canvas = CanvasContext::new();
+        let mut state = CanvasState::new();
+        state.set_square_size(5);
+        state.set_size((100.0, 100.0));
+
+        let from = map.add_named_station(&"From", (0, 0).into());
+        let to = map.add_named_station(&"To", (4, 0).into());
+        map.add_line_through((255, 0, 0), &"Red", &[from, to]);
+        map.add_line_through((0, 0, 255), &"Blue", &[from, to]);
+        let edge = map
+            .get_edge(
+                map.get_edge_id_between_if_exists(from, to)
+                    .unwrap(),
+            )
+            .unwrap()
+            .clone();
+
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+        assert_eq!(canvas.get_record("fill_text"), None);
+        assert_eq!(
+            canvas
+                .get_record("move_to")
+                .map(|r| r.len()),
+            Some(2)
+        );
+
+        // Bundled, the lines are drawn as stripes next to each other together
+        // with the badge.
+        let canvas = CanvasContext::new();
+        state.set_bundling_lines(true);
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+        assert_eq!(
+            canvas
+                .get_record("move_to")
+                .map(|r| r.len()),
+            Some(2)
+        );
+        assert_eq!(
+            canvas
+                .get_record("arc")
+                .map(|r| r.len()),
+            Some(1)
+        );
+        assert!(
+            canvas
+                .get_record("fill_text")
+                .unwrap()[0]
+                .starts_with("2,")
+        );
+    }
+
+    #[test]
+    fn test_draw_bundled_fans_out() {
+        let mut map = Map::new();
+        let mut state = CanvasState::new();
+        state.set_square_size(5);
+        state.set_size((100.0, 100.0));
+        state.set_bundling_lines(true);
+
+        let from = map.add_named_station(&"From", (0, 0).into());
+        let to = map.add_named_station(&"To", (4, 0).into());
+        let next = map.add_named_station(&"Next", (8, 0).into());
+        map.add_line_through((255, 0, 0), &"Red", &[from, to, next]);
+        map.add_line_through((0, 255, 0), &"Green", &[from, to]);
+        map.add_line_through((0, 0, 255), &"Blue", &[from, to]);
+        let edge = map
+            .get_edge(
+                map.get_edge_id_between_if_exists(from, to)
+                    .unwrap(),
+            )
+            .unwrap()
+            .clone();
+
+        let canvas = CanvasContext::new();
+        edge.draw(
+            &map,
+            &canvas,
+            state,
+            1.0,
+            &DrawCache::new(state),
+        );
+
+        // The stripes share a trunk twice the width of a single line.
+        let stripe_width = 2.0 / 3.0;
+        assert!(
+            canvas
+                .get_record("set_line_width")
+                .unwrap()
+                .contains(&format!("{stripe_width}"))
+        );
+
+        // The lines leave the from station in the trunk, but fan out to their
+        // usual distance at the to station, where red continues on its own.
+        let spread = |record: &str| {
+            let ys = canvas
+                .get_record(record)
+                .unwrap()
+                .iter()
+                .map(|r| {
+                    r.split(',')
+                        .nth(1)
+                        .unwrap()
+                        .parse::<f64>()
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            ys.iter()
+                .copied()
+                .fold(f64::MIN, f64::max)
+                - ys.iter()
+                    .copied()
+                    .fold(f64::MAX, f64::min)
+        };
+        assert!((spread("move_to") - 2.0 * stripe_width).abs() < 0.1);
+        assert!((spread("line_to") - 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_stop_tick_positions() {
+        let mut map = Map::new();
+        let mut state = CanvasState::new();
+        state.set_square_size(5);
+        state.set_size((100.0, 100.0));
+
+        let from = Station::new((0, 0).into(), None);
+        let to = Station::new((4, 0).into(), None);
+        let mut edge = Edge::new(from.get_id(), to.get_id(), None);
+        edge.set_nodes(vec![
+            (1, 0).into(),
+            (2, 0).into(),
+            (3, 0).into(),
+        ]);
+        let from_pos = from.get_canvas_pos(state);
+        let to_pos = to.get_canvas_pos(state);
+        map.add_station(from);
+        map.add_station(to);
+
+        let positions = edge.stop_tick_positions(&map, state);
+        assert_eq!(
+            positions
+                .iter()
+                .map(|(pos, _)| *pos)
+                .collect::<Vec<_>>(),
+            vec![from_pos, to_pos]
+        );
+
+        // A contracted station is placed halfway the edge, with the direction
+        // of the edge at that point.
+        assert!(
+            edge.contracted_station_positions(&map, state)
+                .is_empty()
+        );
+        edge.add_contracted_station(StationID::from(99));
+        let positions = edge.stop_tick_positions(&map, state);
+        assert_eq!(positions.len(), 3);
+        assert_eq!(
+            edge.contracted_station_positions(&map, state),
+            vec![positions[1].0]
+        );
+        assert_eq!(
+            positions[1].0,
+            (
+                (from_pos.0 + to_pos.0) / 2.0,
+                from_pos.1
+            )
+        );
+        assert!(
+            positions[1]
+                .1
+                .0
+                > 0.0
+        );
+        assert_eq!(
+            positions[1]
+                .1
+                .1,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_draw_halo() {
+        let mut map = Map::new();
+        let mut state = CanvasState::new();
+        state.set_square_size(5);
+        state.set_size((100.0, 100.0));
+
+        let from = map.add_named_station(&"From", (0, 0).into());
+        let to = map.add_named_station(&"To", (4, 0).into());
+        map.add_line_through((255, 0, 0), &"Red", &[from, to]);
+        map.add_line_through((0, 0, 255), &"Blue", &[from, to]);
+        let edge = map
+            .get_edge(
+                map.get_edge_id_between_if_exists(from, to)
+                    .unwrap(),
+            )
+            .unwrap()
+            .clone();
+
+        // The halo covers both lines and extends beyond them on either side.
+        let canvas = CanvasContext::new();
+        edge.draw_halo(&map, &canvas, state, 1.0);
+        assert_eq!(
+            canvas.get_record("set_line_width"),
+            Some(vec!["3.5".to_owned()])
+        );
+        assert_eq!(
+            canvas
+                .get_record("move_to")
+                .map(|r| r.len()),
+            Some(1)
+        );
+
+        let canvas = CanvasContext::new();
+        state.set_halo_width(0.0);
+        state.set_bundling_lines(true);
+        edge.draw_halo(&map, &canvas, state, 1.0);
+        assert_eq!(
+            canvas.get_record("set_line_width"),
+            Some(vec!["2".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_full_path() {
+        let mut map = Map::new();
+        let from = map.add_named_station(&"From", (0, 0).into());
+        let to = map.add_named_station(&"To", (3, 3).into());
+        map.add_line_through((255, 0, 0), &"Red", &[from, to]);
+        let edge_id = map
+            .get_edge_id_between_if_exists(from, to)
+            .unwrap();
+        let edge = map
+            .get_mut_edge(edge_id)
+            .unwrap();
+        edge.set_nodes(vec![(1, 1).into(), (2, 2).into()]);
+        let edge = edge.clone();
+
+        let path = edge.full_path(&map);
+        assert_eq!(
+            path,
+            vec![
+                GridNode::from((0, 0)),
+                GridNode::from((1, 1)),
+                GridNode::from((2, 2)),
+                GridNode::from((3, 3)),
+            ]
+        );
+        assert_eq!(
+            path.first(),
+            Some(
+                &map.get_station(edge.get_from())
+                    .unwrap()
+                    .get_pos()
+            )
+        );
+        assert_eq!(
+            path.last(),
+            Some(
+                &map.get_station(edge.get_to())
+                    .unwrap()
+                    .get_pos()
+            )
+        );
+    }
 }
@@ -14,7 +14,10 @@ use serde::{
     Serialize,
 };
 
-use crate::components::CanvasState;
+use crate::{
+    algorithms::GridMode,
+    components::CanvasState,
+};
 
 /// Represents a node on the grid.
 #[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
@@ -86,6 +89,15 @@ impl GridNode {
         dx + dy
     }
 
+    /// Get the Chebyshev distance to a target node, which is the amount of
+    /// steps needed if diagonal steps are allowed.
+    pub fn chebyshev_distance_to(self, target: GridNode) -> i32 {
+        let dx = (self.0 - target.0).abs();
+        let dy = (self.1 - target.1).abs();
+
+        dx.max(dy)
+    }
+
     /// Get a list of all the neighbors of this grid node.
     pub fn get_neighbors(self) -> Vec<GridNode> {
         vec![
@@ -100,10 +112,48 @@ impl GridNode {
         ]
     }
 
+    /// Get a list of all the neighbors of this grid node on a grid of the
+    /// given mode.
+    pub fn get_neighbors_for(self, mode: GridMode) -> Vec<GridNode> {
+        match mode {
+            GridMode::Octilinear => self.get_neighbors(),
+            GridMode::Hexagonal => {
+                vec![
+                    Self(self.0, self.1 - 1),
+                    Self(self.0 + 1, self.1 - 1),
+                    Self(self.0 + 1, self.1),
+                    Self(self.0, self.1 + 1),
+                    Self(self.0 - 1, self.1 + 1),
+                    Self(self.0 - 1, self.1),
+                ]
+            },
+        }
+    }
+
+    /// Get the distance to a target node on a hexagonal grid, which is the
+    /// amount of steps needed to reach it.
+    pub fn hex_distance_to(self, target: GridNode) -> i32 {
+        let dx = self.0 - target.0;
+        let dy = self.1 - target.1;
+
+        (dx.abs() + dy.abs() + (dx + dy).abs()) / 2
+    }
+
     /// Check if this node is a neighbor of another node.
     pub fn is_neighbor_of(&self, other: &GridNode) -> bool {
         (self.0 - other.0).abs() <= 1 && (self.1 - other.1).abs() <= 1
     }
+
+    /// Get the node at the given progress (between 0.0 and 1.0) on the way
+    /// from this node to the target node, rounded to the nearest node.
+    pub fn interpolate(self, target: GridNode, progress: f64) -> GridNode {
+        let progress = progress.clamp(0.0, 1.0);
+
+        Self(
+            (f64::from(self.0) + f64::from(target.0 - self.0) * progress).round() as i32,
+            (f64::from(self.1) + f64::from(target.1 - self.1) * progress).round() as i32,
+        )
+    }
 }
 
 impl From<(i32, i32)> for GridNode {
@@ -234,6 +284,26 @@ mod tests {
         assert_eq!(dist, 8);
     }
 
+    #[test]
+    fn test_interpolate() {
+        let from = GridNode::from((0, 10));
+        let to = GridNode::from((6, 4));
+
+        assert_eq!(from.interpolate(to, 0.0), (0, 10));
+        assert_eq!(from.interpolate(to, 0.5), (3, 7));
+        assert_eq!(from.interpolate(to, 1.0), (6, 4));
+        assert_eq!(from.interpolate(to, 2.0), (6, 4));
+    }
+
+    #[test]
+    fn test_chebyshev_distance() {
+        let dist = GridNode::from((4, 5)).chebyshev_distance_to(GridNode::from((10, 7)));
+        assert_eq!(dist, 6);
+
+        let dist = GridNode::from((10, 5)).chebyshev_distance_to(GridNode::from((4, 7)));
+        assert_eq!(dist, 6);
+    }
+
     #[test]
     fn test_get_neighbors() {
         let neighbors = GridNode::from((4, 5)).get_neighbors();
@@ -252,4 +322,38 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_get_hexagonal_neighbors() {
+        let node = GridNode::from((4, 5));
+        let neighbors = node.get_neighbors_for(GridMode::Hexagonal);
+
+        assert_eq!(
+            neighbors,
+            vec![
+                (4, 4),
+                (5, 4),
+                (5, 5),
+                (4, 6),
+                (3, 6),
+                (3, 5)
+            ]
+        );
+        for neighbor in neighbors {
+            assert_eq!(node.hex_distance_to(neighbor), 1);
+        }
+
+        assert_eq!(
+            node.get_neighbors_for(GridMode::Octilinear),
+            node.get_neighbors()
+        );
+        assert_eq!(
+            node.hex_distance_to(GridNode::from((6, 6))),
+            3
+        );
+        assert_eq!(
+            node.hex_distance_to(GridNode::from((6, 3))),
+            2
+        );
+    }
 }
@@ -0,0 +1,114 @@
+//! Contains the [`Interchange`] struct and all its methods.
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    Map,
+    StationID,
+};
+use crate::{
+    algorithms::CanvasContext,
+    components::CanvasState,
+};
+
+/// An out-of-station interchange between two nearby stations, drawn as a
+/// connector between them. It is not part of any line and is ignored when
+/// routing edges.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interchange {
+    /// The station with the lowest id of the two.
+    first: StationID,
+    /// The station with the highest id of the two.
+    second: StationID,
+}
+
+impl Interchange {
+    /// Create a new [`Interchange`] between the given stations. The order of
+    /// the stations does not matter.
+    pub fn new(a: StationID, b: StationID) -> Self {
+        Self {
+            first: a.min(b),
+            second: a.max(b),
+        }
+    }
+
+    /// A getter method for the stations connected by the interchange.
+    pub fn get_stations(&self) -> (StationID, StationID) {
+        (self.first, self.second)
+    }
+
+    /// Check if the interchange connects to the given station.
+    pub fn contains(&self, station: StationID) -> bool {
+        self.first == station || self.second == station
+    }
+
+    /// Draw the interchange as a thick gray connector between its stations.
+    pub fn draw(&self, map: &Map, canvas: &CanvasContext<'_>, state: CanvasState, base_alpha: f64) {
+        let (Some(first), Some(second)) = (
+            map.get_station(self.first),
+            map.get_station(self.second),
+        ) else {
+            return;
+        };
+
+        let from = first.get_canvas_pos(state);
+        let to = second.get_canvas_pos(state);
+
+        let mut width = state.drawn_square_size() / 2.5;
+        if width < 3.0 {
+            width = 3.0;
+        }
+
+        canvas
+            .set_line_dash(&[])
+            .unwrap();
+        canvas.set_line_width(width);
+        canvas.set_global_alpha(0.8 * base_alpha);
+        canvas.set_stroke_style_str(
+            state
+                .get_theme()
+                .muted(),
+        );
+
+        canvas.begin_path();
+        canvas.move_to(from.0, from.1);
+        canvas.line_to(to.0, to.1);
+        canvas.stroke();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Station;
+
+    #[test]
+    fn test_draw_interchange() {
+        let mut state = CanvasState::new();
+        state.set_square_size(10);
+
+        let mut map = Map::new();
+        let a: StationID = 1.into();
+        let b: StationID = 2.into();
+        map.add_station(Station::new((1, 1).into(), Some(a)));
+        map.add_station(Station::new((2, 2).into(), Some(b)));
+
+        let interchange = Interchange::new(b, a);
+        assert_eq!(interchange.get_stations(), (a, b));
+
+        let canvas = CanvasContext::new();
+        interchange.draw(&map, &canvas, state, 1.0);
+
+        assert_eq!(
+            canvas.get_record("move_to"),
+            Some(vec!["10.0,10.0".to_string()])
+        );
+        assert_eq!(
+            canvas.get_record("line_to"),
+            Some(vec!["20.0,20.0".to_string()])
+        );
+    }
+}
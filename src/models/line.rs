@@ -1,5 +1,9 @@
 //! Contains the [`Line`] struct and all its methods.
 use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
     f64::consts::PI,
     fmt::Display,
 };
@@ -11,6 +15,7 @@ use serde::{
 };
 
 use super::{
+    map::IDMapping,
     station::StationID,
     EdgeID,
     GridNode,
@@ -20,9 +25,26 @@ use super::{
 use crate::{
     algorithms::CanvasContext,
     components::CanvasState,
-    utils::IDManager,
+    utils::{
+        IDManager,
+        Result,
+    },
+    Error,
 };
 
+/// The built-in colors for lines, suggested in turn for new lines and cycled
+/// through to quickly recolor existing ones.
+pub const LINE_PALETTE: [(u8, u8, u8); 8] = [
+    (227, 6, 19),
+    (0, 101, 189),
+    (0, 150, 64),
+    (255, 204, 0),
+    (128, 0, 128),
+    (243, 146, 0),
+    (0, 169, 224),
+    (145, 86, 36),
+];
+
 /// An identifier for a line.
 #[derive(Clone, Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -46,6 +68,57 @@ impl Display for LineID {
     }
 }
 
+/// Statistics of a single line, used to evaluate the network at a glance.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LineStats {
+    /// The amount of stations the line visits, excluding checkpoints.
+    pub station_count: usize,
+    /// The combined length of the routes of all edges of the line.
+    pub length: f64,
+    /// The amount of bends in the edges of the line.
+    pub bend_count: usize,
+    /// The amount of stations of the line that are also visited by another
+    /// line.
+    pub interchange_count: usize,
+}
+
+/// A service pattern of a line, like a limited-stop service, given by the
+/// subset of the stations of the line it stops at.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoutePattern {
+    /// Name of the pattern.
+    name: String,
+    /// The stations the pattern stops at.
+    stations: Vec<StationID>,
+}
+
+impl RoutePattern {
+    /// Create a new [`RoutePattern`] with the given name that stops at the
+    /// given stations.
+    pub fn new(name: &impl ToString, stations: Vec<StationID>) -> Self {
+        Self {
+            name: name.to_string(),
+            stations,
+        }
+    }
+
+    /// A getter for the name of the pattern.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// A getter for the stations the pattern stops at.
+    pub fn get_stations(&self) -> &[StationID] {
+        &self.stations
+    }
+
+    /// Check if the pattern stops at the given station.
+    pub fn stops_at(&self, station: StationID) -> bool {
+        self.stations
+            .contains(&station)
+    }
+}
+
 /// Represents a metro line, including its stations, name and color.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Line {
@@ -59,6 +132,8 @@ pub struct Line {
     stations: Vec<StationID>,
     /// All edges between the stations.
     edges: Vec<EdgeID>,
+    /// The service patterns of the line, if it has any.
+    patterns: Vec<RoutePattern>,
 }
 
 impl Line {
@@ -75,6 +150,33 @@ impl Line {
             id: id.unwrap_or_else(IDManager::next_line_id),
             color: (0, 0, 0),
             name: String::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Replace the id of the line and the ids of its stations, edges and the
+    /// stations of its service patterns with their new ids in the given
+    /// mapping.
+    pub(super) fn remap_ids(&mut self, mapping: &IDMapping) {
+        self.id = mapping
+            .line(self.id)
+            .unwrap_or(self.id);
+        self.stations = self
+            .stations
+            .iter()
+            .filter_map(|station| mapping.station(*station))
+            .collect();
+        self.edges = self
+            .edges
+            .iter()
+            .filter_map(|edge| mapping.edge(*edge))
+            .collect();
+        for pattern in &mut self.patterns {
+            pattern.stations = pattern
+                .stations
+                .iter()
+                .filter_map(|station| mapping.station(*station))
+                .collect();
         }
     }
 
@@ -123,11 +225,11 @@ impl Line {
                 map.removed_edge(edge_id, self.get_id());
 
                 self.add_edge(
-                    map.get_edge_id_between(station, before_station),
+                    map.get_line_edge_id_between(station, before_station, self.id),
                     map,
                 );
                 self.add_edge(
-                    map.get_edge_id_between(after_station, station),
+                    map.get_line_edge_id_between(after_station, station, self.id),
                     map,
                 );
                 return;
@@ -138,7 +240,7 @@ impl Line {
         if let Some(after_station) = after {
             // Insert edge between station and the station it comes before
             self.add_edge(
-                map.get_edge_id_between(station, after_station),
+                map.get_line_edge_id_between(station, after_station, self.id),
                 map,
             );
             return;
@@ -147,7 +249,7 @@ impl Line {
         if let Some(before_station) = before {
             // Insert edge between station and the station it comes after
             self.add_edge(
-                map.get_edge_id_between(before_station, station),
+                map.get_line_edge_id_between(before_station, station, self.id),
                 map,
             );
         }
@@ -163,6 +265,7 @@ impl Line {
         {
             self.stations
                 .remove(index);
+            self.remove_station_from_patterns(station_id);
         } else {
             return;
         }
@@ -203,7 +306,11 @@ impl Line {
             .into_iter()
             .combinations(2)
         {
-            let edge_id = map.get_edge_id_between(combinations[0].0, combinations[1].0);
+            let edge_id = map.get_line_edge_id_between(
+                combinations[0].0,
+                combinations[1].0,
+                self.id,
+            );
             {
                 let mut nodes = Vec::new();
 
@@ -265,6 +372,7 @@ impl Line {
     pub fn remove_station_obj(&mut self, station: &Station) {
         self.stations
             .retain(|s| s != &station.get_id());
+        self.remove_station_from_patterns(station.get_id());
         self.edges
             .retain(|e| {
                 station
@@ -316,6 +424,16 @@ impl Line {
         self.color
     }
 
+    /// Change the color of the line to the one after its current color in the
+    /// [`LINE_PALETTE`], or to the first one if its color is not in it.
+    pub fn cycle_color(&mut self) {
+        let next = LINE_PALETTE
+            .iter()
+            .position(|color| *color == self.color)
+            .map_or(0, |i| (i + 1) % LINE_PALETTE.len());
+        self.color = LINE_PALETTE[next];
+    }
+
     /// A setter for the station's name.
     #[inline]
     pub fn set_name(&mut self, name: &impl ToString) {
@@ -333,11 +451,396 @@ impl Line {
         self.id
     }
 
+    /// A getter for the service patterns of the line.
+    pub fn get_patterns(&self) -> &[RoutePattern] {
+        &self.patterns
+    }
+
+    /// A setter for the service patterns of the line.
+    pub fn set_patterns(&mut self, patterns: Vec<RoutePattern>) {
+        self.patterns = patterns;
+    }
+
+    /// Remove the given station from all service patterns of the line.
+    fn remove_station_from_patterns(&mut self, station: StationID) {
+        for pattern in &mut self.patterns {
+            pattern
+                .stations
+                .retain(|s| *s != station);
+        }
+    }
+
     /// A getter for the edges the line uses.
     pub fn get_edges(&self) -> &[EdgeID] {
         &self.edges
     }
 
+    /// Split the stations of the line into groups that are connected to each
+    /// other by the edges of the line. Edges that no longer exist on the map
+    /// are ignored.
+    fn connected_groups(&self, map: &Map) -> Vec<Vec<StationID>> {
+        let mut neighbors: HashMap<StationID, Vec<StationID>> = HashMap::new();
+        for edge in self
+            .edges
+            .iter()
+            .filter_map(|id| map.get_edge(*id))
+        {
+            neighbors
+                .entry(edge.get_from())
+                .or_default()
+                .push(edge.get_to());
+            neighbors
+                .entry(edge.get_to())
+                .or_default()
+                .push(edge.get_from());
+        }
+
+        let mut seen = HashSet::new();
+        let mut groups = Vec::new();
+        for start in &self.stations {
+            if !seen.insert(*start) {
+                continue;
+            }
+
+            let mut group = vec![*start];
+            let mut index = 0;
+            while let Some(station) = group
+                .get(index)
+                .copied()
+            {
+                for neighbor in neighbors
+                    .get(&station)
+                    .into_iter()
+                    .flatten()
+                {
+                    if seen.insert(*neighbor) {
+                        group.push(*neighbor);
+                    }
+                }
+                index += 1;
+            }
+            groups.push(group);
+        }
+
+        groups
+    }
+
+    /// Check if the line forms a single connected piece on the given map. This
+    /// means all its edges and stations exist, every edge connects stations of
+    /// the line and there is no gap between any of its stations.
+    pub fn is_continuous(&self, map: &Map) -> bool {
+        let edges_valid = self
+            .edges
+            .iter()
+            .all(|id| {
+                map.get_edge(*id)
+                    .is_some_and(|edge| {
+                        self.stations
+                            .contains(&edge.get_from())
+                            && self
+                                .stations
+                                .contains(&edge.get_to())
+                    })
+            });
+        let stations_valid = self
+            .stations
+            .iter()
+            .all(|id| {
+                map.get_station(*id)
+                    .is_some()
+            });
+
+        edges_valid
+            && stations_valid
+            && self
+                .connected_groups(map)
+                .len()
+                <= 1
+    }
+
+    /// Try to turn the line back into a single connected piece after it was
+    /// left inconsistent by an edit. Edges and stations that no longer exist
+    /// are removed, the stations of its edges are added back and edges on the
+    /// map that are still marked as used by the line are reconnected. The
+    /// edges and stations are then ordered along the line. Returns an error
+    /// if a gap remains, as no edge exists to close it.
+    pub fn repair(&mut self, map: &Map) -> Result<()> {
+        self.edges
+            .retain(|id| {
+                map.get_edge(*id)
+                    .is_some()
+            });
+        for edge in map.get_edges() {
+            if edge
+                .get_lines()
+                .contains(&self.id)
+                && !self
+                    .edges
+                    .contains(&edge.get_id())
+            {
+                self.edges
+                    .push(edge.get_id());
+            }
+        }
+
+        for edge in self
+            .edges
+            .iter()
+            .filter_map(|id| map.get_edge(*id))
+        {
+            for station in [edge.get_from(), edge.get_to()] {
+                if !self
+                    .stations
+                    .contains(&station)
+                {
+                    self.stations
+                        .push(station);
+                }
+            }
+        }
+        self.stations
+            .retain(|id| {
+                map.get_station(*id)
+                    .is_some()
+            });
+
+        // Start walking the line at one of its ends, so the stations are
+        // ordered along it. A loop has no ends and is walked from its first
+        // station instead.
+        let end = self
+            .get_line_ends(map)
+            .first()
+            .copied();
+        self.order_from(map, end);
+
+        if self.is_continuous(map) {
+            Ok(())
+        } else {
+            Err(Error::other(format!(
+                "line {} has a gap that can not be closed",
+                self.get_name()
+            )))
+        }
+    }
+
+    /// Order the stations and edges of the line along it, walking the line from
+    /// the given station if it is on the line, or from its first station
+    /// otherwise.
+    fn order_from(&mut self, map: &Map, start: Option<StationID>) {
+        if let Some(index) = self
+            .stations
+            .iter()
+            .position(|s| Some(*s) == start)
+        {
+            self.stations
+                .swap(0, index);
+        }
+        self.stations = self
+            .connected_groups(map)
+            .concat();
+        self.edges
+            .sort_by_key(|id| {
+                map.get_edge(*id)
+                    .and_then(|edge| {
+                        self.stations
+                            .iter()
+                            .position(|s| edge.is_from(*s) || edge.is_to(*s))
+                    })
+            });
+    }
+
+    /// Reverse the order of the stations and edges of the line, and of the
+    /// stops of its service patterns.
+    pub fn reverse(&mut self) {
+        self.stations
+            .reverse();
+        self.edges
+            .reverse();
+        for pattern in &mut self.patterns {
+            pattern
+                .stations
+                .reverse();
+        }
+    }
+
+    /// Orient the line so it starts at its westernmost terminus, or the
+    /// topmost one of the termini that are equally far west. A loop has no
+    /// termini and is left as it is.
+    pub fn normalize_direction(&mut self, map: &Map) {
+        let Some(start) = self
+            .get_line_ends(map)
+            .into_iter()
+            .filter_map(|id| map.get_station(id))
+            .min_by_key(|station| {
+                (
+                    station
+                        .get_pos()
+                        .0,
+                    station
+                        .get_pos()
+                        .1,
+                )
+            })
+            .map(Station::get_id)
+        else {
+            return;
+        };
+
+        if self
+            .stations
+            .first()
+            == Some(&start)
+        {
+            return;
+        }
+
+        if self
+            .stations
+            .last()
+            == Some(&start)
+        {
+            self.reverse();
+        } else {
+            self.order_from(map, Some(start));
+        }
+    }
+
+    /// Split off the part of the line after the given station into a new line
+    /// with the same name and color. The part holding the first station of
+    /// the line stays on this line and the given station is kept on both.
+    /// Returns an error if the station is not on the line or the line does
+    /// not continue on both sides of it.
+    pub fn split_off(&mut self, map: &Map, at: StationID) -> Result<Line> {
+        if !self
+            .stations
+            .contains(&at)
+        {
+            return Err(Error::other(
+                "Station to split at is not on the line.",
+            ));
+        }
+
+        // Without the station and its edges, the line falls apart into the
+        // part that is kept and the parts that are moved to the new line.
+        let mut without = self.clone();
+        without
+            .stations
+            .retain(|s| *s != at);
+        without
+            .edges
+            .retain(|id| {
+                map.get_edge(*id)
+                    .is_some_and(|edge| !edge.is_from(at) && !edge.is_to(at))
+            });
+        let moved = without
+            .connected_groups(map)
+            .into_iter()
+            .skip(1)
+            .flatten()
+            .collect::<HashSet<_>>();
+        if moved.is_empty() {
+            return Err(Error::other(
+                "The line does not continue past the station to split at.",
+            ));
+        }
+
+        let mut new_line = Line::new(None);
+        new_line.set_name(&self.name);
+        new_line.set_color(self.color);
+        new_line.stations = self
+            .stations
+            .iter()
+            .filter(|s| **s == at || moved.contains(s))
+            .copied()
+            .collect();
+        new_line.edges = self
+            .edges
+            .iter()
+            .filter(|id| {
+                map.get_edge(**id)
+                    .is_some_and(|edge| {
+                        moved.contains(&edge.get_from()) || moved.contains(&edge.get_to())
+                    })
+            })
+            .copied()
+            .collect();
+        new_line.patterns = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                RoutePattern::new(
+                    &pattern.name,
+                    pattern
+                        .stations
+                        .iter()
+                        .filter(|s| {
+                            new_line
+                                .stations
+                                .contains(s)
+                        })
+                        .copied()
+                        .collect(),
+                )
+            })
+            .filter(|pattern| {
+                pattern
+                    .stations
+                    .len()
+                    > 1
+            })
+            .collect();
+
+        self.stations
+            .retain(|s| !moved.contains(s));
+        self.edges
+            .retain(|id| {
+                !new_line
+                    .edges
+                    .contains(id)
+            });
+        for station in &moved {
+            self.remove_station_from_patterns(*station);
+        }
+
+        Ok(new_line)
+    }
+
+    /// Calculate the [`LineStats`] of the line. A line with a single station
+    /// has no edges, so its length and bends are zero.
+    pub fn get_stats(&self, map: &Map) -> LineStats {
+        let edges = self
+            .edges
+            .iter()
+            .filter_map(|id| map.get_edge(*id))
+            .collect::<Vec<_>>();
+        let stations = self
+            .stations
+            .iter()
+            .filter_map(|id| map.get_station(*id))
+            .filter(|s| !s.is_checkpoint())
+            .collect::<Vec<_>>();
+
+        LineStats {
+            station_count: stations.len(),
+            length: edges
+                .iter()
+                .map(|e| e.routed_length(map))
+                .sum(),
+            bend_count: edges
+                .iter()
+                .map(|e| e.bend_count(map.get_grid_mode()))
+                .sum(),
+            interchange_count: stations
+                .iter()
+                .filter(|s| {
+                    map.lines_through_station(s.get_id())
+                        .into_iter()
+                        .any(|l| l.get_id() != self.id)
+                })
+                .count(),
+        }
+    }
+
     /// Get a list of neighbors of the given station.
     pub fn get_station_neighbors(
         &self,
@@ -450,8 +953,43 @@ impl Line {
             })
     }
 
-    /// Gets the start and end stations of the line.
-    fn get_line_ends(&self, map: &Map) -> Vec<StationID> {
+    /// Check if the line is a loop, like a circle line, where every station is
+    /// connected to exactly two others by the edges of the line. Such a line
+    /// has no ends.
+    pub fn is_cycle(&self, map: &Map) -> bool {
+        if self
+            .stations
+            .len()
+            < 3
+            || self
+                .edges
+                .len()
+                != self
+                    .stations
+                    .len()
+        {
+            return false;
+        }
+
+        let degrees = self
+            .edges
+            .iter()
+            .filter_map(|id| map.get_edge(*id))
+            .flat_map(|edge| [edge.get_from(), edge.get_to()])
+            .counts();
+
+        self.stations
+            .iter()
+            .all(|id| degrees.get(id) == Some(&2))
+            && self
+                .connected_groups(map)
+                .len()
+                == 1
+    }
+
+    /// Gets the start and end stations of the line. A line that is a loop, see
+    /// [`Line::is_cycle`], has no ends and an empty list is returned for it.
+    pub fn get_line_ends(&self, map: &Map) -> Vec<StationID> {
         let mut ends = Vec::new();
         let mut middles = Vec::new();
 
@@ -519,16 +1057,15 @@ impl Line {
             width = 1.0;
         }
 
+        let color = state
+            .get_color_vision()
+            .simulate(self.color);
+
         canvas.set_line_width(width);
         canvas.set_global_alpha(1.0 * base_alpha);
         canvas.set_stroke_style_str(&format!(
             "rgb({} {} {})",
-            self.color
-                .0,
-            self.color
-                .1,
-            self.color
-                .2
+            color.0, color.1, color.2,
         ));
         canvas.begin_path();
 
@@ -644,6 +1181,82 @@ mod tests {
         );
     }
 
+    /// Create a map with a single line through four stations in a row.
+    fn continuity_map() -> (Map, LineID) {
+        let mut map = Map::new();
+        for i in 1..=4 {
+            map.add_station(Station::new(
+                (i, 0).into(),
+                Some(StationID::from(i as u64)),
+            ));
+        }
+        let line_id = map.add_line_through(
+            (0, 0, 0),
+            &"Line",
+            &[1.into(), 2.into(), 3.into(), 4.into()],
+        );
+
+        (map, line_id)
+    }
+
+    #[test]
+    fn test_is_continuous() {
+        let (mut map, line_id) = continuity_map();
+        assert!(
+            map.get_line(line_id)
+                .unwrap()
+                .is_continuous(&map)
+        );
+
+        // Removing the middle edge leaves a gap.
+        let middle = map
+            .get_edge_id_between_if_exists(2.into(), 3.into())
+            .unwrap();
+        map.remove_edge(middle);
+        let mut line = map
+            .get_line(line_id)
+            .unwrap()
+            .clone();
+        assert!(!line.is_continuous(&map));
+        assert!(
+            line.repair(&map)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_repair() {
+        let (map, line_id) = continuity_map();
+        let mut line = map
+            .get_line(line_id)
+            .unwrap()
+            .clone();
+
+        // Losing track of an edge that is still marked as used by the line.
+        let middle = map
+            .get_edge_id_between_if_exists(2.into(), 3.into())
+            .unwrap();
+        line.remove_edge_raw(middle);
+        line.get_mut_stations()
+            .reverse();
+        assert!(!line.is_continuous(&map));
+
+        assert!(
+            line.repair(&map)
+                .is_ok()
+        );
+        assert!(line.is_continuous(&map));
+        assert_eq!(
+            line.get_edges()
+                .len(),
+            3
+        );
+        assert_eq!(
+            line.get_stations(),
+            &[1.into(), 2.into(), 3.into(), 4.into()]
+        );
+    }
+
     #[test]
     fn test_remove_station() {
         let mut map = Map::new();
@@ -706,6 +1319,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_route_patterns() {
+        let mut map = Map::new();
+        let mut line = Line::new(None);
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        let station3: StationID = 3.into();
+
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (0, 2).into(),
+            Some(station2),
+        ));
+        map.add_station(Station::new(
+            (0, 4).into(),
+            Some(station3),
+        ));
+
+        line.add_station(&mut map, station1, None, None);
+        line.add_station(&mut map, station2, Some(station1), None);
+        line.add_station(&mut map, station3, Some(station2), None);
+        line.set_patterns(vec![RoutePattern::new(
+            &"limited",
+            vec![station1, station3],
+        )]);
+        let line_id = line.get_id();
+        map.add_line(line.clone());
+
+        let mut state = CanvasState::new();
+        state.set_square_size(10);
+
+        // Without an active pattern, no station is filled in.
+        let canvas = CanvasContext::new();
+        map.draw(&canvas, state, 1.0);
+        assert_eq!(canvas.get_record("fill"), None);
+
+        state.set_active_pattern(Some((line_id, 0)));
+        let canvas = CanvasContext::new();
+        map.draw(&canvas, state, 1.0);
+        assert_eq!(
+            canvas
+                .get_record("fill")
+                .map(|r| r.len()),
+            Some(2)
+        );
+
+        let removed_station = map
+            .get_station(station3)
+            .cloned()
+            .expect("invalid station id");
+        line.remove_station(&mut map, &removed_station);
+        assert_eq!(
+            line.get_patterns()[0].get_stations(),
+            &[station1]
+        );
+    }
+
     #[test]
     fn test_get_edge_stations() {
         let mut map = Map::new();
@@ -821,6 +1494,53 @@ mod tests {
         assert_eq!(ends, vec![station1, station3]);
     }
 
+    #[test]
+    fn test_loop_line() {
+        let mut map = Map::new();
+        let stations = [(0, 0), (4, 0), (4, 4), (0, 4)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, pos)| map.add_named_station(&format!("S{i}"), pos.into()))
+            .collect::<Vec<_>>();
+        let open = map.add_line_through((0, 0, 255), &"Open", &stations);
+        let circle = map.add_line_through(
+            (255, 200, 0),
+            &"Circle",
+            &[
+                stations[0],
+                stations[1],
+                stations[2],
+                stations[3],
+                stations[0],
+            ],
+        );
+
+        let open = map
+            .get_line(open)
+            .unwrap();
+        assert!(!open.is_cycle(&map));
+        assert_eq!(
+            open.get_line_ends(&map),
+            vec![stations[0], stations[3]]
+        );
+
+        let circle = map
+            .get_line(circle)
+            .unwrap();
+        assert!(circle.is_cycle(&map));
+        assert!(
+            circle
+                .get_line_ends(&map)
+                .is_empty()
+        );
+        assert_eq!(
+            circle
+                .get_edges()
+                .len(),
+            4
+        );
+    }
+
     #[test]
     fn test_draw_single_station() {
         let mut map = Map::new();
@@ -882,4 +1602,78 @@ mod tests {
         assert_eq!(canvas.get_record("move_to"), None);
         assert_eq!(canvas.get_record("line_to"), None);
     }
+
+    #[test]
+    fn test_get_stats() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        let station3: StationID = 3.into();
+
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (2, 0).into(),
+            Some(station2),
+        ));
+        map.add_station(Station::new(
+            (4, 2).into(),
+            Some(station3),
+        ));
+
+        let mut line = Line::new(None);
+        line.add_station(&mut map, station1, None, None);
+        line.add_station(&mut map, station2, Some(station1), None);
+        line.add_station(&mut map, station3, Some(station2), None);
+        let edge_id = map.get_edge_id_between(station2, station3);
+        map.get_mut_edge(edge_id)
+            .unwrap()
+            .set_nodes(vec![
+                (2, 1).into(),
+                (3, 2).into(),
+                (4, 2).into(),
+            ]);
+        map.add_line(line.clone());
+
+        // A branch continuing from the last station, which makes that station
+        // an interchange of both lines.
+        let station4: StationID = 4.into();
+        map.add_station(Station::new(
+            (6, 2).into(),
+            Some(station4),
+        ));
+        let mut branch = Line::new(None);
+        branch.add_station(&mut map, station3, None, None);
+        branch.add_station(&mut map, station4, Some(station3), None);
+        map.add_line(branch.clone());
+
+        let stats = line.get_stats(&map);
+        assert_eq!(stats.station_count, 3);
+        assert_eq!(stats.bend_count, 1);
+        assert_eq!(stats.interchange_count, 1);
+        assert!((stats.length - (3.0 + 2f64.sqrt() + 1.0)).abs() < 1e-9);
+
+        let stats = branch.get_stats(&map);
+        assert_eq!(stats.station_count, 2);
+        assert!((stats.length - 2.0).abs() < 1e-9);
+        assert_eq!(stats.bend_count, 0);
+        assert_eq!(stats.interchange_count, 1);
+    }
+
+    #[test]
+    fn test_cycle_color() {
+        let mut line = Line::new(None);
+        line.set_color((1, 2, 3));
+
+        line.cycle_color();
+        assert_eq!(line.get_color(), LINE_PALETTE[0]);
+        line.cycle_color();
+        assert_eq!(line.get_color(), LINE_PALETTE[1]);
+
+        line.set_color(LINE_PALETTE[LINE_PALETTE.len() - 1]);
+        line.cycle_color();
+        assert_eq!(line.get_color(), LINE_PALETTE[0]);
+    }
 }
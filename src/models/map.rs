@@ -1,7 +1,12 @@
 //! Contains the [`Map`] struct and all its methods.
 
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+    VecDeque,
+};
 
+use itertools::Itertools;
 use serde::{
     Deserialize,
     Serialize,
@@ -13,19 +18,160 @@ use super::{
     Edge,
     EdgeID,
     GridNode,
+    Interchange,
     Line,
     Station,
 };
 use crate::{
     algorithms::{
+        color_hue,
+        draw_endcaps,
+        octilinear_direction,
+        BackgroundShape,
         CanvasContext,
+        DrawCache,
+        GridMode,
         OccupiedNodes,
+        DASH_PATTERNS,
     },
     components::CanvasState,
-    utils::Result,
+    utils::{
+        GridTransform,
+        IDManager,
+        Result,
+    },
     Error,
 };
 
+/// The overall quality of the layout of a map, used to compare layouts with
+/// each other. Lower is better for all of its parts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LayoutScore {
+    /// The amount of bends in all edges of the map.
+    pub bend_count: usize,
+    /// The combined length of the routes of all edges of the map.
+    pub edge_length: f64,
+    /// The amount of pairs of edges that cross each other.
+    pub crossing_count: usize,
+    /// The combined distance all stations moved away from their original
+    /// position.
+    pub displacement: f64,
+}
+
+impl LayoutScore {
+    /// Combine the parts of the score into a single number using the given
+    /// weights.
+    pub fn weighted(&self, weights: LayoutWeights) -> f64 {
+        self.bend_count as f64 * weights.bends
+            + self.edge_length * weights.length
+            + self.crossing_count as f64 * weights.crossings
+            + self.displacement * weights.displacement
+    }
+}
+
+/// The weights of the parts of a [`LayoutScore`] when combining them into a
+/// single number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutWeights {
+    /// The weight of every bend.
+    pub bends: f64,
+    /// The weight of every grid square of edge length.
+    pub length: f64,
+    /// The weight of every crossing.
+    pub crossings: f64,
+    /// The weight of every grid square of station displacement.
+    pub displacement: f64,
+}
+
+impl Default for LayoutWeights {
+    fn default() -> Self {
+        Self {
+            bends: 1.0,
+            length: 0.1,
+            crossings: 5.0,
+            displacement: 0.5,
+        }
+    }
+}
+
+/// The nodes the algorithm considers for the stations at both ends of the edge
+/// it is about to route, sent along with its midway updates so they can be
+/// drawn while debugging.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchRegion {
+    /// The edge being routed.
+    pub edge: EdgeID,
+    /// The nodes the from station of the edge may be placed on.
+    pub from_nodes: Vec<GridNode>,
+    /// The nodes the to station of the edge may be placed on.
+    pub to_nodes: Vec<GridNode>,
+}
+
+/// The new ids of the stations, lines and edges of a map when renumbering them
+/// with [`Map::with_normalized_ids`].
+#[derive(Debug, Default)]
+pub(super) struct IDMapping {
+    /// The new id of every station by its old id.
+    stations: HashMap<StationID, StationID>,
+    /// The new id of every line by its old id.
+    lines: HashMap<LineID, LineID>,
+    /// The new id of every edge by its old id.
+    edges: HashMap<EdgeID, EdgeID>,
+}
+
+impl IDMapping {
+    /// Number the stations, lines and edges of the given map from 1 upwards,
+    /// in the order of their current ids, with a separate [`IDManager`].
+    fn new(map: &Map) -> Self {
+        let ids = IDManager::new();
+
+        Self {
+            stations: map
+                .stations
+                .keys()
+                .copied()
+                .sorted()
+                .map(|id| (id, ids.take_station_id()))
+                .collect(),
+            lines: map
+                .lines
+                .keys()
+                .copied()
+                .sorted()
+                .map(|id| (id, ids.take_line_id()))
+                .collect(),
+            edges: map
+                .edges
+                .keys()
+                .copied()
+                .sorted()
+                .map(|id| (id, ids.take_edge_id()))
+                .collect(),
+        }
+    }
+
+    /// Get the new id of the given station, if it is on the map.
+    pub(super) fn station(&self, id: StationID) -> Option<StationID> {
+        self.stations
+            .get(&id)
+            .copied()
+    }
+
+    /// Get the new id of the given line, if it is on the map.
+    pub(super) fn line(&self, id: LineID) -> Option<LineID> {
+        self.lines
+            .get(&id)
+            .copied()
+    }
+
+    /// Get the new id of the given edge, if it is on the map.
+    pub(super) fn edge(&self, id: EdgeID) -> Option<EdgeID> {
+        self.edges
+            .get(&id)
+            .copied()
+    }
+}
+
 /// Represents the metro map as a whole with all its lines and stations.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Map {
@@ -35,6 +181,23 @@ pub struct Map {
     lines: HashMap<LineID, Line>,
     /// A [`HashMap`] of all edges on the map.
     edges: HashMap<EdgeID, Edge>,
+    /// All out-of-station interchanges between stations on the map.
+    #[serde(default)]
+    interchanges: Vec<Interchange>,
+    /// The node sets of the edge the algorithm is routing, only set on the
+    /// midway updates of the algorithm when debugging them is enabled.
+    #[serde(default)]
+    search_region: Option<SearchRegion>,
+    /// The decorative shapes drawn behind the map, like rivers and parks.
+    #[serde(default)]
+    background: Vec<BackgroundShape>,
+    /// The transform the map was placed onto the grid with when it was
+    /// imported, if it was imported from a file with coordinates.
+    #[serde(default)]
+    grid_transform: Option<GridTransform>,
+    /// The shape of the grid the edges of the map are routed on.
+    #[serde(default)]
+    grid_mode: GridMode,
 }
 
 impl Map {
@@ -57,9 +220,69 @@ impl Map {
             stations,
             lines,
             edges,
+            interchanges: Vec::new(),
+            search_region: None,
+            background: Vec::new(),
+            grid_transform: None,
+            grid_mode: GridMode::default(),
         }
     }
 
+    /// Get a copy of the map with its stations, lines and edges renumbered from
+    /// 1 upwards in the order of their current ids, so sparse or huge ids from
+    /// an imported map become a compact range. Every reference between them is
+    /// updated to the new ids, and references to objects that are not on the
+    /// map are dropped.
+    pub fn with_normalized_ids(&self) -> Self {
+        let mapping = IDMapping::new(self);
+
+        let stations = self
+            .stations
+            .values()
+            .map(|station| {
+                let mut station = station.clone();
+                station.remap_ids(&mapping);
+                (station.get_id(), station)
+            })
+            .collect();
+        let lines = self
+            .lines
+            .values()
+            .map(|line| {
+                let mut line = line.clone();
+                line.remap_ids(&mapping);
+                (line.get_id(), line)
+            })
+            .collect();
+        let edges = self
+            .edges
+            .values()
+            .map(|edge| {
+                let mut edge = edge.clone();
+                edge.remap_ids(&mapping);
+                (edge.get_id(), edge)
+            })
+            .collect();
+
+        let mut map = Self::from_inner(stations, lines, edges);
+        map.interchanges = self
+            .interchanges
+            .iter()
+            .filter_map(|interchange| {
+                let (first, second) = interchange.get_stations();
+                Some(Interchange::new(
+                    mapping.station(first)?,
+                    mapping.station(second)?,
+                ))
+            })
+            .collect();
+        map.background
+            .clone_from(&self.background);
+        map.grid_transform = self.grid_transform;
+        map.grid_mode = self.grid_mode;
+        map
+    }
+
     /// Get a [`Station`] with the given id.
     pub fn get_station(&self, id: StationID) -> Option<&Station> {
         self.stations
@@ -117,6 +340,15 @@ impl Map {
             .collect()
     }
 
+    /// Get a list of all [`Edge`]s on the map sorted by their id, for when the
+    /// order they are handled in has to be the same on every run.
+    pub fn get_edges_sorted(&self) -> Vec<&Edge> {
+        self.edges
+            .values()
+            .sorted_by_key(|edge| edge.get_id())
+            .collect()
+    }
+
     /// Get a mutable reference to all [`Edge`]s on the map.
     pub fn get_mut_edges(&mut self) -> Vec<&mut Edge> {
         self.edges
@@ -124,12 +356,23 @@ impl Map {
             .collect()
     }
 
-    /// Get the [`Edge`] between the two given stations.
+    /// Get the ids of all [`Edge`]s between the two given stations, ordered by
+    /// id. There can be multiple if lines take parallel routes between them.
+    pub fn get_edge_ids_between(&self, from: StationID, to: StationID) -> Vec<EdgeID> {
+        self.edges
+            .values()
+            .filter(|e| (e.is_from(from) && e.is_to(to)) || (e.is_from(to) && e.is_to(from)))
+            .map(Edge::get_id)
+            .sorted()
+            .collect()
+    }
+
+    /// Get the [`Edge`] between the two given stations. If there are multiple,
+    /// the one with the lowest id is returned.
     pub fn get_edge_id_between_if_exists(&self, from: StationID, to: StationID) -> Option<EdgeID> {
-        self.get_edges()
+        self.get_edge_ids_between(from, to)
             .into_iter()
-            .find(|e| (e.is_from(from) && e.is_to(to)) || (e.is_from(to) && e.is_to(from)))
-            .map(Edge::get_id)
+            .next()
     }
 
     /// Get the id of the [`Edge`] between the two given stations, else create
@@ -139,6 +382,43 @@ impl Map {
             return e;
         }
 
+        self.add_parallel_edge(from, to)
+    }
+
+    /// Get the id of the [`Edge`] between the two given stations that the given
+    /// line uses. If the line does not use any of them yet, an existing edge
+    /// is returned or a new one is created.
+    pub fn get_line_edge_id_between(
+        &mut self,
+        from: StationID,
+        to: StationID,
+        line: LineID,
+    ) -> EdgeID {
+        let edge_ids = self.get_edge_ids_between(from, to);
+
+        if let Some(e) = edge_ids
+            .iter()
+            .find(|id| {
+                self.get_edge(**id)
+                    .is_some_and(|e| {
+                        e.get_lines()
+                            .contains(&line)
+                    })
+            })
+        {
+            return *e;
+        }
+
+        if let Some(e) = edge_ids.first() {
+            return *e;
+        }
+
+        self.add_parallel_edge(from, to)
+    }
+
+    /// Add a new [`Edge`] between the two given stations, even if there
+    /// already are edges between them, and return its id.
+    pub fn add_parallel_edge(&mut self, from: StationID, to: StationID) -> EdgeID {
         let new = Edge::new(from, to, None);
         let new_id = new.get_id();
         self.add_edge(new);
@@ -153,6 +433,78 @@ impl Map {
             .collect()
     }
 
+    /// A getter for the stations on the map sorted by their id, for when the
+    /// order they are handled in has to be the same on every run.
+    pub fn get_stations_sorted(&self) -> Vec<&Station> {
+        self.stations
+            .values()
+            .sorted_by_key(|station| station.get_id())
+            .collect()
+    }
+
+    /// Check that neither the amount of stations nor the amount of edges on
+    /// the map is more than the given limit, returning an
+    /// [`Error::MapTooLarge`] if one is. A limit of 0 means there is no limit.
+    pub fn check_size(&self, limit: usize) -> Result<()> {
+        let stations = self
+            .stations
+            .len();
+        let edges = self
+            .edges
+            .len();
+
+        if limit > 0 && (stations > limit || edges > limit) {
+            return Err(Error::MapTooLarge {
+                stations,
+                edges,
+                limit,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Get the station that comes after the given one when going through all
+    /// stations in order of their id, or before it if going backwards. Wraps
+    /// around at the ends, and starts at the first or last station if no
+    /// station or an unknown station is given.
+    pub fn get_next_station_id(
+        &self,
+        current: Option<StationID>,
+        backwards: bool,
+    ) -> Option<StationID> {
+        let ids = self
+            .stations
+            .keys()
+            .copied()
+            .sorted()
+            .collect::<Vec<_>>();
+        let count = ids.len();
+
+        let index = match current.and_then(|id| {
+            ids.iter()
+                .position(|s| *s == id)
+        }) {
+            Some(index) if backwards => (index + count - 1) % count,
+            Some(index) => (index + 1) % count,
+            None if backwards => count.checked_sub(1)?,
+            None => 0,
+        };
+
+        ids.get(index)
+            .copied()
+    }
+
+    /// Get the largest weight of any station on the map, if any station has a
+    /// positive weight.
+    pub fn get_max_station_weight(&self) -> Option<f64> {
+        self.stations
+            .values()
+            .filter_map(Station::get_weight)
+            .filter(|w| *w > 0.0)
+            .max_by(f64::total_cmp)
+    }
+
     /// A mutable getter for the stations on the map.
     pub fn get_mut_stations(&mut self) -> Vec<&mut Station> {
         self.stations
@@ -167,6 +519,26 @@ impl Map {
             .insert(station.get_id(), station);
     }
 
+    /// Add a new station with the given name at the given position to the map,
+    /// returning the id it was given.
+    ///
+    /// ```
+    /// use metro_map_editor::models::Map;
+    ///
+    /// let mut map = Map::new();
+    /// let station = map.add_named_station(&"Central", (0, 0).into());
+    ///
+    /// assert_eq!(map.get_station(station).unwrap().get_name(), "Central");
+    /// ```
+    pub fn add_named_station(&mut self, name: &impl ToString, pos: GridNode) -> StationID {
+        let mut station = Station::new(pos, None);
+        station.set_name(name);
+
+        let id = station.get_id();
+        self.add_station(station);
+        id
+    }
+
     /// Remove a station from the map.
     pub fn remove_station(&mut self, id: StationID) {
         let Some(station) = self
@@ -186,10 +558,202 @@ impl Map {
             self.add_line(line);
         }
 
+        self.interchanges
+            .retain(|i| !i.contains(id));
         self.stations
             .remove(&id);
     }
 
+    /// A getter method for the interchanges between stations on the map.
+    pub fn get_interchanges(&self) -> &[Interchange] {
+        &self.interchanges
+    }
+
+    /// Add an interchange between the two given stations. Returns an error if
+    /// either station does not exist, or if both are the same station. Adding
+    /// an interchange that already exists does nothing.
+    pub fn add_interchange(&mut self, a: StationID, b: StationID) -> Result<()> {
+        if a == b {
+            return Err(Error::other(
+                "An interchange needs two different stations.",
+            ));
+        }
+        for id in [a, b] {
+            if self
+                .get_station(id)
+                .is_none()
+            {
+                return Err(Error::other(format!(
+                    "Station {id} for interchange not found."
+                )));
+            }
+        }
+
+        let interchange = Interchange::new(a, b);
+        if !self
+            .interchanges
+            .contains(&interchange)
+        {
+            self.interchanges
+                .push(interchange);
+        }
+        Ok(())
+    }
+
+    /// Remove the interchange between the two given stations, if it exists.
+    pub fn remove_interchange(&mut self, a: StationID, b: StationID) {
+        let interchange = Interchange::new(a, b);
+        self.interchanges
+            .retain(|i| *i != interchange);
+    }
+
+    /// Replace all interchanges on the map with the given ones, skipping any
+    /// whose stations are not on the map.
+    pub fn set_interchanges(&mut self, interchanges: Vec<Interchange>) {
+        self.interchanges = interchanges
+            .into_iter()
+            .filter(|i| {
+                let (a, b) = i.get_stations();
+                self.stations
+                    .contains_key(&a)
+                    && self
+                        .stations
+                        .contains_key(&b)
+            })
+            .collect();
+    }
+
+    /// A getter for the node sets of the edge the algorithm is routing, only
+    /// set on its midway updates when debugging them is enabled.
+    pub fn get_search_region(&self) -> Option<&SearchRegion> {
+        self.search_region
+            .as_ref()
+    }
+
+    /// Set the node sets of the edge the algorithm is routing.
+    pub fn set_search_region(&mut self, search_region: Option<SearchRegion>) {
+        self.search_region = search_region;
+    }
+
+    /// A getter for the decorative shapes drawn behind the map.
+    pub fn get_background(&self) -> &[BackgroundShape] {
+        &self.background
+    }
+
+    /// Set the decorative shapes drawn behind the map.
+    pub fn set_background(&mut self, background: Vec<BackgroundShape>) {
+        self.background = background;
+    }
+
+    /// A getter for the transform the map was placed onto the grid with when
+    /// it was imported, if any.
+    pub fn get_grid_transform(&self) -> Option<GridTransform> {
+        self.grid_transform
+    }
+
+    /// Set the transform the map was placed onto the grid with when it was
+    /// imported.
+    pub fn set_grid_transform(&mut self, grid_transform: Option<GridTransform>) {
+        self.grid_transform = grid_transform;
+    }
+
+    /// A getter for the shape of the grid the edges of the map are routed on.
+    pub fn get_grid_mode(&self) -> GridMode {
+        self.grid_mode
+    }
+
+    /// Set the shape of the grid the edges of the map are routed on.
+    pub fn set_grid_mode(&mut self, grid_mode: GridMode) {
+        self.grid_mode = grid_mode;
+    }
+
+    /// Get the edges of the corridor the given edge is part of, being all edges
+    /// connected to it through stations that are used by exactly the same
+    /// lines.
+    pub fn corridor_edges(&self, edge_id: EdgeID) -> Vec<EdgeID> {
+        let Some(edge) = self.get_edge(edge_id) else {
+            return Vec::new();
+        };
+        let lines = edge
+            .get_lines()
+            .iter()
+            .collect::<HashSet<_>>();
+
+        let mut corridor = vec![edge_id];
+        let mut index = 0;
+        while let Some(current) = corridor
+            .get(index)
+            .and_then(|id| self.get_edge(*id))
+        {
+            let neighbors = [current.get_from(), current.get_to()]
+                .into_iter()
+                .filter_map(|id| self.get_station(id))
+                .flat_map(Station::get_edges)
+                .filter_map(|id| self.get_edge(*id))
+                .filter(|e| {
+                    e.get_lines()
+                        .iter()
+                        .collect::<HashSet<_>>()
+                        == lines
+                })
+                .map(Edge::get_id)
+                .collect::<Vec<_>>();
+
+            for neighbor in neighbors {
+                if !corridor.contains(&neighbor) {
+                    corridor.push(neighbor);
+                }
+            }
+            index += 1;
+        }
+
+        corridor
+    }
+
+    /// Get the stations and edges of the connected part of the map the given
+    /// station is in, being everything that can be reached from it over the
+    /// edges of the map. The given station comes first.
+    pub fn connected_component(&self, station_id: StationID) -> (Vec<StationID>, Vec<EdgeID>) {
+        if self
+            .get_station(station_id)
+            .is_none()
+        {
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut stations = vec![station_id];
+        let mut edges = Vec::new();
+        let mut seen_stations = HashSet::from([station_id]);
+        let mut seen_edges = HashSet::new();
+        let mut index = 0;
+        while let Some(station) = stations
+            .get(index)
+            .and_then(|id| self.get_station(*id))
+        {
+            for edge in station
+                .get_edges()
+                .iter()
+                .filter_map(|id| self.get_edge(*id))
+            {
+                if seen_edges.insert(edge.get_id()) {
+                    edges.push(edge.get_id());
+                }
+
+                let other = if edge.get_from() == station.get_id() {
+                    edge.get_to()
+                } else {
+                    edge.get_from()
+                };
+                if seen_stations.insert(other) {
+                    stations.push(other);
+                }
+            }
+            index += 1;
+        }
+
+        (stations, edges)
+    }
+
     /// Add a line to the map.
     pub fn add_line(&mut self, line: Line) {
         for edge_id in line.get_edges() {
@@ -202,6 +766,47 @@ impl Map {
             .insert(line.get_id(), line);
     }
 
+    /// Add a new line with the given color and name to the map, running through
+    /// the given stations in order. The edges between the stations are created
+    /// as well. Returns the id the line was given.
+    ///
+    /// ```
+    /// use metro_map_editor::models::Map;
+    ///
+    /// let mut map = Map::new();
+    /// let stations = [
+    ///     map.add_named_station(&"West", (0, 0).into()),
+    ///     map.add_named_station(&"Central", (3, 0).into()),
+    ///     map.add_named_station(&"East", (6, 0).into()),
+    /// ];
+    /// let line = map.add_line_through((255, 0, 0), &"Red", &stations);
+    ///
+    /// let line = map.get_line(line).unwrap();
+    /// assert_eq!(line.get_stations(), &stations);
+    /// assert_eq!(line.get_edges().len(), 2);
+    /// assert!(line.is_continuous(&map));
+    /// ```
+    pub fn add_line_through(
+        &mut self,
+        color: (u8, u8, u8),
+        name: &impl ToString,
+        stations: &[StationID],
+    ) -> LineID {
+        let mut line = Line::new(None);
+        line.set_color(color);
+        line.set_name(name);
+
+        let mut before = None;
+        for station in stations {
+            line.add_station(self, *station, before, None);
+            before = Some(*station);
+        }
+
+        let id = line.get_id();
+        self.add_line(line);
+        id
+    }
+
     /// Get mutable [`Line`] if exists, else add new line with that [`LineID`]
     /// and return it.
     pub fn get_or_add_line(&mut self, id: LineID) -> &Line {
@@ -236,6 +841,118 @@ impl Map {
         Ok(())
     }
 
+    /// Split a line in two at the given station, as in [`Line::split_off`].
+    /// The edges moved to the new line stay on the map, together with any
+    /// other lines that follow them. Returns the id of the new line.
+    pub fn split_line(&mut self, line_id: LineID, at: StationID) -> Result<LineID> {
+        let mut line = self
+            .get_line(line_id)
+            .cloned()
+            .ok_or(Error::other(format!(
+                "Line {line_id} to split not found."
+            )))?;
+        let new_line = line.split_off(self, at)?;
+        let new_id = new_line.get_id();
+
+        for edge_id in new_line.get_edges() {
+            if let Some(edge) = self.get_mut_edge(*edge_id) {
+                edge.remove_line(line_id);
+            }
+        }
+        self.add_line(line);
+        self.add_line(new_line);
+
+        Ok(new_id)
+    }
+
+    /// Orient every line so it starts at its westernmost terminus, as in
+    /// [`Line::normalize_direction`]. This makes drawing that depends on the
+    /// direction of a line, like its endcaps, consistent across the map.
+    pub fn normalize_line_directions(&mut self) {
+        let ids = self
+            .lines
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        for id in ids {
+            let Some(mut line) = self
+                .get_line(id)
+                .cloned()
+            else {
+                continue;
+            };
+            line.normalize_direction(self);
+            self.lines
+                .insert(id, line);
+        }
+    }
+
+    /// Join line `b` into line `a` where they share an end station, so their
+    /// stations and edges form a single line that keeps the name and color
+    /// of `a`. Line `b` is removed from the map. Returns an error, leaving the
+    /// map unchanged, if the lines do not share an end station or the joined
+    /// line has a gap.
+    pub fn join_lines(&mut self, a: LineID, b: LineID) -> Result<()> {
+        if a == b {
+            return Err(Error::other(
+                "Cannot join a line with itself.",
+            ));
+        }
+
+        let mut line = self
+            .get_line(a)
+            .cloned()
+            .ok_or(Error::other(format!(
+                "Line {a} to join not found."
+            )))?;
+        let other = self
+            .get_line(b)
+            .cloned()
+            .ok_or(Error::other(format!(
+                "Line {b} to join not found."
+            )))?;
+
+        let other_ends = other.get_line_ends(self);
+        if !line
+            .get_line_ends(self)
+            .iter()
+            .any(|station| other_ends.contains(station))
+        {
+            return Err(Error::other(format!(
+                "Lines {a} and {b} do not share an end station."
+            )));
+        }
+
+        // Join the lines on a copy of the map, so the map is left as it is if the
+        // joined line can not be ordered.
+        let mut map = self.clone();
+        map.lines
+            .remove(&b);
+        for edge_id in other.get_edges() {
+            let Some(edge) = map.get_mut_edge(*edge_id) else {
+                continue;
+            };
+            edge.remove_line(b);
+            line.add_edge(*edge_id, &mut map);
+        }
+        for station in other.get_stations() {
+            line.add_station(&mut map, *station, None, None);
+        }
+
+        let mut patterns = line
+            .get_patterns()
+            .to_vec();
+        patterns.extend_from_slice(other.get_patterns());
+        line.set_patterns(patterns);
+
+        // Order the stations and edges along the joined line.
+        line.repair(&map)?;
+        map.add_line(line);
+        *self = map;
+
+        Ok(())
+    }
+
     /// Add an edge to map, if an edge with that ID already exists, it will get
     /// replaces.
     pub fn add_edge(&mut self, edge: Edge) {
@@ -285,6 +1002,41 @@ impl Map {
             .collect::<Vec<_>>()
     }
 
+    /// Get all lines that pass through the given station, ordered by their id.
+    pub fn lines_through_station(&self, id: StationID) -> Vec<&Line> {
+        self.get_station(id)
+            .map(|station| {
+                station
+                    .get_edges()
+                    .iter()
+                    .filter_map(|edge_id| self.get_edge(*edge_id))
+                    .flat_map(|edge| {
+                        edge.get_lines()
+                            .iter()
+                            .copied()
+                    })
+                    .unique()
+                    .sorted()
+                    .filter_map(|line_id| self.get_line(line_id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get all lines that use the given edge, ordered by their id.
+    pub fn lines_on_edge(&self, id: EdgeID) -> Vec<&Line> {
+        self.get_edge(id)
+            .map(|edge| {
+                edge.get_lines()
+                    .iter()
+                    .copied()
+                    .sorted()
+                    .filter_map(|line_id| self.get_line(line_id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// Get the edge located on the given grid node.
     pub fn edge_at_node(&self, node: GridNode) -> Option<EdgeID> {
         self.edges
@@ -318,6 +1070,152 @@ impl Map {
         }
     }
 
+    /// Split the given edge in two by inserting a new station at the given
+    /// node, with both new edges following the same lines as the original edge.
+    /// The nodes of the original edge are divided over the two new edges.
+    /// Returns the id of the new station.
+    pub fn split_edge(&mut self, edge_id: EdgeID, node: GridNode) -> Result<StationID> {
+        let edge = self
+            .get_edge(edge_id)
+            .cloned()
+            .ok_or(Error::other(format!(
+                "Edge {edge_id} to split not found."
+            )))?;
+        let from_pos = self
+            .get_station(edge.get_from())
+            .ok_or(Error::other(
+                "Edge to split has an invalid from station.",
+            ))?
+            .get_pos();
+
+        if edge
+            .get_lines()
+            .is_empty()
+        {
+            return Err(Error::other(
+                "Cannot split an edge without lines.",
+            ));
+        }
+        if self
+            .station_at_node(node)
+            .is_some()
+        {
+            return Err(Error::other(
+                "Cannot insert a station on top of another station.",
+            ));
+        }
+
+        let station = Station::new(node, None);
+        let station_id = station.get_id();
+        self.add_station(station);
+
+        for line_id in edge.get_lines() {
+            let mut line = self
+                .get_line(*line_id)
+                .cloned()
+                .ok_or(Error::other(format!(
+                    "Line {line_id} on edge to split not found."
+                )))?;
+            line.add_station(
+                self,
+                station_id,
+                Some(edge.get_from()),
+                Some(edge.get_to()),
+            );
+            self.add_line(line);
+        }
+
+        // Divide the nodes of the original edge, ordered from its from station
+        // to its to station, over the two new edges.
+        let mut nodes = edge
+            .get_nodes()
+            .to_vec();
+        if nodes
+            .first()
+            .is_some_and(|first| !from_pos.is_neighbor_of(first))
+        {
+            nodes.reverse();
+        }
+        let (before, after) = match nodes
+            .iter()
+            .position(|n| *n == node)
+        {
+            Some(i) => {
+                (
+                    nodes[..i].to_vec(),
+                    nodes[i + 1..].to_vec(),
+                )
+            },
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let temp_map = self.clone();
+        for (start, end, segment) in [
+            (edge.get_from(), station_id, before),
+            (station_id, edge.get_to(), after),
+        ] {
+            for new_edge_id in self.get_edge_ids_between(start, end) {
+                let new_edge = self
+                    .get_mut_edge(new_edge_id)
+                    .expect("edge id was taken from the map");
+
+                if segment.is_empty() {
+                    new_edge.calculate_nodes(&temp_map);
+                    continue;
+                }
+
+                if new_edge.is_from(start) {
+                    new_edge.set_nodes(segment.clone());
+                } else {
+                    new_edge.set_nodes(
+                        segment
+                            .iter()
+                            .rev()
+                            .copied()
+                            .collect(),
+                    );
+                }
+            }
+        }
+
+        Ok(station_id)
+    }
+
+    /// Insert a new station at the middle node of the given edge, splitting
+    /// it in two as in [`Map::split_edge`]. If the edge does not visit any
+    /// nodes between its stations, the station is inserted halfway between
+    /// them. Returns the id of the new station.
+    pub fn insert_station_at_edge_midpoint(&mut self, edge_id: EdgeID) -> Result<StationID> {
+        let edge = self
+            .get_edge(edge_id)
+            .ok_or(Error::other(format!(
+                "Edge {edge_id} to split not found."
+            )))?;
+
+        let midpoint = if let Some(node) = edge
+            .get_nodes()
+            .get(
+                edge.get_nodes()
+                    .len()
+                    / 2,
+            ) {
+            *node
+        } else {
+            let (Some(from), Some(to)) = (
+                self.get_station(edge.get_from()),
+                self.get_station(edge.get_to()),
+            ) else {
+                return Err(Error::other(
+                    "Edge to split has an invalid station.",
+                ));
+            };
+            from.get_pos()
+                .interpolate(to.get_pos(), 0.5)
+        };
+
+        self.split_edge(edge_id, midpoint)
+    }
+
     /// Update the locations of stations and the edges from the ones in the
     /// partial map.
     pub fn update_from_partial(&mut self, other: &Map) -> Result<()> {
@@ -332,7 +1230,7 @@ impl Map {
 
         for edge in other.get_edges() {
             let own_id = self
-                .get_edge_id_between_if_exists(edge.get_from(), edge.get_to())
+                .find_matching_edge(edge)
                 .ok_or(Error::other(format!(
                     "Edge going from {} to {} in partial not found in full map.\nEdges of {}: {:?}\nEdges of {}: {:?}",
                     edge.get_from(),
@@ -357,6 +1255,29 @@ impl Map {
         Ok(())
     }
 
+    /// Find the [`Edge`] in this map that corresponds to the given edge from
+    /// another version of the map. Edges with the same id are preferred,
+    /// then an edge between the same stations that carries the same lines.
+    fn find_matching_edge(&self, edge: &Edge) -> Option<EdgeID> {
+        if self
+            .get_edge(edge.get_id())
+            .is_some_and(|e| e.get_from() == edge.get_from() && e.get_to() == edge.get_to())
+        {
+            return Some(edge.get_id());
+        }
+
+        let edge_ids = self.get_edge_ids_between(edge.get_from(), edge.get_to());
+
+        edge_ids
+            .iter()
+            .find(|id| {
+                self.get_edge(**id)
+                    .is_some_and(|e| e.get_lines() == edge.get_lines())
+            })
+            .or(edge_ids.first())
+            .copied()
+    }
+
     /// Get all nodes occupied by stations and edges.
     pub fn get_occupied_nodes(&self) -> OccupiedNodes {
         let mut occupied = OccupiedNodes::new();
@@ -409,33 +1330,473 @@ impl Map {
         occupied
     }
 
-    /// Draw the map to the given canvas.
-    pub fn draw(&self, canvas: &CanvasContext<'_>, state: CanvasState, base_alpha: f64) {
-        for edge in self.get_edges() {
-            edge.draw(self, canvas, state, base_alpha);
-        }
-
-        for line in self.get_lines() {
-            line.draw(self, canvas, state, base_alpha);
+    /// Lock all stations and edges on the map, so the algorithm leaves the
+    /// whole layout in place.
+    pub fn lock_all(&mut self) {
+        for station in self.get_mut_stations() {
+            station.lock();
         }
-
-        for station in self.get_stations() {
-            station.draw(canvas, state, base_alpha);
+        for edge in self.get_mut_edges() {
+            edge.lock();
         }
     }
 
-    /// Return the map with all checkpoints removed.
-    pub fn without_checkpoints(&self) -> Self {
-        let mut map = self.clone();
-        for station in self.get_stations() {
-            if station.is_checkpoint() {
-                map.remove_station(station.get_id());
-            }
+    /// Unlock all stations and edges on the map.
+    pub fn unlock_all(&mut self) {
+        for station in self.get_mut_stations() {
+            station.unlock();
+        }
+        for edge in self.get_mut_edges() {
+            edge.unlock();
         }
-        map
     }
 
-    /// Use the A* algorithm to calculate the edges between all stations
+    /// Get the ids of all edges whose nodes were edited by hand, but that are
+    /// not locked and so would be rerouted by recalculating the map.
+    pub fn get_unlocked_manual_edges(&self) -> Vec<EdgeID> {
+        self.get_edges()
+            .into_iter()
+            .filter(|edge| edge.is_manually_edited() && !edge.is_locked())
+            .map(Edge::get_id)
+            .collect()
+    }
+
+    /// Lock all edges whose nodes were edited by hand, so recalculating the
+    /// map keeps them in place.
+    pub fn lock_manual_edges(&mut self) {
+        for edge_id in self.get_unlocked_manual_edges() {
+            if let Some(edge) = self.get_mut_edge(edge_id) {
+                edge.lock();
+            }
+        }
+    }
+
+    /// Find all segments of edges with routed geometry that are not aligned
+    /// octilinearly with the grid, returning the edge and the start and end of
+    /// the segment. Both the nodes of the edge and the stations at its ends
+    /// are checked, ordered by edge id.
+    pub fn find_non_octilinear_segments(&self) -> Vec<(EdgeID, GridNode, GridNode)> {
+        self.edges
+            .values()
+            .filter(|edge| {
+                !edge
+                    .get_nodes()
+                    .is_empty()
+            })
+            .sorted_by_key(|edge| edge.get_id())
+            .flat_map(|edge| {
+                let from = self
+                    .get_station(edge.get_from())
+                    .map(Station::get_pos);
+                let to = self
+                    .get_station(edge.get_to())
+                    .map(Station::get_pos);
+
+                from.into_iter()
+                    .chain(
+                        edge.get_nodes()
+                            .iter()
+                            .copied(),
+                    )
+                    .chain(to)
+                    .tuple_windows()
+                    .filter(|(start, end)| octilinear_direction(*start, *end).is_none())
+                    .map(|(start, end)| (edge.get_id(), start, end))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Find all pairs of edges that visit the same grid node without sharing a
+    /// station, meaning they are drawn on top of each other. Every pair is
+    /// ordered by id and only included once.
+    pub fn find_overlapping_edges(&self) -> Vec<(EdgeID, EdgeID)> {
+        let mut visited_by: HashMap<GridNode, Vec<&Edge>> = HashMap::new();
+        for edge in self
+            .edges
+            .values()
+        {
+            for node in edge.get_nodes() {
+                visited_by
+                    .entry(*node)
+                    .or_default()
+                    .push(edge);
+            }
+        }
+
+        let shares_station = |a: &Edge, b: &Edge| {
+            a.is_from(b.get_from())
+                || a.is_from(b.get_to())
+                || a.is_to(b.get_from())
+                || a.is_to(b.get_to())
+        };
+
+        visited_by
+            .values()
+            .filter(|edges| edges.len() > 1)
+            .flat_map(|edges| {
+                edges
+                    .iter()
+                    .tuple_combinations()
+                    .filter(|(a, b)| a.get_id() != b.get_id() && !shares_station(a, b))
+                    .map(|(a, b)| {
+                        (
+                            a.get_id()
+                                .min(b.get_id()),
+                            a.get_id()
+                                .max(b.get_id()),
+                        )
+                    })
+            })
+            .unique()
+            .sorted()
+            .collect()
+    }
+
+    /// Get the lowest and highest grid coordinates used by the map, as the
+    /// top-left and bottom-right corner of the area containing all stations
+    /// and the nodes of all edges. Returns [`None`] if the map has no
+    /// stations.
+    pub fn bounds(&self) -> Option<(GridNode, GridNode)> {
+        self.stations
+            .values()
+            .map(Station::get_pos)
+            .chain(
+                self.edges
+                    .values()
+                    .flat_map(|e| {
+                        e.get_nodes()
+                            .iter()
+                            .copied()
+                    }),
+            )
+            .fold(None, |bounds, node| {
+                let (min, max) = bounds.unwrap_or((node, node));
+                Some((
+                    GridNode(
+                        min.0
+                            .min(node.0),
+                        min.1
+                            .min(node.1),
+                    ),
+                    GridNode(
+                        max.0
+                            .max(node.0),
+                        max.1
+                            .max(node.1),
+                    ),
+                ))
+            })
+    }
+
+    /// The total amount of bends in all edges of the map, on the grid the map
+    /// is routed on.
+    pub fn total_bend_count(&self) -> usize {
+        self.edges
+            .values()
+            .map(|edge| edge.bend_count(self.grid_mode))
+            .sum()
+    }
+
+    /// The total cost of the bends in all edges of the map, on the grid the map
+    /// is routed on.
+    pub fn total_bend_cost(&self) -> f64 {
+        self.edges
+            .values()
+            .map(|edge| edge.total_bend_cost(self.grid_mode))
+            .sum()
+    }
+
+    /// Get how far every station moved away from its original position, as
+    /// the pairs of station id and displacement. The stations that moved the
+    /// most come first. Checkpoints are left out.
+    pub fn station_displacements(&self) -> Vec<(StationID, f64)> {
+        self.stations
+            .values()
+            .filter(|s| !s.is_checkpoint())
+            .map(|s| {
+                (
+                    s.get_id(),
+                    s.get_pos()
+                        .diagonal_distance_to(s.get_original_pos()),
+                )
+            })
+            .sorted_by(|a, b| {
+                b.1.total_cmp(&a.1)
+                    .then(a.0.cmp(&b.0))
+            })
+            .collect()
+    }
+
+    /// Score the layout of the map on its bends, edge length, crossings and
+    /// station displacement. Crossings are the pairs of edges found by
+    /// [`Map::find_overlapping_edges`].
+    pub fn layout_score(&self) -> LayoutScore {
+        LayoutScore {
+            bend_count: self.total_bend_count(),
+            edge_length: self
+                .edges
+                .values()
+                .map(|edge| edge.routed_length(self))
+                .sum(),
+            crossing_count: self
+                .find_overlapping_edges()
+                .len(),
+            displacement: self
+                .station_displacements()
+                .into_iter()
+                .map(|(_, displacement)| displacement)
+                .sum(),
+        }
+    }
+
+    /// Assign a dash pattern to every line for the color-blind-safe rendering
+    /// mode. The lines are ordered by the hue of their color before the
+    /// patterns are handed out in turn, so lines with a similar color get
+    /// different patterns.
+    pub fn get_line_dash_patterns(&self) -> HashMap<LineID, &'static [u8]> {
+        self.lines
+            .values()
+            .sorted_by(|a, b| {
+                color_hue(a.get_color())
+                    .total_cmp(&color_hue(b.get_color()))
+                    .then(
+                        a.get_id()
+                            .cmp(&b.get_id()),
+                    )
+            })
+            .enumerate()
+            .map(|(i, line)| {
+                (
+                    line.get_id(),
+                    DASH_PATTERNS[i % DASH_PATTERNS.len()],
+                )
+            })
+            .collect()
+    }
+
+    /// Draw the map to the given canvas.
+    pub fn draw(&self, canvas: &CanvasContext<'_>, state: CanvasState, base_alpha: f64) {
+        self.draw_chunk(
+            canvas,
+            state,
+            base_alpha,
+            &DrawCache::new(state),
+            0,
+            usize::MAX,
+        );
+    }
+
+    /// Draw a single chunk of the map to the given canvas. The edges, lines,
+    /// interchanges and stations are split into chunks of the given size in the
+    /// order they are drawn in, and the endcaps are drawn with the last chunk.
+    /// Returns if there are chunks left to draw after the given one. The same
+    /// cache is expected to be given for every chunk of the map.
+    pub fn draw_chunk(
+        &self,
+        canvas: &CanvasContext<'_>,
+        state: CanvasState,
+        base_alpha: f64,
+        cache: &DrawCache,
+        chunk: usize,
+        chunk_size: usize,
+    ) -> bool {
+        let order = cache.draw_order(self);
+        let edges = &order.edges;
+        let lines = &order.lines;
+        let stations = &order.stations;
+
+        let total = edges.len()
+            + lines.len()
+            + self
+                .interchanges
+                .len()
+            + stations.len();
+        let start = chunk.saturating_mul(chunk_size);
+        let end = start
+            .saturating_add(chunk_size)
+            .min(total);
+
+        // The indices within a group of the given length of the objects in the
+        // chunk, given how many objects are drawn before the group.
+        let in_chunk = |before: usize, len: usize| {
+            start
+                .saturating_sub(before)
+                .min(len)
+                ..end
+                    .saturating_sub(before)
+                    .min(len)
+        };
+        let mut before = 0;
+
+        // The halos of all edges are drawn with the first chunk, so none of
+        // them are drawn over the lines of another edge.
+        if chunk == 0 && state.is_halo_shown() {
+            for edge in edges
+                .iter()
+                .filter_map(|id| self.get_edge(*id))
+            {
+                edge.draw_halo(self, canvas, state, base_alpha);
+            }
+        }
+
+        for edge in edges[in_chunk(before, edges.len())]
+            .iter()
+            .filter_map(|id| self.get_edge(*id))
+        {
+            edge.draw(self, canvas, state, base_alpha, cache);
+        }
+        before += edges.len();
+
+        for line in lines[in_chunk(before, lines.len())]
+            .iter()
+            .filter_map(|id| self.get_line(*id))
+        {
+            line.draw(self, canvas, state, base_alpha);
+        }
+        before += lines.len();
+
+        for interchange in &self.interchanges[in_chunk(
+            before,
+            self.interchanges
+                .len(),
+        )] {
+            interchange.draw(self, canvas, state, base_alpha);
+        }
+        before += self
+            .interchanges
+            .len();
+
+        for station in stations[in_chunk(before, stations.len())]
+            .iter()
+            .filter_map(|id| self.get_station(*id))
+        {
+            station.draw(self, canvas, state, base_alpha, cache);
+        }
+
+        if end < total {
+            return true;
+        }
+
+        if state.is_endcaps_shown() {
+            draw_endcaps(canvas, self, state, base_alpha);
+        }
+        false
+    }
+
+    /// Return the map with all checkpoints removed.
+    pub fn without_checkpoints(&self) -> Self {
+        let mut map = self.clone();
+        for station in self.get_stations() {
+            if station.is_checkpoint() {
+                map.remove_station(station.get_id());
+            }
+        }
+        map
+    }
+
+    /// Scale the positions of all stations and background shapes by the given
+    /// factor, which keeps the layout of the map the same on the canvas when
+    /// the size of the grid squares changes. A station that would end up on
+    /// the same node as another is moved to the closest free node instead.
+    /// Afterwards all edges are recalculated.
+    pub fn rescale_grid(&mut self, factor: f64) {
+        let scale = |node: GridNode| {
+            GridNode::new(
+                (f64::from(node.0) * factor).round() as i32,
+                (f64::from(node.1) * factor).round() as i32,
+            )
+        };
+
+        self.resolve_overlaps(scale);
+        for station in self.get_mut_stations() {
+            station.set_original_pos(scale(station.get_original_pos()));
+        }
+        for (outline, _) in &mut self.background {
+            for node in outline {
+                *node = scale(*node);
+            }
+        }
+        self.grid_transform = self
+            .grid_transform
+            .map(|transform| transform.rescaled(factor));
+
+        let temp_map = self.clone();
+        for edge in self
+            .edges
+            .values_mut()
+        {
+            edge.calculate_nodes(&temp_map);
+        }
+    }
+
+    /// Move every station to the node given by the mapping of its current
+    /// position. A station that would end up on the same node as another is
+    /// moved to the closest free node instead, with locked stations claiming
+    /// their node first. Returns the stations whose position changed.
+    fn resolve_overlaps(&mut self, target_of: impl Fn(GridNode) -> GridNode) -> Vec<StationID> {
+        let mut taken = HashSet::new();
+        let mut moved = Vec::new();
+        for id in self
+            .stations
+            .values()
+            .sorted_by_key(|station| (!station.is_locked(), station.get_id()))
+            .map(Station::get_id)
+            .collect::<Vec<_>>()
+        {
+            let station = self
+                .stations
+                .get_mut(&id)
+                .expect("station id was taken from the map");
+
+            let target = target_of(station.get_pos());
+            let mut visited = HashSet::from([target]);
+            let mut queue = VecDeque::from([target]);
+            let mut pos = target;
+            while let Some(node) = queue.pop_front() {
+                if !taken.contains(&node) {
+                    pos = node;
+                    break;
+                }
+
+                for neighbor in node.get_neighbors() {
+                    if visited.insert(neighbor) {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+
+            taken.insert(pos);
+            if pos != station.get_pos() {
+                moved.push(id);
+            }
+            station.set_pos(pos);
+        }
+        moved
+    }
+
+    /// Snap every station to the nearest grid intersection. Positions rounded
+    /// onto the grid, for example when importing a map, can leave several
+    /// stations on the same node, so those are spread out to the closest free
+    /// nodes and the unlocked edges attached to a moved station are
+    /// recalculated.
+    pub fn snap_to_grid(&mut self) {
+        let moved: HashSet<_> = self
+            .resolve_overlaps(|node| node)
+            .into_iter()
+            .collect();
+
+        let temp_map = self.clone();
+        for edge in self.get_mut_edges() {
+            if !edge.is_locked()
+                && (moved.contains(&edge.get_from()) || moved.contains(&edge.get_to()))
+            {
+                edge.calculate_nodes(&temp_map);
+            }
+        }
+    }
+
+    /// Use the A* algorithm to calculate the edges between all stations
     /// quickly.
     pub fn quickcalc_edges(&mut self) {
         let temp_map = self.clone();
@@ -446,3 +1807,1234 @@ impl Map {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RoutePattern;
+
+    #[test]
+    fn test_bounds() {
+        let mut map = Map::new();
+        assert_eq!(map.bounds(), None);
+
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        map.add_station(Station::new(
+            (2, 3).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (6, -1).into(),
+            Some(station2),
+        ));
+        assert_eq!(
+            map.bounds(),
+            Some((GridNode(2, -1), GridNode(6, 3)))
+        );
+
+        // An edge bulging outside of the stations is included.
+        let edge = map.get_edge_id_between(station1, station2);
+        map.get_mut_edge(edge)
+            .unwrap()
+            .set_nodes(vec![
+                (2, 4).into(),
+                (3, 5).into(),
+                (7, 1).into(),
+            ]);
+        assert_eq!(
+            map.bounds(),
+            Some((GridNode(2, -1), GridNode(7, 5)))
+        );
+    }
+
+    #[test]
+    fn test_station_displacements() {
+        let mut map = Map::new();
+        assert!(
+            map.station_displacements()
+                .is_empty()
+        );
+
+        let still: StationID = 1.into();
+        let moved: StationID = 2.into();
+        let far: StationID = 3.into();
+        map.add_station(Station::new((0, 0).into(), Some(still)));
+        map.add_station(Station::new((5, 0).into(), Some(moved)));
+        map.add_station(Station::new((10, 0).into(), Some(far)));
+
+        map.get_mut_station(moved)
+            .unwrap()
+            .set_pos((7, 0).into());
+        map.get_mut_station(far)
+            .unwrap()
+            .set_pos((10, 6).into());
+
+        let displacements = map.station_displacements();
+        assert_eq!(
+            displacements
+                .iter()
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>(),
+            vec![far, moved, still]
+        );
+        assert_eq!(displacements[1].1, 2.0);
+        assert_eq!(displacements[2].1, 0.0);
+    }
+
+    #[test]
+    fn test_insert_station_at_edge_midpoint() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (6, 0).into(),
+            Some(station2),
+        ));
+
+        let mut line = Line::new(Some(1.into()));
+        line.add_station(&mut map, station1, None, None);
+        line.add_station(&mut map, station2, Some(station1), None);
+        map.add_line(line);
+        map.quickcalc_edges();
+
+        let edge_id = map
+            .get_edge_id_between_if_exists(station1, station2)
+            .unwrap();
+        let new_station = map
+            .insert_station_at_edge_midpoint(edge_id)
+            .unwrap();
+
+        assert_eq!(
+            map.get_station(new_station)
+                .unwrap()
+                .get_pos(),
+            GridNode::from((3, 0))
+        );
+        assert!(
+            map.get_edge(edge_id)
+                .is_none()
+        );
+
+        for (start, end) in [
+            (station1, new_station),
+            (new_station, station2),
+        ] {
+            let edge = map
+                .get_edge(
+                    map.get_edge_id_between_if_exists(start, end)
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(
+                edge.get_nodes()
+                    .len(),
+                2
+            );
+            assert_eq!(edge.get_lines(), &[1.into()]);
+        }
+        assert_eq!(
+            map.get_line(1.into())
+                .unwrap()
+                .get_stations()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_insert_station_at_short_edge() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (1, 1).into(),
+            Some(station2),
+        ));
+
+        let mut line = Line::new(None);
+        line.add_station(&mut map, station1, None, None);
+        line.add_station(&mut map, station2, Some(station1), None);
+        map.add_line(line);
+
+        let edge_id = map
+            .get_edge_id_between_if_exists(station1, station2)
+            .unwrap();
+        assert!(
+            map.insert_station_at_edge_midpoint(edge_id)
+                .is_err()
+        );
+        assert_eq!(
+            map.get_stations()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_get_next_station_id() {
+        let mut map = Map::new();
+        assert_eq!(
+            map.get_next_station_id(None, false),
+            None
+        );
+
+        for id in [3u64, 1, 2] {
+            map.add_station(Station::new(
+                (id as i32, 0).into(),
+                Some(id.into()),
+            ));
+        }
+
+        assert_eq!(
+            map.get_next_station_id(None, false),
+            Some(1.into())
+        );
+        assert_eq!(
+            map.get_next_station_id(None, true),
+            Some(3.into())
+        );
+        assert_eq!(
+            map.get_next_station_id(Some(1.into()), false),
+            Some(2.into())
+        );
+        assert_eq!(
+            map.get_next_station_id(Some(3.into()), false),
+            Some(1.into())
+        );
+        assert_eq!(
+            map.get_next_station_id(Some(1.into()), true),
+            Some(3.into())
+        );
+        assert_eq!(
+            map.get_next_station_id(Some(8.into()), false),
+            Some(1.into())
+        );
+    }
+
+    #[test]
+    fn test_get_stations_and_edges_sorted() {
+        let mut map = Map::new();
+        for id in [5u64, 3, 4, 1, 2] {
+            map.add_station(Station::new(
+                (id as i32, 0).into(),
+                Some(id.into()),
+            ));
+        }
+        for (from, to) in [(4u64, 5u64), (1, 2), (3, 4), (2, 3)] {
+            map.get_edge_id_between(from.into(), to.into());
+        }
+
+        let station_ids = map
+            .get_stations_sorted()
+            .into_iter()
+            .map(Station::get_id)
+            .collect::<Vec<_>>();
+        assert!(station_ids.is_sorted());
+        assert_eq!(station_ids.len(), 5);
+
+        let edge_ids = map
+            .get_edges_sorted()
+            .into_iter()
+            .map(Edge::get_id)
+            .collect::<Vec<_>>();
+        assert!(edge_ids.is_sorted());
+        assert_eq!(edge_ids.len(), 4);
+    }
+
+    #[test]
+    fn test_get_max_station_weight() {
+        let mut map = Map::new();
+        let mut station = Station::new((0, 0).into(), None);
+        map.add_station(station.clone());
+        assert_eq!(map.get_max_station_weight(), None);
+
+        station.set_weight(Some(120.0));
+        map.add_station(station);
+        let mut other = Station::new((1, 0).into(), None);
+        other.set_weight(Some(30.5));
+        map.add_station(other);
+
+        assert_eq!(
+            map.get_max_station_weight(),
+            Some(120.0)
+        );
+    }
+
+    #[test]
+    fn test_find_overlapping_edges() {
+        let mut map = Map::new();
+        for (id, pos) in [
+            (1, (0, 2)),
+            (2, (4, 2)),
+            (3, (2, 0)),
+            (4, (2, 4)),
+            (5, (4, 0)),
+        ] {
+            map.add_station(Station::new(
+                pos.into(),
+                Some(id.into()),
+            ));
+        }
+
+        let mut horizontal = Edge::new(1.into(), 2.into(), Some(1.into()));
+        horizontal.set_nodes(vec![
+            (1, 2).into(),
+            (2, 2).into(),
+            (3, 2).into(),
+        ]);
+        let mut vertical = Edge::new(3.into(), 4.into(), Some(2.into()));
+        vertical.set_nodes(vec![
+            (2, 1).into(),
+            (2, 2).into(),
+            (2, 3).into(),
+        ]);
+        let mut connected = Edge::new(2.into(), 5.into(), Some(3.into()));
+        connected.set_nodes(vec![(3, 2).into(), (4, 1).into()]);
+        map.add_edge(horizontal);
+        map.add_edge(vertical);
+        map.add_edge(connected);
+
+        assert_eq!(
+            map.find_overlapping_edges(),
+            vec![(1.into(), 2.into())]
+        );
+    }
+
+    #[test]
+    fn test_layout_score() {
+        let mut map = Map::new();
+        assert_eq!(
+            map.layout_score(),
+            LayoutScore::default()
+        );
+
+        for (id, pos) in [
+            (1, (0, 2)),
+            (2, (4, 2)),
+            (3, (2, 0)),
+            (4, (2, 4)),
+            (5, (6, 3)),
+        ] {
+            map.add_station(Station::new(
+                pos.into(),
+                Some(id.into()),
+            ));
+        }
+        map.get_mut_station(5.into())
+            .unwrap()
+            .set_pos((6, 0).into());
+
+        let mut horizontal = Edge::new(1.into(), 2.into(), Some(1.into()));
+        horizontal.set_nodes(vec![
+            (1, 2).into(),
+            (2, 2).into(),
+            (3, 2).into(),
+        ]);
+        let mut vertical = Edge::new(3.into(), 4.into(), Some(2.into()));
+        vertical.set_nodes(vec![
+            (2, 1).into(),
+            (2, 2).into(),
+            (2, 3).into(),
+        ]);
+        let mut bent = Edge::new(2.into(), 5.into(), Some(3.into()));
+        bent.set_nodes(vec![
+            (4, 1).into(),
+            (4, 0).into(),
+            (5, 0).into(),
+        ]);
+        map.add_edge(horizontal);
+        map.add_edge(vertical);
+        map.add_edge(bent);
+
+        let score = map.layout_score();
+        assert_eq!(
+            score,
+            LayoutScore {
+                bend_count: 1,
+                edge_length: 12.0,
+                crossing_count: 1,
+                displacement: 3.0,
+            }
+        );
+        assert!((score.weighted(LayoutWeights::default()) - 8.7).abs() < 1e-9);
+        assert_eq!(
+            score.weighted(LayoutWeights {
+                bends: 0.0,
+                length: 0.0,
+                crossings: 1.0,
+                displacement: 0.0,
+            }),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_total_bend_cost_grid_mode() {
+        let mut map = Map::new();
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(1.into()),
+        ));
+        map.add_station(Station::new(
+            (1, 2).into(),
+            Some(2.into()),
+        ));
+
+        let mut edge = Edge::new(1.into(), 2.into(), Some(1.into()));
+        edge.set_nodes(vec![
+            (0, 0).into(),
+            (1, 0).into(),
+            (1, 1).into(),
+        ]);
+        map.add_edge(edge);
+
+        assert_eq!(map.get_grid_mode(), GridMode::Octilinear);
+        assert_eq!(map.total_bend_count(), 1);
+        assert_eq!(map.total_bend_cost(), 2.5);
+
+        // The same bend is a wide one of 120 degrees on the hexagonal grid.
+        map.set_grid_mode(GridMode::Hexagonal);
+        assert_eq!(map.total_bend_count(), 1);
+        assert_eq!(map.total_bend_cost(), 1.0);
+        assert_eq!(
+            map.layout_score()
+                .bend_count,
+            1
+        );
+    }
+
+    #[test]
+    fn test_find_non_octilinear_segments() {
+        let mut map = Map::new();
+        for (id, pos) in [(1, (0, 0)), (2, (6, 2)), (3, (0, 4))] {
+            map.add_station(Station::new(
+                pos.into(),
+                Some(id.into()),
+            ));
+        }
+
+        let mut aligned = Edge::new(1.into(), 2.into(), Some(1.into()));
+        aligned.set_nodes(vec![
+            (1, 1).into(),
+            (2, 2).into(),
+            (5, 2).into(),
+        ]);
+        let mut skewed = Edge::new(2.into(), 3.into(), Some(2.into()));
+        skewed.set_nodes(vec![(5, 3).into(), (2, 4).into()]);
+        // Edges without routed geometry are not checked.
+        let unrouted = Edge::new(1.into(), 3.into(), Some(3.into()));
+        map.add_edge(aligned);
+        map.add_edge(skewed);
+        map.add_edge(unrouted);
+
+        assert_eq!(
+            map.find_non_octilinear_segments(),
+            vec![(2.into(), (5, 3).into(), (2, 4).into())]
+        );
+    }
+
+    #[test]
+    fn test_rescale_grid() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        let station3: StationID = 3.into();
+
+        map.add_station(Station::new(
+            (4, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (3, 0).into(),
+            Some(station2),
+        ));
+        map.add_station(Station::new(
+            (8, 6).into(),
+            Some(station3),
+        ));
+
+        let mut line = Line::new(None);
+        line.add_station(&mut map, station1, None, None);
+        line.add_station(&mut map, station3, Some(station1), None);
+        map.add_line(line);
+
+        map.rescale_grid(0.5);
+
+        let pos1 = map
+            .get_station(station1)
+            .unwrap()
+            .get_pos();
+        let pos2 = map
+            .get_station(station2)
+            .unwrap()
+            .get_pos();
+        assert_eq!(pos1, GridNode::from((2, 0)));
+        assert_ne!(pos2, pos1);
+        assert!(pos2.is_neighbor_of(&pos1));
+        assert_eq!(
+            map.get_station(station3)
+                .unwrap()
+                .get_pos(),
+            GridNode::from((4, 3))
+        );
+
+        let edge = map
+            .get_edge(
+                map.get_edge_id_between_if_exists(station1, station3)
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            edge.get_nodes()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_snap_to_grid() {
+        let state = CanvasState::new();
+        let square = state.drawn_square_size();
+
+        // Near-integer positions as they could come from an imported map,
+        // where the first three round onto the same grid node.
+        let mut map = Map::new();
+        for (id, (x, y)) in [
+            (2.98, 4.02),
+            (3.03, 3.96),
+            (3.2, 4.1),
+            (7.01, 6.99),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            map.add_station(Station::new(
+                GridNode::from_canvas_pos((x * square, y * square), state),
+                Some(StationID::from(id as u64 + 1)),
+            ));
+        }
+        let mut line = Line::new(None);
+        line.add_station(&mut map, 2.into(), None, None);
+        line.add_station(&mut map, 4.into(), Some(2.into()), None);
+        map.add_line(line);
+
+        map.snap_to_grid();
+
+        let positions = map
+            .get_stations()
+            .into_iter()
+            .map(Station::get_pos)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions
+                .iter()
+                .unique()
+                .count(),
+            positions.len()
+        );
+
+        let origin = GridNode::from_canvas_pos((0.0, 0.0), state);
+        let offset = |pos: GridNode| (pos.0 - origin.0, pos.1 - origin.1);
+        assert_eq!(
+            offset(
+                map.get_station(1.into())
+                    .unwrap()
+                    .get_pos()
+            ),
+            (3, 4)
+        );
+        for id in [2, 3] {
+            assert!(
+                map.get_station(id.into())
+                    .unwrap()
+                    .get_pos()
+                    .is_neighbor_of(&(3 + origin.0, 4 + origin.1).into())
+            );
+        }
+        assert_eq!(
+            offset(
+                map.get_station(4.into())
+                    .unwrap()
+                    .get_pos()
+            ),
+            (7, 7)
+        );
+
+        // The edge of the moved station is routed again.
+        let edge = map
+            .get_edge_id_between_if_exists(2.into(), 4.into())
+            .unwrap();
+        assert!(
+            !map.get_edge(edge)
+                .unwrap()
+                .get_nodes()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_get_line_dash_patterns() {
+        let mut map = Map::new();
+
+        let mut red = Line::new(Some(1.into()));
+        red.set_color((255, 0, 0));
+        let mut dark_red = Line::new(Some(2.into()));
+        dark_red.set_color((200, 10, 0));
+        let mut blue = Line::new(Some(3.into()));
+        blue.set_color((0, 0, 255));
+
+        map.add_line(blue);
+        map.add_line(dark_red);
+        map.add_line(red);
+
+        let patterns = map.get_line_dash_patterns();
+        assert_eq!(patterns.len(), 3);
+        assert_eq!(patterns[&1.into()], DASH_PATTERNS[0]);
+        assert_eq!(patterns[&2.into()], DASH_PATTERNS[1]);
+        assert_eq!(patterns[&3.into()], DASH_PATTERNS[2]);
+    }
+
+    #[test]
+    fn test_parallel_edges_for_different_lines() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+        let station3: StationID = 3.into();
+
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(station1),
+        ));
+        map.add_station(Station::new(
+            (4, 0).into(),
+            Some(station2),
+        ));
+        map.add_station(Station::new(
+            (2, 2).into(),
+            Some(station3),
+        ));
+
+        let mut express = Line::new(None);
+        express.add_station(&mut map, station1, None, None);
+        express.add_station(&mut map, station2, Some(station1), None);
+        map.add_line(express.clone());
+
+        let mut local = Line::new(None);
+        let local_edge = map.add_parallel_edge(station1, station2);
+        local.add_edge(local_edge, &mut map);
+        map.add_line(local.clone());
+
+        let express_edge = express.get_edges()[0];
+        assert_ne!(express_edge, local_edge);
+        assert_eq!(
+            map.get_edge_ids_between(station1, station2)
+                .len(),
+            2
+        );
+        assert_eq!(
+            map.get_line_edge_id_between(station1, station2, express.get_id()),
+            express_edge
+        );
+        assert_eq!(
+            map.get_line_edge_id_between(station2, station1, local.get_id()),
+            local_edge
+        );
+
+        // Reroute the local line via station 3, the express should be unaffected.
+        local.add_station(
+            &mut map,
+            station3,
+            Some(station1),
+            Some(station2),
+        );
+        map.add_line(local.clone());
+
+        assert!(
+            map.get_edge(local_edge)
+                .is_none()
+        );
+        assert_eq!(
+            map.get_edge_ids_between(station1, station2),
+            vec![express_edge]
+        );
+        assert_eq!(express.get_edges(), &[express_edge]);
+        assert_eq!(
+            map.get_edge(express_edge)
+                .unwrap()
+                .get_lines(),
+            &[express.get_id()]
+        );
+        assert_eq!(
+            local
+                .get_edges()
+                .len(),
+            2
+        );
+        assert!(
+            !local
+                .get_edges()
+                .contains(&express_edge)
+        );
+    }
+
+    #[test]
+    fn test_with_normalized_ids() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+        let json = r##"{
+            "stations": [
+                {"id": "s7", "name": "A", "x": 10.0, "y": 10.0},
+                {"id": "s9000", "name": "B", "x": 50.0, "y": 10.0},
+                {"id": "s300", "name": "C", "x": 50.0, "y": 50.0},
+                {"id": "s42", "name": "D", "x": 90.0, "y": 50.0}
+            ],
+            "lines": [
+                {"id": "l55", "name": "red", "color": "#FF0000",
+                 "patterns": [{"name": "express", "stations": ["s7", "s300"]}]},
+                {"id": "l8", "name": "blue", "color": "#0000FF"}
+            ],
+            "edges": [
+                {"source": "s7", "target": "s9000", "nodes": [], "lines": ["l55"]},
+                {"source": "s9000", "target": "s300", "nodes": [], "lines": ["l55", "l8"]},
+                {"source": "s300", "target": "s42", "nodes": [], "lines": ["l8"]}
+            ],
+            "interchanges": [{"source": "s7", "target": "s42"}]
+        }"##;
+        let mut map = crate::utils::json::decode_map(json, canvas).expect("failed to decode json");
+        let edge = map
+            .get_edge_id_between_if_exists(9000.into(), 300.into())
+            .unwrap();
+        map.get_mut_edge(edge)
+            .unwrap()
+            .add_contracted_station(42.into());
+
+        let normalized = map.with_normalized_ids();
+
+        assert_eq!(
+            normalized
+                .get_stations_sorted()
+                .into_iter()
+                .map(|s| (u64::from(s.get_id()), s.get_name()))
+                .collect::<Vec<_>>(),
+            vec![(1, "A"), (2, "D"), (3, "C"), (4, "B")]
+        );
+        assert_eq!(
+            normalized
+                .get_lines()
+                .into_iter()
+                .map(|l| u64::from(l.get_id()))
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            normalized
+                .get_edges_sorted()
+                .into_iter()
+                .map(|e| u64::from(e.get_id()))
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        // Every reference points at an object on the renumbered map.
+        for edge in normalized.get_edges() {
+            for station in [edge.get_from(), edge.get_to()] {
+                assert!(
+                    normalized
+                        .get_station(station)
+                        .unwrap()
+                        .get_edges()
+                        .contains(&edge.get_id())
+                );
+            }
+            for line in edge.get_lines() {
+                assert!(
+                    normalized
+                        .get_line(*line)
+                        .unwrap()
+                        .get_edges()
+                        .contains(&edge.get_id())
+                );
+            }
+        }
+        for line in normalized.get_lines() {
+            for station in line
+                .get_stations()
+                .iter()
+                .chain(
+                    line.get_patterns()
+                        .iter()
+                        .flat_map(RoutePattern::get_stations),
+                )
+            {
+                assert!(
+                    normalized
+                        .get_station(*station)
+                        .is_some()
+                );
+            }
+        }
+        let contracted = normalized
+            .get_edges()
+            .into_iter()
+            .flat_map(Edge::get_contracted_stations)
+            .map(|s| {
+                normalized
+                    .get_station(*s)
+                    .unwrap()
+                    .get_name()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(contracted, vec!["D"]);
+        let (first, second) = normalized.get_interchanges()[0].get_stations();
+        assert_eq!(
+            (
+                normalized
+                    .get_station(first)
+                    .unwrap()
+                    .get_name(),
+                normalized
+                    .get_station(second)
+                    .unwrap()
+                    .get_name()
+            ),
+            ("A", "D")
+        );
+
+        // The renumbered map has the same topology, also after a round trip
+        // through a file.
+        let topology = |map: &Map| {
+            map.get_edges()
+                .into_iter()
+                .map(|e| {
+                    (
+                        map.get_station(e.get_from())
+                            .unwrap()
+                            .get_name()
+                            .to_owned(),
+                        map.get_station(e.get_to())
+                            .unwrap()
+                            .get_name()
+                            .to_owned(),
+                        e.get_lines()
+                            .iter()
+                            .map(|l| {
+                                map.get_line(*l)
+                                    .unwrap()
+                                    .get_name()
+                                    .to_owned()
+                            })
+                            .sorted()
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        let encoded =
+            crate::utils::json::encode_map(&normalized, canvas).expect("failed to encode map");
+        let decoded =
+            crate::utils::json::decode_map(&encoded, canvas).expect("failed to decode map");
+        assert_eq!(topology(&normalized), topology(&map));
+        assert_eq!(topology(&decoded), topology(&map));
+        assert_eq!(
+            decoded
+                .get_lines()
+                .into_iter()
+                .map(|l| {
+                    l.get_patterns()
+                        .len()
+                })
+                .sum::<usize>(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_lines_through_station_and_on_edge() {
+        let test_file_content = std::fs::read_to_string("existing_maps/routing_test.json")
+            .expect("test data file does not exist");
+        let map = crate::utils::json::decode_map(&test_file_content, CanvasState::new())
+            .expect("failed to decode json");
+
+        let names = |lines: Vec<&Line>| {
+            lines
+                .into_iter()
+                .map(|l| {
+                    l.get_name()
+                        .to_string()
+                })
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(map.lines_through_station(5.into())),
+            vec!["lineU1", "lineU2", "lineU3"]
+        );
+        assert_eq!(
+            names(map.lines_through_station(1.into())),
+            vec!["lineU1"]
+        );
+        assert!(
+            map.lines_through_station(100.into())
+                .is_empty()
+        );
+
+        let edge = map
+            .get_edge_id_between_if_exists(5.into(), 9.into())
+            .unwrap();
+        assert_eq!(
+            names(map.lines_on_edge(edge)),
+            vec!["lineU1", "lineU3"]
+        );
+        let edge = map
+            .get_edge_id_between_if_exists(8.into(), 4.into())
+            .unwrap();
+        assert_eq!(
+            names(map.lines_on_edge(edge)),
+            vec!["lineU4"]
+        );
+    }
+
+    #[test]
+    fn test_corridor_edges() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (2, 0).into());
+        let c = map.add_named_station(&"C", (4, 0).into());
+        let d = map.add_named_station(&"D", (6, 0).into());
+        let e = map.add_named_station(&"E", (4, 2).into());
+        map.add_line_through((255, 0, 0), &"Red", &[a, b, c, d]);
+        map.add_line_through((0, 0, 255), &"Blue", &[a, b, c, e]);
+
+        let edge = |from, to| {
+            map.get_edge_id_between_if_exists(from, to)
+                .unwrap()
+        };
+
+        let mut corridor = map.corridor_edges(edge(a, b));
+        corridor.sort();
+        let mut expected = vec![edge(a, b), edge(b, c)];
+        expected.sort();
+        assert_eq!(corridor, expected);
+
+        assert_eq!(
+            map.corridor_edges(edge(c, d)),
+            vec![edge(c, d)]
+        );
+    }
+
+    #[test]
+    fn test_connected_component() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (2, 0).into());
+        let c = map.add_named_station(&"C", (4, 0).into());
+        let d = map.add_named_station(&"D", (0, 4).into());
+        let e = map.add_named_station(&"E", (2, 4).into());
+        map.add_line_through((255, 0, 0), &"Red", &[a, b, c]);
+        map.add_line_through((0, 0, 255), &"Blue", &[d, e]);
+
+        let (mut stations, edges) = map.connected_component(b);
+        stations.sort();
+        let mut expected = vec![a, b, c];
+        expected.sort();
+        assert_eq!(stations, expected);
+        assert_eq!(edges.len(), 2);
+
+        let (stations, edges) = map.connected_component(d);
+        assert_eq!(stations, vec![d, e]);
+        assert_eq!(
+            edges,
+            vec![
+                map.get_edge_id_between_if_exists(d, e)
+                    .unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lock_manual_edges() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (2, 0).into());
+        let c = map.add_named_station(&"C", (4, 0).into());
+        map.add_line_through((255, 0, 0), &"Red", &[a, b, c]);
+
+        let edited = map
+            .get_edge_id_between_if_exists(a, b)
+            .unwrap();
+        let routed = map
+            .get_edge_id_between_if_exists(b, c)
+            .unwrap();
+        map.get_mut_edge(edited)
+            .unwrap()
+            .mark_manually_edited();
+
+        assert_eq!(
+            map.get_unlocked_manual_edges(),
+            vec![edited]
+        );
+
+        map.lock_manual_edges();
+        assert!(
+            map.get_edge(edited)
+                .unwrap()
+                .is_locked()
+        );
+        assert!(
+            !map.get_edge(routed)
+                .unwrap()
+                .is_locked()
+        );
+        assert!(
+            map.get_unlocked_manual_edges()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_check_size() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (2, 0).into());
+        let c = map.add_named_station(&"C", (4, 0).into());
+        map.add_line_through((255, 0, 0), &"Red", &[a, b, c]);
+
+        assert!(map
+            .check_size(3)
+            .is_ok());
+        assert!(map
+            .check_size(0)
+            .is_ok());
+        assert_eq!(
+            map.check_size(2),
+            Err(Error::MapTooLarge {
+                stations: 3,
+                edges: 2,
+                limit: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_split_line() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (2, 0).into());
+        let c = map.add_named_station(&"C", (4, 0).into());
+        let d = map.add_named_station(&"D", (6, 0).into());
+        let e = map.add_named_station(&"E", (4, 2).into());
+        let red = map.add_line_through((255, 0, 0), &"Red", &[a, b, c, d]);
+        map.add_line_through((0, 0, 255), &"Blue", &[e, c, d]);
+        let shared = map
+            .get_edge_id_between_if_exists(c, d)
+            .unwrap();
+
+        assert!(
+            map.split_line(red, a)
+                .is_err()
+        );
+
+        let new = map
+            .split_line(red, b)
+            .unwrap();
+        let original = map
+            .get_line(red)
+            .unwrap();
+        let split = map
+            .get_line(new)
+            .unwrap();
+
+        let mut covered = original
+            .get_stations()
+            .iter()
+            .chain(split.get_stations())
+            .copied()
+            .collect::<Vec<_>>();
+        covered.sort();
+        covered.dedup();
+        let mut expected = vec![a, b, c, d];
+        expected.sort();
+        assert_eq!(covered, expected);
+
+        assert!(
+            original
+                .get_stations()
+                .contains(&b)
+        );
+        assert!(
+            split
+                .get_stations()
+                .contains(&b)
+        );
+        assert_eq!(split.get_name(), "Red");
+        assert_eq!(
+            original
+                .get_edges()
+                .len(),
+            1
+        );
+        assert_eq!(
+            split
+                .get_edges()
+                .len(),
+            2
+        );
+        assert!(original.is_continuous(&map));
+        assert!(split.is_continuous(&map));
+
+        // The shared edge now follows the new line instead of the original,
+        // but is kept for the other line.
+        let shared = map
+            .get_edge(shared)
+            .unwrap();
+        assert!(
+            shared
+                .get_lines()
+                .contains(&new)
+        );
+        assert!(
+            !shared
+                .get_lines()
+                .contains(&red)
+        );
+        assert_eq!(
+            shared
+                .get_lines()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_join_lines() {
+        let mut map = Map::new();
+        let a = map.add_named_station(&"A", (0, 0).into());
+        let b = map.add_named_station(&"B", (2, 0).into());
+        let c = map.add_named_station(&"C", (4, 0).into());
+        let d = map.add_named_station(&"D", (6, 2).into());
+        let red = map.add_line_through((255, 0, 0), &"Red", &[a, b]);
+        let blue = map.add_line_through((0, 0, 255), &"Blue", &[c, b]);
+        let green = map.add_line_through((0, 255, 0), &"Green", &[d]);
+
+        assert!(
+            map.join_lines(red, green)
+                .is_err()
+        );
+        assert!(
+            map.join_lines(red, red)
+                .is_err()
+        );
+
+        map.join_lines(red, blue)
+            .unwrap();
+        assert!(
+            map.get_line(blue)
+                .is_none()
+        );
+
+        let line = map
+            .get_line(red)
+            .unwrap();
+        assert_eq!(line.get_name(), "Red");
+        assert_eq!(line.get_color(), (255, 0, 0));
+        assert_eq!(
+            line.get_stations()
+                .len(),
+            3
+        );
+        assert_eq!(
+            line.get_edges()
+                .len(),
+            2
+        );
+        assert_eq!(
+            line.get_stations()[1],
+            b
+        );
+        assert!(line.is_continuous(&map));
+        for edge in map.get_edges() {
+            assert_eq!(edge.get_lines(), &[red]);
+        }
+
+        // Joining a line with a gap fails without changing either line.
+        let e = map.add_named_station(&"E", (8, 0).into());
+        let f = map.add_named_station(&"F", (10, 0).into());
+        let g = map.add_named_station(&"G", (12, 0).into());
+        let purple = map.add_line_through((255, 0, 255), &"Purple", &[c, e, f, g]);
+        let gap = map.get_edge_id_between(e, f);
+        map.remove_edge(gap);
+        assert!(
+            map.join_lines(red, purple)
+                .is_err()
+        );
+        assert_eq!(
+            map.get_line(red)
+                .unwrap()
+                .get_edges()
+                .len(),
+            2
+        );
+        assert_eq!(
+            map.get_edge(
+                map.get_edge_id_between_if_exists(c, e)
+                    .unwrap()
+            )
+            .unwrap()
+                .get_lines(),
+            &[purple]
+        );
+    }
+
+    #[test]
+    fn test_normalize_line_directions() {
+        let mut map = Map::new();
+        let east = map.add_named_station(&"East", (6, 0).into());
+        let center = map.add_named_station(&"Center", (3, 0).into());
+        let west = map.add_named_station(&"West", (0, 0).into());
+        let bottom = map.add_named_station(&"Bottom", (0, 8).into());
+        let top = map.add_named_station(&"Top", (0, 4).into());
+        let red = map.add_line_through(
+            (255, 0, 0),
+            &"Red",
+            &[east, center, west],
+        );
+        let blue = map.add_line_through((0, 0, 255), &"Blue", &[bottom, top]);
+
+        map.normalize_line_directions();
+        let red_line = map
+            .get_line(red)
+            .unwrap();
+        assert_eq!(
+            red_line.get_stations(),
+            &[west, center, east]
+        );
+        let blue_line = map
+            .get_line(blue)
+            .unwrap();
+        assert_eq!(blue_line.get_stations(), &[top, bottom]);
+
+        // Normalizing again changes nothing.
+        map.normalize_line_directions();
+        assert_eq!(
+            map.get_line(red)
+                .unwrap()
+                .get_stations(),
+            &[west, center, east]
+        );
+    }
+}
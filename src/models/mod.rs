@@ -2,6 +2,7 @@
 
 mod edge;
 mod grid_node;
+mod interchange;
 mod line;
 mod map;
 mod selected_line;
@@ -13,11 +14,20 @@ pub use edge::{
     EdgeID,
 };
 pub use grid_node::GridNode;
+pub use interchange::Interchange;
 pub use line::{
     Line,
     LineID,
+    LineStats,
+    RoutePattern,
+    LINE_PALETTE,
+};
+pub use map::{
+    LayoutScore,
+    LayoutWeights,
+    Map,
+    SearchRegion,
 };
-pub use map::Map;
 pub use selected_line::SelectedLine;
 pub use selected_station::SelectedStation;
 pub use station::{
@@ -12,6 +12,7 @@ use crate::{
         draw_edge,
         run_a_star,
         CanvasContext,
+        GridMode,
     },
     components::CanvasState,
 };
@@ -144,6 +145,8 @@ impl SelectedLine {
                 &run_a_star(
                     self.get_current_hover(),
                     before_station.get_pos(),
+                    GridMode::Octilinear,
+                    true,
                 ),
                 canvas,
                 state,
@@ -160,6 +163,8 @@ impl SelectedLine {
                 &run_a_star(
                     after_station.get_pos(),
                     self.get_current_hover(),
+                    GridMode::Octilinear,
+                    true,
                 ),
                 canvas,
                 state,
@@ -11,6 +11,8 @@ use crate::{
         draw_edge,
         run_a_star,
         CanvasContext,
+        DrawCache,
+        GridMode,
     },
     components::CanvasState,
 };
@@ -128,6 +130,7 @@ impl SelectedStation {
         canvas: &CanvasContext<'_>,
         state: CanvasState,
         all_selected: &[Self],
+        cache: &DrawCache,
     ) {
         let mut station = self
             .station
@@ -161,7 +164,7 @@ impl SelectedStation {
         }
 
         // draw station
-        station.draw(canvas, state, 0.5);
+        station.draw(map, canvas, state, 0.5, cache);
 
         // draw edges to adjacent stations
         let mut edge_width = state.drawn_square_size() / 10.0 + 0.5;
@@ -170,7 +173,11 @@ impl SelectedStation {
         }
 
         canvas.set_line_width(edge_width);
-        canvas.set_stroke_style_str("black");
+        canvas.set_stroke_style_str(
+            state
+                .get_theme()
+                .foreground(),
+        );
         canvas.begin_path();
 
         for before_id in self
@@ -194,7 +201,12 @@ impl SelectedStation {
             draw_edge(
                 before.get_pos(),
                 station.get_pos(),
-                &run_a_star(before.get_pos(), station.get_pos()),
+                &run_a_star(
+                    before.get_pos(),
+                    station.get_pos(),
+                    GridMode::Octilinear,
+                    true,
+                ),
                 canvas,
                 state,
                 0.0,
@@ -221,7 +233,12 @@ impl SelectedStation {
             draw_edge(
                 station.get_pos(),
                 after.get_pos(),
-                &run_a_star(station.get_pos(), after.get_pos()),
+                &run_a_star(
+                    station.get_pos(),
+                    after.get_pos(),
+                    GridMode::Octilinear,
+                    true,
+                ),
                 canvas,
                 state,
                 0.0,
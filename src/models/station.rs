@@ -12,6 +12,7 @@ use serde::{
 };
 
 use super::{
+    map::IDMapping,
     Edge,
     EdgeID,
     GridNode,
@@ -20,11 +21,24 @@ use super::{
 use crate::{
     algorithms::{
         calc_label_pos,
+        draw_upright_label,
+        pick_free_label_pos,
+        station_label_direction,
         CanvasContext,
+        DrawCache,
+        LabelObstacles,
     },
     components::CanvasState,
     utils::IDManager,
 };
+
+/// The radius of the station with the smallest weight, as a fraction of the
+/// default radius.
+const MIN_WEIGHTED_RADIUS: f64 = 0.6;
+/// The radius of the station with the largest weight, as a fraction of the
+/// default radius.
+const MAX_WEIGHTED_RADIUS: f64 = 1.6;
+
 /// An identifier for a station.
 #[derive(Clone, Debug, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -72,6 +86,13 @@ pub struct Station {
     /// The total cost of all the edges attached to the station, used in the
     /// local search algorithm.
     cost: f64,
+    /// The importance of the station, like its ridership, used to scale the
+    /// size of its marker.
+    weight: Option<f64>,
+    /// How far the label of the station is moved away from its automatically
+    /// placed position by the user, in grid squares.
+    #[serde(default)]
+    label_offset: (i32, i32),
 }
 
 impl Station {
@@ -92,6 +113,8 @@ impl Station {
             is_settled: false,
             is_checkpoint: false,
             cost: 0.0,
+            weight: None,
+            label_offset: (0, 0),
         }
     }
 
@@ -112,6 +135,8 @@ impl Station {
             is_settled: false,
             is_checkpoint: true,
             cost: 0.0,
+            weight: None,
+            label_offset: (0, 0),
         }
     }
 
@@ -121,6 +146,19 @@ impl Station {
         self.id
     }
 
+    /// Replace the id of the station and the ids of its edges with their new
+    /// ids in the given mapping.
+    pub(super) fn remap_ids(&mut self, mapping: &IDMapping) {
+        self.id = mapping
+            .station(self.id)
+            .unwrap_or(self.id);
+        self.edges = self
+            .edges
+            .iter()
+            .filter_map(|edge| mapping.edge(*edge))
+            .collect();
+    }
+
     /// A getter for the grid position.
     #[inline]
     pub fn get_pos(&self) -> GridNode {
@@ -216,6 +254,88 @@ impl Station {
         self.cost += cost;
     }
 
+    /// A getter for the weight of the station.
+    #[inline]
+    pub fn get_weight(&self) -> Option<f64> {
+        self.weight
+    }
+
+    /// A setter for the weight of the station.
+    pub fn set_weight(&mut self, weight: Option<f64>) {
+        self.weight = weight;
+    }
+
+    /// A getter for how far the label of the station is moved away from its
+    /// automatically placed position, in grid squares.
+    #[inline]
+    pub fn get_label_offset(&self) -> (i32, i32) {
+        self.label_offset
+    }
+
+    /// A setter for how far the label of the station is moved away from its
+    /// automatically placed position, in grid squares.
+    pub fn set_label_offset(&mut self, offset: (i32, i32)) {
+        self.label_offset = offset;
+    }
+
+    /// Get the position on the canvas the label of the station is drawn at,
+    /// which is the first position next to the station given by
+    /// [`calc_label_pos`] that is free of other stations and edges, moved by
+    /// the label offset of the station.
+    pub fn get_label_pos(&self, obstacles: &LabelObstacles, state: CanvasState) -> (f64, f64) {
+        let pos = pick_free_label_pos(
+            obstacles,
+            &calc_label_pos(
+                state,
+                self.get_canvas_pos(state),
+                None,
+                None,
+            ),
+        );
+        let square_size = state.drawn_square_size();
+
+        (
+            pos.0
+                + f64::from(
+                    self.label_offset
+                        .0,
+                ) * square_size,
+            pos.1
+                + f64::from(
+                    self.label_offset
+                        .1,
+                ) * square_size,
+        )
+    }
+
+    /// Get the radius of the station marker given the default radius. If
+    /// weighted station sizes are enabled and the station has a weight, the
+    /// radius is scaled by the square root of its weight relative to the
+    /// largest weight on the map, which is taken from the given cache.
+    fn get_radius(
+        &self,
+        map: &Map,
+        state: CanvasState,
+        cache: &DrawCache,
+        default_radius: f64,
+    ) -> f64 {
+        if !state.is_weighted_station_size() {
+            return default_radius;
+        }
+
+        match (
+            self.get_weight(),
+            cache.max_station_weight(map),
+        ) {
+            (Some(weight), Some(max)) => {
+                let fraction = (weight.max(0.0) / max).sqrt();
+                default_radius
+                    * (MIN_WEIGHTED_RADIUS + (MAX_WEIGHTED_RADIUS - MIN_WEIGHTED_RADIUS) * fraction)
+            },
+            _ => default_radius,
+        }
+    }
+
     /// Add an edge to the station.
     pub fn add_edge(&mut self, edge: EdgeID) {
         if self
@@ -269,8 +389,30 @@ impl Station {
         );
     }
 
-    /// Draw the station to the given canvas.
-    pub fn draw(&self, canvas: &CanvasContext<'_>, state: CanvasState, base_alpha: f64) {
+    /// Check if the station is a stop of the service pattern that is
+    /// displayed.
+    fn is_in_active_pattern(&self, map: &Map, state: CanvasState) -> bool {
+        state
+            .get_active_pattern()
+            .and_then(|(line, index)| {
+                map.get_line(line)?
+                    .get_patterns()
+                    .get(index)
+                    .map(|pattern| pattern.stops_at(self.get_id()))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Draw the station to the given canvas, with the values about the whole
+    /// map it needs taken from the given cache.
+    pub fn draw(
+        &self,
+        map: &Map,
+        canvas: &CanvasContext<'_>,
+        state: CanvasState,
+        base_alpha: f64,
+        cache: &DrawCache,
+    ) {
         if !state.is_on_canvas(self.get_pos()) {
             return;
         }
@@ -281,11 +423,20 @@ impl Station {
         if width < 2.0 {
             width = 2.0;
         }
-        let radius = state.drawn_square_size() / 3.0;
+        let radius = self.get_radius(
+            map,
+            state,
+            cache,
+            state.drawn_square_size() / 3.0,
+        );
 
         canvas.set_line_width(width);
         canvas.set_global_alpha(1.0 * base_alpha);
-        canvas.set_stroke_style_str("black");
+        canvas.set_stroke_style_str(
+            state
+                .get_theme()
+                .foreground(),
+        );
 
         if self.is_checkpoint() {
             canvas.begin_path();
@@ -310,13 +461,28 @@ impl Station {
                     2.0 * f64::consts::PI,
                 )
                 .unwrap();
+            if self.is_in_active_pattern(map, state) {
+                canvas.set_fill_style_str(
+                    state
+                        .get_theme()
+                        .foreground(),
+                );
+                canvas.fill();
+            }
             canvas.stroke();
         }
 
         if self.is_locked() {
-            let locked_label_pos = calc_label_pos(state, canvas_pos, None, None)[0]; // FIXME: Check for occupancy
+            let locked_label_pos = pick_free_label_pos(
+                cache.label_obstacles(map),
+                &calc_label_pos(state, canvas_pos, None, None),
+            );
 
-            canvas.set_stroke_style_str("grey");
+            canvas.set_stroke_style_str(
+                state
+                    .get_theme()
+                    .muted(),
+            );
             canvas.begin_path();
             canvas
                 .arc(
@@ -327,10 +493,52 @@ impl Station {
                     2.0 * f64::consts::PI,
                 )
                 .unwrap();
-            canvas.set_fill_style_str("grey");
+            canvas.set_fill_style_str(
+                state
+                    .get_theme()
+                    .muted(),
+            );
             canvas.fill();
             canvas.stroke();
         }
+
+        self.draw_label(map, canvas, cache, state);
+    }
+
+    /// Draw the name of the station at its label position, if labels are shown
+    /// and the station has a name.
+    fn draw_label(
+        &self,
+        map: &Map,
+        canvas: &CanvasContext<'_>,
+        cache: &DrawCache,
+        state: CanvasState,
+    ) {
+        if !state.is_labels_shown()
+            || self.is_checkpoint()
+            || self
+                .get_name()
+                .is_empty()
+        {
+            return;
+        }
+
+        let canvas_pos = self.get_canvas_pos(state);
+        let label_pos = self.get_label_pos(cache.label_obstacles(map), state);
+
+        canvas.set_font(&state.label_font_css());
+        canvas.set_fill_style_str(
+            state
+                .get_theme()
+                .foreground(),
+        );
+        draw_upright_label(
+            canvas,
+            self.get_name(),
+            label_pos,
+            canvas_pos,
+            station_label_direction(canvas_pos, label_pos),
+        );
     }
 }
 
@@ -0,0 +1,179 @@
+//! Contains the functionality for importing a [`Map`] from a simple
+//! adjacency-list text format. Every line of text describes a metro line,
+//! starting with its name followed by its stations in order, like
+//! `Red: A - B - C - D`. Stations with the same name are the same station, so
+//! they can be shared between lines as an interchange.
+
+use std::collections::HashMap;
+
+use super::Result;
+use crate::{
+    components::CanvasState,
+    models::{
+        GridNode,
+        Line,
+        Map,
+        Station,
+        StationID,
+    },
+    Error,
+};
+
+/// The amount of grid nodes between two stations in the default layout.
+const STATION_SPACING: i32 = 3;
+
+/// Split a line of text into the name of the metro line and the names of its
+/// stations.
+fn parse_line(text: &str, line_number: usize) -> Result<(&str, Vec<&str>)> {
+    let (name, stations) = text
+        .split_once(':')
+        .ok_or(Error::decode_error(format!(
+            "line {line_number} has no ':' between the line name and its stations"
+        )))?;
+
+    let stations = stations
+        .split(" - ")
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    if stations
+        .iter()
+        .any(|s| s.is_empty())
+    {
+        return Err(Error::decode_error(format!(
+            "line {line_number} contains an empty station name"
+        )));
+    }
+
+    Ok((name.trim(), stations))
+}
+
+/// Decode the given adjacency-list text into a [`Map`].
+/// Stations are laid out on a default grid within the visible part of the
+/// canvas, with the new stations of every metro line on their own row, for the
+/// algorithm to arrange afterwards.
+pub fn decode_map(input: &str, state: CanvasState) -> Result<Map> {
+    let mut map = Map::new();
+    let mut stations: HashMap<String, StationID> = HashMap::new();
+
+    let origin = state.get_offset();
+    let mut row = 0;
+
+    for (i, text) in input
+        .lines()
+        .enumerate()
+    {
+        if text
+            .trim()
+            .is_empty()
+        {
+            continue;
+        }
+
+        let (name, station_names) = parse_line(text, i + 1)?;
+
+        let mut line = Line::new(None);
+        line.set_name(&name);
+
+        let mut previous = None;
+        let mut added_station = false;
+        for (column, station_name) in station_names
+            .into_iter()
+            .enumerate()
+        {
+            let station_id = if let Some(id) = stations.get(station_name) {
+                *id
+            } else {
+                let pos = GridNode::new(
+                    origin.0 + STATION_SPACING * (column as i32 + 1),
+                    origin.1 + STATION_SPACING * (row + 1),
+                );
+                let mut station = Station::new(pos, None);
+                station.set_name(&station_name);
+
+                let id = station.get_id();
+                map.add_station(station);
+                stations.insert(station_name.to_owned(), id);
+                added_station = true;
+                id
+            };
+
+            line.add_station(&mut map, station_id, previous, None);
+            previous = Some(station_id);
+        }
+
+        map.add_line(line);
+        if added_station {
+            row += 1;
+        }
+    }
+
+    map.quickcalc_edges();
+
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line("Red: A - B - Saint-Denis", 1).unwrap(),
+            ("Red", vec!["A", "B", "Saint-Denis"])
+        );
+        assert!(parse_line("Red A - B", 1).is_err());
+        assert!(parse_line("Red: A -  - B", 1).is_err());
+    }
+
+    #[test]
+    fn test_decode_map() {
+        let input = "Red: A - B - C - D\n\n   \nBlue: E - B - F\n";
+        let map = decode_map(input, CanvasState::new()).unwrap();
+
+        assert_eq!(
+            map.get_lines()
+                .len(),
+            2
+        );
+        assert_eq!(
+            map.get_stations()
+                .len(),
+            6
+        );
+        assert_eq!(
+            map.get_edges()
+                .len(),
+            5
+        );
+
+        let interchange = map
+            .get_stations()
+            .into_iter()
+            .find(|s| s.get_name() == "B")
+            .unwrap();
+        assert_eq!(
+            interchange
+                .get_edges()
+                .len(),
+            4
+        );
+
+        let blue = map
+            .get_lines()
+            .into_iter()
+            .find(|l| l.get_name() == "Blue")
+            .unwrap();
+        assert_eq!(
+            blue.get_stations()
+                .len(),
+            3
+        );
+        assert_eq!(
+            blue.get_edges()
+                .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Contains the functionality for assembling a series of PNG images into a
+//! single animated PNG (APNG). The frames are expected to be compressed PNG
+//! files already, like the ones a canvas exports, so their image data only has
+//! to be moved into the chunks of the animation instead of being encoded again.
+
+use super::Result;
+use crate::Error;
+
+/// The signature every PNG file starts with.
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Calculate the CRC-32 checksum of the given data, as used at the end of
+/// every PNG chunk.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// Append a chunk of the given type and with the given data to the PNG file.
+fn write_chunk(out: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) {
+    let length = u32::try_from(data.len()).expect("PNG chunk is too large");
+    out.extend(length.to_be_bytes());
+
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// Split the given PNG file into its chunks, as their type and data.
+fn read_chunks(png: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    let mut rest = png
+        .strip_prefix(&PNG_SIGNATURE)
+        .ok_or(Error::other("frame is not a PNG image"))?;
+
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let truncated = || Error::other("frame contains a truncated PNG chunk");
+
+        let (length, after) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(truncated)?;
+        let (kind, after) = after
+            .split_first_chunk::<4>()
+            .ok_or_else(truncated)?;
+        let length = u32::from_be_bytes(*length) as usize;
+        if after.len() < length + 4 {
+            return Err(truncated());
+        }
+
+        chunks.push((*kind, &after[..length]));
+        // Skip the checksum after the data.
+        rest = &after[length + 4..];
+    }
+
+    Ok(chunks)
+}
+
+/// Assemble the given PNG images into an animated PNG that shows them in
+/// order at the given amount of frames per second, looping forever.
+///
+/// All frames need to have the same size and format. Returns an error if there
+/// are no frames, or if one of them is not a valid PNG image.
+pub fn encode_apng(frames: &[Vec<u8>], fps: u32) -> Result<Vec<u8>> {
+    let frames = frames
+        .iter()
+        .map(|frame| read_chunks(frame))
+        .collect::<Result<Vec<_>>>()?;
+
+    let header = frames
+        .first()
+        .ok_or(Error::other(
+            "there are no frames to animate",
+        ))?
+        .iter()
+        .find(|(kind, _)| kind == b"IHDR")
+        .map(|(_, data)| *data)
+        .filter(|data| data.len() == 13)
+        .ok_or(Error::other(
+            "frame has no valid PNG header",
+        ))?;
+
+    let frame_count = u32::try_from(frames.len()).expect("too many frames");
+    let delay_denominator = u16::try_from(fps.max(1)).unwrap_or(u16::MAX);
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut out, *b"IHDR", header);
+
+    let mut animation_control = frame_count
+        .to_be_bytes()
+        .to_vec();
+    // Loop forever.
+    animation_control.extend(0u32.to_be_bytes());
+    write_chunk(&mut out, *b"acTL", &animation_control);
+
+    // Keep the chunks describing the colors of the image, like the palette,
+    // which have to come before the image data.
+    for (kind, data) in frames[0]
+        .iter()
+        .take_while(|(kind, _)| kind != b"IDAT")
+        .filter(|(kind, _)| kind != b"IHDR")
+    {
+        write_chunk(&mut out, *kind, data);
+    }
+
+    let mut sequence = 0u32;
+    for (i, frame) in frames
+        .iter()
+        .enumerate()
+    {
+        if frame
+            .iter()
+            .find(|(kind, _)| kind == b"IHDR")
+            .is_none_or(|(_, data)| *data != header)
+        {
+            return Err(Error::other(format!(
+                "frame {i} has a different size or format than the first frame"
+            )));
+        }
+
+        // The frame covers the whole image, replacing the frame before it.
+        let mut frame_control = sequence
+            .to_be_bytes()
+            .to_vec();
+        frame_control.extend(&header[..8]);
+        frame_control.extend([0; 8]);
+        frame_control.extend(1u16.to_be_bytes());
+        frame_control.extend(delay_denominator.to_be_bytes());
+        frame_control.extend([0, 0]);
+        write_chunk(&mut out, *b"fcTL", &frame_control);
+        sequence += 1;
+
+        for (_, data) in frame
+            .iter()
+            .filter(|(kind, _)| kind == b"IDAT")
+        {
+            // The first frame doubles as the still image shown by viewers
+            // that do not support animations.
+            if i == 0 {
+                write_chunk(&mut out, *b"IDAT", data);
+            } else {
+                let mut frame_data = sequence
+                    .to_be_bytes()
+                    .to_vec();
+                frame_data.extend(*data);
+                write_chunk(&mut out, *b"fdAT", &frame_data);
+                sequence += 1;
+            }
+        }
+    }
+
+    write_chunk(&mut out, *b"IEND", &[]);
+
+    Ok(out)
+}
+
+/// Pick the given maximum amount of items spread evenly over the given items,
+/// always keeping the first and last one. All items are kept if there are not
+/// more than the maximum.
+pub fn sample_evenly<T>(items: Vec<T>, max: usize) -> Vec<T> {
+    let len = items.len();
+    if len <= max {
+        return items;
+    }
+    if max < 2 {
+        return items
+            .into_iter()
+            .last()
+            .into_iter()
+            .take(max)
+            .collect();
+    }
+
+    let mut next = 0;
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            if *i * (max - 1) >= next * (len - 1) {
+                next += 1;
+                true
+            } else {
+                false
+            }
+        })
+        .map(|(_, item)| item)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a PNG file with the given header and image data chunks.
+    fn png(header: &[u8], image_data: &[&[u8]]) -> Vec<u8> {
+        let mut out = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut out, *b"IHDR", header);
+        for data in image_data {
+            write_chunk(&mut out, *b"IDAT", data);
+        }
+        write_chunk(&mut out, *b"IEND", &[]);
+        out
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_encode_apng() {
+        let header = [0, 0, 0, 4, 0, 0, 0, 2, 8, 6, 0, 0, 0];
+        let frames = [
+            png(&header, &[b"first"]),
+            png(&header, &[b"second", b"third"]),
+        ];
+
+        let apng = encode_apng(&frames, 10).unwrap();
+        let chunks = read_chunks(&apng).unwrap();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|(kind, _)| String::from_utf8_lossy(kind).into_owned())
+                .collect::<Vec<_>>(),
+            ["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fdAT", "IEND"]
+        );
+        assert_eq!(chunks[1].1, [0, 0, 0, 2, 0, 0, 0, 0]);
+        assert_eq!(
+            chunks[4].1,
+            [0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 10, 0, 0]
+        );
+        assert_eq!(chunks[6].1, *b"\0\0\0\x03third");
+
+        let other_size = png(
+            &[0, 0, 0, 8, 0, 0, 0, 2, 8, 6, 0, 0, 0],
+            &[b"x"],
+        );
+        assert!(encode_apng(&[frames[0].clone(), other_size], 10).is_err());
+        assert!(encode_apng(&[], 10).is_err());
+        assert!(encode_apng(&[b"not a png".to_vec()], 10).is_err());
+    }
+
+    #[test]
+    fn test_sample_evenly() {
+        assert_eq!(
+            sample_evenly((0..5).collect(), 10),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            sample_evenly((0..10).collect(), 4),
+            [0, 3, 6, 9]
+        );
+        assert_eq!(
+            sample_evenly((0..9).collect(), 3),
+            [0, 4, 8]
+        );
+        assert_eq!(sample_evenly((0..9).collect(), 1), [8]);
+        assert!(sample_evenly((0..9).collect::<Vec<_>>(), 0).is_empty());
+    }
+}
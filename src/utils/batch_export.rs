@@ -0,0 +1,172 @@
+//! Contains the batch mode, which lays out every map file in a directory with
+//! the map algorithm and writes the results to an output directory.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use futures_util::FutureExt;
+
+use super::{
+    graphml,
+    json,
+    svg::map_to_svg,
+    Error,
+    Result,
+};
+use crate::{
+    algorithms::{
+        recalculate_map,
+        Updater,
+    },
+    models::Map,
+    CanvasState,
+    MapState,
+};
+
+/// Check if the given path is a map file that can be laid out.
+fn is_map_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("graphml")
+            })
+}
+
+/// Decode the map file at the given path, using its extension to determine
+/// the format.
+fn decode_map_file(path: &Path, canvas: CanvasState) -> Result<Map> {
+    let contents = std::fs::read_to_string(path).map_err(Error::other)?;
+
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    {
+        json::decode_map(&contents, canvas)
+    } else {
+        graphml::decode_map(&contents, canvas)
+    }
+}
+
+/// Lay out the map file at the given path and write the result as JSON, and
+/// optionally as SVG, into the output directory.
+fn export_map_file(
+    path: &Path,
+    output_dir: &Path,
+    canvas: CanvasState,
+    with_svg: bool,
+) -> Result<()> {
+    let mut map = decode_map_file(path, canvas)?;
+
+    let mut state = MapState::new(map.clone());
+    state.calculate_algorithm_settings();
+
+    recalculate_map(
+        state.get_algorithm_settings(),
+        &mut map,
+        Updater::NoUpdates,
+    )
+    .now_or_never()
+    .ok_or(Error::other(
+        "map recalculation did not finish",
+    ))??;
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or(Error::other("invalid file name"))?;
+
+    std::fs::write(
+        output_dir.join(format!("{stem}.json")),
+        json::encode_map(&map, canvas)?,
+    )
+    .map_err(Error::other)?;
+
+    if with_svg {
+        std::fs::write(
+            output_dir.join(format!("{stem}.svg")),
+            map_to_svg(&map, canvas),
+        )
+        .map_err(Error::other)?;
+    }
+
+    Ok(())
+}
+
+/// Run the batch export for the input and output directories given as command
+/// line arguments, like `<input dir> <output dir> [--svg]`. Every map file is
+/// laid out, even if others fail, and a summary is printed at the end.
+pub fn run_batch_export() {
+    let args: Vec<String> = std::env::args()
+        .skip(1)
+        .collect();
+    let with_svg = args
+        .iter()
+        .any(|arg| arg == "--svg");
+    let dirs = args
+        .iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    let [input_dir, output_dir] = dirs[..] else {
+        eprintln!("usage: <input dir> <output dir> [--svg]");
+        std::process::exit(2);
+    };
+    let output_dir = Path::new(output_dir);
+
+    let mut files = std::fs::read_dir(input_dir)
+        .unwrap_or_else(|e| panic!("failed to read input directory {input_dir}: {e}"))
+        .filter_map(|entry| {
+            entry
+                .ok()
+                .map(|e| e.path())
+        })
+        .filter(|path| is_map_file(path))
+        .collect::<Vec<PathBuf>>();
+    files.sort();
+
+    std::fs::create_dir_all(output_dir).unwrap_or_else(|e| {
+        panic!(
+            "failed to create output directory {}: {e}",
+            output_dir.display()
+        )
+    });
+
+    let mut canvas = CanvasState::new();
+    canvas.set_square_size(7);
+    canvas.set_size((800.0, 1648.0));
+
+    let mut failed = Vec::new();
+    for file in &files {
+        match export_map_file(file, output_dir, canvas, with_svg) {
+            Ok(()) => println!("{}: ok", file.display()),
+            Err(e) => {
+                println!("{}: failed: {e}", file.display());
+                failed.push(file);
+            },
+        }
+    }
+
+    println!(
+        "laid out {} of {} maps",
+        files.len() - failed.len(),
+        files.len()
+    );
+    if !failed.is_empty() {
+        println!(
+            "failed: {}",
+            failed
+                .iter()
+                .map(|file| {
+                    file.display()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        std::process::exit(1);
+    }
+}
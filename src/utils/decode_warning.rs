@@ -0,0 +1,227 @@
+//! Contains the [`DecodeWarning`] type and the [`DecodeReport`] used to collect
+//! them while decoding a map file.
+
+use std::fmt::Display;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    Error,
+    Result,
+};
+use crate::models::Map;
+
+/// A problem with a single element of a map file, which caused that element to
+/// be skipped while decoding the file leniently, or was flagged while it was
+/// still loaded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeWarning {
+    /// A description of the element that was skipped, like `station 3`.
+    element: String,
+    /// Why the element was skipped.
+    message: String,
+    /// If the element was skipped, otherwise it was loaded as is.
+    skipped: bool,
+}
+
+impl DecodeWarning {
+    /// Create a new [`DecodeWarning`] for the given element with the given
+    /// message.
+    pub fn new(element: &impl ToString, message: &impl ToString) -> Self {
+        Self {
+            element: element.to_string(),
+            message: message.to_string(),
+            skipped: true,
+        }
+    }
+
+    /// Create a new [`DecodeWarning`] for an element that was loaded, but has
+    /// a problem the user should know about.
+    pub fn flagged(element: &impl ToString, message: &impl ToString) -> Self {
+        Self {
+            element: element.to_string(),
+            message: message.to_string(),
+            skipped: false,
+        }
+    }
+
+    /// A getter method for the description of the skipped element.
+    pub fn get_element(&self) -> &str {
+        &self.element
+    }
+
+    /// A getter method for why the element was skipped.
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    /// If the element was skipped, instead of only flagged.
+    pub fn is_skipped(&self) -> bool {
+        self.skipped
+    }
+}
+
+/// Flag every segment of the routed edges of the given map that is not aligned
+/// octilinearly with the grid.
+pub fn flag_non_octilinear_segments(map: &Map) -> Vec<DecodeWarning> {
+    map.find_non_octilinear_segments()
+        .into_iter()
+        .map(|(edge, start, end)| {
+            DecodeWarning::flagged(
+                &format!("edge {edge}"),
+                &format!("segment from {start} to {end} is not octilinear"),
+            )
+        })
+        .collect()
+}
+
+impl Display for DecodeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.skipped {
+            write!(
+                f,
+                "skipped {}: {}",
+                self.element, self.message
+            )
+        } else {
+            write!(f, "{}: {}", self.element, self.message)
+        }
+    }
+}
+
+/// Keeps track of how a map file is being decoded. In strict mode, the first
+/// invalid element aborts decoding, in lenient mode it is skipped and a
+/// [`DecodeWarning`] is recorded instead.
+#[derive(Clone, Debug, Default)]
+pub(super) struct DecodeReport {
+    /// If invalid elements should be skipped instead of aborting decoding.
+    lenient: bool,
+    /// The warnings for all skipped elements.
+    warnings: Vec<DecodeWarning>,
+}
+
+impl DecodeReport {
+    /// Create a new [`DecodeReport`] that aborts on the first invalid element.
+    pub(super) fn strict() -> Self {
+        Self::default()
+    }
+
+    /// Create a new [`DecodeReport`] that skips invalid elements.
+    pub(super) fn lenient() -> Self {
+        Self {
+            lenient: true,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Check the result of decoding the given element. In strict mode an error
+    /// is returned as is, in lenient mode it is recorded as a warning and
+    /// `None` is returned so the element can be skipped.
+    pub(super) fn check<T>(
+        &mut self,
+        element: &impl Display,
+        result: Result<T>,
+    ) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.lenient => {
+                self.warnings
+                    .push(DecodeWarning::new(element, &e));
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Record the given problem with the given element, see
+    /// [`DecodeReport::check`].
+    pub(super) fn report(&mut self, element: &impl Display, error: Error) -> Result<()> {
+        self.check::<()>(element, Err(error))
+            .map(|_| ())
+    }
+
+    /// Get all the warnings recorded while decoding.
+    pub(super) fn into_warnings(self) -> Vec<DecodeWarning> {
+        self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Edge,
+        Station,
+    };
+
+    #[test]
+    fn test_decode_report() {
+        let mut strict = DecodeReport::strict();
+        assert_eq!(
+            strict
+                .check(&"station 1", Ok(5))
+                .unwrap(),
+            Some(5)
+        );
+        assert!(
+            strict
+                .report(
+                    &"station 2",
+                    Error::decode_error("no x coordinate provided")
+                )
+                .is_err()
+        );
+        assert!(
+            strict
+                .into_warnings()
+                .is_empty()
+        );
+
+        let mut lenient = DecodeReport::lenient();
+        assert_eq!(
+            lenient
+                .check::<u8>(
+                    &"station 2",
+                    Err(Error::decode_error(
+                        "no x coordinate provided"
+                    ))
+                )
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            lenient.into_warnings(),
+            vec![DecodeWarning::new(
+                &"station 2",
+                &"Decode error: no x coordinate provided"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_flag_non_octilinear_segments() {
+        let mut map = Map::new();
+        map.add_station(Station::new(
+            (0, 0).into(),
+            Some(1.into()),
+        ));
+        map.add_station(Station::new(
+            (3, 1).into(),
+            Some(2.into()),
+        ));
+        let mut edge = Edge::new(1.into(), 2.into(), Some(1.into()));
+        edge.set_nodes(vec![(1, 0).into()]);
+        map.add_edge(edge);
+
+        let warnings = flag_non_octilinear_segments(&map);
+        assert_eq!(warnings.len(), 1);
+        assert!(!warnings[0].is_skipped());
+        assert_eq!(
+            warnings[0].to_string(),
+            "edge 1: segment from (1, 0) to (3, 1) is not octilinear"
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Contains the functions for letting the user download a file created in the
+//! browser.
+
+use leptos::prelude::document;
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use web_sys::{
+    js_sys::{
+        Array,
+        Uint8Array,
+    },
+    Blob,
+    BlobPropertyBag,
+    HtmlAnchorElement,
+    Url,
+};
+
+use super::Result;
+
+/// Let the user download the given blob as a file with the given name.
+pub fn download_blob(blob: &Blob, file_name: &str) -> Result<()> {
+    let url = Url::create_object_url_with_blob(blob)?;
+
+    let elem = document()
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()
+        .expect("to convert the element to an anchor element");
+
+    elem.set_href(&url);
+    elem.set_download(file_name);
+    elem.click();
+
+    Url::revoke_object_url(&url)?;
+    Ok(())
+}
+
+/// Let the user download the given text as a file with the given mime type and
+/// name.
+pub fn download_text(text: &str, mime_type: &str, file_name: &str) -> Result<()> {
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+
+    let parts = Array::of1(&JsValue::from_str(text));
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+
+    download_blob(&blob, file_name)
+}
+
+/// Let the user download the given bytes as a file with the given mime type and
+/// name.
+pub fn download_bytes(bytes: &[u8], mime_type: &str, file_name: &str) -> Result<()> {
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+
+    let parts = Array::of1(&Uint8Array::from(bytes));
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+
+    download_blob(&blob, file_name)
+}
@@ -15,6 +15,8 @@ use serde::{
 use serde_json::Map;
 use wasm_bindgen::JsValue;
 
+use crate::algorithms::EdgeRoutingFailure;
+
 /// A custom error type for the application.
 #[derive(Debug, Clone)]
 pub enum Error {
@@ -23,6 +25,16 @@ pub enum Error {
     InvalidFloat(FloatIsNan),
     EarlyAbort,
     DecodeError(String),
+    EdgeRouting(Box<EdgeRoutingFailure>),
+    MapTooLarge {
+        stations: usize,
+        edges: usize,
+        limit: usize,
+    },
+    BackgroundTooLarge {
+        shapes: usize,
+        limit: usize,
+    },
     Other(String),
 }
 
@@ -45,6 +57,13 @@ impl Error {
             Self::InvalidFloat(_) => "invalid_float",
             Self::EarlyAbort => "early_abort",
             Self::DecodeError(_) => "decode_error",
+            Self::EdgeRouting(_) => "edge_routing",
+            Self::MapTooLarge {
+                ..
+            } => "map_too_large",
+            Self::BackgroundTooLarge {
+                ..
+            } => "background_too_large",
             Self::Other(_) => "other",
         }
     }
@@ -59,6 +78,30 @@ impl Error {
                 "Algorithm was aborted early as no possible improvement can be reached.".to_string()
             },
             Self::DecodeError(_) => "Failed to decode the given file.".to_string(),
+            Self::EdgeRouting(failure) => {
+                format!(
+                    "Could not route the edge between stations {} and {}, it has been selected.",
+                    failure.get_from(),
+                    failure.get_to()
+                )
+            },
+            Self::MapTooLarge {
+                stations,
+                edges,
+                limit,
+            } => {
+                format!(
+                    "The map has {stations} stations and {edges} edges, more than the limit of {limit} set in the settings."
+                )
+            },
+            Self::BackgroundTooLarge {
+                shapes,
+                limit,
+            } => {
+                format!(
+                    "The file has {shapes} background shapes, more than the limit of {limit} set in the settings."
+                )
+            },
             Self::Other(e) => format!("Error: {e}"),
         }
     }
@@ -77,6 +120,26 @@ impl Display for Error {
                 )
             },
             Self::DecodeError(e) => write!(f, "Decode error: {e}"),
+            Self::EdgeRouting(e) => write!(f, "Edge routing error: {e}"),
+            Self::MapTooLarge {
+                stations,
+                edges,
+                limit,
+            } => {
+                write!(
+                    f,
+                    "Map too large: {stations} stations and {edges} edges exceed the limit of {limit}"
+                )
+            },
+            Self::BackgroundTooLarge {
+                shapes,
+                limit,
+            } => {
+                write!(
+                    f,
+                    "Background too large: {shapes} shapes exceed the limit of {limit}"
+                )
+            },
             Self::Other(e) => write!(f, "Other error: {e}"),
         }
     }
@@ -126,6 +189,29 @@ impl PartialEq<Error> for Error {
             (Self::DecodeError(e1), Self::DecodeError(e2)) | (Self::Other(e1), Self::Other(e2)) => {
                 e1 == e2
             },
+            (Self::EdgeRouting(e1), Self::EdgeRouting(e2)) => e1 == e2,
+            (
+                Self::MapTooLarge {
+                    stations: s1,
+                    edges: e1,
+                    limit: l1,
+                },
+                Self::MapTooLarge {
+                    stations: s2,
+                    edges: e2,
+                    limit: l2,
+                },
+            ) => s1 == s2 && e1 == e2 && l1 == l2,
+            (
+                Self::BackgroundTooLarge {
+                    shapes: s1,
+                    limit: l1,
+                },
+                Self::BackgroundTooLarge {
+                    shapes: s2,
+                    limit: l2,
+                },
+            ) => s1 == s2 && l1 == l2,
             _ => false,
         }
     }
@@ -153,6 +239,21 @@ impl Serialize for Error {
                     self.get_type()
                         .to_string()
                 },
+                Self::EdgeRouting(e) => {
+                    serde_json::to_string(e).map_err(serde::ser::Error::custom)?
+                },
+                Self::MapTooLarge {
+                    stations,
+                    edges,
+                    limit,
+                } => {
+                    serde_json::to_string(&(stations, edges, limit))
+                        .map_err(serde::ser::Error::custom)?
+                },
+                Self::BackgroundTooLarge {
+                    shapes,
+                    limit,
+                } => serde_json::to_string(&(shapes, limit)).map_err(serde::ser::Error::custom)?,
                 Self::DecodeError(e) | Self::Other(e) => e.to_string(),
             }
             .into(),
@@ -212,6 +313,41 @@ impl<'de> Deserialize<'de> for Error {
                     ))?;
                 Ok(Self::Other(e.to_string()))
             },
+            "edge_routing" => {
+                let e = map
+                    .get("data")
+                    .ok_or(D::Error::custom(
+                        "edge routing error must have data",
+                    ))?;
+                Ok(Self::EdgeRouting(
+                    serde_json::from_str(e).map_err(D::Error::custom)?,
+                ))
+            },
+            "map_too_large" => {
+                let e = map
+                    .get("data")
+                    .ok_or(D::Error::custom(
+                        "map too large error must have data",
+                    ))?;
+                let (stations, edges, limit) = serde_json::from_str(e).map_err(D::Error::custom)?;
+                Ok(Self::MapTooLarge {
+                    stations,
+                    edges,
+                    limit,
+                })
+            },
+            "background_too_large" => {
+                let e = map
+                    .get("data")
+                    .ok_or(D::Error::custom(
+                        "background too large error must have data",
+                    ))?;
+                let (shapes, limit) = serde_json::from_str(e).map_err(D::Error::custom)?;
+                Ok(Self::BackgroundTooLarge {
+                    shapes,
+                    limit,
+                })
+            },
             "early_abort" => Ok(Self::EarlyAbort),
             _ => Err(D::Error::custom("unknown error type")),
         }
@@ -241,6 +377,8 @@ macro_rules! unwrap_or_return {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::algorithms::OccupiedNode;
+
     #[test]
     fn test_error_serde() {
         let error_json: Result<()> = Err(Error::Json(Arc::new(
@@ -269,6 +407,50 @@ mod tests {
         let deserialized_decode: crate::Error = serde_json::from_str(&serialized_decode).unwrap();
         assert_eq!(error_decode, deserialized_decode);
 
+        let error_edge_routing = Error::EdgeRouting(Box::new(EdgeRoutingFailure::new(
+            1.into(),
+            (2.into(), &[((0, 0).into(), 0.0)]),
+            (3.into(), &[((2, 0).into(), 0.0)]),
+            &HashMap::from([(
+                (1, 0).into(),
+                OccupiedNode::Edge(4.into()),
+            )]),
+            &"No path found",
+        )));
+        let serialized_edge_routing = serde_json::to_string(&error_edge_routing).unwrap();
+        let deserialized_edge_routing: crate::Error =
+            serde_json::from_str(&serialized_edge_routing).unwrap();
+        assert_eq!(
+            error_edge_routing,
+            deserialized_edge_routing
+        );
+
+        let error_map_too_large = Error::MapTooLarge {
+            stations: 30,
+            edges: 40,
+            limit: 50,
+        };
+        let serialized_map_too_large = serde_json::to_string(&error_map_too_large).unwrap();
+        let deserialized_map_too_large: crate::Error =
+            serde_json::from_str(&serialized_map_too_large).unwrap();
+        assert_eq!(
+            error_map_too_large,
+            deserialized_map_too_large
+        );
+
+        let error_background_too_large = Error::BackgroundTooLarge {
+            shapes: 60,
+            limit: 50,
+        };
+        let serialized_background_too_large =
+            serde_json::to_string(&error_background_too_large).unwrap();
+        let deserialized_background_too_large: crate::Error =
+            serde_json::from_str(&serialized_background_too_large).unwrap();
+        assert_eq!(
+            error_background_too_large,
+            deserialized_background_too_large
+        );
+
         let error_other: Result<crate::models::Station> = Err(Error::Other("test".to_string()));
         let serialized_other = serde_json::to_string(&error_other).unwrap();
         let deserialized_other = serde_json::from_str(&serialized_other).unwrap();
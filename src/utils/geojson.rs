@@ -0,0 +1,347 @@
+//! Contains the functionality for importing the polygons of a `GeoJSON` file as
+//! decorative shapes drawn behind the map, like rivers and parks. All other
+//! geometries, like points and lines, are ignored.
+
+use serde_json::Value;
+
+use super::{
+    parse_color,
+    parsing::normalize_coords,
+    GridTransform,
+    Result,
+};
+use crate::{
+    algorithms::BackgroundShape,
+    components::CanvasState,
+    models::GridNode,
+    Error,
+};
+
+/// The color of shapes that are water, like rivers and lakes.
+const WATER_COLOR: (u8, u8, u8) = (170, 211, 223);
+/// The color of all other shapes, like parks and forests.
+const PARK_COLOR: (u8, u8, u8) = (200, 230, 180);
+
+/// The outer ring of a polygon in the coordinates of the file, together with
+/// its color.
+type Polygon = (Vec<(f64, f64)>, (u8, u8, u8));
+
+/// Determine the color of a feature from its properties. A valid `fill`
+/// property is used as it is, otherwise features mentioning water are blue and
+/// all others are green.
+fn feature_color(properties: &Value) -> (u8, u8, u8) {
+    if let Some(color) = properties
+        .get("fill")
+        .and_then(Value::as_str)
+        .and_then(|fill| parse_color(fill).ok())
+    {
+        return color;
+    }
+
+    let is_water = properties
+        .as_object()
+        .is_some_and(|properties| {
+            properties
+                .iter()
+                .any(|(key, value)| {
+                    key == "water"
+                        || key == "waterway"
+                        || value.as_str() == Some("water")
+                        || value.as_str() == Some("riverbank")
+                })
+        });
+
+    if is_water {
+        WATER_COLOR
+    } else {
+        PARK_COLOR
+    }
+}
+
+/// Parse the outer ring of a `GeoJSON` polygon into its coordinates.
+fn parse_ring(polygon: &Value) -> Result<Vec<(f64, f64)>> {
+    polygon
+        .get(0)
+        .and_then(Value::as_array)
+        .ok_or(Error::decode_error(
+            "polygon has no outer ring",
+        ))?
+        .iter()
+        .map(|point| {
+            match (
+                point
+                    .get(0)
+                    .and_then(Value::as_f64),
+                point
+                    .get(1)
+                    .and_then(Value::as_f64),
+            ) {
+                (Some(x), Some(y)) => Ok((x, y)),
+                _ => {
+                    Err(Error::decode_error(
+                        "polygon contains an invalid position",
+                    ))
+                },
+            }
+        })
+        .collect()
+}
+
+/// Collect the outer rings of all polygons in the given `GeoJSON` object
+/// together with their color.
+fn collect_polygons(
+    object: &Value,
+    color: (u8, u8, u8),
+    polygons: &mut Vec<Polygon>,
+) -> Result<()> {
+    let empty = Vec::new();
+    let members = |key: &str| {
+        object
+            .get(key)
+            .and_then(Value::as_array)
+            .unwrap_or(&empty)
+    };
+
+    match object
+        .get("type")
+        .and_then(Value::as_str)
+    {
+        Some("FeatureCollection") => {
+            for feature in members("features") {
+                collect_polygons(feature, color, polygons)?;
+            }
+        },
+        Some("Feature") => {
+            if let Some(geometry) = object.get("geometry") {
+                let color = object
+                    .get("properties")
+                    .map_or(color, feature_color);
+                collect_polygons(geometry, color, polygons)?;
+            }
+        },
+        Some("GeometryCollection") => {
+            for geometry in members("geometries") {
+                collect_polygons(geometry, color, polygons)?;
+            }
+        },
+        Some("Polygon") => {
+            polygons.push((
+                parse_ring(&object["coordinates"])?,
+                color,
+            ));
+        },
+        Some("MultiPolygon") => {
+            for polygon in members("coordinates") {
+                polygons.push((parse_ring(polygon)?, color));
+            }
+        },
+        _ => {},
+    }
+
+    Ok(())
+}
+
+/// Check if the middle node lies on the straight line through the other two.
+fn is_collinear(before: GridNode, middle: GridNode, after: GridNode) -> bool {
+    (middle.0 - before.0) * (after.1 - middle.1) == (middle.1 - before.1) * (after.0 - middle.0)
+}
+
+/// Simplify the outline of a shape after it has been rounded to the grid, by
+/// removing the nodes that are the same as the one before them or that lie on
+/// a straight line between their neighbors.
+fn simplify_outline(outline: &[GridNode]) -> Vec<GridNode> {
+    let mut simplified: Vec<GridNode> = Vec::new();
+
+    for node in outline
+        .iter()
+        .copied()
+    {
+        while let [.., before, last] = simplified[..] {
+            if !is_collinear(before, last, node) {
+                break;
+            }
+            simplified.pop();
+        }
+
+        if simplified.last() != Some(&node) {
+            simplified.push(node);
+        }
+    }
+
+    // The ring of a polygon ends where it started.
+    if simplified.len() > 1 && simplified.first() == simplified.last() {
+        simplified.pop();
+    }
+
+    simplified
+}
+
+/// Decode the polygons in the given `GeoJSON` into shapes to draw behind the
+/// map. The coordinates are placed onto the grid with the given transform of
+/// the imported map, so they line up with its stations. Without one, they are
+/// fit onto the canvas in the same way those of an imported map are. The
+/// outlines are simplified to the nodes of the grid, so many or large polygons
+/// stay cheap to draw.
+pub fn decode_background(
+    input: &str,
+    state: CanvasState,
+    transform: Option<GridTransform>,
+) -> Result<Vec<BackgroundShape>> {
+    let object: Value = serde_json::from_str(input)?;
+
+    let mut polygons = Vec::new();
+    collect_polygons(&object, PARK_COLOR, &mut polygons)?;
+    if polygons.is_empty() {
+        return Err(Error::decode_error(
+            "the GeoJSON file contains no polygons",
+        ));
+    }
+
+    let transform = transform.unwrap_or_else(|| {
+        let coords = polygons
+            .iter()
+            .flat_map(|(ring, _)| ring.iter())
+            .copied()
+            .collect();
+        GridTransform::new(normalize_coords(coords, state).1, state)
+    });
+
+    Ok(polygons
+        .into_iter()
+        .filter_map(|(ring, color)| {
+            let outline = ring
+                .into_iter()
+                .map(|(x, y)| transform.to_grid(x, y))
+                .collect::<Vec<_>>();
+            let outline = simplify_outline(&outline);
+
+            (outline.len() >= 3).then_some((outline, color))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::Station,
+        utils::json::decode_map,
+    };
+
+    #[test]
+    fn test_simplify_outline() {
+        let outline = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+            (3, 2),
+            (2, 2),
+            (0, 2),
+            (0, 0),
+        ]
+        .map(GridNode::from);
+
+        assert_eq!(
+            simplify_outline(&outline),
+            [(0, 0), (2, 0), (2, 2), (0, 2)].map(GridNode::from)
+        );
+    }
+
+    #[test]
+    fn test_decode_background() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let input = r##"{
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "properties": { "natural": "water" },
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [[[0, 0], [10, 0], [10, 2], [0, 2], [0, 0]]]
+                    }
+                },
+                {
+                    "type": "Feature",
+                    "properties": { "leisure": "park", "fill": "#ff0000" },
+                    "geometry": {
+                        "type": "MultiPolygon",
+                        "coordinates": [
+                            [[[0, 5], [5, 5], [5, 10], [0, 10], [0, 5]]],
+                            [[[9, 9], [9.01, 9], [9.01, 9.01], [9, 9]]]
+                        ]
+                    }
+                },
+                {
+                    "type": "Feature",
+                    "properties": {},
+                    "geometry": { "type": "Point", "coordinates": [3, 3] }
+                }
+            ]
+        }"##;
+
+        let shapes = decode_background(input, canvas, None).unwrap();
+
+        // The tiny polygon collapses onto a single node and is dropped.
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(shapes[0].1, WATER_COLOR);
+        assert_eq!(shapes[1].1, (255, 0, 0));
+        for (outline, _) in &shapes {
+            assert_eq!(outline.len(), 4);
+        }
+
+        assert!(decode_background(
+            r#"{ "type": "Point", "coordinates": [3, 3] }"#,
+            canvas,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_decode_background_with_transform() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let map = decode_map(
+            r#"{
+                "stations": [
+                    { "id": "a", "x": 0.0, "y": 0.0 },
+                    { "id": "b", "x": 20.0, "y": 0.0 },
+                    { "id": "c", "x": 20.0, "y": 10.0 }
+                ],
+                "lines": [],
+                "edges": []
+            }"#,
+            canvas,
+        )
+        .unwrap();
+
+        // The corners of the shape are exactly on the stations of the map,
+        // even though the shape alone covers a different area.
+        let input = r#"{
+            "type": "Polygon",
+            "coordinates": [[[0, 0], [20, 0], [20, 10], [0, 0]]]
+        }"#;
+        let shapes = decode_background(input, canvas, map.get_grid_transform()).unwrap();
+
+        let mut corners = shapes[0]
+            .0
+            .clone();
+        corners.sort_by_key(|node| (node.0, node.1));
+        let mut stations = map
+            .get_stations()
+            .into_iter()
+            .map(Station::get_pos)
+            .collect::<Vec<_>>();
+        stations.sort_by_key(|node| (node.0, node.1));
+        assert_eq!(corners, stations);
+    }
+}
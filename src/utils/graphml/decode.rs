@@ -21,7 +21,10 @@ use crate::{
         parsing::{
             normalize_coords,
             parse_id,
+            GridTransform,
+            NormalizationSettings,
         },
+        DecodeReport,
         Error,
         Result,
     },
@@ -121,14 +124,31 @@ fn node_to_station(node: &Node, state: CanvasState) -> Result<Station> {
             .clone(),
     );
 
+    if let Some(weight) = node
+        .data
+        .iter()
+        .find(|d| d.key == "weight")
+    {
+        station.set_weight(Some(
+            weight
+                .value
+                .parse()
+                .map_err(|_| Error::decode_error("weight is invalid"))?,
+        ));
+    }
+
     Ok(station)
 }
 
 /// GraphML sometimes has maps/stations located in weird places (like all x
 /// coordinates being negative or only difference being in the decimals), this
-/// normalizes them so they fit within the canvas as it currently is.
-fn normalize_stations(mut items: Vec<GraphItem>, state: CanvasState) -> Result<Vec<GraphItem>> {
-    let mut coords = items
+/// normalizes them so they fit within the canvas as it currently is. Returns
+/// the normalized items together with the settings used.
+fn normalize_stations(
+    mut items: Vec<GraphItem>,
+    state: CanvasState,
+) -> Result<(Vec<GraphItem>, NormalizationSettings)> {
+    let coords = items
         .iter()
         .filter_map(|item| {
             if let GraphItem::Node(node) = item {
@@ -139,7 +159,7 @@ fn normalize_stations(mut items: Vec<GraphItem>, state: CanvasState) -> Result<V
         })
         .collect::<Result<Vec<_>>>()?;
 
-    coords = normalize_coords(coords, state).0;
+    let (coords, normalization_settings) = normalize_coords(coords, state);
 
     for (item, (x, y)) in items
         .iter_mut()
@@ -164,29 +184,73 @@ fn normalize_stations(mut items: Vec<GraphItem>, state: CanvasState) -> Result<V
         }
     }
 
-    Ok(items)
+    Ok((items, normalization_settings))
 }
 
-/// Translates the [`GraphMlMap`] to a [`Map`]
-pub fn graphml_to_map(mut graph: GraphMlMap, state: CanvasState) -> Result<Map> {
+/// Translates the [`GraphMlMap`] to a [`Map`]. Invalid elements are handled
+/// according to the given [`DecodeReport`].
+#[allow(clippy::too_many_lines)] // mostly due to reporting the invalid elements
+pub fn graphml_to_map(
+    mut graph: GraphMlMap,
+    state: CanvasState,
+    report: &mut DecodeReport,
+) -> Result<Map> {
     let mut map = Map::new();
 
     // First add a Line for every edge defined
-    for key in &graph.key {
-        if key.for_item == "edge" {
-            map.add_line(edge_to_line(key)?);
+    for key in graph
+        .key
+        .iter()
+        .filter(|k| k.for_item == "edge")
+    {
+        if let Some(line) = report.check(
+            &format!("line {}", key.id),
+            edge_to_line(key),
+        )? {
+            map.add_line(line);
+        }
+    }
+
+    // Stations without valid coordinates can't be placed, so skip them
+    let mut content = Vec::new();
+    for item in graph
+        .graph
+        .content
+        .drain(..)
+    {
+        let has_valid_coords = match &item {
+            GraphItem::Node(node) => {
+                report
+                    .check(
+                        &format!("station {}", node.id),
+                        get_node_coords(node),
+                    )?
+                    .is_some()
+            },
+            GraphItem::Edge(_) => true,
+        };
+
+        if has_valid_coords {
+            content.push(item);
         }
     }
 
     // Ensure the location of the stations is correct
+    let (content, normalization_settings) = normalize_stations(content, state)?;
     graph
         .graph
-        .content = normalize_stations(
-        graph
-            .graph
-            .content,
-        state,
-    )?;
+        .content = content;
+    if graph
+        .graph
+        .content
+        .iter()
+        .any(|item| matches!(item, GraphItem::Node(_)))
+    {
+        map.set_grid_transform(Some(GridTransform::new(
+            normalization_settings,
+            state,
+        )));
+    }
 
     // Load in all the stations
     for item in &graph
@@ -194,28 +258,34 @@ pub fn graphml_to_map(mut graph: GraphMlMap, state: CanvasState) -> Result<Map>
         .content
     {
         if let GraphItem::Node(n) = item {
-            map.add_station(node_to_station(n, state)?);
-        }
-    }
+            let element = format!("station {}", n.id);
+            let Some(station) = report.check(&element, node_to_station(n, state))? else {
+                continue;
+            };
+
+            // Check there is no station overlap
+            // FIXME: instead of erroring, it should look for a free spot in its neighbors
+            if let Some(other) = map
+                .get_stations()
+                .into_iter()
+                .find(|s| s.get_pos() == station.get_pos())
+            {
+                report.report(
+                    &element,
+                    Error::decode_error(format!(
+                        "station {}({}) has the same position as another station {}({}) on this map of size {:?} with squares of size {}",
+                        station.get_name(),
+                        station.get_id(),
+                        other.get_name(),
+                        other.get_id(),
+                        state.get_size(),
+                        state.get_square_size()
+                    )),
+                )?;
+                continue;
+            }
 
-    // Check there is no station overlap
-    // FIXME: instead of erroring, it should look for a free spot in its neighbors
-    for station in map.get_stations() {
-        if let Some(other) = map
-            .get_stations()
-            .iter()
-            .filter(|s| s.get_id() != station.get_id())
-            .find(|s| s.get_pos() == station.get_pos())
-        {
-            return Err(Error::decode_error(format!(
-                "station {}({}) has the same position as another station {}({}) on this map of size {:?} with squares of size {}",
-                station.get_name(),
-                station.get_id(),
-                other.get_name(),
-                other.get_id(),
-                state.get_size(),
-                state.get_square_size()
-            )));
+            map.add_station(station);
         }
     }
 
@@ -225,20 +295,42 @@ pub fn graphml_to_map(mut graph: GraphMlMap, state: CanvasState) -> Result<Map>
         .content
     {
         if let GraphItem::Edge(e) = item {
+            let element = format!("edge {}", e.id);
+            let source = parse_id(&e.source).into();
+            let target = parse_id(&e.target).into();
+
+            if map
+                .get_station(source)
+                .is_none()
+                || map
+                    .get_station(target)
+                    .is_none()
+            {
+                report.report(
+                    &element,
+                    Error::decode_error(format!(
+                        "edge {} references non-existent station {} or {}",
+                        e.id, e.source, e.target
+                    )),
+                )?;
+                continue;
+            }
+
             for data in &e.data {
-                let mut line = map
-                    .get_mut_line(parse_id(&data.key).into())
-                    .ok_or(Error::decode_error(format!(
-                        "edge {} referenced non-existant line {}",
-                        e.id, data.key
-                    )))?
-                    .clone();
-                line.add_station(
-                    &mut map,
-                    parse_id(&e.source).into(),
-                    Some(parse_id(&e.target).into()),
-                    None,
-                );
+                let Some(mut line) = report.check(
+                    &element,
+                    map.get_line(parse_id(&data.key).into())
+                        .cloned()
+                        .ok_or(Error::decode_error(format!(
+                            "edge {} referenced non-existant line {}",
+                            e.id, data.key
+                        ))),
+                )?
+                else {
+                    continue;
+                };
+
+                line.add_station(&mut map, source, Some(target), None);
                 map.add_line(line);
             }
         }
@@ -295,6 +387,10 @@ mod tests {
                     key: "y".to_owned(),
                     value: "155".to_owned(),
                 },
+                Data {
+                    key: "weight".to_owned(),
+                    value: "4210".to_owned(),
+                },
             ],
         };
         let mut canvas = CanvasState::new();
@@ -308,6 +404,7 @@ mod tests {
         assert_eq!(result.get_id(), example.get_id());
         assert_eq!(result.get_pos(), example.get_pos());
         assert_eq!(result.get_name(), example.get_name());
+        assert_eq!(result.get_weight(), Some(4210.0));
     }
 
     #[test]
@@ -386,7 +483,9 @@ mod tests {
         let mut canvas = CanvasState::new();
         canvas.set_square_size(5);
 
-        let result = normalize_stations(items, canvas).unwrap();
+        let result = normalize_stations(items, canvas)
+            .unwrap()
+            .0;
 
         if let GraphItem::Node(node) = &result[0] {
             assert_eq!(
@@ -522,7 +621,12 @@ mod tests {
         let mut canvas = CanvasState::new();
         canvas.set_square_size(5);
 
-        let map = graphml_to_map(graphml, canvas).unwrap();
+        let map = graphml_to_map(
+            graphml,
+            canvas,
+            &mut DecodeReport::strict(),
+        )
+        .unwrap();
 
         let result_line = map
             .get_line(0.into())
@@ -9,7 +9,11 @@ mod graphml_map;
 use decode::graphml_to_map;
 use graphml_map::GraphMlMap;
 
-use super::Result;
+use super::{
+    DecodeReport,
+    DecodeWarning,
+    Result,
+};
 use crate::{
     components::CanvasState,
     models::Map,
@@ -21,7 +25,19 @@ use crate::{
 pub fn decode_map(input: &str, state: CanvasState) -> Result<Map> {
     let decoded: GraphMlMap = from_str(input)?;
 
-    graphml_to_map(decoded, state)
+    graphml_to_map(decoded, state, &mut DecodeReport::strict())
+}
+
+/// Decode the given GraphML string into a [`Map`] struct like [`decode_map`],
+/// but skip any invalid stations, lines or edges instead of failing. Returns a
+/// warning for every skipped element next to the map.
+pub fn decode_map_lenient(input: &str, state: CanvasState) -> Result<(Map, Vec<DecodeWarning>)> {
+    let decoded: GraphMlMap = from_str(input)?;
+
+    let mut report = DecodeReport::lenient();
+    let map = graphml_to_map(decoded, state, &mut report)?;
+
+    Ok((map, report.into_warnings()))
 }
 
 #[cfg(test)]
@@ -49,4 +65,38 @@ mod tests {
         assert_eq!(result_station.get_pos(), (30, 58));
         assert_eq!(result_station.get_name(), "test 2");
     }
+
+    #[test]
+    fn test_decode_map_lenient() {
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.graphml")
+            .expect("test data file does not exist")
+            .replace(r#"<data key="x">90.0</data>"#, "");
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+
+        assert!(decode_map(&test_file_content, canvas).is_err());
+
+        let (result, warnings) =
+            decode_map_lenient(&test_file_content, canvas).expect("failed to decode graphml");
+
+        assert_eq!(
+            result
+                .get_stations()
+                .len(),
+            2
+        );
+        assert_eq!(
+            result
+                .get_edges()
+                .len(),
+            1
+        );
+        assert_eq!(
+            warnings
+                .iter()
+                .map(DecodeWarning::get_element)
+                .collect::<Vec<_>>(),
+            vec!["station n2", "edge e1"]
+        );
+    }
 }
@@ -32,8 +32,10 @@ pub struct IDManager {
 }
 
 impl IDManager {
-    /// Create a new [`IDManager`] with all ids starting at 1.
-    const fn new() -> Self {
+    /// Create a new [`IDManager`] with all ids starting at 1. Besides the
+    /// global one, a separate manager can be used to number ids independently
+    /// of it, like when renumbering the ids of a map.
+    pub const fn new() -> Self {
         Self {
             station_id: Mutex::new(1),
             line_id: Mutex::new(1),
@@ -41,6 +43,31 @@ impl IDManager {
         }
     }
 
+    /// Take the next id from the given counter of a manager.
+    fn take_id(counter: &Mutex<u64>) -> u64 {
+        let mut id_lock = counter
+            .lock()
+            .unwrap();
+        let id = *id_lock;
+        *id_lock += 1;
+        id
+    }
+
+    /// Get the next station id of this manager instead of the global one.
+    pub fn take_station_id(&self) -> StationID {
+        StationID::from(Self::take_id(&self.station_id))
+    }
+
+    /// Get the next line id of this manager instead of the global one.
+    pub fn take_line_id(&self) -> LineID {
+        LineID::from(Self::take_id(&self.line_id))
+    }
+
+    /// Get the next edge id of this manager instead of the global one.
+    pub fn take_edge_id(&self) -> EdgeID {
+        EdgeID::from(Self::take_id(&self.edge_id))
+    }
+
     /// Get the next station id. This is the next sequential id and should
     /// therefore be unique.
     pub fn next_station_id() -> StationID {
@@ -154,6 +181,12 @@ impl IDManager {
     }
 }
 
+impl Default for IDManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// A serializable struct that contains the data of the [`IDManager`].
 /// This can be used to save the state of the [`IDManager`] and transfer it
 /// between the main thread and any web-workers.
@@ -0,0 +1,355 @@
+//! Contains the compact variant of the JSON format and the conversion between
+//! it and the regular [`JSONMap`].
+//!
+//! The compact format uses single letter keys and stores every object as an
+//! array of its values instead of an object with keys:
+//!
+//! - `s`: the stations as `[id, x, y, name, weight]`,
+//! - `l`: the lines as `[id, name, color]`,
+//! - `e`: the edges as `[source, target, nodes, lines]`, where the nodes are a
+//!   flat list of `x, y` pairs and the lines a list of line ids,
+//! - `i`: the interchanges as `[source, target]`, left out if there are none,
+//! - `p`: the route patterns of the lines as `[line, name, stations]`, where
+//!   the stations are a list of station ids, left out if there are none,
+//! - `o`: the label offsets of the stations as `[station, x, y]`, left out if
+//!   no label has been moved,
+//! - `d`: the directions edges are required to leave their stations in as
+//!   `[edge, source direction, target direction]`, where the edge is its index
+//!   in `e`, left out if no direction is required,
+//! - `b`: the background shapes as `[color, outline]`, where the outline is a
+//!   flat list of `x, y` pairs, left out if there are none,
+//! - `c`: the edges whose lines are drawn bundled together, as their indices
+//!   in `e`, left out if no edge is bundled,
+//! - `v`: the version of the format, which is the first version if missing.
+//!
+//! All ids are numbers, and names, colors and weights can be `null`.
+
+// No need to document all fields on the model structs here.
+#![allow(clippy::missing_docs_in_private_items)]
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::json_models::{
+    legacy_version,
+    EdgeNode,
+    JSONBackgroundShape,
+    JSONEdge,
+    JSONInterchange,
+    JSONLine,
+    JSONMap,
+    JSONRoutePattern,
+    JSONStation,
+};
+use crate::{
+    algorithms::EdgeDirection,
+    utils::parsing::parse_id,
+};
+
+/// Represents a station for the compact JSON file.
+type CompactStation = (
+    u64,
+    f64,
+    f64,
+    Option<String>,
+    Option<f64>,
+);
+
+/// Represents a line for the compact JSON file.
+type CompactLine = (u64, Option<String>, Option<String>);
+
+/// Represents a connection between two stations for the compact JSON file.
+type CompactEdge = (u64, u64, Vec<f64>, Vec<u64>);
+
+/// Represents the directions an edge is required to leave its stations in for
+/// the compact JSON file.
+type CompactEdgeDirections = (
+    usize,
+    Option<EdgeDirection>,
+    Option<EdgeDirection>,
+);
+
+/// Represents a decorative shape drawn behind the map for the compact JSON
+/// file.
+type CompactBackgroundShape = (String, Vec<f64>);
+
+/// Flatten the given nodes into a list of their coordinates.
+fn flatten_nodes(nodes: &[EdgeNode]) -> Vec<f64> {
+    nodes
+        .iter()
+        .flat_map(|n| [n.x, n.y])
+        .collect()
+}
+
+/// Turn the given flattened list of coordinates back into nodes.
+fn expand_nodes(coords: &[f64]) -> Vec<EdgeNode> {
+    coords
+        .chunks_exact(2)
+        .map(|n| {
+            EdgeNode {
+                x: n[0],
+                y: n[1],
+            }
+        })
+        .collect()
+}
+
+/// Convert a background shape into the compact form, with its outline
+/// flattened into a list of coordinates.
+fn compact_background_shape(shape: &JSONBackgroundShape) -> CompactBackgroundShape {
+    (
+        shape
+            .color
+            .clone(),
+        flatten_nodes(&shape.outline),
+    )
+}
+
+/// Convert a background shape from the compact form back into a regular one.
+fn expand_background_shape((color, outline): CompactBackgroundShape) -> JSONBackgroundShape {
+    JSONBackgroundShape {
+        color,
+        outline: expand_nodes(&outline),
+    }
+}
+
+/// Represents a route pattern of a line for the compact JSON file.
+type CompactRoutePattern = (u64, Option<String>, Vec<u64>);
+
+/// Represents the whole map in the compact JSON file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct CompactJSONMap {
+    #[serde(rename = "v", default = "legacy_version")]
+    pub version: u64,
+    #[serde(rename = "s")]
+    pub stations: Vec<CompactStation>,
+    #[serde(rename = "l")]
+    pub lines: Vec<CompactLine>,
+    #[serde(rename = "e")]
+    pub edges: Vec<CompactEdge>,
+    #[serde(rename = "i", default, skip_serializing_if = "Vec::is_empty")]
+    pub interchanges: Vec<(u64, u64)>,
+    #[serde(rename = "p", default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<CompactRoutePattern>,
+    #[serde(rename = "o", default, skip_serializing_if = "Vec::is_empty")]
+    pub label_offsets: Vec<(u64, i32, i32)>,
+    #[serde(rename = "d", default, skip_serializing_if = "Vec::is_empty")]
+    pub edge_directions: Vec<CompactEdgeDirections>,
+    #[serde(rename = "b", default, skip_serializing_if = "Vec::is_empty")]
+    pub background: Vec<CompactBackgroundShape>,
+    #[serde(rename = "c", default, skip_serializing_if = "Vec::is_empty")]
+    pub bundled_edges: Vec<usize>,
+}
+
+impl From<&JSONMap> for CompactJSONMap {
+    #[allow(clippy::too_many_lines)] // mostly due to compacting every field of the map
+    fn from(map: &JSONMap) -> Self {
+        Self {
+            version: map.version,
+            stations: map
+                .stations
+                .iter()
+                .map(|s| {
+                    (
+                        parse_id(&s.id),
+                        s.x,
+                        s.y,
+                        s.name
+                            .clone(),
+                        s.weight,
+                    )
+                })
+                .collect(),
+            lines: map
+                .lines
+                .iter()
+                .map(|l| {
+                    (
+                        parse_id(&l.id),
+                        l.name
+                            .clone(),
+                        l.color
+                            .clone(),
+                    )
+                })
+                .collect(),
+            edges: map
+                .edges
+                .iter()
+                .map(|e| {
+                    (
+                        parse_id(&e.source),
+                        parse_id(&e.target),
+                        flatten_nodes(&e.nodes),
+                        e.lines
+                            .iter()
+                            .map(|l| parse_id(l))
+                            .collect(),
+                    )
+                })
+                .collect(),
+            interchanges: map
+                .interchanges
+                .iter()
+                .map(|i| (parse_id(&i.source), parse_id(&i.target)))
+                .collect(),
+            patterns: map
+                .lines
+                .iter()
+                .flat_map(|l| {
+                    l.patterns
+                        .iter()
+                        .map(|p| {
+                            (
+                                parse_id(&l.id),
+                                p.name
+                                    .clone(),
+                                p.stations
+                                    .iter()
+                                    .map(|s| parse_id(s))
+                                    .collect(),
+                            )
+                        })
+                })
+                .collect(),
+            label_offsets: map
+                .stations
+                .iter()
+                .filter_map(|s| {
+                    s.label_offset
+                        .map(|(x, y)| (parse_id(&s.id), x, y))
+                })
+                .collect(),
+            edge_directions: map
+                .edges
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| {
+                    e.source_direction
+                        .is_some()
+                        || e.target_direction
+                            .is_some()
+                })
+                .map(|(i, e)| {
+                    (
+                        i,
+                        e.source_direction,
+                        e.target_direction,
+                    )
+                })
+                .collect(),
+            background: map
+                .background
+                .iter()
+                .map(compact_background_shape)
+                .collect(),
+            bundled_edges: map
+                .edges
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.bundled)
+                .map(|(i, _)| i)
+                .collect(),
+        }
+    }
+}
+
+impl From<CompactJSONMap> for JSONMap {
+    fn from(map: CompactJSONMap) -> Self {
+        let station_id = |id: u64| format!("s{id}");
+        let line_id = |id: u64| format!("l{id}");
+
+        let mut patterns = map.patterns;
+
+        Self {
+            version: map.version,
+            stations: map
+                .stations
+                .into_iter()
+                .map(|(id, x, y, name, weight)| {
+                    JSONStation {
+                        id: station_id(id),
+                        name,
+                        x,
+                        y,
+                        weight,
+                        label_offset: map
+                            .label_offsets
+                            .iter()
+                            .find(|(station, ..)| *station == id)
+                            .map(|(_, x, y)| (*x, *y)),
+                    }
+                })
+                .collect(),
+            lines: map
+                .lines
+                .into_iter()
+                .map(|(id, name, color)| {
+                    JSONLine {
+                        id: line_id(id),
+                        name,
+                        color,
+                        patterns: patterns
+                            .extract_if(.., |(line, ..)| *line == id)
+                            .map(|(_, name, stations)| {
+                                JSONRoutePattern {
+                                    name,
+                                    stations: stations
+                                        .into_iter()
+                                        .map(station_id)
+                                        .collect(),
+                                }
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+            edges: map
+                .edges
+                .into_iter()
+                .enumerate()
+                .map(|(i, (source, target, nodes, lines))| {
+                    let (source_direction, target_direction) = map
+                        .edge_directions
+                        .iter()
+                        .find(|(edge, ..)| *edge == i)
+                        .map_or((None, None), |(_, source, target)| {
+                            (*source, *target)
+                        });
+
+                    JSONEdge {
+                        source: station_id(source),
+                        target: station_id(target),
+                        nodes: expand_nodes(&nodes),
+                        lines: lines
+                            .into_iter()
+                            .map(line_id)
+                            .collect(),
+                        source_direction,
+                        target_direction,
+                        bundled: map
+                            .bundled_edges
+                            .contains(&i),
+                    }
+                })
+                .collect(),
+            interchanges: map
+                .interchanges
+                .into_iter()
+                .map(|(source, target)| {
+                    JSONInterchange {
+                        source: station_id(source),
+                        target: station_id(target),
+                    }
+                })
+                .collect(),
+            background: map
+                .background
+                .into_iter()
+                .map(expand_background_shape)
+                .collect(),
+        }
+    }
+}
@@ -11,6 +11,7 @@ use crate::{
         GridNode,
         Line,
         Map,
+        RoutePattern,
         Station,
     },
     utils::{
@@ -19,8 +20,10 @@ use crate::{
             normalize_coords,
             parse_color,
             parse_id,
+            GridTransform,
             NormalizationSettings,
         },
+        DecodeReport,
         Error,
         Result,
     },
@@ -51,8 +54,14 @@ fn normalize_stations(
     (stations, normalization_settings)
 }
 
-/// Translates the [`JSONMap`] to a [`Map`]
-pub fn json_to_map(mut graph: JSONMap, state: CanvasState) -> Result<Map> {
+/// Translates the [`JSONMap`] to a [`Map`]. Invalid elements are handled
+/// according to the given [`DecodeReport`].
+#[allow(clippy::too_many_lines)] // mostly due to reporting the invalid elements
+pub fn json_to_map(
+    mut graph: JSONMap,
+    state: CanvasState,
+    report: &mut DecodeReport,
+) -> Result<Map> {
     let mut map = Map::new();
 
     let temp = normalize_stations(graph.stations, state);
@@ -72,25 +81,30 @@ pub fn json_to_map(mut graph: JSONMap, state: CanvasState) -> Result<Map> {
         if let Some(name) = json_station.name {
             station.set_name(&name);
         }
+        station.set_weight(json_station.weight);
+        if let Some(offset) = json_station.label_offset {
+            station.set_label_offset(offset);
+        }
 
-        map.add_station(station);
-    }
-
-    // Check there is no station overlap
-    // FIXME: instead of erroring, it should look for a free spot in its neighbors
-    for station in map.get_stations() {
+        // Check there is no station overlap
+        // FIXME: instead of erroring, it should look for a free spot in its neighbors
         if map
             .get_stations()
-            .iter()
-            .filter(|s| s.get_id() != station.get_id())
+            .into_iter()
             .any(|s| s.get_pos() == station.get_pos())
         {
-            return Err(Error::decode_error(format!(
-                "station {}({}) has the same position as another station",
-                station.get_name(),
-                station.get_id()
-            )));
+            report.report(
+                &format!("station {}", json_station.id),
+                Error::decode_error(format!(
+                    "station {}({}) has the same position as another station",
+                    station.get_name(),
+                    station.get_id()
+                )),
+            )?;
+            continue;
         }
+
+        map.add_station(station);
     }
 
     // Add lines
@@ -105,9 +119,50 @@ pub fn json_to_map(mut graph: JSONMap, state: CanvasState) -> Result<Map> {
         }
 
         if let Some(color) = json_line.color {
-            line.set_color(parse_color(&color)?);
+            let Some(color) = report.check(
+                &format!("line {}", json_line.id),
+                parse_color(&color),
+            )?
+            else {
+                continue;
+            };
+            line.set_color(color);
         }
 
+        let mut patterns = Vec::new();
+        for json_pattern in json_line.patterns {
+            let stations = json_pattern
+                .stations
+                .iter()
+                .map(|id| parse_id(id).into())
+                .collect::<Vec<_>>();
+
+            if let Some(missing) = stations
+                .iter()
+                .position(|id| {
+                    map.get_station(*id)
+                        .is_none()
+                })
+            {
+                report.report(
+                    &format!("line {}", json_line.id),
+                    Error::decode_error(format!(
+                        "route pattern references non-existent station {}",
+                        json_pattern.stations[missing]
+                    )),
+                )?;
+                continue;
+            }
+
+            patterns.push(RoutePattern::new(
+                &json_pattern
+                    .name
+                    .unwrap_or_default(),
+                stations,
+            ));
+        }
+        line.set_patterns(patterns);
+
         map.add_line(line);
     }
 
@@ -116,12 +171,31 @@ pub fn json_to_map(mut graph: JSONMap, state: CanvasState) -> Result<Map> {
         .edges
         .drain(..)
     {
-        let edge_id = map.get_edge_id_between(
-            parse_id(&json_edge.source).into(),
-            parse_id(&json_edge.target).into(),
+        let element = format!(
+            "edge {}-{}",
+            json_edge.source, json_edge.target
         );
+        let source = parse_id(&json_edge.source).into();
+        let target = parse_id(&json_edge.target).into();
+
+        if map
+            .get_station(source)
+            .is_none()
+            || map
+                .get_station(target)
+                .is_none()
+        {
+            report.report(
+                &element,
+                Error::decode_error(format!(
+                    "edge references non-existent station {} or {}",
+                    json_edge.source, json_edge.target
+                )),
+            )?;
+            continue;
+        }
 
-        let nodes = json_edge
+        let nodes: Vec<GridNode> = json_edge
             .nodes
             .iter()
             .map(|node| {
@@ -129,34 +203,131 @@ pub fn json_to_map(mut graph: JSONMap, state: CanvasState) -> Result<Map> {
                 GridNode::from_canvas_pos((node.0, node.1), state)
             })
             .collect();
-        map.get_mut_edge(edge_id)
-            .unwrap()
-            .set_nodes(nodes);
+
+        // Some files list an edge once for every line on it, so an entry along
+        // the same route as an existing edge between the stations is that
+        // edge, while an entry along another route is a parallel edge.
+        let edge_id = map
+            .get_edge_ids_between(source, target)
+            .into_iter()
+            .find(|id| {
+                map.get_edge(*id)
+                    .is_some_and(|edge| {
+                        edge.get_nodes() == nodes
+                            || edge
+                                .get_nodes()
+                                .iter()
+                                .rev()
+                                .eq(&nodes)
+                    })
+            })
+            .unwrap_or_else(|| map.add_parallel_edge(source, target));
+        let edge = map
+            .get_mut_edge(edge_id)
+            .unwrap();
+        edge.set_nodes(nodes);
+        edge.set_required_direction(source, json_edge.source_direction);
+        edge.set_required_direction(target, json_edge.target_direction);
+        edge.set_bundled(json_edge.bundled);
 
         // Add edge to lines
         for line_id in &json_edge.lines {
-            let mut line = map
-                .get_line(parse_id(line_id).into())
-                .ok_or(Error::decode_error(format!(
-                    "edge references non-existent line {line_id}",
-                )))?
-                .clone();
+            let Some(mut line) = report.check(
+                &element,
+                map.get_line(parse_id(line_id).into())
+                    .cloned()
+                    .ok_or(Error::decode_error(format!(
+                        "edge references non-existent line {line_id}",
+                    ))),
+            )?
+            else {
+                continue;
+            };
 
             line.add_edge(edge_id, &mut map);
             map.add_line(line);
         }
     }
 
+    // Add interchanges
+    for json_interchange in graph
+        .interchanges
+        .drain(..)
+    {
+        let source = parse_id(&json_interchange.source).into();
+        let target = parse_id(&json_interchange.target).into();
+
+        if source == target
+            || map
+                .get_station(source)
+                .is_none()
+            || map
+                .get_station(target)
+                .is_none()
+        {
+            report.report(
+                &format!(
+                    "interchange {}-{}",
+                    json_interchange.source, json_interchange.target
+                ),
+                Error::decode_error(format!(
+                    "interchange needs two different existing stations, got {} and {}",
+                    json_interchange.source, json_interchange.target
+                )),
+            )?;
+            continue;
+        }
+
+        map.add_interchange(source, target)?;
+    }
+
+    // Add background shapes, placed onto the grid the same way as the stations
+    let transform = GridTransform::new(normalization_settings, state);
+    let mut background = Vec::new();
+    for (i, json_shape) in graph
+        .background
+        .drain(..)
+        .enumerate()
+    {
+        let Some(color) = report.check(
+            &format!("background shape {i}"),
+            parse_color(&json_shape.color),
+        )?
+        else {
+            continue;
+        };
+
+        let outline = json_shape
+            .outline
+            .iter()
+            .map(|node| transform.to_grid(node.x, node.y))
+            .collect();
+        background.push((outline, color));
+    }
+    map.set_background(background);
+
+    if !map
+        .get_stations()
+        .is_empty()
+    {
+        map.set_grid_transform(Some(transform));
+    }
+
     Ok(map)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::json::json_models::{
+    use crate::{
+        models::Interchange,
+        utils::json::json_models::{
         EdgeNode,
         JSONEdge,
+        JSONInterchange,
         JSONLine,
+        JSONRoutePattern,
+    },
     };
 
     #[test]
@@ -172,18 +343,24 @@ mod tests {
                     name: None,
                     x: -1.0,
                     y: -3.0,
+                    weight: None,
+                    label_offset: None,
                 },
                 JSONStation {
                     id: "2".to_string(),
                     name: None,
                     x: 1.0,
                     y: 5.0,
+                    weight: None,
+                    label_offset: None,
                 },
                 JSONStation {
                     id: "3".to_string(),
                     name: None,
                     x: 3.0,
                     y: 1.0,
+                    weight: None,
+                    label_offset: None,
                 },
             ],
             canvas,
@@ -197,18 +374,24 @@ mod tests {
                     name: None,
                     x: 10.0,
                     y: 10.0,
+                    weight: None,
+                    label_offset: None,
                 },
                 JSONStation {
                     id: "2".to_string(),
                     name: None,
                     x: 50.0,
                     y: 90.0,
+                    weight: None,
+                    label_offset: None,
                 },
                 JSONStation {
                     id: "3".to_string(),
                     name: None,
                     x: 90.0,
                     y: 50.0,
+                    weight: None,
+                    label_offset: None,
                 }
             ]
         );
@@ -222,30 +405,41 @@ mod tests {
 
         let result = json_to_map(
             JSONMap {
+                version: 1,
                 stations: vec![
                     JSONStation {
                         id: "0".to_string(),
                         name: None,
                         x: -1.0,
                         y: -3.0,
+                        weight: None,
+                        label_offset: None,
                     },
                     JSONStation {
                         id: "1".to_string(),
                         name: Some("test 2".to_string()),
                         x: 1.0,
                         y: 5.0,
+                        weight: None,
+                        label_offset: None,
                     },
                     JSONStation {
                         id: "s3".to_string(),
                         name: None,
                         x: 3.0,
                         y: 1.0,
+                        weight: None,
+                        label_offset: None,
                     },
                 ],
                 lines: vec![JSONLine {
                     id: "0".to_string(),
                     name: Some("lineU1".to_string()),
                     color: Some("rgb(84, 167, 33)".to_string()),
+                    patterns: vec![JSONRoutePattern {
+                        name: Some("limited".to_string()),
+                        stations: vec!["0".to_string(), "s3".to_string()],
+                    }],
                 }],
                 edges: vec![
                     JSONEdge {
@@ -256,16 +450,28 @@ mod tests {
                             x: 0.0,
                             y: 0.0,
                         }],
+                        source_direction: None,
+                        target_direction: None,
+                        bundled: false,
                     },
                     JSONEdge {
                         source: "1".to_string(),
                         target: "s3".to_string(),
                         lines: vec!["0".to_string()],
                         nodes: vec![],
+                        source_direction: None,
+                        target_direction: None,
+                        bundled: false,
                     },
                 ],
+                interchanges: vec![JSONInterchange {
+                    source: "s3".to_string(),
+                    target: "0".to_string(),
+                }],
+                background: Vec::new(),
             },
             canvas,
+            &mut DecodeReport::strict(),
         )
         .unwrap();
 
@@ -306,11 +512,135 @@ mod tests {
         assert_eq!(result_line.get_color(), (84, 167, 33));
         assert_eq!(result_line.get_name(), "lineU1");
         assert_eq!(line_edges, edges);
+        assert_eq!(
+            result_line.get_patterns(),
+            &[RoutePattern::new(
+                &"limited",
+                vec![0.into(), 3.into()]
+            )]
+        );
 
         let result_station = result
             .get_station(1.into())
             .expect("no station with id 1");
         assert_eq!(result_station.get_pos(), (10, 18));
         assert_eq!(result_station.get_name(), "test 2");
+
+        assert_eq!(
+            result.get_interchanges(),
+            &[Interchange::new(0.into(), 3.into())]
+        );
+    }
+
+    #[test]
+    fn test_json_to_map_lenient() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let mut report = DecodeReport::lenient();
+        let result = json_to_map(
+            JSONMap {
+                version: 1,
+                stations: vec![
+                    JSONStation {
+                        id: "0".to_string(),
+                        name: None,
+                        x: -1.0,
+                        y: -3.0,
+                        weight: None,
+                        label_offset: None,
+                    },
+                    JSONStation {
+                        id: "1".to_string(),
+                        name: None,
+                        x: 1.0,
+                        y: 5.0,
+                        weight: None,
+                        label_offset: None,
+                    },
+                    JSONStation {
+                        id: "2".to_string(),
+                        name: None,
+                        x: 1.0,
+                        y: 5.0,
+                        weight: None,
+                        label_offset: None,
+                    },
+                ],
+                lines: vec![JSONLine {
+                    id: "0".to_string(),
+                    name: None,
+                    color: Some("not a color".to_string()),
+                    patterns: Vec::new(),
+                }],
+                edges: vec![
+                    JSONEdge {
+                        source: "0".to_string(),
+                        target: "1".to_string(),
+                        lines: vec!["0".to_string()],
+                        nodes: vec![],
+                        source_direction: None,
+                        target_direction: None,
+                        bundled: false,
+                    },
+                    JSONEdge {
+                        source: "1".to_string(),
+                        target: "2".to_string(),
+                        lines: vec![],
+                        nodes: vec![],
+                        source_direction: None,
+                        target_direction: None,
+                        bundled: false,
+                    },
+                ],
+                interchanges: vec![
+                    JSONInterchange {
+                        source: "0".to_string(),
+                        target: "1".to_string(),
+                    },
+                    JSONInterchange {
+                        source: "1".to_string(),
+                        target: "2".to_string(),
+                    },
+                ],
+                background: Vec::new(),
+            },
+            canvas,
+            &mut report,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result
+                .get_stations()
+                .len(),
+            2
+        );
+        assert!(
+            result
+                .get_lines()
+                .is_empty()
+        );
+        assert_eq!(
+            result
+                .get_edges()
+                .len(),
+            1
+        );
+        assert_eq!(
+            report
+                .into_warnings()
+                .iter()
+                .map(|w| w.get_element())
+                .collect::<Vec<_>>(),
+            vec![
+                "station 2",
+                "line 0",
+                "edge 0-1",
+                "edge 1-2",
+                "interchange 1-2"
+            ]
+        );
     }
 }
@@ -2,10 +2,14 @@
 
 use super::json_models::{
     EdgeNode,
+    JSONBackgroundShape,
     JSONEdge,
+    JSONInterchange,
     JSONLine,
     JSONMap,
+    JSONRoutePattern,
     JSONStation,
+    JSON_VERSION,
 };
 use crate::{
     components::CanvasState,
@@ -41,6 +45,8 @@ fn encode_station(station: &Station, state: CanvasState) -> JSONStation {
         x: pos.0,
         y: pos.1,
         name,
+        weight: station.get_weight(),
+        label_offset: Some(station.get_label_offset()).filter(|offset| *offset != (0, 0)),
     }
 }
 
@@ -64,10 +70,31 @@ fn encode_line(line: &Line) -> JSONLine {
         )
     };
 
+    let patterns = line
+        .get_patterns()
+        .iter()
+        .map(|pattern| {
+            JSONRoutePattern {
+                name: Some(
+                    pattern
+                        .get_name()
+                        .to_owned(),
+                )
+                .filter(|name| !name.is_empty()),
+                stations: pattern
+                    .get_stations()
+                    .iter()
+                    .map(|s| "s".to_owned() + &u64::from(*s).to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+
     JSONLine {
         id: "l".to_owned() + &u64::from(line.get_id()).to_string(),
         name,
         color,
+        patterns,
     }
 }
 
@@ -103,15 +130,21 @@ fn encode_edge(edge: &Edge, state: CanvasState) -> JSONEdge {
         target,
         nodes,
         lines,
+        source_direction: edge.get_required_direction(edge.get_from()),
+        target_direction: edge.get_required_direction(edge.get_to()),
+        bundled: edge.is_bundled(),
     }
 }
 
 /// Translates the [`Map`] to a [`JSONMap`]
 pub fn map_to_json(graph: &Map, state: CanvasState) -> JSONMap {
     let mut json_map = JSONMap {
+        version: JSON_VERSION,
         stations: Vec::new(),
         lines: Vec::new(),
         edges: Vec::new(),
+        interchanges: Vec::new(),
+        background: Vec::new(),
     };
 
     let graph = graph.without_checkpoints();
@@ -168,5 +201,39 @@ pub fn map_to_json(graph: &Map, state: CanvasState) -> JSONMap {
             });
     }
 
+    // Add interchanges
+    json_map.interchanges = graph
+        .get_interchanges()
+        .iter()
+        .map(|i| {
+            let (source, target) = i.get_stations();
+            JSONInterchange {
+                source: "s".to_owned() + &u64::from(source).to_string(),
+                target: "s".to_owned() + &u64::from(target).to_string(),
+            }
+        })
+        .collect();
+
+    // Add background shapes
+    json_map.background = graph
+        .get_background()
+        .iter()
+        .map(|(outline, (r, g, b))| {
+            JSONBackgroundShape {
+                color: format!("#{r:02X}{g:02X}{b:02X}"),
+                outline: outline
+                    .iter()
+                    .map(|node| {
+                        let pos = node.to_canvas_pos(state);
+                        EdgeNode {
+                            x: pos.0,
+                            y: pos.1,
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
     json_map
 }
@@ -9,6 +9,17 @@ use serde::{
     Serialize,
 };
 
+use crate::algorithms::EdgeDirection;
+
+/// The version of the JSON format written by the editor.
+pub const JSON_VERSION: u64 = 1;
+
+/// The version of files without a version field, which were written before
+/// versions were added and are in the first version of the format.
+pub fn legacy_version() -> u64 {
+    1
+}
+
 /// Represents a node in an edge for the JSON file.
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct EdgeNode {
@@ -24,6 +35,27 @@ pub struct JSONEdge {
     #[serde(default)]
     pub nodes: Vec<EdgeNode>,
     pub lines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_direction: Option<EdgeDirection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_direction: Option<EdgeDirection>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bundled: bool,
+}
+
+/// Represents an out-of-station interchange between two stations for the JSON
+/// file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct JSONInterchange {
+    pub source: String,
+    pub target: String,
+}
+
+/// Represents a service pattern of a line for the JSON file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct JSONRoutePattern {
+    pub name: Option<String>,
+    pub stations: Vec<String>,
 }
 
 /// Represents a line for the JSON file.
@@ -32,6 +64,8 @@ pub struct JSONLine {
     pub id: String,
     pub name: Option<String>,
     pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<JSONRoutePattern>,
 }
 
 /// Represents a station for the JSON file.
@@ -41,12 +75,39 @@ pub struct JSONStation {
     pub name: Option<String>,
     pub x: f64,
     pub y: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_offset: Option<(i32, i32)>,
+}
+
+/// Represents a decorative shape drawn behind the map for the JSON file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct JSONBackgroundShape {
+    pub color: String,
+    pub outline: Vec<EdgeNode>,
 }
 
 /// Represents the whole map in the JSON file.
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct JSONMap {
+    #[serde(default = "legacy_version")]
+    pub version: u64,
     pub stations: Vec<JSONStation>,
     pub lines: Vec<JSONLine>,
     pub edges: Vec<JSONEdge>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interchanges: Vec<JSONInterchange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub background: Vec<JSONBackgroundShape>,
+}
+
+/// Represents a node of the grid occupied by a station or edge, for exporting
+/// the occupied nodes of an algorithm run.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct JSONOccupiedNode {
+    pub x: i32,
+    pub y: i32,
+    pub kind: String,
+    pub id: String,
 }
@@ -1,29 +1,159 @@
 //! This module provides the capability of decoding JSON data into the
 //! [`Map`] struct used in this project and vice versa.
 
-use serde_json::from_str;
+use itertools::Itertools;
+use serde_json::{
+    from_str,
+    from_value,
+    Value,
+};
 
+mod compact;
 mod decode;
 mod encode;
 mod json_models;
+mod topology;
 
+use compact::CompactJSONMap;
 use decode::json_to_map;
 use encode::map_to_json;
-use json_models::JSONMap;
+use json_models::{
+    JSONMap,
+    JSONOccupiedNode,
+    JSON_VERSION,
+};
+use topology::{
+    topology_to_map,
+    TopologyJSONMap,
+    TOPOLOGY_VERSION,
+};
 
-use super::Result;
+use super::{
+    DecodeReport,
+    DecodeWarning,
+    Result,
+};
 use crate::{
+    algorithms::{
+        OccupiedNode,
+        OccupiedNodes,
+    },
     components::CanvasState,
     models::Map,
+    Error,
 };
 
-/// Decode the given JSON string into a [`Map`] struct.
+/// A JSON file parsed into one of the formats it can be in.
+enum ParsedJSON {
+    /// A map in the regular or the compact format.
+    Map(JSONMap),
+    /// A map in the topology format.
+    Topology(TopologyJSONMap),
+}
+
+/// Parse the given JSON string once and read it in the format it is in. Files
+/// in the topology format are marked by their `topology` field holding the
+/// version of the format, all others are in the regular or compact format.
+fn parse_json(input: &str) -> Result<ParsedJSON> {
+    let value: Value = from_str(input)?;
+
+    if value
+        .get("topology")
+        .is_some()
+    {
+        return Ok(ParsedJSON::Topology(parse_topology(
+            value,
+        )?));
+    }
+
+    Ok(ParsedJSON::Map(parse_json_map(value)?))
+}
+
+/// Read the given JSON value into a [`JSONMap`], detecting if it is in the
+/// regular or the compact format and which version of the format it uses.
+/// Files without a version are read as the first version.
+fn parse_json_map(value: Value) -> Result<JSONMap> {
+    let compact = value
+        .get("s")
+        .is_some();
+    let version = value
+        .get(if compact { "v" } else { "version" })
+        .map_or(Some(1), Value::as_u64)
+        .ok_or_else(|| Error::decode_error("the version of the file is not a number"))?;
+
+    match version {
+        1 => read_v1(value, compact),
+        _ => {
+            Err(Error::decode_error(format!(
+                "the file uses version {version} of the format, but only up to version {JSON_VERSION} is supported"
+            )))
+        },
+    }
+}
+
+/// Read the given JSON value into a [`TopologyJSONMap`], checking the version
+/// of the topology format held by its `topology` field.
+fn parse_topology(value: Value) -> Result<TopologyJSONMap> {
+    match value
+        .get("topology")
+        .and_then(Value::as_u64)
+    {
+        Some(TOPOLOGY_VERSION) => Ok(from_value(value)?),
+        Some(version) => {
+            Err(Error::decode_error(format!(
+                "the file uses version {version} of the topology format, but only up to version {TOPOLOGY_VERSION} is supported"
+            )))
+        },
+        None => Err(Error::decode_error("the version of the file is not a number")),
+    }
+}
+
+/// Read a file in the first version of the format, which is the current one.
+/// Readers for older versions should migrate their files forward to the
+/// current format, so the decoder only has to handle a single version.
+fn read_v1(value: Value, compact: bool) -> Result<JSONMap> {
+    if compact {
+        Ok(from_value::<CompactJSONMap>(value)?.into())
+    } else {
+        Ok(from_value(value)?)
+    }
+}
+
+/// Decode the given JSON string, in either the regular, the compact or the
+/// topology format, into a [`Map`] struct.
 /// This decoder also requires the target grid square size to know which station
 /// goes onto which grid node.
 pub fn decode_map(input: &str, state: CanvasState) -> Result<Map> {
-    let decoded: JSONMap = from_str(input)?;
+    match parse_json(input)? {
+        ParsedJSON::Topology(topology) => topology_to_map(topology, state),
+        ParsedJSON::Map(decoded) => {
+            json_to_map(
+                decoded,
+                state,
+                &mut DecodeReport::strict(),
+            )
+        },
+    }
+}
+
+/// Decode the given JSON string into a [`Map`] struct like [`decode_map`], but
+/// skip any invalid stations, lines or edges instead of failing. Returns a
+/// warning for every skipped element next to the map.
+pub fn decode_map_lenient(input: &str, state: CanvasState) -> Result<(Map, Vec<DecodeWarning>)> {
+    let decoded = match parse_json(input)? {
+        ParsedJSON::Topology(topology) => {
+            return Ok((
+                topology_to_map(topology, state)?,
+                Vec::new(),
+            ));
+        },
+        ParsedJSON::Map(decoded) => decoded,
+    };
+
+    let mut report = DecodeReport::lenient();
+    let map = json_to_map(decoded, state, &mut report)?;
 
-    json_to_map(decoded, state)
+    Ok((map, report.into_warnings()))
 }
 
 /// Encode the given [`Map`] into a JSON string.
@@ -33,9 +163,64 @@ pub fn encode_map(map: &Map, state: CanvasState) -> Result<String> {
     serde_json::to_string(&json_map).map_err(Into::into)
 }
 
+/// Encode the given [`Map`] into a JSON string in the compact format, which
+/// uses short keys and arrays instead of objects to reduce the file size of
+/// large maps.
+pub fn encode_map_compact(map: &Map, state: CanvasState) -> Result<String> {
+    let json_map = map_to_json(map, state);
+
+    serde_json::to_string(&CompactJSONMap::from(&json_map)).map_err(Into::into)
+}
+
+/// Encode the given [`Map`] into a JSON string in the topology format, which
+/// only describes which stations are connected by which lines and leaves out
+/// all coordinates and routed edges. Decoding it gives a map that still has to
+/// be laid out.
+pub fn encode_topology(map: &Map, state: CanvasState) -> Result<String> {
+    let json_map = map_to_json(map, state);
+
+    serde_json::to_string(&TopologyJSONMap::from(json_map)).map_err(Into::into)
+}
+
+/// Encode the given [`OccupiedNodes`] into a JSON string, listing for every
+/// node if it is occupied by a station or an edge and by which one. The nodes
+/// are sorted by their coordinates, so the output of different runs can be
+/// compared.
+pub fn encode_occupied_nodes(occupied: &OccupiedNodes) -> Result<String> {
+    let nodes = occupied
+        .iter()
+        .sorted_by_key(|(node, _)| (node.0, node.1))
+        .map(|(node, occupier)| {
+            let (kind, id) = match occupier {
+                OccupiedNode::Station(id) => ("station", format!("s{id}")),
+                OccupiedNode::Edge(id) => ("edge", format!("e{id}")),
+            };
+
+            JSONOccupiedNode {
+                x: node.0,
+                y: node.1,
+                kind: kind.to_owned(),
+                id,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string(&nodes).map_err(Into::into)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        algorithms::EdgeDirection,
+        models::{
+            Interchange,
+            Line,
+            RoutePattern,
+            Station,
+        },
+        utils::graphml,
+    };
 
     #[test]
     fn test_decode_map() {
@@ -59,6 +244,34 @@ mod tests {
         assert_eq!(result_station.get_name(), "test-2");
     }
 
+    #[test]
+    fn test_decode_map_square_size() {
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+
+        for (square_size, zoom_factor) in [(5, 1.0), (10, 1.0), (10, 1.5)] {
+            let mut canvas = CanvasState::new();
+            canvas.set_size((300.0, 300.0));
+            canvas.set_square_size(square_size);
+            canvas.set_zoom_factor(zoom_factor);
+            let (width, height) = canvas.get_visible_size();
+
+            let result = decode_map(&test_file_content, canvas).expect("failed to decode json");
+
+            for station in result.get_stations() {
+                let pos = station.get_pos();
+                assert!(
+                    pos.0 >= 0 && pos.0 <= width as i32,
+                    "station {pos} outside of width {width} for square size {square_size}"
+                );
+                assert!(
+                    pos.1 >= 0 && pos.1 <= height as i32,
+                    "station {pos} outside of height {height} for square size {square_size}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_encode_map() {
         let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
@@ -70,11 +283,452 @@ mod tests {
         let map = decode_map(&test_file_content, canvas).expect("failed to decode json");
         let result = encode_map(&map, canvas).expect("failed to encode map");
 
+        // The test file is from before the version field was added, which is
+        // now written first.
+        let expected = test_file_content
+            .replace("\n", "")
+            .replace(" ", "");
         assert_eq!(
             result,
-            test_file_content
-                .replace("\n", "")
-                .replace(" ", "")
+            format!(
+                "{{\"version\":{JSON_VERSION},{}",
+                &expected[1..]
+            )
+        );
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let json_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+        let graphml_content = std::fs::read_to_string("existing_maps/small_test.graphml")
+            .expect("test data file does not exist");
+        let maps = [
+            decode_map(&json_content, canvas).expect("failed to decode json"),
+            graphml::decode_map(&graphml_content, canvas).expect("failed to decode graphml"),
+        ];
+
+        for mut map in maps {
+            let mut stations = map
+                .get_stations()
+                .into_iter()
+                .map(Station::get_id)
+                .collect::<Vec<_>>();
+            stations.sort();
+            map.add_interchange(stations[0], stations[1])
+                .unwrap();
+
+            let mut line = map
+                .get_lines()
+                .into_iter()
+                .min_by_key(|l| l.get_id())
+                .expect("map has no lines")
+                .clone();
+            let pattern_stations = line.get_stations()[..2].to_vec();
+            line.set_patterns(vec![RoutePattern::new(
+                &"limited",
+                pattern_stations,
+            )]);
+            map.add_line(line);
+
+            let outline = map
+                .get_stations()
+                .into_iter()
+                .map(Station::get_pos)
+                .take(3)
+                .collect();
+            map.set_background(vec![(outline, (170, 211, 223))]);
+
+            let edge = map.get_edges_sorted()[0].get_id();
+            map.get_mut_edge(edge)
+                .unwrap()
+                .set_bundled(true);
+
+            let regular = encode_map(&map, canvas).expect("failed to encode map");
+            assert!(regular.contains("\"patterns\":[{\"name\":\"limited\""));
+            assert!(regular.contains("\"bundled\":true"));
+            assert!(regular.contains("\"background\":[{\"color\":\"#AAD3DF\""));
+
+            let compact = encode_map_compact(&map, canvas).expect("failed to encode compact map");
+            assert!(compact.len() < regular.len());
+            assert!(compact.starts_with("{\"v\":1,\"s\":[["));
+
+            let result = decode_map(&compact, canvas).expect("failed to decode compact map");
+            assert_eq!(
+                encode_map(&result, canvas).expect("failed to encode map"),
+                regular
+            );
+            assert_eq!(
+                result.get_background(),
+                map.get_background()
+            );
+        }
+    }
+
+    #[test]
+    fn test_topology_round_trip() {
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let map = decode_map(&test_file_content, canvas).expect("failed to decode json");
+        let topology = encode_topology(&map, canvas).expect("failed to encode topology");
+        assert!(topology.starts_with(r#"{"topology":1,"stations":[{"id":"s"#));
+        assert!(!topology.contains("\"x\""));
+        assert!(!topology.contains("\"nodes\""));
+
+        let result = decode_map(&topology, canvas).expect("failed to decode topology");
+        assert_eq!(
+            encode_topology(&result, canvas).expect("failed to encode topology"),
+            topology
+        );
+        assert_eq!(
+            result
+                .get_edges()
+                .len(),
+            map.get_edges()
+                .len()
+        );
+
+        // Without coordinates, every station gets its own spot to be laid out
+        // from.
+        let positions = result
+            .get_stations()
+            .into_iter()
+            .map(Station::get_pos)
+            .unique()
+            .count();
+        assert_eq!(
+            positions,
+            map.get_stations()
+                .len()
+        );
+
+        assert!(decode_map(
+            r#"{"topology":1,"stations":[{"id":"s1","name":null}],"lines":[],"edges":[{"source":"s1","target":"s2","lines":[]}]}"#,
+            canvas
+        )
+        .is_err());
+        assert!(decode_map(
+            r#"{"topology":2,"stations":[],"lines":[],"edges":[]}"#,
+            canvas
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_topology_parallel_edges() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let topology = r##"{"topology":1,"stations":[{"id":"s1","name":null},{"id":"s2","name":null}],"lines":[{"id":"l1","name":null,"color":"#FF0000"},{"id":"l2","name":null,"color":"#0000FF"}],"edges":[{"source":"s1","target":"s2","lines":["l1"]},{"source":"s1","target":"s2","lines":["l2"]}]}"##;
+        let map = decode_map(topology, canvas).expect("failed to decode topology");
+
+        // Every entry becomes its own edge, each on its own line.
+        let edges = map.get_edges();
+        assert_eq!(edges.len(), 2);
+        for edge in edges {
+            assert_eq!(
+                edge.get_lines()
+                    .len(),
+                1
+            );
+        }
+
+        let encoded = encode_topology(&map, canvas).expect("failed to encode topology");
+        assert_eq!(
+            decode_map(&encoded, canvas)
+                .expect("failed to decode topology")
+                .get_edges()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_parallel_edges_round_trip() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let mut map = Map::new();
+        let station1 = Station::new((0, 0).into(), None);
+        let station2 = Station::new((4, 0).into(), None);
+        let (id1, id2) = (station1.get_id(), station2.get_id());
+        map.add_station(station1);
+        map.add_station(station2);
+
+        let mut express = Line::new(None);
+        express.add_station(&mut map, id1, None, None);
+        express.add_station(&mut map, id2, Some(id1), None);
+        map.add_line(express);
+
+        let mut local = Line::new(None);
+        let local_edge = map.add_parallel_edge(id1, id2);
+        local.add_edge(local_edge, &mut map);
+        map.add_line(local);
+        map.get_mut_edge(local_edge)
+            .unwrap()
+            .set_nodes(vec![(2, 2).into()]);
+
+        let encoded = encode_map(&map, canvas).expect("failed to encode map");
+        let result = decode_map(&encoded, canvas).expect("failed to decode encoded map");
+
+        // Both edges are still there, each on its own line and with its own
+        // nodes.
+        let edges = result
+            .get_edge_ids_between(id1, id2)
+            .into_iter()
+            .map(|id| {
+                result
+                    .get_edge(id)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(edges.len(), 2);
+        for edge in &edges {
+            assert_eq!(
+                edge.get_lines()
+                    .len(),
+                1
+            );
+        }
+        assert_eq!(
+            edges
+                .iter()
+                .map(|edge| {
+                    edge.get_nodes()
+                        .len()
+                })
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        // Entries along the same route are one edge listed once per line.
+        let per_line = r##"{"stations":[{"id":"s1","name":null,"x":0.0,"y":0.0},{"id":"s2","name":null,"x":20.0,"y":0.0}],"lines":[{"id":"l1","name":null,"color":"#FF0000"},{"id":"l2","name":null,"color":"#0000FF"}],"edges":[{"source":"s1","target":"s2","lines":["l1"]},{"source":"s2","target":"s1","lines":["l2"]}]}"##;
+        let result = decode_map(per_line, canvas).expect("failed to decode json");
+        assert_eq!(
+            result
+                .get_edges()
+                .into_iter()
+                .map(|edge| {
+                    edge.get_lines()
+                        .len()
+                })
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_encode_map_weight() {
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let mut map = decode_map(&test_file_content, canvas).expect("failed to decode json");
+        let station_id = map
+            .get_stations()
+            .first()
+            .expect("map has no stations")
+            .get_id();
+        map.get_mut_station(station_id)
+            .unwrap()
+            .set_weight(Some(1520.5));
+
+        let encoded = encode_map(&map, canvas).expect("failed to encode map");
+        let result = decode_map(&encoded, canvas).expect("failed to decode encoded map");
+
+        for station in result.get_stations() {
+            let expected = if station.get_id() == station_id {
+                Some(1520.5)
+            } else {
+                None
+            };
+            assert_eq!(station.get_weight(), expected);
+        }
+    }
+
+    #[test]
+    fn test_encode_map_label_offset() {
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let mut map = decode_map(&test_file_content, canvas).expect("failed to decode json");
+        let station_id = map
+            .get_stations()
+            .first()
+            .expect("map has no stations")
+            .get_id();
+        map.get_mut_station(station_id)
+            .unwrap()
+            .set_label_offset((2, -1));
+
+        for encoded in [
+            encode_map(&map, canvas).expect("failed to encode map"),
+            encode_map_compact(&map, canvas).expect("failed to encode compact map"),
+        ] {
+            let result = decode_map(&encoded, canvas).expect("failed to decode encoded map");
+
+            for station in result.get_stations() {
+                let expected = if station.get_id() == station_id {
+                    (2, -1)
+                } else {
+                    (0, 0)
+                };
+                assert_eq!(station.get_label_offset(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_map_required_direction() {
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let mut map = decode_map(&test_file_content, canvas).expect("failed to decode json");
+        let edge = map
+            .get_edges()
+            .first()
+            .copied()
+            .cloned()
+            .expect("map has no edges");
+        map.get_mut_edge(edge.get_id())
+            .unwrap()
+            .set_required_direction(
+                edge.get_to(),
+                Some(EdgeDirection::DiagUpLeft),
+            );
+
+        for encoded in [
+            encode_map(&map, canvas).expect("failed to encode map"),
+            encode_map_compact(&map, canvas).expect("failed to encode compact map"),
+        ] {
+            let result = decode_map(&encoded, canvas).expect("failed to decode encoded map");
+
+            for result_edge in result.get_edges() {
+                let expected = if result_edge.get_from() == edge.get_from()
+                    && result_edge.get_to() == edge.get_to()
+                {
+                    Some(EdgeDirection::DiagUpLeft)
+                } else {
+                    None
+                };
+                assert_eq!(
+                    result_edge.get_required_direction(result_edge.get_to()),
+                    expected
+                );
+                assert_eq!(
+                    result_edge.get_required_direction(result_edge.get_from()),
+                    None
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_map_interchanges() {
+        let test_file_content = std::fs::read_to_string("existing_maps/small_test.json")
+            .expect("test data file does not exist");
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        canvas.set_size((100.0, 100.0));
+
+        let mut map = decode_map(&test_file_content, canvas).expect("failed to decode json");
+        let mut stations = map
+            .get_stations()
+            .into_iter()
+            .map(Station::get_id)
+            .collect::<Vec<_>>();
+        stations.sort();
+        map.add_interchange(stations[1], stations[0])
+            .unwrap();
+
+        let encoded = encode_map(&map, canvas).expect("failed to encode map");
+        let result = decode_map(&encoded, canvas).expect("failed to decode encoded map");
+
+        assert_eq!(
+            result.get_interchanges(),
+            &[Interchange::new(stations[0], stations[1])]
+        );
+    }
+
+    #[test]
+    fn test_json_versions() {
+        // Files from before the version field was added are read as version 1.
+        let legacy = r#"{"stations":[{"id":"s1","name":"a","x":0.0,"y":0.0}],"lines":[],"edges":[]}"#;
+        let parsed = parse_json_map(from_str(legacy).unwrap()).expect("failed to parse legacy file");
+        assert_eq!(parsed.version, 1);
+        assert_eq!(
+            parsed
+                .stations
+                .len(),
+            1
+        );
+        assert!(parse_json_map(from_str(r#"{"s":[],"l":[],"e":[]}"#).unwrap()).is_ok());
+
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(5);
+        let map = decode_map(legacy, canvas).expect("failed to decode legacy file");
+        for encoded in [
+            encode_map(&map, canvas).unwrap(),
+            encode_map_compact(&map, canvas).unwrap(),
+        ] {
+            assert_eq!(
+                parse_json_map(from_str(&encoded).unwrap())
+                    .unwrap()
+                    .version,
+                JSON_VERSION
+            );
+        }
+        assert!(
+            encode_map(&map, canvas)
+                .unwrap()
+                .starts_with(r#"{"version":1,"#)
+        );
+
+        assert!(matches!(
+            parse_json_map(from_str(r#"{"version":2,"stations":[],"lines":[],"edges":[]}"#).unwrap()),
+            Err(Error::DecodeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_encode_occupied_nodes() {
+        let occupied = OccupiedNodes::from([
+            (
+                (2, 0).into(),
+                OccupiedNode::Station(1.into()),
+            ),
+            (
+                (0, 1).into(),
+                OccupiedNode::Edge(4.into()),
+            ),
+            (
+                (0, 0).into(),
+                OccupiedNode::Station(2.into()),
+            ),
+        ]);
+
+        assert_eq!(
+            encode_occupied_nodes(&occupied).unwrap(),
+            r#"[{"x":0,"y":0,"kind":"station","id":"s2"},{"x":0,"y":1,"kind":"edge","id":"e4"},{"x":2,"y":0,"kind":"station","id":"s1"}]"#
         );
     }
 }
@@ -0,0 +1,214 @@
+//! Contains the topology-only variant of the JSON format and the conversion
+//! between it and the regular [`JSONMap`] and the [`Map`].
+//!
+//! The topology format only describes how the map is connected, for use in
+//! graph-analysis tools. It leaves out all coordinates, routed edge nodes,
+//! interchanges, route patterns and label offsets:
+//!
+//! - `topology`: the version of the format, which also marks the file as one,
+//! - `stations`: the stations as `{id, name}`,
+//! - `lines`: the lines as `{id, name, color}`,
+//! - `edges`: the edges as `{source, target, lines}`.
+//!
+//! As there are no coordinates, a decoded map is laid out on a default grid
+//! and needs to be arranged by the algorithm afterwards.
+
+// No need to document all fields on the model structs here.
+#![allow(clippy::missing_docs_in_private_items)]
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::json_models::JSONMap;
+use crate::{
+    components::CanvasState,
+    models::{
+        GridNode,
+        Line,
+        Map,
+        Station,
+    },
+    utils::{
+        parsing::{
+            parse_color,
+            parse_id,
+        },
+        Error,
+        Result,
+    },
+};
+
+/// The version of the topology format written by the editor.
+pub const TOPOLOGY_VERSION: u64 = 1;
+
+/// The amount of grid nodes between two stations in the default layout.
+const STATION_SPACING: i32 = 3;
+
+/// Represents a station for the topology JSON file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct TopologyStation {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+/// Represents a line for the topology JSON file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct TopologyLine {
+    pub id: String,
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+/// Represents a connection between two stations for the topology JSON file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct TopologyEdge {
+    pub source: String,
+    pub target: String,
+    pub lines: Vec<String>,
+}
+
+/// Represents the whole map in the topology JSON file.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct TopologyJSONMap {
+    #[serde(rename = "topology")]
+    pub version: u64,
+    pub stations: Vec<TopologyStation>,
+    pub lines: Vec<TopologyLine>,
+    pub edges: Vec<TopologyEdge>,
+}
+
+impl From<JSONMap> for TopologyJSONMap {
+    fn from(map: JSONMap) -> Self {
+        Self {
+            version: TOPOLOGY_VERSION,
+            stations: map
+                .stations
+                .into_iter()
+                .map(|s| {
+                    TopologyStation {
+                        id: s.id,
+                        name: s.name,
+                    }
+                })
+                .collect(),
+            lines: map
+                .lines
+                .into_iter()
+                .map(|l| {
+                    TopologyLine {
+                        id: l.id,
+                        name: l.name,
+                        color: l.color,
+                    }
+                })
+                .collect(),
+            edges: map
+                .edges
+                .into_iter()
+                .map(|e| {
+                    TopologyEdge {
+                        source: e.source,
+                        target: e.target,
+                        lines: e.lines,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Translates the [`TopologyJSONMap`] to a [`Map`]. The stations are laid out
+/// in a square on a default grid within the visible part of the canvas, in the
+/// order they are listed in, for the algorithm to arrange afterwards.
+pub fn topology_to_map(topology: TopologyJSONMap, state: CanvasState) -> Result<Map> {
+    let mut map = Map::new();
+
+    let origin = state.get_offset();
+    let columns = (topology
+        .stations
+        .len() as f64)
+        .sqrt()
+        .ceil()
+        .max(1.0) as usize;
+
+    for (i, json_station) in topology
+        .stations
+        .into_iter()
+        .enumerate()
+    {
+        let id = parse_id(&json_station.id).into();
+        if map
+            .get_station(id)
+            .is_some()
+        {
+            return Err(Error::decode_error(format!(
+                "station {} is listed more than once",
+                json_station.id
+            )));
+        }
+
+        let pos = GridNode::new(
+            origin.0 + STATION_SPACING * ((i % columns) as i32 + 1),
+            origin.1 + STATION_SPACING * ((i / columns) as i32 + 1),
+        );
+        let mut station = Station::new(pos, Some(id));
+        if let Some(name) = json_station.name {
+            station.set_name(&name);
+        }
+
+        map.add_station(station);
+    }
+
+    for json_line in topology.lines {
+        let mut line = Line::new(Some(parse_id(&json_line.id).into()));
+
+        if let Some(name) = json_line.name {
+            line.set_name(&name);
+        }
+        if let Some(color) = json_line.color {
+            line.set_color(parse_color(&color)?);
+        }
+
+        map.add_line(line);
+    }
+
+    for json_edge in topology.edges {
+        let source = parse_id(&json_edge.source).into();
+        let target = parse_id(&json_edge.target).into();
+
+        if map
+            .get_station(source)
+            .is_none()
+            || map
+                .get_station(target)
+                .is_none()
+        {
+            return Err(Error::decode_error(format!(
+                "edge references non-existent station {} or {}",
+                json_edge.source, json_edge.target
+            )));
+        }
+
+        // Stations can be connected by several edges, so every entry gets its
+        // own edge instead of being merged into an existing one.
+        let edge_id = map.add_parallel_edge(source, target);
+
+        for line_id in &json_edge.lines {
+            let mut line = map
+                .get_line(parse_id(line_id).into())
+                .cloned()
+                .ok_or(Error::decode_error(format!(
+                    "edge references non-existent line {line_id}",
+                )))?;
+
+            line.add_edge(edge_id, &mut map);
+            map.add_line(line);
+        }
+    }
+
+    map.quickcalc_edges();
+
+    Ok(map)
+}
@@ -0,0 +1,168 @@
+//! Contains the functionality for exporting a [`Map`] as a Mermaid flowchart.
+//! This only contains the topology of the map, so no coordinates are included.
+
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::models::{
+    Edge,
+    Map,
+    Station,
+};
+
+/// Escape the characters Mermaid treats specially inside a quoted label.
+fn escape_label(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+
+    for c in label.chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '|' => escaped.push_str("#124;"),
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Get the Mermaid node id of the given station.
+fn station_node_id(station: &Station) -> String {
+    format!("s{}", station.get_id())
+}
+
+/// Get the label of the given edge, consisting of the names of the lines
+/// following it.
+fn edge_label(map: &Map, edge: &Edge) -> String {
+    edge.get_lines()
+        .iter()
+        .filter_map(|id| map.get_line(*id))
+        .map(|line| {
+            if line
+                .get_name()
+                .is_empty()
+            {
+                line.get_id()
+                    .to_string()
+            } else {
+                line.get_name()
+                    .to_owned()
+            }
+        })
+        .join(", ")
+}
+
+/// Convert the given [`Map`] into a Mermaid `graph LR` flowchart, with a node
+/// for every station and a link for every edge, labeled by the lines on it.
+/// Checkpoints are left out.
+pub fn map_to_mermaid(map: &Map) -> String {
+    let map = map.without_checkpoints();
+    let mut output = "graph LR\n".to_owned();
+
+    for station in map
+        .get_stations()
+        .into_iter()
+        .sorted_by_key(|s| s.get_id())
+    {
+        let name = if station
+            .get_name()
+            .is_empty()
+        {
+            station
+                .get_id()
+                .to_string()
+        } else {
+            station
+                .get_name()
+                .to_owned()
+        };
+
+        let _ = writeln!(
+            output,
+            "    {}[\"{}\"]",
+            station_node_id(station),
+            escape_label(&name)
+        );
+    }
+
+    for edge in map
+        .get_edges()
+        .into_iter()
+        .sorted_by_key(|e| e.get_id())
+    {
+        let (Some(from), Some(to)) = (
+            map.get_station(edge.get_from()),
+            map.get_station(edge.get_to()),
+        ) else {
+            continue;
+        };
+
+        let label = edge_label(&map, edge);
+        if label.is_empty() {
+            let _ = writeln!(
+                output,
+                "    {} --- {}",
+                station_node_id(from),
+                station_node_id(to)
+            );
+        } else {
+            let _ = writeln!(
+                output,
+                "    {} ---|\"{}\"| {}",
+                station_node_id(from),
+                escape_label(&label),
+                station_node_id(to)
+            );
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Line,
+        StationID,
+    };
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("Central"), "Central");
+        assert_eq!(
+            escape_label("\"A\" <B> #1 | C"),
+            "#quot;A#quot; #lt;B#gt; #35;1 #124; C"
+        );
+    }
+
+    #[test]
+    fn test_map_to_mermaid() {
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+
+        let mut named = Station::new((0, 0).into(), Some(station1));
+        named.set_name(&"Central \"Main\"");
+        map.add_station(named);
+        map.add_station(Station::new(
+            (0, 2).into(),
+            Some(station2),
+        ));
+
+        let mut line = Line::new(Some(3.into()));
+        line.set_name(&"Red");
+        line.add_station(&mut map, station1, None, None);
+        line.add_station(&mut map, station2, Some(station1), None);
+        map.add_line(line);
+
+        assert_eq!(
+            map_to_mermaid(&map),
+            "graph LR\n    s1[\"Central #quot;Main#quot;\"]\n    s2[\"2\"]\n    s1 ---|\"Red\"| s2\n"
+        );
+    }
+}
@@ -1,16 +1,30 @@
 //! Contains utility functions and structs that can be useful
 //! everywhere else in the project, but may not fit in other modules.
 
+pub mod adjacency;
+pub mod apng;
+mod decode_warning;
+pub mod download;
 mod error;
+pub mod geojson;
 pub mod graphml;
 mod id_manager;
 pub mod json;
 pub mod line_sections;
+pub mod mermaid;
 mod parsing;
+pub mod svg;
 
+#[cfg(feature = "batch")]
+pub mod batch_export;
 #[cfg(feature = "heatmap")]
 pub mod heatmap_data;
 
+pub use decode_warning::{
+    flag_non_octilinear_segments,
+    DecodeWarning,
+};
+use decode_warning::DecodeReport;
 pub use error::{
     Error,
     Result,
@@ -19,9 +33,15 @@ pub use id_manager::{
     IDData,
     IDManager,
 };
-pub use parsing::parse_color;
+pub use parsing::{
+    parse_color,
+    GridTransform,
+};
 
-use crate::models::GridNode;
+use crate::{
+    algorithms::GridMode,
+    models::GridNode,
+};
 
 /// Compares two floats to determine if they do not differ more than 1.0.
 /// This can be used to see if two coordinates are for the same pixel on the
@@ -42,6 +62,41 @@ pub fn calculate_angle(first: GridNode, second: GridNode, third: GridNode) -> f6
         .round()
 }
 
+/// Calculates the angle formed by three grid nodes on a grid of the given mode
+/// and returns it in rounded degrees. The second point is assumed to be the
+/// middle node where the angle is located.
+pub fn calculate_grid_angle(
+    mode: GridMode,
+    first: GridNode,
+    second: GridNode,
+    third: GridNode,
+) -> f64 {
+    if mode == GridMode::Octilinear {
+        return calculate_angle(first, second, third);
+    }
+
+    // Convert the axial coordinates of the hexagonal grid to cartesian ones, so
+    // all neighbors are at the same distance.
+    let cartesian = |node: GridNode| {
+        (
+            f64::from(node.0) + f64::from(node.1) / 2.0,
+            f64::from(node.1) * 3f64.sqrt() / 2.0,
+        )
+    };
+    let (first, second, third) = (
+        cartesian(first),
+        cartesian(second),
+        cartesian(third),
+    );
+
+    let l = (first.1 - second.1).atan2(first.0 - second.0);
+    let r = (third.1 - second.1).atan2(third.0 - second.0);
+    (l - r)
+        .abs()
+        .to_degrees()
+        .round()
+}
+
 /// Calculates the offset of the grid node from the canvas offset.
 pub fn canvas_offset_to_grid_offset(offset: (f64, f64), square_size: f64) -> (i32, i32) {
     (
@@ -88,4 +143,37 @@ mod tests {
             90.0
         );
     }
+
+    #[test]
+    fn test_calculate_grid_angle() {
+        let second = GridNode::from((0, 0));
+        let left = GridNode::from((-1, 0));
+
+        let expected = [
+            ((1, 0), 180.0),
+            ((0, 1), 120.0),
+            ((-1, 1), 60.0),
+            ((1, -1), 120.0),
+            ((0, -1), 60.0),
+        ];
+        for (third, angle) in expected {
+            let third = GridNode::from(third);
+            let result = calculate_grid_angle(GridMode::Hexagonal, left, second, third);
+            assert_eq!(
+                result.min(360.0 - result),
+                angle,
+                "angle to {third}"
+            );
+        }
+
+        assert_eq!(
+            calculate_grid_angle(
+                GridMode::Octilinear,
+                GridNode::from((0, 0)),
+                GridNode::from((1, 1)),
+                GridNode::from((2, 0)),
+            ),
+            90.0
+        );
+    }
 }
@@ -6,11 +6,19 @@ use std::hash::{
     Hasher,
 };
 
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
 use super::Result;
-use crate::components::CanvasState;
+use crate::{
+    components::CanvasState,
+    models::GridNode,
+};
 
 /// Settings used to normalize coordinates.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub(super) struct NormalizationSettings {
     /// The minimum x coordinate.
     pub(super) min_x: f64,
@@ -122,6 +130,52 @@ pub(super) fn normalize_coordinate(x: f64, y: f64, settings: NormalizationSettin
     (x, y)
 }
 
+/// The transform used to place the coordinates of an imported map onto the
+/// grid. Kept with the map, so shapes given in the same coordinates as the
+/// stations of the imported file line up with them.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GridTransform {
+    /// The settings the coordinates were normalized with.
+    settings: NormalizationSettings,
+    /// The state of the canvas the normalized coordinates were placed on the
+    /// grid with.
+    state: CanvasState,
+    /// The factor the grid has been rescaled by since the import.
+    scale: f64,
+}
+
+impl GridTransform {
+    /// Create the transform from the given settings, as returned by
+    /// [`normalize_coords`], and the state of the canvas used when importing.
+    pub(super) fn new(settings: NormalizationSettings, state: CanvasState) -> Self {
+        Self {
+            settings,
+            state,
+            scale: 1.0,
+        }
+    }
+
+    /// Get the transform after the grid has been rescaled by the given factor,
+    /// as done by [`crate::models::Map::rescale_grid`].
+    pub fn rescaled(mut self, factor: f64) -> Self {
+        self.scale *= factor;
+        self
+    }
+
+    /// Get the grid node the given coordinate of the imported file ends up on.
+    pub(super) fn to_grid(self, x: f64, y: f64) -> GridNode {
+        let node = GridNode::from_canvas_pos(
+            normalize_coordinate(x, y, self.settings),
+            self.state,
+        );
+
+        GridNode::new(
+            (f64::from(node.0) * self.scale).round() as i32,
+            (f64::from(node.1) * self.scale).round() as i32,
+        )
+    }
+}
+
 /// Parse the given string into an u64 to create an ID from.
 pub(super) fn parse_id(given: &str) -> u64 {
     given
@@ -0,0 +1,331 @@
+//! Contains the functionality for exporting a [`Map`] as an SVG image.
+//! Every edge is drawn in the color of its first line, without offsetting
+//! parallel lines.
+
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::{
+    algorithms::{
+        station_label_direction,
+        upright_label_angle,
+        LabelObstacles,
+    },
+    components::CanvasState,
+    models::{
+        Edge,
+        Line,
+        Map,
+        Station,
+    },
+};
+
+/// Escape the characters that are not allowed inside SVG text and attributes.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Get the canvas positions of all points the given edge passes through,
+/// including the stations at its ends.
+fn edge_points(map: &Map, edge: &Edge, state: CanvasState) -> Vec<(f64, f64)> {
+    let from = map
+        .get_station(edge.get_from())
+        .map(Station::get_pos);
+    let to = map
+        .get_station(edge.get_to())
+        .map(Station::get_pos);
+
+    from.into_iter()
+        .chain(
+            edge.get_nodes()
+                .iter()
+                .copied(),
+        )
+        .chain(to)
+        .map(|node| node.to_canvas_pos(state))
+        .collect()
+}
+
+/// Write the name of the given station as a text element at the position it
+/// is drawn at on the canvas, avoiding the given obstacles and using the label
+/// font settings of the canvas state. Labels to the left of their station are
+/// anchored at their end, like on the canvas. Nothing is written for stations
+/// without a name, or if labels are hidden.
+fn write_station_label(
+    output: &mut String,
+    station: &Station,
+    obstacles: &LabelObstacles,
+    state: CanvasState,
+) {
+    if !state.is_labels_shown()
+        || station
+            .get_name()
+            .is_empty()
+    {
+        return;
+    }
+
+    let coord = station.get_canvas_pos(state);
+    let pos = station.get_label_pos(obstacles, state);
+    let (_, flipped) = upright_label_angle(
+        coord,
+        station_label_direction(coord, pos),
+    );
+
+    let _ = writeln!(
+        output,
+        "  <text x=\"{}\" y=\"{}\"{} font-family=\"{}\" font-size=\"{}\">{}</text>",
+        pos.0,
+        pos.1,
+        if flipped { " text-anchor=\"end\"" } else { "" },
+        state
+            .get_label_font()
+            .css_family(),
+        state.drawn_label_size(),
+        escape_text(station.get_name())
+    );
+}
+
+/// Write the background shapes of the given map as filled polygons.
+fn write_background(output: &mut String, map: &Map, state: CanvasState) {
+    for (outline, (r, g, b)) in map.get_background() {
+        let _ = writeln!(
+            output,
+            "  <polygon points=\"{}\" fill=\"rgb({r},{g},{b})\"/>",
+            outline
+                .iter()
+                .map(|node| {
+                    let (x, y) = node.to_canvas_pos(state);
+                    format!("{x},{y}")
+                })
+                .join(" ")
+        );
+    }
+}
+
+/// Convert the given [`Map`] into an SVG image, using the given canvas state
+/// to determine the size of the grid. The view box is fit around the map.
+pub fn map_to_svg(map: &Map, state: CanvasState) -> String {
+    let square_size = state.drawn_square_size();
+    let margin = square_size * 2.0;
+    let station_radius = square_size / 3.0;
+    let station_width = (square_size / 10.0 + 1.0).max(2.0);
+    let edge_width = square_size / 4.0;
+
+    let edges = map
+        .get_edges()
+        .into_iter()
+        .sorted_by_key(|e| e.get_id())
+        .map(|edge| (edge, edge_points(map, edge, state)))
+        .collect::<Vec<_>>();
+    let stations = map
+        .get_stations()
+        .into_iter()
+        .filter(|s| !s.is_checkpoint())
+        .sorted_by_key(|s| s.get_id())
+        .collect::<Vec<_>>();
+
+    let all_points = edges
+        .iter()
+        .flat_map(|(_, points)| points.iter())
+        .copied()
+        .chain(
+            stations
+                .iter()
+                .map(|s| s.get_canvas_pos(state)),
+        )
+        .collect::<Vec<_>>();
+    let (min_x, min_y, max_x, max_y) = all_points
+        .iter()
+        .fold(
+            (
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(min_x, min_y, max_x, max_y), (x, y)| {
+                (
+                    min_x.min(*x),
+                    min_y.min(*y),
+                    max_x.max(*x),
+                    max_y.max(*y),
+                )
+            },
+        );
+    let (min_x, min_y, max_x, max_y) = if all_points.is_empty() {
+        (0.0, 0.0, 0.0, 0.0)
+    } else {
+        (min_x, min_y, max_x, max_y)
+    };
+
+    let mut output = String::new();
+    // Writing to a string can not fail, so the results are ignored.
+    let _ = writeln!(
+        output,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+        min_x - margin,
+        min_y - margin,
+        max_x - min_x + 2.0 * margin,
+        max_y - min_y + 2.0 * margin
+    );
+
+    // The background shapes go first, so they end up behind the map.
+    write_background(&mut output, map, state);
+
+    for (edge, points) in &edges {
+        let (r, g, b) = edge
+            .get_lines()
+            .first()
+            .and_then(|id| map.get_line(*id))
+            .map_or((0, 0, 0), Line::get_color);
+
+        let _ = writeln!(
+            output,
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"rgb({r},{g},{b})\" \
+             stroke-width=\"{edge_width}\"/>",
+            points
+                .iter()
+                .map(|(x, y)| format!("{x},{y}"))
+                .join(" ")
+        );
+    }
+
+    let obstacles = LabelObstacles::new(map, state);
+    for station in &stations {
+        let (x, y) = station.get_canvas_pos(state);
+
+        let _ = writeln!(
+            output,
+            "  <circle cx=\"{x}\" cy=\"{y}\" r=\"{station_radius}\" fill=\"white\" \
+             stroke=\"black\" stroke-width=\"{station_width}\"/>",
+        );
+
+        write_station_label(&mut output, station, &obstacles, state);
+    }
+
+    output.push_str("</svg>\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        algorithms::LabelFont,
+        models::StationID,
+    };
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape_text("Central"), "Central");
+        assert_eq!(
+            escape_text("\"A\" & <B>"),
+            "&quot;A&quot; &amp; &lt;B&gt;"
+        );
+    }
+
+    #[test]
+    fn test_map_to_svg() {
+        let mut canvas = CanvasState::new();
+        canvas.set_square_size(10);
+
+        let mut map = Map::new();
+        let station1: StationID = 1.into();
+        let station2: StationID = 2.into();
+
+        let mut named = Station::new((1, 1).into(), Some(station1));
+        named.set_name(&"A & B");
+        map.add_station(named);
+        map.add_station(Station::new(
+            (3, 1).into(),
+            Some(station2),
+        ));
+
+        let edge_id = map.get_edge_id_between(station1, station2);
+        map.get_mut_edge(edge_id)
+            .unwrap()
+            .set_nodes(vec![(2, 1).into()]);
+
+        let mut line = Line::new(None);
+        line.set_color((255, 0, 0));
+        line.add_station(&mut map, station1, None, None);
+        line.add_station(&mut map, station2, Some(station1), None);
+        map.add_line(line);
+
+        let result = map_to_svg(&map, canvas);
+
+        assert!(
+            result.starts_with(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-10 -10 60 40\">"
+            )
+        );
+        assert!(result.contains(
+            "<polyline points=\"10,10 20,10 30,10\" fill=\"none\" stroke=\"rgb(255,0,0)\""
+        ));
+        assert_eq!(
+            result
+                .matches("<circle")
+                .count(),
+            2
+        );
+        assert!(result.contains("font-family=\"sans-serif\" font-size=\"8\">A &amp; B</text>"));
+
+        // The label is placed where it is drawn on the canvas.
+        let label_pos = map
+            .get_station(station1)
+            .unwrap()
+            .get_label_pos(
+                &LabelObstacles::new(&map, canvas),
+                canvas,
+            );
+        assert!(result.contains(&format!(
+            "<text x=\"{}\" y=\"{}\"",
+            label_pos.0, label_pos.1
+        )));
+
+        assert!(!result.contains("<polygon"));
+
+        map.set_background(vec![(
+            vec![
+                (0, 0).into(),
+                (2, 0).into(),
+                (1, 2).into(),
+            ],
+            (0, 0, 255),
+        )]);
+        let result = map_to_svg(&map, canvas);
+        let polygon = result
+            .find("<polygon points=\"0,0 20,0 10,20\" fill=\"rgb(0,0,255)\"/>")
+            .unwrap();
+        assert!(
+            polygon
+                < result
+                    .find("<polyline")
+                    .unwrap()
+        );
+
+        canvas.set_label_font(LabelFont::Serif);
+        canvas.set_label_size(1.5);
+        let result = map_to_svg(&map, canvas);
+        assert!(result.contains("font-family=\"serif\" font-size=\"15\""));
+        assert!(result.ends_with("</svg>\n"));
+
+        canvas.set_labels_shown(false);
+        let result = map_to_svg(&map, canvas);
+        assert!(!result.contains("<text"));
+    }
+}